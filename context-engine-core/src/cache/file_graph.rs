@@ -0,0 +1,184 @@
+//! The part of the knowledge graph that belongs to one file.
+
+use std::collections::{BTreeMap, HashMap};
+
+use lsp_types::Uri;
+use serde::{Deserialize, Serialize};
+
+use crate::graph::{Direction, EdgeKind, KnowledgeGraph, SymbolId, SymbolNode};
+use crate::patterns::UsagePatterns;
+
+/// The symbols a file defines, their outgoing relationships and the usage
+/// patterns of its symbols: everything the analysis of the file produced.
+///
+/// Edges refer to the symbols of the file by their position in [nodes], and
+/// to symbols of other files by document and qualified name, because ids
+/// are only meaningful in the graph that assigned them.
+///
+/// [nodes]: FileGraph::nodes
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::cache::FileGraph;
+/// use context_engine_core::graph::{EdgeKind, KnowledgeGraph, NodeKind, SymbolNode};
+/// use context_engine_core::types::{Position, Range, Uri};
+/// use std::str::FromStr;
+///
+/// let uri = Uri::from_str("file:///src/user.rs").unwrap();
+/// let range = |line| Range::new(Position::new(line, 0), Position::new(line, 10));
+///
+/// let mut graph = KnowledgeGraph::new();
+/// let user = graph.add_node(SymbolNode::new(NodeKind::Type, "User", "app::User", uri.clone(), range(0)));
+/// let name = graph.add_node(SymbolNode::new(NodeKind::Field, "name", "app::User::name", uri.clone(), range(1)));
+/// graph.add_edge(user, EdgeKind::Defines, name);
+///
+/// let file = FileGraph::extract(&graph, &uri);
+/// let restored = FileGraph::restore([&file]);
+/// assert_eq!(restored, graph);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileGraph {
+    /// The file
+    pub uri: Uri,
+    /// The symbols the file defines, in document order
+    pub nodes: Vec<SymbolNode>,
+    /// The relationships starting at the symbols of the file
+    pub edges: Vec<FileEdge>,
+    /// The usage patterns of the symbols of the file, by qualified name
+    pub patterns: BTreeMap<String, UsagePatterns>,
+}
+
+/// A relationship starting at a symbol of a [`FileGraph`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileEdge {
+    /// The position of the source symbol in the nodes of the file
+    pub from: usize,
+    /// The kind of the relationship
+    pub kind: EdgeKind,
+    /// The target symbol
+    pub to: EdgeTarget,
+}
+
+/// The target of a [`FileEdge`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeTarget {
+    /// A symbol of the same file, by position in its nodes
+    Local(usize),
+    /// A symbol of another file
+    External {
+        /// The file that defines the symbol
+        uri: Uri,
+        /// The fully qualified name of the symbol
+        qualified_name: String,
+    },
+}
+
+impl FileGraph {
+    /// Extracts the symbols of a file and their outgoing relationships from
+    /// a graph, without usage patterns.
+    pub fn extract(graph: &KnowledgeGraph, uri: &Uri) -> Self {
+        let nodes: Vec<SymbolNode> = graph.symbols_in(uri).into_iter().cloned().collect();
+        let positions: HashMap<SymbolId, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(position, node)| (node.id(), position))
+            .collect();
+
+        let mut edges = Vec::new();
+        for (from, node) in nodes.iter().enumerate() {
+            for edge in graph.edges_of(node.id(), Direction::Outgoing) {
+                let to = match positions.get(&edge.to) {
+                    Some(position) => EdgeTarget::Local(*position),
+                    None => match graph.node(edge.to) {
+                        Some(target) => EdgeTarget::External {
+                            uri: target.uri.clone(),
+                            qualified_name: target.qualified_name.clone(),
+                        },
+                        None => continue,
+                    },
+                };
+                edges.push(FileEdge {
+                    from,
+                    kind: edge.kind,
+                    to,
+                });
+            }
+        }
+
+        Self {
+            uri: uri.clone(),
+            nodes,
+            edges,
+            patterns: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the usage patterns of a symbol of the file.
+    pub fn with_patterns(
+        mut self,
+        qualified_name: impl Into<String>,
+        patterns: UsagePatterns,
+    ) -> Self {
+        self.patterns.insert(qualified_name.into(), patterns);
+        self
+    }
+
+    /// Builds a graph from the graphs of several files.
+    ///
+    /// All symbols are added before the relationships, so that edges between
+    /// the files are restored regardless of their order. Edges to symbols
+    /// that none of the files define are dropped.
+    pub fn restore<'a>(files: impl IntoIterator<Item = &'a FileGraph>) -> KnowledgeGraph {
+        let mut graph = KnowledgeGraph::new();
+        Self::restore_into(&mut graph, files);
+        graph
+    }
+
+    /// Adds the graphs of several files to a graph, like [`restore`].
+    ///
+    /// Edges to other files may also end at symbols that were already in
+    /// the graph.
+    ///
+    /// [`restore`]: FileGraph::restore
+    pub fn restore_into<'a>(
+        graph: &mut KnowledgeGraph,
+        files: impl IntoIterator<Item = &'a FileGraph>,
+    ) {
+        let files: Vec<(&FileGraph, Vec<SymbolId>)> = files
+            .into_iter()
+            .map(|file| {
+                let ids = file
+                    .nodes
+                    .iter()
+                    .map(|node| graph.add_node(node.clone()))
+                    .collect();
+                (file, ids)
+            })
+            .collect();
+
+        for (file, ids) in &files {
+            for edge in &file.edges {
+                let to = match &edge.to {
+                    EdgeTarget::Local(position) => ids.get(*position).copied(),
+                    EdgeTarget::External {
+                        uri,
+                        qualified_name,
+                    } => graph
+                        .lookup(qualified_name)
+                        .into_iter()
+                        .find(|node| node.uri == *uri)
+                        .map(SymbolNode::id),
+                };
+                if let (Some(from), Some(to)) = (ids.get(edge.from), to) {
+                    graph.add_edge(*from, edge.kind, to);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/file_graph.rs"]
+mod tests;
@@ -0,0 +1,65 @@
+//! Keys of cache entries.
+
+use serde::{Deserialize, Serialize};
+
+/// The version of the analysis that produces cached graphs; entries written
+/// by another version are re-analyzed.
+pub const ANALYZER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// What a cached analysis of a file depends on: the content of the file and
+/// the versions of the analyzer and the language server that produced it.
+///
+/// An entry is only returned for a key equal to the one it was stored with,
+/// so changing any of them invalidates it.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::cache::CacheKey;
+///
+/// let key = CacheKey::new(b"fn main() {}", "rust-analyzer 1.0.0");
+/// assert_eq!(key, CacheKey::new(b"fn main() {}", "rust-analyzer 1.0.0"));
+/// assert_ne!(key, CacheKey::new(b"fn main() {}", "rust-analyzer 1.1.0"));
+/// assert_ne!(key, CacheKey::new(b"fn run() {}", "rust-analyzer 1.0.0"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CacheKey {
+    /// The hash of the content of the file
+    pub content_hash: u64,
+    /// The version of the analyzer
+    pub analyzer_version: String,
+    /// The name and version of the language server
+    pub server_version: String,
+}
+
+impl CacheKey {
+    /// Creates the key of a file content analyzed by this version of the
+    /// analyzer.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The content of the file
+    /// * `server_version` - The name and version of the language server, e.g.
+    ///   `rust-analyzer 1.0.0`
+    pub fn new(content: &[u8], server_version: impl Into<String>) -> Self {
+        Self {
+            content_hash: content_hash(content),
+            analyzer_version: ANALYZER_VERSION.to_string(),
+            server_version: server_version.into(),
+        }
+    }
+}
+
+/// Returns the 64-bit FNV-1a hash of a file content.
+///
+/// The hash is stable across platforms and releases, unlike the hashers of
+/// the standard library, so it can be persisted.
+pub fn content_hash(content: &[u8]) -> u64 {
+    content.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+#[path = "tests/key.rs"]
+mod tests;
@@ -0,0 +1,28 @@
+//! Persistent cache of the analysis of files.
+//!
+//! Indexing a workspace asks the language servers about every file, which
+//! takes minutes on a large codebase. The [`GraphStore`] keeps the result for
+//! each file on disk, as a [`FileGraph`] of its symbols, their relationships
+//! and their usage patterns, so that a restart only analyzes the files that
+//! changed. Entries are keyed by a [`CacheKey`] of the content hash of the
+//! file and the versions of the analyzer and language server, and the store
+//! is an embedded directory of files that needs no external service.
+//!
+//! ## Structs
+//!
+//! * [`GraphStore`] - The on-disk store of analyzed files.
+//! * [`CacheKey`] - What a cached analysis depends on.
+//! * [`FileGraph`] - The part of the knowledge graph that belongs to a file.
+//! * [`FileEdge`] - A relationship starting at a symbol of a file.
+//!
+//! ## Enums
+//!
+//! * [`EdgeTarget`] - The target of a relationship of a file.
+
+mod file_graph;
+mod key;
+mod store;
+
+pub use file_graph::{EdgeTarget, FileEdge, FileGraph};
+pub use key::{ANALYZER_VERSION, CacheKey, content_hash};
+pub use store::{GraphStore, SCHEMA_VERSION};
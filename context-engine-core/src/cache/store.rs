@@ -0,0 +1,375 @@
+//! The embedded on-disk store of analyzed files.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use lsp_types::Uri;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::cache::{CacheKey, FileGraph, content_hash};
+use crate::error::ContextEngineError;
+
+/// The version of the format of the store; a store or entry of another
+/// version is discarded.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The bytes every file of the store starts with.
+const MAGIC: &[u8; 4] = b"CEGS";
+
+/// The prefix of the names of the files the store creates; other files in
+/// its directory are left alone.
+const FILE_PREFIX: &str = "ce-graph";
+
+/// The name of the file that holds the header of the store.
+const HEADER_FILE: &str = "ce-graph.version";
+
+/// The extension of entry files.
+const ENTRY_EXTENSION: &str = "bin";
+
+/// An entry as it is written.
+#[derive(Serialize)]
+struct EntryRef<'a> {
+    key: &'a CacheKey,
+    graph: &'a FileGraph,
+}
+
+/// An entry as it is read.
+#[derive(Deserialize)]
+struct Entry {
+    key: CacheKey,
+    graph: FileGraph,
+}
+
+/// A persistent cache of the analysis of files, so that unchanged files are
+/// not analyzed again after a restart.
+///
+/// The store is a directory with one file per analyzed file, which needs no
+/// external service. Every entry holds the [`FileGraph`] of a file and the
+/// [`CacheKey`] it was stored with; it is only returned for an equal key, so
+/// an entry becomes stale as soon as the content of the file or the version
+/// of the analyzer or language server changes.
+///
+/// The store and each entry start with a header carrying the
+/// [`SCHEMA_VERSION`]. Opening a store written by another version clears
+/// it, and entries of another version are removed when they are read.
+/// Entries are written to a temporary file first and then renamed, so an
+/// interruption never leaves a truncated entry behind. The names of all
+/// files of the store start with `ce-graph`, and only those are ever
+/// removed, so the store may share its directory with other files.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::cache::{CacheKey, FileGraph, GraphStore};
+/// use context_engine_core::graph::KnowledgeGraph;
+/// use context_engine_core::types::Uri;
+/// use std::str::FromStr;
+///
+/// # fn main() -> Result<(), context_engine_core::ContextEngineError> {
+/// # let dir = std::env::temp_dir().join(format!("graph-store-doc-{}", std::process::id()));
+/// let store = GraphStore::open(&dir)?;
+/// let uri = Uri::from_str("file:///src/user.rs").unwrap();
+/// let key = CacheKey::new(b"pub struct User;", "rust-analyzer 1.0.0");
+///
+/// let graph = FileGraph::extract(&KnowledgeGraph::new(), &uri);
+/// store.put(&key, &graph)?;
+/// assert_eq!(store.get(&uri, &key)?, Some(graph));
+///
+/// let changed = CacheKey::new(b"pub struct Account;", "rust-analyzer 1.0.0");
+/// assert_eq!(store.get(&uri, &changed)?, None);
+/// # std::fs::remove_dir_all(&dir).ok();
+/// # Ok(())
+/// # }
+/// ```
+pub struct GraphStore {
+    dir: PathBuf,
+    writes: AtomicU64,
+}
+
+impl GraphStore {
+    /// Opens the store in a directory, creating the directory if needed.
+    ///
+    /// A store without a header, or with the header of another schema
+    /// version, is cleared.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::CacheCorrupted`] if the directory cannot
+    /// be created, read or written.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, ContextEngineError> {
+        let store = Self {
+            dir: dir.into(),
+            writes: AtomicU64::new(0),
+        };
+        std::fs::create_dir_all(&store.dir).map_err(|error| corrupted(&store.dir, error))?;
+
+        let path = store.dir.join(HEADER_FILE);
+        match std::fs::read(&path) {
+            Ok(bytes) if read_header(&bytes).is_some() => {}
+            Ok(_) => {
+                warn!(path = %path.display(), "Clearing graph store of another schema version");
+                store.reset()?;
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => store.reset()?,
+            Err(error) => return Err(corrupted(&path, error)),
+        }
+        Ok(store)
+    }
+
+    /// Returns the directory of the store.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the cached graph of a file, if it was stored with an equal
+    /// key.
+    ///
+    /// Entries of another schema version are removed.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the file is not cached or its entry is stale.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::CacheCorrupted`] if the entry cannot be
+    /// read or decoded; an entry that cannot be decoded is removed, so the
+    /// next call returns `None`.
+    pub fn get(&self, uri: &Uri, key: &CacheKey) -> Result<Option<FileGraph>, ContextEngineError> {
+        let path = self.entry_path(uri);
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(corrupted(&path, error)),
+        };
+
+        let entry = match decode(&path, &bytes) {
+            Ok(Some(entry)) => entry,
+            Ok(None) => {
+                remove_file(&path)?;
+                return Ok(None);
+            }
+            Err(error) => {
+                remove_file(&path)?;
+                return Err(error);
+            }
+        };
+
+        // The entry may belong to another file whose URI has the same hash
+        if entry.graph.uri != *uri || entry.key != *key {
+            return Ok(None);
+        }
+        Ok(Some(entry.graph))
+    }
+
+    /// Stores the graph of a file, replacing its previous entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::CacheCorrupted`] if the graph cannot be
+    /// encoded or the entry cannot be written.
+    pub fn put(&self, key: &CacheKey, graph: &FileGraph) -> Result<(), ContextEngineError> {
+        let path = self.entry_path(&graph.uri);
+        let config = bincode::config::standard();
+        let body = bincode::serde::encode_to_vec(EntryRef { key, graph }, config)
+            .map_err(|error| corrupted(&path, error))?;
+        let mut bytes = header();
+        bytes.extend_from_slice(&body);
+        self.write(&path, &bytes)
+    }
+
+    /// Removes the entry of a file, e.g. because the file was deleted.
+    ///
+    /// # Returns
+    ///
+    /// True if the file was cached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::CacheCorrupted`] if the entry cannot be
+    /// removed.
+    pub fn remove(&self, uri: &Uri) -> Result<bool, ContextEngineError> {
+        let path = self.entry_path(uri);
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(true),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(corrupted(&path, error)),
+        }
+    }
+
+    /// Returns all entries of the current schema version, with the keys
+    /// they were stored with, in no particular order.
+    ///
+    /// Entries of another schema version and entries that cannot be decoded
+    /// are removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::CacheCorrupted`] if the directory or an
+    /// entry cannot be read.
+    pub fn entries(&self) -> Result<Vec<(CacheKey, FileGraph)>, ContextEngineError> {
+        let mut entries = Vec::new();
+        for path in self.files()? {
+            if !is_entry(&path) {
+                continue;
+            }
+            let bytes = match std::fs::read(&path) {
+                Ok(bytes) => bytes,
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(error) => return Err(corrupted(&path, error)),
+            };
+            match decode(&path, &bytes)? {
+                Some(entry) => entries.push((entry.key, entry.graph)),
+                None => remove_file(&path)?,
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Removes all entries.
+    ///
+    /// Only the files the store created are removed, so the store may share
+    /// its directory with other files.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::CacheCorrupted`] if the directory cannot
+    /// be read or an entry cannot be removed.
+    pub fn clear(&self) -> Result<(), ContextEngineError> {
+        for path in self.files()? {
+            let extension = path.extension().and_then(|extension| extension.to_str());
+            if is_owned(&path) && matches!(extension, Some(ENTRY_EXTENSION | "tmp")) {
+                remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of entries, including stale ones.
+    pub fn len(&self) -> usize {
+        self.files()
+            .map(|files| files.iter().filter(|path| is_entry(path)).count())
+            .unwrap_or_default()
+    }
+
+    /// Returns true if the store has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the path of the entry of a file.
+    fn entry_path(&self, uri: &Uri) -> PathBuf {
+        let hash = content_hash(uri.as_str().as_bytes());
+        self.dir
+            .join(format!("{FILE_PREFIX}-{hash:016x}.{ENTRY_EXTENSION}"))
+    }
+
+    /// Clears the store and writes the header of the current version.
+    fn reset(&self) -> Result<(), ContextEngineError> {
+        self.clear()?;
+        self.write(&self.dir.join(HEADER_FILE), &header())
+    }
+
+    /// Writes a file of the store through a temporary file.
+    ///
+    /// The name of the temporary file has the process id and a counter, so
+    /// that processes sharing the store never write the same file.
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<(), ContextEngineError> {
+        let write = self.writes.fetch_add(1, Ordering::Relaxed);
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let temporary = path.with_file_name(format!("{name}.{}.{write}.tmp", std::process::id()));
+        std::fs::write(&temporary, bytes).map_err(|error| corrupted(&temporary, error))?;
+        std::fs::rename(&temporary, path).map_err(|error| {
+            std::fs::remove_file(&temporary).ok();
+            corrupted(path, error)
+        })
+    }
+
+    /// Returns the files in the directory of the store.
+    fn files(&self) -> Result<Vec<PathBuf>, ContextEngineError> {
+        let entries = std::fs::read_dir(&self.dir).map_err(|error| corrupted(&self.dir, error))?;
+        Ok(entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect())
+    }
+}
+
+impl fmt::Debug for GraphStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GraphStore")
+            .field("dir", &self.dir)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the header of the current schema version.
+fn header() -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&SCHEMA_VERSION.to_le_bytes());
+    bytes
+}
+
+/// Returns the bytes after the header, or `None` if the bytes do not start
+/// with the header of the current schema version.
+fn read_header(bytes: &[u8]) -> Option<&[u8]> {
+    let header = header();
+    bytes.strip_prefix(header.as_slice())
+}
+
+/// Decodes an entry file.
+///
+/// # Returns
+///
+/// `None` if the entry is of another schema version.
+fn decode(path: &Path, bytes: &[u8]) -> Result<Option<Entry>, ContextEngineError> {
+    let Some(body) = read_header(bytes) else {
+        debug!(path = %path.display(), "Removing graph store entry of another schema version");
+        return Ok(None);
+    };
+    let config = bincode::config::standard();
+    bincode::serde::decode_from_slice::<Entry, _>(body, config)
+        .map(|(entry, _)| Some(entry))
+        .map_err(|error| corrupted(path, error))
+}
+
+/// Returns true if the store created a file.
+fn is_owned(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with(FILE_PREFIX))
+}
+
+/// Returns true if a file is an entry of the store.
+fn is_entry(path: &Path) -> bool {
+    is_owned(path)
+        && path
+            .extension()
+            .is_some_and(|extension| extension == ENTRY_EXTENSION)
+}
+
+/// Removes a file of the store that may already be gone.
+fn remove_file(path: &Path) -> Result<(), ContextEngineError> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(corrupted(path, error)),
+    }
+}
+
+fn corrupted(path: &Path, error: impl fmt::Display) -> ContextEngineError {
+    ContextEngineError::CacheCorrupted {
+        path: path.display().to_string(),
+        message: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/store.rs"]
+mod tests;
@@ -0,0 +1,114 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use lsp_types::{Position, Range};
+use proptest::prelude::*;
+
+use super::*;
+use crate::graph::NodeKind;
+
+fn uri(path: &str) -> Uri {
+    Uri::from_str(&format!("file:///app/src/{path}")).unwrap()
+}
+
+fn node(kind: NodeKind, qualified_name: &str, file: &str, line: u32) -> SymbolNode {
+    let name = qualified_name.rsplit("::").next().unwrap();
+    let range = Range::new(Position::new(line, 0), Position::new(line, 20));
+    SymbolNode::new(kind, name, qualified_name, uri(file), range)
+}
+
+/// A graph of two files: `user.rs` defines `User`, which `main.rs` uses.
+fn graph() -> KnowledgeGraph {
+    let mut graph = KnowledgeGraph::new();
+    let main = graph.add_node(node(NodeKind::Function, "app::main", "main.rs", 0));
+    let user = graph.add_node(node(NodeKind::Type, "app::user::User", "user.rs", 2));
+    let validate = graph.add_node(node(
+        NodeKind::Method,
+        "app::user::User::validate",
+        "user.rs",
+        5,
+    ));
+    let display = graph.add_node(node(NodeKind::Trait, "std::fmt::Display", "fmt.rs", 0));
+    graph.add_edge(user, EdgeKind::Defines, validate);
+    graph.add_edge(user, EdgeKind::Implements, display);
+    graph.add_edge(main, EdgeKind::Calls, validate);
+    graph
+}
+
+#[test]
+fn test_extract() {
+    let graph = graph();
+    let file = FileGraph::extract(&graph, &uri("user.rs"));
+    let names: Vec<&str> = file.nodes.iter().map(|node| node.name.as_str()).collect();
+    assert_eq!(names, ["User", "validate"]);
+    assert_eq!(
+        file.edges,
+        [
+            FileEdge {
+                from: 0,
+                kind: EdgeKind::Defines,
+                to: EdgeTarget::Local(1),
+            },
+            FileEdge {
+                from: 0,
+                kind: EdgeKind::Implements,
+                to: EdgeTarget::External {
+                    uri: uri("fmt.rs"),
+                    qualified_name: "std::fmt::Display".to_string(),
+                },
+            },
+        ]
+    );
+    assert!(file.patterns.is_empty());
+
+    let patterns = UsagePatterns::from_templates(["user.validate();".to_string()]);
+    let file = file.with_patterns("app::user::User", patterns.clone());
+    assert_eq!(file.patterns.get("app::user::User"), Some(&patterns));
+}
+
+#[test]
+fn test_restore_edges_between_files() {
+    let graph = graph();
+    let files: Vec<FileGraph> = ["main.rs", "user.rs", "fmt.rs"]
+        .iter()
+        .map(|path| FileGraph::extract(&graph, &uri(path)))
+        .collect();
+
+    let restored = FileGraph::restore(&files);
+    assert_eq!(restored.len(), graph.len());
+    assert_eq!(restored.edge_count(), graph.edge_count());
+    let main = restored.lookup("app::main").first().unwrap().id();
+    let callees = restored.neighbors(main, EdgeKind::Calls, Direction::Outgoing);
+    assert_eq!(
+        callees.first().unwrap().qualified_name,
+        "app::user::User::validate"
+    );
+}
+
+#[test]
+fn test_restore_drops_edges_to_missing_files() {
+    let graph = graph();
+    let file = FileGraph::extract(&graph, &uri("user.rs"));
+    let restored = FileGraph::restore([&file]);
+    assert_eq!(restored.len(), 2);
+    assert_eq!(restored.edge_count(), 1);
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn test_extract_and_restore_roundtrip(lines in prop::collection::btree_set(0u32..100, 1..8)) {
+        let mut graph = KnowledgeGraph::new();
+        let mut previous = None;
+        for line in lines {
+            let id = graph.add_node(node(NodeKind::Function, &format!("app::f{line}"), "lib.rs", line));
+            if let Some(previous) = previous {
+                graph.add_edge(previous, EdgeKind::Calls, id);
+            }
+            previous = Some(id);
+        }
+        let file = FileGraph::extract(&graph, &uri("lib.rs"));
+        prop_assert_eq!(FileGraph::restore([&file]), graph);
+    }
+}
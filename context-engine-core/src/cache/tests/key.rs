@@ -0,0 +1,39 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+#[test]
+fn test_key_depends_on_content_and_versions() {
+    let key = CacheKey::new(b"pub struct User;", "rust-analyzer 1.0.0");
+    assert_eq!(key.content_hash, content_hash(b"pub struct User;"));
+    assert_eq!(key.analyzer_version, ANALYZER_VERSION);
+    assert_eq!(key.server_version, "rust-analyzer 1.0.0");
+
+    let analyzer = CacheKey {
+        analyzer_version: "0.0.0".to_string(),
+        ..key.clone()
+    };
+    assert_ne!(key, analyzer);
+    assert_ne!(key, CacheKey::new(b"pub struct User;", "pyright 1.0.0"));
+}
+
+#[test]
+fn test_content_hash() {
+    // Reference values of 64-bit FNV-1a
+    assert_eq!(content_hash(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(content_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn test_keys_of_different_contents_differ(
+        a in prop::collection::vec(any::<u8>(), 0..64),
+        b in prop::collection::vec(any::<u8>(), 0..64),
+    ) {
+        prop_assume!(a != b);
+        prop_assert_ne!(CacheKey::new(&a, "server"), CacheKey::new(&b, "server"));
+    }
+}
@@ -0,0 +1,209 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use lsp_types::{Position, Range};
+use proptest::prelude::*;
+
+use super::*;
+use crate::graph::{KnowledgeGraph, NodeKind, SymbolNode};
+use crate::patterns::UsagePatterns;
+
+const USER: &[u8] = b"pub struct User;";
+
+fn uri() -> Uri {
+    Uri::from_str("file:///app/src/user.rs").unwrap()
+}
+
+fn key(content: &[u8]) -> CacheKey {
+    CacheKey::new(content, "rust-analyzer 1.0.0")
+}
+
+fn file_graph() -> FileGraph {
+    let mut graph = KnowledgeGraph::new();
+    graph.add_node(SymbolNode::new(
+        NodeKind::Type,
+        "User",
+        "app::user::User",
+        uri(),
+        Range::new(Position::new(0, 0), Position::new(0, 16)),
+    ));
+    let patterns = UsagePatterns::from_templates(["User::default();".to_string()]);
+    FileGraph::extract(&graph, &uri()).with_patterns("app::user::User", patterns)
+}
+
+#[test]
+fn test_put_and_get() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = GraphStore::open(dir.path()).unwrap();
+    assert!(store.is_empty());
+    assert_eq!(store.get(&uri(), &key(USER)).unwrap(), None);
+
+    store.put(&key(USER), &file_graph()).unwrap();
+    assert_eq!(store.len(), 1);
+    assert_eq!(store.get(&uri(), &key(USER)).unwrap(), Some(file_graph()));
+
+    // Survives reopening
+    drop(store);
+    let store = GraphStore::open(dir.path()).unwrap();
+    assert_eq!(store.get(&uri(), &key(USER)).unwrap(), Some(file_graph()));
+}
+
+#[test]
+fn test_stale_entries_are_not_returned() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = GraphStore::open(dir.path()).unwrap();
+    store.put(&key(USER), &file_graph()).unwrap();
+
+    assert_eq!(
+        store.get(&uri(), &key(b"pub struct Account;")).unwrap(),
+        None
+    );
+    let server = CacheKey::new(USER, "rust-analyzer 2.0.0");
+    assert_eq!(store.get(&uri(), &server).unwrap(), None);
+    let analyzer = CacheKey {
+        analyzer_version: "0.0.0".to_string(),
+        ..key(USER)
+    };
+    assert_eq!(store.get(&uri(), &analyzer).unwrap(), None);
+    let other = Uri::from_str("file:///app/src/account.rs").unwrap();
+    assert_eq!(store.get(&other, &key(USER)).unwrap(), None);
+}
+
+#[test]
+fn test_remove_and_clear() {
+    let dir = tempfile::tempdir().unwrap();
+    // Files of others survive, even with the extensions of the store
+    let foreign = ["notes.txt", "0123456789abcdef.bin", "upload.0.tmp"];
+    for name in foreign {
+        std::fs::write(dir.path().join(name), "unrelated").unwrap();
+    }
+    let store = GraphStore::open(dir.path()).unwrap();
+    store.put(&key(USER), &file_graph()).unwrap();
+
+    assert!(store.remove(&uri()).unwrap());
+    assert!(!store.remove(&uri()).unwrap());
+    assert_eq!(store.get(&uri(), &key(USER)).unwrap(), None);
+
+    store.put(&key(USER), &file_graph()).unwrap();
+    store.clear().unwrap();
+    assert!(store.is_empty());
+    for name in foreign {
+        assert!(dir.path().join(name).exists(), "{name} was removed");
+    }
+}
+
+#[test]
+fn test_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = GraphStore::open(dir.path()).unwrap();
+    assert!(store.entries().unwrap().is_empty());
+    store.put(&key(USER), &file_graph()).unwrap();
+    let account = Uri::from_str("file:///app/src/account.rs").unwrap();
+    let other = FileGraph::extract(&KnowledgeGraph::new(), &account);
+    store.put(&key(b""), &other).unwrap();
+
+    let mut entries = store.entries().unwrap();
+    entries.sort_by(|a, b| a.1.uri.as_str().cmp(b.1.uri.as_str()));
+    assert_eq!(entries, [(key(b""), other), (key(USER), file_graph())]);
+
+    // Entries of another schema version are removed
+    let path = store.entry_path(&account);
+    std::fs::write(&path, b"CEGS\0\0\0\0").unwrap();
+    assert_eq!(store.entries().unwrap().len(), 1);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_temporary_files_are_named_after_the_process() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = GraphStore::open(dir.path()).unwrap();
+    let path = store.entry_path(&uri());
+    let names = |dir: &std::path::Path| {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+    };
+
+    // Opening wrote the header, so the next write goes to the entry name,
+    // the process id and 1; a directory in its way fails it, and the write
+    // after it moves on
+    let leftover = format!(
+        "{}.{}.1.tmp",
+        path.file_name().unwrap().to_string_lossy(),
+        std::process::id()
+    );
+    std::fs::create_dir(dir.path().join(&leftover)).unwrap();
+    store.put(&key(USER), &file_graph()).unwrap_err();
+    store.put(&key(USER), &file_graph()).unwrap();
+    assert!(names(dir.path()).contains(&leftover));
+    assert!(path.exists());
+}
+
+#[test]
+fn test_schema_mismatch_clears_the_store() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = GraphStore::open(dir.path()).unwrap();
+    store.put(&key(USER), &file_graph()).unwrap();
+    drop(store);
+
+    let mut header = MAGIC.to_vec();
+    header.extend_from_slice(&(SCHEMA_VERSION + 1).to_le_bytes());
+    std::fs::write(dir.path().join(HEADER_FILE), header).unwrap();
+
+    let store = GraphStore::open(dir.path()).unwrap();
+    assert!(store.is_empty());
+    assert_eq!(
+        std::fs::read(dir.path().join(HEADER_FILE)).unwrap(),
+        super::header()
+    );
+}
+
+#[test]
+fn test_entry_of_another_schema_version_is_removed() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = GraphStore::open(dir.path()).unwrap();
+    store.put(&key(USER), &file_graph()).unwrap();
+
+    let path = store.entry_path(&uri());
+    let mut bytes = std::fs::read(&path).unwrap();
+    if let Some(version) = bytes.get_mut(MAGIC.len()) {
+        *version = version.wrapping_add(1);
+    }
+    std::fs::write(&path, bytes).unwrap();
+
+    assert_eq!(store.get(&uri(), &key(USER)).unwrap(), None);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_corrupted_entry_is_removed() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = GraphStore::open(dir.path()).unwrap();
+    store.put(&key(USER), &file_graph()).unwrap();
+
+    let path = store.entry_path(&uri());
+    let mut bytes = super::header();
+    bytes.extend_from_slice(&[0xff; 8]);
+    std::fs::write(&path, bytes).unwrap();
+
+    let error = store.get(&uri(), &key(USER)).unwrap_err();
+    assert!(matches!(error, ContextEngineError::CacheCorrupted { .. }));
+    assert!(store.is_empty());
+    assert_eq!(store.get(&uri(), &key(USER)).unwrap(), None);
+}
+
+// Property-based tests
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn test_only_the_stored_content_hits(stored in "[a-z ]{0,32}", read in "[a-z ]{0,32}") {
+        let dir = tempfile::tempdir().unwrap();
+        let store = GraphStore::open(dir.path()).unwrap();
+        store.put(&key(stored.as_bytes()), &file_graph()).unwrap();
+        let hit = store.get(&uri(), &key(read.as_bytes())).unwrap().is_some();
+        prop_assert_eq!(hit, stored == read);
+    }
+}
@@ -0,0 +1,453 @@
+//! Indexing of the sources of dependencies.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use lsp_types::Uri;
+use tracing::{debug, warn};
+
+use crate::cache::{CacheKey, EdgeTarget, FileGraph, GraphStore, content_hash};
+use crate::deps::{LockedPackage, Lockfile, PackageSource, SourceResolver, cargo_home};
+use crate::document::DocumentStore;
+use crate::error::ContextEngineError;
+use crate::graph::{KnowledgeGraph, NodeKind};
+use crate::index::{IndexConfig, Indexer, discover};
+use crate::lsp::{LanguageRegistry, LspSupervisor};
+use crate::types::UriExt;
+use crate::workspace::Glob;
+
+/// Directories of packages that do not hold their API.
+const DEPENDENCY_EXCLUDES: &[&str] = &["tests/**", "benches/**", "examples/**"];
+
+/// The indexed sources of a dependency.
+///
+/// The qualified names of its symbols are prefixed with the
+/// [namespace](LockedPackage::namespace) of the package instead of its crate
+/// name, e.g. `tokio@1.45.1::sync::Mutex`, so that several versions of a
+/// package can live in one graph next to the workspace. Paths into the
+/// packages it depends on are moved to the namespaces of their locked
+/// versions the same way. The sources are
+/// read-only: they belong to cargo and must not be edited.
+#[derive(Debug, Clone)]
+pub struct DependencyGraph {
+    /// The package
+    pub package: LockedPackage,
+    /// The directory of the source of the package
+    pub dir: PathBuf,
+    /// The graphs of the files of the package
+    pub files: Vec<FileGraph>,
+    /// Whether the graph was loaded from the dependency cache instead of
+    /// being indexed
+    pub cached: bool,
+}
+
+impl DependencyGraph {
+    /// Returns the graph of the package alone.
+    pub fn graph(&self) -> KnowledgeGraph {
+        FileGraph::restore(&self.files)
+    }
+
+    /// Adds the symbols of the package to a graph.
+    pub fn merge_into(&self, graph: &mut KnowledgeGraph) {
+        FileGraph::restore_into(graph, &self.files);
+    }
+
+    /// Returns true if a document belongs to the sources of the package.
+    pub fn contains(&self, uri: &Uri) -> bool {
+        uri.to_file_path()
+            .is_ok_and(|path| path.starts_with(&self.dir))
+    }
+}
+
+/// The result of indexing the dependencies of a workspace.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyOutcome {
+    /// The indexed dependencies, in the order of `Cargo.lock`
+    pub dependencies: Vec<DependencyGraph>,
+    /// The dependencies whose sources are not on disk
+    pub unresolved: Vec<LockedPackage>,
+    /// The dependencies that could not be indexed
+    pub errors: Vec<ContextEngineError>,
+}
+
+impl DependencyOutcome {
+    /// Adds the symbols of all dependencies to a graph.
+    pub fn merge_into(&self, graph: &mut KnowledgeGraph) {
+        for dependency in &self.dependencies {
+            dependency.merge_into(graph);
+        }
+    }
+
+    /// Returns true if a document belongs to the read-only sources of a
+    /// dependency.
+    pub fn is_read_only(&self, uri: &Uri) -> bool {
+        self.dependencies
+            .iter()
+            .any(|dependency| dependency.contains(uri))
+    }
+}
+
+/// Indexes the exact versions of the dependencies a workspace locks in its
+/// `Cargo.lock`.
+///
+/// Each package is resolved to its source by a [`SourceResolver`] and
+/// indexed with an [`Indexer`] rooted at the source, skipping its tests,
+/// benches and examples. The result is cached per file in a [`GraphStore`]
+/// per package version below the [cache directory](default_cache_dir), which
+/// is shared by all workspaces of the machine: once `tokio 1.45.1` is
+/// indexed, every workspace that locks it loads it from the cache, as long
+/// as the sources, the analyzer and the language server did not change.
+///
+/// # Examples
+///
+/// ```no_run
+/// use context_engine_core::deps::DependencyIndexer;
+/// use context_engine_core::document::DocumentStore;
+/// use context_engine_core::graph::KnowledgeGraph;
+/// use context_engine_core::lsp::{LanguageRegistry, LspSupervisor};
+/// use std::sync::Arc;
+///
+/// # async fn example() -> Result<(), context_engine_core::ContextEngineError> {
+/// let documents = Arc::new(DocumentStore::default());
+/// let registry = Arc::new(LanguageRegistry::with_defaults());
+/// let supervisor = Arc::new(LspSupervisor::new("/path/to/workspace", Arc::clone(&documents)));
+/// supervisor.start(registry.server("rust-analyzer").unwrap().clone())?;
+///
+/// let indexer = DependencyIndexer::new("/path/to/workspace", supervisor, registry, documents);
+/// let outcome = indexer.index_lockfile().await?;
+///
+/// let mut graph = KnowledgeGraph::new();
+/// outcome.merge_into(&mut graph);
+/// assert!(!graph.lookup("tokio@1.45.1::sync::Mutex").is_empty());
+/// # Ok(())
+/// # }
+/// ```
+pub struct DependencyIndexer {
+    resolver: SourceResolver,
+    cache_dir: PathBuf,
+    supervisor: Arc<LspSupervisor>,
+    registry: Arc<LanguageRegistry>,
+    documents: Arc<DocumentStore>,
+    config: IndexConfig,
+}
+
+impl DependencyIndexer {
+    /// Creates an indexer for the dependencies of a workspace, using the
+    /// cargo home of the user and the default cache directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The workspace root, which holds `Cargo.lock`
+    /// * `supervisor` - The language servers to query
+    /// * `registry` - Routes files to the servers
+    /// * `documents` - The open documents; the graph uses their position
+    ///   encoding
+    pub fn new(
+        root: impl Into<PathBuf>,
+        supervisor: Arc<LspSupervisor>,
+        registry: Arc<LanguageRegistry>,
+        documents: Arc<DocumentStore>,
+    ) -> Self {
+        let root = root.into();
+        let cargo_home = cargo_home().unwrap_or_else(|| root.join(".cargo"));
+        let mut config = IndexConfig::default();
+        config.excludes.extend(
+            DEPENDENCY_EXCLUDES
+                .iter()
+                .filter_map(|pattern| Glob::new(pattern).ok()),
+        );
+        Self {
+            resolver: SourceResolver::new(root, cargo_home),
+            cache_dir: default_cache_dir(),
+            supervisor,
+            registry,
+            documents,
+            config,
+        }
+    }
+
+    /// Looks for the sources of registry and git dependencies in another
+    /// cargo home directory.
+    pub fn with_cargo_home(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.resolver = SourceResolver::new(self.resolver.root(), dir);
+        self
+    }
+
+    /// Caches the indexed dependencies in another directory.
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = dir.into();
+        self
+    }
+
+    /// Returns the resolver of the sources of dependencies.
+    pub fn resolver(&self) -> &SourceResolver {
+        &self.resolver
+    }
+
+    /// Indexes the dependencies locked by the `Cargo.lock` of the workspace.
+    ///
+    /// Workspace members are skipped. Dependencies that cannot be resolved
+    /// or indexed do not abort the run; they are returned in the
+    /// [`DependencyOutcome`].
+    ///
+    /// # Errors
+    ///
+    /// * [`ContextEngineError::Configuration`] - If `Cargo.lock` cannot be read
+    /// * [`ContextEngineError::Parse`] - If `Cargo.lock` is invalid
+    pub async fn index_lockfile(&self) -> Result<DependencyOutcome, ContextEngineError> {
+        let lockfile = Lockfile::load(&self.resolver.root().join("Cargo.lock"))?;
+        let mut outcome = DependencyOutcome::default();
+        for package in &lockfile.packages {
+            let Some(dir) = self.resolver.resolve(package) else {
+                // Path packages without a source outside the workspace are
+                // its members
+                if package.source != PackageSource::Path {
+                    outcome.unresolved.push(package.clone());
+                }
+                continue;
+            };
+            let dependencies = lockfile.dependencies(package);
+            match self.index_at(package.clone(), &dependencies, dir).await {
+                Ok(dependency) => outcome.dependencies.push(dependency),
+                Err(error) => outcome.errors.push(error),
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Indexes a dependency, or loads it from the cache.
+    ///
+    /// The packages it depends on are looked up in the `Cargo.lock` of the
+    /// workspace, if it can be read.
+    ///
+    /// Requests that fail while indexing are logged; the graph then lacks
+    /// the symbols they concern and is not cached, so that the next call
+    /// indexes the package again.
+    ///
+    /// # Errors
+    ///
+    /// * [`ContextEngineError::Configuration`] - If the source of the package
+    ///   is not on disk
+    /// * [`ContextEngineError::CacheCorrupted`] - If the cache directory of the
+    ///   package cannot be opened
+    pub async fn index(
+        &self,
+        package: &LockedPackage,
+    ) -> Result<DependencyGraph, ContextEngineError> {
+        let dir =
+            self.resolver
+                .resolve(package)
+                .ok_or_else(|| ContextEngineError::Configuration {
+                    key: None,
+                    message: format!("The source of dependency {package} was not found"),
+                })?;
+        let lockfile = Lockfile::load(&self.resolver.root().join("Cargo.lock")).unwrap_or_default();
+        let locked = lockfile
+            .packages
+            .iter()
+            .find(|locked| locked.name == package.name && locked.version == package.version)
+            .unwrap_or(package);
+        let dependencies = lockfile.dependencies(locked);
+        self.index_at(package.clone(), &dependencies, dir).await
+    }
+
+    async fn index_at(
+        &self,
+        package: LockedPackage,
+        dependencies: &[&LockedPackage],
+        dir: PathBuf,
+    ) -> Result<DependencyGraph, ContextEngineError> {
+        let store = GraphStore::open(self.cache_dir.join(cache_name(&package, &dir)))?;
+        let (files, servers) = self.sources(&dir);
+        let server_version = self.server_version(&servers).await;
+        let keys = files
+            .iter()
+            .map(|(uri, content)| (uri.clone(), CacheKey::new(content, server_version.as_str())))
+            .collect::<Vec<_>>();
+
+        let cached = keys
+            .iter()
+            .map(|(uri, key)| match store.get(uri, key) {
+                Ok(graph) => graph,
+                Err(error) => {
+                    warn!(%error, "Discarding corrupted dependency cache entry");
+                    None
+                }
+            })
+            .collect::<Option<Vec<_>>>();
+        if let Some(mut files) = cached.filter(|files| !files.is_empty()) {
+            debug!(%package, "Loaded dependency from cache");
+            namespace(&mut files, &package, dependencies);
+            return Ok(DependencyGraph {
+                package,
+                dir,
+                files,
+                cached: true,
+            });
+        }
+
+        debug!(%package, files = keys.len(), "Indexing dependency");
+        let indexer = Indexer::new(
+            &dir,
+            Arc::clone(&self.supervisor),
+            Arc::clone(&self.registry),
+            Arc::clone(&self.documents),
+            self.config.clone(),
+        );
+        let outcome = indexer.run().await;
+        let mut files = keys
+            .iter()
+            .map(|(uri, _)| FileGraph::extract(&outcome.graph, uri))
+            .collect::<Vec<_>>();
+
+        // The cache is shared by workspaces that may lock other versions of
+        // the dependencies, so entries keep the crate names
+        if outcome.errors.is_empty() {
+            for ((_, key), file) in keys.iter().zip(&files) {
+                if let Err(error) = store.put(key, file) {
+                    warn!(%package, %error, "Failed to cache dependency");
+                    break;
+                }
+            }
+        } else {
+            for error in &outcome.errors {
+                warn!(%package, %error, "Dependency was indexed partially");
+            }
+        }
+        namespace(&mut files, &package, dependencies);
+        Ok(DependencyGraph {
+            package,
+            dir,
+            files,
+            cached: false,
+        })
+    }
+
+    /// Returns the manifest and the source files a started server handles,
+    /// with their content, and the names of those servers.
+    fn sources(&self, dir: &Path) -> (Vec<(Uri, Vec<u8>)>, BTreeSet<String>) {
+        let mut files = Vec::new();
+        let mut servers = BTreeSet::new();
+        let manifest = dir.join("Cargo.toml");
+        if let (Ok(uri), Ok(content)) = (Uri::from_file_path(&manifest), std::fs::read(&manifest)) {
+            files.push((uri, content));
+        }
+        for path in discover(dir, &self.config.excludes) {
+            let (Ok(uri), Ok(text)) = (Uri::from_file_path(&path), std::fs::read_to_string(&path))
+            else {
+                continue;
+            };
+            let server = self
+                .registry
+                .route(&uri, Some(&text))
+                .into_iter()
+                .map(|route| route.server.name.clone())
+                .find(|name| self.supervisor.status(name).is_some());
+            if let Some(server) = server {
+                servers.insert(server);
+                files.push((uri, text.into_bytes()));
+            }
+        }
+        (files, servers)
+    }
+
+    /// Returns the names and versions of servers, as reported when they
+    /// were initialized.
+    async fn server_version(&self, servers: &BTreeSet<String>) -> String {
+        let mut versions = Vec::new();
+        for name in servers {
+            // Wait for the server to be initialized, so that its version is known
+            let _ = self.supervisor.client(name).await;
+            let info = self
+                .supervisor
+                .initialize_result(name)
+                .and_then(|result| result.server_info);
+            versions.push(match info {
+                Some(info) => match info.version {
+                    Some(version) => format!("{} {version}", info.name),
+                    None => info.name,
+                },
+                None => name.clone(),
+            });
+        }
+        versions.join(", ")
+    }
+}
+
+/// Returns the directory the dependency cache of the machine is kept in:
+/// `context-engine/deps` in `$XDG_CACHE_HOME` or in the `.cache` directory
+/// of the user.
+pub fn default_cache_dir() -> PathBuf {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .or_else(|| std::env::var_os("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(".cache"))
+        })
+        .unwrap_or_else(std::env::temp_dir);
+    cache.join("context-engine").join("deps")
+}
+
+/// Returns the name of the cache directory of a package version.
+///
+/// Registry versions are immutable, so their name is enough. Git checkouts
+/// are told apart by commit and path dependencies by directory.
+fn cache_name(package: &LockedPackage, dir: &Path) -> String {
+    let name = format!("{}-{}", package.name, package.version);
+    match &package.source {
+        PackageSource::Registry { .. } => name,
+        PackageSource::Git { rev, .. } => {
+            format!("{name}-git-{}", rev.get(..12).unwrap_or(rev))
+        }
+        PackageSource::Path => {
+            let hash = content_hash(dir.to_string_lossy().as_bytes());
+            format!("{name}-path-{hash:016x}")
+        }
+    }
+}
+
+/// Moves the symbols of a package from its crate name to its namespace,
+/// and paths into the packages it depends on to theirs.
+fn namespace(files: &mut [FileGraph], package: &LockedPackage, dependencies: &[&LockedPackage]) {
+    // The package itself comes first, so that it wins over a dependency
+    // with the same crate name
+    let namespaces = std::iter::once(package)
+        .chain(dependencies.iter().copied())
+        .map(|package| (package.crate_name(), package.namespace()))
+        .collect::<Vec<_>>();
+    let rename = |name: &mut String| {
+        for (crate_name, namespace) in &namespaces {
+            if name == crate_name {
+                *name = namespace.clone();
+                return;
+            }
+            if let Some(rest) = name
+                .strip_prefix(crate_name.as_str())
+                .filter(|rest| rest.starts_with("::"))
+            {
+                *name = format!("{namespace}{rest}");
+                return;
+            }
+        }
+    };
+
+    for file in files {
+        for node in &mut file.nodes {
+            rename(&mut node.qualified_name);
+            if node.kind == NodeKind::Crate {
+                node.detail = Some(package.version.to_string());
+            }
+        }
+        for edge in &mut file.edges {
+            if let EdgeTarget::External { qualified_name, .. } = &mut edge.to {
+                rename(qualified_name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/indexer.rs"]
+mod tests;
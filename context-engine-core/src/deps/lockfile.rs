@@ -0,0 +1,241 @@
+//! The packages locked by a `Cargo.lock` file.
+
+use std::fmt;
+use std::path::Path;
+
+use semver::Version;
+use serde::Deserialize;
+
+use crate::error::ContextEngineError;
+
+/// Where the source of a locked package comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PackageSource {
+    /// A package registry such as crates.io
+    Registry {
+        /// The URL of the registry index, e.g.
+        /// `https://github.com/rust-lang/crates.io-index`
+        index: String,
+    },
+    /// A git repository, pinned to a commit
+    Git {
+        /// The URL of the repository, without query and fragment
+        url: String,
+        /// The full hash of the locked commit
+        rev: String,
+    },
+    /// A local directory: a workspace member or a `path` dependency
+    Path,
+}
+
+impl PackageSource {
+    /// Parses the `source` field of a package in `Cargo.lock`.
+    ///
+    /// Returns `None` for sources of unknown kinds.
+    pub fn parse(source: &str) -> Option<Self> {
+        let (kind, location) = source.split_once('+')?;
+        match kind {
+            "registry" | "sparse" => Some(PackageSource::Registry {
+                index: location.to_string(),
+            }),
+            "git" => {
+                let (url, rev) = location.split_once('#')?;
+                let url = url.split('?').next().unwrap_or(url);
+                Some(PackageSource::Git {
+                    url: url.to_string(),
+                    rev: rev.to_string(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A package of a `Cargo.lock` file, at the exact version the workspace
+/// builds with.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::deps::{LockedPackage, PackageSource};
+///
+/// let package = LockedPackage::new("tokio-util", "0.7.15".parse().unwrap(), PackageSource::Path);
+/// assert_eq!(package.crate_name(), "tokio_util");
+/// assert_eq!(package.namespace(), "tokio_util@0.7.15");
+/// assert_eq!(package.to_string(), "tokio-util 0.7.15");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LockedPackage {
+    /// The name of the package, e.g. `tokio-util`
+    pub name: String,
+    /// The locked version
+    pub version: Version,
+    /// Where the source comes from
+    pub source: PackageSource,
+    /// The packages it depends on as `Cargo.lock` lists them: a name,
+    /// followed by the version if several versions are locked, e.g.
+    /// `syn 2.0.101`
+    pub dependencies: Vec<String>,
+}
+
+impl LockedPackage {
+    /// Creates a locked package.
+    pub fn new(name: impl Into<String>, version: Version, source: PackageSource) -> Self {
+        Self {
+            name: name.into(),
+            version,
+            source,
+            dependencies: Vec::new(),
+        }
+    }
+
+    /// Sets the packages it depends on, see [`LockedPackage::dependencies`].
+    pub fn with_dependencies(mut self, dependencies: Vec<String>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Returns the name of the library crate of the package, e.g.
+    /// `tokio_util`.
+    pub fn crate_name(&self) -> String {
+        self.name.replace('-', "_")
+    }
+
+    /// Returns the namespace of the symbols of the package in the knowledge
+    /// graph, e.g. `tokio_util@0.7.15`.
+    pub fn namespace(&self) -> String {
+        format!("{}@{}", self.crate_name(), self.version)
+    }
+}
+
+impl fmt::Display for LockedPackage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.version)
+    }
+}
+
+/// The raw form of a `Cargo.lock` file.
+#[derive(Deserialize)]
+struct RawLockfile {
+    #[serde(default, rename = "package")]
+    packages: Vec<RawPackage>,
+}
+
+#[derive(Deserialize)]
+struct RawPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+/// The packages of a `Cargo.lock` file.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::deps::{Lockfile, PackageSource};
+///
+/// let lockfile = Lockfile::parse(r#"
+/// version = 4
+///
+/// [[package]]
+/// name = "app"
+/// version = "0.1.0"
+///
+/// [[package]]
+/// name = "tokio"
+/// version = "1.45.1"
+/// source = "registry+https://github.com/rust-lang/crates.io-index"
+/// "#).unwrap();
+///
+/// let tokio = lockfile.package("tokio").unwrap();
+/// assert_eq!(tokio.version.to_string(), "1.45.1");
+/// assert!(matches!(tokio.source, PackageSource::Registry { .. }));
+/// assert_eq!(lockfile.package("app").unwrap().source, PackageSource::Path);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lockfile {
+    /// The locked packages, in the order of the file
+    pub packages: Vec<LockedPackage>,
+}
+
+impl Lockfile {
+    /// Parses the content of a `Cargo.lock` file.
+    ///
+    /// Packages with an invalid version or a source of unknown kind are
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::Parse`] if the content is not a valid
+    /// lock file.
+    pub fn parse(text: &str) -> Result<Self, ContextEngineError> {
+        let raw =
+            toml::from_str::<RawLockfile>(text).map_err(|error| ContextEngineError::Parse {
+                uri: None,
+                message: format!("Invalid Cargo.lock: {}", error.message()),
+            })?;
+        let packages = raw
+            .packages
+            .into_iter()
+            .filter_map(|package| {
+                let version = Version::parse(&package.version).ok()?;
+                let source = match &package.source {
+                    Some(source) => PackageSource::parse(source)?,
+                    None => PackageSource::Path,
+                };
+                Some(
+                    LockedPackage::new(package.name, version, source)
+                        .with_dependencies(package.dependencies),
+                )
+            })
+            .collect();
+        Ok(Self { packages })
+    }
+
+    /// Reads and parses a `Cargo.lock` file.
+    ///
+    /// # Errors
+    ///
+    /// * [`ContextEngineError::Configuration`] - If the file cannot be read
+    /// * [`ContextEngineError::Parse`] - If the file is not a valid lock file
+    pub fn load(path: &Path) -> Result<Self, ContextEngineError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|error| ContextEngineError::Configuration {
+                key: None,
+                message: format!("Failed to read {}: {error}", path.display()),
+            })?;
+        Self::parse(&text)
+    }
+
+    /// Returns the first package with a name.
+    pub fn package(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+
+    /// Returns the locked packages a package depends on.
+    ///
+    /// Dependencies that are not locked, e.g. because their version could
+    /// not be parsed, are left out.
+    pub fn dependencies(&self, package: &LockedPackage) -> Vec<&LockedPackage> {
+        package
+            .dependencies
+            .iter()
+            .filter_map(|dependency| {
+                let mut parts = dependency.split(' ');
+                let name = parts.next()?;
+                let version = parts.next();
+                self.packages.iter().find(|locked| {
+                    locked.name == name
+                        && version.map_or(true, |version| locked.version.to_string() == version)
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/lockfile.rs"]
+mod tests;
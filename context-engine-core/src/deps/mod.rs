@@ -0,0 +1,40 @@
+//! Indexing of the sources of dependencies.
+//!
+//! Answers about a workspace often depend on the code it builds on: the
+//! signature of a function of `tokio`, the traits a `serde` type implements.
+//! This module reads the exact versions a workspace locks in `Cargo.lock`,
+//! finds their sources where cargo put them, i.e. the registry cache, git
+//! checkouts or `path` dependencies, and indexes them with the language
+//! servers of the workspace. The symbols of each package live in a
+//! read-only namespace tagged with its version, e.g.
+//! `tokio@1.45.1::sync::Mutex`, and the result is cached on disk for all
+//! workspaces of the machine, so that each version is indexed only once.
+//!
+//! ## Structs
+//!
+//! * [`Lockfile`] - The packages of a `Cargo.lock` file.
+//! * [`LockedPackage`] - A package at the exact version a workspace builds
+//!   with.
+//! * [`SourceResolver`] - Finds the sources of locked packages on disk.
+//! * [`DependencyIndexer`] - Indexes and caches the sources of dependencies.
+//! * [`DependencyGraph`] - The indexed sources of a dependency.
+//! * [`DependencyOutcome`] - The result of indexing the dependencies of a
+//!   workspace.
+//!
+//! ## Enums
+//!
+//! * [`PackageSource`] - Where the source of a locked package comes from.
+//!
+//! ## Functions
+//!
+//! * [`cargo_home`] - The cargo home directory of the user.
+//! * [`default_cache_dir`] - The directory of the dependency cache of the
+//!   machine.
+
+mod indexer;
+mod lockfile;
+mod resolve;
+
+pub use indexer::{DependencyGraph, DependencyIndexer, DependencyOutcome, default_cache_dir};
+pub use lockfile::{LockedPackage, Lockfile, PackageSource};
+pub use resolve::{SourceResolver, cargo_home};
@@ -0,0 +1,262 @@
+//! Resolution of locked packages to their sources on disk.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use tracing::debug;
+
+use crate::deps::{LockedPackage, PackageSource};
+use crate::index::DEFAULT_EXCLUDES;
+
+/// Finds the sources of locked packages where cargo put them.
+///
+/// * Registry packages are unpacked to
+///   `$CARGO_HOME/registry/src/<registry>/<name>-<version>`.
+/// * Git packages are checked out to
+///   `$CARGO_HOME/git/checkouts/<repository>-<hash>/<short commit>`, possibly
+///   in a subdirectory of the repository.
+/// * Path packages are found through the `path` dependencies of the manifests
+///   anywhere in the workspace, which are read once per resolver. Packages
+///   inside the workspace root are members of the workspace rather than
+///   dependencies and are not resolved.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::deps::{LockedPackage, PackageSource, SourceResolver};
+///
+/// let cargo_home = std::env::temp_dir().join(format!("resolver-doc-{}", std::process::id()));
+/// let source = cargo_home.join("registry/src/index.crates.io-1949cf8c6b5b557f/either-1.15.0");
+/// std::fs::create_dir_all(&source).unwrap();
+/// std::fs::write(source.join("Cargo.toml"), "[package]\nname = \"either\"\n").unwrap();
+///
+/// let resolver = SourceResolver::new("/path/to/workspace", &cargo_home);
+/// let registry = PackageSource::Registry { index: "https://index.crates.io/".to_string() };
+/// let either = LockedPackage::new("either", "1.15.0".parse().unwrap(), registry);
+/// assert_eq!(resolver.resolve(&either), Some(source));
+/// # std::fs::remove_dir_all(&cargo_home).ok();
+/// ```
+#[derive(Debug, Clone)]
+pub struct SourceResolver {
+    root: PathBuf,
+    cargo_home: PathBuf,
+    /// The directories of the `path` dependencies, by package name
+    path_sources: OnceLock<HashMap<String, PathBuf>>,
+}
+
+impl SourceResolver {
+    /// Creates a resolver for a workspace.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The workspace root, which holds `Cargo.lock`
+    /// * `cargo_home` - The cargo home directory, see [`cargo_home`]
+    pub fn new(root: impl Into<PathBuf>, cargo_home: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            cargo_home: cargo_home.into(),
+            path_sources: OnceLock::new(),
+        }
+    }
+
+    /// Returns the workspace root.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the directory of the source of a package, i.e. the directory of
+    /// its `Cargo.toml`.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the source is not on disk, e.g. because the package was not
+    /// downloaded yet, or if the package is a member of the workspace.
+    pub fn resolve(&self, package: &LockedPackage) -> Option<PathBuf> {
+        let dir = match &package.source {
+            PackageSource::Registry { .. } => self.registry_source(package),
+            PackageSource::Git { url, rev } => self.git_source(package, url, rev),
+            PackageSource::Path => self
+                .path_sources
+                .get_or_init(|| self.path_sources())
+                .get(&package.name)
+                .cloned(),
+        };
+        if dir.is_none() {
+            debug!(%package, "Source of dependency not found");
+        }
+        dir
+    }
+
+    fn registry_source(&self, package: &LockedPackage) -> Option<PathBuf> {
+        let name = format!("{}-{}", package.name, package.version);
+        subdirectories(&self.cargo_home.join("registry/src"))
+            .into_iter()
+            .map(|registry| registry.join(&name))
+            .find(|dir| dir.join("Cargo.toml").is_file())
+    }
+
+    fn git_source(&self, package: &LockedPackage, url: &str, rev: &str) -> Option<PathBuf> {
+        let repository = url
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or(url)
+            .trim_end_matches(".git");
+        let prefix = format!("{repository}-");
+        subdirectories(&self.cargo_home.join("git/checkouts"))
+            .into_iter()
+            .filter(|dir| {
+                dir.file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(&prefix))
+            })
+            .flat_map(|dir| subdirectories(&dir))
+            .filter(|checkout| {
+                checkout.file_name().is_some_and(|name| {
+                    let name = name.to_string_lossy();
+                    !name.is_empty() && rev.starts_with(name.as_ref())
+                })
+            })
+            .find_map(|checkout| find_manifest(&checkout, &package.name, 3))
+    }
+
+    /// Returns the directories of the `path` dependencies of the workspace
+    /// manifests that are outside the workspace, by package name.
+    fn path_sources(&self) -> HashMap<String, PathBuf> {
+        let mut sources = HashMap::new();
+        let root = self.root.canonicalize().ok();
+        let mut manifests = Vec::new();
+        workspace_manifests(&self.root, &mut manifests);
+
+        for manifest in manifests {
+            let Some(dir) = manifest.parent() else {
+                continue;
+            };
+            let Some(table) = read_manifest(&manifest) else {
+                continue;
+            };
+            for path in dependency_paths(&table) {
+                let dependency = dir.join(path);
+                let Ok(dependency) = dependency.canonicalize() else {
+                    continue;
+                };
+                let inside = root
+                    .as_ref()
+                    .is_some_and(|root| dependency.starts_with(root));
+                if inside {
+                    continue;
+                }
+                if let Some(name) = package_name(&dependency.join("Cargo.toml")) {
+                    sources.insert(name, dependency);
+                }
+            }
+        }
+        sources
+    }
+}
+
+/// Returns the cargo home directory: `$CARGO_HOME`, or `.cargo` in the home
+/// directory of the user.
+pub fn cargo_home() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("CARGO_HOME") {
+        return Some(PathBuf::from(dir));
+    }
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".cargo"))
+}
+
+/// Returns the subdirectories of a directory, sorted by path.
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs
+}
+
+/// Collects the manifests in a directory of the workspace and, recursively,
+/// in its subdirectories, skipping hidden and excluded directories, e.g.
+/// `target`, and symbolic links.
+fn workspace_manifests(dir: &Path, manifests: &mut Vec<PathBuf>) {
+    let manifest = dir.join("Cargo.toml");
+    if manifest.is_file() {
+        manifests.push(manifest);
+    }
+    for subdirectory in subdirectories(dir) {
+        let walk = subdirectory.file_name().is_some_and(|name| {
+            let name = name.to_string_lossy();
+            !name.starts_with('.') && !DEFAULT_EXCLUDES.contains(&name.as_ref())
+        }) && !subdirectory.is_symlink();
+        if walk {
+            workspace_manifests(&subdirectory, manifests);
+        }
+    }
+}
+
+/// Returns the directory below `dir`, at most `depth` levels deep, whose
+/// manifest defines the package `name`.
+fn find_manifest(dir: &Path, name: &str, depth: usize) -> Option<PathBuf> {
+    if package_name(&dir.join("Cargo.toml")).as_deref() == Some(name) {
+        return Some(dir.to_path_buf());
+    }
+    if depth == 0 {
+        return None;
+    }
+    subdirectories(dir)
+        .into_iter()
+        .filter(|dir| {
+            dir.file_name()
+                .is_some_and(|name| !name.to_string_lossy().starts_with('.'))
+        })
+        .find_map(|dir| find_manifest(&dir, name, depth - 1))
+}
+
+fn read_manifest(path: &Path) -> Option<toml::Table> {
+    let text = std::fs::read_to_string(path).ok()?;
+    toml::from_str::<toml::Table>(&text).ok()
+}
+
+/// Returns the name of the package a manifest defines.
+fn package_name(manifest: &Path) -> Option<String> {
+    let table = read_manifest(manifest)?;
+    Some(table.get("package")?.get("name")?.as_str()?.to_string())
+}
+
+/// Returns the `path` of the dependencies of a manifest, including those of
+/// `[workspace.dependencies]` and of target-specific tables.
+fn dependency_paths(manifest: &toml::Table) -> Vec<String> {
+    const TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+    let mut tables = Vec::new();
+    for name in TABLES {
+        tables.extend(manifest.get(name));
+    }
+    tables.extend(
+        manifest
+            .get("workspace")
+            .and_then(|workspace| workspace.get("dependencies")),
+    );
+    if let Some(targets) = manifest.get("target").and_then(toml::Value::as_table) {
+        for target in targets.values() {
+            for name in TABLES {
+                tables.extend(target.get(name));
+            }
+        }
+    }
+
+    tables
+        .into_iter()
+        .filter_map(toml::Value::as_table)
+        .flat_map(|table| table.values())
+        .filter_map(|dependency| dependency.get("path")?.as_str())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "tests/resolve.rs"]
+mod tests;
@@ -0,0 +1,344 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use proptest::prelude::*;
+use serde_json::{Value, json};
+
+use super::*;
+use crate::JsonRpcError;
+use crate::graph::SymbolNode;
+use crate::lsp::RestartPolicy;
+use crate::lsp::testing::{FakeLauncher, FakeServer, FakeSession};
+use crate::types::{LineIndex, Position, Range};
+
+const LOCKFILE: &str = r#"version = 4
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["either", "missing"]
+
+[[package]]
+name = "either"
+version = "1.15.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "missing"
+version = "2.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+const EITHER_LIB: &str = "pub mod iter;\n\npub fn left() {}\n";
+
+const EITHER_ITER: &str = "pub fn right() {}\n";
+
+/// A server that reports the functions of the documents.
+struct SymbolServer {
+    /// The version the server reports in its `serverInfo`
+    version: &'static str,
+}
+
+impl FakeServer for SymbolServer {
+    fn initialize(&self) -> Value {
+        json!({
+            "capabilities": { "documentSymbolProvider": true },
+            "serverInfo": { "name": "fake-analyzer", "version": self.version },
+        })
+    }
+
+    async fn request(
+        &self,
+        session: &FakeSession,
+        method: &str,
+        params: Value,
+    ) -> Option<Result<Value, JsonRpcError>> {
+        if method != "textDocument/documentSymbol" {
+            return Some(Ok(Value::Null));
+        }
+        let uri = params.pointer("/textDocument/uri").and_then(Value::as_str);
+        let text = uri.and_then(|uri| session.text(uri)).unwrap_or_default();
+        Some(Ok(document_symbols(&text)))
+    }
+}
+
+/// Reports a function symbol for every `pub fn` of a text.
+#[allow(deprecated)]
+fn document_symbols(text: &str) -> Value {
+    let index = LineIndex::new(text);
+    let position = |offset| {
+        index
+            .position(offset, &lsp_types::PositionEncodingKind::UTF16)
+            .unwrap()
+    };
+    let symbols = text
+        .match_indices("pub fn ")
+        .map(|(offset, keyword)| {
+            let start = offset + keyword.len();
+            let rest = text.get(start..).unwrap_or_default();
+            let len = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            (start, rest.get(..len).unwrap_or_default())
+        })
+        .map(|(start, name)| {
+            let selection = Range::new(position(start), position(start + name.len()));
+            lsp_types::DocumentSymbol {
+                name: name.to_string(),
+                detail: None,
+                kind: lsp_types::SymbolKind::FUNCTION,
+                tags: None,
+                deprecated: None,
+                range: Range::new(Position::new(selection.start.line, 0), selection.end),
+                selection_range: selection,
+                children: None,
+            }
+        })
+        .collect::<Vec<_>>();
+    json!(symbols)
+}
+
+fn write(path: &Path, content: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+/// A workspace that locks `either`, whose source is in a fake cargo home,
+/// and `missing`, which was not downloaded.
+struct Fixture {
+    dir: tempfile::TempDir,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("workspace");
+        write(
+            &root.join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n",
+        );
+        write(&root.join("Cargo.lock"), LOCKFILE);
+        write(&root.join("src/main.rs"), "fn main() {}\n");
+
+        let source = dir.path().join("cargo/registry/src/index/either-1.15.0");
+        write(
+            &source.join("Cargo.toml"),
+            "[package]\nname = \"either\"\nversion = \"1.15.0\"\n",
+        );
+        write(&source.join("src/lib.rs"), EITHER_LIB);
+        write(&source.join("src/iter.rs"), EITHER_ITER);
+        write(&source.join("tests/either.rs"), "pub fn in_test() {}\n");
+        Self { dir }
+    }
+
+    fn root(&self) -> PathBuf {
+        self.dir.path().join("workspace")
+    }
+
+    fn source(&self) -> PathBuf {
+        self.dir
+            .path()
+            .join("cargo/registry/src/index/either-1.15.0")
+    }
+
+    fn indexer(&self, version: &'static str) -> (DependencyIndexer, FakeLauncher<SymbolServer>) {
+        let launcher = FakeLauncher::new(SymbolServer { version });
+        let documents = Arc::new(DocumentStore::default());
+        let supervisor = Arc::new(LspSupervisor::with_launcher(
+            self.root(),
+            Arc::clone(&documents),
+            launcher.clone(),
+            RestartPolicy::default(),
+        ));
+        let registry = LanguageRegistry::with_defaults();
+        let mut server = registry.server("rust-analyzer").unwrap().clone();
+        server.request_timeout_ms = 5_000;
+        supervisor.start(server).unwrap();
+
+        let indexer =
+            DependencyIndexer::new(self.root(), supervisor, Arc::new(registry), documents)
+                .with_cargo_home(self.dir.path().join("cargo"))
+                .with_cache_dir(self.dir.path().join("cache"));
+        (indexer, launcher)
+    }
+}
+
+/// The URIs of the `textDocument/documentSymbol` requests received.
+fn symbol_requests(launcher: &FakeLauncher<SymbolServer>) -> Vec<String> {
+    launcher
+        .log()
+        .params("textDocument/documentSymbol")
+        .iter()
+        .filter_map(|params| params.pointer("/textDocument/uri").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect()
+}
+
+fn names(graph: &KnowledgeGraph) -> Vec<String> {
+    let mut names = graph
+        .nodes()
+        .map(|node| format!("{} {}", node.kind, node.qualified_name))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn test_index_lockfile_into_namespaces() {
+    let fixture = Fixture::new();
+    let (indexer, fake) = fixture.indexer("1.0.0");
+    let outcome = indexer.index_lockfile().await.unwrap();
+
+    assert!(outcome.errors.is_empty(), "{:?}", outcome.errors);
+    let unresolved = outcome
+        .unresolved
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(unresolved, ["missing 2.0.0"]);
+    assert_eq!(outcome.dependencies.len(), 1);
+    let either = outcome.dependencies.first().unwrap();
+    assert_eq!(either.package.name, "either");
+    assert!(!either.cached);
+
+    let mut graph = KnowledgeGraph::new();
+    outcome.merge_into(&mut graph);
+    assert_eq!(
+        names(&graph),
+        [
+            "crate either@1.15.0",
+            "function either@1.15.0::iter::right",
+            "function either@1.15.0::left",
+            "module either@1.15.0",
+            "module either@1.15.0::iter",
+        ]
+    );
+    let krate = graph.lookup("either@1.15.0");
+    assert_eq!(krate.first().unwrap().detail.as_deref(), Some("1.15.0"));
+
+    // Tests of dependencies are not indexed
+    let mut requested = symbol_requests(&fake)
+        .iter()
+        .map(|uri| uri.rsplit('/').next().unwrap().to_string())
+        .collect::<Vec<_>>();
+    requested.sort();
+    assert_eq!(requested, ["iter.rs", "lib.rs"]);
+
+    let lib = Uri::from_file_path(&fixture.source().join("src/lib.rs")).unwrap();
+    let main = Uri::from_file_path(&fixture.root().join("src/main.rs")).unwrap();
+    assert!(outcome.is_read_only(&lib));
+    assert!(!outcome.is_read_only(&main));
+}
+
+#[tokio::test]
+async fn test_cache_is_shared_across_indexers() {
+    let fixture = Fixture::new();
+    let (indexer, _) = fixture.indexer("1.0.0");
+    let first = indexer.index_lockfile().await.unwrap();
+
+    // Another workspace session with the same cache directory
+    let (indexer, fake) = fixture.indexer("1.0.0");
+    let second = indexer.index_lockfile().await.unwrap();
+    let either = second.dependencies.first().unwrap();
+    assert!(either.cached);
+    assert!(symbol_requests(&fake).is_empty());
+    assert_eq!(
+        names(&either.graph()),
+        names(&first.dependencies.first().unwrap().graph())
+    );
+}
+
+#[tokio::test]
+async fn test_server_upgrade_reindexes() {
+    let fixture = Fixture::new();
+    let (indexer, _) = fixture.indexer("1.0.0");
+    indexer.index_lockfile().await.unwrap();
+
+    let (indexer, fake) = fixture.indexer("2.0.0");
+    let either = LockedPackage::new(
+        "either",
+        "1.15.0".parse().unwrap(),
+        PackageSource::Registry {
+            index: "https://github.com/rust-lang/crates.io-index".to_string(),
+        },
+    );
+    let dependency = indexer.index(&either).await.unwrap();
+    assert!(!dependency.cached);
+    assert_eq!(symbol_requests(&fake).len(), 2);
+}
+
+#[tokio::test]
+async fn test_index_unresolved_package() {
+    let fixture = Fixture::new();
+    let (indexer, _) = fixture.indexer("1.0.0");
+    let missing = LockedPackage::new(
+        "missing",
+        "2.0.0".parse().unwrap(),
+        PackageSource::Registry {
+            index: "https://github.com/rust-lang/crates.io-index".to_string(),
+        },
+    );
+    let error = indexer.index(&missing).await.unwrap_err();
+    assert!(matches!(error, ContextEngineError::Configuration { .. }));
+}
+
+#[test]
+fn test_cache_names() {
+    let dir = Path::new("/deps/patched");
+    let git = LockedPackage::new(
+        "patched",
+        "0.3.0".parse().unwrap(),
+        PackageSource::Git {
+            url: "https://github.com/example/patched.git".to_string(),
+            rev: "0a1b2c3d4e5f60718293a4b5c6d7e8f901234567".to_string(),
+        },
+    );
+    assert_eq!(cache_name(&git, dir), "patched-0.3.0-git-0a1b2c3d4e5f");
+    let path = LockedPackage::new("patched", "0.3.0".parse().unwrap(), PackageSource::Path);
+    assert_ne!(
+        cache_name(&path, dir),
+        cache_name(&path, Path::new("/other"))
+    );
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_namespace_renames_only_the_crates(
+        module in "[a-z]{1,8}",
+        item in "[a-z]{1,8}",
+    ) {
+        let package = LockedPackage::new("my-crate", "1.2.3".parse().unwrap(), PackageSource::Path);
+        let dependency = LockedPackage::new("other-dep", "0.4.0".parse().unwrap(), PackageSource::Path);
+        let uri = Uri::from_str("file:///deps/my-crate/src/lib.rs").unwrap();
+        let other_uri = Uri::from_str("file:///deps/other-dep/src/lib.rs").unwrap();
+        let range = Range::new(Position::new(0, 0), Position::new(0, 1));
+        let mut graph = KnowledgeGraph::new();
+        let inner = format!("my_crate::{module}::{item}");
+        let lookalike = format!("my_crate_{module}::{item}");
+        let from = graph.add_node(SymbolNode::new(NodeKind::Function, item.clone(), inner, uri.clone(), range));
+        graph.add_node(SymbolNode::new(NodeKind::Function, item.clone(), lookalike.clone(), uri.clone(), range));
+        let external = format!("other_dep::{module}::{item}");
+        let to = graph.add_node(SymbolNode::new(NodeKind::Function, item.clone(), external, other_uri, range));
+        graph.add_edge(from, crate::graph::EdgeKind::References, to);
+        let mut files = vec![FileGraph::extract(&graph, &uri)];
+        namespace(&mut files, &package, &[&dependency]);
+
+        let names = files
+            .iter()
+            .flat_map(|file| &file.nodes)
+            .map(|node| node.qualified_name.clone())
+            .collect::<Vec<_>>();
+        prop_assert_eq!(names, vec![format!("my_crate@1.2.3::{module}::{item}"), lookalike]);
+        let targets = files
+            .iter()
+            .flat_map(|file| &file.edges)
+            .filter_map(|edge| match &edge.to {
+                EdgeTarget::External { qualified_name, .. } => Some(qualified_name.clone()),
+                EdgeTarget::Local(_) => None,
+            })
+            .collect::<Vec<_>>();
+        prop_assert_eq!(targets, vec![format!("other_dep@0.4.0::{module}::{item}")]);
+    }
+}
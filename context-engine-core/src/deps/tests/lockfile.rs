@@ -0,0 +1,168 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+const LOCKFILE: &str = r#"# This file is automatically @generated by Cargo.
+version = 4
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "tokio-util",
+ "patched",
+]
+
+[[package]]
+name = "tokio-util"
+version = "0.7.15"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "66a539a9ad6d5d281510d5bd368c973d636c02dbf8a67300bfb6b950696ad7df"
+
+[[package]]
+name = "patched"
+version = "0.3.0-alpha.1"
+source = "git+https://github.com/example/patched.git?branch=main#0a1b2c3d4e5f60718293a4b5c6d7e8f901234567"
+
+[[package]]
+name = "mirror"
+version = "1.0.0"
+source = "sparse+https://mirror.example.com/index/"
+
+[[package]]
+name = "broken"
+version = "not a version"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "unknown"
+version = "1.0.0"
+source = "svn+https://example.com/unknown"
+"#;
+
+#[test]
+fn test_parse_sources() {
+    let lockfile = Lockfile::parse(LOCKFILE).unwrap();
+    let names = lockfile
+        .packages
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "app 0.1.0",
+            "tokio-util 0.7.15",
+            "patched 0.3.0-alpha.1",
+            "mirror 1.0.0"
+        ]
+    );
+
+    assert_eq!(lockfile.package("app").unwrap().source, PackageSource::Path);
+    assert_eq!(
+        lockfile.package("tokio-util").unwrap().source,
+        PackageSource::Registry {
+            index: "https://github.com/rust-lang/crates.io-index".to_string()
+        }
+    );
+    assert_eq!(
+        lockfile.package("patched").unwrap().source,
+        PackageSource::Git {
+            url: "https://github.com/example/patched.git".to_string(),
+            rev: "0a1b2c3d4e5f60718293a4b5c6d7e8f901234567".to_string(),
+        }
+    );
+    assert_eq!(
+        lockfile.package("mirror").unwrap().source,
+        PackageSource::Registry {
+            index: "https://mirror.example.com/index/".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_namespace_includes_prerelease() {
+    let lockfile = Lockfile::parse(LOCKFILE).unwrap();
+    let patched = lockfile.package("patched").unwrap();
+    assert_eq!(patched.namespace(), "patched@0.3.0-alpha.1");
+    let tokio_util = lockfile.package("tokio-util").unwrap();
+    assert_eq!(tokio_util.namespace(), "tokio_util@0.7.15");
+}
+
+#[test]
+fn test_dependencies_resolve_to_locked_versions() {
+    let lockfile = Lockfile::parse(
+        r#"
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["syn 1.0.109", "syn 2.0.101", "tokio", "unlocked"]
+
+[[package]]
+name = "syn"
+version = "1.0.109"
+
+[[package]]
+name = "syn"
+version = "2.0.101"
+
+[[package]]
+name = "tokio"
+version = "1.45.1"
+"#,
+    )
+    .unwrap();
+    let app = lockfile.package("app").unwrap();
+    let dependencies = lockfile
+        .dependencies(app)
+        .into_iter()
+        .map(LockedPackage::namespace)
+        .collect::<Vec<_>>();
+    assert_eq!(dependencies, ["syn@1.0.109", "syn@2.0.101", "tokio@1.45.1"]);
+    let tokio = lockfile.package("tokio").unwrap();
+    assert!(lockfile.dependencies(tokio).is_empty());
+}
+
+#[test]
+fn test_parse_empty_lockfile() {
+    assert_eq!(
+        Lockfile::parse("version = 4\n").unwrap(),
+        Lockfile::default()
+    );
+}
+
+#[test]
+fn test_invalid_lockfile() {
+    let error = Lockfile::parse("[[package]\nname = ").unwrap_err();
+    assert!(matches!(error, ContextEngineError::Parse { uri: None, .. }));
+    assert!(error.to_string().contains("Invalid Cargo.lock"));
+}
+
+#[test]
+fn test_load_missing_lockfile() {
+    let dir = tempfile::tempdir().unwrap();
+    let error = Lockfile::load(&dir.path().join("Cargo.lock")).unwrap_err();
+    assert!(matches!(error, ContextEngineError::Configuration { .. }));
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_registry_packages_round_trip(
+        name in "[a-z][a-z0-9_-]{0,15}",
+        major in 0u64..100,
+        minor in 0u64..100,
+        patch in 0u64..100,
+    ) {
+        let text = format!(
+            "[[package]]\nname = \"{name}\"\nversion = \"{major}.{minor}.{patch}\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n"
+        );
+        let lockfile = Lockfile::parse(&text).unwrap();
+        let package = lockfile.package(&name).unwrap();
+        prop_assert_eq!(package.version.clone(), Version::new(major, minor, patch));
+        prop_assert!(!package.crate_name().contains('-'));
+        prop_assert_eq!(package.namespace(), format!("{}@{major}.{minor}.{patch}", package.crate_name()));
+    }
+}
@@ -0,0 +1,183 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+const REV: &str = "0a1b2c3d4e5f60718293a4b5c6d7e8f901234567";
+
+fn write(path: &Path, content: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+fn manifest(name: &str) -> String {
+    format!("[package]\nname = \"{name}\"\nversion = \"1.0.0\"\n")
+}
+
+fn registry() -> PackageSource {
+    PackageSource::Registry {
+        index: "https://github.com/rust-lang/crates.io-index".to_string(),
+    }
+}
+
+fn package(name: &str, version: &str, source: PackageSource) -> LockedPackage {
+    LockedPackage::new(name, version.parse().unwrap(), source)
+}
+
+#[test]
+fn test_resolve_registry_package() {
+    let home = tempfile::tempdir().unwrap();
+    let source = home
+        .path()
+        .join("registry/src/index.crates.io-1949cf8c6b5b557f/tokio-1.45.1");
+    write(&source.join("Cargo.toml"), &manifest("tokio"));
+    // Downloaded but not unpacked
+    std::fs::create_dir_all(
+        home.path()
+            .join("registry/src/index.crates.io-1949cf8c6b5b557f/tokio-1.44.0"),
+    )
+    .unwrap();
+
+    let resolver = SourceResolver::new("/workspace", home.path());
+    assert_eq!(
+        resolver.resolve(&package("tokio", "1.45.1", registry())),
+        Some(source)
+    );
+    assert_eq!(
+        resolver.resolve(&package("tokio", "1.44.0", registry())),
+        None
+    );
+    assert_eq!(
+        resolver.resolve(&package("serde", "1.0.0", registry())),
+        None
+    );
+}
+
+#[test]
+fn test_resolve_git_package() {
+    let home = tempfile::tempdir().unwrap();
+    let checkout = home
+        .path()
+        .join("git/checkouts/tools-5f3c1b2a9d8e7f60/0a1b2c3");
+    write(
+        &checkout.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/*\"]\n",
+    );
+    write(
+        &checkout.join("crates/patched/Cargo.toml"),
+        &manifest("patched"),
+    );
+    write(
+        &checkout.join("crates/other/Cargo.toml"),
+        &manifest("other"),
+    );
+    // Another commit of the same repository
+    write(
+        &home
+            .path()
+            .join("git/checkouts/tools-5f3c1b2a9d8e7f60/ffffff0/crates/patched/Cargo.toml"),
+        &manifest("patched"),
+    );
+
+    let resolver = SourceResolver::new("/workspace", home.path());
+    let git = PackageSource::Git {
+        url: "https://github.com/example/tools.git".to_string(),
+        rev: REV.to_string(),
+    };
+    assert_eq!(
+        resolver.resolve(&package("patched", "0.3.0", git.clone())),
+        Some(checkout.join("crates/patched"))
+    );
+    assert_eq!(resolver.resolve(&package("missing", "0.3.0", git)), None);
+}
+
+#[test]
+fn test_resolve_path_dependency() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("workspace");
+    write(
+        &root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"app\"]\n\n[workspace.dependencies]\nshared = { path = \
+         \"../shared\" }\n",
+    );
+    write(
+        &root.join("app/Cargo.toml"),
+        "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\ncore = { path = \
+         \"../core\" }\n\n[target.'cfg(unix)'.dev-dependencies]\nfixtures = { path = \
+         \"../../fixtures\" }\n",
+    );
+    write(&root.join("core/Cargo.toml"), &manifest("core"));
+    // Members may be nested at any depth
+    write(
+        &root.join("crates/tools/cli/Cargo.toml"),
+        "[package]\nname = \"cli\"\nversion = \"0.1.0\"\n\n[dependencies]\nvendored = { path = \
+         \"../../../../vendored\" }\n",
+    );
+    write(
+        &dir.path().join("vendored/Cargo.toml"),
+        &manifest("vendored"),
+    );
+    write(
+        &root.join("target/debug/Cargo.toml"),
+        "[dependencies]\nbuilt = { path = \"../../../built\" }\n",
+    );
+    write(&dir.path().join("built/Cargo.toml"), &manifest("built"));
+    write(&dir.path().join("shared/Cargo.toml"), &manifest("shared"));
+    write(
+        &dir.path().join("fixtures/Cargo.toml"),
+        &manifest("fixtures"),
+    );
+
+    let resolver = SourceResolver::new(&root, dir.path().join("cargo"));
+    let resolve = |name| resolver.resolve(&package(name, "1.0.0", PackageSource::Path));
+    assert_eq!(
+        resolve("shared"),
+        Some(dir.path().join("shared").canonicalize().unwrap())
+    );
+    assert_eq!(
+        resolve("fixtures"),
+        Some(dir.path().join("fixtures").canonicalize().unwrap())
+    );
+    assert_eq!(
+        resolve("vendored"),
+        Some(dir.path().join("vendored").canonicalize().unwrap())
+    );
+    // Excluded directories are not searched
+    assert_eq!(resolve("built"), None);
+    // Members of the workspace are not dependencies
+    assert_eq!(resolve("core"), None);
+    assert_eq!(resolve("app"), None);
+
+    // The manifests are read once
+    write(
+        &root.join("late/Cargo.toml"),
+        "[dependencies]\nlate = { path = \"../../late\" }\n",
+    );
+    write(&dir.path().join("late/Cargo.toml"), &manifest("late"));
+    assert_eq!(resolve("late"), None);
+    let resolver = SourceResolver::new(&root, dir.path().join("cargo"));
+    let late = resolver.resolve(&package("late", "1.0.0", PackageSource::Path));
+    assert_eq!(late, Some(dir.path().join("late").canonicalize().unwrap()));
+}
+
+// Property-based tests
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn prop_registry_resolution_matches_version(
+        major in 0u64..5,
+        minor in 0u64..5,
+        other in 0u64..5,
+    ) {
+        let home = tempfile::tempdir().unwrap();
+        let source = home.path().join(format!("registry/src/index/either-{major}.{minor}.0"));
+        write(&source.join("Cargo.toml"), &manifest("either"));
+
+        let resolver = SourceResolver::new("/workspace", home.path());
+        let version = format!("{major}.{other}.0");
+        let resolved = resolver.resolve(&package("either", &version, registry()));
+        prop_assert_eq!(resolved.is_some(), other == minor);
+    }
+}
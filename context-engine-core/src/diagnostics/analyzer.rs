@@ -0,0 +1,553 @@
+//! Analysis of unsaved content in overlays of the language servers.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, PublishDiagnostics,
+};
+use lsp_types::request::{DocumentDiagnosticRequest, Request};
+use lsp_types::{
+    Diagnostic, DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    DocumentDiagnosticParams, DocumentDiagnosticReport, DocumentDiagnosticReportResult,
+    PositionEncodingKind, PublishDiagnosticsParams, Range, TextDocumentContentChangeEvent,
+    TextDocumentIdentifier, TextDocumentItem, Uri, VersionedTextDocumentIdentifier,
+};
+use parking_lot::Mutex;
+use tokio::sync::{OwnedMutexGuard, mpsc};
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+use crate::diagnostics::{Analysis, DiagnosticInfo, DiagnosticMode, ServerReport};
+use crate::document::DocumentStore;
+use crate::error::ContextEngineError;
+use crate::lsp::{LanguageRegistry, LspClient, LspSupervisor, ServerStatus};
+use crate::types::LineIndex;
+
+/// How long a [`ContentAnalyzer`] waits for diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnalyzeConfig {
+    /// How long a server that does not support pull diagnostics must be
+    /// quiet after publishing diagnostics for the content before they count
+    /// as settled
+    pub quiescence: Duration,
+    /// The longest an analysis waits for the diagnostics of its servers
+    pub timeout: Duration,
+}
+
+impl Default for AnalyzeConfig {
+    fn default() -> Self {
+        Self {
+            quiescence: Duration::from_millis(500),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Validates unsaved content against the real workspace.
+///
+/// The content is opened as an in-memory overlay in the running language
+/// servers of its document: a `textDocument/didOpen` for a document that is
+/// not open, or a `textDocument/didChange` with the full content for one
+/// that is. Neither the files on disk nor the [`DocumentStore`] change.
+/// Once the diagnostics settled, the servers are brought back to the state
+/// of the store, with a `textDocument/didChange` to its text or a
+/// `textDocument/didClose`. The servers are restored even if the analysis
+/// is cancelled, i.e. its future dropped.
+///
+/// Diagnostics settle when
+///
+/// * a server that supports pull diagnostics answers a
+///   `textDocument/diagnostic` request, or
+/// * any other server did not publish diagnostics for the overlay for
+///   [`AnalyzeConfig::quiescence`].
+///
+/// Analyses of the same document wait for each other, while analyses of
+/// different documents run at the same time. The versions the servers see
+/// for a document only increase, across overlays, so that diagnostics
+/// published for an earlier overlay are never taken for those of a later
+/// one.
+pub struct ContentAnalyzer {
+    supervisor: Arc<LspSupervisor>,
+    registry: Arc<LanguageRegistry>,
+    documents: Arc<DocumentStore>,
+    config: AnalyzeConfig,
+    /// One lock per document that has an overlay
+    locks: Arc<Mutex<HashMap<Uri, Arc<tokio::sync::Mutex<()>>>>>,
+    /// The last version sent to the servers per document
+    versions: Versions,
+}
+
+impl ContentAnalyzer {
+    /// Creates an analyzer with the default configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `supervisor` - Runs the language servers that analyze the content
+    /// * `registry` - Routes the content to its language servers
+    /// * `documents` - The open documents, whose state the servers are restored
+    ///   to
+    pub fn new(
+        supervisor: Arc<LspSupervisor>,
+        registry: Arc<LanguageRegistry>,
+        documents: Arc<DocumentStore>,
+    ) -> Self {
+        Self {
+            supervisor,
+            registry,
+            documents,
+            config: AnalyzeConfig::default(),
+            locks: Arc::default(),
+            versions: Versions::default(),
+        }
+    }
+
+    /// Replaces the configuration of the analyzer.
+    pub fn with_config(mut self, config: AnalyzeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Returns the configuration of the analyzer.
+    pub fn config(&self) -> &AnalyzeConfig {
+        &self.config
+    }
+
+    /// Analyzes content as if it were the text of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The document the content is analyzed as; it need not exist
+    /// * `content` - The proposed text of the document
+    /// * `language_id` - The language of the content; if `None`, the language
+    ///   of the open document or the one the registry recognizes
+    ///
+    /// # Returns
+    ///
+    /// The diagnostics of all running servers of the language. A server
+    /// that did not settle within [`AnalyzeConfig::timeout`] contributes the
+    /// diagnostics it published last and is marked as not
+    /// [settled](ServerReport::settled).
+    ///
+    /// # Errors
+    ///
+    /// See [`ContentAnalyzer::overlay`].
+    pub async fn analyze(
+        &self,
+        uri: &Uri,
+        content: &str,
+        language_id: Option<&str>,
+    ) -> Result<Analysis, ContextEngineError> {
+        let mut overlay = self.overlay(uri, content, language_id).await?;
+        let deadline = Instant::now() + self.config.timeout;
+        let receivers = std::mem::take(&mut overlay.receivers);
+
+        let line_index = LineIndex::new(content);
+        let mut diagnostics = Vec::new();
+        let mut servers = Vec::new();
+        for ((server, client), receiver) in overlay.servers.iter().zip(receivers) {
+            let (report, items) = self
+                .collect(server, client, receiver, uri, overlay.version, deadline)
+                .await;
+            let encoding = client.capabilities().position_encoding();
+            diagnostics.extend(items.into_iter().map(|diagnostic| {
+                let mut info = DiagnosticInfo::from_lsp(server, diagnostic);
+                info.range = convert(
+                    &line_index,
+                    info.range,
+                    &encoding,
+                    self.documents.encoding(),
+                );
+                info
+            }));
+            servers.push(report);
+        }
+        let language_id = overlay.language_id.clone();
+        drop(overlay);
+
+        diagnostics.sort_by(|a, b| {
+            let key = |info: &DiagnosticInfo| (info.range.start.line, info.range.start.character);
+            key(a).cmp(&key(b)).then_with(|| a.server.cmp(&b.server))
+        });
+        Ok(Analysis {
+            uri: uri.clone(),
+            language_id,
+            diagnostics,
+            servers,
+        })
+    }
+
+    /// Opens content as an overlay of a document in the running servers of
+    /// its language, e.g. to request code actions for unsaved content.
+    ///
+    /// The servers see the content until the returned [`Overlay`] is
+    /// dropped. Overlays and analyses of the same document wait for each
+    /// other.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The document the content is opened as; it need not exist
+    /// * `content` - The text of the document in the servers
+    /// * `language_id` - The language of the content; if `None`, the language
+    ///   of the open document or the one the registry recognizes
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::Configuration`] if the language is
+    /// unknown or no language server of it is running.
+    pub async fn overlay(
+        &self,
+        uri: &Uri,
+        content: &str,
+        language_id: Option<&str>,
+    ) -> Result<Overlay, ContextEngineError> {
+        let language_id = self.language_id(uri, content, language_id)?;
+        let lock = Arc::clone(self.locks.lock().entry(uri.clone()).or_default());
+        let lock = DocumentLock {
+            locks: Arc::clone(&self.locks),
+            uri: uri.clone(),
+            guard: Some(lock.lock_owned().await),
+        };
+
+        let mut servers = Vec::new();
+        for server in self.registry.servers_for(&language_id) {
+            if self.supervisor.status(&server.name) != Some(ServerStatus::Running) {
+                continue;
+            }
+            match self.supervisor.client(&server.name).await {
+                Ok(client) => servers.push((server.name.clone(), client)),
+                Err(error) => debug!(server = %server.name, %error, "Skipping unavailable server"),
+            }
+        }
+        if servers.is_empty() {
+            return Err(ContextEngineError::Configuration {
+                key: None,
+                message: format!("No language server for {language_id} is running"),
+            });
+        }
+        Ok(Overlay::open(
+            Arc::clone(&self.documents),
+            self.versions.clone(),
+            uri,
+            language_id,
+            content,
+            servers,
+            lock,
+        ))
+    }
+
+    fn language_id(
+        &self,
+        uri: &Uri,
+        content: &str,
+        language_id: Option<&str>,
+    ) -> Result<String, ContextEngineError> {
+        if let Some(language_id) = language_id {
+            return Ok(language_id.to_string());
+        }
+        if let Some(document) = self.documents.get(uri) {
+            return Ok(document.language_id().to_string());
+        }
+        self.registry
+            .language_id(uri, Some(content))
+            .map(str::to_string)
+            .ok_or_else(|| ContextEngineError::Configuration {
+                key: None,
+                message: format!("No language is configured for {}", uri.as_str()),
+            })
+    }
+
+    /// Waits for the diagnostics of one server, pulling them if the server
+    /// supports it.
+    async fn collect(
+        &self,
+        server: &str,
+        client: &LspClient,
+        receiver: mpsc::UnboundedReceiver<PublishDiagnosticsParams>,
+        uri: &Uri,
+        version: i32,
+        deadline: Instant,
+    ) -> (ServerReport, Vec<Diagnostic>) {
+        let report = |mode, settled| ServerReport {
+            server: server.to_string(),
+            mode,
+            settled,
+        };
+
+        if client.supports(DocumentDiagnosticRequest::METHOD) {
+            let params = DocumentDiagnosticParams {
+                text_document: TextDocumentIdentifier::new(uri.clone()),
+                identifier: None,
+                previous_result_id: None,
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            };
+            let request = self
+                .supervisor
+                .request::<DocumentDiagnosticRequest>(server, params);
+            match tokio::time::timeout_at(deadline, request).await {
+                Ok(Ok(result)) => return (report(DiagnosticMode::Pull, true), pulled(result)),
+                Ok(Err(error)) => {
+                    debug!(server, %error, "Pulling diagnostics failed, waiting for published ones");
+                }
+                Err(_) => return (report(DiagnosticMode::Pull, false), Vec::new()),
+            }
+        }
+
+        let (diagnostics, settled) =
+            quiesce(receiver, uri, version, self.config.quiescence, deadline).await;
+        (report(DiagnosticMode::Push, settled), diagnostics)
+    }
+}
+
+/// Content opened as a document in language servers, created by
+/// [`ContentAnalyzer::overlay`].
+///
+/// Dropping the overlay restores the servers to the state of the document
+/// store: the document gets the text of the store, or is closed if the
+/// store does not have it open.
+pub struct Overlay {
+    documents: Arc<DocumentStore>,
+    versions: Versions,
+    uri: Uri,
+    language_id: String,
+    content: String,
+    servers: Vec<(String, Arc<LspClient>)>,
+    /// The diagnostics the servers publish, subscribed before the overlay
+    /// opened so that none are missed
+    receivers: Vec<mpsc::UnboundedReceiver<PublishDiagnosticsParams>>,
+    /// The version of the document in the servers while the overlay is open
+    version: i32,
+    /// Released after the servers are restored
+    _lock: DocumentLock,
+}
+
+impl Overlay {
+    fn open(
+        documents: Arc<DocumentStore>,
+        versions: Versions,
+        uri: &Uri,
+        language_id: String,
+        content: &str,
+        servers: Vec<(String, Arc<LspClient>)>,
+        lock: DocumentLock,
+    ) -> Self {
+        let receivers = servers
+            .iter()
+            .map(|(_, client)| client.subscribe::<PublishDiagnostics>())
+            .collect();
+        let open = documents.get(uri);
+        let version = versions.next(uri, open.as_ref().map_or(0, |document| document.version()));
+        for (server, client) in &servers {
+            let sent = match &open {
+                Some(_) => client.notify::<DidChangeTextDocument>(change(uri, version, content)),
+                None => client.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+                    text_document: TextDocumentItem::new(
+                        uri.clone(),
+                        language_id.clone(),
+                        version,
+                        content.to_string(),
+                    ),
+                }),
+            };
+            if let Err(error) = sent {
+                warn!(server = %server, %error, "Failed to open overlay");
+            }
+        }
+        Self {
+            documents,
+            versions,
+            uri: uri.clone(),
+            language_id,
+            content: content.to_string(),
+            servers,
+            receivers,
+            version,
+            _lock: lock,
+        }
+    }
+
+    /// Returns the document the content is opened as.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Returns the language of the content.
+    pub fn language_id(&self) -> &str {
+        &self.language_id
+    }
+
+    /// Returns the content the servers see.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Returns the version of the document in the servers while the overlay
+    /// is open.
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// Returns the names and clients of the servers the content is opened
+    /// in.
+    pub fn servers(&self) -> &[(String, Arc<LspClient>)] {
+        &self.servers
+    }
+
+    /// Returns the client of a server the content is opened in.
+    pub fn client(&self, server: &str) -> Option<&Arc<LspClient>> {
+        self.servers
+            .iter()
+            .find(|(name, _)| name == server)
+            .map(|(_, client)| client)
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        // The store may have changed while the overlay was open
+        let document = self.documents.get(&self.uri);
+        let version = document
+            .as_ref()
+            .map(|document| self.versions.next(&self.uri, document.version()));
+        for (server, client) in &self.servers {
+            let sent =
+                match (&document, version) {
+                    (Some(document), Some(version)) => client.notify::<DidChangeTextDocument>(
+                        change(&self.uri, version, document.text()),
+                    ),
+                    _ => client.notify::<DidCloseTextDocument>(DidCloseTextDocumentParams {
+                        text_document: TextDocumentIdentifier::new(self.uri.clone()),
+                    }),
+                };
+            if let Err(error) = sent {
+                warn!(server = %server, %error, "Failed to restore document after overlay");
+            }
+        }
+    }
+}
+
+/// The last version of every document sent to the servers for overlays.
+#[derive(Debug, Clone, Default)]
+struct Versions(Arc<Mutex<HashMap<Uri, i32>>>);
+
+impl Versions {
+    /// Returns the next version of a document: higher than any version sent
+    /// before and than the version of the document in the store.
+    fn next(&self, uri: &Uri, store_version: i32) -> i32 {
+        let mut versions = self.0.lock();
+        let version = versions.entry(uri.clone()).or_insert(0);
+        *version = (*version).max(store_version).saturating_add(1);
+        *version
+    }
+}
+
+/// The lock of a document held while content is opened as it.
+struct DocumentLock {
+    locks: Arc<Mutex<HashMap<Uri, Arc<tokio::sync::Mutex<()>>>>>,
+    uri: Uri,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl Drop for DocumentLock {
+    fn drop(&mut self) {
+        let Some(guard) = self.guard.take() else {
+            return;
+        };
+        let lock = Arc::clone(OwnedMutexGuard::mutex(&guard));
+        drop(guard);
+        // Forget the lock once no other overlay waits for it
+        let mut locks = self.locks.lock();
+        if Arc::strong_count(&lock) == 2 {
+            locks.remove(&self.uri);
+        }
+    }
+}
+
+/// A `textDocument/didChange` that replaces the whole text of a document.
+fn change(uri: &Uri, version: i32, text: &str) -> DidChangeTextDocumentParams {
+    DidChangeTextDocumentParams {
+        text_document: VersionedTextDocumentIdentifier::new(uri.clone(), version),
+        content_changes: vec![TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: text.to_string(),
+        }],
+    }
+}
+
+/// Returns the diagnostics of a pull report.
+fn pulled(result: DocumentDiagnosticReportResult) -> Vec<Diagnostic> {
+    match result {
+        DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(report)) => {
+            report.full_document_diagnostic_report.items
+        }
+        // Without a previous result ID, servers answer with full reports
+        _ => Vec::new(),
+    }
+}
+
+/// Waits until a server stopped publishing diagnostics for a version of a
+/// document.
+///
+/// # Returns
+///
+/// The diagnostics published last, and whether the server was quiet for
+/// `quiescence` before the deadline.
+async fn quiesce(
+    mut receiver: mpsc::UnboundedReceiver<PublishDiagnosticsParams>,
+    uri: &Uri,
+    version: i32,
+    quiescence: Duration,
+    deadline: Instant,
+) -> (Vec<Diagnostic>, bool) {
+    let mut latest: Option<(Vec<Diagnostic>, Instant)> = None;
+    loop {
+        let wait = match &latest {
+            Some((_, published)) => (*published + quiescence).min(deadline),
+            None => deadline,
+        };
+        match tokio::time::timeout_at(wait, receiver.recv()).await {
+            Ok(Some(params)) => {
+                // Unversioned diagnostics are taken to be of the overlay
+                if params.uri == *uri && params.version.map_or(true, |v| v == version) {
+                    latest = Some((params.diagnostics, Instant::now()));
+                }
+            }
+            Ok(None) => {
+                let diagnostics = latest.map(|(diagnostics, _)| diagnostics);
+                return (diagnostics.unwrap_or_default(), false);
+            }
+            Err(_) => {
+                let settled = latest.is_some() && wait < deadline;
+                let diagnostics = latest.map(|(diagnostics, _)| diagnostics);
+                return (diagnostics.unwrap_or_default(), settled);
+            }
+        }
+    }
+}
+
+/// Converts a range of the content between position encodings, keeping it
+/// if it is out of bounds.
+fn convert(
+    index: &LineIndex,
+    range: Range,
+    from: &PositionEncodingKind,
+    to: &PositionEncodingKind,
+) -> Range {
+    if from == to {
+        return range;
+    }
+    match (
+        index.convert(range.start, from, to),
+        index.convert(range.end, from, to),
+    ) {
+        (Ok(start), Ok(end)) => Range::new(start, end),
+        _ => range,
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/analyzer.rs"]
+mod tests;
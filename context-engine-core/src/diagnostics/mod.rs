@@ -0,0 +1,50 @@
+//! Diagnostics of unsaved content.
+//!
+//! Before writing code to disk, an agent wants to know whether it compiles
+//! against the real workspace. The [`ContentAnalyzer`] opens proposed
+//! content as an in-memory overlay in the language servers of its document,
+//! waits until their diagnostics settle, using pull diagnostics where a
+//! server supports them and quiescence of `textDocument/publishDiagnostics`
+//! otherwise, and restores the previous state of the document afterwards.
+//! The diagnostics come back as an [`Analysis`]:
+//!
+//! ```json
+//! {
+//!   "uri": "file:///app/src/user.rs",
+//!   "language_id": "rust",
+//!   "diagnostics": [
+//!     {
+//!       "range": { "start": { "line": 4, "character": 8 }, "end": { "line": 4, "character": 13 } },
+//!       "severity": "error",
+//!       "code": "E0308",
+//!       "source": "rustc",
+//!       "message": "mismatched types",
+//!       "server": "rust-analyzer"
+//!     }
+//!   ],
+//!   "servers": [{ "server": "rust-analyzer", "mode": "pull", "settled": true }]
+//! }
+//! ```
+//!
+//! Other requests about unsaved content, e.g. for its code actions, are sent
+//! while an [`Overlay`] of it is open.
+//!
+//! ## Structs
+//!
+//! * [`ContentAnalyzer`] - Analyzes unsaved content in overlays.
+//! * [`Overlay`] - Content opened as a document in language servers.
+//! * [`AnalyzeConfig`] - How long an analyzer waits for diagnostics.
+//! * [`Analysis`] - The diagnostics of analyzed content.
+//! * [`DiagnosticInfo`] - A problem found in the content.
+//! * [`ServerReport`] - How a language server took part in an analysis.
+//!
+//! ## Enums
+//!
+//! * [`Severity`] - How severe a diagnostic is.
+//! * [`DiagnosticMode`] - How a server delivered its diagnostics.
+
+mod analyzer;
+mod report;
+
+pub use analyzer::{AnalyzeConfig, ContentAnalyzer, Overlay};
+pub use report::{Analysis, DiagnosticInfo, DiagnosticMode, ServerReport, Severity};
@@ -0,0 +1,171 @@
+//! Structured diagnostics of analyzed content.
+
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range, Uri};
+use serde::{Deserialize, Serialize};
+
+/// How severe a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The code does not compile or run
+    Error,
+    /// The code is likely wrong
+    Warning,
+    /// Something worth knowing about the code
+    Information,
+    /// A suggestion, e.g. a simpler way to write the code
+    Hint,
+}
+
+impl Severity {
+    /// Converts an LSP severity; unknown severities have no equivalent.
+    pub fn from_lsp(severity: DiagnosticSeverity) -> Option<Self> {
+        match severity {
+            DiagnosticSeverity::ERROR => Some(Severity::Error),
+            DiagnosticSeverity::WARNING => Some(Severity::Warning),
+            DiagnosticSeverity::INFORMATION => Some(Severity::Information),
+            DiagnosticSeverity::HINT => Some(Severity::Hint),
+            _ => None,
+        }
+    }
+
+    /// Converts the severity to its LSP equivalent.
+    pub fn to_lsp(self) -> DiagnosticSeverity {
+        match self {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Information => DiagnosticSeverity::INFORMATION,
+            Severity::Hint => DiagnosticSeverity::HINT,
+        }
+    }
+}
+
+/// A problem a language server found in the analyzed content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticInfo {
+    /// The range of the problem, in the position encoding of the document
+    /// store
+    pub range: Range,
+    /// The severity; `None` if the server left it to the client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    /// The code of the problem, e.g. `E0308`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// The tool that found the problem, e.g. `rustc` or `clippy`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// The description of the problem
+    pub message: String,
+    /// The name of the language server that reported the problem
+    pub server: String,
+}
+
+impl DiagnosticInfo {
+    /// Converts a diagnostic of a language server, keeping its range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::diagnostics::{DiagnosticInfo, Severity};
+    /// use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range};
+    ///
+    /// let diagnostic = Diagnostic {
+    ///     range: Range::new(Position::new(1, 4), Position::new(1, 9)),
+    ///     severity: Some(DiagnosticSeverity::ERROR),
+    ///     code: Some(NumberOrString::String("E0308".to_string())),
+    ///     source: Some("rustc".to_string()),
+    ///     message: "mismatched types".to_string(),
+    ///     ..Diagnostic::default()
+    /// };
+    /// let info = DiagnosticInfo::from_lsp("rust-analyzer", diagnostic);
+    /// assert_eq!(info.severity, Some(Severity::Error));
+    /// assert_eq!(info.code.as_deref(), Some("E0308"));
+    /// ```
+    pub fn from_lsp(server: &str, diagnostic: Diagnostic) -> Self {
+        Self {
+            range: diagnostic.range,
+            severity: diagnostic.severity.and_then(Severity::from_lsp),
+            code: diagnostic.code.map(|code| match code {
+                NumberOrString::Number(number) => number.to_string(),
+                NumberOrString::String(string) => string,
+            }),
+            source: diagnostic.source,
+            message: diagnostic.message,
+            server: server.to_string(),
+        }
+    }
+
+    /// Converts the diagnostic back to an LSP diagnostic, e.g. to ask a
+    /// server for code actions that fix it. Numeric codes become numbers
+    /// again.
+    pub fn to_lsp(&self) -> Diagnostic {
+        Diagnostic {
+            range: self.range,
+            severity: self.severity.map(Severity::to_lsp),
+            code: self.code.as_ref().map(|code| match code.parse() {
+                Ok(number) => NumberOrString::Number(number),
+                Err(_) => NumberOrString::String(code.clone()),
+            }),
+            source: self.source.clone(),
+            message: self.message.clone(),
+            ..Diagnostic::default()
+        }
+    }
+}
+
+/// How a language server delivered its diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticMode {
+    /// Answered a `textDocument/diagnostic` request
+    Pull,
+    /// Sent `textDocument/publishDiagnostics` notifications
+    Push,
+}
+
+/// How a language server took part in an analysis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerReport {
+    /// The name of the server
+    pub server: String,
+    /// How the server delivered its diagnostics
+    pub mode: DiagnosticMode,
+    /// False if the server was still publishing diagnostics, or had not
+    /// published any, when the analysis timed out
+    pub settled: bool,
+}
+
+/// The diagnostics of content analyzed by the language servers of its
+/// document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Analysis {
+    /// The document the content was analyzed as
+    pub uri: Uri,
+    /// The language of the content
+    pub language_id: String,
+    /// The diagnostics of all servers, ordered by their start
+    pub diagnostics: Vec<DiagnosticInfo>,
+    /// The servers that analyzed the content
+    pub servers: Vec<ServerReport>,
+}
+
+impl Analysis {
+    /// Returns the number of diagnostics with a severity.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Some(severity))
+            .count()
+    }
+
+    /// Returns true if every server settled, i.e. the diagnostics are
+    /// complete.
+    pub fn is_settled(&self) -> bool {
+        self.servers.iter().all(|server| server.settled)
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/report.rs"]
+mod tests;
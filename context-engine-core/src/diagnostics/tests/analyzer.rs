@@ -0,0 +1,402 @@
+#![allow(clippy::unwrap_used)]
+
+use std::path::Path;
+use std::str::FromStr;
+
+use lsp_types::Position;
+use proptest::prelude::*;
+use serde_json::{Value, json};
+
+use super::*;
+use crate::JsonRpcError;
+use crate::diagnostics::Severity;
+use crate::lsp::testing::{self, FakeLauncher, FakeLog, FakeServer, FakeSession};
+
+/// A server that reports an error on every line containing `bad`.
+#[derive(Default)]
+struct FakeDiagnostics {
+    /// Whether the server answers pull diagnostic requests instead of
+    /// publishing diagnostics
+    pull: bool,
+    /// Whether the server never reports diagnostics
+    silent: bool,
+}
+
+impl FakeServer for FakeDiagnostics {
+    fn initialize(&self) -> Value {
+        let capabilities = match self.pull {
+            true => json!({ "diagnosticProvider": {
+                "interFileDependencies": true,
+                "workspaceDiagnostics": false,
+            } }),
+            false => json!({}),
+        };
+        json!({ "capabilities": capabilities })
+    }
+
+    async fn request(
+        &self,
+        session: &FakeSession,
+        method: &str,
+        params: Value,
+    ) -> Option<Result<Value, JsonRpcError>> {
+        if method != "textDocument/diagnostic" {
+            return Some(Ok(Value::Null));
+        }
+        let params: DocumentDiagnosticParams = serde_json::from_value(params).unwrap();
+        let text = session.text(params.text_document.uri.as_str()).unwrap();
+        Some(Ok(json!({ "kind": "full", "items": diagnose(&text) })))
+    }
+
+    async fn notify(&self, session: &FakeSession, method: &str, params: Value) {
+        if !matches!(method, "textDocument/didOpen" | "textDocument/didChange")
+            || self.pull
+            || self.silent
+        {
+            return;
+        }
+        let uri = params.pointer("/textDocument/uri").and_then(Value::as_str);
+        let uri = Uri::from_str(uri.unwrap()).unwrap();
+        let document = session.document(uri.as_str()).unwrap();
+        // A quick syntactic pass first, the full analysis later
+        publish(session, &uri, document.version, Vec::new());
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        publish(session, &uri, document.version, diagnose(&document.text));
+    }
+}
+
+fn file_name(uri: &Uri) -> String {
+    uri.as_str().rsplit('/').next().unwrap().to_string()
+}
+
+/// The diagnostics of the fake server for a text.
+fn diagnose(text: &str) -> Vec<Diagnostic> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| line.contains("bad"))
+        .map(|(line, text)| Diagnostic {
+            range: Range::new(
+                Position::new(line as u32, 0),
+                Position::new(line as u32, text.len() as u32),
+            ),
+            severity: Some(lsp_types::DiagnosticSeverity::ERROR),
+            code: Some(lsp_types::NumberOrString::String("E1".to_string())),
+            source: Some("fake".to_string()),
+            message: "bad code".to_string(),
+            ..Diagnostic::default()
+        })
+        .collect()
+}
+
+fn publish(session: &FakeSession, uri: &Uri, version: i32, diagnostics: Vec<Diagnostic>) {
+    let params = PublishDiagnosticsParams::new(uri.clone(), diagnostics, Some(version));
+    session.notify(
+        "textDocument/publishDiagnostics",
+        serde_json::to_value(params).unwrap(),
+    );
+}
+
+/// The document notifications received by the server, as `<kind> <file
+/// name> [<version> <text>]`.
+fn document_events(log: &FakeLog) -> Vec<String> {
+    log.messages()
+        .into_iter()
+        .filter_map(|message| match message.method.as_str() {
+            "textDocument/didOpen" => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(message.params).unwrap();
+                let document = params.text_document;
+                Some(format!(
+                    "open {} {} {:?}",
+                    file_name(&document.uri),
+                    document.version,
+                    document.text
+                ))
+            }
+            "textDocument/didChange" => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(message.params).unwrap();
+                let document = params.text_document;
+                let text = params.content_changes.into_iter().next().unwrap().text;
+                Some(format!(
+                    "change {} {} {text:?}",
+                    file_name(&document.uri),
+                    document.version
+                ))
+            }
+            "textDocument/didClose" => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(message.params).unwrap();
+                Some(format!("close {}", file_name(&params.text_document.uri)))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn uri(path: &str) -> Uri {
+    Uri::from_str(&format!("file:///app/{path}")).unwrap()
+}
+
+fn config(timeout_ms: u64) -> AnalyzeConfig {
+    AnalyzeConfig {
+        quiescence: Duration::from_millis(150),
+        timeout: Duration::from_millis(timeout_ms),
+    }
+}
+
+/// Starts rust-analyzer on a fake server and returns an analyzer over it.
+async fn start(
+    launcher: FakeLauncher<FakeDiagnostics>,
+    documents: Arc<DocumentStore>,
+) -> ContentAnalyzer {
+    let registry = Arc::new(LanguageRegistry::with_defaults());
+    let server = registry.server("rust-analyzer").unwrap().clone();
+    let supervisor = testing::start(Path::new("/app"), Arc::clone(&documents), launcher, server)
+        .await
+        .unwrap();
+    ContentAnalyzer::new(supervisor, registry, documents).with_config(config(2000))
+}
+
+/// Waits until the server received a number of notifications.
+async fn events(launcher: &FakeLauncher<FakeDiagnostics>, count: usize) -> Vec<String> {
+    testing::wait_until(|| document_events(launcher.log()).len() >= count).await;
+    document_events(launcher.log())
+}
+
+fn lines(analysis: &Analysis) -> Vec<u32> {
+    analysis
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.range.start.line)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_unopened_document_is_opened_and_closed() {
+    let launcher = FakeLauncher::new(FakeDiagnostics::default());
+    let documents = Arc::new(DocumentStore::default());
+    let analyzer = start(launcher.clone(), Arc::clone(&documents)).await;
+
+    let analysis = analyzer
+        .analyze(&uri("src/new.rs"), "fn f() {}\nbad\n", None)
+        .await
+        .unwrap();
+    assert_eq!(analysis.language_id, "rust");
+    assert_eq!(lines(&analysis), [1]);
+    let diagnostic = analysis.diagnostics.first().unwrap();
+    assert_eq!(diagnostic.severity, Some(Severity::Error));
+    assert_eq!(diagnostic.code.as_deref(), Some("E1"));
+    assert_eq!(diagnostic.source.as_deref(), Some("fake"));
+    assert_eq!(diagnostic.server, "rust-analyzer");
+    assert_eq!(
+        analysis.servers,
+        [ServerReport {
+            server: "rust-analyzer".to_string(),
+            mode: DiagnosticMode::Push,
+            settled: true,
+        }]
+    );
+
+    assert_eq!(
+        events(&launcher, 2).await,
+        [r#"open new.rs 1 "fn f() {}\nbad\n""#, "close new.rs"]
+    );
+    assert!(documents.is_empty());
+}
+
+#[tokio::test]
+async fn test_open_document_is_restored() {
+    let launcher = FakeLauncher::new(FakeDiagnostics::default());
+    let documents = Arc::new(DocumentStore::default());
+    let item = TextDocumentItem::new(uri("src/lib.rs"), "rust".to_string(), 3, "ok".to_string());
+    documents.open(item).unwrap();
+    let analyzer = start(launcher.clone(), Arc::clone(&documents)).await;
+
+    let analysis = analyzer
+        .analyze(&uri("src/lib.rs"), "bad", None)
+        .await
+        .unwrap();
+    assert_eq!(lines(&analysis), [0]);
+    assert_eq!(
+        events(&launcher, 3).await,
+        [
+            r#"open lib.rs 3 "ok""#,
+            r#"change lib.rs 4 "bad""#,
+            r#"change lib.rs 5 "ok""#,
+        ]
+    );
+    analyzer
+        .analyze(&uri("src/lib.rs"), "bad", None)
+        .await
+        .unwrap();
+    assert_eq!(
+        events(&launcher, 5).await.get(3..),
+        Some(
+            &[
+                r#"change lib.rs 6 "bad""#.to_string(),
+                r#"change lib.rs 7 "ok""#.to_string()
+            ][..]
+        )
+    );
+    let document = documents.get(&uri("src/lib.rs")).unwrap();
+    assert_eq!((document.version(), document.text()), (3, "ok"));
+}
+
+#[tokio::test]
+async fn test_pull_diagnostics() {
+    let launcher = FakeLauncher::new(FakeDiagnostics {
+        pull: true,
+        ..FakeDiagnostics::default()
+    });
+    let analyzer = start(launcher, Arc::new(DocumentStore::default())).await;
+
+    let analysis = analyzer
+        .analyze(&uri("src/new.rs"), "bad\nok\nbad", Some("rust"))
+        .await
+        .unwrap();
+    assert_eq!(lines(&analysis), [0, 2]);
+    let server = analysis.servers.first().unwrap();
+    assert_eq!((server.mode, server.settled), (DiagnosticMode::Pull, true));
+}
+
+#[tokio::test]
+async fn test_concurrent_analyses_of_different_documents() {
+    let launcher = FakeLauncher::new(FakeDiagnostics::default());
+    let analyzer = start(launcher.clone(), Arc::new(DocumentStore::default())).await;
+
+    let (a, b) = (uri("src/a.rs"), uri("src/b.rs"));
+    let (a, b) = tokio::join!(
+        analyzer.analyze(&a, "bad", None),
+        analyzer.analyze(&b, "ok\nok\nbad", None),
+    );
+    assert_eq!(lines(&a.unwrap()), [0]);
+    assert_eq!(lines(&b.unwrap()), [2]);
+
+    let events = events(&launcher, 4).await;
+    assert!(events.contains(&"close a.rs".to_string()));
+    assert!(events.contains(&"close b.rs".to_string()));
+    assert!(analyzer.locks.lock().is_empty());
+}
+
+#[tokio::test]
+async fn test_silent_server_does_not_settle() {
+    let launcher = FakeLauncher::new(FakeDiagnostics {
+        silent: true,
+        ..FakeDiagnostics::default()
+    });
+    let analyzer = start(launcher, Arc::new(DocumentStore::default()))
+        .await
+        .with_config(config(300));
+
+    let analysis = analyzer
+        .analyze(&uri("src/new.rs"), "bad", None)
+        .await
+        .unwrap();
+    assert!(analysis.diagnostics.is_empty());
+    assert!(!analysis.is_settled());
+}
+
+#[tokio::test]
+async fn test_cancelled_analysis_restores_document() {
+    let launcher = FakeLauncher::new(FakeDiagnostics {
+        silent: true,
+        ..FakeDiagnostics::default()
+    });
+    let analyzer = start(launcher.clone(), Arc::new(DocumentStore::default())).await;
+
+    let new = uri("src/new.rs");
+    let analysis = analyzer.analyze(&new, "bad", None);
+    let cancelled = tokio::time::timeout(Duration::from_millis(100), analysis).await;
+    assert!(cancelled.is_err());
+
+    assert_eq!(
+        events(&launcher, 2).await,
+        [r#"open new.rs 1 "bad""#, "close new.rs"]
+    );
+}
+
+#[tokio::test]
+async fn test_overlays_of_a_document_wait_for_each_other() {
+    let launcher = FakeLauncher::new(FakeDiagnostics {
+        silent: true,
+        ..FakeDiagnostics::default()
+    });
+    let analyzer = start(launcher.clone(), Arc::new(DocumentStore::default())).await;
+    let new = uri("src/new.rs");
+
+    let first = analyzer.overlay(&new, "first", None).await.unwrap();
+    assert_eq!(first.language_id(), "rust");
+    assert_eq!(first.version(), 1);
+    assert!(first.client("rust-analyzer").is_some());
+    let second = analyzer.overlay(&new, "second", None);
+    tokio::pin!(second);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(50), &mut second)
+            .await
+            .is_err()
+    );
+
+    drop(first);
+    let second = second.await.unwrap();
+    assert_eq!(second.content(), "second");
+    drop(second);
+    assert_eq!(
+        events(&launcher, 4).await,
+        [
+            r#"open new.rs 1 "first""#,
+            "close new.rs",
+            // Versions keep increasing, so late diagnostics of the first
+            // overlay cannot be taken for the second one's
+            r#"open new.rs 2 "second""#,
+            "close new.rs",
+        ]
+    );
+    assert!(analyzer.locks.lock().is_empty());
+}
+
+#[tokio::test]
+async fn test_without_running_server() {
+    let analyzer = ContentAnalyzer::new(
+        Arc::new(LspSupervisor::new(
+            "/app",
+            Arc::new(DocumentStore::default()),
+        )),
+        Arc::new(LanguageRegistry::with_defaults()),
+        Arc::new(DocumentStore::default()),
+    );
+    let error = analyzer
+        .analyze(&uri("src/lib.rs"), "fn f() {}", None)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ContextEngineError::Configuration { .. }));
+
+    let error = analyzer
+        .analyze(&uri("notes.unknown"), "text", None)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("No language is configured"));
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_ascii_ranges_are_kept_across_encodings(
+        text in "[a-z \n]{0,40}",
+        offsets in (0usize..40, 0usize..40),
+    ) {
+        let index = LineIndex::new(&text);
+        let (start, end) = (offsets.0.min(text.len()), offsets.1.min(text.len()));
+        let range = Range::new(
+            index.position(start, &PositionEncodingKind::UTF16).unwrap(),
+            index.position(end, &PositionEncodingKind::UTF16).unwrap(),
+        );
+        let converted = convert(
+            &index,
+            range,
+            &PositionEncodingKind::UTF16,
+            &PositionEncodingKind::UTF8,
+        );
+        prop_assert_eq!(converted, range);
+    }
+}
@@ -0,0 +1,115 @@
+#![allow(clippy::unwrap_used)]
+
+use lsp_types::Position;
+use proptest::prelude::*;
+use serde_json::json;
+
+use super::*;
+
+fn info(severity: Option<Severity>, line: u32) -> DiagnosticInfo {
+    DiagnosticInfo {
+        range: Range::new(Position::new(line, 0), Position::new(line, 1)),
+        severity,
+        code: None,
+        source: None,
+        message: "problem".to_string(),
+        server: "rust-analyzer".to_string(),
+    }
+}
+
+#[test]
+fn test_numeric_code_becomes_string() {
+    let diagnostic = Diagnostic {
+        code: Some(NumberOrString::Number(2304)),
+        message: "Cannot find name".to_string(),
+        ..Diagnostic::default()
+    };
+    let info = DiagnosticInfo::from_lsp("tsserver", diagnostic);
+    assert_eq!(info.code.as_deref(), Some("2304"));
+    assert_eq!(info.severity, None);
+    assert_eq!(info.server, "tsserver");
+}
+
+#[test]
+fn test_conversion_back_to_lsp() {
+    let diagnostic = Diagnostic {
+        range: Range::new(Position::new(3, 1), Position::new(3, 8)),
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: Some(NumberOrString::Number(2304)),
+        source: Some("ts".to_string()),
+        message: "Cannot find name".to_string(),
+        ..Diagnostic::default()
+    };
+    let info = DiagnosticInfo::from_lsp("tsserver", diagnostic.clone());
+    assert_eq!(info.to_lsp(), diagnostic);
+
+    let mut named = info;
+    named.code = Some("E0308".to_string());
+    assert_eq!(
+        named.to_lsp().code,
+        Some(NumberOrString::String("E0308".to_string()))
+    );
+}
+
+#[test]
+fn test_serialization_skips_missing_fields() {
+    let value = serde_json::to_value(info(Some(Severity::Warning), 2)).unwrap();
+    assert_eq!(
+        value,
+        json!({
+            "range": {
+                "start": { "line": 2, "character": 0 },
+                "end": { "line": 2, "character": 1 },
+            },
+            "severity": "warning",
+            "message": "problem",
+            "server": "rust-analyzer",
+        })
+    );
+}
+
+#[test]
+fn test_counts_and_settlement() {
+    let mut analysis = Analysis {
+        uri: "file:///app/src/lib.rs".parse().unwrap(),
+        language_id: "rust".to_string(),
+        diagnostics: vec![
+            info(Some(Severity::Error), 0),
+            info(Some(Severity::Error), 1),
+            info(Some(Severity::Hint), 2),
+            info(None, 3),
+        ],
+        servers: vec![ServerReport {
+            server: "rust-analyzer".to_string(),
+            mode: DiagnosticMode::Push,
+            settled: true,
+        }],
+    };
+    assert_eq!(analysis.count(Severity::Error), 2);
+    assert_eq!(analysis.count(Severity::Warning), 0);
+    assert!(analysis.is_settled());
+
+    analysis.servers.push(ServerReport {
+        server: "clippy".to_string(),
+        mode: DiagnosticMode::Pull,
+        settled: false,
+    });
+    assert!(!analysis.is_settled());
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_known_severities_convert(value in 1..=4i32) {
+        let severity = serde_json::from_value::<DiagnosticSeverity>(json!(value)).unwrap();
+        let converted = Severity::from_lsp(severity).unwrap();
+        let expected = [
+            Severity::Error,
+            Severity::Warning,
+            Severity::Information,
+            Severity::Hint,
+        ];
+        prop_assert_eq!(Some(&converted), expected.get(value as usize - 1));
+        prop_assert_eq!(converted.to_lsp(), severity);
+    }
+}
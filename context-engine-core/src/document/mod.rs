@@ -0,0 +1,17 @@
+//! In-memory representation of document contents.
+//!
+//! The Context Engine analyzes code before it is saved to disk, so it keeps
+//! its own copy of the text of every open document. This module provides the
+//! versioned [`TextDocument`] snapshots and the [`DocumentStore`] that applies
+//! LSP `textDocument/didChange` content changes to them.
+//!
+//! ## Structs
+//!
+//! * [`DocumentStore`] - Thread-safe store of the open documents.
+//! * [`TextDocument`] - Immutable snapshot of a document at one version.
+
+mod store;
+mod text_document;
+
+pub use store::DocumentStore;
+pub use text_document::TextDocument;
@@ -0,0 +1,207 @@
+//! Store of the documents that are open in the Context Engine.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use lsp_types::{
+    DidChangeTextDocumentParams, PositionEncodingKind, TextDocumentContentChangeEvent,
+    TextDocumentItem, Uri,
+};
+use parking_lot::RwLock;
+
+use crate::document::TextDocument;
+use crate::error::ContextEngineError;
+
+/// In-memory store of versioned text for open documents.
+///
+/// The store follows the LSP document synchronization model: documents are
+/// opened with their full text, changed with full or incremental content
+/// changes whose versions strictly increase, and closed. Reads return
+/// [`Arc`]-shared [`TextDocument`] snapshots, so readers never block writers
+/// and keep a consistent view of the text while edits are applied.
+///
+/// The store can be shared between threads.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::document::DocumentStore;
+/// use context_engine_core::types::{Position, PositionEncodingKind, Range, Uri};
+/// use lsp_types::{TextDocumentContentChangeEvent, TextDocumentItem};
+/// use std::str::FromStr;
+///
+/// let store = DocumentStore::new(PositionEncodingKind::UTF16);
+/// let uri = Uri::from_str("file:///src/lib.rs").unwrap();
+///
+/// store
+///     .open(TextDocumentItem::new(uri.clone(), "rust".into(), 1, "// 🦀\nfn a() {}".into()))
+///     .unwrap();
+/// let before = store.get(&uri).unwrap();
+///
+/// // The emoji is two UTF-16 code units wide
+/// let change = TextDocumentContentChangeEvent {
+///     range: Some(Range::new(Position::new(0, 3), Position::new(0, 5))),
+///     range_length: None,
+///     text: "crab".to_string(),
+/// };
+/// let after = store.apply_changes(&uri, 2, &[change]).unwrap();
+///
+/// assert_eq!(after.text(), "// crab\nfn a() {}");
+/// assert_eq!(before.text(), "// 🦀\nfn a() {}");
+/// ```
+#[derive(Debug)]
+pub struct DocumentStore {
+    encoding: PositionEncodingKind,
+    documents: RwLock<HashMap<Uri, Arc<TextDocument>>>,
+}
+
+impl Default for DocumentStore {
+    fn default() -> Self {
+        Self::new(PositionEncodingKind::UTF16)
+    }
+}
+
+impl DocumentStore {
+    /// Creates an empty store whose change ranges are counted in the given
+    /// encoding.
+    pub fn new(encoding: PositionEncodingKind) -> Self {
+        Self {
+            encoding,
+            documents: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the encoding in which change ranges are counted.
+    pub fn encoding(&self) -> &PositionEncodingKind {
+        &self.encoding
+    }
+
+    /// Opens a document.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::DocumentAlreadyOpen`] if the document is
+    /// already open.
+    pub fn open(&self, item: TextDocumentItem) -> Result<Arc<TextDocument>, ContextEngineError> {
+        let mut documents = self.documents.write();
+        if documents.contains_key(&item.uri) {
+            return Err(ContextEngineError::DocumentAlreadyOpen { uri: item.uri });
+        }
+
+        let document = Arc::new(TextDocument::from_item(item, self.encoding.clone()));
+        documents.insert(document.uri().clone(), Arc::clone(&document));
+        Ok(document)
+    }
+
+    /// Applies the content changes of a `textDocument/didChange` notification
+    /// to an open document.
+    ///
+    /// See [`TextDocument::with_changes`] for how changes are applied. The
+    /// changes are computed outside of the store's lock, so concurrent readers
+    /// are never blocked by a large edit.
+    ///
+    /// # Returns
+    ///
+    /// The snapshot of the document after the changes.
+    ///
+    /// # Errors
+    ///
+    /// * [`ContextEngineError::DocumentNotOpen`] - If the document is not open
+    /// * [`ContextEngineError::VersionConflict`] - If the version does not
+    ///   increase
+    /// * [`ContextEngineError::Location`] - If a change range is invalid
+    pub fn apply_changes(
+        &self,
+        uri: &Uri,
+        version: i32,
+        changes: &[TextDocumentContentChangeEvent],
+    ) -> Result<Arc<TextDocument>, ContextEngineError> {
+        loop {
+            let base = self.snapshot(uri)?;
+            let changed = Arc::new(base.with_changes(changes, version)?);
+
+            let mut documents = self.documents.write();
+            match documents.get_mut(uri) {
+                // Another change landed while this one was computed; retry on
+                // top of it so that the version check sees the latest version.
+                Some(current) if !Arc::ptr_eq(current, &base) => continue,
+                Some(current) => {
+                    *current = Arc::clone(&changed);
+                    return Ok(changed);
+                }
+                None => return Err(ContextEngineError::DocumentNotOpen { uri: uri.clone() }),
+            }
+        }
+    }
+
+    /// Applies a `textDocument/didChange` notification.
+    ///
+    /// # Errors
+    ///
+    /// See [`DocumentStore::apply_changes`].
+    pub fn change(
+        &self,
+        params: &DidChangeTextDocumentParams,
+    ) -> Result<Arc<TextDocument>, ContextEngineError> {
+        self.apply_changes(
+            &params.text_document.uri,
+            params.text_document.version,
+            &params.content_changes,
+        )
+    }
+
+    /// Closes a document and returns its last snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::DocumentNotOpen`] if the document is not
+    /// open.
+    pub fn close(&self, uri: &Uri) -> Result<Arc<TextDocument>, ContextEngineError> {
+        self.documents
+            .write()
+            .remove(uri)
+            .ok_or_else(|| ContextEngineError::DocumentNotOpen { uri: uri.clone() })
+    }
+
+    /// Returns the current snapshot of a document, if it is open.
+    pub fn get(&self, uri: &Uri) -> Option<Arc<TextDocument>> {
+        self.documents.read().get(uri).cloned()
+    }
+
+    /// Returns the current snapshot of a document.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::DocumentNotOpen`] if the document is not
+    /// open.
+    pub fn snapshot(&self, uri: &Uri) -> Result<Arc<TextDocument>, ContextEngineError> {
+        self.get(uri)
+            .ok_or_else(|| ContextEngineError::DocumentNotOpen { uri: uri.clone() })
+    }
+
+    /// Returns true if the document is open.
+    pub fn is_open(&self, uri: &Uri) -> bool {
+        self.documents.read().contains_key(uri)
+    }
+
+    /// Returns the snapshots of all open documents, ordered by URI.
+    pub fn snapshots(&self) -> Vec<Arc<TextDocument>> {
+        let mut snapshots = self.documents.read().values().cloned().collect::<Vec<_>>();
+        snapshots.sort_by(|a, b| a.uri().cmp(b.uri()));
+        snapshots
+    }
+
+    /// Returns the number of open documents.
+    pub fn len(&self) -> usize {
+        self.documents.read().len()
+    }
+
+    /// Returns true if no document is open.
+    pub fn is_empty(&self) -> bool {
+        self.documents.read().is_empty()
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/store.rs"]
+mod tests;
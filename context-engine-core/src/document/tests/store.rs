@@ -0,0 +1,175 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+use std::thread;
+
+use lsp_types::{Position, Range, VersionedTextDocumentIdentifier};
+
+use super::*;
+
+fn uri(path: &str) -> Uri {
+    Uri::from_str(&format!("file://{path}")).unwrap()
+}
+
+fn item(uri: &Uri, text: &str) -> TextDocumentItem {
+    TextDocumentItem::new(uri.clone(), "rust".to_string(), 1, text.to_string())
+}
+
+fn append(text: &str, line: u32) -> TextDocumentContentChangeEvent {
+    TextDocumentContentChangeEvent {
+        range: Some(Range::new(Position::new(line, 0), Position::new(line, 0))),
+        range_length: None,
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn test_open_change_close() {
+    let store = DocumentStore::default();
+    assert_eq!(store.encoding(), &PositionEncodingKind::UTF16);
+    assert!(store.is_empty());
+
+    let lib = uri("/src/lib.rs");
+    store.open(item(&lib, "mod a;\n")).unwrap();
+    assert!(store.is_open(&lib));
+
+    let params = DidChangeTextDocumentParams {
+        text_document: VersionedTextDocumentIdentifier::new(lib.clone(), 2),
+        content_changes: vec![append("mod b;\n", 1)],
+    };
+    let changed = store.change(&params).unwrap();
+    assert_eq!(changed.text(), "mod a;\nmod b;\n");
+    assert_eq!(store.snapshot(&lib).unwrap().version(), 2);
+
+    let closed = store.close(&lib).unwrap();
+    assert_eq!(closed.version(), 2);
+    assert!(!store.is_open(&lib));
+    assert_eq!(store.get(&lib), None);
+}
+
+#[test]
+fn test_document_state_errors() {
+    let store = DocumentStore::new(PositionEncodingKind::UTF8);
+    let lib = uri("/src/lib.rs");
+
+    assert_eq!(
+        store.apply_changes(&lib, 2, &[append("x", 0)]).unwrap_err(),
+        ContextEngineError::DocumentNotOpen { uri: lib.clone() }
+    );
+    assert_eq!(
+        store.close(&lib).unwrap_err(),
+        ContextEngineError::DocumentNotOpen { uri: lib.clone() }
+    );
+
+    store.open(item(&lib, "")).unwrap();
+    assert_eq!(
+        store.open(item(&lib, "other")).unwrap_err(),
+        ContextEngineError::DocumentAlreadyOpen { uri: lib.clone() }
+    );
+    assert_eq!(store.get(&lib).unwrap().text(), "");
+
+    store.apply_changes(&lib, 3, &[append("x", 0)]).unwrap();
+    assert_eq!(
+        store.apply_changes(&lib, 3, &[append("y", 0)]).unwrap_err(),
+        ContextEngineError::VersionConflict {
+            uri: lib.clone(),
+            current: 3,
+            received: 3,
+        }
+    );
+    assert_eq!(store.get(&lib).unwrap().text(), "x");
+}
+
+#[test]
+fn test_snapshots_are_isolated_from_changes() {
+    let store = DocumentStore::default();
+    let main = uri("/src/main.rs");
+    let lib = uri("/src/lib.rs");
+    store.open(item(&main, "fn main() {}")).unwrap();
+    store.open(item(&lib, "")).unwrap();
+
+    let before = store.get(&lib).unwrap();
+    store
+        .apply_changes(&lib, 2, &[append("pub mod a;", 0)])
+        .unwrap();
+
+    assert_eq!(before.text(), "");
+    assert_eq!(before.version(), 1);
+    assert_eq!(
+        store
+            .snapshots()
+            .iter()
+            .map(|doc| (doc.uri().as_str(), doc.version()))
+            .collect::<Vec<_>>(),
+        vec![("file:///src/lib.rs", 2), ("file:///src/main.rs", 1)]
+    );
+    assert_eq!(store.len(), 2);
+}
+
+#[test]
+fn test_concurrent_readers_and_writer() {
+    let store = DocumentStore::default();
+    let lib = uri("/src/lib.rs");
+    store.open(item(&lib, "")).unwrap();
+
+    thread::scope(|scope| {
+        let writer = scope.spawn(|| {
+            for version in 2..102 {
+                store
+                    .apply_changes(&lib, version, &[append("line\n", 0)])
+                    .unwrap();
+            }
+        });
+
+        for _ in 0..4 {
+            scope.spawn(|| {
+                let mut last_version = 0;
+                while last_version < 101 {
+                    let snapshot = store.get(&lib).unwrap();
+                    // Every snapshot is internally consistent and versions
+                    // never go backwards
+                    assert!(snapshot.version() >= last_version);
+                    let lines = snapshot.text().matches('\n').count();
+                    assert_eq!(lines, usize::try_from(snapshot.version() - 1).unwrap());
+                    last_version = snapshot.version();
+                }
+            });
+        }
+
+        writer.join().unwrap();
+    });
+
+    assert_eq!(store.get(&lib).unwrap().version(), 101);
+}
+
+#[test]
+fn test_concurrent_writers_serialize_versions() {
+    let store = DocumentStore::default();
+    let lib = uri("/src/lib.rs");
+    store.open(item(&lib, "")).unwrap();
+
+    // Two writers race for every version; exactly one of them wins each one
+    let applied = thread::scope(|scope| {
+        let writers = (0..2)
+            .map(|_| {
+                scope.spawn(|| {
+                    (2..52)
+                        .filter(|&version| {
+                            store
+                                .apply_changes(&lib, version, &[append("x", 0)])
+                                .is_ok()
+                        })
+                        .count()
+                })
+            })
+            .collect::<Vec<_>>();
+        writers
+            .into_iter()
+            .map(|writer| writer.join().unwrap())
+            .sum::<usize>()
+    });
+
+    let snapshot = store.get(&lib).unwrap();
+    assert_eq!(snapshot.text().len(), applied);
+    assert!(applied >= 50);
+}
@@ -0,0 +1,175 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use proptest::prelude::*;
+
+use super::*;
+
+fn document(text: &str, encoding: PositionEncodingKind) -> TextDocument {
+    let uri = Uri::from_str("file:///src/lib.rs").unwrap();
+    TextDocument::new(uri, "rust", 1, text, encoding)
+}
+
+fn incremental(range: Range, text: &str) -> TextDocumentContentChangeEvent {
+    TextDocumentContentChangeEvent {
+        range: Some(range),
+        range_length: None,
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn test_full_change_replaces_text() {
+    let doc = document("fn a() {}\n", PositionEncodingKind::UTF16);
+    let change = TextDocumentContentChangeEvent {
+        range: None,
+        range_length: None,
+        text: "fn b() {}\nfn c() {}".to_string(),
+    };
+
+    let changed = doc.with_changes(&[change], 2).unwrap();
+    assert_eq!(changed.text(), "fn b() {}\nfn c() {}");
+    assert_eq!(changed.line_index().line_count(), 2);
+    assert_eq!(
+        changed.full_range(),
+        Range::new(Position::new(0, 0), Position::new(1, 9))
+    );
+}
+
+#[test]
+fn test_incremental_changes_apply_sequentially() {
+    let doc = document("let x = 1;\nlet y = 2;\n", PositionEncodingKind::UTF16);
+    let changes = [
+        // Insert a new first line; the second change sees the shifted text
+        incremental(
+            Range::new(Position::new(0, 0), Position::new(0, 0)),
+            "// header\n",
+        ),
+        incremental(Range::new(Position::new(2, 4), Position::new(2, 5)), "z"),
+    ];
+
+    let changed = doc.with_changes(&changes, 2).unwrap();
+    assert_eq!(changed.text(), "// header\nlet x = 1;\nlet z = 2;\n");
+    assert_eq!(changed.version(), 2);
+}
+
+#[test]
+fn test_incremental_change_respects_encoding() {
+    let text = "let s = \"🦀\"; // crab\n";
+    let utf16 = document(text, PositionEncodingKind::UTF16);
+    let utf8 = document(text, PositionEncodingKind::UTF8);
+
+    // The comment starts after the emoji: UTF-16 column 14, UTF-8 column 16
+    let utf16_change = incremental(Range::new(Position::new(0, 14), Position::new(0, 21)), "");
+    let utf8_change = incremental(Range::new(Position::new(0, 16), Position::new(0, 23)), "");
+
+    let expected = "let s = \"🦀\"; \n";
+    assert_eq!(
+        utf16.with_changes(&[utf16_change], 2).unwrap().text(),
+        expected
+    );
+    assert_eq!(
+        utf8.with_changes(&[utf8_change], 2).unwrap().text(),
+        expected
+    );
+
+    // Splitting the surrogate pair of the emoji is rejected
+    let split = incremental(Range::new(Position::new(0, 10), Position::new(0, 11)), "");
+    assert!(matches!(
+        utf16.with_changes(&[split], 2),
+        Err(ContextEngineError::Location { .. })
+    ));
+}
+
+#[test]
+fn test_version_must_increase() {
+    let doc = document("fn a() {}", PositionEncodingKind::UTF16);
+    let change = incremental(Range::new(Position::new(0, 3), Position::new(0, 4)), "b");
+
+    for version in [0, 1] {
+        let error = doc
+            .with_changes(std::slice::from_ref(&change), version)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ContextEngineError::VersionConflict {
+                uri: doc.uri().clone(),
+                current: 1,
+                received: version,
+            }
+        );
+    }
+
+    // Versions may skip numbers
+    assert_eq!(doc.with_changes(&[change], 5).unwrap().version(), 5);
+}
+
+#[test]
+fn test_invalid_range_leaves_document_unchanged() {
+    let doc = document("fn a() {}", PositionEncodingKind::UTF16);
+    let changes = [
+        incremental(Range::new(Position::new(0, 3), Position::new(0, 4)), "b"),
+        incremental(Range::new(Position::new(3, 0), Position::new(3, 1)), "c"),
+    ];
+
+    assert!(doc.with_changes(&changes, 2).is_err());
+    assert_eq!(doc.text(), "fn a() {}");
+    assert_eq!(doc.version(), 1);
+}
+
+#[test]
+fn test_positions_and_slices() {
+    let doc = document("fn a() {}\r\nfn 🦀() {}", PositionEncodingKind::UTF16);
+
+    assert_eq!(doc.offset(Position::new(1, 3)).unwrap(), 14);
+    assert_eq!(doc.position(18).unwrap(), Position::new(1, 5));
+    assert_eq!(
+        doc.slice(Range::new(Position::new(1, 3), Position::new(1, 5)))
+            .unwrap(),
+        "🦀"
+    );
+    assert!(
+        doc.slice(Range::new(Position::new(2, 0), Position::new(2, 1)))
+            .is_err()
+    );
+
+    let item = doc.to_item();
+    assert_eq!(item.language_id, "rust");
+    assert_eq!(
+        TextDocument::from_item(item, PositionEncodingKind::UTF16),
+        doc
+    );
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_incremental_changes_match_string_edits(
+        text in "[a-z🦀\n]{0,40}",
+        edits in prop::collection::vec((any::<prop::sample::Index>(), any::<prop::sample::Index>(), "[a-z🦀\n]{0,5}"), 1..5),
+    ) {
+        let mut doc = document(&text, PositionEncodingKind::UTF16);
+        let mut expected = text;
+
+        for (version, (a, b, replacement)) in (2..).zip(edits) {
+            let boundaries = expected
+                .char_indices()
+                .map(|(offset, _)| offset)
+                .chain([expected.len()])
+                .collect::<Vec<_>>();
+            let (start, end) = {
+                let a = *a.get(&boundaries);
+                let b = *b.get(&boundaries);
+                (a.min(b), a.max(b))
+            };
+
+            let range = Range::new(doc.position(start).unwrap(), doc.position(end).unwrap());
+            doc = doc.with_changes(&[incremental(range, &replacement)], version).unwrap();
+            expected.replace_range(start..end, &replacement);
+
+            prop_assert_eq!(doc.text(), expected.as_str());
+            prop_assert_eq!(doc.line_index(), &LineIndex::new(&expected));
+        }
+    }
+}
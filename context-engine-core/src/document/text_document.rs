@@ -0,0 +1,223 @@
+//! Versioned text of a single document.
+
+use lsp_types::{
+    Position, PositionEncodingKind, Range, TextDocumentContentChangeEvent, TextDocumentItem, Uri,
+};
+
+use crate::error::ContextEngineError;
+use crate::types::{LineIndex, LocationError, RangeExt};
+
+/// An immutable snapshot of the text of a document at one version.
+///
+/// Applying changes never modifies a snapshot; it produces a new one. This
+/// makes snapshots safe to share between threads while the document keeps
+/// being edited.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::document::TextDocument;
+/// use context_engine_core::types::{Position, PositionEncodingKind, Range, Uri};
+/// use lsp_types::TextDocumentContentChangeEvent;
+/// use std::str::FromStr;
+///
+/// let uri = Uri::from_str("file:///src/main.rs").unwrap();
+/// let document = TextDocument::new(uri, "rust", 1, "fn main() {}", PositionEncodingKind::UTF16);
+///
+/// let change = TextDocumentContentChangeEvent {
+///     range: Some(Range::new(Position::new(0, 3), Position::new(0, 7))),
+///     range_length: None,
+///     text: "start".to_string(),
+/// };
+/// let edited = document.with_changes(&[change], 2).unwrap();
+///
+/// assert_eq!(edited.text(), "fn start() {}");
+/// assert_eq!(edited.version(), 2);
+/// assert_eq!(document.text(), "fn main() {}");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextDocument {
+    uri: Uri,
+    language_id: String,
+    version: i32,
+    text: String,
+    line_index: LineIndex,
+    encoding: PositionEncodingKind,
+}
+
+impl TextDocument {
+    /// Creates a snapshot of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI of the document
+    /// * `language_id` - The LSP language identifier of the document
+    /// * `version` - The version of the text
+    /// * `text` - The text of the document
+    /// * `encoding` - The encoding in which positions into the document are
+    ///   counted
+    pub fn new(
+        uri: Uri,
+        language_id: impl Into<String>,
+        version: i32,
+        text: impl Into<String>,
+        encoding: PositionEncodingKind,
+    ) -> Self {
+        let text = text.into();
+        Self {
+            uri,
+            language_id: language_id.into(),
+            version,
+            line_index: LineIndex::new(&text),
+            text,
+            encoding,
+        }
+    }
+
+    /// Creates a snapshot from a `textDocument/didOpen` item.
+    pub fn from_item(item: TextDocumentItem, encoding: PositionEncodingKind) -> Self {
+        Self::new(
+            item.uri,
+            item.language_id,
+            item.version,
+            item.text,
+            encoding,
+        )
+    }
+
+    /// Returns the document as a `textDocument/didOpen` item, e.g. to open it
+    /// in a language server.
+    pub fn to_item(&self) -> TextDocumentItem {
+        TextDocumentItem::new(
+            self.uri.clone(),
+            self.language_id.clone(),
+            self.version,
+            self.text.clone(),
+        )
+    }
+
+    /// Returns the URI of the document.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Returns the LSP language identifier of the document.
+    pub fn language_id(&self) -> &str {
+        &self.language_id
+    }
+
+    /// Returns the version of the snapshot.
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// Returns the text of the snapshot.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the line index of the text.
+    pub fn line_index(&self) -> &LineIndex {
+        &self.line_index
+    }
+
+    /// Returns the encoding in which positions into the document are counted.
+    pub fn encoding(&self) -> &PositionEncodingKind {
+        &self.encoding
+    }
+
+    /// Returns the byte offset of a position.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the position does not exist in the text.
+    pub fn offset(&self, position: Position) -> Result<usize, LocationError> {
+        self.line_index.offset(position, &self.encoding)
+    }
+
+    /// Returns the position of a byte offset.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the offset does not point to a valid position.
+    pub fn position(&self, offset: usize) -> Result<Position, LocationError> {
+        self.line_index.position(offset, &self.encoding)
+    }
+
+    /// Returns the range spanning the whole text.
+    pub fn full_range(&self) -> Range {
+        let end = self
+            .line_index
+            .end_position(&self.encoding)
+            .unwrap_or_default();
+        Range::new(Position::new(0, 0), end)
+    }
+
+    /// Returns the text within a range.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range is not valid in the text.
+    pub fn slice(&self, range: Range) -> Result<&str, LocationError> {
+        let offsets = range.to_offsets(&self.line_index, &self.encoding)?;
+        Ok(self.text.get(offsets).unwrap_or_default())
+    }
+
+    /// Returns a new snapshot with the changes of a `textDocument/didChange`
+    /// notification applied.
+    ///
+    /// Changes are applied in order, each one to the text produced by the
+    /// previous one, as the protocol requires. A change without a range
+    /// replaces the whole text.
+    ///
+    /// # Arguments
+    ///
+    /// * `changes` - The content changes to apply
+    /// * `version` - The version of the document after the changes
+    ///
+    /// # Errors
+    ///
+    /// * [`ContextEngineError::VersionConflict`] - If `version` is not greater
+    ///   than the version of the snapshot
+    /// * [`ContextEngineError::Location`] - If a change range is not valid in
+    ///   the text it applies to
+    pub fn with_changes(
+        &self,
+        changes: &[TextDocumentContentChangeEvent],
+        version: i32,
+    ) -> Result<TextDocument, ContextEngineError> {
+        if version <= self.version {
+            return Err(ContextEngineError::VersionConflict {
+                uri: self.uri.clone(),
+                current: self.version,
+                received: version,
+            });
+        }
+
+        let mut text = self.text.clone();
+        let mut line_index = self.line_index.clone();
+        for change in changes {
+            match change.range {
+                Some(range) => {
+                    let offsets = range.to_offsets(&line_index, &self.encoding)?;
+                    text.replace_range(offsets, &change.text);
+                }
+                None => text.clone_from(&change.text),
+            }
+            line_index = LineIndex::new(&text);
+        }
+
+        Ok(Self {
+            uri: self.uri.clone(),
+            language_id: self.language_id.clone(),
+            version,
+            text,
+            line_index,
+            encoding: self.encoding.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/text_document.rs"]
+mod tests;
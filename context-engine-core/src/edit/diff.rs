@@ -0,0 +1,257 @@
+//! Unified diffs of texts.
+
+/// The number of unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+/// The largest number of changed lines the shortest diff is searched for;
+/// texts that differ more are diffed as one replacement of the lines between
+/// their common prefix and suffix.
+const MAX_EDIT_DISTANCE: usize = 1000;
+
+/// A line of an edit script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line {
+    /// The line at an index of the old text equals the line at an index of
+    /// the new text
+    Equal(usize, usize),
+    /// The line at an index of the old text is removed
+    Delete(usize),
+    /// The line at an index of the new text is inserted
+    Insert(usize),
+}
+
+/// Returns a unified diff of two texts, as produced by `diff -u`.
+///
+/// Lines are compared including their terminators; a last line without a
+/// terminator is marked with `\ No newline at end of file`.
+///
+/// # Arguments
+///
+/// * `old_name` - The name of the old text in the `---` header, e.g.
+///   `a/src/lib.rs` or `/dev/null`
+/// * `new_name` - The name of the new text in the `+++` header
+/// * `old` - The old text
+/// * `new` - The new text
+///
+/// # Returns
+///
+/// The diff; empty if the texts are equal.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::edit::unified_diff;
+///
+/// let diff = unified_diff("a/greet.txt", "b/greet.txt", "hello\nworld\n", "hello\nthere\n");
+/// assert_eq!(
+///     diff,
+///     "--- a/greet.txt\n+++ b/greet.txt\n@@ -1,2 +1,2 @@\n hello\n-world\n+there\n"
+/// );
+/// ```
+pub fn unified_diff(old_name: &str, new_name: &str, old: &str, new: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    let old_lines = old.split_inclusive('\n').collect::<Vec<_>>();
+    let new_lines = new.split_inclusive('\n').collect::<Vec<_>>();
+    let script = edit_script(&old_lines, &new_lines);
+
+    let mut diff = format!("--- {old_name}\n+++ {new_name}\n");
+    for hunk in hunks(&script) {
+        let Some(lines) = script.get(hunk.clone()) else {
+            continue;
+        };
+        // The positions of the hunk in both texts
+        let (old_start, new_start) = positions(&script, hunk.start);
+        let old_len = lines
+            .iter()
+            .filter(|line| !matches!(line, Line::Insert(_)))
+            .count();
+        let new_len = lines
+            .iter()
+            .filter(|line| !matches!(line, Line::Delete(_)))
+            .count();
+        diff.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            span(old_start, old_len),
+            span(new_start, new_len)
+        ));
+        for line in lines {
+            let (prefix, text) = match *line {
+                Line::Equal(old, _) => (' ', old_lines.get(old)),
+                Line::Delete(old) => ('-', old_lines.get(old)),
+                Line::Insert(new) => ('+', new_lines.get(new)),
+            };
+            let text = text.copied().unwrap_or_default();
+            diff.push(prefix);
+            diff.push_str(text);
+            if !text.ends_with('\n') {
+                diff.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    diff
+}
+
+/// Formats the position and length of a hunk in one text, e.g. `4,3`.
+fn span(start: usize, len: usize) -> String {
+    match len {
+        // An empty hunk is positioned after the line it follows
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{len}", start + 1),
+    }
+}
+
+/// Returns the lines of the old and new text before a line of the script.
+fn positions(script: &[Line], index: usize) -> (usize, usize) {
+    script
+        .iter()
+        .take(index)
+        .fold((0, 0), |(old, new), line| match line {
+            Line::Equal(..) => (old + 1, new + 1),
+            Line::Delete(_) => (old + 1, new),
+            Line::Insert(_) => (old, new + 1),
+        })
+}
+
+/// Groups the changed lines of a script with their context into hunks.
+fn hunks(script: &[Line]) -> Vec<std::ops::Range<usize>> {
+    let mut hunks: Vec<std::ops::Range<usize>> = Vec::new();
+    let changes = script
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Equal(..)))
+        .map(|(index, _)| index);
+    for change in changes {
+        let start = change.saturating_sub(CONTEXT);
+        let end = (change + 1 + CONTEXT).min(script.len());
+        match hunks.last_mut() {
+            // Hunks whose contexts touch are merged
+            Some(last) if last.end >= start => last.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+    hunks
+}
+
+/// Returns the shortest edit script turning the old lines into the new
+/// ones.
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Line> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let old_rest = old.get(prefix..).unwrap_or_default();
+    let new_rest = new.get(prefix..).unwrap_or_default();
+    let suffix = old_rest
+        .iter()
+        .rev()
+        .zip(new_rest.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = old_rest.get(..old_rest.len() - suffix).unwrap_or_default();
+    let new_middle = new_rest.get(..new_rest.len() - suffix).unwrap_or_default();
+
+    let mut script = (0..prefix)
+        .map(|index| Line::Equal(index, index))
+        .collect::<Vec<_>>();
+    let middle = myers(old_middle, new_middle).unwrap_or_else(|| {
+        let deleted = (0..old_middle.len()).map(Line::Delete);
+        deleted
+            .chain((0..new_middle.len()).map(Line::Insert))
+            .collect()
+    });
+    script.extend(middle.into_iter().map(|line| match line {
+        Line::Equal(old, new) => Line::Equal(old + prefix, new + prefix),
+        Line::Delete(old) => Line::Delete(old + prefix),
+        Line::Insert(new) => Line::Insert(new + prefix),
+    }));
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    script.extend((0..suffix).map(|index| Line::Equal(old_end + index, new_end + index)));
+    script
+}
+
+/// Returns the value of a diagonal in a vector of furthest reaching `x`
+/// positions that starts at diagonal `first`.
+fn furthest(v: &[isize], first: isize, diagonal: isize) -> isize {
+    usize::try_from(diagonal - first)
+        .ok()
+        .and_then(|index| v.get(index))
+        .copied()
+        .unwrap_or(0)
+}
+
+/// Finds the shortest edit script with Myers' algorithm.
+///
+/// # Returns
+///
+/// The script, `None` if more than [`MAX_EDIT_DISTANCE`] lines change.
+fn myers(old: &[&str], new: &[&str]) -> Option<Vec<Line>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = (n + m).min(MAX_EDIT_DISTANCE as isize);
+    // The furthest `x` of every diagonal `k = x - y`, starting at `-max - 1`
+    let first = -max - 1;
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    // The diagonals `-d..=d` of `v` before each step `d`
+    let mut trace = Vec::new();
+
+    let mut found = false;
+    'search: for d in 0..=max {
+        let window = v
+            .get((-d - first) as usize..=(d - first) as usize)
+            .unwrap_or_default();
+        trace.push(window.to_vec());
+        for k in (-d..=d).step_by(2) {
+            let down =
+                k == -d || (k != d && furthest(&v, first, k - 1) < furthest(&v, first, k + 1));
+            let mut x = match down {
+                true => furthest(&v, first, k + 1),
+                false => furthest(&v, first, k - 1) + 1,
+            };
+            let mut y = x - k;
+            while x < n && y < m && old.get(x as usize) == new.get(y as usize) {
+                x += 1;
+                y += 1;
+            }
+            if let Some(slot) = v.get_mut((k - first) as usize) {
+                *slot = x;
+            }
+            if x >= n && y >= m {
+                found = true;
+                break 'search;
+            }
+        }
+    }
+    if !found {
+        return None;
+    }
+
+    let mut script = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let down = k == -d || (k != d && furthest(v, -d, k - 1) < furthest(v, -d, k + 1));
+        let previous_k = if down { k + 1 } else { k - 1 };
+        let previous_x = furthest(v, -d, previous_k);
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            script.push(Line::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if down {
+                y -= 1;
+                script.push(Line::Insert(y as usize));
+            } else {
+                x -= 1;
+                script.push(Line::Delete(x as usize));
+            }
+        }
+    }
+    script.reverse();
+    Some(script)
+}
+
+#[cfg(test)]
+#[path = "tests/diff.rs"]
+mod tests;
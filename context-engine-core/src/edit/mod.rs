@@ -0,0 +1,37 @@
+//! Workspace edits.
+//!
+//! Code actions and renames of a language server answer with a
+//! `WorkspaceEdit`: text edits of documents, possibly mixed with the
+//! creation, renaming and deletion of files. The [`EditPlanner`] computes
+//! what such an edit does to every file without changing anything, so that
+//! an agent can preview it as a unified diff (see [`unified_diff`]). The
+//! resulting [`EditPlan`] is then applied to the in-memory document store or
+//! written to disk.
+//!
+//! The [`Renamer`] builds on the planner to rename a symbol across the
+//! workspace: its [`RenamePreview`] lists every range that changes before
+//! the rename is written to disk at once.
+//!
+//! ## Structs
+//!
+//! * [`EditPlanner`] - Computes the effect of a workspace edit.
+//! * [`EditPlan`] - The effect of a workspace edit on the files.
+//! * [`FileChange`] - The effect of a workspace edit on one file.
+//! * [`Renamer`] - Computes renames of symbols.
+//! * [`RenamePreview`] - A rename of a symbol, computed but not applied.
+//!
+//! ## Enums
+//!
+//! * [`FileOperation`] - What a workspace edit does to a file.
+//!
+//! ## Functions
+//!
+//! * [`unified_diff`] - A unified diff of two texts.
+
+mod diff;
+mod plan;
+mod rename;
+
+pub use diff::unified_diff;
+pub use plan::{EditPlan, EditPlanner, FileChange, FileOperation};
+pub use rename::{RenamePreview, Renamer};
@@ -0,0 +1,767 @@
+//! Planning and applying workspace edits.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use lsp_types::{
+    CreateFile, DeleteFile, DocumentChangeOperation, DocumentChanges, OneOf, PositionEncodingKind,
+    RenameFile, ResourceOp, TextDocumentContentChangeEvent, TextDocumentEdit, TextDocumentItem,
+    TextEdit, Uri, WorkspaceEdit,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::document::DocumentStore;
+use crate::edit::unified_diff;
+use crate::error::ContextEngineError;
+use crate::lsp::{LanguageRegistry, LspSupervisor};
+use crate::types::{LineIndex, RangeExt, UriExt};
+
+/// The language of documents whose language is not recognized.
+const PLAIN_TEXT: &str = "plaintext";
+
+/// What a workspace edit does to a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FileOperation {
+    /// The file is created
+    Create,
+    /// The text of the file changes
+    Modify,
+    /// The file is moved from another URI, possibly changing its text
+    Rename {
+        /// The URI the file is moved from
+        from: Uri,
+    },
+    /// The file is deleted
+    Delete,
+}
+
+/// The effect of a workspace edit on one file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChange {
+    /// The file; for a rename, the URI it is moved to
+    pub uri: Uri,
+    /// What happens to the file
+    pub operation: FileOperation,
+    /// The text before the edit; `None` if the file did not exist
+    pub before: Option<String>,
+    /// The text after the edit; `None` if the file is deleted
+    pub after: Option<String>,
+}
+
+impl FileChange {
+    /// Returns a unified diff of the change, with `/dev/null` standing for
+    /// a file that does not exist.
+    pub fn diff(&self) -> String {
+        let old_name = match &self.operation {
+            FileOperation::Create => "/dev/null".to_string(),
+            FileOperation::Rename { from } => display(from),
+            FileOperation::Modify | FileOperation::Delete => display(&self.uri),
+        };
+        let new_name = match &self.operation {
+            FileOperation::Delete => "/dev/null".to_string(),
+            _ => display(&self.uri),
+        };
+        let before = self.before.as_deref().unwrap_or_default();
+        let after = self.after.as_deref().unwrap_or_default();
+        let diff = unified_diff(&old_name, &new_name, before, after);
+        match diff.is_empty() {
+            // Moved or created without content
+            true => format!("--- {old_name}\n+++ {new_name}\n"),
+            false => diff,
+        }
+    }
+}
+
+/// Returns the path of a file URI, or the URI itself.
+fn display(uri: &Uri) -> String {
+    uri.to_file_path()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| uri.as_str().to_string())
+}
+
+/// Computes what a [`WorkspaceEdit`] does to the files of a workspace
+/// without changing anything.
+///
+/// Files are read from, in this order, the contents set with
+/// [`EditPlanner::with_content`], the open documents of the store, and the
+/// disk. Text edits are applied in the position encoding of the language
+/// server that made the edit. An edit of a versioned document must match
+/// the version of the open document.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::document::DocumentStore;
+/// use context_engine_core::edit::{EditPlanner, FileOperation};
+/// use lsp_types::{Position, PositionEncodingKind, Range, TextEdit, Uri, WorkspaceEdit};
+/// use std::collections::HashMap;
+///
+/// let uri: Uri = "file:///app/src/lib.rs".parse().unwrap();
+/// let edit = WorkspaceEdit::new(HashMap::from([(
+///     uri.clone(),
+///     vec![TextEdit::new(
+///         Range::new(Position::new(0, 3), Position::new(0, 4)),
+///         "answer".to_string(),
+///     )],
+/// )]));
+///
+/// let documents = DocumentStore::default();
+/// let plan = EditPlanner::new(&documents, PositionEncodingKind::UTF16)
+///     .with_content(uri, "fn f() -> u8 { 42 }\n")
+///     .plan(&edit)
+///     .unwrap();
+/// let change = plan.changes().first().unwrap();
+/// assert_eq!(change.operation, FileOperation::Modify);
+/// assert_eq!(change.after.as_deref(), Some("fn answer() -> u8 { 42 }\n"));
+/// ```
+pub struct EditPlanner<'a> {
+    documents: &'a DocumentStore,
+    encoding: PositionEncodingKind,
+    contents: Vec<(Uri, String)>,
+}
+
+impl<'a> EditPlanner<'a> {
+    /// Creates a planner over the open documents of a store.
+    ///
+    /// # Arguments
+    ///
+    /// * `documents` - The open documents, whose text and versions edits apply
+    ///   to
+    /// * `encoding` - The position encoding of the ranges of the edits
+    pub fn new(documents: &'a DocumentStore, encoding: PositionEncodingKind) -> Self {
+        Self {
+            documents,
+            encoding,
+            contents: Vec::new(),
+        }
+    }
+
+    /// Sets the text a file has before the edit, e.g. unsaved content that
+    /// a language server computed the edit on. The versions of edits of the
+    /// file are not checked.
+    pub fn with_content(mut self, uri: Uri, text: impl Into<String>) -> Self {
+        self.contents.retain(|(known, _)| *known != uri);
+        self.contents.push((uri, text.into()));
+        self
+    }
+
+    /// Computes the effect of an edit.
+    ///
+    /// The document changes of the edit are applied in order; if it has
+    /// none, its changes are applied file by file.
+    ///
+    /// # Errors
+    ///
+    /// * [`ContextEngineError::VersionConflict`] - If a versioned text edit
+    ///   does not match the version of the open document
+    /// * [`ContextEngineError::Edit`] - If a file to edit, rename or delete
+    ///   does not exist, a file to create or rename to exists, text edits
+    ///   overlap, or a file cannot be read
+    /// * [`ContextEngineError::Location`] - If a range is not valid in the text
+    ///   it applies to
+    pub fn plan(&self, edit: &WorkspaceEdit) -> Result<EditPlan, ContextEngineError> {
+        let mut files = Files {
+            planner: self,
+            current: Vec::new(),
+            original: Vec::new(),
+            renames: Vec::new(),
+            from_disk: Vec::new(),
+        };
+        match &edit.document_changes {
+            Some(DocumentChanges::Edits(edits)) => {
+                for edit in edits {
+                    files.text_document_edit(edit)?;
+                }
+            }
+            Some(DocumentChanges::Operations(operations)) => {
+                for operation in operations {
+                    match operation {
+                        DocumentChangeOperation::Edit(edit) => files.text_document_edit(edit)?,
+                        DocumentChangeOperation::Op(ResourceOp::Create(create)) => {
+                            files.create(create)?;
+                        }
+                        DocumentChangeOperation::Op(ResourceOp::Rename(rename)) => {
+                            files.rename(rename)?;
+                        }
+                        DocumentChangeOperation::Op(ResourceOp::Delete(delete)) => {
+                            files.delete(delete)?;
+                        }
+                    }
+                }
+            }
+            None => {
+                let mut changes = edit.changes.iter().flatten().collect::<Vec<_>>();
+                changes.sort_by_key(|(uri, _)| *uri);
+                for (uri, edits) in changes {
+                    files.edit(uri, edits.iter())?;
+                }
+            }
+        }
+        Ok(files.into_plan())
+    }
+
+    /// Reads the text of a file; `None` if it does not exist.
+    ///
+    /// # Returns
+    ///
+    /// The text, and whether it was read from disk.
+    fn read(&self, uri: &Uri) -> Result<(Option<String>, bool), ContextEngineError> {
+        if let Some((_, text)) = self.contents.iter().find(|(known, _)| known == uri) {
+            return Ok((Some(text.clone()), false));
+        }
+        if let Some(document) = self.documents.get(uri) {
+            return Ok((Some(document.text().to_string()), false));
+        }
+        let path = uri.to_file_path()?;
+        Ok((read_file(uri, &path)?, true))
+    }
+}
+
+/// Reads a file from disk; `None` if it does not exist.
+pub(super) fn read_file(uri: &Uri, path: &Path) -> Result<Option<String>, ContextEngineError> {
+    if path.is_dir() {
+        return Err(edit_error(uri, "directories cannot be edited"));
+    }
+    match std::fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(edit_error(
+            uri,
+            format!("cannot read {}: {error}", path.display()),
+        )),
+    }
+}
+
+pub(super) fn edit_error(uri: &Uri, message: impl Into<String>) -> ContextEngineError {
+    ContextEngineError::Edit {
+        uri: Some(uri.clone()),
+        message: message.into(),
+    }
+}
+
+/// The files of a workspace while an edit is planned.
+struct Files<'p, 'a> {
+    planner: &'p EditPlanner<'a>,
+    /// The text of every file touched so far; `None` if it does not exist
+    current: Vec<(Uri, Option<String>)>,
+    /// The text of every file touched so far before the edit
+    original: Vec<(Uri, Option<String>)>,
+    /// The files moved so far, as `(from, to)`
+    renames: Vec<(Uri, Uri)>,
+    /// The files whose text was read from disk
+    from_disk: Vec<Uri>,
+}
+
+impl Files<'_, '_> {
+    fn text(&mut self, uri: &Uri) -> Result<Option<String>, ContextEngineError> {
+        if let Some((_, text)) = self.current.iter().find(|(known, _)| known == uri) {
+            return Ok(text.clone());
+        }
+        let (text, from_disk) = self.planner.read(uri)?;
+        if from_disk {
+            self.from_disk.push(uri.clone());
+        }
+        self.original.push((uri.clone(), text.clone()));
+        self.current.push((uri.clone(), text.clone()));
+        Ok(text)
+    }
+
+    fn set(&mut self, uri: &Uri, text: Option<String>) -> Result<(), ContextEngineError> {
+        self.text(uri)?;
+        if let Some((_, current)) = self.current.iter_mut().find(|(known, _)| known == uri) {
+            *current = text;
+        }
+        Ok(())
+    }
+
+    fn text_document_edit(&mut self, edit: &TextDocumentEdit) -> Result<(), ContextEngineError> {
+        let uri = &edit.text_document.uri;
+        let overridden = self.planner.contents.iter().any(|(known, _)| known == uri);
+        if let (Some(version), false) = (edit.text_document.version, overridden) {
+            if let Some(document) = self.planner.documents.get(uri) {
+                if document.version() != version {
+                    return Err(ContextEngineError::VersionConflict {
+                        uri: uri.clone(),
+                        current: document.version(),
+                        received: version,
+                    });
+                }
+            }
+        }
+        let edits = edit.edits.iter().map(|edit| match edit {
+            OneOf::Left(edit) => edit,
+            OneOf::Right(annotated) => &annotated.text_edit,
+        });
+        self.edit(uri, edits)
+    }
+
+    fn edit<'e>(
+        &mut self,
+        uri: &Uri,
+        edits: impl Iterator<Item = &'e TextEdit>,
+    ) -> Result<(), ContextEngineError> {
+        let Some(text) = self.text(uri)? else {
+            return Err(edit_error(uri, "the file to edit does not exist"));
+        };
+        let index = LineIndex::new(&text);
+        let mut spans = Vec::new();
+        for edit in edits {
+            let offsets = edit.range.to_offsets(&index, &self.planner.encoding)?;
+            spans.push((offsets, edit.new_text.as_str()));
+        }
+        // Stable, so insertions at the same position keep their order
+        spans.sort_by_key(|(offsets, _)| (offsets.start, offsets.end));
+
+        let mut edited = String::with_capacity(text.len());
+        let mut end = 0;
+        for (offsets, new_text) in spans {
+            let Some(unchanged) = text.get(end..offsets.start) else {
+                return Err(edit_error(uri, "text edits overlap"));
+            };
+            edited.push_str(unchanged);
+            edited.push_str(new_text);
+            end = offsets.end;
+        }
+        edited.push_str(text.get(end..).unwrap_or_default());
+        self.set(uri, Some(edited))
+    }
+
+    fn create(&mut self, create: &CreateFile) -> Result<(), ContextEngineError> {
+        let options = create.options.as_ref();
+        let overwrite = options.and_then(|options| options.overwrite) == Some(true);
+        let ignore = options.and_then(|options| options.ignore_if_exists) == Some(true);
+        if self.text(&create.uri)?.is_some() && !overwrite {
+            return match ignore {
+                true => Ok(()),
+                false => Err(edit_error(&create.uri, "the file to create exists")),
+            };
+        }
+        self.set(&create.uri, Some(String::new()))
+    }
+
+    fn rename(&mut self, rename: &RenameFile) -> Result<(), ContextEngineError> {
+        let options = rename.options.as_ref();
+        let overwrite = options.and_then(|options| options.overwrite) == Some(true);
+        let ignore = options.and_then(|options| options.ignore_if_exists) == Some(true);
+        let Some(text) = self.text(&rename.old_uri)? else {
+            return Err(edit_error(
+                &rename.old_uri,
+                "the file to rename does not exist",
+            ));
+        };
+        if self.text(&rename.new_uri)?.is_some() && !overwrite {
+            return match ignore {
+                true => Ok(()),
+                false => Err(edit_error(&rename.new_uri, "the file to rename to exists")),
+            };
+        }
+        self.set(&rename.old_uri, None)?;
+        self.set(&rename.new_uri, Some(text))?;
+        self.renames
+            .push((rename.old_uri.clone(), rename.new_uri.clone()));
+        Ok(())
+    }
+
+    fn delete(&mut self, delete: &DeleteFile) -> Result<(), ContextEngineError> {
+        let ignore = delete
+            .options
+            .as_ref()
+            .and_then(|options| options.ignore_if_not_exists)
+            == Some(true);
+        if self.text(&delete.uri)?.is_none() {
+            return match ignore {
+                true => Ok(()),
+                false => Err(edit_error(&delete.uri, "the file to delete does not exist")),
+            };
+        }
+        self.set(&delete.uri, None)
+    }
+
+    fn into_plan(self) -> EditPlan {
+        let original = |uri: &Uri| {
+            self.original
+                .iter()
+                .find(|(known, _)| known == uri)
+                .and_then(|(_, text)| text.clone())
+        };
+        let mut changes = Vec::new();
+        for (uri, after) in &self.current {
+            let before = original(uri);
+            let moved_here = self.renames.iter().rev().find(|(_, to)| to == uri);
+            let moved_away = self.renames.iter().any(|(from, _)| from == uri);
+            let operation = match (moved_here, &before, after) {
+                (Some((from, _)), _, Some(_)) => {
+                    changes.push(FileChange {
+                        uri: uri.clone(),
+                        operation: FileOperation::Rename { from: from.clone() },
+                        before: original(from),
+                        after: after.clone(),
+                    });
+                    continue;
+                }
+                // Reported as the rename of the file it moved to
+                (_, _, None) if moved_away => continue,
+                (_, None, Some(_)) => FileOperation::Create,
+                (_, Some(_), None) => FileOperation::Delete,
+                (_, Some(before), Some(after)) if before != after => FileOperation::Modify,
+                _ => continue,
+            };
+            changes.push(FileChange {
+                uri: uri.clone(),
+                operation,
+                before,
+                after: after.clone(),
+            });
+        }
+        EditPlan {
+            changes,
+            from_disk: self.from_disk,
+        }
+    }
+}
+
+/// The effect of a workspace edit on the files of a workspace, computed by
+/// an [`EditPlanner`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EditPlan {
+    changes: Vec<FileChange>,
+    /// The files whose text was read from disk, which must not change
+    /// before the plan is written
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    from_disk: Vec<Uri>,
+}
+
+impl EditPlan {
+    /// Returns the changed files, in the order the edit first touched them.
+    pub fn changes(&self) -> &[FileChange] {
+        &self.changes
+    }
+
+    /// Returns true if the edit changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns a unified diff of all changed files.
+    pub fn diff(&self) -> String {
+        self.changes.iter().map(FileChange::diff).collect()
+    }
+
+    /// Checks that every file the plan touches is inside a folder.
+    ///
+    /// Symbolic links and `.` components are resolved, also for files that
+    /// do not exist yet, so that an edit cannot reach outside of the folder
+    /// through a link. Paths with `..` components that would have to be
+    /// resolved are rejected.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::Edit`] for the first file, or file a
+    /// rename moves, that is not a `file` URI inside the folder, and a
+    /// [`ContextEngineError::Configuration`] if the folder does not exist.
+    pub fn check_within(&self, root: &Path) -> Result<(), ContextEngineError> {
+        let root = root
+            .canonicalize()
+            .map_err(|error| ContextEngineError::Configuration {
+                key: None,
+                message: format!("Invalid workspace root {}: {error}", root.display()),
+            })?;
+        for change in &self.changes {
+            if let FileOperation::Rename { from } = &change.operation {
+                check_within(&root, from)?;
+            }
+            check_within(&root, &change.uri)?;
+        }
+        Ok(())
+    }
+
+    /// Applies the changes to the document store only.
+    ///
+    /// Changed documents that are open get the new text as their next
+    /// version; other changed files are opened with their new text, in the
+    /// language the registry recognizes. Deleted documents are closed, and
+    /// renamed documents closed and opened under their new URI. The disk is
+    /// not touched. The language servers of the supervisor are notified of
+    /// every document that is opened, changed or closed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ContextEngineError::Edit`] if a file is outside of the
+    /// workspace root (see [`EditPlan::check_within`]), and any other
+    /// [`ContextEngineError`] if a document changes concurrently in a
+    /// conflicting way.
+    pub fn apply_to_store(
+        &self,
+        supervisor: &LspSupervisor,
+        registry: &LanguageRegistry,
+    ) -> Result<(), ContextEngineError> {
+        self.check_within(supervisor.root())?;
+        self.update_store(supervisor, Some(registry))
+    }
+
+    /// Writes the changes to disk: all of them, or none.
+    ///
+    /// The new texts are written to temporary files next to their files
+    /// first, and the files that are replaced, moved or deleted are moved
+    /// aside. Only then are the new texts moved into place. If a step
+    /// fails, the steps before it are undone. Missing parent directories
+    /// are created.
+    ///
+    /// Open documents of the store follow the files: they get the new text,
+    /// are closed when their file is deleted, and move with their file; the
+    /// language servers of the supervisor are notified of them. The files
+    /// are written even if a document cannot follow them, which is logged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::Edit`] if a file is outside of the
+    /// workspace root (see [`EditPlan::check_within`]), a file that was
+    /// read from disk changed since the edit was planned, or a file cannot
+    /// be written, moved or deleted. The files are left as they were.
+    pub fn apply_to_disk(&self, supervisor: &LspSupervisor) -> Result<(), ContextEngineError> {
+        self.check_within(supervisor.root())?;
+        let mut transaction = Transaction::default();
+        if let Err(error) = transaction.write(self) {
+            transaction.roll_back();
+            return Err(error);
+        }
+        transaction.commit();
+        // The files are written, so the edit succeeded
+        if let Err(error) = self.update_store(supervisor, None) {
+            warn!(%error, "Open documents did not follow a workspace edit");
+        }
+        Ok(())
+    }
+
+    /// Brings the store in line with the changes, and the servers with the
+    /// store; without a registry, only documents that are open follow them.
+    fn update_store(
+        &self,
+        supervisor: &LspSupervisor,
+        registry: Option<&LanguageRegistry>,
+    ) -> Result<(), ContextEngineError> {
+        let documents = supervisor.documents();
+        for change in &self.changes {
+            let mut language_id = None;
+            if let FileOperation::Rename { from } = &change.operation {
+                if documents.is_open(from) {
+                    let document = documents.close(from)?;
+                    supervisor.close_document(&document);
+                    language_id = Some(document.language_id().to_string());
+                }
+            }
+            let Some(after) = &change.after else {
+                if documents.is_open(&change.uri) {
+                    let document = documents.close(&change.uri)?;
+                    supervisor.close_document(&document);
+                }
+                continue;
+            };
+
+            if let Some(document) = documents.get(&change.uri) {
+                let replace = TextDocumentContentChangeEvent {
+                    range: None,
+                    range_length: None,
+                    text: after.clone(),
+                };
+                let document =
+                    documents.apply_changes(&change.uri, document.version() + 1, &[replace])?;
+                supervisor.change_document(&document);
+                continue;
+            }
+            let language_id = match (language_id, registry) {
+                (Some(language_id), _) => language_id,
+                (None, Some(registry)) => registry
+                    .language_id(&change.uri, Some(after))
+                    .unwrap_or(PLAIN_TEXT)
+                    .to_string(),
+                (None, None) => continue,
+            };
+            let document = documents.open(TextDocumentItem::new(
+                change.uri.clone(),
+                language_id,
+                1,
+                after.clone(),
+            ))?;
+            supervisor.open_document(&document);
+        }
+        Ok(())
+    }
+}
+
+/// Checks that a file is inside a canonical folder.
+fn check_within(root: &Path, uri: &Uri) -> Result<(), ContextEngineError> {
+    let outside = || edit_error(uri, "the file is outside of the workspace");
+    let path = uri.to_file_path().map_err(|_| outside())?;
+    match resolve(&path) {
+        Some(path) if path.starts_with(root) => Ok(()),
+        _ => Err(outside()),
+    }
+}
+
+/// Resolves the symbolic links and `.` components of a path that may not
+/// exist yet: its longest existing ancestor is canonicalized, and the
+/// remaining names appended. `None` if a remaining component is `..`.
+fn resolve(path: &Path) -> Option<PathBuf> {
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        match existing.canonicalize() {
+            Ok(resolved) => {
+                return Some(
+                    missing
+                        .into_iter()
+                        .rev()
+                        .fold(resolved, |path, name| path.join(name)),
+                );
+            }
+            Err(_) => {
+                missing.push(existing.file_name()?);
+                existing = existing.parent()?;
+            }
+        }
+    }
+}
+
+/// A step of writing a plan to disk.
+enum Step {
+    /// A missing directory was created
+    CreatedDir(PathBuf),
+    /// A new text was written to a temporary file
+    Staged(PathBuf),
+    /// A file was moved to a backup
+    MovedAside { path: PathBuf, backup: PathBuf },
+    /// A new text was moved from its temporary file into place
+    Placed(PathBuf),
+}
+
+/// The steps of writing a plan to disk, so that they can be undone.
+#[derive(Default)]
+struct Transaction {
+    steps: Vec<Step>,
+}
+
+impl Transaction {
+    fn write(&mut self, plan: &EditPlan) -> Result<(), ContextEngineError> {
+        let mut staged = Vec::new();
+        for change in &plan.changes {
+            let path = change.uri.to_file_path()?;
+            let source = match &change.operation {
+                FileOperation::Rename { from } => from,
+                _ => &change.uri,
+            };
+            let source_path = source.to_file_path()?;
+            if plan.from_disk.contains(source) && read_file(source, &source_path)? != change.before
+            {
+                return Err(edit_error(
+                    source,
+                    "the file changed since the edit was planned",
+                ));
+            }
+            let Some(after) = &change.after else {
+                continue;
+            };
+            if let Some(parent) = path.parent() {
+                self.create_dir(&change.uri, parent)?;
+            }
+            let temp = sibling(&path, "tmp");
+            std::fs::write(&temp, after)
+                .map_err(|error| io_error(&change.uri, "write", &temp, error))?;
+            self.steps.push(Step::Staged(temp.clone()));
+            // The new text keeps the permissions of the file it replaces
+            if let Ok(metadata) = std::fs::metadata(&source_path) {
+                let _ = std::fs::set_permissions(&temp, metadata.permissions());
+            }
+            staged.push((&change.uri, temp, path));
+        }
+
+        for change in &plan.changes {
+            if let FileOperation::Rename { from } = &change.operation {
+                self.move_aside(from, &from.to_file_path()?)?;
+            }
+            self.move_aside(&change.uri, &change.uri.to_file_path()?)?;
+        }
+        for (uri, temp, path) in staged {
+            std::fs::rename(&temp, &path).map_err(|error| io_error(uri, "move", &temp, error))?;
+            self.steps.push(Step::Placed(path));
+        }
+        Ok(())
+    }
+
+    /// Creates a directory and its missing parents.
+    fn create_dir(&mut self, uri: &Uri, dir: &Path) -> Result<(), ContextEngineError> {
+        if dir.as_os_str().is_empty() || dir.is_dir() {
+            return Ok(());
+        }
+        if let Some(parent) = dir.parent() {
+            self.create_dir(uri, parent)?;
+        }
+        std::fs::create_dir(dir).map_err(|error| io_error(uri, "create", dir, error))?;
+        self.steps.push(Step::CreatedDir(dir.to_path_buf()));
+        Ok(())
+    }
+
+    /// Moves a file to a backup next to it, if it exists.
+    fn move_aside(&mut self, uri: &Uri, path: &Path) -> Result<(), ContextEngineError> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let backup = sibling(path, "bak");
+        std::fs::rename(path, &backup).map_err(|error| io_error(uri, "move", path, error))?;
+        self.steps.push(Step::MovedAside {
+            path: path.to_path_buf(),
+            backup,
+        });
+        Ok(())
+    }
+
+    /// Removes the backups.
+    fn commit(self) {
+        for step in self.steps {
+            if let Step::MovedAside { backup, .. } = step {
+                if let Err(error) = std::fs::remove_file(&backup) {
+                    warn!(path = %backup.display(), %error, "Failed to remove backup");
+                }
+            }
+        }
+    }
+
+    /// Undoes the steps in reverse order.
+    fn roll_back(self) {
+        for step in self.steps.into_iter().rev() {
+            let undone = match &step {
+                Step::CreatedDir(dir) => std::fs::remove_dir(dir),
+                Step::Staged(path) | Step::Placed(path) => match std::fs::remove_file(path) {
+                    // A staged file that was placed is gone already
+                    Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+                    result => result,
+                },
+                Step::MovedAside { path, backup } => std::fs::rename(backup, path),
+            };
+            if let Err(error) = undone {
+                warn!(%error, "Failed to undo a step of a workspace edit");
+            }
+        }
+    }
+}
+
+/// Returns a unique path in the directory of a file, for a temporary file
+/// or a backup of it.
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.{}.{extension}", Uuid::new_v4().simple()))
+}
+
+fn io_error(uri: &Uri, action: &str, path: &Path, error: std::io::Error) -> ContextEngineError {
+    edit_error(uri, format!("cannot {action} {}: {error}", path.display()))
+}
+
+#[cfg(test)]
+#[path = "tests/plan.rs"]
+mod tests;
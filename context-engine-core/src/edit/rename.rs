@@ -0,0 +1,301 @@
+//! Renaming symbols across the workspace.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use lsp_types::request::{PrepareRenameRequest, Rename, Request};
+use lsp_types::{
+    DocumentChangeOperation, DocumentChanges, Location, OneOf, Position, PositionEncodingKind,
+    PrepareRenameResponse, Range, RenameParams, TextDocumentEdit, TextDocumentIdentifier,
+    TextDocumentPositionParams, TextEdit, Uri, WorkspaceEdit,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::cache::content_hash;
+use crate::document::DocumentStore;
+use crate::edit::plan::{edit_error, read_file};
+use crate::edit::{EditPlan, EditPlanner, FileOperation};
+use crate::error::ContextEngineError;
+use crate::lsp::{LanguageRegistry, LspSupervisor};
+use crate::types::{LineIndex, UriExt};
+
+/// Computes renames of symbols with the language servers of their
+/// documents.
+///
+/// A rename is computed without changing any file, checked against the
+/// state of the workspace, and then applied to disk all at once or not at
+/// all.
+pub struct Renamer {
+    supervisor: Arc<LspSupervisor>,
+    registry: Arc<LanguageRegistry>,
+    documents: Arc<DocumentStore>,
+}
+
+impl Renamer {
+    /// Creates a renamer.
+    ///
+    /// # Arguments
+    ///
+    /// * `supervisor` - Runs the language servers that compute the renames
+    /// * `registry` - Routes documents to their language servers
+    /// * `documents` - The open documents, whose versions renames must match
+    pub fn new(
+        supervisor: Arc<LspSupervisor>,
+        registry: Arc<LanguageRegistry>,
+        documents: Arc<DocumentStore>,
+    ) -> Self {
+        Self {
+            supervisor,
+            registry,
+            documents,
+        }
+    }
+
+    /// Computes the rename of the symbol at a position.
+    ///
+    /// The first running server of the document that supports renames is
+    /// asked whether the position can be renamed, if it supports that, and
+    /// then for the edit.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The document the symbol is named in
+    /// * `position` - A position in the name of the symbol, in the position
+    ///   encoding of the document store
+    /// * `new_name` - The new name of the symbol
+    /// * `indexed` - The [content hash](content_hash) of every file by URI when
+    ///   the workspace was indexed; files the rename changes must still have
+    ///   it. Files that are not indexed are not checked.
+    ///
+    /// # Errors
+    ///
+    /// * [`ContextEngineError::Configuration`] - If no running server of the
+    ///   document supports renames
+    /// * [`ContextEngineError::Edit`] - If there is no symbol to rename at the
+    ///   position, the rename changes a file that changed since it was indexed,
+    ///   or the edit cannot be planned
+    /// * [`ContextEngineError::VersionConflict`] - If the edit does not match
+    ///   the version of an open document
+    /// * Any error of the server, e.g. for an invalid new name
+    pub async fn preview(
+        &self,
+        uri: &Uri,
+        position: Position,
+        new_name: &str,
+        indexed: &BTreeMap<String, u64>,
+    ) -> Result<RenamePreview, ContextEngineError> {
+        let text = match self.documents.get(uri) {
+            Some(document) => document.text().to_string(),
+            None => read_file(uri, &uri.to_file_path()?)?
+                .ok_or_else(|| edit_error(uri, "the file does not exist"))?,
+        };
+        let routes = self.registry.route(uri, Some(&text));
+        let Some(server) = routes
+            .iter()
+            .map(|route| route.server.name.as_str())
+            .find(|name| self.supervisor.supports(name, Rename::METHOD))
+        else {
+            return Err(ContextEngineError::Configuration {
+                key: None,
+                message: format!(
+                    "No running language server can rename symbols in {}",
+                    uri.as_str()
+                ),
+            });
+        };
+        let client = self.supervisor.client(server).await?;
+        let encoding = client.capabilities().position_encoding();
+        let index = LineIndex::new(&text);
+        let position = index.convert(position, self.documents.encoding(), &encoding)?;
+        let text_document_position =
+            TextDocumentPositionParams::new(TextDocumentIdentifier::new(uri.clone()), position);
+
+        let no_symbol = || {
+            edit_error(
+                uri,
+                format!(
+                    "there is no symbol to rename at line {}, character {}",
+                    position.line + 1,
+                    position.character + 1
+                ),
+            )
+        };
+        let mut old_name = None;
+        if client.supports(PrepareRenameRequest::METHOD) {
+            let prepared = self
+                .supervisor
+                .request::<PrepareRenameRequest>(server, text_document_position.clone())
+                .await?;
+            old_name = match prepared.ok_or_else(no_symbol)? {
+                PrepareRenameResponse::Range(range) => slice(&text, &index, range, &encoding),
+                PrepareRenameResponse::RangeWithPlaceholder { placeholder, .. } => {
+                    Some(placeholder)
+                }
+                PrepareRenameResponse::DefaultBehavior { .. } => None,
+            };
+        }
+        let params = RenameParams {
+            text_document_position,
+            new_name: new_name.to_string(),
+            work_done_progress_params: Default::default(),
+        };
+        let edit = self
+            .supervisor
+            .request::<Rename>(server, params)
+            .await?
+            .ok_or_else(no_symbol)?;
+
+        let plan = EditPlanner::new(&self.documents, encoding.clone()).plan(&edit)?;
+        for change in plan.changes() {
+            let source = match &change.operation {
+                FileOperation::Rename { from } => from,
+                _ => &change.uri,
+            };
+            let Some(hash) = indexed.get(source.as_str()) else {
+                continue;
+            };
+            let before = change.before.as_deref().map(str::as_bytes);
+            if before.map(content_hash) != Some(*hash) {
+                return Err(edit_error(
+                    source,
+                    "the file changed since it was indexed; index the workspace again",
+                ));
+            }
+        }
+        let locations = self.locations(&edit, &plan, &encoding);
+        Ok(RenamePreview {
+            server: server.to_string(),
+            old_name,
+            new_name: new_name.to_string(),
+            locations,
+            plan,
+        })
+    }
+
+    /// Returns the ranges of the text edits of a rename, in the position
+    /// encoding of the store and ordered by document.
+    ///
+    /// Ranges are converted in the text of the file before the rename; a
+    /// range that is not valid in it is kept as the server sent it.
+    fn locations(
+        &self,
+        edit: &WorkspaceEdit,
+        plan: &EditPlan,
+        encoding: &PositionEncodingKind,
+    ) -> Vec<Location> {
+        let mut indexes = BTreeMap::new();
+        let mut locations = text_edits(edit)
+            .into_iter()
+            .map(|(uri, edit)| {
+                let index = indexes.entry(uri.as_str().to_string()).or_insert_with(|| {
+                    plan.changes()
+                        .iter()
+                        .find(|change| match &change.operation {
+                            FileOperation::Rename { from } => from == uri,
+                            _ => change.uri == *uri,
+                        })
+                        .and_then(|change| change.before.as_deref())
+                        .map(LineIndex::new)
+                });
+                let store = self.documents.encoding();
+                let range = index
+                    .as_ref()
+                    .and_then(|index| {
+                        let start = index.convert(edit.range.start, encoding, store).ok()?;
+                        let end = index.convert(edit.range.end, encoding, store).ok()?;
+                        Some(Range::new(start, end))
+                    })
+                    .unwrap_or(edit.range);
+                Location::new(uri.clone(), range)
+            })
+            .collect::<Vec<_>>();
+        locations
+            .sort_by(|a, b| (a.uri.as_str(), a.range.start).cmp(&(b.uri.as_str(), b.range.start)));
+        locations.dedup();
+        locations
+    }
+}
+
+/// A rename of a symbol, computed but not applied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamePreview {
+    /// The language server that computed the rename
+    pub server: String,
+    /// The name of the symbol before the rename, if the server told it
+    pub old_name: Option<String>,
+    /// The name of the symbol after the rename
+    pub new_name: String,
+    /// The ranges of the text that is replaced, in the position encoding of
+    /// the document store, ordered by document
+    pub locations: Vec<Location>,
+    /// The effect of the rename on the files
+    pub plan: EditPlan,
+}
+
+impl RenamePreview {
+    /// Returns the ranges of the text that is replaced in one document.
+    pub fn locations_in<'a>(&'a self, uri: &'a Uri) -> impl Iterator<Item = &'a Location> {
+        self.locations
+            .iter()
+            .filter(move |location| location.uri == *uri)
+    }
+
+    /// Writes the rename to disk: all of its changes, or none.
+    ///
+    /// Files outside of the workspace root of the supervisor are never
+    /// written.
+    ///
+    /// # Errors
+    ///
+    /// See [`EditPlan::apply_to_disk`].
+    pub fn apply(&self, supervisor: &LspSupervisor) -> Result<(), ContextEngineError> {
+        self.plan.apply_to_disk(supervisor)
+    }
+}
+
+/// Returns the text of a range, if it is valid.
+fn slice(
+    text: &str,
+    index: &LineIndex,
+    range: Range,
+    encoding: &PositionEncodingKind,
+) -> Option<String> {
+    let start = index.offset(range.start, encoding).ok()?;
+    let end = index.offset(range.end, encoding).ok()?;
+    text.get(start..end).map(str::to_string)
+}
+
+/// Returns the text edits of a workspace edit with their documents.
+fn text_edits(edit: &WorkspaceEdit) -> Vec<(&Uri, &TextEdit)> {
+    fn document_edits(edit: &TextDocumentEdit) -> impl Iterator<Item = (&Uri, &TextEdit)> {
+        edit.edits.iter().map(move |text_edit| {
+            let text_edit = match text_edit {
+                OneOf::Left(text_edit) => text_edit,
+                OneOf::Right(annotated) => &annotated.text_edit,
+            };
+            (&edit.text_document.uri, text_edit)
+        })
+    }
+    match &edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => edits.iter().flat_map(document_edits).collect(),
+        Some(DocumentChanges::Operations(operations)) => operations
+            .iter()
+            .filter_map(|operation| match operation {
+                DocumentChangeOperation::Edit(edit) => Some(edit),
+                DocumentChangeOperation::Op(_) => None,
+            })
+            .flat_map(document_edits)
+            .collect(),
+        None => edit
+            .changes
+            .iter()
+            .flatten()
+            .flat_map(|(uri, edits)| edits.iter().map(move |edit| (uri, edit)))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/rename.rs"]
+mod tests;
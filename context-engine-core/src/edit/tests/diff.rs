@@ -0,0 +1,110 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+#[test]
+fn test_equal_texts_have_no_diff() {
+    assert_eq!(unified_diff("a", "b", "same\n", "same\n"), "");
+}
+
+#[test]
+fn test_separate_hunks() {
+    let old = (1..=20).map(|n| format!("{n}\n")).collect::<String>();
+    let new = old.replacen("2\n", "two\n", 1).replace("19\n", "");
+    assert_eq!(
+        unified_diff("a/n.txt", "b/n.txt", &old, &new),
+        "--- a/n.txt\n+++ b/n.txt\n@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n@@ -16,5 +16,4 @@\n \
+         16\n 17\n 18\n-19\n 20\n"
+    );
+}
+
+#[test]
+fn test_close_changes_share_a_hunk() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+    let new = "A\nb\nc\nd\ne\nf\ng\nH\n";
+    let diff = unified_diff("old", "new", old, new);
+    assert_eq!(diff.matches("@@ -").count(), 1);
+    assert!(diff.contains("@@ -1,8 +1,8 @@\n-a\n+A\n"));
+}
+
+#[test]
+fn test_created_and_deleted_texts() {
+    assert_eq!(
+        unified_diff("/dev/null", "b/new.rs", "", "fn f() {}\n"),
+        "--- /dev/null\n+++ b/new.rs\n@@ -0,0 +1 @@\n+fn f() {}\n"
+    );
+    assert_eq!(
+        unified_diff("a/old.rs", "/dev/null", "x\ny\n", ""),
+        "--- a/old.rs\n+++ /dev/null\n@@ -1,2 +0,0 @@\n-x\n-y\n"
+    );
+}
+
+#[test]
+fn test_missing_newline_at_end() {
+    assert_eq!(
+        unified_diff("a", "b", "x\ny", "x\ny\n"),
+        "--- a\n+++ b\n@@ -1,2 +1,2 @@\n x\n-y\n\\ No newline at end of file\n+y\n"
+    );
+}
+
+#[test]
+fn test_large_rewrite_is_one_replacement() {
+    let old = (0..1500).map(|n| format!("old {n}\n")).collect::<String>();
+    let new = (0..1500).map(|n| format!("new {n}\n")).collect::<String>();
+    let diff = unified_diff("a", "b", &old, &new);
+    assert!(diff.starts_with("--- a\n+++ b\n@@ -1,1500 +1,1500 @@\n-old 0\n"));
+    assert_eq!(
+        diff.lines().filter(|line| line.starts_with('-')).count(),
+        1501
+    );
+}
+
+/// The length of the longest common subsequence of two sequences.
+fn lcs(old: &[&str], new: &[&str]) -> usize {
+    let mut previous = vec![0; new.len() + 1];
+    for a in old {
+        let mut row = vec![0];
+        for (j, b) in new.iter().enumerate() {
+            let diagonal = previous.get(j).copied().unwrap_or(0);
+            let up = previous.get(j + 1).copied().unwrap_or(0);
+            let left = row.last().copied().unwrap_or(0);
+            row.push(if a == b { diagonal + 1 } else { up.max(left) });
+        }
+        previous = row;
+    }
+    previous.last().copied().unwrap_or(0)
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_edit_script_is_shortest_and_complete(
+        old in proptest::collection::vec("[abc]\n", 0..24),
+        new in proptest::collection::vec("[abc]\n", 0..24),
+    ) {
+        let old = old.iter().map(String::as_str).collect::<Vec<_>>();
+        let new = new.iter().map(String::as_str).collect::<Vec<_>>();
+        let script = edit_script(&old, &new);
+
+        let mut old_lines = Vec::new();
+        let mut new_lines = Vec::new();
+        let mut equal = 0;
+        for line in &script {
+            match *line {
+                Line::Equal(a, b) => {
+                    prop_assert_eq!(old.get(a), new.get(b));
+                    old_lines.push(a);
+                    new_lines.push(b);
+                    equal += 1;
+                }
+                Line::Delete(a) => old_lines.push(a),
+                Line::Insert(b) => new_lines.push(b),
+            }
+        }
+        prop_assert_eq!(old_lines, (0..old.len()).collect::<Vec<_>>());
+        prop_assert_eq!(new_lines, (0..new.len()).collect::<Vec<_>>());
+        prop_assert_eq!(equal, lcs(&old, &new));
+    }
+}
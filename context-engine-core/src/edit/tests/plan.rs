@@ -0,0 +1,502 @@
+#![allow(clippy::unwrap_used)]
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use lsp_types::{
+    CreateFileOptions, DeleteFileOptions, OptionalVersionedTextDocumentIdentifier, Position, Range,
+};
+use proptest::prelude::*;
+
+use super::*;
+
+fn uri(path: &str) -> Uri {
+    Uri::from_str(&format!("file://{path}")).unwrap()
+}
+
+fn replace(line: u32, start: u32, end: u32, text: &str) -> TextEdit {
+    TextEdit::new(
+        Range::new(Position::new(line, start), Position::new(line, end)),
+        text.to_string(),
+    )
+}
+
+fn document_edit(uri: &Uri, version: Option<i32>, edits: Vec<TextEdit>) -> TextDocumentEdit {
+    TextDocumentEdit {
+        text_document: OptionalVersionedTextDocumentIdentifier {
+            uri: uri.clone(),
+            version,
+        },
+        edits: edits.into_iter().map(OneOf::Left).collect(),
+    }
+}
+
+fn operations(operations: Vec<DocumentChangeOperation>) -> WorkspaceEdit {
+    WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Operations(operations)),
+        ..WorkspaceEdit::default()
+    }
+}
+
+fn create(uri: &Uri, options: Option<CreateFileOptions>) -> DocumentChangeOperation {
+    DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+        uri: uri.clone(),
+        options,
+        annotation_id: None,
+    }))
+}
+
+fn rename(old_uri: &Uri, new_uri: &Uri) -> DocumentChangeOperation {
+    DocumentChangeOperation::Op(ResourceOp::Rename(RenameFile {
+        old_uri: old_uri.clone(),
+        new_uri: new_uri.clone(),
+        options: None,
+        annotation_id: None,
+    }))
+}
+
+fn delete(uri: &Uri, options: Option<DeleteFileOptions>) -> DocumentChangeOperation {
+    DocumentChangeOperation::Op(ResourceOp::Delete(DeleteFile {
+        uri: uri.clone(),
+        options,
+    }))
+}
+
+/// A supervisor of no servers in a workspace, over an empty store.
+fn supervisor(root: &std::path::Path) -> LspSupervisor {
+    LspSupervisor::new(root, std::sync::Arc::new(DocumentStore::default()))
+}
+
+fn open(documents: &DocumentStore, uri: &Uri, version: i32, text: &str) {
+    documents
+        .open(TextDocumentItem::new(
+            uri.clone(),
+            "rust".to_string(),
+            version,
+            text.to_string(),
+        ))
+        .unwrap();
+}
+
+#[test]
+fn test_edits_of_one_file() {
+    let lib = uri("/app/src/lib.rs");
+    let documents = DocumentStore::default();
+    open(&documents, &lib, 3, "let a = 1;\nlet b = a;\n");
+    let edit = WorkspaceEdit::new(HashMap::from([(
+        lib.clone(),
+        vec![replace(1, 8, 9, "first"), replace(0, 4, 5, "first")],
+    )]));
+
+    let plan = EditPlanner::new(&documents, PositionEncodingKind::UTF16)
+        .plan(&edit)
+        .unwrap();
+    assert_eq!(
+        plan.changes(),
+        [FileChange {
+            uri: lib.clone(),
+            operation: FileOperation::Modify,
+            before: Some("let a = 1;\nlet b = a;\n".to_string()),
+            after: Some("let first = 1;\nlet b = first;\n".to_string()),
+        }]
+    );
+    let expected = concat!(
+        "--- /app/src/lib.rs\n+++ /app/src/lib.rs\n@@ -1,2 +1,2 @@\n",
+        "-let a = 1;\n-let b = a;\n+let first = 1;\n+let b = first;\n",
+    );
+    assert_eq!(plan.diff(), expected);
+    // Planning changes nothing
+    assert_eq!(documents.get(&lib).unwrap().version(), 3);
+}
+
+#[test]
+fn test_edits_in_the_server_encoding() {
+    let lib = uri("/app/src/lib.rs");
+    let documents = DocumentStore::default();
+    let edit = WorkspaceEdit::new(HashMap::from([(lib.clone(), vec![replace(0, 8, 9, "b")])]));
+    // "é" is one UTF-16 unit but two UTF-8 bytes
+    let plan = EditPlanner::new(&documents, PositionEncodingKind::UTF8)
+        .with_content(lib.clone(), "\"é\"; let a;")
+        .plan(&edit)
+        .unwrap();
+    assert_eq!(
+        plan.changes().first().unwrap().after.as_deref(),
+        Some("\"é\"; leb a;")
+    );
+}
+
+#[test]
+fn test_version_conflict() {
+    let lib = uri("/app/src/lib.rs");
+    let documents = DocumentStore::default();
+    open(&documents, &lib, 4, "let a = 1;\n");
+    let edit = WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Edits(vec![document_edit(
+            &lib,
+            Some(3),
+            vec![replace(0, 4, 5, "b")],
+        )])),
+        ..WorkspaceEdit::default()
+    };
+
+    let planner = EditPlanner::new(&documents, PositionEncodingKind::UTF16);
+    let error = planner.plan(&edit).unwrap_err();
+    assert!(matches!(
+        error,
+        ContextEngineError::VersionConflict {
+            current: 4,
+            received: 3,
+            ..
+        }
+    ));
+
+    // Content computed outside the store is not versioned
+    let plan = planner
+        .with_content(lib.clone(), "let a = 2;\n")
+        .plan(&edit)
+        .unwrap();
+    assert_eq!(
+        plan.changes().first().unwrap().after.as_deref(),
+        Some("let b = 2;\n")
+    );
+}
+
+#[test]
+fn test_overlapping_edits() {
+    let lib = uri("/app/src/lib.rs");
+    let documents = DocumentStore::default();
+    let edit = WorkspaceEdit::new(HashMap::from([(
+        lib.clone(),
+        vec![replace(0, 0, 5, "x"), replace(0, 3, 8, "y")],
+    )]));
+    let error = EditPlanner::new(&documents, PositionEncodingKind::UTF16)
+        .with_content(lib, "let a = 1;\n")
+        .plan(&edit)
+        .unwrap_err();
+    assert!(error.to_string().contains("text edits overlap"));
+}
+
+#[test]
+fn test_resource_operations() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = |name: &str| Uri::from_file_path(&dir.path().join(name)).unwrap();
+    let (old, moved, new, gone) = (
+        file("old.rs"),
+        file("sub/moved.rs"),
+        file("new.rs"),
+        file("gone.rs"),
+    );
+    std::fs::write(dir.path().join("old.rs"), "fn old() {}\n").unwrap();
+    std::fs::write(dir.path().join("gone.rs"), "fn gone() {}\n").unwrap();
+
+    let edit = operations(vec![
+        create(&new, None),
+        DocumentChangeOperation::Edit(document_edit(
+            &new,
+            None,
+            vec![replace(0, 0, 0, "fn new() {}\n")],
+        )),
+        rename(&old, &moved),
+        DocumentChangeOperation::Edit(document_edit(&moved, None, vec![replace(0, 3, 6, "moved")])),
+        delete(&gone, None),
+    ]);
+    let supervisor = supervisor(dir.path());
+    let documents = supervisor.documents();
+    open(documents, &old, 1, "fn old() {}\n");
+    let plan = EditPlanner::new(documents, PositionEncodingKind::UTF16)
+        .plan(&edit)
+        .unwrap();
+    let operations = plan
+        .changes()
+        .iter()
+        .map(|change| change.operation.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        operations,
+        [
+            FileOperation::Create,
+            FileOperation::Rename { from: old.clone() },
+            FileOperation::Delete,
+        ]
+    );
+    let diff = plan.changes().get(1).unwrap().diff();
+    assert!(diff.contains("-fn old() {}\n+fn moved() {}\n"));
+
+    plan.apply_to_disk(&supervisor).unwrap();
+    let read = |name: &str| std::fs::read_to_string(dir.path().join(name)).ok();
+    assert_eq!(read("new.rs").as_deref(), Some("fn new() {}\n"));
+    assert_eq!(read("sub/moved.rs").as_deref(), Some("fn moved() {}\n"));
+    assert_eq!(read("old.rs"), None);
+    assert_eq!(read("gone.rs"), None);
+    // The open document moved with its file; no other file was opened
+    assert!(!documents.is_open(&old));
+    assert_eq!(documents.get(&moved).unwrap().text(), "fn moved() {}\n");
+    assert_eq!(documents.len(), 1);
+}
+
+/// The names of the entries of a directory, sorted.
+fn entries(dir: &std::path::Path) -> Vec<String> {
+    let mut names = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_apply_to_disk_writes_all_files_or_none() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("lib.rs"), "mod a;\n").unwrap();
+    std::fs::write(dir.path().join("gone.rs"), "fn gone() {}\n").unwrap();
+    let file = |name: &str| Uri::from_file_path(&dir.path().join(name)).unwrap();
+
+    let edit = operations(vec![
+        DocumentChangeOperation::Edit(document_edit(
+            &file("lib.rs"),
+            None,
+            vec![replace(0, 4, 5, "b")],
+        )),
+        delete(&file("gone.rs"), None),
+        create(&file("new/deep/new.rs"), None),
+        create(&file("blocked/new.rs"), None),
+    ]);
+    let supervisor = supervisor(dir.path());
+    let plan = EditPlanner::new(supervisor.documents(), PositionEncodingKind::UTF16)
+        .plan(&edit)
+        .unwrap();
+    // A file where the directory of the last new file would be
+    std::fs::write(dir.path().join("blocked"), "").unwrap();
+    let error = plan.apply_to_disk(&supervisor).unwrap_err();
+    assert!(matches!(error, ContextEngineError::Edit { .. }));
+
+    assert_eq!(entries(dir.path()), ["blocked", "gone.rs", "lib.rs"]);
+    let read = |name: &str| std::fs::read_to_string(dir.path().join(name)).unwrap();
+    assert_eq!(read("lib.rs"), "mod a;\n");
+    assert_eq!(read("gone.rs"), "fn gone() {}\n");
+}
+
+#[test]
+fn test_apply_to_disk_fails_if_a_file_changed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lib.rs");
+    std::fs::write(&path, "mod a;\n").unwrap();
+    let lib = Uri::from_file_path(&path).unwrap();
+    let edit = WorkspaceEdit::new(HashMap::from([(lib, vec![replace(0, 4, 5, "b")])]));
+    let supervisor = supervisor(dir.path());
+    let plan = EditPlanner::new(supervisor.documents(), PositionEncodingKind::UTF16)
+        .plan(&edit)
+        .unwrap();
+
+    std::fs::write(&path, "mod c;\n").unwrap();
+    let error = plan.apply_to_disk(&supervisor).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("changed since the edit was planned")
+    );
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "mod c;\n");
+    assert_eq!(entries(dir.path()), ["lib.rs"]);
+}
+
+#[test]
+fn test_create_existing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("lib.rs"), "fn f() {}\n").unwrap();
+    let lib = Uri::from_file_path(&dir.path().join("lib.rs")).unwrap();
+    let documents = DocumentStore::default();
+    let planner = EditPlanner::new(&documents, PositionEncodingKind::UTF16);
+
+    let error = planner
+        .plan(&operations(vec![create(&lib, None)]))
+        .unwrap_err();
+    assert!(error.to_string().contains("the file to create exists"));
+
+    let ignore = CreateFileOptions {
+        overwrite: None,
+        ignore_if_exists: Some(true),
+    };
+    let plan = planner
+        .plan(&operations(vec![create(&lib, Some(ignore))]))
+        .unwrap();
+    assert!(plan.is_empty());
+
+    let overwrite = CreateFileOptions {
+        overwrite: Some(true),
+        ignore_if_exists: None,
+    };
+    let plan = planner
+        .plan(&operations(vec![create(&lib, Some(overwrite))]))
+        .unwrap();
+    assert_eq!(plan.changes().first().unwrap().after.as_deref(), Some(""));
+}
+
+#[test]
+fn test_missing_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = Uri::from_file_path(&dir.path().join("missing.rs")).unwrap();
+    let documents = DocumentStore::default();
+    let planner = EditPlanner::new(&documents, PositionEncodingKind::UTF16);
+
+    let error = planner
+        .plan(&operations(vec![delete(&missing, None)]))
+        .unwrap_err();
+    assert!(matches!(error, ContextEngineError::Edit { .. }));
+    let ignore = DeleteFileOptions {
+        recursive: None,
+        ignore_if_not_exists: Some(true),
+        annotation_id: None,
+    };
+    let plan = planner
+        .plan(&operations(vec![delete(&missing, Some(ignore))]))
+        .unwrap();
+    assert!(plan.is_empty());
+
+    let edit = WorkspaceEdit::new(HashMap::from([(missing, vec![replace(0, 0, 0, "x")])]));
+    let error = planner.plan(&edit).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("the file to edit does not exist")
+    );
+}
+
+#[test]
+fn test_apply_to_store() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = |name: &str| Uri::from_file_path(&dir.path().join(name)).unwrap();
+    let (lib, new, old, moved) = (
+        file("lib.rs"),
+        file("new.rs"),
+        file("old.rs"),
+        file("moved.rs"),
+    );
+    let supervisor = supervisor(dir.path());
+    let documents = supervisor.documents();
+    open(documents, &lib, 2, "mod old;\n");
+    open(documents, &old, 1, "fn old() {}\n");
+    let edit = operations(vec![
+        DocumentChangeOperation::Edit(document_edit(
+            &lib,
+            Some(2),
+            vec![replace(0, 4, 7, "moved")],
+        )),
+        create(&new, None),
+        rename(&old, &moved),
+    ]);
+
+    let plan = EditPlanner::new(documents, PositionEncodingKind::UTF16)
+        .plan(&edit)
+        .unwrap();
+    plan.apply_to_store(&supervisor, &LanguageRegistry::with_defaults())
+        .unwrap();
+
+    let lib = documents.get(&lib).unwrap();
+    assert_eq!((lib.version(), lib.text()), (3, "mod moved;\n"));
+    let new = documents.get(&new).unwrap();
+    assert_eq!(
+        (new.version(), new.text(), new.language_id()),
+        (1, "", "rust")
+    );
+    assert!(!documents.is_open(&old));
+    assert_eq!(documents.get(&moved).unwrap().text(), "fn old() {}\n");
+    // Nothing was written
+    assert_eq!(entries(dir.path()), Vec::<String>::new());
+}
+
+#[test]
+fn test_files_outside_of_the_workspace_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("workspace");
+    std::fs::create_dir(&root).unwrap();
+    std::fs::write(root.join("lib.rs"), "mod a;\n").unwrap();
+    std::fs::write(dir.path().join("secret.rs"), "const KEY: u8 = 1;\n").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(dir.path(), root.join("link")).unwrap();
+    let file = |path: &std::path::Path| Uri::from_file_path(path).unwrap();
+    let supervisor = supervisor(&root);
+    let registry = LanguageRegistry::with_defaults();
+
+    let mut outside = vec![
+        operations(vec![create(&file(&dir.path().join("new.rs")), None)]),
+        operations(vec![create(&file(&root.join("../new.rs")), None)]),
+        operations(vec![rename(
+            &file(&root.join("lib.rs")),
+            &file(&dir.path().join("lib.rs")),
+        )]),
+        operations(vec![rename(
+            &file(&dir.path().join("secret.rs")),
+            &file(&root.join("secret.rs")),
+        )]),
+    ];
+    #[cfg(unix)]
+    outside.push(operations(vec![delete(
+        &file(&root.join("link/secret.rs")),
+        None,
+    )]));
+    for edit in outside {
+        let plan = EditPlanner::new(supervisor.documents(), PositionEncodingKind::UTF16)
+            .plan(&edit)
+            .unwrap();
+        let error = plan.check_within(&root).unwrap_err();
+        assert!(error.to_string().contains("outside of the workspace"));
+        assert!(plan.apply_to_disk(&supervisor).is_err());
+        assert!(plan.apply_to_store(&supervisor, &registry).is_err());
+    }
+    assert!(supervisor.documents().is_empty());
+    assert_eq!(entries(dir.path()), ["secret.rs", "workspace"]);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("secret.rs")).unwrap(),
+        "const KEY: u8 = 1;\n"
+    );
+
+    // New files in new directories inside of the workspace are fine
+    let edit = operations(vec![create(&file(&root.join("new/deep/new.rs")), None)]);
+    let plan = EditPlanner::new(supervisor.documents(), PositionEncodingKind::UTF16)
+        .plan(&edit)
+        .unwrap();
+    plan.check_within(&root).unwrap();
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_insertions_keep_their_order(
+        text in "[a-z\n]{0,40}",
+        inserts in proptest::collection::vec((0usize..=40, "[A-Z]{1,3}"), 0..6),
+    ) {
+        let lib = uri("/app/src/lib.rs");
+        let index = LineIndex::new(&text);
+        let mut offsets = inserts
+            .iter()
+            .map(|(offset, inserted)| ((*offset).min(text.len()), inserted.as_str()))
+            .collect::<Vec<_>>();
+        let edits = offsets
+            .iter()
+            .map(|(offset, inserted)| {
+                let position = index.position(*offset, &PositionEncodingKind::UTF16).unwrap();
+                TextEdit::new(Range::new(position, position), inserted.to_string())
+            })
+            .collect::<Vec<_>>();
+        let edit = WorkspaceEdit::new(HashMap::from([(lib.clone(), edits)]));
+
+        let documents = DocumentStore::default();
+        let plan = EditPlanner::new(&documents, PositionEncodingKind::UTF16)
+            .with_content(lib, text.clone())
+            .plan(&edit)
+            .unwrap();
+
+        // Insertions at the same offset keep the order of the edit
+        offsets.sort_by_key(|(offset, _)| *offset);
+        let mut expected = String::new();
+        let mut end = 0;
+        for (offset, inserted) in offsets {
+            expected.push_str(text.get(end..offset).unwrap());
+            expected.push_str(inserted);
+            end = offset;
+        }
+        expected.push_str(text.get(end..).unwrap());
+        let after = plan.changes().first().and_then(|change| change.after.clone());
+        prop_assert_eq!(after.unwrap_or_else(|| text.clone()), expected);
+    }
+}
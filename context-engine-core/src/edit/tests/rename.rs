@@ -0,0 +1,284 @@
+#![allow(clippy::unwrap_used)]
+
+use std::path::Path;
+use std::str::FromStr;
+
+use lsp_types::TextDocumentItem;
+use proptest::prelude::*;
+use serde_json::{Value, json};
+
+use super::*;
+use crate::JsonRpcError;
+use crate::lsp::testing::{self, FakeLauncher, FakeServer, FakeSession};
+
+/// A server with UTF-8 positions that renames a word in every `.rs` file
+/// of the folder of the document, reading the files from disk.
+struct RenameServer;
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The byte range of the word at a UTF-8 position of a file.
+fn word_at(path: &Path, position: Position) -> Option<(String, Range)> {
+    let text = std::fs::read_to_string(path).unwrap();
+    let line = text.lines().nth(position.line as usize)?;
+    let at = position.character as usize;
+    let start = line.get(..at)?.rfind(|c| !is_word(c)).map_or(0, |i| i + 1);
+    let end = line
+        .get(at..)?
+        .find(|c| !is_word(c))
+        .map_or(line.len(), |i| at + i);
+    let word = line.get(start..end).filter(|word| !word.is_empty())?;
+    let range = Range::new(
+        Position::new(position.line, start as u32),
+        Position::new(position.line, end as u32),
+    );
+    Some((word.to_string(), range))
+}
+
+/// The workspace edit replacing every occurrence of a word in the `.rs`
+/// files of a folder.
+fn rename(folder: &Path, word: &str, new_name: &str) -> Value {
+    let mut changes = serde_json::Map::new();
+    for entry in std::fs::read_dir(folder).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("rs") {
+            continue;
+        }
+        let text = std::fs::read_to_string(&path).unwrap();
+        let edits = text
+            .lines()
+            .enumerate()
+            .flat_map(|(line, text)| {
+                text.match_indices(word).map(move |(at, _)| {
+                    TextEdit::new(
+                        Range::new(
+                            Position::new(line as u32, at as u32),
+                            Position::new(line as u32, (at + word.len()) as u32),
+                        ),
+                        new_name.to_string(),
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        if !edits.is_empty() {
+            let uri = Uri::from_file_path(&path).unwrap();
+            changes.insert(uri.as_str().to_string(), json!(edits));
+        }
+    }
+    json!({ "changes": changes })
+}
+
+impl FakeServer for RenameServer {
+    fn initialize(&self) -> Value {
+        json!({ "capabilities": {
+            "positionEncoding": "utf-8",
+            "renameProvider": { "prepareProvider": true },
+        } })
+    }
+
+    async fn request(
+        &self,
+        _session: &FakeSession,
+        method: &str,
+        params: Value,
+    ) -> Option<Result<Value, JsonRpcError>> {
+        let result = match method {
+            "textDocument/prepareRename" => {
+                let params: TextDocumentPositionParams = serde_json::from_value(params).unwrap();
+                let path = params.text_document.uri.to_file_path().unwrap();
+                let result = word_at(&path, params.position)
+                    .map(|(word, range)| json!({ "range": range, "placeholder": word }));
+                Ok(json!(result))
+            }
+            "textDocument/rename" => {
+                let params: RenameParams = serde_json::from_value(params).unwrap();
+                let position = params.text_document_position;
+                let path = position.text_document.uri.to_file_path().unwrap();
+                match word_at(&path, position.position) {
+                    _ if !params.new_name.chars().all(is_word) => Err(JsonRpcError::new(
+                        -32602,
+                        "the new name is not an identifier",
+                    )),
+                    Some((word, _)) => Ok(rename(path.parent().unwrap(), &word, &params.new_name)),
+                    None => Ok(Value::Null),
+                }
+            }
+            _ => Ok(Value::Null),
+        };
+        Some(result)
+    }
+}
+
+/// Starts rust-analyzer on the fake server and returns a renamer over it.
+async fn start(root: &Path, documents: Arc<DocumentStore>) -> Renamer {
+    let registry = Arc::new(LanguageRegistry::with_defaults());
+    let config = registry.server("rust-analyzer").unwrap().clone();
+    let launcher = FakeLauncher::new(RenameServer);
+    let supervisor = testing::start(root, Arc::clone(&documents), launcher, config)
+        .await
+        .unwrap();
+    Renamer::new(supervisor, registry, documents)
+}
+
+/// Writes the files of a workspace and returns their URIs.
+fn workspace(dir: &Path, files: &[(&str, &str)]) -> Vec<Uri> {
+    files
+        .iter()
+        .map(|(name, text)| {
+            let path = dir.join(name);
+            std::fs::write(&path, text).unwrap();
+            Uri::from_file_path(&path).unwrap()
+        })
+        .collect()
+}
+
+/// The content hashes of files as `workspace.index` records them.
+fn indexed(files: &[(&Uri, &str)]) -> BTreeMap<String, u64> {
+    files
+        .iter()
+        .map(|(uri, text)| (uri.as_str().to_string(), content_hash(text.as_bytes())))
+        .collect()
+}
+
+const LIB: &str = "pub fn greet() {}\n";
+const MAIN: &str = "// größe\nfn main() { /* ä */ greet(); greet(); }\n";
+
+#[tokio::test]
+async fn test_preview_and_apply() {
+    let dir = tempfile::tempdir().unwrap();
+    let uris = workspace(dir.path(), &[("lib.rs", LIB), ("main.rs", MAIN)]);
+    let (lib, main) = (uris.first().unwrap(), uris.get(1).unwrap());
+    let documents = Arc::new(DocumentStore::default());
+    let renamer = start(dir.path(), Arc::clone(&documents)).await;
+
+    let indexed = indexed(&[(lib, LIB), (main, MAIN)]);
+    let preview = renamer
+        .preview(main, Position::new(1, 22), "welcome", &indexed)
+        .await
+        .unwrap();
+    assert_eq!(preview.server, "rust-analyzer");
+    assert_eq!(preview.old_name.as_deref(), Some("greet"));
+    assert_eq!(preview.new_name, "welcome");
+    // The UTF-8 ranges of the server are converted to UTF-16
+    let ranges = preview
+        .locations_in(main)
+        .map(|location| location.range)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ranges,
+        [
+            Range::new(Position::new(1, 20), Position::new(1, 25)),
+            Range::new(Position::new(1, 29), Position::new(1, 34)),
+        ]
+    );
+    assert_eq!(preview.locations_in(lib).count(), 1);
+    assert_eq!(preview.plan.changes().len(), 2);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("lib.rs")).unwrap(),
+        LIB
+    );
+
+    preview.apply(&renamer.supervisor).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("lib.rs")).unwrap(),
+        "pub fn welcome() {}\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("main.rs")).unwrap(),
+        "// größe\nfn main() { /* ä */ welcome(); welcome(); }\n"
+    );
+}
+
+#[tokio::test]
+async fn test_preview_of_an_open_document() {
+    let dir = tempfile::tempdir().unwrap();
+    let uris = workspace(dir.path(), &[("lib.rs", LIB)]);
+    let lib = uris.first().unwrap();
+    let documents = Arc::new(DocumentStore::default());
+    documents
+        .open(TextDocumentItem::new(
+            lib.clone(),
+            "rust".to_string(),
+            3,
+            LIB.to_string(),
+        ))
+        .unwrap();
+    let renamer = start(dir.path(), Arc::clone(&documents)).await;
+
+    let preview = renamer
+        .preview(lib, Position::new(0, 8), "welcome", &BTreeMap::new())
+        .await
+        .unwrap();
+    preview.apply(&renamer.supervisor).unwrap();
+    let document = documents.get(lib).unwrap();
+    assert_eq!(document.text(), "pub fn welcome() {}\n");
+    assert_eq!(document.version(), 4);
+}
+
+#[tokio::test]
+async fn test_files_changed_since_indexing_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let uris = workspace(dir.path(), &[("lib.rs", LIB), ("main.rs", MAIN)]);
+    let (lib, main) = (uris.first().unwrap(), uris.get(1).unwrap());
+    let renamer = start(dir.path(), Arc::default()).await;
+
+    let indexed = indexed(&[(lib, LIB), (main, "fn main() { greet(); }\n")]);
+    let error = renamer
+        .preview(lib, Position::new(0, 8), "welcome", &indexed)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        &error,
+        ContextEngineError::Edit { uri: Some(uri), message }
+            if uri == main && message.contains("index the workspace again")
+    ));
+}
+
+#[tokio::test]
+async fn test_nothing_to_rename() {
+    let dir = tempfile::tempdir().unwrap();
+    let uris = workspace(dir.path(), &[("lib.rs", LIB)]);
+    let lib = uris.first().unwrap();
+    let renamer = start(dir.path(), Arc::default()).await;
+
+    let error = renamer
+        .preview(lib, Position::new(1, 0), "welcome", &BTreeMap::new())
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ContextEngineError::Edit { .. }));
+    assert!(error.to_string().contains("no symbol to rename at line 2"));
+
+    // Invalid names are refused by the server
+    let error = renamer
+        .preview(lib, Position::new(0, 8), "not a name", &BTreeMap::new())
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("not an identifier"));
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_text_edits_keep_their_documents(lines in 0u32..20, files in 1usize..4) {
+        let workspace_edit = WorkspaceEdit::new((0..files)
+            .map(|file| {
+                let uri = Uri::from_str(&format!("file:///app/{file}.rs")).unwrap();
+                let edits = (0..lines)
+                    .map(|line| TextEdit::new(
+                        Range::new(Position::new(line, 0), Position::new(line, 1)),
+                        "x".to_string(),
+                    ))
+                    .collect();
+                (uri, edits)
+            })
+            .collect());
+        let edits = text_edits(&workspace_edit);
+        prop_assert_eq!(edits.len(), files * lines as usize);
+        for (uri, edit) in edits {
+            let changes = workspace_edit.changes.as_ref().and_then(|changes| changes.get(uri));
+            prop_assert!(changes.unwrap().contains(edit));
+        }
+    }
+}
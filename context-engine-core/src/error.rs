@@ -1 +1,379 @@
+//! Error types of the Context Engine.
+//!
+//! This module defines [`ContextEngineError`], the top-level error returned by
+//! the components of the Context Engine. Every variant carries structured
+//! context (server name, document URI, request method) and maps to a stable
+//! [`ErrorCode`], so that AI agents can branch on the kind of failure instead
+//! of parsing messages. Errors convert into [`JsonRpcError`] objects, which is
+//! the error shape of both MCP and LSP responses.
 
+use std::fmt;
+
+use lsp_types::Uri;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::types::LocationError;
+
+/// Stable error codes of [`ContextEngineError`]s.
+///
+/// The codes follow the JSON-RPC 2.0 conventions: codes from the reserved
+/// range are used where the protocols define a matching error, all other
+/// codes are taken from the range `-32099..=-32000` reserved for
+/// implementation-defined server errors. Codes never change once released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The request parameters are invalid (JSON-RPC `InvalidParams`)
+    InvalidParams,
+    /// Communication with a language server failed
+    Transport,
+    /// A language server process exited unexpectedly
+    ServerCrashed,
+    /// A message or document could not be parsed
+    Parse,
+    /// The persistent cache is unreadable or inconsistent
+    CacheCorrupted,
+    /// The configuration is missing or invalid
+    Configuration,
+    /// A request did not complete in time
+    Timeout,
+    /// A request was cancelled (LSP `RequestCancelled`)
+    Cancelled,
+    /// A document is not open, or is already open
+    DocumentState,
+    /// A document changed concurrently (LSP `ContentModified`)
+    ContentModified,
+    /// A language server answered a request with an error
+    ServerError,
+    /// A workspace edit could not be applied
+    Edit,
+}
+
+impl ErrorCode {
+    /// Returns the numeric JSON-RPC error code.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::ErrorCode;
+    ///
+    /// assert_eq!(ErrorCode::InvalidParams.as_i64(), -32602);
+    /// assert_eq!(ErrorCode::Cancelled.as_i64(), -32800);
+    /// assert_eq!(ErrorCode::Transport.as_i64(), -32001);
+    /// ```
+    pub fn as_i64(self) -> i64 {
+        match self {
+            ErrorCode::InvalidParams => -32602,
+            ErrorCode::Transport => -32001,
+            ErrorCode::ServerCrashed => -32002,
+            ErrorCode::Parse => -32003,
+            ErrorCode::CacheCorrupted => -32004,
+            ErrorCode::Configuration => -32005,
+            ErrorCode::Timeout => -32006,
+            ErrorCode::DocumentState => -32007,
+            ErrorCode::ServerError => -32008,
+            ErrorCode::Edit => -32009,
+            ErrorCode::Cancelled => -32800,
+            ErrorCode::ContentModified => -32801,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_i64())
+    }
+}
+
+/// A JSON-RPC 2.0 error object.
+///
+/// This is the `error` member of a JSON-RPC response, as used by both the
+/// Model Context Protocol and the Language Server Protocol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonRpcError {
+    /// The numeric error code
+    pub code: i64,
+    /// A short description of the error
+    pub message: String,
+    /// Additional structured information about the error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl JsonRpcError {
+    /// Creates a new error object without additional data.
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl fmt::Display for JsonRpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)
+    }
+}
+
+/// Errors that can occur in the Context Engine.
+///
+/// The error serializes as an object whose `kind` field names the variant,
+/// alongside the variant's context fields.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::{ContextEngineError, ErrorCode};
+///
+/// let error = ContextEngineError::Timeout {
+///     server: "rust-analyzer".to_string(),
+///     method: "textDocument/references".to_string(),
+///     timeout_ms: 30_000,
+/// };
+/// assert_eq!(error.code(), ErrorCode::Timeout);
+///
+/// let json_rpc = error.to_json_rpc_error();
+/// assert_eq!(json_rpc.code, -32006);
+/// assert_eq!(json_rpc.data.unwrap()["kind"], "timeout");
+/// ```
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ContextEngineError {
+    /// Error that occurs when a message cannot be exchanged with a language
+    /// server, e.g. because its pipes are closed or a frame is malformed
+    #[error("Transport error with language server '{server}': {message}")]
+    Transport {
+        /// Name of the language server
+        server: String,
+        /// The request method being processed, if any
+        method: Option<String>,
+        /// Description of the failure
+        message: String,
+    },
+
+    /// Error that occurs when a language server process exits unexpectedly
+    #[error("Language server '{server}' crashed (exit code {exit_code:?}): {message}")]
+    ServerCrashed {
+        /// Name of the language server
+        server: String,
+        /// Exit code of the process, if it exited normally
+        exit_code: Option<i32>,
+        /// Description of the failure
+        message: String,
+    },
+
+    /// Error that occurs when a message or a document cannot be parsed
+    #[error("Parse error: {message}")]
+    Parse {
+        /// The document that failed to parse, if any
+        uri: Option<Uri>,
+        /// Description of the failure
+        message: String,
+    },
+
+    /// Error that occurs when the persistent cache cannot be read or is
+    /// inconsistent
+    #[error("Cache corrupted at {path}: {message}")]
+    CacheCorrupted {
+        /// Path of the corrupted cache entry
+        path: String,
+        /// Description of the corruption
+        message: String,
+    },
+
+    /// Error that occurs when the configuration is missing or invalid
+    #[error("Configuration error: {message}")]
+    Configuration {
+        /// The configuration key that is invalid, if known
+        key: Option<String>,
+        /// Description of the problem
+        message: String,
+    },
+
+    /// Error that occurs when a request to a language server does not complete
+    /// in time
+    #[error("Request '{method}' to language server '{server}' timed out after {timeout_ms} ms")]
+    Timeout {
+        /// Name of the language server
+        server: String,
+        /// The request method that timed out
+        method: String,
+        /// The timeout in milliseconds
+        timeout_ms: u64,
+    },
+
+    /// Error that occurs when a language server answers a request with an
+    /// error response
+    #[error("Language server '{server}' failed request '{method}': {message} (code {code})")]
+    ServerError {
+        /// Name of the language server
+        server: String,
+        /// The request method that failed
+        method: String,
+        /// The JSON-RPC error code returned by the server
+        code: i64,
+        /// The error message returned by the server
+        message: String,
+    },
+
+    /// Error that occurs when a request is cancelled before it completes
+    #[error("Request '{method}' was cancelled")]
+    Cancelled {
+        /// The request method that was cancelled
+        method: String,
+    },
+
+    /// Error that occurs when a document that is not open is accessed
+    #[error("Document is not open: {}", uri.as_str())]
+    DocumentNotOpen {
+        /// The URI of the document
+        uri: Uri,
+    },
+
+    /// Error that occurs when a document that is already open is opened again
+    #[error("Document is already open: {}", uri.as_str())]
+    DocumentAlreadyOpen {
+        /// The URI of the document
+        uri: Uri,
+    },
+
+    /// Error that occurs when a document change does not increase the
+    /// document version
+    #[error("Document version conflict for {}: current version {current}, received {received}", uri.as_str())]
+    VersionConflict {
+        /// The URI of the document
+        uri: Uri,
+        /// The version of the document before the change
+        current: i32,
+        /// The version carried by the change
+        received: i32,
+    },
+
+    /// Error that occurs when a workspace edit cannot be applied, e.g.
+    /// because it creates a file that exists or edits one that does not
+    #[error("Cannot apply edit{}: {message}", uri.as_ref().map(|uri| format!(" to {}", uri.as_str())).unwrap_or_default())]
+    Edit {
+        /// The file the edit failed on, if any
+        uri: Option<Uri>,
+        /// Description of the failure
+        message: String,
+    },
+
+    /// Error that occurs when a position, range, location or URI is invalid
+    #[error(transparent)]
+    Location {
+        /// The underlying location error
+        #[from]
+        source: LocationError,
+    },
+}
+
+impl ContextEngineError {
+    /// Returns the stable error code of the error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ContextEngineError::Transport { .. } => ErrorCode::Transport,
+            ContextEngineError::ServerCrashed { .. } => ErrorCode::ServerCrashed,
+            ContextEngineError::Parse { .. } => ErrorCode::Parse,
+            ContextEngineError::CacheCorrupted { .. } => ErrorCode::CacheCorrupted,
+            ContextEngineError::Configuration { .. } => ErrorCode::Configuration,
+            ContextEngineError::Timeout { .. } => ErrorCode::Timeout,
+            ContextEngineError::ServerError { .. } => ErrorCode::ServerError,
+            ContextEngineError::Cancelled { .. } => ErrorCode::Cancelled,
+            ContextEngineError::DocumentNotOpen { .. }
+            | ContextEngineError::DocumentAlreadyOpen { .. } => ErrorCode::DocumentState,
+            ContextEngineError::VersionConflict { .. } => ErrorCode::ContentModified,
+            ContextEngineError::Edit { .. } => ErrorCode::Edit,
+            ContextEngineError::Location { .. } => ErrorCode::InvalidParams,
+        }
+    }
+
+    /// Returns the name of the language server the error relates to, if any.
+    pub fn server(&self) -> Option<&str> {
+        match self {
+            ContextEngineError::Transport { server, .. }
+            | ContextEngineError::ServerCrashed { server, .. }
+            | ContextEngineError::Timeout { server, .. }
+            | ContextEngineError::ServerError { server, .. } => Some(server),
+            _ => None,
+        }
+    }
+
+    /// Returns the URI of the document the error relates to, if any.
+    pub fn uri(&self) -> Option<&Uri> {
+        match self {
+            ContextEngineError::Parse { uri, .. } | ContextEngineError::Edit { uri, .. } => {
+                uri.as_ref()
+            }
+            ContextEngineError::DocumentNotOpen { uri }
+            | ContextEngineError::DocumentAlreadyOpen { uri }
+            | ContextEngineError::VersionConflict { uri, .. } => Some(uri),
+            _ => None,
+        }
+    }
+
+    /// Returns the request method the error relates to, if any.
+    pub fn method(&self) -> Option<&str> {
+        match self {
+            ContextEngineError::Transport { method, .. } => method.as_deref(),
+            ContextEngineError::Timeout { method, .. }
+            | ContextEngineError::ServerError { method, .. }
+            | ContextEngineError::Cancelled { method } => Some(method),
+            _ => None,
+        }
+    }
+
+    /// Returns true if retrying the operation may succeed, e.g. after a
+    /// language server has been restarted.
+    ///
+    /// Server errors are transient if the server reported that the content
+    /// changed while the request was processed (LSP `ContentModified` and
+    /// `ServerCancelled`).
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ContextEngineError::Transport { .. }
+                | ContextEngineError::ServerCrashed { .. }
+                | ContextEngineError::Timeout { .. }
+                | ContextEngineError::ServerError {
+                    code: -32802 | -32801,
+                    ..
+                }
+        )
+    }
+
+    /// Converts the error into a JSON-RPC error object.
+    ///
+    /// The `data` member holds the serialized error, so clients receive the
+    /// `kind` of the error and all of its context fields.
+    pub fn to_json_rpc_error(&self) -> JsonRpcError {
+        JsonRpcError {
+            code: self.code().as_i64(),
+            message: self.to_string(),
+            data: serde_json::to_value(self).ok(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ContextEngineError {
+    fn from(error: serde_json::Error) -> Self {
+        ContextEngineError::Parse {
+            uri: None,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<ContextEngineError> for JsonRpcError {
+    fn from(error: ContextEngineError) -> Self {
+        error.to_json_rpc_error()
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/error.rs"]
+mod tests;
@@ -0,0 +1,452 @@
+//! Context cards: everything an assistant needs to know about a symbol.
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::graph::{
+    Direction, EdgeKind, KnowledgeGraph, NodeKind, Relation, SymbolId, SymbolNode, SymbolRef,
+};
+use crate::patterns::UsagePattern;
+
+/// The number of characters of serialized JSON that make up a token on
+/// average, to estimate the size of a card.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// The sections of a card in the order they are trimmed, least valuable
+/// first.
+pub const TRIM_ORDER: [&str; 9] = [
+    "related_types",
+    "used_by",
+    "uses",
+    "usage_patterns",
+    "implementors",
+    "members",
+    "constructors",
+    "implements",
+    "methods",
+];
+
+/// The edges that relate a symbol to the types it uses.
+const USES: [EdgeKind; 4] = [
+    EdgeKind::Calls,
+    EdgeKind::References,
+    EdgeKind::Returns,
+    EdgeKind::TakesParameter,
+];
+
+/// A method of a type, either inherent or from a trait it implements.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MethodRef {
+    /// The method
+    #[serde(flatten)]
+    pub method: SymbolRef,
+    /// The qualified name of the trait the method belongs to, or `None` for
+    /// an inherent method
+    #[serde(rename = "trait", default, skip_serializing_if = "Option::is_none")]
+    pub trait_name: Option<String>,
+}
+
+/// A type related to the symbol of a card, e.g. the type of a parameter of
+/// one of its methods.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelatedType {
+    /// The type
+    #[serde(flatten)]
+    pub symbol: SymbolRef,
+    /// The number of steps from the symbol of the card, 1 for the types it
+    /// uses directly
+    pub depth: usize,
+    /// The relationship that led to the type
+    pub via: EdgeKind,
+}
+
+/// Everything about a symbol in one answer: its definition, its methods,
+/// inherent and from traits, how it is constructed and used, and the types
+/// around it, each with the precise location of its signature.
+///
+/// Cards only refer to other symbols; a client fetches the parts it needs,
+/// e.g. the body of one method, from their locations. To fit the context
+/// window of a model, [`ContextCard::trim_to`] drops the least valuable
+/// entries first (see [`TRIM_ORDER`]).
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::graph::{ContextCard, EdgeKind, KnowledgeGraph, NodeKind, SymbolNode};
+/// use context_engine_core::types::{Position, Range, Uri};
+/// use std::str::FromStr;
+///
+/// let uri = Uri::from_str("file:///src/user.rs").unwrap();
+/// let line = |line| Range::new(Position::new(line, 0), Position::new(line, 10));
+///
+/// let mut graph = KnowledgeGraph::new();
+/// let user = graph.add_node(SymbolNode::new(NodeKind::Type, "User", "app::User", uri.clone(), line(0)));
+/// let new = graph.add_node(
+///     SymbolNode::new(NodeKind::Method, "new", "app::User::new", uri.clone(), line(2))
+///         .with_detail("pub fn new(name: String) -> Self"),
+/// );
+/// let validate = graph.add_node(SymbolNode::new(
+///     NodeKind::Method,
+///     "validate",
+///     "app::User::validate",
+///     uri,
+///     line(4),
+/// ));
+/// graph.add_edge(user, EdgeKind::Defines, new);
+/// graph.add_edge(user, EdgeKind::Defines, validate);
+///
+/// let card = ContextCard::new(&graph, user, 1).unwrap();
+/// assert_eq!(card.methods.len(), 2);
+/// assert_eq!(card.constructors.first().unwrap().qualified_name, "app::User::new");
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextCard {
+    /// The symbol, with the locations of its signature, body and doc comment
+    pub symbol: SymbolNode,
+    /// The module, type or trait that defines the symbol
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defined_in: Option<SymbolRef>,
+    /// The methods of a type or trait, inherent ones first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub methods: Vec<MethodRef>,
+    /// The traits a type implements, or the supertraits of a trait
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub implements: Vec<SymbolRef>,
+    /// The functions and methods that create instances of a type
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constructors: Vec<SymbolRef>,
+    /// The other members of a type, e.g. fields and variants, or the
+    /// symbols a module defines
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<SymbolRef>,
+    /// The types that implement a trait
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub implementors: Vec<SymbolRef>,
+    /// How the codebase uses the symbol, most frequent first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub usage_patterns: Vec<UsagePattern>,
+    /// The symbols the symbol calls or refers to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uses: Vec<Relation>,
+    /// The symbols that call or refer to the symbol
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub used_by: Vec<Relation>,
+    /// The types around the symbol, nearest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related_types: Vec<RelatedType>,
+    /// The number of entries [`ContextCard::trim_to`] dropped, by section
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub truncated: BTreeMap<String, usize>,
+}
+
+impl ContextCard {
+    /// Builds the card of a symbol from a graph.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The graph that contains the symbol
+    /// * `id` - The id of the symbol
+    /// * `depth` - How many steps away from the symbol related types are
+    ///   collected; 0 for none
+    ///
+    /// # Returns
+    ///
+    /// The card, or `None` if the graph has no symbol with the id. The card
+    /// has no usage patterns, which come from the references a language
+    /// server finds (see
+    /// [`PatternExtractor`](crate::patterns::PatternExtractor)).
+    pub fn new(graph: &KnowledgeGraph, id: SymbolId, depth: usize) -> Option<Self> {
+        let symbol = graph.node(id)?.clone();
+        let children = graph.neighbors(id, EdgeKind::Defines, Direction::Outgoing);
+        let defined_in = graph
+            .neighbors(id, EdgeKind::Defines, Direction::Incoming)
+            .first()
+            .map(|&parent| SymbolRef::from(parent));
+        let implements = graph.neighbors(id, EdgeKind::Implements, Direction::Outgoing);
+        // Types the card lists in other sections are not repeated as related
+        let listed = implements
+            .iter()
+            .map(|node| node.id())
+            .chain(defined_in.as_ref().map(|parent| parent.id))
+            .collect::<HashSet<_>>();
+
+        let is_type = matches!(symbol.kind, NodeKind::Type | NodeKind::Trait);
+        let (methods, members) = if is_type {
+            let (methods, members): (Vec<_>, Vec<_>) =
+                children.iter().partition(|child| is_callable(child));
+            (methods_of(graph, &methods, &implements), members)
+        } else {
+            (Vec::new(), children.clone())
+        };
+        let constructors = if symbol.kind == NodeKind::Type {
+            constructors_of(graph, &symbol, &children)
+        } else {
+            Vec::new()
+        };
+        let implementors = if symbol.kind == NodeKind::Trait {
+            graph.neighbors(id, EdgeKind::Implements, Direction::Incoming)
+        } else {
+            Vec::new()
+        };
+
+        Some(Self {
+            defined_in,
+            methods,
+            implements: implements.into_iter().map(SymbolRef::from).collect(),
+            constructors,
+            members: members.into_iter().map(SymbolRef::from).collect(),
+            implementors: implementors.into_iter().map(SymbolRef::from).collect(),
+            usage_patterns: Vec::new(),
+            uses: relations(graph, id, Direction::Outgoing),
+            used_by: relations(graph, id, Direction::Incoming),
+            related_types: related_types(graph, id, depth, &listed),
+            truncated: BTreeMap::new(),
+            symbol,
+        })
+    }
+
+    /// Sets the usage patterns.
+    pub fn with_usage_patterns(mut self, patterns: Vec<UsagePattern>) -> Self {
+        self.usage_patterns = patterns;
+        self
+    }
+
+    /// Returns an estimate of the number of tokens of the card as JSON.
+    pub fn estimated_tokens(&self) -> usize {
+        estimate_tokens(self)
+    }
+
+    /// Drops entries, least valuable first, until the card fits a budget.
+    ///
+    /// Sections are trimmed in [`TRIM_ORDER`], each from its end, so that
+    /// the most distant related types and the least frequent usage patterns
+    /// go first. The symbol itself is always kept, so a card may exceed a
+    /// very small budget. Dropped entries are counted in
+    /// [`ContextCard::truncated`].
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - The maximum number of tokens, as estimated by
+    ///   [`ContextCard::estimated_tokens`]
+    pub fn trim_to(&mut self, budget: usize) {
+        let mut tokens = self.estimated_tokens();
+        for section in TRIM_ORDER {
+            while tokens > budget {
+                let Some(removed) = self.pop(section) else {
+                    break;
+                };
+                tokens = tokens.saturating_sub(removed);
+                *self.truncated.entry(section.to_string()).or_default() += 1;
+            }
+        }
+    }
+
+    /// Removes the last entry of a section and returns its estimated size.
+    fn pop(&mut self, section: &str) -> Option<usize> {
+        match section {
+            "related_types" => self
+                .related_types
+                .pop()
+                .map(|entry| estimate_tokens(&entry)),
+            "used_by" => self.used_by.pop().map(|entry| estimate_tokens(&entry)),
+            "uses" => self.uses.pop().map(|entry| estimate_tokens(&entry)),
+            "usage_patterns" => self
+                .usage_patterns
+                .pop()
+                .map(|entry| estimate_tokens(&entry)),
+            "implementors" => self.implementors.pop().map(|entry| estimate_tokens(&entry)),
+            "members" => self.members.pop().map(|entry| estimate_tokens(&entry)),
+            "constructors" => self.constructors.pop().map(|entry| estimate_tokens(&entry)),
+            "implements" => self.implements.pop().map(|entry| estimate_tokens(&entry)),
+            "methods" => self.methods.pop().map(|entry| estimate_tokens(&entry)),
+            _ => None,
+        }
+    }
+}
+
+fn estimate_tokens(value: &impl Serialize) -> usize {
+    serde_json::to_string(value).map_or(0, |json| json.len().div_ceil(CHARS_PER_TOKEN))
+}
+
+fn is_callable(node: &SymbolNode) -> bool {
+    matches!(node.kind, NodeKind::Method | NodeKind::Function)
+}
+
+/// Returns the methods of a type: its own, attributed to the trait that
+/// declares a method of the same name, then the methods of its traits that
+/// it does not define itself.
+fn methods_of(
+    graph: &KnowledgeGraph,
+    own: &[&SymbolNode],
+    traits: &[&SymbolNode],
+) -> Vec<MethodRef> {
+    let trait_methods = traits
+        .iter()
+        .flat_map(|&tr| {
+            graph
+                .neighbors(tr.id(), EdgeKind::Defines, Direction::Outgoing)
+                .into_iter()
+                .filter(|method| is_callable(method))
+                .map(move |method| (tr, method))
+        })
+        .collect::<Vec<_>>();
+    let trait_of = |name: &str| {
+        trait_methods
+            .iter()
+            .find(|(_, method)| method.name == name)
+            .map(|(tr, _)| tr.qualified_name.clone())
+    };
+
+    let mut methods = own
+        .iter()
+        .map(|&method| MethodRef {
+            method: SymbolRef::from(method),
+            trait_name: trait_of(&method.name),
+        })
+        .collect::<Vec<_>>();
+    let defined = own
+        .iter()
+        .map(|method| method.name.as_str())
+        .collect::<HashSet<_>>();
+    methods.extend(
+        trait_methods
+            .iter()
+            .filter(|(_, method)| !defined.contains(method.name.as_str()))
+            .map(|(tr, method)| MethodRef {
+                method: SymbolRef::from(*method),
+                trait_name: Some(tr.qualified_name.clone()),
+            }),
+    );
+    methods.sort_by(|a, b| {
+        a.trait_name
+            .cmp(&b.trait_name)
+            .then_with(|| a.method.qualified_name.cmp(&b.method.qualified_name))
+            .then(a.method.id.cmp(&b.method.id))
+    });
+    methods
+}
+
+/// Returns the functions that create instances of a type: the callables
+/// that return it according to the graph or to their signature, and the
+/// constructors of languages that have them.
+fn constructors_of(
+    graph: &KnowledgeGraph,
+    symbol: &SymbolNode,
+    children: &[&SymbolNode],
+) -> Vec<SymbolRef> {
+    let returning = graph
+        .neighbors(symbol.id(), EdgeKind::Returns, Direction::Incoming)
+        .into_iter()
+        .filter(|node| is_callable(node));
+    let own = children.iter().copied().filter(|child| {
+        is_callable(child)
+            && (matches!(child.name.as_str(), "constructor" | "__init__" | "__new__")
+                || child
+                    .detail
+                    .as_deref()
+                    .is_some_and(|detail| returns(detail, &symbol.name)))
+    });
+
+    let mut seen = HashSet::new();
+    own.chain(returning)
+        .filter(|node| seen.insert(node.id()))
+        .map(SymbolRef::from)
+        .collect()
+}
+
+/// Returns true if a signature returns `Self` or a type, possibly wrapped,
+/// e.g. `fn parse(text: &str) -> Result<User, Error>`.
+fn returns(signature: &str, type_name: &str) -> bool {
+    let Some((_, returned)) = signature.rsplit_once("->") else {
+        return false;
+    };
+    returned
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .any(|word| word == "Self" || word == type_name)
+}
+
+/// Returns the relationships of a symbol to the symbols it uses or that use
+/// it, depending on the direction.
+fn relations(graph: &KnowledgeGraph, id: SymbolId, direction: Direction) -> Vec<Relation> {
+    graph
+        .edges_of(id, direction)
+        .into_iter()
+        .filter(|edge| USES.contains(&edge.kind))
+        .filter_map(|edge| {
+            Some(Relation {
+                kind: edge.kind,
+                direction,
+                symbol: SymbolRef::from(graph.node(edge.other(direction))?),
+            })
+        })
+        .collect()
+}
+
+/// Returns the types related to a symbol up to a depth: the types it and
+/// its members implement, call, refer to, take or return, then theirs.
+/// Members of types stand for the type that defines them. The `listed`
+/// types are followed but left out of the result.
+fn related_types(
+    graph: &KnowledgeGraph,
+    id: SymbolId,
+    depth: usize,
+    listed: &HashSet<SymbolId>,
+) -> Vec<RelatedType> {
+    let mut seen = HashSet::from([id]);
+    let mut related = Vec::new();
+    let mut frontier = vec![id];
+    for level in 1..=depth {
+        let mut next = Vec::new();
+        for current in frontier {
+            let members = graph.neighbors(current, EdgeKind::Defines, Direction::Outgoing);
+            let sources = std::iter::once(current).chain(members.iter().map(|node| node.id()));
+            for source in sources {
+                for edge in graph.edges_of(source, Direction::Outgoing) {
+                    if edge.kind != EdgeKind::Implements && !USES.contains(&edge.kind) {
+                        continue;
+                    }
+                    let Some(owner) = owning_type(graph, edge.to) else {
+                        continue;
+                    };
+                    if !seen.insert(owner.id()) {
+                        continue;
+                    }
+                    if !listed.contains(&owner.id()) {
+                        related.push(RelatedType {
+                            symbol: SymbolRef::from(owner),
+                            depth: level,
+                            via: edge.kind,
+                        });
+                    }
+                    next.push(owner.id());
+                }
+            }
+        }
+        frontier = next;
+    }
+    related.sort_by(|a, b| {
+        a.depth
+            .cmp(&b.depth)
+            .then_with(|| a.symbol.qualified_name.cmp(&b.symbol.qualified_name))
+            .then(a.symbol.id.cmp(&b.symbol.id))
+    });
+    related
+}
+
+/// Returns the type or trait a symbol is, or the one that defines it.
+fn owning_type(graph: &KnowledgeGraph, id: SymbolId) -> Option<&SymbolNode> {
+    let is_type = |node: &&SymbolNode| matches!(node.kind, NodeKind::Type | NodeKind::Trait);
+    let node = graph.node(id)?;
+    if is_type(&node) {
+        return Some(node);
+    }
+    graph
+        .neighbors(id, EdgeKind::Defines, Direction::Incoming)
+        .into_iter()
+        .find(is_type)
+}
+
+#[cfg(test)]
+#[path = "tests/card.rs"]
+mod tests;
@@ -0,0 +1,106 @@
+//! Relationships between symbols, the edges of the knowledge graph.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::graph::SymbolId;
+
+/// The kind of a relationship between two symbols.
+///
+/// Edges point from the symbol that has the relationship to the one it
+/// relates to, e.g. from a caller to the function it calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// A crate, module or type defines a member
+    Defines,
+    /// A type implements a trait or interface, or extends a class
+    Implements,
+    /// A function or method calls another one
+    Calls,
+    /// A symbol mentions another one in its signature or body
+    References,
+    /// A function or method returns a type
+    Returns,
+    /// A function or method takes a parameter of a type
+    TakesParameter,
+    /// A module re-exports a symbol defined elsewhere
+    ReExports,
+    /// A crate depends on another crate
+    DependsOn,
+}
+
+impl EdgeKind {
+    /// All edge kinds, in order.
+    pub const ALL: [EdgeKind; 8] = [
+        EdgeKind::Defines,
+        EdgeKind::Implements,
+        EdgeKind::Calls,
+        EdgeKind::References,
+        EdgeKind::Returns,
+        EdgeKind::TakesParameter,
+        EdgeKind::ReExports,
+        EdgeKind::DependsOn,
+    ];
+
+    /// Returns the name of the kind as used in queries and serialized
+    /// graphs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EdgeKind::Defines => "defines",
+            EdgeKind::Implements => "implements",
+            EdgeKind::Calls => "calls",
+            EdgeKind::References => "references",
+            EdgeKind::Returns => "returns",
+            EdgeKind::TakesParameter => "takes_parameter",
+            EdgeKind::ReExports => "re_exports",
+            EdgeKind::DependsOn => "depends_on",
+        }
+    }
+}
+
+impl fmt::Display for EdgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A directed relationship between two symbols.
+///
+/// Edges order by source, kind and target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Edge {
+    /// The symbol that has the relationship
+    pub from: SymbolId,
+    /// The kind of the relationship
+    pub kind: EdgeKind,
+    /// The symbol it relates to
+    pub to: SymbolId,
+}
+
+impl Edge {
+    /// Creates an edge.
+    pub fn new(from: SymbolId, kind: EdgeKind, to: SymbolId) -> Self {
+        Self { from, kind, to }
+    }
+
+    /// Returns the symbol at the other end of the edge, as seen from one
+    /// end in a direction.
+    pub fn other(&self, direction: Direction) -> SymbolId {
+        match direction {
+            Direction::Outgoing => self.to,
+            Direction::Incoming => self.from,
+        }
+    }
+}
+
+/// Which edges of a symbol to follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Edges from the symbol to others, e.g. the functions it calls
+    Outgoing,
+    /// Edges from others to the symbol, e.g. the functions that call it
+    Incoming,
+}
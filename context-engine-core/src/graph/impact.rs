@@ -0,0 +1,446 @@
+//! Impact analysis: the symbols a change of a symbol affects.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::Path;
+
+use lsp_types::{Location, Uri};
+use serde::{Deserialize, Serialize};
+
+use crate::document::DocumentStore;
+use crate::graph::{Direction, Edge, EdgeKind, KnowledgeGraph, SymbolId, SymbolNode, SymbolRef};
+use crate::types::UriExt;
+
+/// The relationships along which a change spreads, followed from the
+/// changed symbol back to the symbols that have them: callers, referrers,
+/// implementors and re-exporting modules.
+pub const IMPACT_EDGES: [EdgeKind; 4] = [
+    EdgeKind::Calls,
+    EdgeKind::References,
+    EdgeKind::Implements,
+    EdgeKind::ReExports,
+];
+
+/// The limits of an impact analysis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImpactConfig {
+    /// How many steps away from the changed symbol dependents are collected
+    pub max_depth: usize,
+    /// The most affected symbols collected
+    pub max_nodes: usize,
+}
+
+impl Default for ImpactConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 3,
+            max_nodes: 100,
+        }
+    }
+}
+
+/// A symbol in an impact tree: the changed symbol at the root, or one that
+/// depends on its parent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImpactNode {
+    /// The symbol
+    #[serde(flatten)]
+    pub symbol: SymbolRef,
+    /// How the symbol depends on its parent; `None` at the root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub via: Option<EdgeKind>,
+    /// Where the symbol uses its parent, if known
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sites: Vec<Location>,
+    /// Whether the symbol is part of the public API
+    pub public: bool,
+    /// Whether the symbol is a test or belongs to tests
+    pub test: bool,
+    /// Whether the symbol closes a cycle: it is also an ancestor of the
+    /// node, whose dependents are listed there
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cycle: bool,
+    /// The symbols that depend on the symbol
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ImpactNode>,
+}
+
+impl ImpactNode {
+    /// Returns the node and all nodes below it, depth first.
+    pub fn descendants(&self) -> Vec<&ImpactNode> {
+        let mut nodes = vec![self];
+        for child in &self.children {
+            nodes.extend(child.descendants());
+        }
+        nodes
+    }
+}
+
+/// The symbols a change of a symbol affects, as a tree of dependents.
+///
+/// Every affected symbol appears once, at the shortest distance from the
+/// changed symbol; a dependent that is also an ancestor of its parent
+/// appears a second time as a leaf marked as a [cycle](ImpactNode::cycle).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImpactTree {
+    /// The changed symbol and its dependents
+    pub root: ImpactNode,
+    /// The number of affected symbols, without the changed one
+    pub affected: usize,
+    /// Whether the depth or node limit left dependents out
+    pub truncated: bool,
+    /// The affected symbols that are part of the public API
+    pub public_api: Vec<SymbolRef>,
+    /// The affected tests
+    pub tests: Vec<SymbolRef>,
+}
+
+/// Computes which symbols a change of a symbol affects, following
+/// [`IMPACT_EDGES`] transitively in a breadth-first walk.
+///
+/// Besides the edges of the graph, the analyzer follows relationships found
+/// elsewhere, e.g. the references a language server reports (see
+/// [`ImpactAnalyzer::with_sites`]). Whether a symbol is public API or a test
+/// is told from its name, its file and the text around its signature, read
+/// from the document store or from disk.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::document::DocumentStore;
+/// use context_engine_core::graph::{EdgeKind, ImpactAnalyzer, KnowledgeGraph, NodeKind, SymbolNode};
+/// use context_engine_core::types::{Position, Range, Uri};
+/// use std::str::FromStr;
+///
+/// let uri = Uri::from_str("file:///src/lib.rs").unwrap();
+/// let line = |line| Range::new(Position::new(line, 0), Position::new(line, 10));
+/// let mut graph = KnowledgeGraph::new();
+/// let parse = graph.add_node(SymbolNode::new(NodeKind::Function, "parse", "app::parse", uri.clone(), line(0)));
+/// let load = graph.add_node(SymbolNode::new(NodeKind::Function, "load", "app::load", uri.clone(), line(2)));
+/// let main = graph.add_node(SymbolNode::new(NodeKind::Function, "main", "app::main", uri, line(4)));
+/// graph.add_edge(load, EdgeKind::Calls, parse);
+/// graph.add_edge(main, EdgeKind::Calls, load);
+///
+/// let documents = DocumentStore::default();
+/// let tree = ImpactAnalyzer::new(&graph, &documents).analyze(parse).unwrap();
+/// assert_eq!(tree.affected, 2);
+/// assert_eq!(tree.root.children.first().unwrap().symbol.qualified_name, "app::load");
+/// ```
+pub struct ImpactAnalyzer<'g> {
+    graph: &'g KnowledgeGraph,
+    documents: &'g DocumentStore,
+    config: ImpactConfig,
+    /// Relationships found outside the graph, with where they occur
+    sites: BTreeMap<Edge, Vec<Location>>,
+}
+
+impl<'g> ImpactAnalyzer<'g> {
+    /// Creates an analyzer with the default limits.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The graph whose edges are followed
+    /// * `documents` - The open documents, whose text is used instead of the
+    ///   files on disk
+    pub fn new(graph: &'g KnowledgeGraph, documents: &'g DocumentStore) -> Self {
+        Self {
+            graph,
+            documents,
+            config: ImpactConfig::default(),
+            sites: BTreeMap::new(),
+        }
+    }
+
+    /// Replaces the limits of the analysis.
+    pub fn with_config(mut self, config: ImpactConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Adds relationships found outside the graph, e.g. references a
+    /// language server reports, with the locations where they occur.
+    /// Relationships of kinds other than [`IMPACT_EDGES`] are ignored.
+    pub fn with_sites(mut self, sites: impl IntoIterator<Item = (Edge, Location)>) -> Self {
+        for (edge, location) in sites {
+            let locations = self.sites.entry(edge).or_default();
+            if !locations.contains(&location) {
+                locations.push(location);
+            }
+        }
+        self
+    }
+
+    /// Computes the symbols a change of a symbol affects.
+    ///
+    /// # Returns
+    ///
+    /// The tree of dependents, or `None` if the graph has no symbol with the
+    /// id.
+    pub fn analyze(&self, id: SymbolId) -> Option<ImpactTree> {
+        self.graph.node(id)?;
+        let mut entries = vec![Entry {
+            id,
+            parent: None,
+            via: None,
+            sites: Vec::new(),
+            cycle: false,
+            children: Vec::new(),
+        }];
+        let mut visited = HashSet::from([id]);
+        let mut queue = VecDeque::from([(0, 0)]);
+        let mut truncated = false;
+
+        while let Some((index, depth)) = queue.pop_front() {
+            let Some(current) = entries.get(index).map(|entry| entry.id) else {
+                continue;
+            };
+            for (dependent, via, sites) in self.dependents(current) {
+                let cycle = visited.contains(&dependent);
+                if cycle && !is_ancestor(&entries, index, dependent) {
+                    continue;
+                }
+                if !cycle
+                    && (depth >= self.config.max_depth || visited.len() > self.config.max_nodes)
+                {
+                    truncated = true;
+                    continue;
+                }
+                let child = entries.len();
+                entries.push(Entry {
+                    id: dependent,
+                    parent: Some(index),
+                    via: Some(via),
+                    sites,
+                    cycle,
+                    children: Vec::new(),
+                });
+                if let Some(entry) = entries.get_mut(index) {
+                    entry.children.push(child);
+                }
+                if !cycle {
+                    visited.insert(dependent);
+                    queue.push_back((child, depth + 1));
+                }
+            }
+        }
+
+        let mut texts = Texts {
+            documents: self.documents,
+            texts: HashMap::new(),
+        };
+        let root = self.build(&entries, 0, &mut texts)?;
+        let affected = root
+            .descendants()
+            .into_iter()
+            .skip(1)
+            .filter(|node| !node.cycle);
+        let (mut public_api, mut tests) = (Vec::new(), Vec::new());
+        let mut count = 0;
+        for node in affected {
+            count += 1;
+            if node.public {
+                public_api.push(node.symbol.clone());
+            }
+            if node.test {
+                tests.push(node.symbol.clone());
+            }
+        }
+        Some(ImpactTree {
+            root,
+            affected: count,
+            truncated,
+            public_api,
+            tests,
+        })
+    }
+
+    /// Returns the symbols that depend on a symbol directly, with the first
+    /// of [`IMPACT_EDGES`] they depend on it by and where, ordered by
+    /// qualified name.
+    fn dependents(&self, id: SymbolId) -> Vec<(SymbolId, EdgeKind, Vec<Location>)> {
+        let mut dependents = BTreeMap::<SymbolId, (EdgeKind, Vec<Location>)>::new();
+        let mut add = |from: SymbolId, kind: EdgeKind, sites: &[Location]| {
+            let (via, locations) = dependents.entry(from).or_insert((kind, Vec::new()));
+            if rank(kind) < rank(*via) {
+                *via = kind;
+            }
+            for site in sites {
+                if !locations.contains(site) {
+                    locations.push(site.clone());
+                }
+            }
+        };
+        for edge in self.graph.edges_of(id, Direction::Incoming) {
+            if IMPACT_EDGES.contains(&edge.kind) {
+                add(edge.from, edge.kind, &[]);
+            }
+        }
+        for (edge, sites) in &self.sites {
+            if edge.to == id
+                && IMPACT_EDGES.contains(&edge.kind)
+                && self.graph.node(edge.from).is_some()
+            {
+                add(edge.from, edge.kind, sites);
+            }
+        }
+
+        let mut dependents = dependents
+            .into_iter()
+            .map(|(from, (via, sites))| (from, via, sites))
+            .collect::<Vec<_>>();
+        dependents.sort_by_cached_key(|(from, _, _)| {
+            let name = self
+                .graph
+                .node(*from)
+                .map(|node| node.qualified_name.clone());
+            (name, *from)
+        });
+        dependents
+    }
+
+    /// Builds the node of an entry and its children.
+    fn build(&self, entries: &[Entry], index: usize, texts: &mut Texts<'_>) -> Option<ImpactNode> {
+        let entry = entries.get(index)?;
+        let symbol = self.graph.node(entry.id)?;
+        let text = texts.get(&symbol.uri);
+        let test = is_test(symbol, text);
+        let public = !test && (self.is_reexported(entry.id) || is_declared_public(symbol, text));
+        let children = match entry.cycle {
+            true => Vec::new(),
+            false => entry
+                .children
+                .iter()
+                .filter_map(|&child| self.build(entries, child, texts))
+                .collect(),
+        };
+        Some(ImpactNode {
+            symbol: SymbolRef::from(symbol),
+            via: entry.via,
+            sites: entry.sites.clone(),
+            public,
+            test,
+            cycle: entry.cycle,
+            children,
+        })
+    }
+
+    fn is_reexported(&self, id: SymbolId) -> bool {
+        self.graph
+            .edges_of(id, Direction::Incoming)
+            .iter()
+            .any(|edge| edge.kind == EdgeKind::ReExports)
+    }
+}
+
+/// A symbol found by the walk.
+struct Entry {
+    id: SymbolId,
+    parent: Option<usize>,
+    via: Option<EdgeKind>,
+    sites: Vec<Location>,
+    cycle: bool,
+    children: Vec<usize>,
+}
+
+/// Returns true if a symbol is the entry at an index or one of its
+/// ancestors.
+fn is_ancestor(entries: &[Entry], index: usize, id: SymbolId) -> bool {
+    let mut current = entries.get(index);
+    while let Some(entry) = current {
+        if entry.id == id {
+            return true;
+        }
+        current = entry.parent.and_then(|parent| entries.get(parent));
+    }
+    false
+}
+
+/// The position of an edge kind in [`IMPACT_EDGES`].
+fn rank(kind: EdgeKind) -> usize {
+    IMPACT_EDGES
+        .iter()
+        .position(|impact| *impact == kind)
+        .unwrap_or(IMPACT_EDGES.len())
+}
+
+/// The texts of the documents of affected symbols, read once each.
+struct Texts<'a> {
+    documents: &'a DocumentStore,
+    texts: HashMap<String, Option<String>>,
+}
+
+impl Texts<'_> {
+    fn get(&mut self, uri: &Uri) -> Option<&str> {
+        let documents = self.documents;
+        self.texts
+            .entry(uri.as_str().to_string())
+            .or_insert_with(|| match documents.get(uri) {
+                Some(document) => Some(document.text().to_string()),
+                None => std::fs::read_to_string(uri.to_file_path().ok()?).ok(),
+            })
+            .as_deref()
+    }
+}
+
+/// Returns true if a symbol is a test or belongs to tests: it is in a
+/// `tests` module or a test file, its name starts with `test_`, or an
+/// attribute or decorator right above its signature mentions tests, e.g.
+/// `#[test]` or `#[cfg(test)]`.
+fn is_test(symbol: &SymbolNode, text: Option<&str>) -> bool {
+    if symbol
+        .qualified_name
+        .split([':', '.'])
+        .any(|segment| segment == "tests" || segment == "test")
+        || symbol.name.starts_with("test_")
+    {
+        return true;
+    }
+    let path = symbol.uri.path().as_str();
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+    let stem = Path::new(file_name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    if path.contains("/tests/")
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_tests")
+        || stem.ends_with(".test")
+        || stem.ends_with(".spec")
+    {
+        return true;
+    }
+
+    let Some(text) = text else {
+        return false;
+    };
+    let line = symbol.signature.start.line as usize;
+    text.lines()
+        .take(line)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .map(str::trim)
+        .take_while(|line| {
+            line.starts_with("#[") || line.starts_with('@') || line.starts_with("//")
+        })
+        .any(|line| (line.starts_with("#[") || line.starts_with('@')) && line.contains("test"))
+}
+
+/// Returns true if a symbol is declared public: `pub` in Rust, exported in
+/// TypeScript, or not private by convention in Python.
+///
+/// The visibility of enclosing modules and types is not taken into account.
+fn is_declared_public(symbol: &SymbolNode, text: Option<&str>) -> bool {
+    if symbol.uri.path().as_str().ends_with(".py") {
+        return !symbol.name.starts_with('_');
+    }
+    let line = text.and_then(|text| text.lines().nth(symbol.signature.start.line as usize));
+    let Some(line) = line.map(str::trim_start) else {
+        return false;
+    };
+    line.starts_with("pub ") || line.starts_with("export ")
+}
+
+#[cfg(test)]
+#[path = "tests/impact.rs"]
+mod tests;
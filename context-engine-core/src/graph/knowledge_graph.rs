@@ -0,0 +1,412 @@
+//! The graph of symbols and their relationships.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use lsp_types::{Position, Uri};
+use serde::{Deserialize, Serialize};
+
+use crate::graph::{
+    Direction, Edge, EdgeKind, Relation, SymbolId, SymbolInfo, SymbolNode, SymbolQuery, SymbolRef,
+};
+use crate::types::RangeExt;
+
+/// The symbols of a codebase and its dependencies, and how they relate.
+///
+/// Nodes are [`SymbolNode`]s, identified by the [`SymbolId`] the graph
+/// assigns when they are added. Edges are typed and directed, e.g. a method
+/// [`Calls`](EdgeKind::Calls) another one, and are indexed in both
+/// directions. Symbols can be looked up by id, by fully qualified name, by
+/// position in a document, or with a [`SymbolQuery`].
+///
+/// Results are always ordered deterministically, so that answers built from
+/// the graph are stable. The graph serializes to its nodes and edges; the
+/// lookup indices are rebuilt when it is deserialized.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::graph::{
+///     Direction, EdgeKind, KnowledgeGraph, NodeKind, SymbolNode,
+/// };
+/// use context_engine_core::types::{Position, Range, Uri};
+/// use std::str::FromStr;
+///
+/// let uri = Uri::from_str("file:///src/user.rs").unwrap();
+/// let range = |start, end| Range::new(Position::new(start, 0), Position::new(end, 1));
+///
+/// let mut graph = KnowledgeGraph::new();
+/// let user = graph.add_node(
+///     SymbolNode::new(NodeKind::Type, "User", "app::User", uri.clone(), range(0, 0))
+///         .with_body(range(0, 3)),
+/// );
+/// let validate = graph.add_node(
+///     SymbolNode::new(NodeKind::Method, "validate", "app::User::validate", uri.clone(), range(5, 5))
+///         .with_body(range(5, 8)),
+/// );
+/// graph.add_edge(user, EdgeKind::Defines, validate);
+///
+/// let found = graph.symbol_at(&uri, Position::new(6, 4)).unwrap();
+/// assert_eq!(found.qualified_name, "app::User::validate");
+///
+/// let owners = graph.neighbors(validate, EdgeKind::Defines, Direction::Incoming);
+/// assert_eq!(owners[0].name, "User");
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "GraphData", into = "GraphData")]
+pub struct KnowledgeGraph {
+    nodes: BTreeMap<SymbolId, SymbolNode>,
+    next_id: u32,
+    names: HashMap<String, Vec<SymbolId>>,
+    documents: HashMap<Uri, Vec<SymbolId>>,
+    outgoing: HashMap<SymbolId, BTreeSet<(EdgeKind, SymbolId)>>,
+    incoming: HashMap<SymbolId, BTreeSet<(EdgeKind, SymbolId)>>,
+}
+
+impl KnowledgeGraph {
+    /// Creates an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a symbol to the graph.
+    ///
+    /// # Returns
+    ///
+    /// The id the graph assigned to the symbol.
+    pub fn add_node(&mut self, mut node: SymbolNode) -> SymbolId {
+        let id = SymbolId::new(self.next_id);
+        self.next_id = self.next_id.saturating_add(1);
+        node.set_id(id);
+        self.insert(node);
+        id
+    }
+
+    fn insert(&mut self, node: SymbolNode) {
+        let id = node.id();
+        self.names
+            .entry(node.qualified_name.clone())
+            .or_default()
+            .push(id);
+        self.documents.entry(node.uri.clone()).or_default().push(id);
+        self.nodes.insert(id, node);
+    }
+
+    /// Removes a symbol and all its edges.
+    pub fn remove_node(&mut self, id: SymbolId) -> Option<SymbolNode> {
+        let node = self.nodes.remove(&id)?;
+        remove_id(&mut self.names, &node.qualified_name, id);
+        remove_id(&mut self.documents, &node.uri, id);
+
+        for (kind, to) in self.outgoing.remove(&id).unwrap_or_default() {
+            remove_edge_from(&mut self.incoming, to, (kind, id));
+        }
+        for (kind, from) in self.incoming.remove(&id).unwrap_or_default() {
+            remove_edge_from(&mut self.outgoing, from, (kind, id));
+        }
+        Some(node)
+    }
+
+    /// Removes all symbols defined in a document, e.g. before indexing a new
+    /// version of it.
+    ///
+    /// # Returns
+    ///
+    /// The removed symbols, ordered by id.
+    pub fn remove_document(&mut self, uri: &Uri) -> Vec<SymbolNode> {
+        let ids = self.documents.get(uri).cloned().unwrap_or_default();
+        ids.into_iter()
+            .filter_map(|id| self.remove_node(id))
+            .collect()
+    }
+
+    /// Adds an edge between two symbols of the graph.
+    ///
+    /// # Returns
+    ///
+    /// True if the edge was added, false if it already existed or one of the
+    /// symbols is not in the graph.
+    pub fn add_edge(&mut self, from: SymbolId, kind: EdgeKind, to: SymbolId) -> bool {
+        if !self.nodes.contains_key(&from) || !self.nodes.contains_key(&to) {
+            return false;
+        }
+        self.incoming.entry(to).or_default().insert((kind, from));
+        self.outgoing.entry(from).or_default().insert((kind, to))
+    }
+
+    /// Removes an edge.
+    ///
+    /// # Returns
+    ///
+    /// True if the edge existed.
+    pub fn remove_edge(&mut self, from: SymbolId, kind: EdgeKind, to: SymbolId) -> bool {
+        remove_edge_from(&mut self.incoming, to, (kind, from));
+        remove_edge_from(&mut self.outgoing, from, (kind, to))
+    }
+
+    /// Returns true if the graph has an edge.
+    pub fn has_edge(&self, from: SymbolId, kind: EdgeKind, to: SymbolId) -> bool {
+        self.outgoing
+            .get(&from)
+            .is_some_and(|edges| edges.contains(&(kind, to)))
+    }
+
+    /// Returns a symbol by id.
+    pub fn node(&self, id: SymbolId) -> Option<&SymbolNode> {
+        self.nodes.get(&id)
+    }
+
+    /// Returns all symbols, ordered by id.
+    pub fn nodes(&self) -> impl Iterator<Item = &SymbolNode> {
+        self.nodes.values()
+    }
+
+    /// Returns all edges, ordered by source, kind and target.
+    pub fn edges(&self) -> Vec<Edge> {
+        let mut edges = self
+            .outgoing
+            .iter()
+            .flat_map(|(&from, edges)| {
+                edges
+                    .iter()
+                    .map(move |&(kind, to)| Edge::new(from, kind, to))
+            })
+            .collect::<Vec<_>>();
+        edges.sort();
+        edges
+    }
+
+    /// Returns the symbols with a fully qualified name, ordered by id.
+    ///
+    /// Names are not unique in general, e.g. a Rust type has a `fmt` method
+    /// for each formatting trait it implements.
+    pub fn lookup(&self, qualified_name: &str) -> Vec<&SymbolNode> {
+        self.names
+            .get(qualified_name)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.nodes.get(id))
+            .collect()
+    }
+
+    /// Returns the symbols defined in a document, in document order.
+    pub fn symbols_in(&self, uri: &Uri) -> Vec<&SymbolNode> {
+        let mut nodes = self
+            .documents
+            .get(uri)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.nodes.get(id))
+            .collect::<Vec<_>>();
+        nodes.sort_by(|a, b| {
+            a.extent()
+                .cmp_position(&b.extent())
+                .then(a.id().cmp(&b.id()))
+        });
+        nodes
+    }
+
+    /// Returns the innermost symbol whose definition, including its doc
+    /// comment and body, contains a position.
+    pub fn symbol_at(&self, uri: &Uri, position: Position) -> Option<&SymbolNode> {
+        self.documents
+            .get(uri)?
+            .iter()
+            .filter_map(|id| self.nodes.get(id))
+            .filter(|node| node.extent().contains_position(&position))
+            .max_by(|a, b| {
+                let (a_extent, b_extent) = (a.extent(), b.extent());
+                a_extent
+                    .start
+                    .cmp(&b_extent.start)
+                    .then(b_extent.end.cmp(&a_extent.end))
+                    .then(a.id().cmp(&b.id()))
+            })
+    }
+
+    /// Returns the edges of a symbol in a direction, ordered by kind and id
+    /// of the other symbol.
+    pub fn edges_of(&self, id: SymbolId, direction: Direction) -> Vec<Edge> {
+        let index = match direction {
+            Direction::Outgoing => &self.outgoing,
+            Direction::Incoming => &self.incoming,
+        };
+        index
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .map(|&(kind, other)| match direction {
+                Direction::Outgoing => Edge::new(id, kind, other),
+                Direction::Incoming => Edge::new(other, kind, id),
+            })
+            .collect()
+    }
+
+    /// Returns the symbols related to a symbol by edges of one kind, ordered
+    /// by id.
+    ///
+    /// # Examples
+    ///
+    /// Callers of a function are its incoming [`Calls`](EdgeKind::Calls)
+    /// neighbors, its callees the outgoing ones.
+    pub fn neighbors(
+        &self,
+        id: SymbolId,
+        kind: EdgeKind,
+        direction: Direction,
+    ) -> Vec<&SymbolNode> {
+        self.edges_of(id, direction)
+            .into_iter()
+            .filter(|edge| edge.kind == kind)
+            .filter_map(|edge| self.nodes.get(&edge.other(direction)))
+            .collect()
+    }
+
+    /// Returns the symbols that match a query.
+    ///
+    /// # Returns
+    ///
+    /// The matching symbols, those whose name or qualified name equals the
+    /// query name first, then ordered by qualified name and id, up to the
+    /// limit of the query.
+    pub fn query(&self, query: &SymbolQuery) -> Vec<&SymbolNode> {
+        let mut nodes = match &query.uri {
+            Some(uri) => self
+                .documents
+                .get(uri)
+                .into_iter()
+                .flatten()
+                .filter_map(|id| self.nodes.get(id))
+                .filter(|node| query.matches(node))
+                .collect::<Vec<_>>(),
+            None => self
+                .nodes
+                .values()
+                .filter(|node| query.matches(node))
+                .collect(),
+        };
+        nodes.sort_by(|a, b| {
+            query
+                .rank(a)
+                .cmp(&query.rank(b))
+                .then_with(|| a.qualified_name.cmp(&b.qualified_name))
+                .then(a.id().cmp(&b.id()))
+        });
+        if let Some(limit) = query.limit {
+            nodes.truncate(limit);
+        }
+        nodes
+    }
+
+    /// Describes a symbol with all its relationships.
+    pub fn describe(&self, id: SymbolId) -> Option<SymbolInfo> {
+        let symbol = self.nodes.get(&id)?.clone();
+        let relations = [Direction::Outgoing, Direction::Incoming]
+            .into_iter()
+            .flat_map(|direction| {
+                self.edges_of(id, direction)
+                    .into_iter()
+                    .filter_map(move |edge| {
+                        let other = self.nodes.get(&edge.other(direction))?;
+                        Some(Relation {
+                            kind: edge.kind,
+                            direction,
+                            symbol: SymbolRef::from(other),
+                        })
+                    })
+            })
+            .collect();
+        Some(SymbolInfo { symbol, relations })
+    }
+
+    /// Returns the number of symbols.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if the graph has no symbols.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the number of edges.
+    pub fn edge_count(&self) -> usize {
+        self.outgoing.values().map(BTreeSet::len).sum()
+    }
+}
+
+impl PartialEq for KnowledgeGraph {
+    fn eq(&self, other: &Self) -> bool {
+        self.nodes == other.nodes && self.outgoing == other.outgoing
+    }
+}
+
+/// Removes an id from the list of a key, and the key once its list is
+/// empty.
+fn remove_id<K, Q>(index: &mut HashMap<K, Vec<SymbolId>>, key: &Q, id: SymbolId)
+where
+    K: std::borrow::Borrow<Q> + std::hash::Hash + Eq,
+    Q: std::hash::Hash + Eq + ?Sized,
+{
+    if let Some(ids) = index.get_mut(key) {
+        ids.retain(|&other| other != id);
+        if ids.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+/// Removes an edge from one direction of the edge index.
+fn remove_edge_from(
+    index: &mut HashMap<SymbolId, BTreeSet<(EdgeKind, SymbolId)>>,
+    id: SymbolId,
+    edge: (EdgeKind, SymbolId),
+) -> bool {
+    let Some(edges) = index.get_mut(&id) else {
+        return false;
+    };
+    let removed = edges.remove(&edge);
+    if edges.is_empty() {
+        index.remove(&id);
+    }
+    removed
+}
+
+/// The serialized form of a [`KnowledgeGraph`].
+#[derive(Serialize, Deserialize)]
+struct GraphData {
+    next_id: u32,
+    nodes: Vec<SymbolNode>,
+    edges: Vec<Edge>,
+}
+
+impl From<KnowledgeGraph> for GraphData {
+    fn from(graph: KnowledgeGraph) -> Self {
+        let edges = graph.edges();
+        Self {
+            next_id: graph.next_id,
+            nodes: graph.nodes.into_values().collect(),
+            edges,
+        }
+    }
+}
+
+impl From<GraphData> for KnowledgeGraph {
+    fn from(data: GraphData) -> Self {
+        let mut graph = KnowledgeGraph::new();
+        for node in data.nodes {
+            if !graph.nodes.contains_key(&node.id()) {
+                graph.next_id = graph.next_id.max(node.id().index().saturating_add(1));
+                graph.insert(node);
+            }
+        }
+        graph.next_id = graph.next_id.max(data.next_id);
+        for edge in data.edges {
+            graph.add_edge(edge.from, edge.kind, edge.to);
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/knowledge_graph.rs"]
+mod tests;
@@ -0,0 +1,54 @@
+//! The knowledge graph of symbols and their relationships.
+//!
+//! Language servers answer one question at a time: where is this defined,
+//! who calls that. To tell an assistant everything about a symbol at once,
+//! the Context Engine collects those answers into a [`KnowledgeGraph`]: typed
+//! [`SymbolNode`]s for crates, modules, types, traits, functions, methods,
+//! fields, variants and constants, connected by typed [`Edge`]s such as
+//! [`Calls`](EdgeKind::Calls) or [`Implements`](EdgeKind::Implements).
+//!
+//! Every symbol knows the precise locations of its signature, body and doc
+//! comment, so that a client can fetch exactly the part it needs with
+//! standard tools instead of reading whole files.
+//!
+//! Followed backwards, the edges tell what a change affects: the
+//! [`ImpactAnalyzer`] walks callers, referrers, implementors and re-exports
+//! transitively into an [`ImpactTree`].
+//!
+//! ## Structs
+//!
+//! * [`KnowledgeGraph`] - The symbols of a codebase and how they relate.
+//! * [`SymbolNode`] - A symbol with the locations of its definition.
+//! * [`SymbolId`] - Identifier of a symbol in its graph.
+//! * [`Edge`] - A directed relationship between two symbols.
+//! * [`SymbolQuery`] - A search for symbols by name, kind and document.
+//! * [`SymbolInfo`] - A symbol together with all its relationships.
+//! * [`Relation`] - A relationship of a symbol, as seen from the symbol.
+//! * [`SymbolRef`] - A short reference to a symbol.
+//! * [`ContextCard`] - Everything about a symbol, trimmed to a token budget.
+//! * [`MethodRef`] - A method of a type, inherent or from a trait.
+//! * [`RelatedType`] - A type around the symbol of a card.
+//! * [`ImpactAnalyzer`] - Computes the symbols a change of a symbol affects.
+//! * [`ImpactConfig`] - The limits of an impact analysis.
+//! * [`ImpactTree`] - The symbols a change affects, as a tree of dependents.
+//! * [`ImpactNode`] - A symbol in an impact tree.
+//!
+//! ## Enums
+//!
+//! * [`NodeKind`] - The kind of a symbol.
+//! * [`EdgeKind`] - The kind of a relationship.
+//! * [`Direction`] - Which edges of a symbol to follow.
+
+mod card;
+mod edge;
+mod impact;
+mod knowledge_graph;
+mod node;
+mod query;
+
+pub use card::{ContextCard, MethodRef, RelatedType, TRIM_ORDER};
+pub use edge::{Direction, Edge, EdgeKind};
+pub use impact::{IMPACT_EDGES, ImpactAnalyzer, ImpactConfig, ImpactNode, ImpactTree};
+pub use knowledge_graph::KnowledgeGraph;
+pub use node::{NodeKind, SymbolId, SymbolNode};
+pub use query::{Relation, SymbolInfo, SymbolQuery, SymbolRef};
@@ -0,0 +1,214 @@
+//! Symbols, the nodes of the knowledge graph.
+
+use std::fmt;
+
+use lsp_types::{Location, Range, Uri};
+use serde::{Deserialize, Serialize};
+
+use crate::types::RangeExt;
+
+/// Identifier of a node in a [`KnowledgeGraph`](crate::graph::KnowledgeGraph).
+///
+/// Ids are handed out by the graph in the order nodes are added and are only
+/// meaningful together with the graph that issued them.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct SymbolId(u32);
+
+impl SymbolId {
+    /// Returns the raw index of the id in its graph.
+    pub fn index(self) -> u32 {
+        self.0
+    }
+
+    pub(crate) fn new(index: u32) -> Self {
+        Self(index)
+    }
+}
+
+impl fmt::Display for SymbolId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sym#{}", self.0)
+    }
+}
+
+/// The kind of a symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    /// A package of code, e.g. a Rust crate or an npm package
+    Crate,
+    /// A module, namespace or file
+    Module,
+    /// A struct, enum, union, class, interface or type alias
+    Type,
+    /// A trait, or an interface that types implement
+    Trait,
+    /// A free function
+    Function,
+    /// A function that belongs to a type or trait
+    Method,
+    /// A field of a struct or class
+    Field,
+    /// A variant of an enum
+    Variant,
+    /// A constant or static
+    Constant,
+}
+
+impl NodeKind {
+    /// Returns the name of the kind as used in queries and serialized
+    /// graphs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NodeKind::Crate => "crate",
+            NodeKind::Module => "module",
+            NodeKind::Type => "type",
+            NodeKind::Trait => "trait",
+            NodeKind::Function => "function",
+            NodeKind::Method => "method",
+            NodeKind::Field => "field",
+            NodeKind::Variant => "variant",
+            NodeKind::Constant => "constant",
+        }
+    }
+}
+
+impl fmt::Display for NodeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A symbol of the codebase or one of its dependencies.
+///
+/// Besides its names, a symbol records where the parts of its definition
+/// are, so that a client can fetch exactly the part it needs: the signature,
+/// the body and the doc comment are separate ranges of the same document.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::graph::{NodeKind, SymbolNode};
+/// use context_engine_core::types::{Position, Range, Uri};
+/// use std::str::FromStr;
+///
+/// let uri = Uri::from_str("file:///src/user.rs").unwrap();
+/// let node = SymbolNode::new(
+///     NodeKind::Method,
+///     "validate",
+///     "app::user::User::validate",
+///     uri,
+///     Range::new(Position::new(12, 4), Position::new(12, 48)),
+/// )
+/// .with_body(Range::new(Position::new(12, 49), Position::new(20, 5)))
+/// .with_doc(Range::new(Position::new(10, 4), Position::new(11, 40)));
+///
+/// assert_eq!(
+///     node.extent(),
+///     Range::new(Position::new(10, 4), Position::new(20, 5))
+/// );
+/// assert_eq!(node.signature_location().range.start.line, 12);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolNode {
+    id: SymbolId,
+    /// The kind of the symbol
+    pub kind: NodeKind,
+    /// The name of the symbol, e.g. `validate`
+    pub name: String,
+    /// The fully qualified name of the symbol, e.g. `app::user::User::validate`
+    pub qualified_name: String,
+    /// The document that defines the symbol
+    pub uri: Uri,
+    /// The range of the signature, e.g. `pub fn validate(&self) -> Result<()>`
+    pub signature: Range,
+    /// The range of the body, if the symbol has one
+    pub body: Option<Range>,
+    /// The range of the doc comment, if the symbol has one
+    pub doc: Option<Range>,
+    /// Additional information from the language server, e.g. the type of a
+    /// field
+    pub detail: Option<String>,
+}
+
+impl SymbolNode {
+    /// Creates a symbol without body, doc comment and detail.
+    ///
+    /// The id of the symbol is assigned when it is added to a graph.
+    pub fn new(
+        kind: NodeKind,
+        name: impl Into<String>,
+        qualified_name: impl Into<String>,
+        uri: Uri,
+        signature: Range,
+    ) -> Self {
+        Self {
+            id: SymbolId::default(),
+            kind,
+            name: name.into(),
+            qualified_name: qualified_name.into(),
+            uri,
+            signature,
+            body: None,
+            doc: None,
+            detail: None,
+        }
+    }
+
+    /// Sets the range of the body.
+    pub fn with_body(mut self, body: Range) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// Sets the range of the doc comment.
+    pub fn with_doc(mut self, doc: Range) -> Self {
+        self.doc = Some(doc);
+        self
+    }
+
+    /// Sets the detail.
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Returns the id of the symbol in its graph.
+    pub fn id(&self) -> SymbolId {
+        self.id
+    }
+
+    pub(crate) fn set_id(&mut self, id: SymbolId) {
+        self.id = id;
+    }
+
+    /// Returns the location of the signature.
+    pub fn signature_location(&self) -> Location {
+        Location::new(self.uri.clone(), self.signature)
+    }
+
+    /// Returns the location of the body.
+    pub fn body_location(&self) -> Option<Location> {
+        self.body.map(|body| Location::new(self.uri.clone(), body))
+    }
+
+    /// Returns the location of the doc comment.
+    pub fn doc_location(&self) -> Option<Location> {
+        self.doc.map(|doc| Location::new(self.uri.clone(), doc))
+    }
+
+    /// Returns the range that covers the whole definition: the doc comment,
+    /// the signature and the body.
+    pub fn extent(&self) -> Range {
+        [self.body, self.doc]
+            .into_iter()
+            .flatten()
+            .fold(self.signature, |extent, range| extent.cover(&range))
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/node.rs"]
+mod tests;
@@ -0,0 +1,152 @@
+//! Queries over the knowledge graph and their serializable results.
+
+use lsp_types::{Location, Uri};
+use serde::{Deserialize, Serialize};
+
+use crate::graph::{Direction, EdgeKind, NodeKind, SymbolId, SymbolNode};
+
+/// A search for symbols by name, kind and document.
+///
+/// All criteria are optional; a default query matches every symbol. Queries
+/// deserialize from the arguments of MCP tools, e.g.
+/// `{"name": "builder", "kinds": ["method"]}`.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::graph::{NodeKind, SymbolQuery};
+///
+/// let query: SymbolQuery =
+///     serde_json::from_str(r#"{"name": "user", "kinds": ["type", "trait"]}"#).unwrap();
+/// assert_eq!(
+///     query,
+///     SymbolQuery::named("user")
+///         .with_kind(NodeKind::Type)
+///         .with_kind(NodeKind::Trait)
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SymbolQuery {
+    /// A case-insensitive substring of the qualified names to match
+    pub name: Option<String>,
+    /// The kinds to match; any kind if empty
+    pub kinds: Vec<NodeKind>,
+    /// The document that defines the symbols
+    pub uri: Option<Uri>,
+    /// The maximum number of results
+    pub limit: Option<usize>,
+}
+
+impl SymbolQuery {
+    /// Creates a query that matches every symbol.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a query for symbols whose qualified name contains `name`,
+    /// ignoring case.
+    pub fn named(name: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Adds a kind to match.
+    pub fn with_kind(mut self, kind: NodeKind) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    /// Restricts the query to the symbols of a document.
+    pub fn in_document(mut self, uri: Uri) -> Self {
+        self.uri = Some(uri);
+        self
+    }
+
+    /// Limits the number of results.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns true if a symbol matches the query.
+    pub fn matches(&self, node: &SymbolNode) -> bool {
+        self.uri.as_ref().map_or(true, |uri| &node.uri == uri)
+            && (self.kinds.is_empty() || self.kinds.contains(&node.kind))
+            && self.name.as_ref().map_or(true, |name| {
+                node.qualified_name
+                    .to_lowercase()
+                    .contains(&name.to_lowercase())
+            })
+    }
+
+    /// Returns how well a matching symbol matches: 0 if its name or
+    /// qualified name is the query name, 1 otherwise.
+    pub(crate) fn rank(&self, node: &SymbolNode) -> u8 {
+        match &self.name {
+            Some(name)
+                if node.name.eq_ignore_ascii_case(name)
+                    || node.qualified_name.eq_ignore_ascii_case(name) =>
+            {
+                0
+            }
+            _ => 1,
+        }
+    }
+}
+
+/// A short reference to a symbol: what it is and where its signature is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolRef {
+    /// The id of the symbol in its graph
+    pub id: SymbolId,
+    /// The kind of the symbol
+    pub kind: NodeKind,
+    /// The fully qualified name of the symbol
+    pub qualified_name: String,
+    /// The location of the signature of the symbol
+    pub location: Location,
+}
+
+impl From<&SymbolNode> for SymbolRef {
+    fn from(node: &SymbolNode) -> Self {
+        Self {
+            id: node.id(),
+            kind: node.kind,
+            qualified_name: node.qualified_name.clone(),
+            location: node.signature_location(),
+        }
+    }
+}
+
+/// A relationship of a symbol, as seen from the symbol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Relation {
+    /// The kind of the relationship
+    pub kind: EdgeKind,
+    /// Whether the symbol has the relationship (outgoing) or is its target
+    /// (incoming)
+    pub direction: Direction,
+    /// The symbol at the other end
+    pub symbol: SymbolRef,
+}
+
+/// A symbol together with all its relationships.
+///
+/// This is the answer to "tell me everything about this symbol": the
+/// locations of its signature, body and doc comment, and references to the
+/// symbols it relates to, which can be described in turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolInfo {
+    /// The symbol
+    pub symbol: SymbolNode,
+    /// Its relationships, outgoing ones first, each group ordered by kind
+    /// and id of the related symbol
+    pub relations: Vec<Relation>,
+}
+
+#[cfg(test)]
+#[path = "tests/query.rs"]
+mod tests;
@@ -0,0 +1,275 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use lsp_types::{Position, Range, Uri};
+use proptest::prelude::*;
+
+use super::*;
+
+fn add(graph: &mut KnowledgeGraph, kind: NodeKind, qualified_name: &str, line: u32) -> SymbolId {
+    let uri = Uri::from_str("file:///src/lib.rs").unwrap();
+    let name = qualified_name.rsplit("::").next().unwrap();
+    let range = Range::new(Position::new(line, 0), Position::new(line, 10));
+    graph.add_node(SymbolNode::new(kind, name, qualified_name, uri, range))
+}
+
+fn names<'a>(refs: impl IntoIterator<Item = &'a SymbolRef>) -> Vec<&'a str> {
+    refs.into_iter()
+        .map(|symbol| symbol.qualified_name.as_str())
+        .collect()
+}
+
+/// A user type with inherent and trait methods, a field, a constructor,
+/// a caller and a few related types.
+struct Fixture {
+    graph: KnowledgeGraph,
+    user: SymbolId,
+    display: SymbolId,
+    validate: SymbolId,
+}
+
+fn fixture() -> Fixture {
+    let mut graph = KnowledgeGraph::new();
+    let module = add(&mut graph, NodeKind::Module, "app", 0);
+    let user = add(&mut graph, NodeKind::Type, "app::User", 1);
+    let name = add(&mut graph, NodeKind::Field, "app::User::name", 2);
+    let new = add(&mut graph, NodeKind::Method, "app::User::new", 3);
+    let validate = add(&mut graph, NodeKind::Method, "app::User::validate", 4);
+    let fmt = add(&mut graph, NodeKind::Method, "app::User::fmt", 5);
+    let display = add(&mut graph, NodeKind::Trait, "app::Display", 6);
+    let display_fmt = add(&mut graph, NodeKind::Method, "app::Display::fmt", 7);
+    let to_string = add(&mut graph, NodeKind::Method, "app::Display::to_string", 8);
+    let error = add(&mut graph, NodeKind::Type, "app::ValidationError", 9);
+    let error_new = add(
+        &mut graph,
+        NodeKind::Method,
+        "app::ValidationError::new",
+        10,
+    );
+    let rule = add(&mut graph, NodeKind::Type, "app::Rule", 11);
+    let main = add(&mut graph, NodeKind::Function, "app::main", 12);
+
+    for child in [user, display, error, rule, main] {
+        graph.add_edge(module, EdgeKind::Defines, child);
+    }
+    for member in [name, new, validate, fmt] {
+        graph.add_edge(user, EdgeKind::Defines, member);
+    }
+    graph.add_edge(display, EdgeKind::Defines, display_fmt);
+    graph.add_edge(display, EdgeKind::Defines, to_string);
+    graph.add_edge(error, EdgeKind::Defines, error_new);
+    graph.add_edge(user, EdgeKind::Implements, display);
+    graph.add_edge(error, EdgeKind::Implements, display);
+    graph.add_edge(main, EdgeKind::Returns, user);
+    graph.add_edge(main, EdgeKind::Calls, validate);
+    graph.add_edge(validate, EdgeKind::Calls, error_new);
+    graph.add_edge(error, EdgeKind::References, rule);
+
+    let detail = "pub fn new(name: String) -> Self".to_string();
+    let mut node = graph.node(new).unwrap().clone();
+    node.detail = Some(detail);
+    graph.remove_node(new);
+    let new = graph.add_node(node);
+    graph.add_edge(user, EdgeKind::Defines, new);
+
+    Fixture {
+        graph,
+        user,
+        display,
+        validate,
+    }
+}
+
+#[test]
+fn test_card_of_type() {
+    let Fixture { graph, user, .. } = fixture();
+    let card = ContextCard::new(&graph, user, 1).unwrap();
+
+    assert_eq!(card.symbol.qualified_name, "app::User");
+    assert_eq!(card.defined_in.as_ref().unwrap().qualified_name, "app");
+    let methods = card
+        .methods
+        .iter()
+        .map(|method| {
+            (
+                method.method.qualified_name.as_str(),
+                method.trait_name.as_deref(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        methods,
+        [
+            ("app::User::new", None),
+            ("app::User::validate", None),
+            ("app::Display::to_string", Some("app::Display")),
+            ("app::User::fmt", Some("app::Display")),
+        ]
+    );
+    assert_eq!(names(&card.implements), ["app::Display"]);
+    assert_eq!(names(&card.members), ["app::User::name"]);
+    assert_eq!(names(&card.constructors), ["app::User::new", "app::main"]);
+    assert!(card.implementors.is_empty());
+    assert_eq!(
+        names(card.used_by.iter().map(|relation| &relation.symbol)),
+        ["app::main"]
+    );
+    // Display is listed as implemented, not repeated as related
+    let related = card
+        .related_types
+        .iter()
+        .map(|related| {
+            (
+                related.symbol.qualified_name.as_str(),
+                related.depth,
+                related.via,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(related, [("app::ValidationError", 1, EdgeKind::Calls)]);
+}
+
+#[test]
+fn test_depth_of_related_types() {
+    let Fixture { graph, user, .. } = fixture();
+    let related = |depth| {
+        ContextCard::new(&graph, user, depth)
+            .unwrap()
+            .related_types
+            .into_iter()
+            .map(|related| (related.symbol.qualified_name, related.depth))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(related(0), []);
+    assert_eq!(
+        related(2),
+        [
+            ("app::ValidationError".to_string(), 1),
+            ("app::Rule".to_string(), 2)
+        ]
+    );
+    assert_eq!(related(5), related(2));
+}
+
+#[test]
+fn test_card_of_trait_and_function() {
+    let Fixture {
+        graph,
+        display,
+        validate,
+        ..
+    } = fixture();
+    let card = ContextCard::new(&graph, display, 1).unwrap();
+    assert_eq!(
+        names(&card.implementors),
+        ["app::User", "app::ValidationError"]
+    );
+    assert_eq!(card.methods.len(), 2);
+    assert!(card.constructors.is_empty());
+
+    let card = ContextCard::new(&graph, validate, 1).unwrap();
+    assert_eq!(card.defined_in.unwrap().qualified_name, "app::User");
+    assert!(card.methods.is_empty());
+    assert_eq!(
+        names(card.uses.iter().map(|relation| &relation.symbol)),
+        ["app::ValidationError::new"]
+    );
+    assert_eq!(
+        names(card.used_by.iter().map(|relation| &relation.symbol)),
+        ["app::main"]
+    );
+    assert_eq!(
+        names(card.related_types.iter().map(|related| &related.symbol)),
+        ["app::ValidationError"]
+    );
+    assert!(ContextCard::new(&graph, SymbolId::new(999), 1).is_none());
+}
+
+#[test]
+fn test_returns() {
+    assert!(returns("fn new() -> Self", "User"));
+    assert!(returns("fn parse(s: &str) -> Result<User, Error>", "User"));
+    assert!(!returns("fn name(&self) -> &str", "User"));
+    assert!(!returns("fn users(&self) -> Vec<UserId>", "User"));
+    assert!(!returns("fn set(&mut self, user: User)", "User"));
+}
+
+#[test]
+fn test_trim_drops_low_value_sections_first() {
+    let Fixture { graph, user, .. } = fixture();
+    let patterns = crate::patterns::UsagePatterns::from_templates(
+        ["let ... = User::new(...);", "user.validate()?;"].map(String::from),
+    );
+    let full = ContextCard::new(&graph, user, 2)
+        .unwrap()
+        .with_usage_patterns(patterns.usage_patterns);
+    let tokens = full.estimated_tokens();
+
+    let mut untouched = full.clone();
+    untouched.trim_to(tokens);
+    assert_eq!(untouched, full);
+
+    let mut trimmed = full.clone();
+    trimmed.trim_to(tokens - 1);
+    assert!(trimmed.related_types.len() < full.related_types.len());
+    assert_eq!(trimmed.methods, full.methods);
+    assert_eq!(trimmed.truncated.get("related_types"), Some(&1));
+
+    let mut minimal = full.clone();
+    minimal.trim_to(0);
+    assert_eq!(minimal.symbol, full.symbol);
+    assert!(minimal.methods.is_empty() && minimal.usage_patterns.is_empty());
+    assert_eq!(minimal.truncated.get("methods"), Some(&full.methods.len()));
+    assert_eq!(minimal.truncated.get("usage_patterns"), Some(&2));
+}
+
+#[test]
+fn test_serialization_skips_empty_sections() {
+    let Fixture {
+        graph, validate, ..
+    } = fixture();
+    let card = ContextCard::new(&graph, validate, 0).unwrap();
+    let json = serde_json::to_value(&card).unwrap();
+    assert!(json.get("methods").is_none());
+    assert!(json.get("truncated").is_none());
+    assert_eq!(json.pointer("/used_by/0/kind").unwrap(), "calls");
+    assert_eq!(serde_json::from_value::<ContextCard>(json).unwrap(), card);
+
+    let Fixture { graph, user, .. } = fixture();
+    let card = ContextCard::new(&graph, user, 0).unwrap();
+    let json = serde_json::to_value(&card).unwrap();
+    assert_eq!(json.pointer("/methods/3/trait").unwrap(), "app::Display");
+    assert!(json.pointer("/methods/0/trait").is_none());
+    assert_eq!(
+        json.pointer("/methods/0/qualified_name").unwrap(),
+        "app::User::new"
+    );
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_trimmed_cards_fit_or_keep_only_the_symbol(budget in 0usize..600, depth in 0usize..3) {
+        let Fixture { graph, user, .. } = fixture();
+        let mut card = ContextCard::new(&graph, user, depth).unwrap();
+        let full = card.clone();
+        card.trim_to(budget);
+
+        let sections_empty = card.methods.is_empty()
+            && card.implements.is_empty()
+            && card.constructors.is_empty()
+            && card.members.is_empty()
+            && card.used_by.is_empty()
+            && card.related_types.is_empty();
+        prop_assert!(card.estimated_tokens() <= budget + 16 || sections_empty);
+        prop_assert_eq!(&card.symbol, &full.symbol);
+        let dropped = card.truncated.values().sum::<usize>();
+        let total = |card: &ContextCard| {
+            card.methods.len() + card.implements.len() + card.constructors.len()
+                + card.members.len() + card.used_by.len() + card.uses.len()
+                + card.related_types.len()
+        };
+        prop_assert_eq!(total(&card) + dropped, total(&full));
+    }
+}
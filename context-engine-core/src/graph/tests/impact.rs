@@ -0,0 +1,259 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use lsp_types::{Position, Range};
+use proptest::prelude::*;
+
+use super::*;
+use crate::graph::NodeKind;
+
+const LIB_RS: &str = "pub fn parse() {}\n\npub(crate) fn load() {}\n\nfn main() {}\n#[test]\nfn \
+                      loads() {}\npub trait Parse {}\nstruct Json;\npub fn recurse() {}\n";
+
+/// A graph over the symbols of `lib.rs`, written to a folder.
+struct Fixture {
+    _dir: tempfile::TempDir,
+    uri: Uri,
+    graph: KnowledgeGraph,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(&path, LIB_RS).unwrap();
+        Self {
+            uri: Uri::from_file_path(&path).unwrap(),
+            _dir: dir,
+            graph: KnowledgeGraph::new(),
+        }
+    }
+
+    fn add(&mut self, kind: NodeKind, qualified_name: &str, line: u32) -> SymbolId {
+        let name = qualified_name.rsplit("::").next().unwrap();
+        let range = Range::new(Position::new(line, 0), Position::new(line, 10));
+        self.graph.add_node(SymbolNode::new(
+            kind,
+            name,
+            qualified_name,
+            self.uri.clone(),
+            range,
+        ))
+    }
+
+    fn analyze(&self, id: SymbolId, config: ImpactConfig) -> ImpactTree {
+        let documents = DocumentStore::default();
+        ImpactAnalyzer::new(&self.graph, &documents)
+            .with_config(config)
+            .analyze(id)
+            .unwrap()
+    }
+}
+
+fn names(symbols: &[SymbolRef]) -> Vec<&str> {
+    symbols
+        .iter()
+        .map(|symbol| symbol.qualified_name.as_str())
+        .collect()
+}
+
+fn children(node: &ImpactNode) -> Vec<&str> {
+    node.children
+        .iter()
+        .map(|child| child.symbol.qualified_name.as_str())
+        .collect()
+}
+
+#[test]
+fn test_transitive_dependents() {
+    let mut fixture = Fixture::new();
+    let parse = fixture.add(NodeKind::Function, "app::parse", 0);
+    let load = fixture.add(NodeKind::Function, "app::load", 2);
+    let main = fixture.add(NodeKind::Function, "app::main", 4);
+    let loads = fixture.add(NodeKind::Function, "app::loads", 6);
+    fixture.graph.add_edge(load, EdgeKind::Calls, parse);
+    fixture.graph.add_edge(main, EdgeKind::Calls, load);
+    fixture.graph.add_edge(loads, EdgeKind::Calls, load);
+    fixture.graph.add_edge(loads, EdgeKind::References, parse);
+    // Edges that do not spread a change are not followed
+    fixture.graph.add_edge(parse, EdgeKind::Calls, main);
+
+    let tree = fixture.analyze(parse, ImpactConfig::default());
+    assert!(tree.root.public);
+    assert_eq!(children(&tree.root), ["app::load", "app::loads"]);
+    let load_node = tree.root.children.first().unwrap();
+    assert_eq!(load_node.via, Some(EdgeKind::Calls));
+    assert!(!load_node.public);
+    // `loads` is only listed where it is nearest to `parse`
+    assert_eq!(children(load_node), ["app::main"]);
+    assert_eq!(tree.affected, 3);
+    assert!(!tree.truncated);
+    assert_eq!(names(&tree.tests), ["app::loads"]);
+    assert!(tree.public_api.is_empty());
+}
+
+#[test]
+fn test_cycles_are_marked() {
+    let mut fixture = Fixture::new();
+    let parse = fixture.add(NodeKind::Function, "app::parse", 0);
+    let load = fixture.add(NodeKind::Function, "app::load", 2);
+    let main = fixture.add(NodeKind::Function, "app::main", 4);
+    let recurse = fixture.add(NodeKind::Function, "app::recurse", 9);
+    fixture.graph.add_edge(load, EdgeKind::Calls, parse);
+    fixture.graph.add_edge(main, EdgeKind::Calls, load);
+    fixture.graph.add_edge(load, EdgeKind::Calls, main);
+    fixture.graph.add_edge(recurse, EdgeKind::Calls, recurse);
+
+    let tree = fixture.analyze(parse, ImpactConfig::default());
+    let main_node = tree
+        .root
+        .children
+        .first()
+        .unwrap()
+        .children
+        .first()
+        .unwrap();
+    assert_eq!(children(main_node), ["app::load"]);
+    let cycle = main_node.children.first().unwrap();
+    assert!(cycle.cycle);
+    assert!(cycle.children.is_empty());
+    assert_eq!(tree.affected, 2);
+
+    let tree = fixture.analyze(recurse, ImpactConfig::default());
+    assert_eq!(children(&tree.root), ["app::recurse"]);
+    assert!(tree.root.children.first().unwrap().cycle);
+    assert_eq!(tree.affected, 0);
+    assert!(tree.public_api.is_empty());
+}
+
+#[test]
+fn test_limits() {
+    let mut fixture = Fixture::new();
+    let chain = (0..5)
+        .map(|index| fixture.add(NodeKind::Function, &format!("app::f{index}"), 1))
+        .collect::<Vec<_>>();
+    for pair in chain.windows(2) {
+        if let [callee, caller] = pair {
+            fixture.graph.add_edge(*caller, EdgeKind::Calls, *callee);
+        }
+    }
+    let root = *chain.first().unwrap();
+
+    let tree = fixture.analyze(root, ImpactConfig::default());
+    assert_eq!((tree.affected, tree.truncated), (3, true));
+    let config = ImpactConfig {
+        max_depth: 10,
+        max_nodes: 2,
+    };
+    let tree = fixture.analyze(root, config);
+    assert_eq!((tree.affected, tree.truncated), (2, true));
+    let config = ImpactConfig {
+        max_depth: 4,
+        max_nodes: 4,
+    };
+    let tree = fixture.analyze(root, config);
+    assert_eq!((tree.affected, tree.truncated), (4, false));
+}
+
+#[test]
+fn test_implementors_reexports_and_sites() {
+    let mut fixture = Fixture::new();
+    let parse = fixture.add(NodeKind::Trait, "app::Parse", 7);
+    let json = fixture.add(NodeKind::Type, "app::Json", 8);
+    let prelude = fixture.add(NodeKind::Module, "app::prelude", 1);
+    let main = fixture.add(NodeKind::Function, "app::main", 4);
+    fixture.graph.add_edge(json, EdgeKind::Implements, parse);
+    fixture.graph.add_edge(prelude, EdgeKind::ReExports, json);
+    fixture.graph.add_edge(main, EdgeKind::Calls, parse);
+
+    let site = |line| {
+        Location::new(
+            fixture.uri.clone(),
+            Range::new(Position::new(line, 3), Position::new(line, 8)),
+        )
+    };
+    let documents = DocumentStore::default();
+    let tree = ImpactAnalyzer::new(&fixture.graph, &documents)
+        .with_sites([
+            (Edge::new(main, EdgeKind::References, parse), site(4)),
+            (Edge::new(main, EdgeKind::References, parse), site(4)),
+            (Edge::new(main, EdgeKind::Defines, json), site(5)),
+        ])
+        .analyze(parse)
+        .unwrap();
+    assert_eq!(children(&tree.root), ["app::Json", "app::main"]);
+    let json_node = tree.root.children.first().unwrap();
+    assert_eq!(json_node.via, Some(EdgeKind::Implements));
+    assert_eq!(children(json_node), ["app::prelude"]);
+    let main_node = tree.root.children.get(1).unwrap();
+    assert_eq!(main_node.via, Some(EdgeKind::Calls));
+    assert_eq!(main_node.sites, [site(4)]);
+    // A re-exported type is public API
+    assert_eq!(names(&tree.public_api), ["app::Json"]);
+}
+
+#[test]
+fn test_tests_and_public_api_by_file() {
+    let uri = |path: &str| Uri::from_str(&format!("file:///app/{path}")).unwrap();
+    let symbol = |uri, name: &str| {
+        let range = Range::new(Position::new(0, 0), Position::new(0, 1));
+        SymbolNode::new(NodeKind::Function, name, format!("app::{name}"), uri, range)
+    };
+    assert!(is_test(&symbol(uri("tests/api.rs"), "works"), None));
+    assert!(is_test(&symbol(uri("src/user.test.ts"), "works"), None));
+    assert!(is_test(&symbol(uri("test_user.py"), "works"), None));
+    assert!(is_test(&symbol(uri("src/lib.rs"), "test_works"), None));
+    assert!(!is_test(
+        &symbol(uri("src/lib.rs"), "works"),
+        Some("fn works() {}\n")
+    ));
+    let mut attributed = symbol(uri("src/lib.rs"), "works");
+    attributed.signature = Range::new(Position::new(1, 0), Position::new(1, 1));
+    assert!(is_test(
+        &attributed,
+        Some("#[tokio::test]\nfn works() {}\n")
+    ));
+    assert!(!is_test(&attributed, Some("#[inline]\nfn works() {}\n")));
+
+    assert!(is_declared_public(&symbol(uri("user.py"), "load"), None));
+    assert!(!is_declared_public(&symbol(uri("user.py"), "_load"), None));
+    assert!(is_declared_public(
+        &symbol(uri("user.ts"), "load"),
+        Some("export function load() {}\n")
+    ));
+    assert!(!is_declared_public(
+        &symbol(uri("lib.rs"), "load"),
+        Some("pub(super) fn load() {}\n")
+    ));
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_affected_symbols_appear_once(
+        edges in proptest::collection::vec((0u32..8, 0u32..8), 0..24),
+        max_depth in 0usize..5,
+        max_nodes in 0usize..8,
+    ) {
+        let mut fixture = Fixture::new();
+        let ids = (0..8)
+            .map(|index| fixture.add(NodeKind::Function, &format!("app::f{index}"), index))
+            .collect::<Vec<_>>();
+        for (from, to) in edges {
+            let (Some(from), Some(to)) = (ids.get(from as usize), ids.get(to as usize)) else {
+                continue;
+            };
+            fixture.graph.add_edge(*from, EdgeKind::Calls, *to);
+        }
+        let tree = fixture.analyze(*ids.first().unwrap(), ImpactConfig { max_depth, max_nodes });
+
+        let nodes = tree.root.descendants();
+        let expanded = nodes.iter().filter(|node| !node.cycle).map(|node| node.symbol.id);
+        let unique = expanded.clone().collect::<HashSet<_>>();
+        prop_assert_eq!(unique.len(), expanded.count());
+        prop_assert_eq!(unique.len(), tree.affected + 1);
+        prop_assert!(tree.affected <= max_nodes);
+        prop_assert!(nodes.iter().filter(|node| node.cycle).all(|node| node.children.is_empty()));
+    }
+}
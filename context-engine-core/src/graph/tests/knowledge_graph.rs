@@ -0,0 +1,263 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use lsp_types::Range;
+use proptest::prelude::*;
+
+use super::*;
+use crate::graph::NodeKind;
+
+fn uri(path: &str) -> Uri {
+    Uri::from_str(&format!("file://{path}")).unwrap()
+}
+
+fn lines(start: u32, end: u32) -> Range {
+    Range::new(Position::new(start, 0), Position::new(end, 1))
+}
+
+fn node(kind: NodeKind, qualified_name: &str, path: &str, start: u32, end: u32) -> SymbolNode {
+    let name = qualified_name.rsplit("::").next().unwrap();
+    SymbolNode::new(kind, name, qualified_name, uri(path), lines(start, start))
+        .with_body(lines(start, end))
+}
+
+/// A crate with a module that defines a type with a method, and a function
+/// in another file that calls the method.
+fn sample() -> (KnowledgeGraph, [SymbolId; 5]) {
+    let mut graph = KnowledgeGraph::new();
+    let krate = graph.add_node(node(NodeKind::Crate, "app", "/lib.rs", 0, 40));
+    let module = graph.add_node(node(NodeKind::Module, "app::user", "/user.rs", 0, 30));
+    let user = graph.add_node(node(NodeKind::Type, "app::user::User", "/user.rs", 2, 5));
+    let validate = graph.add_node(node(
+        NodeKind::Method,
+        "app::user::User::validate",
+        "/user.rs",
+        8,
+        12,
+    ));
+    let main = graph.add_node(node(NodeKind::Function, "app::main", "/main.rs", 0, 4));
+
+    graph.add_edge(krate, EdgeKind::Defines, module);
+    graph.add_edge(module, EdgeKind::Defines, user);
+    graph.add_edge(user, EdgeKind::Defines, validate);
+    graph.add_edge(main, EdgeKind::Calls, validate);
+    graph.add_edge(main, EdgeKind::References, user);
+    (graph, [krate, module, user, validate, main])
+}
+
+#[test]
+fn test_add_and_lookup() {
+    let (graph, [_, _, user, validate, _]) = sample();
+    assert_eq!(graph.len(), 5);
+    assert_eq!(graph.edge_count(), 5);
+
+    let found = graph.lookup("app::user::User");
+    assert_eq!(
+        found.iter().map(|node| node.id()).collect::<Vec<_>>(),
+        [user]
+    );
+    assert_eq!(graph.node(validate).unwrap().name, "validate");
+    assert!(graph.lookup("app::User").is_empty());
+}
+
+#[test]
+fn test_duplicate_qualified_names() {
+    let mut graph = KnowledgeGraph::new();
+    let display = graph.add_node(node(NodeKind::Method, "app::User::fmt", "/user.rs", 10, 12));
+    let debug = graph.add_node(node(NodeKind::Method, "app::User::fmt", "/user.rs", 20, 22));
+    let ids = graph
+        .lookup("app::User::fmt")
+        .iter()
+        .map(|node| node.id())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, [display, debug]);
+
+    graph.remove_node(display);
+    assert_eq!(graph.lookup("app::User::fmt").len(), 1);
+}
+
+#[test]
+fn test_symbol_at_finds_innermost() {
+    let (graph, [_, module, user, validate, _]) = sample();
+    let at = |line| {
+        graph
+            .symbol_at(&uri("/user.rs"), Position::new(line, 0))
+            .map(|node| node.id())
+    };
+
+    assert_eq!(at(3), Some(user));
+    assert_eq!(at(9), Some(validate));
+    assert_eq!(at(20), Some(module));
+    assert_eq!(at(31), None);
+    assert_eq!(
+        graph.symbol_at(&uri("/other.rs"), Position::new(0, 0)),
+        None
+    );
+}
+
+#[test]
+fn test_edges_in_both_directions() {
+    let (graph, [_, _, user, validate, main]) = sample();
+
+    let callers = graph.neighbors(validate, EdgeKind::Calls, Direction::Incoming);
+    assert_eq!(
+        callers.iter().map(|node| node.id()).collect::<Vec<_>>(),
+        [main]
+    );
+    assert_eq!(
+        graph.edges_of(main, Direction::Outgoing),
+        [
+            Edge::new(main, EdgeKind::Calls, validate),
+            Edge::new(main, EdgeKind::References, user),
+        ]
+    );
+    assert!(graph.has_edge(user, EdgeKind::Defines, validate));
+    assert!(!graph.has_edge(validate, EdgeKind::Defines, user));
+}
+
+#[test]
+fn test_edges_need_known_nodes() {
+    let (mut graph, [krate, module, ..]) = sample();
+    assert!(!graph.add_edge(krate, EdgeKind::Defines, module));
+    assert!(!graph.add_edge(krate, EdgeKind::DependsOn, SymbolId::new(99)));
+    assert!(graph.remove_edge(krate, EdgeKind::Defines, module));
+    assert!(!graph.remove_edge(krate, EdgeKind::Defines, module));
+    assert_eq!(graph.edge_count(), 4);
+}
+
+#[test]
+fn test_remove_document() {
+    let (mut graph, [krate, module, _, validate, main]) = sample();
+    let removed = graph.remove_document(&uri("/user.rs"));
+    assert_eq!(removed.len(), 3);
+    assert_eq!(graph.len(), 2);
+    assert!(graph.symbols_in(&uri("/user.rs")).is_empty());
+    assert!(graph.edges_of(main, Direction::Outgoing).is_empty());
+    assert!(graph.edges_of(krate, Direction::Outgoing).is_empty());
+    assert!(graph.node(module).is_none());
+
+    // Ids are not reused
+    let again = graph.add_node(node(
+        NodeKind::Method,
+        "app::user::User::validate",
+        "/user.rs",
+        8,
+        12,
+    ));
+    assert!(again > validate);
+}
+
+#[test]
+fn test_symbols_in_document_order() {
+    let (graph, [_, module, user, validate, _]) = sample();
+    let ids = graph
+        .symbols_in(&uri("/user.rs"))
+        .iter()
+        .map(|node| node.id())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, [module, user, validate]);
+}
+
+#[test]
+fn test_query() {
+    let (graph, [_, module, user, validate, _]) = sample();
+    let ids = |query: &SymbolQuery| {
+        graph
+            .query(query)
+            .iter()
+            .map(|node| node.id())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(ids(&SymbolQuery::named("user")), [module, user, validate]);
+    assert_eq!(ids(&SymbolQuery::named("User")), [module, user, validate]);
+    assert_eq!(ids(&SymbolQuery::named("validate")), [validate]);
+    assert_eq!(ids(&SymbolQuery::named("user").with_limit(1)), [module]);
+    assert_eq!(
+        ids(&SymbolQuery::new()
+            .with_kind(NodeKind::Method)
+            .in_document(uri("/user.rs"))),
+        [validate]
+    );
+    assert!(ids(&SymbolQuery::new().in_document(uri("/missing.rs"))).is_empty());
+}
+
+#[test]
+fn test_describe() {
+    let (graph, [_, module, user, validate, main]) = sample();
+    let info = graph.describe(user).unwrap();
+    assert_eq!(info.symbol.id(), user);
+    let relations = info
+        .relations
+        .iter()
+        .map(|relation| (relation.kind, relation.direction, relation.symbol.id))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        relations,
+        [
+            (EdgeKind::Defines, Direction::Outgoing, validate),
+            (EdgeKind::Defines, Direction::Incoming, module),
+            (EdgeKind::References, Direction::Incoming, main),
+        ]
+    );
+    assert!(graph.describe(SymbolId::new(42)).is_none());
+}
+
+#[test]
+fn test_serialization_roundtrip() {
+    let (mut graph, [_, _, user, ..]) = sample();
+    graph.remove_node(user);
+
+    let json = serde_json::to_string(&graph).unwrap();
+    let decoded: KnowledgeGraph = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, graph);
+    assert_eq!(decoded.lookup("app::main").len(), 1);
+
+    let config = bincode::config::standard();
+    let bytes = bincode::serde::encode_to_vec(&graph, config).unwrap();
+    let (decoded, _): (KnowledgeGraph, _) =
+        bincode::serde::decode_from_slice(&bytes, config).unwrap();
+    assert_eq!(decoded, graph);
+
+    // New ids continue after the removed one
+    let mut decoded = decoded;
+    let id = decoded.add_node(node(NodeKind::Constant, "app::MAX", "/lib.rs", 1, 1));
+    assert_eq!(id, SymbolId::new(5));
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_edge_indices_stay_consistent(
+        edges in prop::collection::vec((0u32..8, prop::sample::select(EdgeKind::ALL.to_vec()), 0u32..8), 0..40),
+        removals in prop::collection::vec(0u32..8, 0..4),
+    ) {
+        let mut graph = KnowledgeGraph::new();
+        for index in 0..8 {
+            graph.add_node(node(NodeKind::Function, &format!("f{index}"), "/a.rs", index, index));
+        }
+        for (from, kind, to) in edges {
+            graph.add_edge(SymbolId::new(from), kind, SymbolId::new(to));
+        }
+        for id in removals {
+            graph.remove_node(SymbolId::new(id));
+        }
+
+        let all = graph.edges();
+        prop_assert_eq!(all.len(), graph.edge_count());
+        let mut incoming = graph
+            .nodes()
+            .flat_map(|node| graph.edges_of(node.id(), Direction::Incoming))
+            .collect::<Vec<_>>();
+        incoming.sort();
+        prop_assert_eq!(&incoming, &all);
+        for edge in &all {
+            prop_assert!(graph.node(edge.from).is_some() && graph.node(edge.to).is_some());
+        }
+
+        let json = serde_json::to_string(&graph).unwrap();
+        let decoded: KnowledgeGraph = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!(decoded, graph);
+    }
+}
@@ -0,0 +1,95 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use lsp_types::Position;
+use proptest::prelude::*;
+
+use super::*;
+
+fn uri() -> Uri {
+    Uri::from_str("file:///src/lib.rs").unwrap()
+}
+
+fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+    Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1))
+}
+
+#[test]
+fn test_locations_share_the_document() {
+    let node = SymbolNode::new(
+        NodeKind::Field,
+        "email",
+        "app::User::email",
+        uri(),
+        range((3, 4), (3, 22)),
+    )
+    .with_doc(range((2, 4), (2, 30)))
+    .with_detail("String");
+
+    assert_eq!(
+        node.signature_location(),
+        Location::new(uri(), range((3, 4), (3, 22)))
+    );
+    assert_eq!(
+        node.doc_location(),
+        Some(Location::new(uri(), range((2, 4), (2, 30))))
+    );
+    assert_eq!(node.body_location(), None);
+    assert_eq!(node.extent(), range((2, 4), (3, 22)));
+    assert_eq!(node.detail.as_deref(), Some("String"));
+}
+
+#[test]
+fn test_kinds_serialize_as_their_names() {
+    for kind in [
+        NodeKind::Crate,
+        NodeKind::Module,
+        NodeKind::Type,
+        NodeKind::Trait,
+        NodeKind::Function,
+        NodeKind::Method,
+        NodeKind::Field,
+        NodeKind::Variant,
+        NodeKind::Constant,
+    ] {
+        assert_eq!(serde_json::to_value(kind).unwrap(), kind.as_str());
+        assert_eq!(kind.to_string(), kind.as_str());
+    }
+    assert_eq!(SymbolId::new(7).to_string(), "sym#7");
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_extent_contains_every_part(
+        lines in prop::collection::vec(0u32..100, 6),
+        body in any::<bool>(),
+        doc in any::<bool>(),
+    ) {
+        let mut lines = lines;
+        lines.sort_unstable();
+        let [doc_start, doc_end, signature_start, signature_end, body_start, body_end] =
+            lines[..] else { unreachable!() };
+
+        let mut node = SymbolNode::new(
+            NodeKind::Function,
+            "f",
+            "f",
+            uri(),
+            range((signature_start, 0), (signature_end, 1)),
+        );
+        if body {
+            node = node.with_body(range((body_start, 0), (body_end, 1)));
+        }
+        if doc {
+            node = node.with_doc(range((doc_start, 0), (doc_end, 1)));
+        }
+
+        let extent = node.extent();
+        let parts = [Some(node.signature), node.body, node.doc];
+        for part in parts.into_iter().flatten() {
+            prop_assert!(extent.contains_range(&part));
+        }
+    }
+}
@@ -0,0 +1,71 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use lsp_types::{Position, Range};
+
+use super::*;
+
+fn node(kind: NodeKind, name: &str, qualified_name: &str, path: &str) -> SymbolNode {
+    let uri = Uri::from_str(&format!("file://{path}")).unwrap();
+    let range = Range::new(Position::new(0, 0), Position::new(0, 1));
+    SymbolNode::new(kind, name, qualified_name, uri, range)
+}
+
+#[test]
+fn test_matches() {
+    let user = node(NodeKind::Type, "User", "app::model::User", "/src/model.rs");
+
+    assert!(SymbolQuery::new().matches(&user));
+    assert!(SymbolQuery::named("model::user").matches(&user));
+    assert!(!SymbolQuery::named("admin").matches(&user));
+    assert!(
+        SymbolQuery::named("user")
+            .with_kind(NodeKind::Trait)
+            .with_kind(NodeKind::Type)
+            .matches(&user)
+    );
+    assert!(
+        !SymbolQuery::new()
+            .with_kind(NodeKind::Method)
+            .matches(&user)
+    );
+
+    let uri = Uri::from_str("file:///src/model.rs").unwrap();
+    assert!(SymbolQuery::new().in_document(uri).matches(&user));
+    let other = Uri::from_str("file:///src/main.rs").unwrap();
+    assert!(!SymbolQuery::new().in_document(other).matches(&user));
+}
+
+#[test]
+fn test_rank_prefers_exact_names() {
+    let query = SymbolQuery::named("user");
+    assert_eq!(
+        query.rank(&node(NodeKind::Type, "User", "app::User", "/a.rs")),
+        0
+    );
+    assert_eq!(
+        query.rank(&node(NodeKind::Type, "UserId", "app::UserId", "/a.rs")),
+        1
+    );
+    assert_eq!(
+        SymbolQuery::named("app::User").rank(&node(NodeKind::Type, "User", "app::User", "/a.rs")),
+        0
+    );
+}
+
+#[test]
+fn test_symbol_info_serialization() {
+    let mut graph = crate::graph::KnowledgeGraph::new();
+    let user = graph.add_node(
+        node(NodeKind::Type, "User", "app::User", "/src/user.rs")
+            .with_doc(Range::new(Position::new(0, 0), Position::new(0, 12))),
+    );
+    let name = graph.add_node(
+        node(NodeKind::Field, "name", "app::User::name", "/src/user.rs").with_detail("String"),
+    );
+    graph.add_edge(user, EdgeKind::Defines, name);
+
+    let info = graph.describe(name).unwrap();
+    insta::assert_snapshot!(serde_json::to_string_pretty(&info).unwrap());
+}
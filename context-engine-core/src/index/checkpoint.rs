@@ -0,0 +1,134 @@
+//! The state of an indexing run that survives interruptions.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::str::FromStr;
+
+use lsp_types::Uri;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::graph::{KnowledgeGraph, SymbolId};
+use crate::index::IndexPhase;
+use crate::index::symbols::ImplRecord;
+
+/// The version of the checkpoint format; checkpoints of other versions are
+/// discarded.
+pub(crate) const CHECKPOINT_VERSION: u32 = 2;
+
+/// An indexed file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FileEntry {
+    /// The hash of the content the symbols were collected from
+    pub hash: u64,
+    /// The language server that reported the symbols
+    pub server: String,
+    /// The name and version the server reported when it was initialized
+    pub version: String,
+}
+
+/// The progress of an indexing run, saved after every batch so that an
+/// interrupted run resumes where it stopped.
+///
+/// The graph always contains the symbols of the indexed [files]; symbols
+/// whose relationships were collected are in [related].
+///
+/// [files]: IndexCheckpoint::files
+/// [related]: IndexCheckpoint::related
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct IndexCheckpoint {
+    /// The version of the format
+    pub version: u32,
+    /// The workspace root the checkpoint belongs to
+    pub root: String,
+    /// The phase the run was in
+    pub phase: Option<IndexPhase>,
+    /// The indexed files by URI
+    pub files: BTreeMap<String, FileEntry>,
+    /// The symbols whose relationships were collected
+    pub related: BTreeSet<SymbolId>,
+    /// The trait implementations of the indexed files
+    pub impls: Vec<ImplRecord>,
+    /// Symbols that belong to a type defined in another file, with the
+    /// qualified name of the type
+    pub owners: Vec<(SymbolId, String)>,
+    /// The graph built so far
+    pub graph: KnowledgeGraph,
+}
+
+impl IndexCheckpoint {
+    /// Creates an empty checkpoint for a workspace.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            version: CHECKPOINT_VERSION,
+            root: root.display().to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Loads the checkpoint of a workspace, or creates an empty one if the
+    /// file does not exist, cannot be decoded, or belongs to another
+    /// workspace or format version.
+    pub fn load(path: &Path, root: &Path) -> Self {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                if error.kind() != std::io::ErrorKind::NotFound {
+                    warn!(path = %path.display(), %error, "Failed to read index checkpoint");
+                }
+                return Self::new(root);
+            }
+        };
+
+        let config = bincode::config::standard();
+        match bincode::serde::decode_from_slice::<Self, _>(&bytes, config) {
+            Ok((checkpoint, _))
+                if checkpoint.version == CHECKPOINT_VERSION
+                    && checkpoint.root == root.display().to_string() =>
+            {
+                checkpoint
+            }
+            Ok(_) => Self::new(root),
+            Err(error) => {
+                warn!(path = %path.display(), %error, "Discarding corrupted index checkpoint");
+                Self::new(root)
+            }
+        }
+    }
+
+    /// Removes an indexed file and its symbols, e.g. because it changed.
+    pub fn forget(&mut self, uri: &str) {
+        self.files.remove(uri);
+        self.impls.retain(|record| record.uri != uri);
+        if let Ok(uri) = Uri::from_str(uri) {
+            self.graph.remove_document(&uri);
+        }
+        let graph = &self.graph;
+        self.owners.retain(|(id, _)| graph.node(*id).is_some());
+    }
+
+    /// Saves the checkpoint, logging failures: a lost checkpoint only costs
+    /// work when the run is resumed.
+    pub fn save(&self, path: &Path) {
+        let config = bincode::config::standard();
+        let result = bincode::serde::encode_to_vec(self, config)
+            .map_err(|error| error.to_string())
+            .and_then(|bytes| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+                }
+                // Write a temporary file first so that an interruption never
+                // leaves a truncated checkpoint
+                let temporary = path.with_extension("tmp");
+                std::fs::write(&temporary, bytes).map_err(|error| error.to_string())?;
+                std::fs::rename(&temporary, path).map_err(|error| error.to_string())
+            });
+        if let Err(error) = result {
+            warn!(path = %path.display(), %error, "Failed to save index checkpoint");
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/checkpoint.rs"]
+mod tests;
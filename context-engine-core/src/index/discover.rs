@@ -0,0 +1,51 @@
+//! Discovery of the source files of a workspace.
+
+use std::path::{Path, PathBuf};
+
+use tracing::warn;
+
+use crate::workspace::Glob;
+
+/// Names of directories that hold build output or installed dependencies
+/// rather than sources.
+pub(crate) const DEFAULT_EXCLUDES: &[&str] =
+    &["target", "node_modules", "__pycache__", "dist", "build"];
+
+/// Returns the files below `root`, sorted by path.
+///
+/// Hidden files and directories are skipped, as are the paths whose path
+/// relative to `root` matches one of the `excludes`; an excluded directory
+/// is not entered.
+pub(crate) fn discover(root: &Path, excludes: &[Glob]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(error) => {
+                warn!(path = %dir.display(), %error, "Failed to read workspace directory");
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            if hidden || excludes.iter().any(|glob| glob.is_match_path(relative)) {
+                continue;
+            }
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => pending.push(path),
+                Ok(file_type) if file_type.is_file() => files.push(path),
+                _ => {}
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+#[path = "tests/discover.rs"]
+mod tests;
@@ -0,0 +1,1122 @@
+//! The pipeline that populates the knowledge graph from language servers.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use lsp_types::notification::{DidCloseTextDocument, DidOpenTextDocument};
+use lsp_types::request::{
+    CallHierarchyOutgoingCalls, CallHierarchyPrepare, DocumentSymbolRequest, GotoImplementation,
+    GotoImplementationParams, TypeHierarchyPrepare, TypeHierarchySupertypes,
+    WorkspaceSymbolRequest,
+};
+use lsp_types::{
+    CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse,
+    GotoDefinitionResponse, Location, OneOf, Position, PositionEncodingKind, Range,
+    TextDocumentIdentifier, TextDocumentItem, TextDocumentPositionParams,
+    TypeHierarchyPrepareParams, TypeHierarchySupertypesParams, Uri, WorkspaceSymbolParams,
+    WorkspaceSymbolResponse,
+};
+use tokio::sync::{Semaphore, watch};
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
+
+use crate::cache::content_hash;
+use crate::document::{DocumentStore, TextDocument};
+use crate::error::ContextEngineError;
+use crate::graph::{Direction, EdgeKind, KnowledgeGraph, NodeKind, SymbolId, SymbolNode};
+use crate::index::checkpoint::{FileEntry, IndexCheckpoint};
+use crate::index::discover::{DEFAULT_EXCLUDES, discover};
+use crate::index::symbols::{FlatSymbol, SymbolSource};
+use crate::index::{IndexPhase, IndexProgress};
+use crate::lsp::{LanguageRegistry, LspSupervisor};
+use crate::types::UriExt;
+use crate::workspace::Glob;
+
+/// How an [`Indexer`] walks the workspace and talks to the servers.
+#[derive(Debug, Clone)]
+pub struct IndexConfig {
+    /// The maximum number of files each language server works on at the
+    /// same time
+    pub concurrency: usize,
+    /// The number of files after which the checkpoint is saved
+    pub batch_size: usize,
+    /// Paths relative to the workspace root that are not indexed
+    pub excludes: Vec<Glob>,
+    /// The file the progress of the run is saved to, so that an interrupted
+    /// run resumes where it stopped; runs always start over without it
+    pub checkpoint: Option<PathBuf>,
+}
+
+impl IndexConfig {
+    /// Saves the progress of runs to a checkpoint file.
+    pub fn with_checkpoint(mut self, path: impl Into<PathBuf>) -> Self {
+        self.checkpoint = Some(path.into());
+        self
+    }
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            batch_size: 64,
+            excludes: DEFAULT_EXCLUDES
+                .iter()
+                .filter_map(|name| Glob::new(name).ok())
+                .collect(),
+            checkpoint: None,
+        }
+    }
+}
+
+/// The result of an indexing run.
+#[derive(Debug, Clone)]
+pub struct IndexOutcome {
+    /// The graph of the workspace
+    pub graph: KnowledgeGraph,
+    /// The number of files whose symbols were collected by this run
+    pub indexed: usize,
+    /// The number of files taken over from the checkpoint because they did
+    /// not change
+    pub unchanged: usize,
+    /// Whether the run continued from a checkpoint
+    pub resumed: bool,
+    /// The [content hash](crate::cache::content_hash) of every indexed
+    /// file by URI, to tell whether a file changed since it was indexed
+    pub files: BTreeMap<String, u64>,
+    /// The requests that failed; the files they concern are retried by the
+    /// next run
+    pub errors: Vec<ContextEngineError>,
+}
+
+/// A source file of the workspace and the language server that indexes it.
+#[derive(Debug, Clone)]
+struct SourceFile {
+    /// The content, with positions in the encoding of the graph
+    document: Arc<TextDocument>,
+    hash: u64,
+    server: String,
+    /// Whether the file is open in the document store, in which case the
+    /// server already knows its content
+    open: bool,
+}
+
+/// The symbols a server reported for a file.
+enum SymbolResponse {
+    Nested(Vec<DocumentSymbol>),
+    Flat(Vec<FlatSymbol>),
+}
+
+/// A symbol whose relationships are requested, at the position of its name
+/// in the encoding of its server.
+#[derive(Debug, Clone, Copy)]
+struct RelationRequest {
+    id: SymbolId,
+    kind: NodeKind,
+    position: Position,
+}
+
+/// The other end of a relationship, as located by a server.
+#[derive(Debug, Clone)]
+struct RelationTarget {
+    id: SymbolId,
+    kind: EdgeKind,
+    direction: Direction,
+    location: Location,
+}
+
+/// The package a file belongs to.
+struct Package {
+    name: String,
+    dir: PathBuf,
+    /// The manifest of the package, or the workspace root
+    uri: Option<Uri>,
+}
+
+/// Populates a [`KnowledgeGraph`] from the language servers of a
+/// workspace.
+///
+/// A run
+///
+/// 1. walks the workspace for the files the [`LanguageRegistry`] routes to a
+///    started server of the [`LspSupervisor`],
+/// 2. collects the symbols of each file with `textDocument/documentSymbol`, or
+///    with `workspace/symbol` from servers without it, and adds a crate node
+///    per package and a module node per file, and
+/// 3. collects the relationships of types, traits, functions and methods with
+///    `typeHierarchy/supertypes`, `textDocument/implementation` and
+///    `callHierarchy/outgoingCalls`.
+///
+/// Each server works on at most [`IndexConfig::concurrency`] files at a
+/// time. Files that are not open in the [`DocumentStore`] are opened in the
+/// server for the duration of their requests. Progress is published as an
+/// [`IndexProgress`] and, with a checkpoint file configured, saved after
+/// every batch of files: a later run skips the files whose content did not
+/// change and the symbols whose relationships were already collected.
+///
+/// # Examples
+///
+/// ```no_run
+/// use context_engine_core::document::DocumentStore;
+/// use context_engine_core::index::{IndexConfig, Indexer};
+/// use context_engine_core::lsp::{LanguageRegistry, LspSupervisor};
+/// use std::sync::Arc;
+///
+/// # async fn example() {
+/// let documents = Arc::new(DocumentStore::default());
+/// let registry = Arc::new(LanguageRegistry::with_defaults());
+/// let supervisor = Arc::new(LspSupervisor::new("/path/to/workspace", Arc::clone(&documents)));
+/// for server in registry.servers() {
+///     let _ = supervisor.start(server.clone());
+/// }
+///
+/// let config = IndexConfig::default().with_checkpoint("/path/to/workspace/.index");
+/// let indexer = Indexer::new("/path/to/workspace", supervisor, registry, documents, config);
+///
+/// let mut progress = indexer.progress();
+/// tokio::spawn(async move {
+///     while progress.changed().await.is_ok() {
+///         println!("{}%", progress.borrow().percentage());
+///     }
+/// });
+///
+/// let outcome = indexer.run().await;
+/// println!("{} symbols", outcome.graph.len());
+/// # }
+/// ```
+pub struct Indexer {
+    root: PathBuf,
+    supervisor: Arc<LspSupervisor>,
+    registry: Arc<LanguageRegistry>,
+    documents: Arc<DocumentStore>,
+    config: IndexConfig,
+    progress: watch::Sender<IndexProgress>,
+}
+
+impl Indexer {
+    /// Creates an indexer.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The workspace root
+    /// * `supervisor` - The language servers to query
+    /// * `registry` - Routes files to the servers
+    /// * `documents` - The open documents, whose content takes precedence over
+    ///   the files on disk; the graph uses their position encoding
+    /// * `config` - How to index
+    pub fn new(
+        root: impl Into<PathBuf>,
+        supervisor: Arc<LspSupervisor>,
+        registry: Arc<LanguageRegistry>,
+        documents: Arc<DocumentStore>,
+        config: IndexConfig,
+    ) -> Self {
+        Self {
+            root: root.into(),
+            supervisor,
+            registry,
+            documents,
+            config,
+            progress: watch::Sender::new(IndexProgress::default()),
+        }
+    }
+
+    /// Returns a receiver that observes the progress of runs.
+    pub fn progress(&self) -> watch::Receiver<IndexProgress> {
+        self.progress.subscribe()
+    }
+
+    /// Indexes the workspace.
+    ///
+    /// Failed requests do not abort the run; they are logged and returned
+    /// in the [`IndexOutcome`], and the files they concern are not marked
+    /// as indexed.
+    pub async fn run(&self) -> IndexOutcome {
+        self.report(IndexPhase::Discovering, 0, 0);
+        let mut errors = Vec::new();
+        let files = self.sources();
+        let encodings = self.server_encodings(&files, &mut errors).await;
+
+        let mut checkpoint = match &self.config.checkpoint {
+            Some(path) => IndexCheckpoint::load(path, &self.root),
+            None => IndexCheckpoint::new(&self.root),
+        };
+        let resumed = !checkpoint.files.is_empty();
+
+        // Forget the files that changed, disappeared or moved to another
+        // server or server version since the checkpoint; the relationships
+        // of all symbols may depend on them
+        let current = files
+            .iter()
+            .map(|file| (file.document.uri().as_str(), file))
+            .collect::<HashMap<_, _>>();
+        let stale = checkpoint
+            .files
+            .iter()
+            .filter(|(uri, entry)| {
+                current.get(uri.as_str()).map_or(true, |file| {
+                    file.hash != entry.hash
+                        || file.server != entry.server
+                        || self
+                            .supervisor
+                            .server_version(&file.server)
+                            .is_some_and(|version| version != entry.version)
+                })
+            })
+            .map(|(uri, _)| uri.clone())
+            .collect::<Vec<_>>();
+        for uri in &stale {
+            debug!(uri, "Reindexing changed file");
+            checkpoint.forget(uri);
+        }
+        if !stale.is_empty() {
+            checkpoint.related.clear();
+        }
+
+        let available = |file: &&SourceFile| encodings.contains_key(&file.server);
+        let pending = files
+            .iter()
+            .filter(available)
+            .filter(|file| !checkpoint.files.contains_key(file.document.uri().as_str()))
+            .collect::<Vec<_>>();
+        let unchanged = checkpoint.files.len();
+        let indexed = self
+            .collect_symbols(
+                &mut checkpoint,
+                &pending,
+                unchanged,
+                &encodings,
+                &mut errors,
+            )
+            .await;
+
+        link(&mut checkpoint);
+        self.collect_relations(&mut checkpoint, &current, &encodings, &mut errors)
+            .await;
+
+        checkpoint.phase = Some(IndexPhase::Done);
+        self.save(&checkpoint);
+        self.report(IndexPhase::Done, 0, 0);
+        IndexOutcome {
+            files: checkpoint
+                .files
+                .into_iter()
+                .map(|(uri, entry)| (uri, entry.hash))
+                .collect(),
+            graph: checkpoint.graph,
+            indexed,
+            unchanged,
+            resumed,
+            errors,
+        }
+    }
+
+    /// Returns the files of the workspace that a started server handles.
+    fn sources(&self) -> Vec<SourceFile> {
+        let encoding = self.documents.encoding();
+        discover(&self.root, &self.config.excludes)
+            .into_iter()
+            .filter_map(|path| {
+                let uri = Uri::from_file_path(&path).ok()?;
+                let (text, open) = match self.documents.get(&uri) {
+                    Some(document) => (document.text().to_string(), true),
+                    None => (std::fs::read_to_string(&path).ok()?, false),
+                };
+                let language_id = self.registry.language_id(&uri, Some(&text))?.to_string();
+                let server = self
+                    .registry
+                    .route(&uri, Some(&text))
+                    .into_iter()
+                    .map(|route| route.server.name.clone())
+                    .find(|name| self.supervisor.status(name).is_some())?;
+
+                Some(SourceFile {
+                    hash: content_hash(text.as_bytes()),
+                    document: Arc::new(TextDocument::new(
+                        uri,
+                        language_id,
+                        0,
+                        text,
+                        encoding.clone(),
+                    )),
+                    server,
+                    open,
+                })
+            })
+            .collect()
+    }
+
+    /// Waits for the servers of the files and returns their position
+    /// encodings; servers that fail to start are left out.
+    async fn server_encodings(
+        &self,
+        files: &[SourceFile],
+        errors: &mut Vec<ContextEngineError>,
+    ) -> HashMap<String, PositionEncodingKind> {
+        let servers = files
+            .iter()
+            .map(|file| file.server.as_str())
+            .collect::<HashSet<_>>();
+        let mut encodings = HashMap::new();
+        for server in servers {
+            match self.supervisor.client(server).await {
+                Ok(client) => {
+                    encodings.insert(
+                        server.to_string(),
+                        client.capabilities().position_encoding(),
+                    );
+                }
+                Err(error) => {
+                    warn!(server, %error, "Not indexing the files of an unavailable language server");
+                    errors.push(error);
+                }
+            }
+        }
+        encodings
+    }
+
+    /// Collects the symbols of the pending files in batches, saving the
+    /// checkpoint after each batch.
+    ///
+    /// # Returns
+    ///
+    /// The number of files whose symbols were collected.
+    async fn collect_symbols(
+        &self,
+        checkpoint: &mut IndexCheckpoint,
+        pending: &[&SourceFile],
+        unchanged: usize,
+        encodings: &HashMap<String, PositionEncodingKind>,
+        errors: &mut Vec<ContextEngineError>,
+    ) -> usize {
+        let total = unchanged + pending.len();
+        let mut done = unchanged;
+        self.report(IndexPhase::Symbols, done, total);
+        checkpoint.phase = Some(IndexPhase::Symbols);
+
+        let workspace_symbols = self.workspace_symbols(pending, errors).await;
+        let semaphores = self.semaphores(encodings);
+        let mut indexed = 0;
+        for batch in pending.chunks(self.config.batch_size.max(1)) {
+            let mut tasks = JoinSet::new();
+            let mut results = Vec::new();
+            for (index, file) in batch.iter().enumerate() {
+                if let Some(symbols) = workspace_symbols.get(&file.server) {
+                    let symbols = symbols
+                        .get(file.document.uri().as_str())
+                        .cloned()
+                        .unwrap_or_default();
+                    results.push((index, Ok(SymbolResponse::Flat(symbols))));
+                    continue;
+                }
+                let Some(semaphore) = semaphores.get(&file.server) else {
+                    continue;
+                };
+                let supervisor = Arc::clone(&self.supervisor);
+                let semaphore = Arc::clone(semaphore);
+                let file = (*file).clone();
+                tasks.spawn(async move {
+                    let _permit = semaphore.acquire_owned().await.ok();
+                    (index, document_symbols(&supervisor, &file).await)
+                });
+            }
+            while let Some(joined) = tasks.join_next().await {
+                match joined {
+                    Ok(result) => results.push(result),
+                    Err(error) => warn!(%error, "Symbol indexing task failed"),
+                }
+            }
+
+            // Add the files in a fixed order, so that symbol ids do not
+            // depend on the order the servers answered in
+            results.sort_by_key(|(index, _)| *index);
+            for (index, result) in results {
+                let Some(file) = batch.get(index) else {
+                    continue;
+                };
+                match result {
+                    Ok(response) => {
+                        if let Some(encoding) = encodings.get(&file.server) {
+                            self.add_file(checkpoint, file, response, encoding);
+                            indexed += 1;
+                        }
+                    }
+                    Err(error) => {
+                        warn!(uri = file.document.uri().as_str(), %error, "Failed to collect symbols");
+                        errors.push(error);
+                    }
+                }
+            }
+
+            done += batch.len();
+            self.save(checkpoint);
+            self.report(IndexPhase::Symbols, done, total);
+        }
+        indexed
+    }
+
+    /// Requests the symbols of the whole workspace from the servers of the
+    /// pending files that do not provide document symbols.
+    ///
+    /// # Returns
+    ///
+    /// The symbols by server and URI.
+    async fn workspace_symbols(
+        &self,
+        pending: &[&SourceFile],
+        errors: &mut Vec<ContextEngineError>,
+    ) -> HashMap<String, HashMap<String, Vec<FlatSymbol>>> {
+        let servers = pending
+            .iter()
+            .map(|file| file.server.as_str())
+            .filter(|server| {
+                !self
+                    .supervisor
+                    .supports(server, "textDocument/documentSymbol")
+            })
+            .collect::<HashSet<_>>();
+
+        let mut symbols = HashMap::new();
+        for server in servers {
+            let mut by_uri: HashMap<String, Vec<FlatSymbol>> = HashMap::new();
+            if self.supervisor.supports(server, "workspace/symbol") {
+                let params = WorkspaceSymbolParams {
+                    query: String::new(),
+                    ..WorkspaceSymbolParams::default()
+                };
+                let response = self
+                    .supervisor
+                    .request::<WorkspaceSymbolRequest>(server, params)
+                    .await;
+                let found = match response {
+                    Ok(Some(WorkspaceSymbolResponse::Flat(found))) => found
+                        .iter()
+                        .map(|symbol| (symbol.location.uri.clone(), FlatSymbol::from(symbol)))
+                        .collect(),
+                    Ok(Some(WorkspaceSymbolResponse::Nested(found))) => found
+                        .into_iter()
+                        .filter_map(|symbol| match symbol.location {
+                            OneOf::Left(location) => Some((
+                                location.uri,
+                                FlatSymbol {
+                                    name: symbol.name,
+                                    kind: symbol.kind,
+                                    container_name: symbol.container_name,
+                                    range: location.range,
+                                },
+                            )),
+                            OneOf::Right(_) => None,
+                        })
+                        .collect(),
+                    Ok(None) => Vec::new(),
+                    Err(error) => {
+                        warn!(server, %error, "Failed to collect workspace symbols");
+                        errors.push(error);
+                        Vec::new()
+                    }
+                };
+                for (uri, symbol) in found {
+                    by_uri
+                        .entry(uri.as_str().to_string())
+                        .or_default()
+                        .push(symbol);
+                }
+            }
+            symbols.insert(server.to_string(), by_uri);
+        }
+        symbols
+    }
+
+    /// Adds the module node and the symbols of a file to the graph.
+    fn add_file(
+        &self,
+        checkpoint: &mut IndexCheckpoint,
+        file: &SourceFile,
+        response: SymbolResponse,
+        encoding: &PositionEncodingKind,
+    ) {
+        let document = &file.document;
+        let path = document.uri().to_file_path().unwrap_or_default();
+        let separator = match document.language_id() {
+            "rust" => "::",
+            _ => ".",
+        };
+        let package = package(&self.root, &path, document.language_id());
+        let module = module_name(&package, &path, separator);
+        let graph = &mut checkpoint.graph;
+        let known = graph
+            .lookup(&package.name)
+            .iter()
+            .any(|node| node.kind == NodeKind::Crate);
+        if let Some(uri) = package.uri.filter(|_| !known) {
+            graph.add_node(SymbolNode::new(
+                NodeKind::Crate,
+                package.name.as_str(),
+                package.name.as_str(),
+                uri,
+                Range::default(),
+            ));
+        }
+
+        let source = SymbolSource::new(Arc::clone(document), encoding);
+        let name = module.rsplit(separator).next().unwrap_or(&module);
+        let module_id = graph.add_node(source.module_node(name, &module));
+        let symbols = match response {
+            SymbolResponse::Nested(symbols) => {
+                source.document_symbols(&module, separator, &symbols)
+            }
+            SymbolResponse::Flat(symbols) => source.flat_symbols(&module, separator, &symbols),
+        };
+
+        let mut ids = Vec::with_capacity(symbols.symbols.len());
+        for symbol in symbols.symbols {
+            let id = graph.add_node(symbol.node);
+            match (
+                symbol.owner,
+                symbol.parent.and_then(|parent| ids.get(parent)),
+            ) {
+                (Some(owner), _) => checkpoint.owners.push((id, owner)),
+                (None, Some(parent)) => {
+                    graph.add_edge(*parent, EdgeKind::Defines, id);
+                }
+                (None, None) => {
+                    graph.add_edge(module_id, EdgeKind::Defines, id);
+                }
+            }
+            ids.push(id);
+        }
+        checkpoint.impls.extend(symbols.impls);
+        checkpoint.files.insert(
+            document.uri().as_str().to_string(),
+            FileEntry {
+                hash: file.hash,
+                server: file.server.clone(),
+                version: self
+                    .supervisor
+                    .server_version(&file.server)
+                    .unwrap_or_default(),
+            },
+        );
+    }
+
+    /// Collects the relationships of the symbols that do not have them yet,
+    /// file by file in batches, saving the checkpoint after each batch.
+    async fn collect_relations(
+        &self,
+        checkpoint: &mut IndexCheckpoint,
+        files: &HashMap<&str, &SourceFile>,
+        encodings: &HashMap<String, PositionEncodingKind>,
+        errors: &mut Vec<ContextEngineError>,
+    ) {
+        checkpoint.phase = Some(IndexPhase::Relations);
+        let mut pending: BTreeMap<&str, Vec<RelationRequest>> = BTreeMap::new();
+        for node in checkpoint.graph.nodes() {
+            let relates = matches!(
+                node.kind,
+                NodeKind::Type | NodeKind::Trait | NodeKind::Function | NodeKind::Method
+            );
+            if !relates || checkpoint.related.contains(&node.id()) {
+                continue;
+            }
+            let Some(file) = files.get(node.uri.as_str()) else {
+                continue;
+            };
+            let Some(encoding) = encodings.get(&file.server) else {
+                continue;
+            };
+            let Some(position) = name_position(&file.document, node, encoding) else {
+                continue;
+            };
+            pending
+                .entry(file.document.uri().as_str())
+                .or_default()
+                .push(RelationRequest {
+                    id: node.id(),
+                    kind: node.kind,
+                    position,
+                });
+        }
+
+        let total = pending.len();
+        let mut done = 0;
+        self.report(IndexPhase::Relations, done, total);
+        let semaphores = self.semaphores(encodings);
+        let pending = pending.into_iter().collect::<Vec<_>>();
+        for batch in pending.chunks(self.config.batch_size.max(1)) {
+            let mut tasks = JoinSet::new();
+            for (uri, requests) in batch {
+                let Some(file) = files.get(uri) else {
+                    continue;
+                };
+                let Some(semaphore) = semaphores.get(&file.server) else {
+                    continue;
+                };
+                let supervisor = Arc::clone(&self.supervisor);
+                let semaphore = Arc::clone(semaphore);
+                let file = (*file).clone();
+                let requests = requests.clone();
+                tasks.spawn(async move {
+                    let _permit = semaphore.acquire_owned().await.ok();
+                    relations(&supervisor, &file, &requests).await
+                });
+            }
+            let mut results = Vec::new();
+            while let Some(joined) = tasks.join_next().await {
+                match joined {
+                    Ok(result) => results.push(result),
+                    Err(error) => warn!(%error, "Relation indexing task failed"),
+                }
+            }
+
+            results.sort_by_key(|(ids, _, _)| ids.first().copied());
+            for (ids, targets, failures) in results {
+                for target in targets {
+                    add_relation(&mut checkpoint.graph, files, encodings, &target);
+                }
+                if failures.is_empty() {
+                    checkpoint.related.extend(ids);
+                }
+                errors.extend(failures);
+            }
+
+            done += batch.len();
+            self.save(checkpoint);
+            self.report(IndexPhase::Relations, done, total);
+        }
+    }
+
+    /// Returns a semaphore per server that bounds its concurrent work.
+    fn semaphores(
+        &self,
+        encodings: &HashMap<String, PositionEncodingKind>,
+    ) -> HashMap<String, Arc<Semaphore>> {
+        encodings
+            .keys()
+            .map(|server| {
+                let permits = self.config.concurrency.max(1);
+                (server.clone(), Arc::new(Semaphore::new(permits)))
+            })
+            .collect()
+    }
+
+    fn save(&self, checkpoint: &IndexCheckpoint) {
+        if let Some(path) = &self.config.checkpoint {
+            checkpoint.save(path);
+        }
+    }
+
+    fn report(&self, phase: IndexPhase, done: usize, total: usize) {
+        self.progress
+            .send_replace(IndexProgress::new(phase, done, total));
+    }
+}
+
+/// Opens a file in its server unless the document store already did.
+async fn open(supervisor: &LspSupervisor, file: &SourceFile) -> Result<(), ContextEngineError> {
+    if file.open {
+        return Ok(());
+    }
+    let client = supervisor.client(&file.server).await?;
+    client.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem::new(
+            file.document.uri().clone(),
+            file.document.language_id().to_string(),
+            0,
+            file.document.text().to_string(),
+        ),
+    })
+}
+
+/// Closes a file that [`open`] opened.
+async fn close(supervisor: &LspSupervisor, file: &SourceFile) {
+    if file.open {
+        return;
+    }
+    if let Ok(client) = supervisor.client(&file.server).await {
+        let params = DidCloseTextDocumentParams {
+            text_document: TextDocumentIdentifier::new(file.document.uri().clone()),
+        };
+        if let Err(error) = client.notify::<DidCloseTextDocument>(params) {
+            debug!(%error, "Failed to close indexed document");
+        }
+    }
+}
+
+/// Requests the symbols of a file.
+async fn document_symbols(
+    supervisor: &LspSupervisor,
+    file: &SourceFile,
+) -> Result<SymbolResponse, ContextEngineError> {
+    open(supervisor, file).await?;
+    let params = DocumentSymbolParams {
+        text_document: TextDocumentIdentifier::new(file.document.uri().clone()),
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    };
+    let response = supervisor
+        .request::<DocumentSymbolRequest>(&file.server, params)
+        .await;
+    close(supervisor, file).await;
+
+    Ok(match response? {
+        Some(DocumentSymbolResponse::Nested(symbols)) => SymbolResponse::Nested(symbols),
+        Some(DocumentSymbolResponse::Flat(symbols)) => {
+            SymbolResponse::Flat(symbols.iter().map(FlatSymbol::from).collect())
+        }
+        None => SymbolResponse::Nested(Vec::new()),
+    })
+}
+
+/// Requests the relationships of the symbols of a file.
+///
+/// # Returns
+///
+/// The symbols, the other ends of their relationships, and the errors of
+/// the requests that failed.
+async fn relations(
+    supervisor: &LspSupervisor,
+    file: &SourceFile,
+    requests: &[RelationRequest],
+) -> (Vec<SymbolId>, Vec<RelationTarget>, Vec<ContextEngineError>) {
+    let ids = requests.iter().map(|request| request.id).collect();
+    let mut targets = Vec::new();
+    let mut errors = Vec::new();
+    if let Err(error) = open(supervisor, file).await {
+        errors.push(error);
+        return (ids, targets, errors);
+    }
+
+    let server = file.server.as_str();
+    let supports = |method: &str| supervisor.supports(server, method);
+    for request in requests {
+        let position = TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(file.document.uri().clone()),
+            request.position,
+        );
+        let target = |kind, direction, location| RelationTarget {
+            id: request.id,
+            kind,
+            direction,
+            location,
+        };
+
+        let types = matches!(request.kind, NodeKind::Type | NodeKind::Trait);
+        if types && supports("textDocument/prepareTypeHierarchy") {
+            let result = async {
+                let params = TypeHierarchyPrepareParams {
+                    text_document_position_params: position.clone(),
+                    work_done_progress_params: Default::default(),
+                };
+                let items = supervisor
+                    .request::<TypeHierarchyPrepare>(server, params)
+                    .await?
+                    .unwrap_or_default();
+                let Some(item) = items.into_iter().next() else {
+                    return Ok(Vec::new());
+                };
+                let params = TypeHierarchySupertypesParams {
+                    item,
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                };
+                let supertypes = supervisor
+                    .request::<TypeHierarchySupertypes>(server, params)
+                    .await?
+                    .unwrap_or_default();
+                Ok::<_, ContextEngineError>(supertypes)
+            };
+            match result.await {
+                Ok(supertypes) => targets.extend(supertypes.into_iter().map(|item| {
+                    let location = Location::new(item.uri, item.selection_range);
+                    target(EdgeKind::Implements, Direction::Outgoing, location)
+                })),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        if request.kind == NodeKind::Trait && supports("textDocument/implementation") {
+            let params = GotoImplementationParams {
+                text_document_position_params: position.clone(),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            };
+            match supervisor
+                .request::<GotoImplementation>(server, params)
+                .await
+            {
+                Ok(response) => {
+                    let locations = match response {
+                        Some(GotoDefinitionResponse::Scalar(location)) => vec![location],
+                        Some(GotoDefinitionResponse::Array(locations)) => locations,
+                        Some(GotoDefinitionResponse::Link(links)) => links
+                            .into_iter()
+                            .map(|link| Location::new(link.target_uri, link.target_selection_range))
+                            .collect(),
+                        None => Vec::new(),
+                    };
+                    targets.extend(locations.into_iter().map(|location| {
+                        target(EdgeKind::Implements, Direction::Incoming, location)
+                    }));
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+
+        let callable = matches!(request.kind, NodeKind::Function | NodeKind::Method);
+        if callable && supports("textDocument/prepareCallHierarchy") {
+            let result = async {
+                let params = CallHierarchyPrepareParams {
+                    text_document_position_params: position.clone(),
+                    work_done_progress_params: Default::default(),
+                };
+                let items = supervisor
+                    .request::<CallHierarchyPrepare>(server, params)
+                    .await?
+                    .unwrap_or_default();
+                let Some(item) = items.into_iter().next() else {
+                    return Ok(Vec::new());
+                };
+                let params = CallHierarchyOutgoingCallsParams {
+                    item,
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                };
+                let calls = supervisor
+                    .request::<CallHierarchyOutgoingCalls>(server, params)
+                    .await?
+                    .unwrap_or_default();
+                Ok::<_, ContextEngineError>(calls)
+            };
+            match result.await {
+                Ok(calls) => targets.extend(calls.into_iter().map(|call| {
+                    let location = Location::new(call.to.uri, call.to.selection_range);
+                    target(EdgeKind::Calls, Direction::Outgoing, location)
+                })),
+                Err(error) => errors.push(error),
+            }
+        }
+    }
+
+    close(supervisor, file).await;
+    (ids, targets, errors)
+}
+
+/// Adds the edge of a relationship if its other end is a symbol of the
+/// graph of a fitting kind.
+fn add_relation(
+    graph: &mut KnowledgeGraph,
+    files: &HashMap<&str, &SourceFile>,
+    encodings: &HashMap<String, PositionEncodingKind>,
+    target: &RelationTarget,
+) {
+    let Some(file) = files.get(target.location.uri.as_str()) else {
+        return;
+    };
+    let Some(encoding) = encodings.get(&file.server) else {
+        return;
+    };
+    let document = &file.document;
+    let Ok(offset) = document
+        .line_index()
+        .offset(target.location.range.start, encoding)
+    else {
+        return;
+    };
+    let Ok(position) = document.position(offset) else {
+        return;
+    };
+    let Some(other) = graph.symbol_at(document.uri(), position) else {
+        return;
+    };
+
+    let fits = match (target.kind, target.direction) {
+        (EdgeKind::Implements, Direction::Outgoing) => {
+            matches!(other.kind, NodeKind::Type | NodeKind::Trait)
+        }
+        (EdgeKind::Implements, Direction::Incoming) => other.kind == NodeKind::Type,
+        (EdgeKind::Calls, _) => matches!(other.kind, NodeKind::Function | NodeKind::Method),
+        _ => false,
+    };
+    let other = other.id();
+    if !fits || other == target.id {
+        return;
+    }
+    match target.direction {
+        Direction::Outgoing => graph.add_edge(target.id, target.kind, other),
+        Direction::Incoming => graph.add_edge(other, target.kind, target.id),
+    };
+}
+
+/// Adds the edges that can only be resolved once the symbols of all files
+/// are known: modules to their parent modules or crates, members to the
+/// types of other files they belong to, and types to the traits of their
+/// `impl` blocks.
+fn link(checkpoint: &mut IndexCheckpoint) {
+    let graph = &mut checkpoint.graph;
+    let defined = |graph: &KnowledgeGraph, id: SymbolId| {
+        graph
+            .edges_of(id, Direction::Incoming)
+            .iter()
+            .any(|edge| edge.kind == EdgeKind::Defines)
+    };
+    let first = |graph: &KnowledgeGraph, name: &str, kind: NodeKind, except: SymbolId| {
+        graph
+            .lookup(name)
+            .into_iter()
+            .filter(|node| node.kind == kind && node.id() != except)
+            .map(SymbolNode::id)
+            .min()
+    };
+
+    let modules = graph
+        .nodes()
+        .filter(|node| node.kind == NodeKind::Module && !defined(graph, node.id()))
+        .map(|node| (node.id(), node.qualified_name.clone()))
+        .collect::<Vec<_>>();
+    for (id, qualified_name) in modules {
+        // The root module of a package belongs to its crate, other modules
+        // to the module their name extends
+        let mut parent = first(graph, &qualified_name, NodeKind::Crate, id);
+        let mut name = qualified_name.as_str();
+        while parent.is_none() {
+            let Some((prefix, _)) = name.rsplit_once("::").or_else(|| name.rsplit_once('.')) else {
+                break;
+            };
+            parent = first(graph, prefix, NodeKind::Module, id)
+                .or_else(|| first(graph, prefix, NodeKind::Crate, id));
+            name = prefix;
+        }
+        if let Some(parent) = parent {
+            graph.add_edge(parent, EdgeKind::Defines, id);
+        }
+    }
+
+    for (id, owner) in &checkpoint.owners {
+        let Some(node) = graph.node(*id) else {
+            continue;
+        };
+        if defined(graph, *id) {
+            continue;
+        }
+        let module = || {
+            graph
+                .symbols_in(&node.uri)
+                .into_iter()
+                .find(|node| node.kind == NodeKind::Module)
+                .map(SymbolNode::id)
+        };
+        if let Some(parent) = first(graph, owner, NodeKind::Type, *id).or_else(module) {
+            graph.add_edge(parent, EdgeKind::Defines, *id);
+        }
+    }
+
+    for record in &checkpoint.impls {
+        let implementor = first(
+            graph,
+            &record.type_name,
+            NodeKind::Type,
+            SymbolId::default(),
+        );
+        let implemented = graph
+            .nodes()
+            .filter(|node| node.kind == NodeKind::Trait && node.name == record.trait_name)
+            .map(SymbolNode::id)
+            .min();
+        if let Some((implementor, implemented)) = implementor.zip(implemented) {
+            graph.add_edge(implementor, EdgeKind::Implements, implemented);
+        }
+    }
+}
+
+/// Returns the position of the name of a symbol in the encoding of its
+/// server, or the start of its signature if the name is not found there.
+fn name_position(
+    document: &TextDocument,
+    node: &SymbolNode,
+    encoding: &PositionEncodingKind,
+) -> Option<Position> {
+    let start = document.offset(node.signature.start).ok()?;
+    let end = document.offset(node.signature.end).ok()?;
+    let signature = document.text().get(start..end)?;
+    let offset = start + signature.find(node.name.as_str()).unwrap_or_default();
+    document.line_index().position(offset, encoding).ok()
+}
+
+/// Returns the package a file belongs to: the nearest Rust crate or npm
+/// package around it, or else the workspace.
+fn package(root: &Path, path: &Path, language_id: &str) -> Package {
+    let manifest = match language_id {
+        "rust" => Some("Cargo.toml"),
+        "typescript" | "typescriptreact" | "javascript" | "javascriptreact" => Some("package.json"),
+        _ => None,
+    };
+
+    let found = manifest.and_then(|manifest| {
+        path.ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(root))
+            .find_map(|dir| {
+                let file = dir.join(manifest);
+                let text = std::fs::read_to_string(&file).ok()?;
+                let name = match manifest {
+                    "Cargo.toml" => {
+                        let table = toml::from_str::<toml::Table>(&text).ok()?;
+                        table
+                            .get("package")?
+                            .get("name")?
+                            .as_str()?
+                            .replace('-', "_")
+                    }
+                    _ => {
+                        let value = serde_json::from_str::<serde_json::Value>(&text).ok()?;
+                        value.get("name")?.as_str()?.to_string()
+                    }
+                };
+                Some(Package {
+                    name,
+                    dir: dir.to_path_buf(),
+                    uri: Uri::from_file_path(&file).ok(),
+                })
+            })
+    });
+    found.unwrap_or_else(|| Package {
+        name: root.file_name().map_or_else(
+            || "workspace".to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        ),
+        dir: root.to_path_buf(),
+        uri: Uri::from_file_path(root).ok(),
+    })
+}
+
+/// Returns the qualified name of the module of a file: the package name
+/// followed by the path of the file in the package, without a leading
+/// `src` folder and without file names that stand for their folder, such as
+/// `mod.rs` and `__init__.py`.
+fn module_name(package: &Package, path: &Path, separator: &str) -> String {
+    let relative = path.strip_prefix(&package.dir).unwrap_or(path);
+    let relative = relative.strip_prefix("src").unwrap_or(relative);
+    let mut segments = relative
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if let Some(file) = segments.pop() {
+        let stem = file.split('.').next().unwrap_or(&file).to_string();
+        if !["lib", "main", "mod", "index", "__init__"].contains(&stem.as_str()) {
+            segments.push(stem);
+        }
+    }
+    std::iter::once(package.name.clone())
+        .chain(segments)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+#[cfg(test)]
+#[path = "tests/indexer.rs"]
+mod tests;
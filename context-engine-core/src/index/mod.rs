@@ -0,0 +1,33 @@
+//! Population of the knowledge graph from language servers.
+//!
+//! The [`Indexer`] walks the workspace, asks the language servers for the
+//! symbols of every file and for the relationships between them, and turns
+//! the answers into the nodes and edges of a
+//! [`KnowledgeGraph`](crate::graph::KnowledgeGraph). The parts of each
+//! definition, i.e. its signature, body and doc comment, come from the
+//! [syntax layer](crate::syntax). Runs report their [`IndexProgress`] and,
+//! with a checkpoint file, resume after an interruption without repeating
+//! the work on unchanged files.
+//!
+//! ## Structs
+//!
+//! * [`Indexer`] - Populates a knowledge graph from the language servers of a
+//!   workspace.
+//! * [`IndexConfig`] - How an indexer walks the workspace and talks to the
+//!   servers.
+//! * [`IndexOutcome`] - The graph and statistics of an indexing run.
+//! * [`IndexProgress`] - How far an indexing run got.
+//!
+//! ## Enums
+//!
+//! * [`IndexPhase`] - The phases of an indexing run.
+
+mod checkpoint;
+mod discover;
+mod indexer;
+mod progress;
+mod symbols;
+
+pub(crate) use discover::{DEFAULT_EXCLUDES, discover};
+pub use indexer::{IndexConfig, IndexOutcome, Indexer};
+pub use progress::{IndexPhase, IndexProgress};
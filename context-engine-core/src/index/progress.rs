@@ -0,0 +1,138 @@
+//! Progress of an indexing run.
+
+use std::fmt;
+
+use lsp_types::{
+    WorkDoneProgress, WorkDoneProgressBegin, WorkDoneProgressEnd, WorkDoneProgressReport,
+};
+use serde::{Deserialize, Serialize};
+
+/// The phases of an indexing run, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexPhase {
+    /// Walking the workspace for source files
+    Discovering,
+    /// Collecting the symbols of each file
+    Symbols,
+    /// Collecting the relationships of each symbol: implementations, type
+    /// hierarchies and calls
+    Relations,
+    /// The run is complete
+    Done,
+}
+
+impl IndexPhase {
+    /// Returns the name of the phase.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndexPhase::Discovering => "discovering",
+            IndexPhase::Symbols => "symbols",
+            IndexPhase::Relations => "relations",
+            IndexPhase::Done => "done",
+        }
+    }
+}
+
+impl fmt::Display for IndexPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How far an indexing run got.
+///
+/// The symbol phase makes up the first half of the [percentage] and the
+/// relation phase the second half, in the spirit of LSP `$/progress`
+/// reports.
+///
+/// [percentage]: IndexProgress::percentage
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::index::{IndexPhase, IndexProgress};
+///
+/// let progress = IndexProgress::new(IndexPhase::Relations, 30, 120);
+/// assert_eq!(progress.percentage(), 62);
+/// assert_eq!(progress.message(), "relations 30/120");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexProgress {
+    /// The current phase
+    pub phase: IndexPhase,
+    /// The number of files the phase is done with
+    pub done: usize,
+    /// The number of files of the phase
+    pub total: usize,
+}
+
+impl IndexProgress {
+    /// Creates a progress report.
+    pub fn new(phase: IndexPhase, done: usize, total: usize) -> Self {
+        Self { phase, done, total }
+    }
+
+    /// Returns the progress of the whole run, from 0 to 100.
+    pub fn percentage(&self) -> u32 {
+        let (start, span) = match self.phase {
+            IndexPhase::Discovering => return 0,
+            IndexPhase::Symbols => (0, 50),
+            IndexPhase::Relations => (50, 50),
+            IndexPhase::Done => return 100,
+        };
+        let done = self.done.min(self.total) as u64;
+        let share = match self.total {
+            0 => span,
+            total => done * span / total as u64,
+        };
+        (start + share) as u32
+    }
+
+    /// Returns true if the run is complete.
+    pub fn is_done(&self) -> bool {
+        self.phase == IndexPhase::Done
+    }
+
+    /// Returns a short description of the progress, e.g. `symbols 3/10`.
+    pub fn message(&self) -> String {
+        match self.phase {
+            IndexPhase::Discovering | IndexPhase::Done => self.phase.to_string(),
+            _ => format!("{} {}/{}", self.phase, self.done, self.total),
+        }
+    }
+
+    /// Converts the progress into the value of an LSP `$/progress`
+    /// notification: `begin` while discovering, `end` when done and
+    /// `report` in between.
+    pub fn to_work_done(&self) -> WorkDoneProgress {
+        match self.phase {
+            IndexPhase::Discovering => WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: "Indexing".to_string(),
+                cancellable: Some(false),
+                message: Some(self.message()),
+                percentage: Some(0),
+            }),
+            IndexPhase::Symbols | IndexPhase::Relations => {
+                WorkDoneProgress::Report(WorkDoneProgressReport {
+                    cancellable: Some(false),
+                    message: Some(self.message()),
+                    percentage: Some(self.percentage()),
+                })
+            }
+            IndexPhase::Done => WorkDoneProgress::End(WorkDoneProgressEnd {
+                message: Some(self.message()),
+            }),
+        }
+    }
+}
+
+impl Default for IndexProgress {
+    fn default() -> Self {
+        Self::new(IndexPhase::Discovering, 0, 0)
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/progress.rs"]
+mod tests;
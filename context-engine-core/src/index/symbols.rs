@@ -0,0 +1,565 @@
+//! Conversion of the symbols that language servers report into graph nodes.
+
+use std::ops;
+use std::sync::Arc;
+
+use lsp_types::{
+    DocumentSymbol, Position, PositionEncodingKind, Range, SymbolInformation, SymbolKind,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::document::TextDocument;
+use crate::graph::{NodeKind, SymbolNode};
+use crate::syntax::{Language, SyntaxKind, SyntaxNode, SyntaxTree};
+
+/// A symbol of a file, before it is added to the graph.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FileSymbol {
+    /// The node of the symbol
+    pub node: SymbolNode,
+    /// The index of the symbol that defines it among the symbols of the file,
+    /// or `None` if the module of the file does
+    pub parent: Option<usize>,
+    /// The qualified name of a type defined in another file that the symbol
+    /// belongs to, e.g. for a method in a Rust `impl` block
+    pub owner: Option<String>,
+}
+
+/// A Rust `impl` block of a trait for a type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ImplRecord {
+    /// The URI of the file of the block
+    pub uri: String,
+    /// The qualified name of the type
+    pub type_name: String,
+    /// The name of the trait, without its path
+    pub trait_name: String,
+}
+
+/// The symbols of a file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct FileSymbols {
+    /// The symbols, parents before their children
+    pub symbols: Vec<FileSymbol>,
+    /// The trait implementations of the file
+    pub impls: Vec<ImplRecord>,
+}
+
+/// A symbol of a flat symbol list, as `workspace/symbol` reports them.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FlatSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub container_name: Option<String>,
+    pub range: Range,
+}
+
+impl From<&SymbolInformation> for FlatSymbol {
+    fn from(symbol: &SymbolInformation) -> Self {
+        Self {
+            name: symbol.name.clone(),
+            kind: symbol.kind,
+            container_name: symbol.container_name.clone(),
+            range: symbol.location.range,
+        }
+    }
+}
+
+/// Returns the kind of node for an LSP symbol kind, or `None` if symbols of
+/// the kind are not part of the graph.
+///
+/// # Arguments
+///
+/// * `kind` - The LSP kind
+/// * `in_type` - Whether the symbol is a member of a type or trait
+/// * `top_level` - Whether the symbol is defined directly in its module
+pub(crate) fn node_kind(kind: SymbolKind, in_type: bool, top_level: bool) -> Option<NodeKind> {
+    match kind {
+        SymbolKind::FILE | SymbolKind::MODULE | SymbolKind::NAMESPACE | SymbolKind::PACKAGE => {
+            Some(NodeKind::Module)
+        }
+        SymbolKind::CLASS | SymbolKind::STRUCT | SymbolKind::ENUM | SymbolKind::TYPE_PARAMETER => {
+            Some(NodeKind::Type)
+        }
+        SymbolKind::INTERFACE => Some(NodeKind::Trait),
+        SymbolKind::METHOD | SymbolKind::CONSTRUCTOR => Some(NodeKind::Method),
+        SymbolKind::FUNCTION if in_type => Some(NodeKind::Method),
+        SymbolKind::FUNCTION => Some(NodeKind::Function),
+        SymbolKind::FIELD | SymbolKind::PROPERTY => Some(NodeKind::Field),
+        SymbolKind::ENUM_MEMBER => Some(NodeKind::Variant),
+        SymbolKind::CONSTANT if in_type || top_level => Some(NodeKind::Constant),
+        SymbolKind::VARIABLE if top_level => Some(NodeKind::Constant),
+        _ => None,
+    }
+}
+
+/// Splits the name of a Rust `impl` block, e.g. `impl<T> Display for
+/// Point<T>`, into the names of its trait and type, without paths and
+/// generic arguments.
+pub(crate) fn parse_impl(name: &str) -> Option<(Option<&str>, &str)> {
+    let rest = name.strip_prefix("impl")?;
+    let rest = match rest.strip_prefix('<') {
+        Some(generics) => {
+            let mut depth = 1;
+            let end = generics.char_indices().find_map(|(index, c)| {
+                match c {
+                    '<' => depth += 1,
+                    '>' => depth -= 1,
+                    _ => {}
+                }
+                (depth == 0).then_some(index + 1)
+            })?;
+            generics.get(end..)?
+        }
+        None if rest.starts_with(char::is_whitespace) => rest,
+        None => return None,
+    };
+
+    fn simple(path: &str) -> &str {
+        let path = path.trim().trim_start_matches('&');
+        let path = match path.strip_prefix('\'') {
+            Some(lifetime) => lifetime.split_once(' ').map_or(lifetime, |(_, path)| path),
+            None => path,
+        };
+        let path = path
+            .trim_start()
+            .trim_start_matches("mut ")
+            .trim_start_matches("dyn ");
+        let path = path.split('<').next().unwrap_or(path);
+        path.rsplit("::").next().unwrap_or(path).trim()
+    }
+    match rest.split_once(" for ") {
+        Some((trait_name, type_name)) => {
+            let trait_name = trait_name.trim().trim_start_matches('!');
+            Some((Some(simple(trait_name)), simple(type_name)))
+        }
+        None => Some((None, simple(rest))),
+    }
+}
+
+/// A file together with its syntax tree, converting the symbols a language
+/// server reports for it into graph nodes.
+///
+/// The parts of each definition, i.e. its signature, body and doc comment,
+/// come from the syntax tree of the file; the language server only tells
+/// where the definitions are. Positions are converted from the encoding of
+/// the server into the encoding of the graph.
+pub(crate) struct SymbolSource<'a> {
+    document: Arc<TextDocument>,
+    tree: Option<SyntaxTree>,
+    server_encoding: &'a PositionEncodingKind,
+}
+
+impl<'a> SymbolSource<'a> {
+    /// Parses a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The file, in the position encoding of the graph
+    /// * `server_encoding` - The position encoding of the language server
+    pub fn new(document: Arc<TextDocument>, server_encoding: &'a PositionEncodingKind) -> Self {
+        let tree = Language::from_language_id(document.language_id())
+            .map(|language| SyntaxTree::parse(language, document.text()));
+        Self {
+            document,
+            tree,
+            server_encoding,
+        }
+    }
+
+    /// Returns the node of the module of the file: its body is the whole
+    /// file and its doc comment the leading module documentation.
+    pub fn module_node(&self, name: &str, qualified_name: &str) -> SymbolNode {
+        let start = Range::new(Position::new(0, 0), Position::new(0, 0));
+        let mut node = SymbolNode::new(
+            NodeKind::Module,
+            name,
+            qualified_name,
+            self.document.uri().clone(),
+            start,
+        )
+        .with_body(self.document.full_range());
+
+        let Some(tree) = &self.tree else {
+            return node;
+        };
+        let children = tree.root().children();
+        let doc = match tree.language() {
+            Language::Rust => comment_run(children, tree.language(), self.document.text(), true),
+            Language::Python => children.first().and_then(docstring),
+            Language::TypeScript => None,
+        };
+        if let Some(doc) = doc.and_then(|doc| self.range(doc)) {
+            node = node.with_doc(doc);
+        }
+        node
+    }
+
+    /// Converts the hierarchy of a `textDocument/documentSymbol` response.
+    ///
+    /// # Arguments
+    ///
+    /// * `module` - The qualified name of the module of the file
+    /// * `separator` - The separator of qualified names, e.g. `::`
+    /// * `symbols` - The symbols of the response
+    pub fn document_symbols(
+        &self,
+        module: &str,
+        separator: &str,
+        symbols: &[DocumentSymbol],
+    ) -> FileSymbols {
+        let mut file = FileSymbols::default();
+        let scope = Scope {
+            qualified_name: module,
+            parent: None,
+            owner: None,
+            in_type: false,
+            top_level: true,
+        };
+        self.walk(&mut file, symbols, &scope, separator);
+        file
+    }
+
+    fn walk(
+        &self,
+        file: &mut FileSymbols,
+        symbols: &[DocumentSymbol],
+        scope: &Scope<'_>,
+        separator: &str,
+    ) {
+        for symbol in symbols {
+            let children = symbol.children.as_deref().unwrap_or_default();
+            if symbol.kind == SymbolKind::OBJECT {
+                if let Some((trait_name, type_name)) = parse_impl(&symbol.name) {
+                    let qualified_name = join(scope.qualified_name, separator, type_name);
+                    if let Some(trait_name) = trait_name {
+                        file.impls.push(ImplRecord {
+                            uri: self.document.uri().as_str().to_string(),
+                            type_name: qualified_name.clone(),
+                            trait_name: trait_name.to_string(),
+                        });
+                    }
+                    let parent = file
+                        .symbols
+                        .iter()
+                        .position(|symbol| symbol.node.qualified_name == qualified_name);
+                    let owner = parent.is_none().then_some(qualified_name.as_str());
+                    let scope = Scope {
+                        qualified_name: &qualified_name,
+                        parent,
+                        owner,
+                        in_type: true,
+                        top_level: false,
+                    };
+                    self.walk(file, children, &scope, separator);
+                }
+                continue;
+            }
+
+            let Some(kind) = node_kind(symbol.kind, scope.in_type, scope.top_level) else {
+                continue;
+            };
+            let qualified_name = join(scope.qualified_name, separator, &symbol.name);
+            let Some(mut node) = self.node(
+                kind,
+                &symbol.name,
+                &qualified_name,
+                symbol.range,
+                symbol.selection_range,
+            ) else {
+                continue;
+            };
+            if kind == NodeKind::Module && node.body.is_none() {
+                // A declaration such as `mod user;`, whose file has a module
+                // node of its own
+                continue;
+            }
+            if let Some(detail) = &symbol.detail {
+                node = node.with_detail(detail.clone());
+            }
+            file.symbols.push(FileSymbol {
+                node,
+                parent: scope.parent,
+                owner: scope.owner.map(str::to_string),
+            });
+
+            if matches!(kind, NodeKind::Module | NodeKind::Type | NodeKind::Trait) {
+                let scope = Scope {
+                    qualified_name: &qualified_name,
+                    parent: Some(file.symbols.len() - 1),
+                    owner: None,
+                    in_type: kind != NodeKind::Module,
+                    top_level: kind == NodeKind::Module,
+                };
+                self.walk(file, children, &scope, separator);
+            }
+        }
+    }
+
+    /// Converts a flat symbol list, e.g. from `workspace/symbol`, nesting the
+    /// symbols by their container names.
+    pub fn flat_symbols(
+        &self,
+        module: &str,
+        separator: &str,
+        symbols: &[FlatSymbol],
+    ) -> FileSymbols {
+        let mut file = FileSymbols::default();
+        let mut symbols = symbols.iter().collect::<Vec<_>>();
+        symbols.sort_by_key(|symbol| symbol.range.start);
+
+        for symbol in symbols {
+            let parent = symbol.container_name.as_ref().and_then(|container| {
+                file.symbols
+                    .iter()
+                    .rposition(|candidate| &candidate.node.name == container)
+            });
+            let parent_kind = parent
+                .and_then(|parent| file.symbols.get(parent))
+                .map(|parent| parent.node.kind);
+            let in_type = matches!(parent_kind, Some(NodeKind::Type | NodeKind::Trait));
+            let top_level = parent_kind.is_none() || parent_kind == Some(NodeKind::Module);
+            let Some(kind) = node_kind(symbol.kind, in_type, top_level) else {
+                continue;
+            };
+
+            let scope = parent
+                .and_then(|parent| file.symbols.get(parent))
+                .map_or(module, |parent| parent.node.qualified_name.as_str());
+            let qualified_name = join(scope, separator, &symbol.name);
+            let selection = self.name_range(symbol.range, &symbol.name);
+            if let Some(node) =
+                self.node(kind, &symbol.name, &qualified_name, symbol.range, selection)
+            {
+                file.symbols.push(FileSymbol {
+                    node,
+                    parent,
+                    owner: None,
+                });
+            }
+        }
+        file
+    }
+
+    fn offset(&self, position: Position) -> Option<usize> {
+        self.document
+            .line_index()
+            .offset(position, self.server_encoding)
+            .ok()
+    }
+
+    /// Returns the range of a symbol name within a range of the server, or
+    /// the start of the range if the name is not found.
+    fn name_range(&self, range: Range, name: &str) -> Range {
+        let found = self
+            .offset(range.start)
+            .zip(self.offset(range.end))
+            .and_then(|(start, end)| {
+                let text = self.document.text().get(start..end)?;
+                let offset = start + text.find(name)?;
+                let start = self
+                    .document
+                    .line_index()
+                    .position(offset, self.server_encoding);
+                let end = self
+                    .document
+                    .line_index()
+                    .position(offset + name.len(), self.server_encoding);
+                Some(Range::new(start.ok()?, end.ok()?))
+            });
+        found.unwrap_or(Range::new(range.start, range.start))
+    }
+
+    /// Creates the node of a symbol, taking the parts of its definition from
+    /// the syntax tree.
+    fn node(
+        &self,
+        kind: NodeKind,
+        name: &str,
+        qualified_name: &str,
+        range: Range,
+        selection: Range,
+    ) -> Option<SymbolNode> {
+        let start = self.offset(range.start)?;
+        let end = self.offset(range.end)?.max(start);
+        let selection = self.offset(selection.start)?..self.offset(selection.end)?;
+
+        let (signature, body, doc) = match self.definition(selection.clone()) {
+            Some(parts) => parts,
+            None => {
+                // Without a definition in the tree, the first line of the
+                // symbol stands for its signature
+                let text = self.document.text();
+                let line_end = text
+                    .get(start..end)
+                    .and_then(|range| range.find('\n'))
+                    .map_or(end, |newline| start + newline);
+                (trim(text, start..line_end), None, None)
+            }
+        };
+
+        let mut node = SymbolNode::new(
+            kind,
+            name,
+            qualified_name,
+            self.document.uri().clone(),
+            self.range(signature)?,
+        );
+        if let Some(body) = body.and_then(|body| self.range(body)) {
+            node = node.with_body(body);
+        }
+        if let Some(doc) = doc.and_then(|doc| self.range(doc)) {
+            node = node.with_doc(doc);
+        }
+        Some(node)
+    }
+
+    /// Returns the signature, body and doc comment of the innermost
+    /// definition around a name, in bytes.
+    #[allow(clippy::type_complexity)]
+    fn definition(
+        &self,
+        selection: ops::Range<usize>,
+    ) -> Option<(
+        ops::Range<usize>,
+        Option<ops::Range<usize>>,
+        Option<ops::Range<usize>>,
+    )> {
+        let tree = self.tree.as_ref()?;
+        let text = self.document.text();
+        let ancestors = tree.ancestors_for_byte_range(selection.start, selection.end);
+        let (index, definition) = ancestors.iter().enumerate().rev().find(|(_, node)| {
+            node.kind().is_definition()
+                || (node.kind() == SyntaxKind::Variable && tree.language() != Language::Rust)
+        })?;
+        let parent = index
+            .checked_sub(1)
+            .and_then(|parent| ancestors.get(parent));
+
+        let children = definition.children();
+        let leading = children
+            .iter()
+            .take_while(|child| matches!(child.kind(), SyntaxKind::Attribute | SyntaxKind::Comment))
+            .count();
+        let signature_start = children
+            .get(leading)
+            .map_or(definition.start_byte(), SyntaxNode::start_byte);
+        let body = definition.child_by_field_name("body");
+        let signature_end = body.map_or(definition.end_byte(), SyntaxNode::start_byte);
+        let signature = trim(text, signature_start..signature_end.max(signature_start));
+
+        // Doc comments precede the definition, possibly among its attributes
+        let mut docs = Vec::new();
+        if let Some(parent) = parent {
+            let position = parent
+                .children()
+                .iter()
+                .position(|child| std::ptr::eq(child, *definition))
+                .unwrap_or_default();
+            let before = parent.children().get(..position).unwrap_or_default();
+            docs.extend(comment_run(before, tree.language(), text, false));
+        }
+        let attributes = children.get(..leading).unwrap_or_default();
+        docs.extend(
+            attributes
+                .iter()
+                .filter(|child| is_doc_comment(child, tree.language(), text, false))
+                .map(SyntaxNode::byte_range),
+        );
+        if tree.language() == Language::Python {
+            let first = body.and_then(|body| body.children().first());
+            docs.extend(first.and_then(docstring));
+        }
+        let doc = docs
+            .into_iter()
+            .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end));
+
+        Some((signature, body.map(SyntaxNode::byte_range), doc))
+    }
+
+    fn range(&self, bytes: ops::Range<usize>) -> Option<Range> {
+        let start = self.document.position(bytes.start).ok()?;
+        let end = self.document.position(bytes.end).ok()?;
+        Some(Range::new(start, end))
+    }
+}
+
+/// The symbol whose children are being converted.
+struct Scope<'a> {
+    qualified_name: &'a str,
+    parent: Option<usize>,
+    owner: Option<&'a str>,
+    in_type: bool,
+    top_level: bool,
+}
+
+/// Joins a qualified name and a name.
+fn join(scope: &str, separator: &str, name: &str) -> String {
+    match scope.is_empty() {
+        true => name.to_string(),
+        false => format!("{scope}{separator}{name}"),
+    }
+}
+
+/// Trims whitespace off both ends of a byte range of a text.
+fn trim(text: &str, range: ops::Range<usize>) -> ops::Range<usize> {
+    let slice = text.get(range.clone()).unwrap_or_default();
+    let start = range.start + (slice.len() - slice.trim_start().len());
+    let end = start + slice.trim().len();
+    start..end
+}
+
+/// Returns true if a node is a doc comment of the item after it or, for
+/// inner doc comments, of the enclosing module.
+fn is_doc_comment(node: &SyntaxNode, language: Language, text: &str, inner: bool) -> bool {
+    if node.kind() != SyntaxKind::Comment {
+        return false;
+    }
+    let comment = node.text(text);
+    match (language, inner) {
+        (Language::Rust, false) => {
+            (comment.starts_with("///") && !comment.starts_with("////"))
+                || (comment.starts_with("/**") && !comment.starts_with("/**/"))
+        }
+        (Language::Rust, true) => comment.starts_with("//!") || comment.starts_with("/*!"),
+        (Language::TypeScript, false) => comment.starts_with("/**") && !comment.starts_with("/**/"),
+        _ => false,
+    }
+}
+
+/// Returns the range of the doc comments at the end of a list of nodes, or
+/// at its start for inner doc comments.
+fn comment_run(
+    nodes: &[SyntaxNode],
+    language: Language,
+    text: &str,
+    inner: bool,
+) -> Option<ops::Range<usize>> {
+    let is_doc = |node: &&SyntaxNode| is_doc_comment(node, language, text, inner);
+    let run = match inner {
+        true => nodes.iter().take_while(is_doc).collect::<Vec<_>>(),
+        false => {
+            let mut run = nodes.iter().rev().take_while(is_doc).collect::<Vec<_>>();
+            run.reverse();
+            run
+        }
+    };
+    let first = run.first()?;
+    let last = run.last()?;
+    Some(first.start_byte()..last.end_byte())
+}
+
+/// Returns the range of a Python docstring, i.e. a statement that is a
+/// single string.
+fn docstring(node: &SyntaxNode) -> Option<ops::Range<usize>> {
+    let is_string = |node: &SyntaxNode| node.kind() == SyntaxKind::String;
+    match node.children() {
+        [string] if is_string(string) => Some(node.byte_range()),
+        [] if is_string(node) => Some(node.byte_range()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/symbols.rs"]
+mod tests;
@@ -0,0 +1,110 @@
+#![allow(clippy::unwrap_used)]
+
+use lsp_types::{Position, Range};
+use proptest::prelude::*;
+
+use super::*;
+use crate::cache::content_hash;
+use crate::graph::{NodeKind, SymbolNode};
+
+fn checkpoint(root: &Path) -> IndexCheckpoint {
+    let uri = Uri::from_str("file:///app/src/user.rs").unwrap();
+    let mut checkpoint = IndexCheckpoint::new(root);
+    let user = checkpoint.graph.add_node(SymbolNode::new(
+        NodeKind::Type,
+        "User",
+        "app::user::User",
+        uri.clone(),
+        Range::new(Position::new(1, 0), Position::new(1, 15)),
+    ));
+    checkpoint.files.insert(
+        uri.as_str().to_string(),
+        FileEntry {
+            hash: content_hash(b"pub struct User;"),
+            server: "rust-analyzer".to_string(),
+            version: "rust-analyzer 1.87.0".to_string(),
+        },
+    );
+    checkpoint.related.insert(user);
+    checkpoint.impls.push(ImplRecord {
+        uri: uri.as_str().to_string(),
+        type_name: "app::user::User".to_string(),
+        trait_name: "Display".to_string(),
+    });
+    checkpoint.phase = Some(IndexPhase::Relations);
+    checkpoint
+}
+
+#[test]
+fn test_save_and_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cache/index.bin");
+    let saved = checkpoint(dir.path());
+    saved.save(&path);
+
+    let loaded = IndexCheckpoint::load(&path, dir.path());
+    assert_eq!(loaded.phase, Some(IndexPhase::Relations));
+    assert_eq!(loaded.files, saved.files);
+    assert_eq!(loaded.related, saved.related);
+    assert_eq!(loaded.impls, saved.impls);
+    assert_eq!(loaded.graph, saved.graph);
+    assert!(!path.with_extension("tmp").exists());
+}
+
+#[test]
+fn test_load_discards_foreign_and_corrupted_checkpoints() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+
+    // Missing
+    assert!(IndexCheckpoint::load(&path, dir.path()).files.is_empty());
+
+    // Another workspace
+    checkpoint(dir.path()).save(&path);
+    let other = dir.path().join("other");
+    let loaded = IndexCheckpoint::load(&path, &other);
+    assert!(loaded.files.is_empty());
+    assert_eq!(loaded.root, other.display().to_string());
+
+    // Another format version
+    let mut outdated = checkpoint(dir.path());
+    outdated.version = CHECKPOINT_VERSION + 1;
+    outdated.save(&path);
+    assert!(IndexCheckpoint::load(&path, dir.path()).files.is_empty());
+
+    // Corrupted
+    std::fs::write(&path, b"not a checkpoint").unwrap();
+    let loaded = IndexCheckpoint::load(&path, dir.path());
+    assert!(loaded.files.is_empty());
+    assert_eq!(loaded.version, CHECKPOINT_VERSION);
+}
+
+#[test]
+fn test_forget() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut checkpoint = checkpoint(dir.path());
+    let user = checkpoint.graph.nodes().next().unwrap().id();
+    checkpoint.owners.push((user, "app::Account".to_string()));
+
+    checkpoint.forget("file:///app/src/user.rs");
+    assert!(checkpoint.files.is_empty());
+    assert!(checkpoint.impls.is_empty());
+    assert!(checkpoint.owners.is_empty());
+    assert!(checkpoint.graph.is_empty());
+}
+
+#[test]
+fn test_content_hash() {
+    // Reference values of 64-bit FNV-1a
+    assert_eq!(content_hash(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(content_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    assert_ne!(content_hash(b"fn a() {}"), content_hash(b"fn b() {}"));
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn test_content_hash_is_deterministic(content in prop::collection::vec(any::<u8>(), 0..256)) {
+        prop_assert_eq!(content_hash(&content), content_hash(&content.clone()));
+    }
+}
@@ -0,0 +1,56 @@
+#![allow(clippy::unwrap_used)]
+
+use super::*;
+
+#[test]
+fn test_discover() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    for path in [
+        "Cargo.toml",
+        "src/lib.rs",
+        "src/user/mod.rs",
+        "src/user/account.rs",
+        "target/debug/build.rs",
+        "web/node_modules/react/index.js",
+        "web/src/app.generated.ts",
+        "web/src/app.ts",
+        ".git/config",
+        "src/.hidden.rs",
+    ] {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "").unwrap();
+    }
+
+    let excludes = DEFAULT_EXCLUDES
+        .iter()
+        .chain(&["*.generated.ts"])
+        .map(|pattern| Glob::new(pattern).unwrap())
+        .collect::<Vec<_>>();
+    let files = discover(root, &excludes)
+        .into_iter()
+        .map(|path| {
+            path.strip_prefix(root)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        files,
+        [
+            "Cargo.toml",
+            "src/lib.rs",
+            "src/user/account.rs",
+            "src/user/mod.rs",
+            "web/src/app.ts",
+        ]
+    );
+}
+
+#[test]
+fn test_discover_missing_root() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(discover(&dir.path().join("missing"), &[]).is_empty());
+}
@@ -0,0 +1,411 @@
+#![allow(clippy::unwrap_used)]
+
+use std::fmt::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use lsp_types::{CallHierarchyItem, CallHierarchyOutgoingCall, SymbolKind};
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+
+use super::*;
+use crate::JsonRpcError;
+use crate::lsp::RestartPolicy;
+use crate::lsp::testing::{FakeLauncher, FakeLog, FakeServer, FakeSession};
+use crate::types::LineIndex;
+
+const CARGO_TOML: &str = "[package]\nname = \"app\"\nversion = \"0.1.0\"\n";
+
+const LIB: &str = r#"//! The application.
+
+pub mod user;
+
+/// Greets a user.
+pub fn greet(user: &user::User) -> String {
+    user.name()
+}
+"#;
+
+const USER: &str = r#"/// Something with a name.
+pub trait Named {
+    fn name(&self) -> String;
+}
+
+/// A user.
+pub struct User {
+    name: String,
+}
+
+impl Named for User {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+"#;
+
+/// Scripted behavior and observations of the fake server.
+#[derive(Default)]
+struct FakeState {
+    /// The texts of the documents opened by URI, including closed ones
+    texts: Mutex<HashMap<String, String>>,
+    /// Time the server takes to answer `textDocument/documentSymbol`, in
+    /// milliseconds
+    delay_ms: AtomicU64,
+    /// The version the server reports
+    version: Option<String>,
+}
+
+impl FakeServer for FakeState {
+    fn initialize(&self) -> Value {
+        json!({ "capabilities": {
+            "documentSymbolProvider": true,
+            "implementationProvider": true,
+            "callHierarchyProvider": true,
+        }, "serverInfo": { "name": "fake", "version": self.version } })
+    }
+
+    async fn request(
+        &self,
+        _session: &FakeSession,
+        method: &str,
+        params: Value,
+    ) -> Option<Result<Value, JsonRpcError>> {
+        if method == "textDocument/documentSymbol" {
+            let delay = self.delay_ms.load(Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+        }
+        Some(Ok(respond(self, method, params)))
+    }
+
+    async fn notify(&self, session: &FakeSession, method: &str, params: Value) {
+        if method == "textDocument/didOpen" {
+            let uri = params
+                .pointer("/textDocument/uri")
+                .and_then(Value::as_str)
+                .unwrap();
+            let text = session.text(uri).unwrap();
+            self.texts.lock().insert(uri.to_string(), text);
+        }
+    }
+}
+
+/// The file names of the messages with a method received by the server.
+fn requests(log: &FakeLog, method: &str) -> Vec<String> {
+    log.params(method)
+        .iter()
+        .map(|params| {
+            let uri = params
+                .pointer("/textDocument/uri")
+                .or_else(|| params.pointer("/item/uri"))
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            uri.rsplit('/').next().unwrap().to_string()
+        })
+        .collect()
+}
+
+/// Returns the range of the `nth` occurrence of `needle` in `text`.
+fn find(text: &str, needle: &str, nth: usize) -> Option<Range> {
+    let index = LineIndex::new(text);
+    let (offset, _) = text.match_indices(needle).nth(nth)?;
+    let position = |offset| index.position(offset, &PositionEncodingKind::UTF16).ok();
+    Some(Range::new(
+        position(offset)?,
+        position(offset + needle.len())?,
+    ))
+}
+
+#[allow(deprecated)]
+fn symbol(
+    name: &str,
+    kind: SymbolKind,
+    selection: Range,
+    children: Vec<DocumentSymbol>,
+) -> DocumentSymbol {
+    let end = children
+        .last()
+        .map_or(selection.end, |child| child.range.end);
+    DocumentSymbol {
+        name: name.to_string(),
+        detail: None,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: Range::new(Position::new(selection.start.line, 0), end),
+        selection_range: selection,
+        children: (!children.is_empty()).then_some(children),
+    }
+}
+
+/// The symbols rust-analyzer reports for the files of the workspace.
+fn document_symbols(text: &str) -> Vec<DocumentSymbol> {
+    let at = |needle, nth| find(text, needle, nth);
+    let mut symbols = Vec::new();
+    if let Some(greet) = at("greet", 0) {
+        symbols.push(symbol("greet", SymbolKind::FUNCTION, greet, vec![]));
+    }
+    if let Some(named) = at("Named", 0) {
+        let method = symbol("name", SymbolKind::METHOD, at("name", 1).unwrap(), vec![]);
+        symbols.push(symbol("Named", SymbolKind::INTERFACE, named, vec![method]));
+        let field = symbol("name", SymbolKind::FIELD, at("name", 2).unwrap(), vec![]);
+        symbols.push(symbol(
+            "User",
+            SymbolKind::STRUCT,
+            at("User", 1).unwrap(),
+            vec![field],
+        ));
+        let method = symbol("name", SymbolKind::METHOD, at("name", 3).unwrap(), vec![]);
+        let block = at("impl Named for User", 0).unwrap();
+        symbols.push(symbol(
+            "impl Named for User",
+            SymbolKind::OBJECT,
+            block,
+            vec![method],
+        ));
+    }
+    if let Some(guest) = at("guest", 0) {
+        symbols.push(symbol("guest", SymbolKind::FUNCTION, guest, vec![]));
+    }
+    symbols
+}
+
+/// Answers a request of the indexer.
+fn respond(state: &FakeState, method: &str, params: Value) -> Value {
+    let uri = params
+        .pointer("/textDocument/uri")
+        .or_else(|| params.pointer("/item/uri"))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let text = state.texts.lock().get(&uri).cloned().unwrap_or_default();
+    let user_uri = uri.replace("lib.rs", "user.rs");
+    let user_text = state.texts.lock().get(&user_uri).cloned();
+
+    match method {
+        "textDocument/documentSymbol" => json!(document_symbols(&text)),
+        "textDocument/prepareCallHierarchy" => {
+            let position = params.get("position").cloned().unwrap();
+            json!([{
+                "name": "item",
+                "kind": 12,
+                "uri": uri,
+                "range": { "start": position, "end": position },
+                "selectionRange": { "start": position, "end": position },
+            }])
+        }
+        "callHierarchy/outgoingCalls" => {
+            let item =
+                serde_json::from_value::<CallHierarchyItem>(params.get("item").cloned().unwrap())
+                    .unwrap();
+            // `greet` calls the `name` method of `User`
+            let calls = match find(&text, "greet", 0) {
+                Some(greet) if greet.start == item.selection_range.start => {
+                    let target = find(USER, "name", 3).unwrap();
+                    let to = CallHierarchyItem {
+                        uri: Uri::from_str(&user_uri).unwrap(),
+                        range: target,
+                        selection_range: target,
+                        ..item
+                    };
+                    vec![CallHierarchyOutgoingCall {
+                        to,
+                        from_ranges: vec![],
+                    }]
+                }
+                _ => vec![],
+            };
+            json!(calls)
+        }
+        "textDocument/implementation" => match user_text {
+            Some(user) => json!([{ "uri": user_uri, "range": find(&user, "User", 1) }]),
+            None => Value::Null,
+        },
+        _ => Value::Null,
+    }
+}
+
+/// A workspace with an `app` crate.
+fn workspace() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("Cargo.toml"), CARGO_TOML).unwrap();
+    std::fs::write(dir.path().join("src/lib.rs"), LIB).unwrap();
+    std::fs::write(dir.path().join("src/user.rs"), USER).unwrap();
+    dir
+}
+
+fn indexer(root: &Path, config: IndexConfig) -> (Indexer, FakeLauncher<FakeState>) {
+    indexer_on(root, config, FakeState::default())
+}
+
+/// An indexer of a workspace on a fake server with a state.
+fn indexer_on(
+    root: &Path,
+    config: IndexConfig,
+    state: FakeState,
+) -> (Indexer, FakeLauncher<FakeState>) {
+    let launcher = FakeLauncher::new(state);
+    let documents = Arc::new(DocumentStore::default());
+    let supervisor = Arc::new(LspSupervisor::with_launcher(
+        root,
+        Arc::clone(&documents),
+        launcher.clone(),
+        RestartPolicy::default(),
+    ));
+    let registry = LanguageRegistry::with_defaults();
+    let mut server = registry.server("rust-analyzer").unwrap().clone();
+    server.request_timeout_ms = 5_000;
+    supervisor.start(server).unwrap();
+
+    let indexer = Indexer::new(root, supervisor, Arc::new(registry), documents, config);
+    (indexer, launcher)
+}
+
+/// Renders the edges of a graph by qualified names.
+fn edges(graph: &KnowledgeGraph) -> String {
+    let name = |id| {
+        let node = graph.node(id).unwrap();
+        format!("{} {}", node.kind, node.qualified_name)
+    };
+    let mut edges = graph
+        .edges()
+        .into_iter()
+        .map(|edge| format!("{} -{}-> {}", name(edge.from), edge.kind, name(edge.to)))
+        .collect::<Vec<_>>();
+    edges.sort();
+    edges.into_iter().fold(String::new(), |mut rendered, edge| {
+        writeln!(rendered, "{edge}").unwrap();
+        rendered
+    })
+}
+
+#[tokio::test]
+async fn test_index_workspace() {
+    let dir = workspace();
+    let (indexer, fake) = indexer(dir.path(), IndexConfig::default());
+    let progress = indexer.progress();
+
+    let outcome = indexer.run().await;
+    assert!(outcome.errors.is_empty(), "{:?}", outcome.errors);
+    assert_eq!(outcome.indexed, 2);
+    assert!(!outcome.resumed);
+    insta::assert_snapshot!(edges(&outcome.graph));
+
+    let greet = outcome.graph.lookup("app::greet");
+    let greet = greet.first().unwrap();
+    let uri = greet.uri.clone();
+    let text = std::fs::read_to_string(dir.path().join("src/lib.rs")).unwrap();
+    assert_eq!(outcome.files.len(), 2);
+    assert_eq!(
+        outcome.files.get(uri.as_str()),
+        Some(&content_hash(text.as_bytes()))
+    );
+    let document = TextDocument::new(uri, "rust", 0, text, PositionEncodingKind::UTF16);
+    assert_eq!(
+        document.slice(greet.signature).unwrap(),
+        "pub fn greet(user: &user::User) -> String"
+    );
+    assert_eq!(
+        document.slice(greet.doc.unwrap()).unwrap(),
+        "/// Greets a user."
+    );
+
+    // Files that are not open are opened for the requests about them
+    let mut closed = requests(fake.log(), "textDocument/didClose");
+    closed.sort();
+    assert_eq!(closed, ["lib.rs", "lib.rs", "user.rs", "user.rs"]);
+    assert_eq!(
+        *progress.borrow(),
+        IndexProgress::new(IndexPhase::Done, 0, 0)
+    );
+    assert_eq!(progress.borrow().percentage(), 100);
+}
+
+#[tokio::test]
+async fn test_resume_skips_unchanged_files() {
+    let dir = workspace();
+    let config = IndexConfig::default().with_checkpoint(dir.path().join(".index/checkpoint"));
+    let (indexer, fake) = indexer(dir.path(), config.clone());
+    let first = indexer.run().await;
+    assert_eq!(requests(fake.log(), "textDocument/documentSymbol").len(), 2);
+
+    let user = format!("{USER}\npub fn guest() -> User {{\n    todo!()\n}}\n");
+    std::fs::write(dir.path().join("src/user.rs"), user).unwrap();
+    let (indexer, fake) = self::indexer(dir.path(), config);
+    let second = indexer.run().await;
+
+    assert!(second.resumed);
+    assert_eq!(second.unchanged, 1);
+    assert_eq!(second.indexed, 1);
+    assert_eq!(
+        requests(fake.log(), "textDocument/documentSymbol"),
+        ["user.rs"]
+    );
+    assert_eq!(second.graph.lookup("app::user::guest").len(), 1);
+    assert_eq!(second.graph.len(), first.graph.len() + 1);
+    let mut expected = edges(&first.graph)
+        .lines()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    expected.push("module app::user -defines-> function app::user::guest".to_string());
+    expected.sort();
+    assert_eq!(edges(&second.graph).lines().collect::<Vec<_>>(), expected);
+}
+
+#[tokio::test]
+async fn test_new_server_version_reindexes_all_files() {
+    let dir = workspace();
+    let config = IndexConfig::default().with_checkpoint(dir.path().join(".index/checkpoint"));
+    let (indexer, _) = indexer(dir.path(), config.clone());
+    let first = indexer.run().await;
+    assert_eq!(first.indexed, 2);
+
+    let (indexer, fake) = self::indexer(dir.path(), config.clone());
+    assert_eq!(indexer.run().await.unchanged, 2);
+    assert!(requests(fake.log(), "textDocument/documentSymbol").is_empty());
+
+    let state = FakeState {
+        version: Some("2.0.0".to_string()),
+        ..FakeState::default()
+    };
+    let (indexer, fake) = indexer_on(dir.path(), config, state);
+    let outcome = indexer.run().await;
+    assert_eq!((outcome.indexed, outcome.unchanged), (2, 0));
+    assert_eq!(requests(fake.log(), "textDocument/documentSymbol").len(), 2);
+    assert_eq!(outcome.graph.len(), first.graph.len());
+}
+
+#[tokio::test]
+async fn test_interrupted_run_resumes() {
+    let dir = workspace();
+    let config = IndexConfig {
+        concurrency: 1,
+        batch_size: 1,
+        ..IndexConfig::default()
+    }
+    .with_checkpoint(dir.path().join(".index/checkpoint"));
+
+    let (indexer, fake) = indexer(dir.path(), config.clone());
+    fake.server().delay_ms.store(200, Ordering::SeqCst);
+    let mut progress = indexer.progress();
+    let run = tokio::spawn(async move { indexer.run().await });
+    // Interrupt the run once the first file is saved
+    progress
+        .wait_for(|progress| progress.phase == IndexPhase::Symbols && progress.done >= 1)
+        .await
+        .unwrap();
+    run.abort();
+    assert!(run.await.unwrap_err().is_cancelled());
+
+    let (indexer, fake) = self::indexer(dir.path(), config);
+    let outcome = indexer.run().await;
+    assert!(outcome.resumed);
+    assert_eq!(outcome.unchanged, 1);
+    assert_eq!(
+        requests(fake.log(), "textDocument/documentSymbol"),
+        ["user.rs"]
+    );
+    assert!(outcome.errors.is_empty(), "{:?}", outcome.errors);
+    assert_eq!(outcome.graph.lookup("app::greet").len(), 1);
+}
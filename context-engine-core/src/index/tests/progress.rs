@@ -0,0 +1,66 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+#[test]
+fn test_percentage() {
+    assert_eq!(IndexProgress::default().percentage(), 0);
+    assert_eq!(
+        IndexProgress::new(IndexPhase::Symbols, 0, 10).percentage(),
+        0
+    );
+    assert_eq!(
+        IndexProgress::new(IndexPhase::Symbols, 5, 10).percentage(),
+        25
+    );
+    assert_eq!(
+        IndexProgress::new(IndexPhase::Symbols, 10, 10).percentage(),
+        50
+    );
+    assert_eq!(
+        IndexProgress::new(IndexPhase::Relations, 0, 0).percentage(),
+        100
+    );
+    assert_eq!(
+        IndexProgress::new(IndexPhase::Relations, 1, 4).percentage(),
+        62
+    );
+    assert_eq!(IndexProgress::new(IndexPhase::Done, 0, 0).percentage(), 100);
+}
+
+#[test]
+fn test_to_work_done() {
+    let begin = IndexProgress::default().to_work_done();
+    assert!(matches!(begin, WorkDoneProgress::Begin(ref begin) if begin.title == "Indexing"));
+
+    let report = IndexProgress::new(IndexPhase::Symbols, 3, 4).to_work_done();
+    insta::assert_snapshot!(
+        serde_json::to_string(&report).unwrap(),
+        @r#"{"kind":"report","cancellable":false,"message":"symbols 3/4","percentage":37}"#
+    );
+
+    let end = IndexProgress::new(IndexPhase::Done, 0, 0).to_work_done();
+    insta::assert_snapshot!(
+        serde_json::to_string(&end).unwrap(),
+        @r#"{"kind":"end","message":"done"}"#
+    );
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn test_percentage_is_monotonic(
+        phase in prop::sample::select(vec![IndexPhase::Symbols, IndexPhase::Relations]),
+        total in 0usize..1000,
+        done in 0usize..1000,
+    ) {
+        let progress = IndexProgress::new(phase, done, total);
+        let next = IndexProgress::new(phase, done + 1, total);
+        prop_assert!(progress.percentage() <= next.percentage());
+        prop_assert!(next.percentage() <= 100);
+        let later = IndexProgress::new(IndexPhase::Relations, 0, total.max(1));
+        prop_assert!(phase == IndexPhase::Relations || progress.percentage() <= later.percentage());
+    }
+}
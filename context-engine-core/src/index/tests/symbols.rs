@@ -0,0 +1,345 @@
+#![allow(clippy::unwrap_used)]
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use lsp_types::Uri;
+use proptest::prelude::*;
+
+use super::*;
+
+const USER: &str = r#"//! User accounts.
+//! Users log in with their name.
+
+use std::fmt;
+
+/// A user of the application.
+#[derive(Debug)]
+pub struct User {
+    /// The login name
+    pub name: String,
+}
+
+impl User {
+    /// Returns true if the name is valid.
+    pub fn validate(&self) -> bool {
+        !self.name.is_empty()
+    }
+}
+
+impl fmt::Display for User {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl Clone for Account {
+    fn clone(&self) -> Self {
+        todo!()
+    }
+}
+
+mod tests;
+"#;
+
+const SERVICE: &str = r#""""Services of the application."""
+
+
+class Service:
+    """Loads items."""
+
+    def load(self, id):
+        return self.items[id]
+
+
+def run():
+    pass
+"#;
+
+static UTF16: PositionEncodingKind = PositionEncodingKind::UTF16;
+
+fn source(language_id: &str, text: &str) -> SymbolSource<'static> {
+    let uri = Uri::from_str("file:///app/src/user.rs").unwrap();
+    let document = TextDocument::new(uri, language_id, 1, text, PositionEncodingKind::UTF16);
+    SymbolSource::new(Arc::new(document), &UTF16)
+}
+
+/// Returns the range of the `nth` occurrence of `needle` in `text`.
+fn find(text: &str, needle: &str, nth: usize) -> Range {
+    let index = crate::types::LineIndex::new(text);
+    let (offset, _) = text.match_indices(needle).nth(nth).unwrap();
+    let position = |offset| {
+        index
+            .position(offset, &PositionEncodingKind::UTF16)
+            .unwrap()
+    };
+    Range::new(position(offset), position(offset + needle.len()))
+}
+
+/// Returns the range from the start of the line of `start` to the end of
+/// `end`, the way servers report the range of a symbol.
+fn span(start: Range, end: Range) -> Range {
+    Range::new(Position::new(start.start.line, 0), end.end)
+}
+
+#[allow(deprecated)]
+fn symbol(
+    name: &str,
+    kind: SymbolKind,
+    range: Range,
+    selection_range: Range,
+    children: Vec<DocumentSymbol>,
+) -> DocumentSymbol {
+    DocumentSymbol {
+        name: name.to_string(),
+        detail: None,
+        kind,
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range,
+        children: (!children.is_empty()).then_some(children),
+    }
+}
+
+/// Renders symbols with the text of their parts.
+fn render(source: &SymbolSource<'_>, file: &FileSymbols) -> String {
+    let document = &source.document;
+    let text = |range: Option<Range>| match range {
+        Some(range) => format!("{:?}", document.slice(range).unwrap()),
+        None => "-".to_string(),
+    };
+    let mut rendered = String::new();
+    for symbol in &file.symbols {
+        let node = &symbol.node;
+        writeln!(
+            rendered,
+            "{} {} parent={:?} owner={:?}\n  signature: {}\n  body: {}\n  doc: {}",
+            node.kind,
+            node.qualified_name,
+            symbol.parent,
+            symbol.owner,
+            text(Some(node.signature)),
+            text(node.body).lines().next().unwrap_or_default(),
+            text(node.doc),
+        )
+        .unwrap();
+    }
+    for record in &file.impls {
+        writeln!(
+            rendered,
+            "impl {} for {}",
+            record.trait_name, record.type_name
+        )
+        .unwrap();
+    }
+    rendered
+}
+
+fn user_symbols() -> Vec<DocumentSymbol> {
+    let text = USER;
+    let item = |start: &str, nth: usize, end: &str, end_nth: usize| {
+        span(find(text, start, nth), find(text, end, end_nth))
+    };
+    vec![
+        symbol(
+            "User",
+            SymbolKind::STRUCT,
+            item("/// A user", 0, "}", 0),
+            find(text, "User", 2),
+            vec![symbol(
+                "name",
+                SymbolKind::FIELD,
+                item("/// The login", 0, "String", 0),
+                find(text, "name", 2),
+                vec![],
+            )],
+        ),
+        symbol(
+            "impl User",
+            SymbolKind::OBJECT,
+            item("impl User", 0, "\n}", 1),
+            find(text, "User", 3),
+            vec![symbol(
+                "validate",
+                SymbolKind::METHOD,
+                item("/// Returns true", 0, "    }", 0),
+                find(text, "validate", 0),
+                vec![],
+            )],
+        ),
+        symbol(
+            "impl fmt::Display for User",
+            SymbolKind::OBJECT,
+            item("impl fmt::Display", 0, "\n}", 2),
+            find(text, "User", 4),
+            vec![symbol(
+                "fmt",
+                SymbolKind::METHOD,
+                item("fn fmt", 0, "    }", 1),
+                find(text, "fmt", 2),
+                vec![],
+            )],
+        ),
+        symbol(
+            "impl Clone for Account",
+            SymbolKind::OBJECT,
+            item("impl Clone", 0, "\n}", 3),
+            find(text, "Account", 0),
+            vec![symbol(
+                "clone",
+                SymbolKind::METHOD,
+                item("fn clone", 0, "    }", 2),
+                find(text, "clone", 0),
+                vec![],
+            )],
+        ),
+        symbol(
+            "tests",
+            SymbolKind::MODULE,
+            item("mod tests", 0, "mod tests;", 0),
+            find(text, "tests", 0),
+            vec![],
+        ),
+    ]
+}
+
+#[test]
+fn test_node_kind() {
+    assert_eq!(
+        node_kind(SymbolKind::STRUCT, false, true),
+        Some(NodeKind::Type)
+    );
+    assert_eq!(
+        node_kind(SymbolKind::INTERFACE, false, true),
+        Some(NodeKind::Trait)
+    );
+    assert_eq!(
+        node_kind(SymbolKind::FUNCTION, false, true),
+        Some(NodeKind::Function)
+    );
+    assert_eq!(
+        node_kind(SymbolKind::FUNCTION, true, false),
+        Some(NodeKind::Method)
+    );
+    assert_eq!(
+        node_kind(SymbolKind::VARIABLE, false, true),
+        Some(NodeKind::Constant)
+    );
+    assert_eq!(node_kind(SymbolKind::VARIABLE, false, false), None);
+    assert_eq!(node_kind(SymbolKind::OBJECT, false, true), None);
+}
+
+#[test]
+fn test_parse_impl() {
+    assert_eq!(parse_impl("impl User"), Some((None, "User")));
+    assert_eq!(
+        parse_impl("impl<T: Copy> fmt::Display for Point<T>"),
+        Some((Some("Display"), "Point"))
+    );
+    assert_eq!(
+        parse_impl("impl<'a> From<&'a str> for &'a crate::Name"),
+        Some((Some("From"), "Name"))
+    );
+    assert_eq!(parse_impl("implementation"), None);
+    assert_eq!(parse_impl("User"), None);
+}
+
+#[test]
+fn test_document_symbols() {
+    let source = source("rust", USER);
+    let file = source.document_symbols("app::user", "::", &user_symbols());
+    insta::assert_snapshot!(render(&source, &file));
+}
+
+#[test]
+fn test_module_node() {
+    let source = source("rust", USER);
+    let node = source.module_node("user", "app::user");
+    assert_eq!(node.kind, NodeKind::Module);
+    assert_eq!(node.body, Some(source.document.full_range()));
+    assert_eq!(
+        source.document.slice(node.doc.unwrap()).unwrap(),
+        "//! User accounts.\n//! Users log in with their name."
+    );
+
+    let source = self::source("python", SERVICE);
+    let node = source.module_node("service", "app.service");
+    assert_eq!(
+        source.document.slice(node.doc.unwrap()).unwrap(),
+        r#""""Services of the application.""""#
+    );
+}
+
+#[test]
+fn test_flat_symbols() {
+    let text = SERVICE;
+    let flat = |name: &str, kind, container: Option<&str>, range| FlatSymbol {
+        name: name.to_string(),
+        kind,
+        container_name: container.map(str::to_string),
+        range,
+    };
+    let symbols = [
+        flat(
+            "run",
+            SymbolKind::FUNCTION,
+            None,
+            span(find(text, "def run", 0), find(text, "pass", 0)),
+        ),
+        flat(
+            "load",
+            SymbolKind::FUNCTION,
+            Some("Service"),
+            span(find(text, "def load", 0), find(text, "[id]", 0)),
+        ),
+        flat(
+            "Service",
+            SymbolKind::CLASS,
+            None,
+            span(find(text, "class", 0), find(text, "[id]", 0)),
+        ),
+    ];
+
+    let source = source("python", text);
+    let file = source.flat_symbols("app.service", ".", &symbols);
+    insta::assert_snapshot!(render(&source, &file));
+}
+
+#[test]
+fn test_symbols_without_tree_use_first_line() {
+    let text = "first line\nsecond line\n";
+    let source = source("plaintext", text);
+    let symbols = [symbol(
+        "first",
+        SymbolKind::FUNCTION,
+        span(find(text, "first", 0), find(text, "second line", 0)),
+        find(text, "first", 0),
+        vec![],
+    )];
+    let file = source.document_symbols("notes", ".", &symbols);
+    let node = &file.symbols.first().unwrap().node;
+    assert_eq!(source.document.slice(node.signature).unwrap(), "first line");
+    assert_eq!(node.body, None);
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn test_parse_impl_returns_simple_names(
+        generics in prop::sample::select(vec!["", "<T>", "<'a, T: Clone>"]),
+        trait_path in prop::sample::select(vec!["", "Display", "fmt::Display", "From<T>"]),
+        type_path in prop::sample::select(vec!["User", "crate::User", "User<T>", "&'a User"]),
+    ) {
+        let name = match trait_path {
+            "" => format!("impl{generics} {type_path}"),
+            _ => format!("impl{generics} {trait_path} for {type_path}"),
+        };
+        let (trait_name, type_name) = parse_impl(&name).unwrap();
+        prop_assert_eq!(type_name, "User");
+        prop_assert_eq!(trait_name.is_some(), !trait_path.is_empty());
+        for name in trait_name.into_iter().chain([type_name]) {
+            prop_assert!(!name.contains(['<', ':', '&', ' ']));
+        }
+    }
+}
@@ -4,8 +4,21 @@
 //! including MCP protocol implementation, LSP client integration,
 //! and symbol analysis capabilities.
 
-// pub mod error;
+pub mod cache;
+pub mod deps;
+pub mod diagnostics;
+pub mod document;
+pub mod edit;
+pub mod error;
+pub mod graph;
+pub mod index;
+pub mod lsp;
+pub mod patterns;
+pub mod syntax;
 pub mod types;
+pub mod vcs;
+pub mod watch;
+pub mod workspace;
 
 // Re-export commonly used types
-// pub use error::ContextEngineError;
+pub use error::{ContextEngineError, ErrorCode, JsonRpcError};
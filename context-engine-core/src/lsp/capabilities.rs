@@ -0,0 +1,585 @@
+//! Capabilities negotiated with a language server.
+
+use lsp_types::{
+    CallHierarchyServerCapability, ClientCapabilities, CodeActionCapabilityResolveSupport,
+    CodeActionClientCapabilities, CodeActionKind, CodeActionKindLiteralSupport,
+    CodeActionLiteralSupport, CodeActionProviderCapability, DiagnosticClientCapabilities,
+    DiagnosticServerCapabilities, DidChangeWatchedFilesClientCapabilities,
+    DocumentSymbolClientCapabilities, DynamicRegistrationClientCapabilities, FailureHandlingKind,
+    GeneralClientCapabilities, GotoCapability, HoverClientCapabilities, HoverProviderCapability,
+    InlayHintClientCapabilities, MarkupKind, OneOf, PositionEncodingKind,
+    PublishDiagnosticsClientCapabilities, Registration, RenameClientCapabilities,
+    ResourceOperationKind, SemanticTokenModifier, SemanticTokenType,
+    SemanticTokensClientCapabilities, SemanticTokensClientCapabilitiesRequests,
+    SemanticTokensFullOptions, SemanticTokensServerCapabilities, ServerCapabilities, SymbolKind,
+    SymbolKindCapability, TextDocumentClientCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncClientCapabilities, TextDocumentSyncKind, TextDocumentSyncSaveOptions,
+    TokenFormat, Unregistration, WindowClientCapabilities, WorkspaceClientCapabilities,
+    WorkspaceEditClientCapabilities, WorkspaceSymbolClientCapabilities,
+};
+use parking_lot::RwLock;
+
+/// Methods that every language server supports.
+const LIFECYCLE_METHODS: [&str; 5] = [
+    "initialize",
+    "initialized",
+    "shutdown",
+    "exit",
+    "$/cancelRequest",
+];
+
+/// Returns the capabilities the client advertises in the `initialize`
+/// request.
+///
+/// The client supports every [`PositionEncodingKind`] of the LSP; the
+/// `preferred` encoding is listed first, so that servers which support it
+/// report positions in it.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::lsp::client_capabilities;
+/// use context_engine_core::types::PositionEncodingKind;
+///
+/// let capabilities = client_capabilities(&PositionEncodingKind::UTF8);
+/// let encodings = capabilities.general.unwrap().position_encodings.unwrap();
+/// assert_eq!(encodings.first(), Some(&PositionEncodingKind::UTF8));
+/// assert!(encodings.contains(&PositionEncodingKind::UTF16));
+/// ```
+pub fn client_capabilities(preferred: &PositionEncodingKind) -> ClientCapabilities {
+    let mut position_encodings = vec![preferred.clone()];
+    for encoding in [
+        PositionEncodingKind::UTF16,
+        PositionEncodingKind::UTF8,
+        PositionEncodingKind::UTF32,
+    ] {
+        if !position_encodings.contains(&encoding) {
+            position_encodings.push(encoding);
+        }
+    }
+
+    let dynamic = DynamicRegistrationClientCapabilities {
+        dynamic_registration: Some(true),
+    };
+    let goto = GotoCapability {
+        dynamic_registration: Some(true),
+        link_support: Some(true),
+    };
+    let symbol_kind = SymbolKindCapability {
+        value_set: Some(symbol_kinds()),
+    };
+
+    ClientCapabilities {
+        workspace: Some(WorkspaceClientCapabilities {
+            symbol: Some(WorkspaceSymbolClientCapabilities {
+                dynamic_registration: Some(true),
+                symbol_kind: Some(symbol_kind.clone()),
+                ..WorkspaceSymbolClientCapabilities::default()
+            }),
+            did_change_watched_files: Some(DidChangeWatchedFilesClientCapabilities {
+                dynamic_registration: Some(true),
+                relative_pattern_support: Some(true),
+            }),
+            workspace_edit: Some(WorkspaceEditClientCapabilities {
+                document_changes: Some(true),
+                resource_operations: Some(vec![
+                    ResourceOperationKind::Create,
+                    ResourceOperationKind::Rename,
+                    ResourceOperationKind::Delete,
+                ]),
+                failure_handling: Some(FailureHandlingKind::Abort),
+                ..WorkspaceEditClientCapabilities::default()
+            }),
+            workspace_folders: Some(true),
+            configuration: Some(true),
+            ..WorkspaceClientCapabilities::default()
+        }),
+        text_document: Some(TextDocumentClientCapabilities {
+            synchronization: Some(TextDocumentSyncClientCapabilities {
+                dynamic_registration: Some(true),
+                did_save: Some(true),
+                ..TextDocumentSyncClientCapabilities::default()
+            }),
+            hover: Some(HoverClientCapabilities {
+                dynamic_registration: Some(true),
+                content_format: Some(vec![MarkupKind::Markdown, MarkupKind::PlainText]),
+            }),
+            references: Some(dynamic),
+            code_action: Some(CodeActionClientCapabilities {
+                dynamic_registration: Some(true),
+                code_action_literal_support: Some(CodeActionLiteralSupport {
+                    code_action_kind: CodeActionKindLiteralSupport {
+                        value_set: code_action_kinds(),
+                    },
+                }),
+                is_preferred_support: Some(true),
+                disabled_support: Some(true),
+                data_support: Some(true),
+                resolve_support: Some(CodeActionCapabilityResolveSupport {
+                    properties: vec!["edit".to_string()],
+                }),
+                ..CodeActionClientCapabilities::default()
+            }),
+            document_highlight: Some(dynamic),
+            document_symbol: Some(DocumentSymbolClientCapabilities {
+                dynamic_registration: Some(true),
+                symbol_kind: Some(symbol_kind),
+                hierarchical_document_symbol_support: Some(true),
+                ..DocumentSymbolClientCapabilities::default()
+            }),
+            declaration: Some(goto),
+            definition: Some(goto),
+            type_definition: Some(goto),
+            implementation: Some(goto),
+            rename: Some(RenameClientCapabilities {
+                dynamic_registration: Some(true),
+                prepare_support: Some(true),
+                ..RenameClientCapabilities::default()
+            }),
+            publish_diagnostics: Some(PublishDiagnosticsClientCapabilities {
+                related_information: Some(true),
+                version_support: Some(true),
+                ..PublishDiagnosticsClientCapabilities::default()
+            }),
+            diagnostic: Some(DiagnosticClientCapabilities {
+                dynamic_registration: Some(true),
+                related_document_support: Some(false),
+            }),
+            call_hierarchy: Some(dynamic),
+            semantic_tokens: Some(SemanticTokensClientCapabilities {
+                dynamic_registration: Some(true),
+                requests: SemanticTokensClientCapabilitiesRequests {
+                    range: Some(true),
+                    full: Some(SemanticTokensFullOptions::Bool(true)),
+                },
+                token_types: semantic_token_types(),
+                token_modifiers: semantic_token_modifiers(),
+                formats: vec![TokenFormat::RELATIVE],
+                multiline_token_support: Some(true),
+                ..SemanticTokensClientCapabilities::default()
+            }),
+            type_hierarchy: Some(dynamic),
+            inlay_hint: Some(InlayHintClientCapabilities {
+                dynamic_registration: Some(true),
+                resolve_support: None,
+            }),
+            ..TextDocumentClientCapabilities::default()
+        }),
+        window: Some(WindowClientCapabilities {
+            work_done_progress: Some(true),
+            ..WindowClientCapabilities::default()
+        }),
+        general: Some(GeneralClientCapabilities {
+            position_encodings: Some(position_encodings),
+            ..GeneralClientCapabilities::default()
+        }),
+        ..ClientCapabilities::default()
+    }
+}
+
+/// The capabilities of a language server: the ones it reported in its
+/// `initialize` response plus the ones it registered dynamically with
+/// `client/registerCapability`.
+///
+/// Features that need a capability a server lacks should check
+/// [`NegotiatedCapabilities::supports`] first and degrade gracefully, instead
+/// of sending a request the server answers with an error or not at all.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::lsp::NegotiatedCapabilities;
+/// use lsp_types::request::{HoverRequest, References, Request};
+/// use lsp_types::{HoverProviderCapability, ServerCapabilities};
+///
+/// let capabilities = NegotiatedCapabilities::default();
+/// capabilities.set_server_capabilities(ServerCapabilities {
+///     hover_provider: Some(HoverProviderCapability::Simple(true)),
+///     ..ServerCapabilities::default()
+/// });
+///
+/// assert!(capabilities.supports(HoverRequest::METHOD));
+/// assert!(!capabilities.supports(References::METHOD));
+/// ```
+#[derive(Debug, Default)]
+pub struct NegotiatedCapabilities {
+    server: RwLock<ServerCapabilities>,
+    registrations: RwLock<Vec<Registration>>,
+}
+
+impl NegotiatedCapabilities {
+    /// Returns the capabilities the server reported in its `initialize`
+    /// response.
+    pub fn server_capabilities(&self) -> ServerCapabilities {
+        self.server.read().clone()
+    }
+
+    /// Replaces the capabilities the server reported in its `initialize`
+    /// response.
+    pub fn set_server_capabilities(&self, capabilities: ServerCapabilities) {
+        *self.server.write() = capabilities;
+    }
+
+    /// Returns the encoding of the positions exchanged with the server,
+    /// UTF-16 unless the server chose another one.
+    pub fn position_encoding(&self) -> PositionEncodingKind {
+        self.server
+            .read()
+            .position_encoding
+            .clone()
+            .unwrap_or(PositionEncodingKind::UTF16)
+    }
+
+    /// Returns the capabilities the server registered dynamically, in the
+    /// order they were registered.
+    pub fn registrations(&self) -> Vec<Registration> {
+        self.registrations.read().clone()
+    }
+
+    /// Adds dynamically registered capabilities, replacing registrations with
+    /// the same ID.
+    pub fn register(&self, registrations: impl IntoIterator<Item = Registration>) {
+        let mut current = self.registrations.write();
+        for registration in registrations {
+            current.retain(|existing| existing.id != registration.id);
+            current.push(registration);
+        }
+    }
+
+    /// Removes dynamically registered capabilities.
+    pub fn unregister(&self, unregistrations: &[Unregistration]) {
+        self.registrations.write().retain(|registration| {
+            !unregistrations
+                .iter()
+                .any(|unregistration| unregistration.id == registration.id)
+        });
+    }
+
+    /// Returns true if the server supports a request or notification method,
+    /// either statically or through a dynamic registration.
+    ///
+    /// Follow-up methods are supported together with the method that starts
+    /// them, e.g. `callHierarchy/incomingCalls` with
+    /// `textDocument/prepareCallHierarchy`. Unknown methods are only supported
+    /// if they were registered dynamically.
+    pub fn supports(&self, method: &str) -> bool {
+        if LIFECYCLE_METHODS.contains(&method) {
+            return true;
+        }
+
+        let registered = registration_method(method);
+        if self
+            .registrations
+            .read()
+            .iter()
+            .any(|registration| registration.method == registered)
+        {
+            return true;
+        }
+
+        supports_statically(&self.server.read(), method)
+    }
+}
+
+/// Returns the method under which the capability for `method` is registered
+/// dynamically.
+fn registration_method(method: &str) -> &str {
+    match method {
+        "completionItem/resolve" => "textDocument/completion",
+        "codeAction/resolve" => "textDocument/codeAction",
+        "codeLens/resolve" => "textDocument/codeLens",
+        "documentLink/resolve" => "textDocument/documentLink",
+        "inlayHint/resolve" => "textDocument/inlayHint",
+        "workspaceSymbol/resolve" => "workspace/symbol",
+        "textDocument/prepareRename" => "textDocument/rename",
+        "textDocument/colorPresentation" => "textDocument/documentColor",
+        _ if method.starts_with("callHierarchy/") => "textDocument/prepareCallHierarchy",
+        _ if method.starts_with("typeHierarchy/") => "textDocument/prepareTypeHierarchy",
+        _ if method.starts_with("textDocument/semanticTokens/") => "textDocument/semanticTokens",
+        _ => method,
+    }
+}
+
+/// Returns true if the `initialize` response of the server declares support
+/// for `method`.
+fn supports_statically(capabilities: &ServerCapabilities, method: &str) -> bool {
+    let c = capabilities;
+    match method {
+        "textDocument/didOpen" | "textDocument/didClose" => match &c.text_document_sync {
+            Some(TextDocumentSyncCapability::Kind(kind)) => *kind != TextDocumentSyncKind::NONE,
+            Some(TextDocumentSyncCapability::Options(options)) => options.open_close == Some(true),
+            None => false,
+        },
+        "textDocument/didChange" => match &c.text_document_sync {
+            Some(TextDocumentSyncCapability::Kind(kind)) => *kind != TextDocumentSyncKind::NONE,
+            Some(TextDocumentSyncCapability::Options(options)) => options
+                .change
+                .is_some_and(|kind| kind != TextDocumentSyncKind::NONE),
+            None => false,
+        },
+        "textDocument/didSave" => match &c.text_document_sync {
+            Some(TextDocumentSyncCapability::Options(options)) => match &options.save {
+                Some(TextDocumentSyncSaveOptions::Supported(supported)) => *supported,
+                Some(TextDocumentSyncSaveOptions::SaveOptions(_)) => true,
+                None => false,
+            },
+            _ => false,
+        },
+        "textDocument/willSave" | "textDocument/willSaveWaitUntil" => match &c.text_document_sync {
+            Some(TextDocumentSyncCapability::Options(options)) => {
+                let flag = if method.ends_with("WaitUntil") {
+                    options.will_save_wait_until
+                } else {
+                    options.will_save
+                };
+                flag == Some(true)
+            }
+            _ => false,
+        },
+        "textDocument/hover" => match &c.hover_provider {
+            Some(HoverProviderCapability::Simple(supported)) => *supported,
+            Some(HoverProviderCapability::Options(_)) => true,
+            None => false,
+        },
+        "textDocument/completion" => c.completion_provider.is_some(),
+        "completionItem/resolve" => c
+            .completion_provider
+            .as_ref()
+            .is_some_and(|options| options.resolve_provider == Some(true)),
+        "textDocument/signatureHelp" => c.signature_help_provider.is_some(),
+        "textDocument/declaration" => c.declaration_provider.is_some(),
+        "textDocument/definition" => one_of(&c.definition_provider),
+        "textDocument/typeDefinition" => c.type_definition_provider.is_some(),
+        "textDocument/implementation" => c.implementation_provider.is_some(),
+        "textDocument/references" => one_of(&c.references_provider),
+        "textDocument/documentHighlight" => one_of(&c.document_highlight_provider),
+        "textDocument/documentSymbol" => one_of(&c.document_symbol_provider),
+        "workspace/symbol" => one_of(&c.workspace_symbol_provider),
+        "workspaceSymbol/resolve" => matches!(
+            &c.workspace_symbol_provider,
+            Some(OneOf::Right(options)) if options.resolve_provider == Some(true)
+        ),
+        "textDocument/codeAction" => match &c.code_action_provider {
+            Some(CodeActionProviderCapability::Simple(supported)) => *supported,
+            Some(CodeActionProviderCapability::Options(_)) => true,
+            None => false,
+        },
+        "codeAction/resolve" => matches!(
+            &c.code_action_provider,
+            Some(CodeActionProviderCapability::Options(options))
+                if options.resolve_provider == Some(true)
+        ),
+        "textDocument/codeLens" => c.code_lens_provider.is_some(),
+        "codeLens/resolve" => c
+            .code_lens_provider
+            .as_ref()
+            .is_some_and(|options| options.resolve_provider == Some(true)),
+        "textDocument/documentLink" => c.document_link_provider.is_some(),
+        "documentLink/resolve" => c
+            .document_link_provider
+            .as_ref()
+            .is_some_and(|options| options.resolve_provider == Some(true)),
+        "textDocument/documentColor" | "textDocument/colorPresentation" => {
+            c.color_provider.is_some()
+        }
+        "textDocument/formatting" => one_of(&c.document_formatting_provider),
+        "textDocument/rangeFormatting" => one_of(&c.document_range_formatting_provider),
+        "textDocument/onTypeFormatting" => c.document_on_type_formatting_provider.is_some(),
+        "textDocument/rename" => one_of(&c.rename_provider),
+        "textDocument/prepareRename" => matches!(
+            &c.rename_provider,
+            Some(OneOf::Right(options)) if options.prepare_provider == Some(true)
+        ),
+        "textDocument/foldingRange" => c.folding_range_provider.is_some(),
+        "textDocument/selectionRange" => c.selection_range_provider.is_some(),
+        "workspace/executeCommand" => c.execute_command_provider.is_some(),
+        "textDocument/prepareCallHierarchy"
+        | "callHierarchy/incomingCalls"
+        | "callHierarchy/outgoingCalls" => match &c.call_hierarchy_provider {
+            Some(CallHierarchyServerCapability::Simple(supported)) => *supported,
+            Some(_) => true,
+            None => false,
+        },
+        "textDocument/semanticTokens/full" | "textDocument/semanticTokens/full/delta" => {
+            let full = match &c.semantic_tokens_provider {
+                Some(SemanticTokensServerCapabilities::SemanticTokensOptions(options)) => {
+                    options.full.as_ref()
+                }
+                Some(SemanticTokensServerCapabilities::SemanticTokensRegistrationOptions(
+                    options,
+                )) => options.semantic_tokens_options.full.as_ref(),
+                None => None,
+            };
+            match full {
+                Some(SemanticTokensFullOptions::Bool(supported)) => {
+                    *supported && !method.ends_with("/delta")
+                }
+                Some(SemanticTokensFullOptions::Delta { delta }) => {
+                    !method.ends_with("/delta") || *delta == Some(true)
+                }
+                None => false,
+            }
+        }
+        "textDocument/semanticTokens/range" => {
+            let range = match &c.semantic_tokens_provider {
+                Some(SemanticTokensServerCapabilities::SemanticTokensOptions(options)) => {
+                    options.range
+                }
+                Some(SemanticTokensServerCapabilities::SemanticTokensRegistrationOptions(
+                    options,
+                )) => options.semantic_tokens_options.range,
+                None => None,
+            };
+            range == Some(true)
+        }
+        "textDocument/moniker" => one_of(&c.moniker_provider),
+        "textDocument/linkedEditingRange" => c.linked_editing_range_provider.is_some(),
+        "textDocument/inlineValue" => one_of(&c.inline_value_provider),
+        "textDocument/inlayHint" | "inlayHint/resolve" => {
+            let resolve = method == "inlayHint/resolve";
+            match &c.inlay_hint_provider {
+                Some(OneOf::Left(supported)) => *supported && !resolve,
+                Some(OneOf::Right(lsp_types::InlayHintServerCapabilities::Options(options))) => {
+                    !resolve || options.resolve_provider == Some(true)
+                }
+                Some(OneOf::Right(_)) => !resolve,
+                None => false,
+            }
+        }
+        "textDocument/diagnostic" => c.diagnostic_provider.is_some(),
+        "workspace/diagnostic" => match &c.diagnostic_provider {
+            Some(DiagnosticServerCapabilities::Options(options)) => options.workspace_diagnostics,
+            Some(DiagnosticServerCapabilities::RegistrationOptions(options)) => {
+                options.diagnostic_options.workspace_diagnostics
+            }
+            None => false,
+        },
+        "workspace/willCreateFiles"
+        | "workspace/didCreateFiles"
+        | "workspace/willRenameFiles"
+        | "workspace/didRenameFiles"
+        | "workspace/willDeleteFiles"
+        | "workspace/didDeleteFiles" => {
+            let Some(operations) = c
+                .workspace
+                .as_ref()
+                .and_then(|workspace| workspace.file_operations.as_ref())
+            else {
+                return false;
+            };
+            match method.trim_start_matches("workspace/") {
+                "willCreateFiles" => operations.will_create.is_some(),
+                "didCreateFiles" => operations.did_create.is_some(),
+                "willRenameFiles" => operations.will_rename.is_some(),
+                "didRenameFiles" => operations.did_rename.is_some(),
+                "willDeleteFiles" => operations.will_delete.is_some(),
+                _ => operations.did_delete.is_some(),
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Returns true if a capability that is either a flag or options is enabled.
+fn one_of<T>(capability: &Option<OneOf<bool, T>>) -> bool {
+    match capability {
+        Some(OneOf::Left(supported)) => *supported,
+        Some(OneOf::Right(_)) => true,
+        None => false,
+    }
+}
+
+fn symbol_kinds() -> Vec<SymbolKind> {
+    vec![
+        SymbolKind::FILE,
+        SymbolKind::MODULE,
+        SymbolKind::NAMESPACE,
+        SymbolKind::PACKAGE,
+        SymbolKind::CLASS,
+        SymbolKind::METHOD,
+        SymbolKind::PROPERTY,
+        SymbolKind::FIELD,
+        SymbolKind::CONSTRUCTOR,
+        SymbolKind::ENUM,
+        SymbolKind::INTERFACE,
+        SymbolKind::FUNCTION,
+        SymbolKind::VARIABLE,
+        SymbolKind::CONSTANT,
+        SymbolKind::STRING,
+        SymbolKind::NUMBER,
+        SymbolKind::BOOLEAN,
+        SymbolKind::ARRAY,
+        SymbolKind::OBJECT,
+        SymbolKind::KEY,
+        SymbolKind::NULL,
+        SymbolKind::ENUM_MEMBER,
+        SymbolKind::STRUCT,
+        SymbolKind::EVENT,
+        SymbolKind::OPERATOR,
+        SymbolKind::TYPE_PARAMETER,
+    ]
+}
+
+fn code_action_kinds() -> Vec<String> {
+    [
+        CodeActionKind::EMPTY,
+        CodeActionKind::QUICKFIX,
+        CodeActionKind::REFACTOR,
+        CodeActionKind::REFACTOR_EXTRACT,
+        CodeActionKind::REFACTOR_INLINE,
+        CodeActionKind::REFACTOR_REWRITE,
+        CodeActionKind::SOURCE,
+        CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+        CodeActionKind::SOURCE_FIX_ALL,
+    ]
+    .into_iter()
+    .map(|kind| kind.as_str().to_string())
+    .collect()
+}
+
+fn semantic_token_types() -> Vec<SemanticTokenType> {
+    vec![
+        SemanticTokenType::NAMESPACE,
+        SemanticTokenType::TYPE,
+        SemanticTokenType::CLASS,
+        SemanticTokenType::ENUM,
+        SemanticTokenType::INTERFACE,
+        SemanticTokenType::STRUCT,
+        SemanticTokenType::TYPE_PARAMETER,
+        SemanticTokenType::PARAMETER,
+        SemanticTokenType::VARIABLE,
+        SemanticTokenType::PROPERTY,
+        SemanticTokenType::ENUM_MEMBER,
+        SemanticTokenType::EVENT,
+        SemanticTokenType::FUNCTION,
+        SemanticTokenType::METHOD,
+        SemanticTokenType::MACRO,
+        SemanticTokenType::KEYWORD,
+        SemanticTokenType::MODIFIER,
+        SemanticTokenType::COMMENT,
+        SemanticTokenType::STRING,
+        SemanticTokenType::NUMBER,
+        SemanticTokenType::REGEXP,
+        SemanticTokenType::OPERATOR,
+        SemanticTokenType::DECORATOR,
+    ]
+}
+
+fn semantic_token_modifiers() -> Vec<SemanticTokenModifier> {
+    vec![
+        SemanticTokenModifier::DECLARATION,
+        SemanticTokenModifier::DEFINITION,
+        SemanticTokenModifier::READONLY,
+        SemanticTokenModifier::STATIC,
+        SemanticTokenModifier::DEPRECATED,
+        SemanticTokenModifier::ABSTRACT,
+        SemanticTokenModifier::ASYNC,
+        SemanticTokenModifier::MODIFICATION,
+        SemanticTokenModifier::DOCUMENTATION,
+        SemanticTokenModifier::DEFAULT_LIBRARY,
+    ]
+}
+
+#[cfg(test)]
+#[path = "tests/capabilities.rs"]
+mod tests;
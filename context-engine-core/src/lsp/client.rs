@@ -0,0 +1,604 @@
+//! Client side of a JSON-RPC connection to a language server.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::Duration;
+
+use lsp_types::notification::{Cancel, Initialized, Notification};
+use lsp_types::request::{
+    Initialize, RegisterCapability, Request, UnregisterCapability, WorkDoneProgressCreate,
+    WorkspaceConfiguration,
+};
+use lsp_types::{
+    CancelParams, ConfigurationParams, InitializeParams, InitializeResult, InitializedParams,
+};
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, BufReader};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::error::{ContextEngineError, JsonRpcError};
+use crate::lsp::{
+    Message, NegotiatedCapabilities, NotificationMessage, RequestId, RequestMessage,
+    ResponseMessage, codec,
+};
+
+/// Time after which requests without an explicit timeout fail.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// JSON-RPC error code for requests whose method has no handler.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// JSON-RPC error code for requests whose parameters cannot be deserialized.
+const INVALID_PARAMS: i64 = -32602;
+
+/// LSP error code of requests cancelled by the server.
+const REQUEST_CANCELLED: i64 = -32800;
+
+type RequestHandler = Arc<dyn Fn(Option<Value>) -> Result<Value, JsonRpcError> + Send + Sync>;
+type Subscriber = Box<dyn Fn(Option<&Value>) -> bool + Send>;
+type PendingResponse = oneshot::Sender<Result<Value, JsonRpcError>>;
+
+/// A connection to a language server.
+///
+/// The client speaks JSON-RPC 2.0 with `Content-Length` framing over any pair
+/// of byte streams, usually the stdin and stdout of a language server child
+/// process. It
+///
+/// * correlates the responses of the server with the requests awaiting them,
+/// * answers requests of the server (e.g. `workspace/configuration`) with the
+///   handler registered for their method, and
+/// * forwards notifications of the server (e.g.
+///   `textDocument/publishDiagnostics`) to their subscribers.
+///
+/// Out of the box the client answers `workspace/configuration` with `null`
+/// for every item, acknowledges `window/workDoneProgress/create`, and records
+/// `client/registerCapability` and `client/unregisterCapability` in its
+/// [capabilities](LspClient::capabilities); register a handler with
+/// [`LspClient::on_request`] to replace these defaults.
+///
+/// Reading and writing happen on background tasks, so the client must be
+/// created within a tokio runtime. The tasks stop when the client is dropped.
+/// Once the connection is closed, all pending and future requests fail with
+/// [`ContextEngineError::Transport`].
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::lsp::LspClient;
+/// use lsp_types::notification::PublishDiagnostics;
+///
+/// # tokio_test::block_on(async {
+/// let (client_io, _server_io) = tokio::io::duplex(4096);
+/// let (reader, writer) = tokio::io::split(client_io);
+///
+/// let client = LspClient::new("rust-analyzer", reader, writer);
+/// let mut diagnostics = client.subscribe::<PublishDiagnostics>();
+/// # let _ = &mut diagnostics;
+///
+/// assert_eq!(client.server(), "rust-analyzer");
+/// assert!(!client.is_closed());
+/// # });
+/// ```
+pub struct LspClient {
+    shared: Arc<Shared>,
+    reader: JoinHandle<()>,
+    writer: JoinHandle<()>,
+}
+
+/// State shared between the client and its background tasks.
+struct Shared {
+    server: String,
+    request_timeout: Duration,
+    next_id: AtomicI32,
+    outgoing: mpsc::UnboundedSender<Message>,
+    connection: Mutex<Connection>,
+    handlers: RwLock<HashMap<String, RequestHandler>>,
+    capabilities: Arc<NegotiatedCapabilities>,
+    subscribers: Mutex<HashMap<String, Vec<Subscriber>>>,
+}
+
+#[derive(Default)]
+struct Connection {
+    pending: HashMap<RequestId, PendingResponse>,
+    closed: Option<String>,
+}
+
+impl LspClient {
+    /// Creates a client that reads messages from `reader` and writes messages
+    /// to `writer`, with the [`DEFAULT_REQUEST_TIMEOUT`].
+    ///
+    /// # Arguments
+    ///
+    /// * `server` - Name of the language server, used in errors and logs
+    /// * `reader` - The stream of messages from the server
+    /// * `writer` - The stream of messages to the server
+    pub fn new<R, W>(server: impl Into<String>, reader: R, writer: W) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        Self::with_request_timeout(server, reader, writer, DEFAULT_REQUEST_TIMEOUT)
+    }
+
+    /// Creates a client whose requests fail after `request_timeout`.
+    ///
+    /// See [`LspClient::new`] for the arguments.
+    pub fn with_request_timeout<R, W>(
+        server: impl Into<String>,
+        reader: R,
+        writer: W,
+        request_timeout: Duration,
+    ) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let (outgoing, messages) = mpsc::unbounded_channel();
+        let shared = Arc::new(Shared {
+            server: server.into(),
+            request_timeout,
+            next_id: AtomicI32::new(1),
+            outgoing,
+            connection: Mutex::new(Connection::default()),
+            handlers: RwLock::new(HashMap::new()),
+            capabilities: Arc::new(NegotiatedCapabilities::default()),
+            subscribers: Mutex::new(HashMap::new()),
+        });
+
+        let client = Self {
+            reader: tokio::spawn(read_loop(Arc::clone(&shared), BufReader::new(reader))),
+            writer: tokio::spawn(write_loop(Arc::clone(&shared), messages, writer)),
+            shared,
+        };
+        client.on_request::<WorkspaceConfiguration, _>(|params: ConfigurationParams| {
+            Ok(vec![Value::Null; params.items.len()])
+        });
+        client.on_request::<WorkDoneProgressCreate, _>(|_| Ok(()));
+        let capabilities = Arc::clone(&client.shared.capabilities);
+        client.on_request::<RegisterCapability, _>(move |params| {
+            capabilities.register(params.registrations);
+            Ok(())
+        });
+        let capabilities = Arc::clone(&client.shared.capabilities);
+        client.on_request::<UnregisterCapability, _>(move |params| {
+            capabilities.unregister(&params.unregisterations);
+            Ok(())
+        });
+        client
+    }
+
+    /// Returns the name of the language server.
+    pub fn server(&self) -> &str {
+        &self.shared.server
+    }
+
+    /// Returns the timeout of requests sent with [`LspClient::request`].
+    pub fn request_timeout(&self) -> Duration {
+        self.shared.request_timeout
+    }
+
+    /// Returns the capabilities of the server.
+    ///
+    /// They are empty until [`LspClient::initialize`] succeeded.
+    pub fn capabilities(&self) -> &NegotiatedCapabilities {
+        &self.shared.capabilities
+    }
+
+    /// Returns true if the server supports a request or notification method.
+    ///
+    /// See [`NegotiatedCapabilities::supports`].
+    pub fn supports(&self, method: &str) -> bool {
+        self.shared.capabilities.supports(method)
+    }
+
+    /// Performs the `initialize`/`initialized` handshake and stores the
+    /// capabilities of the server.
+    ///
+    /// # Errors
+    ///
+    /// See [`LspClient::request`].
+    pub async fn initialize(
+        &self,
+        params: InitializeParams,
+    ) -> Result<InitializeResult, ContextEngineError> {
+        let result = self.request::<Initialize>(params).await?;
+        self.shared
+            .capabilities
+            .set_server_capabilities(result.capabilities.clone());
+        self.notify::<Initialized>(InitializedParams {})?;
+        Ok(result)
+    }
+
+    /// Returns true if the connection to the server is closed.
+    pub fn is_closed(&self) -> bool {
+        self.shared.connection.lock().closed.is_some()
+    }
+
+    /// Sends a request and waits for its response.
+    ///
+    /// # Errors
+    ///
+    /// * [`ContextEngineError::Transport`] - If the connection is closed
+    /// * [`ContextEngineError::Timeout`] - If the server does not respond
+    ///   within the [request timeout](LspClient::request_timeout)
+    /// * [`ContextEngineError::Cancelled`] - If the server cancelled the
+    ///   request
+    /// * [`ContextEngineError::ServerError`] - If the server responded with an
+    ///   error
+    /// * [`ContextEngineError::Parse`] - If the result cannot be deserialized
+    pub async fn request<R>(&self, params: R::Params) -> Result<R::Result, ContextEngineError>
+    where
+        R: Request,
+    {
+        self.request_with_timeout::<R>(params, self.shared.request_timeout)
+            .await
+    }
+
+    /// Sends a request and waits at most `timeout` for its response.
+    ///
+    /// # Errors
+    ///
+    /// See [`LspClient::request`].
+    pub async fn request_with_timeout<R>(
+        &self,
+        params: R::Params,
+        timeout: Duration,
+    ) -> Result<R::Result, ContextEngineError>
+    where
+        R: Request,
+    {
+        let result = self
+            .send_request(R::METHOD, to_params(&params)?, timeout)
+            .await?;
+        serde_json::from_value(result).map_err(|error| ContextEngineError::Parse {
+            uri: None,
+            message: format!(
+                "Invalid result of '{}' from language server '{}': {error}",
+                R::METHOD,
+                self.shared.server
+            ),
+        })
+    }
+
+    /// Sends a request with untyped parameters and waits at most `timeout`
+    /// for its result.
+    ///
+    /// If the request does not complete, e.g. because it timed out or the
+    /// returned future was dropped, the server is sent a `$/cancelRequest`
+    /// notification.
+    ///
+    /// # Errors
+    ///
+    /// See [`LspClient::request`].
+    pub async fn send_request(
+        &self,
+        method: &str,
+        params: Option<Value>,
+        timeout: Duration,
+    ) -> Result<Value, ContextEngineError> {
+        let id = RequestId::Number(self.shared.next_id.fetch_add(1, Ordering::Relaxed));
+        let (sender, receiver) = oneshot::channel();
+        {
+            let mut connection = self.shared.connection.lock();
+            if let Some(reason) = &connection.closed {
+                return Err(self.shared.transport_error(Some(method), reason.clone()));
+            }
+            connection.pending.insert(id.clone(), sender);
+        }
+
+        let pending = PendingRequest {
+            shared: &self.shared,
+            id: id.clone(),
+        };
+        debug!(server = %self.shared.server, method, ?id, "Sending request");
+        self.shared
+            .send(RequestMessage::new(id, method, params).into());
+
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(Ok(result))) => Ok(result),
+            Ok(Ok(Err(error))) => Err(self.shared.response_error(method, error)),
+            Ok(Err(_)) => {
+                let reason = self.shared.close_reason();
+                Err(self.shared.transport_error(Some(method), reason))
+            }
+            Err(_) => {
+                drop(pending);
+                Err(ContextEngineError::Timeout {
+                    server: self.shared.server.clone(),
+                    method: method.to_string(),
+                    timeout_ms: u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX),
+                })
+            }
+        }
+    }
+
+    /// Sends a notification.
+    ///
+    /// # Errors
+    ///
+    /// * [`ContextEngineError::Transport`] - If the connection is closed
+    /// * [`ContextEngineError::Parse`] - If the parameters cannot be serialized
+    pub fn notify<N>(&self, params: N::Params) -> Result<(), ContextEngineError>
+    where
+        N: Notification,
+    {
+        self.send_notification(N::METHOD, to_params(&params)?)
+    }
+
+    /// Sends a notification with untyped parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::Transport`] if the connection is closed.
+    pub fn send_notification(
+        &self,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<(), ContextEngineError> {
+        if let Some(reason) = self.shared.connection.lock().closed.clone() {
+            return Err(self.shared.transport_error(Some(method), reason));
+        }
+
+        debug!(server = %self.shared.server, method, "Sending notification");
+        self.shared
+            .send(NotificationMessage::new(method, params).into());
+        Ok(())
+    }
+
+    /// Registers the handler of requests the server sends for method
+    /// `R::METHOD`, replacing any previous handler.
+    ///
+    /// Handlers run on the task that reads from the server, so they must not
+    /// block. Requests whose parameters cannot be deserialized are answered
+    /// with an `InvalidParams` error without calling the handler.
+    pub fn on_request<R, F>(&self, handler: F)
+    where
+        R: Request,
+        F: Fn(R::Params) -> Result<R::Result, JsonRpcError> + Send + Sync + 'static,
+    {
+        self.set_request_handler(R::METHOD, move |params| {
+            let params = serde_json::from_value(params.unwrap_or(Value::Null))
+                .map_err(|error| JsonRpcError::new(INVALID_PARAMS, error.to_string()))?;
+            let result = handler(params)?;
+            serde_json::to_value(result)
+                .map_err(|error| JsonRpcError::new(INVALID_PARAMS, error.to_string()))
+        });
+    }
+
+    /// Registers the handler of requests with untyped parameters the server
+    /// sends for `method`, replacing any previous handler.
+    ///
+    /// See [`LspClient::on_request`].
+    pub fn set_request_handler<F>(&self, method: impl Into<String>, handler: F)
+    where
+        F: Fn(Option<Value>) -> Result<Value, JsonRpcError> + Send + Sync + 'static,
+    {
+        self.shared
+            .handlers
+            .write()
+            .insert(method.into(), Arc::new(handler));
+    }
+
+    /// Subscribes to the notifications the server sends for method
+    /// `N::METHOD`.
+    ///
+    /// Every subscriber receives every notification sent after it subscribed.
+    /// Notifications whose parameters cannot be deserialized are logged and
+    /// dropped. The stream ends when the connection is closed; dropping the
+    /// receiver ends the subscription.
+    pub fn subscribe<N>(&self) -> mpsc::UnboundedReceiver<N::Params>
+    where
+        N: Notification,
+    {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let server = self.shared.server.clone();
+        let subscriber: Subscriber = Box::new(move |params| {
+            match serde_json::from_value::<N::Params>(params.cloned().unwrap_or(Value::Null)) {
+                Ok(params) => sender.send(params).is_ok(),
+                Err(error) => {
+                    warn!(%server, method = N::METHOD, %error, "Dropping malformed notification");
+                    !sender.is_closed()
+                }
+            }
+        });
+
+        if !self.is_closed() {
+            self.shared
+                .subscribers
+                .lock()
+                .entry(N::METHOD.to_string())
+                .or_default()
+                .push(subscriber);
+        }
+        receiver
+    }
+}
+
+impl fmt::Debug for LspClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LspClient")
+            .field("server", &self.shared.server)
+            .field("request_timeout", &self.shared.request_timeout)
+            .field("closed", &self.is_closed())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+        self.writer.abort();
+    }
+}
+
+impl Shared {
+    /// Queues a message for the writer task.
+    fn send(&self, message: Message) {
+        // The writer task only stops after closing the connection, which
+        // already fails every pending request.
+        let _ = self.outgoing.send(message);
+    }
+
+    /// Handles a message received from the server.
+    fn dispatch(&self, message: Message) {
+        debug!(server = %self.server, %message, "Received message");
+        match message {
+            Message::Response(response) => {
+                let Some(id) = response.id.clone() else {
+                    warn!(server = %self.server, error = ?response.error, "Received response without id");
+                    return;
+                };
+                match self.connection.lock().pending.remove(&id) {
+                    Some(pending) => {
+                        let _ = pending.send(response.into_result());
+                    }
+                    None => {
+                        debug!(server = %self.server, ?id, "Ignoring response to unknown request")
+                    }
+                }
+            }
+            Message::Request(request) => {
+                let handler = self.handlers.read().get(&request.method).cloned();
+                let response = match handler.map(|handler| handler(request.params)) {
+                    Some(Ok(result)) => ResponseMessage::ok(request.id, result),
+                    Some(Err(error)) => ResponseMessage::err(request.id, error),
+                    None => ResponseMessage::err(
+                        request.id,
+                        JsonRpcError::new(
+                            METHOD_NOT_FOUND,
+                            format!("Unhandled method {}", request.method),
+                        ),
+                    ),
+                };
+                self.send(response.into());
+            }
+            Message::Notification(notification) => {
+                if let Some(subscribers) = self.subscribers.lock().get_mut(&notification.method) {
+                    subscribers.retain(|subscriber| subscriber(notification.params.as_ref()));
+                }
+            }
+        }
+    }
+
+    /// Marks the connection as closed and fails all pending requests.
+    fn close(&self, reason: String) {
+        let pending = {
+            let mut connection = self.connection.lock();
+            if connection.closed.is_none() {
+                warn!(server = %self.server, %reason, "Connection to language server closed");
+                connection.closed = Some(reason);
+            }
+            std::mem::take(&mut connection.pending)
+        };
+        // Dropping the senders wakes up the requests waiting for them
+        drop(pending);
+        self.subscribers.lock().clear();
+    }
+
+    fn close_reason(&self) -> String {
+        self.connection
+            .lock()
+            .closed
+            .clone()
+            .unwrap_or_else(|| "connection closed".to_string())
+    }
+
+    fn transport_error(&self, method: Option<&str>, message: String) -> ContextEngineError {
+        ContextEngineError::Transport {
+            server: self.server.clone(),
+            method: method.map(str::to_string),
+            message,
+        }
+    }
+
+    fn response_error(&self, method: &str, error: JsonRpcError) -> ContextEngineError {
+        if error.code == REQUEST_CANCELLED {
+            return ContextEngineError::Cancelled {
+                method: method.to_string(),
+            };
+        }
+
+        ContextEngineError::ServerError {
+            server: self.server.clone(),
+            method: method.to_string(),
+            code: error.code,
+            message: error.message,
+        }
+    }
+}
+
+/// A request that is waiting for its response.
+///
+/// Dropping the guard before the response arrived forgets the request and
+/// asks the server to cancel it.
+struct PendingRequest<'a> {
+    shared: &'a Shared,
+    id: RequestId,
+}
+
+impl Drop for PendingRequest<'_> {
+    fn drop(&mut self) {
+        let pending = self.shared.connection.lock().pending.remove(&self.id);
+        if pending.is_some() {
+            let params = to_params(&CancelParams {
+                id: self.id.clone(),
+            })
+            .ok()
+            .flatten();
+            self.shared
+                .send(NotificationMessage::new(Cancel::METHOD, params).into());
+        }
+    }
+}
+
+/// Serializes request or notification parameters, omitting `null`.
+fn to_params<P: Serialize>(params: &P) -> Result<Option<Value>, ContextEngineError> {
+    match serde_json::to_value(params)? {
+        Value::Null => Ok(None),
+        params => Ok(Some(params)),
+    }
+}
+
+async fn read_loop<R>(shared: Arc<Shared>, mut reader: R)
+where
+    R: AsyncBufRead + Unpin,
+{
+    let reason = loop {
+        match codec::read_frame(&mut reader).await {
+            Ok(Some(content)) => match serde_json::from_slice::<Message>(&content) {
+                Ok(message) => shared.dispatch(message),
+                Err(error) => {
+                    warn!(server = %shared.server, %error, "Ignoring malformed message");
+                }
+            },
+            Ok(None) => break "language server closed the connection".to_string(),
+            Err(error) => break format!("failed to read from language server: {error}"),
+        }
+    };
+    shared.close(reason);
+}
+
+async fn write_loop<W>(
+    shared: Arc<Shared>,
+    mut messages: mpsc::UnboundedReceiver<Message>,
+    mut writer: W,
+) where
+    W: AsyncWrite + Unpin,
+{
+    while let Some(message) = messages.recv().await {
+        if let Err(error) = codec::write_message(&mut writer, &message).await {
+            shared.close(format!("failed to write to language server: {error}"));
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/client.rs"]
+mod tests;
@@ -0,0 +1,142 @@
+//! `Content-Length` framing of LSP messages.
+//!
+//! Every LSP message is preceded by a header part of `Name: value` lines
+//! terminated by `\r\n`, and an empty line. The `Content-Length` header is
+//! mandatory and gives the length of the JSON content in bytes; the optional
+//! `Content-Type` header is ignored because the content is always UTF-8 JSON.
+
+use std::io;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::lsp::Message;
+
+/// Upper bound on the size of a single message, to reject corrupted headers
+/// before allocating.
+const MAX_CONTENT_LENGTH: usize = 256 * 1024 * 1024;
+
+/// Reads the content of the next frame.
+///
+/// # Returns
+///
+/// * `Ok(Some(Vec<u8>))` - The content of the frame
+/// * `Ok(None)` - If the stream ended before the next frame
+/// * `Err(io::Error)` - If the stream ended within a frame, or the header is
+///   malformed
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::lsp::codec::read_frame;
+///
+/// # tokio_test::block_on(async {
+/// let mut input = &b"Content-Length: 2\r\n\r\n{}"[..];
+/// assert_eq!(read_frame(&mut input).await.unwrap(), Some(b"{}".to_vec()));
+/// assert_eq!(read_frame(&mut input).await.unwrap(), None);
+/// # });
+/// ```
+pub async fn read_frame<R>(reader: &mut R) -> io::Result<Option<Vec<u8>>>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    let mut content_length = None;
+    let mut line = String::new();
+    let mut header_lines = 0;
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            if header_lines == 0 {
+                return Ok(None);
+            }
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "stream ended within a message header",
+            ));
+        }
+        header_lines += 1;
+
+        let header = line.trim_end_matches(['\r', '\n']);
+        if header.is_empty() {
+            break;
+        }
+
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(invalid_data(format!("malformed header line {header:?}")));
+        };
+        if name.trim().eq_ignore_ascii_case("Content-Length") {
+            let length = value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| invalid_data(format!("invalid Content-Length {value:?}")))?;
+            content_length = Some(length);
+        }
+    }
+
+    let length = content_length.ok_or_else(|| invalid_data("missing Content-Length header"))?;
+    if length > MAX_CONTENT_LENGTH {
+        return Err(invalid_data(format!(
+            "Content-Length {length} exceeds the limit of {MAX_CONTENT_LENGTH} bytes"
+        )));
+    }
+
+    let mut content = vec![0; length];
+    reader.read_exact(&mut content).await?;
+    Ok(Some(content))
+}
+
+/// Writes a frame with the given content and flushes the writer.
+///
+/// # Errors
+///
+/// Returns an error if writing to the stream fails.
+pub async fn write_frame<W>(writer: &mut W, content: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let header = format!("Content-Length: {}\r\n\r\n", content.len());
+    writer.write_all(header.as_bytes()).await?;
+    writer.write_all(content).await?;
+    writer.flush().await
+}
+
+/// Reads and deserializes the next message.
+///
+/// # Returns
+///
+/// * `Ok(Some(Message))` - The next message
+/// * `Ok(None)` - If the stream ended before the next message
+/// * `Err(io::Error)` - If the frame is malformed or its content is not a
+///   JSON-RPC message
+pub async fn read_message<R>(reader: &mut R) -> io::Result<Option<Message>>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    match read_frame(reader).await? {
+        Some(content) => serde_json::from_slice(&content)
+            .map(Some)
+            .map_err(|error| invalid_data(error.to_string())),
+        None => Ok(None),
+    }
+}
+
+/// Serializes and writes a message.
+///
+/// # Errors
+///
+/// Returns an error if writing to the stream fails.
+pub async fn write_message<W>(writer: &mut W, message: &Message) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let content = serde_json::to_vec(message).map_err(|error| invalid_data(error.to_string()))?;
+    write_frame(writer, &content).await
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(test)]
+#[path = "tests/codec.rs"]
+mod tests;
@@ -0,0 +1,148 @@
+//! Configuration of supervised language servers.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// How to run a language server.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::lsp::ServerConfig;
+///
+/// let config: ServerConfig = toml::from_str(r#"
+///     name = "rust-analyzer"
+///     command = "rust-analyzer"
+///     languages = ["rust"]
+///
+///     [initialization_options]
+///     cargo = { features = "all" }
+/// "#)
+/// .unwrap();
+///
+/// assert!(config.handles("rust"));
+/// assert!(!config.handles("python"));
+/// assert_eq!(config.request_timeout().as_secs(), 30);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerConfig {
+    /// Unique name of the server, used in errors and logs
+    pub name: String,
+    /// The executable to run
+    pub command: String,
+    /// Arguments passed to the executable
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Environment variables set for the process
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// The `initializationOptions` sent with the `initialize` request
+    #[serde(default)]
+    pub initialization_options: Option<Value>,
+    /// Language IDs of the documents the server handles; empty for all
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// Names of files or directories that mark the root folder of a project
+    /// of the server, e.g. `Cargo.toml`
+    #[serde(default)]
+    pub root_markers: Vec<String>,
+    /// Time after which requests to the server fail
+    #[serde(default = "default_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+}
+
+impl ServerConfig {
+    /// Creates a configuration that runs `command` without arguments.
+    pub fn new(name: impl Into<String>, command: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            command: command.into(),
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            initialization_options: None,
+            languages: Vec::new(),
+            root_markers: Vec::new(),
+            request_timeout_ms: default_request_timeout_ms(),
+        }
+    }
+
+    /// Returns true if the server handles documents of the language.
+    pub fn handles(&self, language_id: &str) -> bool {
+        self.languages.is_empty() || self.languages.iter().any(|id| id == language_id)
+    }
+
+    /// Returns the time after which requests to the server fail.
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_millis(self.request_timeout_ms)
+    }
+}
+
+fn default_request_timeout_ms() -> u64 {
+    30_000
+}
+
+/// When and how often a crashed language server is restarted.
+///
+/// Restarts are delayed by an exponential backoff: the first restart waits
+/// `initial_backoff`, every further consecutive restart twice as long, up to
+/// `max_backoff`. A server that ran for at least `reset_after` counts as
+/// healthy, so its next crash starts over with the initial backoff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// Delay before the first restart
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between restarts
+    pub max_backoff: Duration,
+    /// Number of consecutive restarts after which the server is given up
+    pub max_restarts: u32,
+    /// Uptime after which a server counts as healthy again
+    pub reset_after: Duration,
+    /// Number of consecutive request timeouts after which a server counts as
+    /// hung and is killed
+    pub max_timeouts: u32,
+    /// Time a server is given to exit after `shutdown` and `exit` before it
+    /// is killed
+    pub shutdown_timeout: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_restarts: 5,
+            reset_after: Duration::from_secs(60),
+            max_timeouts: 3,
+            shutdown_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Returns the delay before the given consecutive restart, starting at 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::lsp::RestartPolicy;
+    /// use std::time::Duration;
+    ///
+    /// let policy = RestartPolicy::default();
+    /// assert_eq!(policy.backoff(1), Duration::from_millis(500));
+    /// assert_eq!(policy.backoff(3), Duration::from_secs(2));
+    /// assert_eq!(policy.backoff(20), Duration::from_secs(30));
+    /// ```
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/config.rs"]
+mod tests;
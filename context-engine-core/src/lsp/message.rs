@@ -0,0 +1,241 @@
+//! JSON-RPC 2.0 messages exchanged with language servers.
+
+use std::fmt;
+
+use lsp_types::NumberOrString;
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::JsonRpcError;
+
+/// The id of a JSON-RPC request.
+pub type RequestId = NumberOrString;
+
+/// The `jsonrpc` member of every message, which is always `"2.0"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Version;
+
+impl Serialize for Version {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("2.0")
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = String::deserialize(deserializer)?;
+        if version == "2.0" {
+            Ok(Version)
+        } else {
+            Err(de::Error::custom(format!(
+                "unsupported JSON-RPC version {version:?}"
+            )))
+        }
+    }
+}
+
+/// A JSON-RPC 2.0 message.
+///
+/// Requests carry an `id` and a `method`, notifications only a `method`, and
+/// responses only an `id`; deserialization picks the variant by these
+/// members.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::lsp::{Message, RequestId};
+/// use serde_json::json;
+///
+/// let message: Message = serde_json::from_value(json!({
+///     "jsonrpc": "2.0",
+///     "id": 1,
+///     "result": { "capabilities": {} },
+/// }))
+/// .unwrap();
+///
+/// let Message::Response(response) = message else { unreachable!() };
+/// assert_eq!(response.id, Some(RequestId::Number(1)));
+/// assert!(response.into_result().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Message {
+    /// A request that expects a response
+    Request(RequestMessage),
+    /// A notification that expects no response
+    Notification(NotificationMessage),
+    /// A response to a request
+    Response(ResponseMessage),
+}
+
+impl Message {
+    /// Returns the method of a request or notification.
+    pub fn method(&self) -> Option<&str> {
+        match self {
+            Message::Request(request) => Some(&request.method),
+            Message::Notification(notification) => Some(&notification.method),
+            Message::Response(_) => None,
+        }
+    }
+}
+
+impl From<RequestMessage> for Message {
+    fn from(request: RequestMessage) -> Self {
+        Message::Request(request)
+    }
+}
+
+impl From<NotificationMessage> for Message {
+    fn from(notification: NotificationMessage) -> Self {
+        Message::Notification(notification)
+    }
+}
+
+impl From<ResponseMessage> for Message {
+    fn from(response: ResponseMessage) -> Self {
+        Message::Response(response)
+    }
+}
+
+/// A JSON-RPC request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestMessage {
+    /// The protocol version
+    pub jsonrpc: Version,
+    /// The id that correlates the response with the request
+    pub id: RequestId,
+    /// The method to invoke
+    pub method: String,
+    /// The parameters of the method
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+}
+
+impl RequestMessage {
+    /// Creates a new request.
+    pub fn new(id: RequestId, method: impl Into<String>, params: Option<Value>) -> Self {
+        Self {
+            jsonrpc: Version,
+            id,
+            method: method.into(),
+            params,
+        }
+    }
+}
+
+/// A JSON-RPC notification.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationMessage {
+    /// The protocol version
+    pub jsonrpc: Version,
+    /// The method to invoke
+    pub method: String,
+    /// The parameters of the method
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+}
+
+impl NotificationMessage {
+    /// Creates a new notification.
+    pub fn new(method: impl Into<String>, params: Option<Value>) -> Self {
+        Self {
+            jsonrpc: Version,
+            method: method.into(),
+            params,
+        }
+    }
+}
+
+/// A JSON-RPC response.
+///
+/// A successful response has a `result` (which may be `null`), a failed one
+/// an `error`. The `id` is only missing if the request could not be read at
+/// all.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseMessage {
+    /// The protocol version
+    pub jsonrpc: Version,
+    /// The id of the request this response answers
+    pub id: Option<RequestId>,
+    /// The result of a successful request
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_present"
+    )]
+    pub result: Option<Value>,
+    /// The error of a failed request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcError>,
+}
+
+impl ResponseMessage {
+    /// Creates a successful response.
+    pub fn ok(id: RequestId, result: Value) -> Self {
+        Self {
+            jsonrpc: Version,
+            id: Some(id),
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    /// Creates a failed response.
+    pub fn err(id: RequestId, error: JsonRpcError) -> Self {
+        Self {
+            jsonrpc: Version,
+            id: Some(id),
+            result: None,
+            error: Some(error),
+        }
+    }
+
+    /// Returns the outcome of the request.
+    ///
+    /// A response without `result` and `error` is a successful response
+    /// whose result is `null`.
+    pub fn into_result(self) -> Result<Value, JsonRpcError> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.result.unwrap_or(Value::Null)),
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Message::Request(request) => {
+                write!(f, "request {} ({})", request.method, id(&request.id))
+            }
+            Message::Notification(notification) => {
+                write!(f, "notification {}", notification.method)
+            }
+            Message::Response(response) => match &response.id {
+                Some(request_id) => write!(f, "response ({})", id(request_id)),
+                None => write!(f, "response (no id)"),
+            },
+        }
+    }
+}
+
+/// Deserializes a member that is present as `Some`, even if it is `null`.
+fn deserialize_present<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+/// Formats a request id for log messages.
+fn id(id: &RequestId) -> String {
+    match id {
+        NumberOrString::Number(number) => format!("#{number}"),
+        NumberOrString::String(string) => format!("#{string:?}"),
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/message.rs"]
+mod tests;
@@ -0,0 +1,61 @@
+//! Language Server Protocol client.
+//!
+//! This module implements the client side of the JSON-RPC 2.0 connection to
+//! a language server: the `Content-Length` framing of messages in [`codec`],
+//! the message types, and the [`LspClient`] that correlates requests with
+//! responses, answers requests of the server and dispatches its
+//! notifications. The client advertises the [`client_capabilities`] and
+//! keeps track of the [`NegotiatedCapabilities`] of the server. The
+//! [`LspSupervisor`] runs language server processes on top of it and keeps
+//! them alive, and the [`LanguageRegistry`] decides which servers handle a
+//! document.
+//!
+//! ## Structs
+//!
+//! * [`LspClient`] - Connection to a single language server.
+//! * [`LspSupervisor`] - Starts, restarts and stops language servers.
+//! * [`NegotiatedCapabilities`] - The static and dynamic capabilities of a
+//!   language server.
+//! * [`ServerConfig`] - How to run a language server.
+//! * [`RestartPolicy`] - When to restart a failed language server.
+//! * [`LanguageRegistry`] - Maps documents to languages and language servers.
+//! * [`LanguageConfig`] - How to recognize the documents of a language.
+//! * [`ServerRoute`] - A language server and project root for a document.
+//! * [`RequestMessage`], [`NotificationMessage`], [`ResponseMessage`] - The
+//!   JSON-RPC messages exchanged with the server.
+//!
+//! ## Enums
+//!
+//! * [`Message`] - Any JSON-RPC message.
+//! * [`ServerStatus`] - The state of a supervised language server.
+//!
+//! ## Traits
+//!
+//! * [`ServerLauncher`] - Starts language server processes.
+//!
+//! ## Modules
+//!
+//! * [`codec`] - The `Content-Length` framing of messages.
+//! * `testing` - In-process fake language servers for tests, enabled by the
+//!   `test-support` feature.
+
+mod capabilities;
+mod client;
+pub mod codec;
+mod config;
+mod message;
+mod process;
+mod registry;
+mod supervisor;
+#[cfg(any(test, feature = "test-support"))]
+pub mod testing;
+
+pub use capabilities::{NegotiatedCapabilities, client_capabilities};
+pub use client::{DEFAULT_REQUEST_TIMEOUT, LspClient};
+pub use config::{RestartPolicy, ServerConfig};
+pub use message::{
+    Message, NotificationMessage, RequestId, RequestMessage, ResponseMessage, Version,
+};
+pub use process::{CommandLauncher, ServerLauncher, ServerProcess};
+pub use registry::{LanguageConfig, LanguageRegistry, ServerRoute, find_root};
+pub use supervisor::{LspSupervisor, ServerStatus};
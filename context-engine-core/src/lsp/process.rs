@@ -0,0 +1,121 @@
+//! Launching of language server processes.
+
+use std::path::Path;
+use std::process::Stdio;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader};
+use tokio::process::Command;
+use tokio::sync::oneshot;
+use tracing::debug;
+
+use crate::error::ContextEngineError;
+use crate::lsp::ServerConfig;
+
+/// A running language server process.
+///
+/// The process is killed when `kill` is sent or dropped; `exited` resolves
+/// with the exit code once the process has exited (`None` if it was killed by
+/// a signal).
+pub struct ServerProcess {
+    /// The stdout of the server
+    pub reader: Box<dyn AsyncRead + Send + Unpin>,
+    /// The stdin of the server
+    pub writer: Box<dyn AsyncWrite + Send + Unpin>,
+    /// Resolves with the exit code of the process
+    pub exited: oneshot::Receiver<Option<i32>>,
+    /// Kills the process when sent or dropped
+    pub kill: oneshot::Sender<()>,
+}
+
+/// Starts language server processes.
+///
+/// The [`LspSupervisor`](crate::lsp::LspSupervisor) launches servers through
+/// this trait, so that tests can substitute in-process fake servers for child
+/// processes.
+pub trait ServerLauncher: Send + Sync {
+    /// Starts a server.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration of the server
+    /// * `root` - The workspace root, used as the working directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server cannot be started.
+    fn launch(
+        &self,
+        config: &ServerConfig,
+        root: &Path,
+    ) -> Result<ServerProcess, ContextEngineError>;
+}
+
+/// Launches language servers as child processes that communicate over stdio.
+///
+/// The stderr of the servers is forwarded to the `debug` log.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CommandLauncher;
+
+impl ServerLauncher for CommandLauncher {
+    fn launch(
+        &self,
+        config: &ServerConfig,
+        root: &Path,
+    ) -> Result<ServerProcess, ContextEngineError> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .envs(&config.env)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|error| ContextEngineError::Configuration {
+                key: Some(format!("servers.{}.command", config.name)),
+                message: format!("Failed to start '{}': {error}", config.command),
+            })?;
+
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(ContextEngineError::Transport {
+                server: config.name.clone(),
+                method: None,
+                message: "stdio of the language server is not available".to_string(),
+            });
+        };
+
+        if let Some(stderr) = child.stderr.take() {
+            let server = config.name.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    debug!(%server, "{line}");
+                }
+            });
+        }
+
+        let (kill, killed) = oneshot::channel();
+        let (exit, exited) = oneshot::channel();
+        tokio::spawn(async move {
+            let status = tokio::select! {
+                status = child.wait() => status,
+                _ = killed => {
+                    let _ = child.start_kill();
+                    child.wait().await
+                }
+            };
+            let _ = exit.send(status.ok().and_then(|status| status.code()));
+        });
+
+        Ok(ServerProcess {
+            reader: Box::new(stdout),
+            writer: Box::new(stdin),
+            exited,
+            kill,
+        })
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/process.rs"]
+mod tests;
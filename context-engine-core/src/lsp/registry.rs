@@ -0,0 +1,390 @@
+//! Routing of documents to languages and language servers.
+
+use std::path::{Component, Path, PathBuf};
+
+use lsp_types::Uri;
+use serde::{Deserialize, Serialize};
+
+use crate::lsp::ServerConfig;
+use crate::types::UriExt;
+use crate::workspace::Glob;
+
+/// How to recognize the documents of a language.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LanguageConfig {
+    /// The LSP language identifier, e.g. `rust` or `typescriptreact`
+    pub id: String,
+    /// File extensions without the leading dot, e.g. `rs` or `d.ts`
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Glob patterns of paths, e.g. `Dockerfile` or
+    /// `**/.github/workflows/*.yml`
+    #[serde(default)]
+    pub globs: Vec<Glob>,
+    /// Interpreters named in the shebang line of scripts, e.g. `python`
+    #[serde(default)]
+    pub shebangs: Vec<String>,
+}
+
+impl LanguageConfig {
+    /// Creates a language recognized by file extensions.
+    pub fn new(id: impl Into<String>, extensions: &[&str]) -> Self {
+        Self {
+            id: id.into(),
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+            globs: Vec::new(),
+            shebangs: Vec::new(),
+        }
+    }
+
+    /// Adds interpreters whose scripts belong to the language.
+    pub fn with_shebangs(mut self, interpreters: &[&str]) -> Self {
+        self.shebangs
+            .extend(interpreters.iter().map(|name| name.to_string()));
+        self
+    }
+}
+
+/// A language server that handles a document, together with the root folder
+/// of the document's project.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerRoute<'a> {
+    /// The language server
+    pub server: &'a ServerConfig,
+    /// The nearest ancestor folder of the document that contains one of the
+    /// server's root markers; `None` if there is none, in which case the
+    /// workspace root should be used
+    pub root: Option<PathBuf>,
+}
+
+/// Maps documents to language IDs and language IDs to language servers.
+///
+/// A document's language is recognized, in this order, by
+///
+/// 1. the [globs](LanguageConfig::globs) of the languages,
+/// 2. the longest matching [extension](LanguageConfig::extensions), e.g. `d.ts`
+///    before `ts`, and
+/// 3. the interpreter of a shebang line (`#!/usr/bin/env python3`).
+///
+/// Several servers may handle the same language, e.g. a main server and a
+/// linter; they are routed in the order they were added.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::lsp::LanguageRegistry;
+/// use context_engine_core::types::Uri;
+/// use std::str::FromStr;
+///
+/// let registry = LanguageRegistry::with_defaults();
+///
+/// let uri = Uri::from_str("file:///repo/web/src/App.tsx").unwrap();
+/// assert_eq!(registry.language_id(&uri, None), Some("typescriptreact"));
+///
+/// let script = Uri::from_str("file:///repo/tools/release").unwrap();
+/// let content = "#!/usr/bin/env python3\nprint('hi')\n";
+/// assert_eq!(registry.language_id(&script, Some(content)), Some("python"));
+///
+/// let servers = registry
+///     .route(&Uri::from_str("file:///repo/tools/gen.py").unwrap(), None)
+///     .into_iter()
+///     .map(|route| route.server.name.as_str())
+///     .collect::<Vec<_>>();
+/// assert_eq!(servers, ["pyright", "ruff"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LanguageRegistry {
+    #[serde(default)]
+    languages: Vec<LanguageConfig>,
+    #[serde(default)]
+    servers: Vec<ServerConfig>,
+}
+
+impl LanguageRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry for Rust, TypeScript, JavaScript and Python with
+    /// their common language servers.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+
+        registry.add_language(LanguageConfig::new("rust", &["rs"]));
+        registry.add_language(LanguageConfig::new("typescript", &["ts", "mts", "cts"]));
+        registry.add_language(LanguageConfig::new("typescriptreact", &["tsx"]));
+        registry.add_language(
+            LanguageConfig::new("javascript", &["js", "mjs", "cjs"]).with_shebangs(&["node"]),
+        );
+        registry.add_language(LanguageConfig::new("javascriptreact", &["jsx"]));
+        registry
+            .add_language(LanguageConfig::new("python", &["py", "pyi"]).with_shebangs(&["python"]));
+
+        registry.add_server(server(
+            "rust-analyzer",
+            "rust-analyzer",
+            &[],
+            &["rust"],
+            &["Cargo.toml"],
+        ));
+        registry.add_server(server(
+            "typescript-language-server",
+            "typescript-language-server",
+            &["--stdio"],
+            &[
+                "typescript",
+                "typescriptreact",
+                "javascript",
+                "javascriptreact",
+            ],
+            &["tsconfig.json", "jsconfig.json", "package.json"],
+        ));
+        let python_roots = [
+            "pyproject.toml",
+            "setup.py",
+            "setup.cfg",
+            "requirements.txt",
+        ];
+        registry.add_server(server(
+            "pyright",
+            "pyright-langserver",
+            &["--stdio"],
+            &["python"],
+            &python_roots,
+        ));
+        registry.add_server(server(
+            "ruff",
+            "ruff",
+            &["server"],
+            &["python"],
+            &python_roots,
+        ));
+
+        registry
+    }
+
+    /// Adds a language, replacing any language with the same ID.
+    pub fn add_language(&mut self, language: LanguageConfig) {
+        match self.languages.iter_mut().find(|l| l.id == language.id) {
+            Some(existing) => *existing = language,
+            None => self.languages.push(language),
+        }
+    }
+
+    /// Adds a language server, replacing any server with the same name.
+    pub fn add_server(&mut self, server: ServerConfig) {
+        match self.servers.iter_mut().find(|s| s.name == server.name) {
+            Some(existing) => *existing = server,
+            None => self.servers.push(server),
+        }
+    }
+
+    /// Returns the language with the given ID.
+    pub fn language(&self, id: &str) -> Option<&LanguageConfig> {
+        self.languages.iter().find(|language| language.id == id)
+    }
+
+    /// Returns all languages.
+    pub fn languages(&self) -> &[LanguageConfig] {
+        &self.languages
+    }
+
+    /// Returns the language server with the given name.
+    pub fn server(&self, name: &str) -> Option<&ServerConfig> {
+        self.servers.iter().find(|server| server.name == name)
+    }
+
+    /// Returns all language servers.
+    pub fn servers(&self) -> &[ServerConfig] {
+        &self.servers
+    }
+
+    /// Returns the language servers that handle a language, main server
+    /// first.
+    pub fn servers_for(&self, language_id: &str) -> Vec<&ServerConfig> {
+        self.servers
+            .iter()
+            .filter(|server| server.handles(language_id))
+            .collect()
+    }
+
+    /// Returns the ID of the language of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI of the document
+    /// * `content` - The content of the document, if known, to recognize
+    ///   scripts by their shebang line
+    ///
+    /// # Returns
+    ///
+    /// The language ID, or `None` if no language matches.
+    pub fn language_id(&self, uri: &Uri, content: Option<&str>) -> Option<&str> {
+        let path = match uri.to_file_path() {
+            Ok(path) => path
+                .components()
+                .filter_map(|component| match component {
+                    Component::Normal(name) => Some(name.to_string_lossy()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("/"),
+            Err(_) => uri.path().as_str().to_string(),
+        };
+
+        let by_glob = self.languages.iter().find(|language| {
+            language
+                .globs
+                .iter()
+                .any(|glob| glob.is_match(&path) || suffix_matches(glob, &path))
+        });
+        if let Some(language) = by_glob {
+            return Some(&language.id);
+        }
+
+        let filename = uri.filename()?;
+        let by_extension = self
+            .languages
+            .iter()
+            .flat_map(|language| language.extensions.iter().map(move |ext| (language, ext)))
+            .filter(|(_, ext)| has_extension(&filename, ext))
+            .max_by_key(|(_, ext)| ext.len());
+        if let Some((language, _)) = by_extension {
+            return Some(&language.id);
+        }
+
+        let interpreter = shebang_interpreter(content?)?;
+        self.languages
+            .iter()
+            .find(|language| {
+                language
+                    .shebangs
+                    .iter()
+                    .any(|name| interpreter_matches(&interpreter, name))
+            })
+            .map(|language| language.id.as_str())
+    }
+
+    /// Returns the language servers that handle a document, main server
+    /// first, with the root folder of the document's project for each.
+    ///
+    /// See [`LanguageRegistry::language_id`] for the arguments.
+    pub fn route(&self, uri: &Uri, content: Option<&str>) -> Vec<ServerRoute<'_>> {
+        let Some(language_id) = self.language_id(uri, content) else {
+            return Vec::new();
+        };
+        let path = uri.to_file_path().ok();
+
+        self.servers_for(language_id)
+            .into_iter()
+            .map(|server| ServerRoute {
+                server,
+                root: path
+                    .as_deref()
+                    .and_then(|path| find_root(path, &server.root_markers)),
+            })
+            .collect()
+    }
+}
+
+/// Returns the nearest ancestor folder of `path` that contains one of the
+/// markers.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::lsp::find_root;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let crate_dir = dir.path().join("crates/core");
+/// std::fs::create_dir_all(crate_dir.join("src")).unwrap();
+/// std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+/// std::fs::write(crate_dir.join("Cargo.toml"), "").unwrap();
+///
+/// let markers = ["Cargo.toml".to_string()];
+/// let file = crate_dir.join("src/lib.rs");
+/// assert_eq!(find_root(&file, &markers), Some(crate_dir));
+/// ```
+pub fn find_root(path: &Path, markers: &[String]) -> Option<PathBuf> {
+    if markers.is_empty() {
+        return None;
+    }
+
+    path.ancestors()
+        .skip(1)
+        .find(|dir| markers.iter().any(|marker| dir.join(marker).exists()))
+        .map(Path::to_path_buf)
+}
+
+fn server(
+    name: &str,
+    command: &str,
+    args: &[&str],
+    languages: &[&str],
+    root_markers: &[&str],
+) -> ServerConfig {
+    let mut config = ServerConfig::new(name, command);
+    config.args = args.iter().map(|arg| arg.to_string()).collect();
+    config.languages = languages.iter().map(|id| id.to_string()).collect();
+    config.root_markers = root_markers.iter().map(|m| m.to_string()).collect();
+    config
+}
+
+/// Returns true if the glob matches any trailing part of the path, so that
+/// patterns with a `/` do not depend on where the workspace is located.
+fn suffix_matches(glob: &Glob, path: &str) -> bool {
+    if glob.is_basename_pattern() {
+        return false;
+    }
+
+    let mut tail = path;
+    loop {
+        if glob.is_match(tail) {
+            return true;
+        }
+        match tail.split_once('/') {
+            Some((_, rest)) => tail = rest,
+            None => return false,
+        }
+    }
+}
+
+/// Returns true if the file name ends with `.{extension}`, ignoring case.
+fn has_extension(filename: &str, extension: &str) -> bool {
+    let Some(stem_len) = filename.len().checked_sub(extension.len() + 1) else {
+        return false;
+    };
+    filename
+        .get(stem_len..)
+        .and_then(|suffix| suffix.strip_prefix('.'))
+        .is_some_and(|suffix| suffix.eq_ignore_ascii_case(extension))
+        && stem_len > 0
+}
+
+/// Returns the name of the interpreter of a shebang line, e.g. `python3` for
+/// `#!/usr/bin/env -S python3 -u`.
+fn shebang_interpreter(content: &str) -> Option<String> {
+    let line = content.lines().next()?.strip_prefix("#!")?;
+    let mut words = line.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    if program != "env" {
+        return Some(program.to_string());
+    }
+
+    words
+        .find(|word| !word.starts_with('-') && !word.contains('='))
+        .map(|word| word.rsplit('/').next().unwrap_or(word).to_string())
+}
+
+/// Returns true if the interpreter is `name`, optionally followed by a
+/// version, e.g. `python3.12` for `python`.
+fn interpreter_matches(interpreter: &str, name: &str) -> bool {
+    interpreter
+        .strip_prefix(name)
+        .is_some_and(|version| version.chars().all(|c| c.is_ascii_digit() || c == '.'))
+}
+
+#[cfg(test)]
+#[path = "tests/registry.rs"]
+mod tests;
@@ -0,0 +1,701 @@
+//! Supervision of language server processes.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Instant;
+
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Exit, Notification,
+};
+use lsp_types::request::{Initialize, Request, Shutdown};
+use lsp_types::{
+    ClientInfo, DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    InitializeParams, InitializeResult, PositionEncodingKind, TextDocumentContentChangeEvent,
+    TextDocumentIdentifier, Uri, VersionedTextDocumentIdentifier, WorkspaceFolder,
+};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::document::{DocumentStore, TextDocument};
+use crate::error::ContextEngineError;
+use crate::lsp::{
+    CommandLauncher, LspClient, RestartPolicy, ServerConfig, ServerLauncher, client_capabilities,
+};
+use crate::types::UriExt;
+
+/// The state of a supervised language server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ServerStatus {
+    /// The server is being started for the first time
+    Starting,
+    /// The server is initialized and accepts requests
+    Running,
+    /// The server failed and is waiting to be restarted
+    Restarting {
+        /// The number of the consecutive restart
+        attempt: u32,
+        /// The error that made the server fail
+        error: ContextEngineError,
+    },
+    /// The server was stopped
+    Stopped,
+    /// The server failed too often and is not restarted anymore
+    Failed {
+        /// The error that made the server fail the last time
+        error: ContextEngineError,
+    },
+}
+
+impl ServerStatus {
+    /// Returns true if the server cannot currently serve requests because it
+    /// failed.
+    pub fn is_degraded(&self) -> bool {
+        matches!(
+            self,
+            ServerStatus::Restarting { .. } | ServerStatus::Failed { .. }
+        )
+    }
+
+    /// Returns true if the server is still being started or restarted.
+    fn is_pending(&self) -> bool {
+        matches!(
+            self,
+            ServerStatus::Starting | ServerStatus::Restarting { .. }
+        )
+    }
+}
+
+/// Runs language servers and keeps them alive.
+///
+/// For every started server the supervisor
+///
+/// 1. launches the process and performs the `initialize`/`initialized`
+///    handshake,
+/// 2. replays `textDocument/didOpen` for all open documents of the
+///    [`DocumentStore`] the server handles,
+/// 3. restarts the server with exponential backoff when it exits or hangs (see
+///    [`RestartPolicy`]), and
+/// 4. performs the `shutdown`/`exit` sequence when it is stopped.
+///
+/// Failures and restarts are logged and reflected in the [`ServerStatus`] of
+/// the server, so that callers can report a degraded server.
+///
+/// Whoever opens, changes or closes a document of the store tells the
+/// running servers with [`LspSupervisor::open_document`],
+/// [`LspSupervisor::change_document`] and [`LspSupervisor::close_document`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use context_engine_core::document::DocumentStore;
+/// use context_engine_core::lsp::{LspSupervisor, ServerConfig};
+/// use lsp_types::request::WorkspaceSymbolRequest;
+/// use lsp_types::WorkspaceSymbolParams;
+/// use std::sync::Arc;
+///
+/// # async fn example() -> Result<(), context_engine_core::ContextEngineError> {
+/// let documents = Arc::new(DocumentStore::default());
+/// let supervisor = LspSupervisor::new("/path/to/workspace", documents);
+///
+/// supervisor.start(ServerConfig::new("rust-analyzer", "rust-analyzer"))?;
+/// let symbols = supervisor
+///     .request::<WorkspaceSymbolRequest>("rust-analyzer", WorkspaceSymbolParams::default())
+///     .await?;
+///
+/// supervisor.stop_all().await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct LspSupervisor {
+    context: Arc<Context>,
+    servers: Mutex<HashMap<String, Arc<SupervisedServer>>>,
+}
+
+/// Everything the supervision tasks need besides their server.
+struct Context {
+    root: PathBuf,
+    documents: Arc<DocumentStore>,
+    launcher: Box<dyn ServerLauncher>,
+    policy: RestartPolicy,
+}
+
+struct SupervisedServer {
+    config: ServerConfig,
+    status: watch::Sender<ServerStatus>,
+    running: RwLock<Option<Arc<RunningServer>>>,
+    stopping: AtomicBool,
+    stop: watch::Sender<bool>,
+    monitor: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// An initialized instance of a server.
+struct RunningServer {
+    client: Arc<LspClient>,
+    initialize_result: InitializeResult,
+    kill: Mutex<Option<oneshot::Sender<()>>>,
+    kill_reason: Mutex<Option<String>>,
+    timeouts: AtomicU32,
+}
+
+impl LspSupervisor {
+    /// Creates a supervisor that runs servers as child processes in the
+    /// workspace `root`, with the default [`RestartPolicy`].
+    pub fn new(root: impl Into<PathBuf>, documents: Arc<DocumentStore>) -> Self {
+        Self::with_launcher(root, documents, CommandLauncher, RestartPolicy::default())
+    }
+
+    /// Creates a supervisor that starts servers with a custom launcher.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The workspace root
+    /// * `documents` - The open documents to replay to (re)started servers
+    /// * `launcher` - Starts the server processes
+    /// * `policy` - When to restart failed servers
+    pub fn with_launcher(
+        root: impl Into<PathBuf>,
+        documents: Arc<DocumentStore>,
+        launcher: impl ServerLauncher + 'static,
+        policy: RestartPolicy,
+    ) -> Self {
+        Self {
+            context: Arc::new(Context {
+                root: root.into(),
+                documents,
+                launcher: Box::new(launcher),
+                policy,
+            }),
+            servers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the restart policy of the supervisor.
+    pub fn policy(&self) -> &RestartPolicy {
+        &self.context.policy
+    }
+
+    /// Returns the workspace root the servers run in.
+    pub fn root(&self) -> &Path {
+        &self.context.root
+    }
+
+    /// Returns the open documents that are replayed to (re)started servers.
+    pub fn documents(&self) -> &Arc<DocumentStore> {
+        &self.context.documents
+    }
+
+    /// Sends `textDocument/didOpen` for a document just opened in the store
+    /// to the running servers that handle its language.
+    ///
+    /// Servers that are not running get the document when they are
+    /// (re)started. Failures to notify a server are logged.
+    pub fn open_document(&self, document: &TextDocument) {
+        self.notify_handling::<DidOpenTextDocument>(
+            document.language_id(),
+            DidOpenTextDocumentParams {
+                text_document: document.to_item(),
+            },
+        );
+    }
+
+    /// Sends the full text of a document just changed in the store as
+    /// `textDocument/didChange` to the running servers that handle its
+    /// language.
+    ///
+    /// See [`LspSupervisor::open_document`].
+    pub fn change_document(&self, document: &TextDocument) {
+        self.notify_handling::<DidChangeTextDocument>(
+            document.language_id(),
+            DidChangeTextDocumentParams {
+                text_document: VersionedTextDocumentIdentifier::new(
+                    document.uri().clone(),
+                    document.version(),
+                ),
+                content_changes: vec![TextDocumentContentChangeEvent {
+                    range: None,
+                    range_length: None,
+                    text: document.text().to_string(),
+                }],
+            },
+        );
+    }
+
+    /// Sends `textDocument/didClose` for a document just closed in the store
+    /// to the running servers that handle its language.
+    ///
+    /// See [`LspSupervisor::open_document`].
+    pub fn close_document(&self, document: &TextDocument) {
+        self.notify_handling::<DidCloseTextDocument>(
+            document.language_id(),
+            DidCloseTextDocumentParams {
+                text_document: TextDocumentIdentifier::new(document.uri().clone()),
+            },
+        );
+    }
+
+    /// Starts supervising a server.
+    ///
+    /// The server is started in the background; [`LspSupervisor::client`]
+    /// waits until it is initialized. A stopped or failed server with the
+    /// same name is replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::Configuration`] if a server with the same
+    /// name is already running.
+    pub fn start(&self, config: ServerConfig) -> Result<(), ContextEngineError> {
+        let mut servers = self.servers.lock();
+        if let Some(existing) = servers.get(&config.name) {
+            if !matches!(
+                *existing.status.borrow(),
+                ServerStatus::Stopped | ServerStatus::Failed { .. }
+            ) {
+                return Err(ContextEngineError::Configuration {
+                    key: Some(format!("servers.{}", config.name)),
+                    message: format!("Language server '{}' is already running", config.name),
+                });
+            }
+        }
+
+        let server = Arc::new(SupervisedServer {
+            config,
+            status: watch::Sender::new(ServerStatus::Starting),
+            running: RwLock::new(None),
+            stopping: AtomicBool::new(false),
+            stop: watch::Sender::new(false),
+            monitor: Mutex::new(None),
+        });
+        let monitor = tokio::spawn(supervise(Arc::clone(&server), Arc::clone(&self.context)));
+        *server.monitor.lock() = Some(monitor);
+
+        servers.insert(server.config.name.clone(), server);
+        Ok(())
+    }
+
+    /// Returns the status of a server, if it was started.
+    pub fn status(&self, name: &str) -> Option<ServerStatus> {
+        let server = self.servers.lock().get(name).cloned()?;
+        let status = server.status.borrow().clone();
+        Some(status)
+    }
+
+    /// Returns the status of all servers, ordered by name.
+    pub fn statuses(&self) -> Vec<(String, ServerStatus)> {
+        let mut statuses = self
+            .servers
+            .lock()
+            .iter()
+            .map(|(name, server)| (name.clone(), server.status.borrow().clone()))
+            .collect::<Vec<_>>();
+        statuses.sort_by(|(a, _), (b, _)| a.cmp(b));
+        statuses
+    }
+
+    /// Returns a receiver that observes the status changes of a server.
+    pub fn watch_status(&self, name: &str) -> Option<watch::Receiver<ServerStatus>> {
+        let server = self.servers.lock().get(name).cloned()?;
+        Some(server.status.subscribe())
+    }
+
+    /// Returns the client of a server, waiting until the server is
+    /// initialized.
+    ///
+    /// # Errors
+    ///
+    /// * [`ContextEngineError::Configuration`] - If the server is unknown
+    /// * [`ContextEngineError::Timeout`] - If the server is not initialized
+    ///   within its request timeout
+    /// * The error that made the server fail, if it failed
+    /// * [`ContextEngineError::Transport`] - If the server is stopped
+    pub async fn client(&self, name: &str) -> Result<Arc<LspClient>, ContextEngineError> {
+        let running = self.running(name).await?;
+        Ok(Arc::clone(&running.client))
+    }
+
+    /// Returns the result of the `initialize` request of a running server.
+    pub fn initialize_result(&self, name: &str) -> Option<InitializeResult> {
+        let server = self.servers.lock().get(name).cloned()?;
+        let running = server.running.read().clone()?;
+        Some(running.initialize_result.clone())
+    }
+
+    /// Returns the name and version a running server reported when it was
+    /// initialized, e.g. `rust-analyzer 1.87.0`; empty if it reported none.
+    pub fn server_version(&self, name: &str) -> Option<String> {
+        let result = self.initialize_result(name)?;
+        Some(
+            result
+                .server_info
+                .map(|info| match info.version {
+                    Some(version) => format!("{} {version}", info.name),
+                    None => info.name,
+                })
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Returns true if a running server supports a request or notification
+    /// method.
+    ///
+    /// See [`LspClient::supports`].
+    pub fn supports(&self, name: &str, method: &str) -> bool {
+        let Some(server) = self.servers.lock().get(name).cloned() else {
+            return false;
+        };
+        let running = server.running.read().clone();
+        running.is_some_and(|running| running.client.supports(method))
+    }
+
+    /// Sends a request to a server and waits for its response.
+    ///
+    /// Consecutive timeouts are counted; a server that times out
+    /// [`RestartPolicy::max_timeouts`] times in a row counts as hung and is
+    /// killed, which makes the supervisor restart it.
+    ///
+    /// # Errors
+    ///
+    /// See [`LspSupervisor::client`] and [`LspClient::request`].
+    pub async fn request<R>(
+        &self,
+        name: &str,
+        params: R::Params,
+    ) -> Result<R::Result, ContextEngineError>
+    where
+        R: Request,
+    {
+        let running = self.running(name).await?;
+        let result = running.client.request::<R>(params).await;
+
+        if let Err(ContextEngineError::Timeout { .. }) = &result {
+            let timeouts = running.timeouts.fetch_add(1, Ordering::Relaxed) + 1;
+            if timeouts >= self.context.policy.max_timeouts {
+                warn!(server = name, timeouts, "Language server is not responding");
+                running.kill(format!(
+                    "killed after {timeouts} consecutive request timeouts"
+                ));
+            }
+        } else {
+            running.timeouts.store(0, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Stops a server with the `shutdown`/`exit` sequence, killing it if it
+    /// does not exit within [`RestartPolicy::shutdown_timeout`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::Configuration`] if the server is unknown.
+    pub async fn stop(&self, name: &str) -> Result<(), ContextEngineError> {
+        let server = self.server(name)?;
+        server.stopping.store(true, Ordering::SeqCst);
+
+        let running = server.running.read().clone();
+        if let Some(running) = running {
+            let timeout = self.context.policy.shutdown_timeout;
+            if let Err(error) = running
+                .client
+                .request_with_timeout::<Shutdown>((), timeout)
+                .await
+            {
+                warn!(server = name, %error, "Language server failed to shut down");
+            }
+            if let Err(error) = running.client.notify::<Exit>(()) {
+                warn!(server = name, %error, "Failed to send exit notification");
+            }
+
+            let mut status = server.status.subscribe();
+            let exited = status.wait_for(|status| *status == ServerStatus::Stopped);
+            if tokio::time::timeout(timeout, exited).await.is_err() {
+                running.kill("did not exit after shutdown".to_string());
+            }
+        }
+
+        server.stop.send_replace(true);
+        let monitor = server.monitor.lock().take();
+        if let Some(monitor) = monitor {
+            if let Err(error) = monitor.await {
+                error!(server = name, %error, "Supervision task failed");
+            }
+        }
+        server.status.send_replace(ServerStatus::Stopped);
+        Ok(())
+    }
+
+    /// Stops all servers.
+    pub async fn stop_all(&self) {
+        let names = self.servers.lock().keys().cloned().collect::<Vec<_>>();
+        for name in names {
+            // Servers can only disappear from the map by being replaced, and
+            // a replaced server is stopped already
+            let _ = self.stop(&name).await;
+        }
+    }
+
+    /// Sends a notification to every running server that handles a
+    /// language.
+    fn notify_handling<N>(&self, language_id: &str, params: N::Params)
+    where
+        N: Notification,
+        N::Params: Clone,
+    {
+        let servers = self.servers.lock().values().cloned().collect::<Vec<_>>();
+        for server in servers {
+            if !server.config.handles(language_id) {
+                continue;
+            }
+            let running = server.running.read().clone();
+            let Some(running) = running else {
+                continue;
+            };
+            if let Err(error) = running.client.notify::<N>(params.clone()) {
+                warn!(server = %server.config.name, %error, method = N::METHOD, "Failed to sync document");
+            }
+        }
+    }
+
+    fn server(&self, name: &str) -> Result<Arc<SupervisedServer>, ContextEngineError> {
+        self.servers
+            .lock()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ContextEngineError::Configuration {
+                key: Some(format!("servers.{name}")),
+                message: format!("Unknown language server '{name}'"),
+            })
+    }
+
+    /// Waits until a server is running.
+    async fn running(&self, name: &str) -> Result<Arc<RunningServer>, ContextEngineError> {
+        let server = self.server(name)?;
+        let mut status = server.status.subscribe();
+        let timeout = server.config.request_timeout();
+
+        let settled = tokio::time::timeout(timeout, async {
+            let status = status.wait_for(|status| !status.is_pending()).await;
+            status.map(|status| status.clone())
+        })
+        .await;
+
+        let status = match settled {
+            Ok(Ok(status)) => status,
+            Ok(Err(_)) => ServerStatus::Stopped,
+            Err(_) => {
+                return Err(ContextEngineError::Timeout {
+                    server: name.to_string(),
+                    method: Initialize::METHOD.to_string(),
+                    timeout_ms: server.config.request_timeout_ms,
+                });
+            }
+        };
+
+        match status {
+            ServerStatus::Failed { error } => Err(error),
+            ServerStatus::Running => server
+                .running
+                .read()
+                .clone()
+                .ok_or_else(|| server.stopped_error()),
+            _ => Err(server.stopped_error()),
+        }
+    }
+}
+
+impl Drop for LspSupervisor {
+    fn drop(&mut self) {
+        // Ending the supervision tasks drops the running servers, which kills
+        // their processes
+        for server in self.servers.lock().values() {
+            server.stopping.store(true, Ordering::SeqCst);
+            server.stop.send_replace(true);
+        }
+    }
+}
+
+impl SupervisedServer {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn stopped_error(&self) -> ContextEngineError {
+        ContextEngineError::Transport {
+            server: self.config.name.clone(),
+            method: None,
+            message: "language server is stopped".to_string(),
+        }
+    }
+
+    /// Runs one instance of the server until it exits.
+    ///
+    /// # Returns
+    ///
+    /// The error that ended the instance.
+    async fn run(&self, context: &Context) -> ContextEngineError {
+        let process = match context.launcher.launch(&self.config, &context.root) {
+            Ok(process) => process,
+            Err(error) => return error,
+        };
+
+        let client = Arc::new(LspClient::with_request_timeout(
+            self.config.name.clone(),
+            process.reader,
+            process.writer,
+            self.config.request_timeout(),
+        ));
+        let initialize_result = match initialize(
+            &client,
+            &self.config,
+            &context.root,
+            context.documents.encoding(),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(error) => return error,
+        };
+        let running = Arc::new(RunningServer {
+            client,
+            initialize_result,
+            kill: Mutex::new(Some(process.kill)),
+            kill_reason: Mutex::new(None),
+            timeouts: AtomicU32::new(0),
+        });
+        {
+            // Document notifications wait for the slot, so a document opened
+            // or changed after the snapshot of the replay reaches the server
+            // once it is running
+            let mut slot = self.running.write();
+            if let Err(error) = self.replay_documents(&running.client, &context.documents) {
+                return error;
+            }
+            *slot = Some(Arc::clone(&running));
+        }
+        self.status.send_replace(ServerStatus::Running);
+        info!(server = self.name(), "Language server is running");
+
+        let exit_code = process.exited.await.ok().flatten();
+        self.running.write().take();
+
+        let message = running
+            .kill_reason
+            .lock()
+            .take()
+            .unwrap_or_else(|| "language server exited".to_string());
+        ContextEngineError::ServerCrashed {
+            server: self.config.name.clone(),
+            exit_code,
+            message,
+        }
+    }
+
+    /// Opens all open documents the server handles in the server.
+    fn replay_documents(
+        &self,
+        client: &LspClient,
+        documents: &DocumentStore,
+    ) -> Result<(), ContextEngineError> {
+        for document in documents.snapshots() {
+            if self.config.handles(document.language_id()) {
+                client.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+                    text_document: document.to_item(),
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RunningServer {
+    fn kill(&self, reason: String) {
+        self.kill_reason.lock().get_or_insert(reason);
+        if let Some(kill) = self.kill.lock().take() {
+            let _ = kill.send(());
+        }
+    }
+}
+
+/// Performs the `initialize`/`initialized` handshake.
+#[allow(deprecated)]
+async fn initialize(
+    client: &LspClient,
+    config: &ServerConfig,
+    root: &Path,
+    encoding: &PositionEncodingKind,
+) -> Result<InitializeResult, ContextEngineError> {
+    let root_uri = Uri::from_file_path(root)?;
+    let params = InitializeParams {
+        process_id: Some(std::process::id()),
+        root_uri: Some(root_uri.clone()),
+        initialization_options: config.initialization_options.clone(),
+        capabilities: client_capabilities(encoding),
+        workspace_folders: Some(vec![WorkspaceFolder {
+            name: root_uri.filename().unwrap_or_default(),
+            uri: root_uri,
+        }]),
+        client_info: Some(ClientInfo {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        }),
+        ..InitializeParams::default()
+    };
+
+    client.initialize(params).await
+}
+
+/// Keeps a server running until it is stopped or fails too often.
+async fn supervise(server: Arc<SupervisedServer>, context: Arc<Context>) {
+    let policy = &context.policy;
+    let mut stop = server.stop.subscribe();
+    let mut failures = 0;
+
+    loop {
+        let started = Instant::now();
+        let error = tokio::select! {
+            error = server.run(&context) => error,
+            _ = stop.wait_for(|stop| *stop) => break,
+        };
+        if server.stopping.load(Ordering::SeqCst) {
+            break;
+        }
+
+        if started.elapsed() >= policy.reset_after {
+            failures = 0;
+        }
+        failures += 1;
+        if failures > policy.max_restarts {
+            error!(server = server.name(), %error, "Language server failed too often, giving up");
+            server.status.send_replace(ServerStatus::Failed { error });
+            return;
+        }
+
+        let delay = policy.backoff(failures);
+        warn!(
+            server = server.name(),
+            %error,
+            attempt = failures,
+            delay_ms = u64::try_from(delay.as_millis()).unwrap_or(u64::MAX),
+            "Restarting language server"
+        );
+        server.status.send_replace(ServerStatus::Restarting {
+            attempt: failures,
+            error,
+        });
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = stop.wait_for(|stop| *stop) => break,
+        }
+    }
+
+    server.status.send_replace(ServerStatus::Stopped);
+    info!(server = server.name(), "Language server stopped");
+}
+
+#[cfg(test)]
+#[path = "tests/supervisor.rs"]
+mod tests;
@@ -0,0 +1,419 @@
+//! In-process fake language servers for tests.
+//!
+//! A [`FakeLauncher`] runs a [`FakeServer`] on an in-memory pipe instead of
+//! a child process. The harness answers the lifecycle messages, keeps track
+//! of the documents the client opened and records every message in a
+//! [`FakeLog`], so that a fake only implements the requests it is tested
+//! with.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+use tokio::io::{BufReader, DuplexStream};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::document::DocumentStore;
+use crate::error::{ContextEngineError, JsonRpcError};
+use crate::lsp::{
+    LspSupervisor, Message, NotificationMessage, RequestId, RequestMessage, ResponseMessage,
+    RestartPolicy, ServerConfig, ServerLauncher, ServerProcess, codec,
+};
+
+/// The capacity of the pipe between the client and a fake server.
+const PIPE_CAPACITY: usize = 256 * 1024;
+
+/// The behavior of a fake language server.
+///
+/// `initialize`, `shutdown` and `exit` are handled by the harness; the
+/// other messages are passed to the fake after they were recorded.
+pub trait FakeServer: Send + Sync + 'static {
+    /// Called before every launch, which fails with the returned error.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration of the launched server
+    /// * `launch` - The number of the launch, starting at 1
+    ///
+    /// # Errors
+    ///
+    /// Returns the error the launch fails with.
+    fn launch(&self, config: &ServerConfig, launch: u32) -> Result<(), ContextEngineError> {
+        let _ = (config, launch);
+        Ok(())
+    }
+
+    /// Returns the result of the `initialize` request.
+    fn initialize(&self) -> Value {
+        json!({ "capabilities": {} })
+    }
+
+    /// Answers a request of the client.
+    ///
+    /// # Returns
+    ///
+    /// The result or error of the request, or `None` to leave it
+    /// unanswered. The default answers every request with `null`.
+    fn request(
+        &self,
+        session: &FakeSession,
+        method: &str,
+        params: Value,
+    ) -> impl Future<Output = Option<Result<Value, JsonRpcError>>> + Send {
+        let _ = (session, method, params);
+        async { Some(Ok(Value::Null)) }
+    }
+
+    /// Handles a notification of the client.
+    fn notify(
+        &self,
+        session: &FakeSession,
+        method: &str,
+        params: Value,
+    ) -> impl Future<Output = ()> + Send {
+        let _ = (session, method, params);
+        async {}
+    }
+}
+
+/// A message received by a fake server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeMessage {
+    /// The launch of the server that received the message
+    pub launch: u32,
+    /// The method of the request or notification
+    pub method: String,
+    /// The parameters of the message
+    pub params: Value,
+}
+
+/// The launches of a fake server and the messages they received.
+#[derive(Debug, Default)]
+pub struct FakeLog {
+    launches: AtomicU32,
+    messages: Mutex<Vec<FakeMessage>>,
+}
+
+impl FakeLog {
+    /// Returns the number of launches, including failed ones.
+    pub fn launches(&self) -> u32 {
+        self.launches.load(Ordering::SeqCst)
+    }
+
+    /// Returns the messages received by all launches.
+    pub fn messages(&self) -> Vec<FakeMessage> {
+        self.messages.lock().clone()
+    }
+
+    /// Returns the methods received by a launch, in order.
+    pub fn methods(&self, launch: u32) -> Vec<String> {
+        self.messages
+            .lock()
+            .iter()
+            .filter(|message| message.launch == launch)
+            .map(|message| message.method.clone())
+            .collect()
+    }
+
+    /// Returns the parameters of the messages with a method, in order.
+    pub fn params(&self, method: &str) -> Vec<Value> {
+        self.messages
+            .lock()
+            .iter()
+            .filter(|message| message.method == method)
+            .map(|message| message.params.clone())
+            .collect()
+    }
+
+    fn record(&self, launch: u32, method: &str, params: &Value) {
+        self.messages.lock().push(FakeMessage {
+            launch,
+            method: method.to_string(),
+            params: params.clone(),
+        });
+    }
+}
+
+/// A document opened in a fake server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FakeDocument {
+    /// The version of the last open or change
+    pub version: i32,
+    /// The full text of the document
+    pub text: String,
+}
+
+/// One launch of a fake server.
+///
+/// The session follows the documents the client opens, changes with full
+/// texts and closes, and sends the messages of the fake to the client in
+/// order.
+pub struct FakeSession {
+    launch: u32,
+    documents: Mutex<HashMap<String, FakeDocument>>,
+    outgoing: mpsc::UnboundedSender<Message>,
+    next_id: AtomicI32,
+    exit_code: Mutex<Option<i32>>,
+}
+
+impl FakeSession {
+    /// Returns the number of the launch, starting at 1.
+    pub fn launch(&self) -> u32 {
+        self.launch
+    }
+
+    /// Returns an open document.
+    pub fn document(&self, uri: &str) -> Option<FakeDocument> {
+        self.documents.lock().get(uri).cloned()
+    }
+
+    /// Returns the text of an open document.
+    pub fn text(&self, uri: &str) -> Option<String> {
+        self.document(uri).map(|document| document.text)
+    }
+
+    /// Sends a notification to the client.
+    pub fn notify(&self, method: &str, params: Value) {
+        let notification = NotificationMessage::new(method, Some(params));
+        let _ = self.outgoing.send(notification.into());
+    }
+
+    /// Sends a request to the client, whose response is ignored.
+    pub fn request(&self, method: &str, params: Value) {
+        let id = RequestId::Number(self.next_id.fetch_add(1, Ordering::SeqCst));
+        let request = RequestMessage::new(id, method, Some(params));
+        let _ = self.outgoing.send(request.into());
+    }
+
+    /// Exits the server with a code after the current message.
+    pub fn exit(&self, code: i32) {
+        *self.exit_code.lock() = Some(code);
+    }
+
+    /// Updates the open documents from a notification of the client.
+    fn track(&self, method: &str, params: &Value) {
+        let uri = params
+            .pointer("/textDocument/uri")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let version = params
+            .pointer("/textDocument/version")
+            .and_then(Value::as_i64)
+            .and_then(|version| i32::try_from(version).ok())
+            .unwrap_or_default();
+        let mut documents = self.documents.lock();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params
+                    .pointer("/textDocument/text")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                documents.insert(uri, FakeDocument { version, text });
+            }
+            "textDocument/didChange" => {
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Value::as_array)
+                    .and_then(|changes| {
+                        changes
+                            .iter()
+                            .rev()
+                            .find(|change| change.get("range").is_none())
+                    })
+                    .and_then(|change| change.get("text"))
+                    .and_then(Value::as_str);
+                if let (Some(document), Some(text)) = (documents.get_mut(&uri), text) {
+                    document.version = version;
+                    document.text = text.to_string();
+                }
+            }
+            "textDocument/didClose" => {
+                documents.remove(&uri);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Launches a fake server in-process.
+pub struct FakeLauncher<S> {
+    server: Arc<S>,
+    log: Arc<FakeLog>,
+}
+
+impl<S> Clone for FakeLauncher<S> {
+    fn clone(&self) -> Self {
+        Self {
+            server: Arc::clone(&self.server),
+            log: Arc::clone(&self.log),
+        }
+    }
+}
+
+impl<S: FakeServer> FakeLauncher<S> {
+    /// Creates a launcher of a fake server.
+    pub fn new(server: S) -> Self {
+        Self {
+            server: Arc::new(server),
+            log: Arc::default(),
+        }
+    }
+
+    /// Returns the fake server.
+    pub fn server(&self) -> &Arc<S> {
+        &self.server
+    }
+
+    /// Returns the log of the launches.
+    pub fn log(&self) -> &Arc<FakeLog> {
+        &self.log
+    }
+}
+
+impl<S: FakeServer> ServerLauncher for FakeLauncher<S> {
+    fn launch(
+        &self,
+        config: &ServerConfig,
+        _root: &Path,
+    ) -> Result<ServerProcess, ContextEngineError> {
+        let launch = self.log.launches.fetch_add(1, Ordering::SeqCst) + 1;
+        self.server.launch(config, launch)?;
+
+        let (client_io, server_io) = tokio::io::duplex(PIPE_CAPACITY);
+        let (reader, writer) = tokio::io::split(client_io);
+        let (kill, killed) = oneshot::channel();
+        let (exit, exited) = oneshot::channel();
+        let server = Arc::clone(&self.server);
+        let log = Arc::clone(&self.log);
+        tokio::spawn(async move {
+            let exit_code = tokio::select! {
+                exit_code = serve(&*server, &log, launch, server_io) => exit_code,
+                _ = killed => None,
+            };
+            let _ = exit.send(exit_code);
+        });
+
+        Ok(ServerProcess {
+            reader: Box::new(reader),
+            writer: Box::new(writer),
+            exited,
+            kill,
+        })
+    }
+}
+
+/// Serves one launch of a fake server and returns its exit code.
+async fn serve(
+    server: &impl FakeServer,
+    log: &FakeLog,
+    launch: u32,
+    io: DuplexStream,
+) -> Option<i32> {
+    let (reader, mut writer) = tokio::io::split(io);
+    let (outgoing, mut queue) = mpsc::unbounded_channel::<Message>();
+    tokio::spawn(async move {
+        while let Some(message) = queue.recv().await {
+            if codec::write_message(&mut writer, &message).await.is_err() {
+                break;
+            }
+        }
+    });
+    let session = FakeSession {
+        launch,
+        documents: Mutex::default(),
+        outgoing,
+        next_id: AtomicI32::new(1),
+        exit_code: Mutex::default(),
+    };
+
+    let mut reader = BufReader::new(reader);
+    loop {
+        let Ok(Some(message)) = codec::read_message(&mut reader).await else {
+            return Some(1);
+        };
+        match message {
+            Message::Request(request) => {
+                let params = request.params.unwrap_or(Value::Null);
+                log.record(launch, &request.method, &params);
+                let result = match request.method.as_str() {
+                    "initialize" => Some(Ok(server.initialize())),
+                    "shutdown" => Some(Ok(Value::Null)),
+                    method => server.request(&session, method, params).await,
+                };
+                let response = match result {
+                    Some(Ok(result)) => Some(ResponseMessage::ok(request.id, result)),
+                    Some(Err(error)) => Some(ResponseMessage::err(request.id, error)),
+                    None => None,
+                };
+                if let Some(response) = response {
+                    let _ = session.outgoing.send(response.into());
+                }
+            }
+            Message::Notification(notification) => {
+                let params = notification.params.unwrap_or(Value::Null);
+                log.record(launch, &notification.method, &params);
+                if notification.method == "exit" {
+                    return Some(0);
+                }
+                session.track(&notification.method, &params);
+                server.notify(&session, &notification.method, params).await;
+            }
+            Message::Response(_) => {}
+        }
+        if let Some(code) = *session.exit_code.lock() {
+            return Some(code);
+        }
+    }
+}
+
+/// Starts a language server on a fake server with the default restart
+/// policy and waits until it is initialized.
+///
+/// # Arguments
+///
+/// * `root` - The workspace root
+/// * `documents` - The open documents, replayed to the server
+/// * `launcher` - The launcher of the fake server
+/// * `config` - The configuration of the server
+///
+/// # Errors
+///
+/// Returns an error if the server cannot be started.
+pub async fn start<S: FakeServer>(
+    root: &Path,
+    documents: Arc<DocumentStore>,
+    launcher: FakeLauncher<S>,
+    config: ServerConfig,
+) -> Result<Arc<LspSupervisor>, ContextEngineError> {
+    let name = config.name.clone();
+    let supervisor = Arc::new(LspSupervisor::with_launcher(
+        root,
+        documents,
+        launcher,
+        RestartPolicy::default(),
+    ));
+    supervisor.start(config)?;
+    supervisor.client(&name).await?;
+    Ok(supervisor)
+}
+
+/// Waits up to two seconds for a condition to hold.
+///
+/// # Returns
+///
+/// Whether the condition holds.
+pub async fn wait_until(condition: impl Fn() -> bool) -> bool {
+    for _ in 0..400 {
+        if condition() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    condition()
+}
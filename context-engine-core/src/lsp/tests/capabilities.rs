@@ -0,0 +1,163 @@
+#![allow(clippy::unwrap_used)]
+
+use lsp_types::request::{
+    CallHierarchyIncomingCalls, CallHierarchyPrepare, CodeActionResolveRequest, GotoDefinition,
+    HoverRequest, InlayHintRequest, References, Request, SemanticTokensFullDeltaRequest,
+    SemanticTokensFullRequest, SemanticTokensRangeRequest, TypeHierarchySubtypes,
+};
+use proptest::prelude::*;
+use serde_json::json;
+
+use super::*;
+
+fn capabilities(json: serde_json::Value) -> NegotiatedCapabilities {
+    let negotiated = NegotiatedCapabilities::default();
+    negotiated.set_server_capabilities(serde_json::from_value(json).unwrap());
+    negotiated
+}
+
+fn registration(id: &str, method: &str) -> Registration {
+    Registration {
+        id: id.to_string(),
+        method: method.to_string(),
+        register_options: None,
+    }
+}
+
+#[test]
+fn test_client_capabilities_prefer_given_encoding() {
+    let capabilities = client_capabilities(&PositionEncodingKind::UTF32);
+
+    assert_eq!(
+        capabilities.general.unwrap().position_encodings.unwrap(),
+        [
+            PositionEncodingKind::UTF32,
+            PositionEncodingKind::UTF16,
+            PositionEncodingKind::UTF8,
+        ]
+    );
+    let watched_files = capabilities
+        .workspace
+        .unwrap()
+        .did_change_watched_files
+        .unwrap();
+    assert_eq!(watched_files.dynamic_registration, Some(true));
+    let text_document = capabilities.text_document.unwrap();
+    assert!(text_document.call_hierarchy.is_some());
+    assert!(text_document.inlay_hint.is_some());
+    assert_eq!(
+        text_document
+            .document_symbol
+            .unwrap()
+            .hierarchical_document_symbol_support,
+        Some(true)
+    );
+    assert!(
+        !text_document
+            .semantic_tokens
+            .unwrap()
+            .token_types
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_static_capabilities() {
+    let negotiated = capabilities(json!({
+        "textDocumentSync": 2,
+        "hoverProvider": true,
+        "definitionProvider": { "workDoneProgress": true },
+        "referencesProvider": false,
+        "callHierarchyProvider": true,
+        "codeActionProvider": { "resolveProvider": false },
+        "semanticTokensProvider": {
+            "legend": { "tokenTypes": [], "tokenModifiers": [] },
+            "full": { "delta": true }
+        }
+    }));
+
+    for (method, expected) in [
+        ("initialize", true),
+        ("textDocument/didOpen", true),
+        ("textDocument/didChange", true),
+        ("textDocument/didSave", false),
+        (HoverRequest::METHOD, true),
+        (GotoDefinition::METHOD, true),
+        (References::METHOD, false),
+        (CallHierarchyPrepare::METHOD, true),
+        (CallHierarchyIncomingCalls::METHOD, true),
+        ("textDocument/codeAction", true),
+        (CodeActionResolveRequest::METHOD, false),
+        (SemanticTokensFullRequest::METHOD, true),
+        (SemanticTokensFullDeltaRequest::METHOD, true),
+        (SemanticTokensRangeRequest::METHOD, false),
+        (InlayHintRequest::METHOD, false),
+        ("rust-analyzer/expandMacro", false),
+    ] {
+        assert_eq!(negotiated.supports(method), expected, "{method}");
+    }
+    assert_eq!(negotiated.position_encoding(), PositionEncodingKind::UTF16);
+}
+
+#[test]
+fn test_text_document_sync_options() {
+    let negotiated = capabilities(json!({
+        "textDocumentSync": { "openClose": true, "change": 0, "save": { "includeText": false } }
+    }));
+
+    assert!(negotiated.supports("textDocument/didOpen"));
+    assert!(negotiated.supports("textDocument/didClose"));
+    assert!(!negotiated.supports("textDocument/didChange"));
+    assert!(negotiated.supports("textDocument/didSave"));
+    assert!(!negotiated.supports("textDocument/willSave"));
+}
+
+#[test]
+fn test_dynamic_registrations() {
+    let negotiated = NegotiatedCapabilities::default();
+    negotiated.register([
+        registration("1", "textDocument/semanticTokens"),
+        registration("2", "textDocument/prepareTypeHierarchy"),
+        registration("3", "rust-analyzer/expandMacro"),
+    ]);
+
+    assert!(negotiated.supports(SemanticTokensRangeRequest::METHOD));
+    assert!(negotiated.supports(TypeHierarchySubtypes::METHOD));
+    assert!(negotiated.supports("rust-analyzer/expandMacro"));
+    assert!(!negotiated.supports(HoverRequest::METHOD));
+
+    // Registering the same ID again replaces the registration
+    negotiated.register([registration("3", "textDocument/hover")]);
+    assert!(negotiated.supports(HoverRequest::METHOD));
+    assert!(!negotiated.supports("rust-analyzer/expandMacro"));
+
+    negotiated.unregister(&[Unregistration {
+        id: "1".to_string(),
+        method: "textDocument/semanticTokens".to_string(),
+    }]);
+    assert!(!negotiated.supports(SemanticTokensRangeRequest::METHOD));
+    assert_eq!(
+        negotiated
+            .registrations()
+            .into_iter()
+            .map(|registration| registration.id)
+            .collect::<Vec<_>>(),
+        ["2", "3"]
+    );
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_registered_methods_are_supported(method in "[a-zA-Z]{1,10}/[a-zA-Z]{1,10}") {
+        let negotiated = NegotiatedCapabilities::default();
+        negotiated.register([registration("id", &method)]);
+        prop_assert!(negotiated.supports(&method));
+
+        negotiated.unregister(&[Unregistration { id: "id".to_string(), method: method.clone() }]);
+        prop_assert_eq!(
+            negotiated.supports(&method),
+            LIFECYCLE_METHODS.contains(&method.as_str())
+        );
+    }
+}
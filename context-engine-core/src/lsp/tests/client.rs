@@ -0,0 +1,421 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use lsp_types::notification::{LogMessage, PublishDiagnostics};
+use lsp_types::request::Shutdown;
+use lsp_types::{ConfigurationItem, Diagnostic, PublishDiagnosticsParams, Range, Uri};
+use serde_json::json;
+use tokio::io::{DuplexStream, ReadHalf, WriteHalf};
+
+use super::*;
+
+/// The server side of an in-process connection.
+struct FakeServer {
+    reader: BufReader<ReadHalf<DuplexStream>>,
+    writer: WriteHalf<DuplexStream>,
+}
+
+impl FakeServer {
+    async fn recv(&mut self) -> Message {
+        codec::read_message(&mut self.reader)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    async fn recv_request(&mut self) -> RequestMessage {
+        match self.recv().await {
+            Message::Request(request) => request,
+            other => unreachable!("expected a request, got {other}"),
+        }
+    }
+
+    async fn recv_response(&mut self) -> ResponseMessage {
+        match self.recv().await {
+            Message::Response(response) => response,
+            other => unreachable!("expected a response, got {other}"),
+        }
+    }
+
+    async fn send(&mut self, message: impl Into<Message>) {
+        codec::write_message(&mut self.writer, &message.into())
+            .await
+            .unwrap();
+    }
+}
+
+fn connect(request_timeout: Duration) -> (LspClient, FakeServer) {
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+    let (client_reader, client_writer) = tokio::io::split(client_io);
+    let (server_reader, server_writer) = tokio::io::split(server_io);
+
+    let client =
+        LspClient::with_request_timeout("fake-ls", client_reader, client_writer, request_timeout);
+    let server = FakeServer {
+        reader: BufReader::new(server_reader),
+        writer: server_writer,
+    };
+    (client, server)
+}
+
+#[tokio::test]
+async fn test_responses_are_correlated_with_requests() {
+    let (client, mut server) = connect(DEFAULT_REQUEST_TIMEOUT);
+
+    let requests = async {
+        tokio::join!(
+            client.send_request("first", Some(json!([1])), DEFAULT_REQUEST_TIMEOUT),
+            client.send_request("second", None, DEFAULT_REQUEST_TIMEOUT),
+        )
+    };
+    let server = async {
+        let first = server.recv_request().await;
+        let second = server.recv_request().await;
+        assert_eq!(first.method, "first");
+        assert_eq!(first.params, Some(json!([1])));
+        assert_eq!(second.params, None);
+
+        // Answer out of order
+        server
+            .send(ResponseMessage::ok(second.id, json!("two")))
+            .await;
+        server
+            .send(ResponseMessage::ok(first.id, json!("one")))
+            .await;
+    };
+
+    let ((first, second), ()) = tokio::join!(requests, server);
+    assert_eq!(first.unwrap(), json!("one"));
+    assert_eq!(second.unwrap(), json!("two"));
+}
+
+#[tokio::test]
+async fn test_typed_request_without_params() {
+    let (client, mut server) = connect(DEFAULT_REQUEST_TIMEOUT);
+
+    let server = async {
+        let request = server.recv_request().await;
+        assert_eq!(request.method, "shutdown");
+        assert_eq!(request.params, None);
+        server
+            .send(ResponseMessage::ok(request.id, Value::Null))
+            .await;
+    };
+
+    let (result, ()) = tokio::join!(client.request::<Shutdown>(()), server);
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn test_initialize_stores_capabilities_and_registrations() {
+    let (client, mut server) = connect(DEFAULT_REQUEST_TIMEOUT);
+    assert!(!client.supports("textDocument/hover"));
+
+    let server = async {
+        let request = server.recv_request().await;
+        assert_eq!(request.method, "initialize");
+        let params: InitializeParams = serde_json::from_value(request.params.unwrap()).unwrap();
+        assert_eq!(
+            params.capabilities.general.unwrap().position_encodings,
+            Some(vec![
+                lsp_types::PositionEncodingKind::UTF16,
+                lsp_types::PositionEncodingKind::UTF8,
+                lsp_types::PositionEncodingKind::UTF32,
+            ])
+        );
+        server
+            .send(ResponseMessage::ok(
+                request.id,
+                json!({
+                    "capabilities": { "hoverProvider": true, "positionEncoding": "utf-8" }
+                }),
+            ))
+            .await;
+        let Message::Notification(initialized) = server.recv().await else {
+            unreachable!("expected the initialized notification");
+        };
+        assert_eq!(initialized.method, "initialized");
+        server
+    };
+
+    let params = InitializeParams {
+        capabilities: crate::lsp::client_capabilities(&lsp_types::PositionEncodingKind::UTF16),
+        ..InitializeParams::default()
+    };
+    let (result, mut server) = tokio::join!(client.initialize(params), server);
+    result.unwrap();
+    assert!(client.supports("textDocument/hover"));
+    assert!(!client.supports("textDocument/inlayHint"));
+    assert_eq!(
+        client.capabilities().position_encoding(),
+        lsp_types::PositionEncodingKind::UTF8
+    );
+
+    // Dynamic registrations are recorded by the default handlers
+    server
+        .send(RequestMessage::new(
+            RequestId::Number(1),
+            "client/registerCapability",
+            Some(json!({
+                "registrations": [{ "id": "hints", "method": "textDocument/inlayHint" }]
+            })),
+        ))
+        .await;
+    assert_eq!(
+        server.recv_response().await,
+        ResponseMessage::ok(RequestId::Number(1), Value::Null)
+    );
+    assert!(client.supports("textDocument/inlayHint"));
+
+    server
+        .send(RequestMessage::new(
+            RequestId::Number(2),
+            "client/unregisterCapability",
+            Some(json!({
+                "unregisterations": [{ "id": "hints", "method": "textDocument/inlayHint" }]
+            })),
+        ))
+        .await;
+    server.recv_response().await;
+    assert!(!client.supports("textDocument/inlayHint"));
+    assert!(client.capabilities().registrations().is_empty());
+}
+
+#[tokio::test]
+async fn test_error_responses() {
+    let (client, mut server) = connect(DEFAULT_REQUEST_TIMEOUT);
+
+    let server = async {
+        let request = server.recv_request().await;
+        server
+            .send(ResponseMessage::err(
+                request.id,
+                JsonRpcError::new(-32801, "content modified"),
+            ))
+            .await;
+        let request = server.recv_request().await;
+        server
+            .send(ResponseMessage::err(
+                request.id,
+                JsonRpcError::new(REQUEST_CANCELLED, "cancelled"),
+            ))
+            .await;
+    };
+    let requests = async {
+        let modified = client
+            .send_request("textDocument/hover", None, DEFAULT_REQUEST_TIMEOUT)
+            .await;
+        let cancelled = client
+            .send_request("workspace/symbol", None, DEFAULT_REQUEST_TIMEOUT)
+            .await;
+        (modified, cancelled)
+    };
+
+    let ((modified, cancelled), ()) = tokio::join!(requests, server);
+    let modified = modified.unwrap_err();
+    assert_eq!(
+        modified,
+        ContextEngineError::ServerError {
+            server: "fake-ls".to_string(),
+            method: "textDocument/hover".to_string(),
+            code: -32801,
+            message: "content modified".to_string(),
+        }
+    );
+    assert!(modified.is_transient());
+    assert_eq!(
+        cancelled.unwrap_err(),
+        ContextEngineError::Cancelled {
+            method: "workspace/symbol".to_string()
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_timed_out_request_is_cancelled() {
+    let (client, mut server) = connect(Duration::from_millis(20));
+
+    let error = client.request::<Shutdown>(()).await.unwrap_err();
+    assert_eq!(
+        error,
+        ContextEngineError::Timeout {
+            server: "fake-ls".to_string(),
+            method: "shutdown".to_string(),
+            timeout_ms: 20,
+        }
+    );
+
+    let request = server.recv_request().await;
+    let Message::Notification(cancel) = server.recv().await else {
+        unreachable!()
+    };
+    assert_eq!(cancel.method, "$/cancelRequest");
+    assert_eq!(cancel.params, Some(json!({ "id": request.id })));
+
+    // A late response to the cancelled request is ignored
+    server
+        .send(ResponseMessage::ok(request.id, Value::Null))
+        .await;
+    assert!(!client.is_closed());
+}
+
+#[tokio::test]
+async fn test_server_requests_are_answered_by_handlers() {
+    let (client, mut server) = connect(DEFAULT_REQUEST_TIMEOUT);
+    let configuration = |id: i32| {
+        RequestMessage::new(
+            RequestId::Number(id),
+            "workspace/configuration",
+            Some(json!({ "items": [{ "section": "rust-analyzer" }, {}] })),
+        )
+    };
+
+    // Default handlers
+    server.send(configuration(1)).await;
+    assert_eq!(
+        server.recv_response().await,
+        ResponseMessage::ok(RequestId::Number(1), json!([null, null]))
+    );
+    server
+        .send(RequestMessage::new(
+            RequestId::Number(2),
+            "window/workDoneProgress/create",
+            Some(json!({ "token": "indexing" })),
+        ))
+        .await;
+    assert_eq!(
+        server.recv_response().await,
+        ResponseMessage::ok(RequestId::Number(2), Value::Null)
+    );
+
+    // Custom handlers replace the defaults
+    client.on_request::<WorkspaceConfiguration, _>(|params| {
+        Ok(params
+            .items
+            .iter()
+            .map(|item: &ConfigurationItem| json!({ "section": item.section }))
+            .collect())
+    });
+    server.send(configuration(3)).await;
+    assert_eq!(
+        server.recv_response().await.into_result().unwrap(),
+        json!([{ "section": "rust-analyzer" }, { "section": null }])
+    );
+
+    // Invalid parameters and unknown methods are answered with errors
+    server
+        .send(RequestMessage::new(
+            RequestId::Number(4),
+            "workspace/configuration",
+            Some(json!({ "items": 1 })),
+        ))
+        .await;
+    assert_eq!(
+        server.recv_response().await.into_result().unwrap_err().code,
+        INVALID_PARAMS
+    );
+    server
+        .send(RequestMessage::new(
+            RequestId::String("x".into()),
+            "workspace/applyEdit",
+            None,
+        ))
+        .await;
+    let response = server.recv_response().await;
+    assert_eq!(response.id, Some(RequestId::String("x".into())));
+    assert_eq!(response.into_result().unwrap_err().code, METHOD_NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_notifications_are_dispatched_to_subscribers() {
+    let (client, mut server) = connect(DEFAULT_REQUEST_TIMEOUT);
+    let mut first = client.subscribe::<PublishDiagnostics>();
+    let mut second = client.subscribe::<PublishDiagnostics>();
+    let dropped = client.subscribe::<PublishDiagnostics>();
+    drop(dropped);
+    let mut logs = client.subscribe::<LogMessage>();
+
+    let params = PublishDiagnosticsParams::new(
+        Uri::from_str("file:///src/lib.rs").unwrap(),
+        vec![Diagnostic::new_simple(
+            Range::default(),
+            "unused".to_string(),
+        )],
+        Some(4),
+    );
+    // Notifications with malformed parameters are dropped
+    server
+        .send(NotificationMessage::new(
+            "textDocument/publishDiagnostics",
+            Some(json!({ "uri": 1 })),
+        ))
+        .await;
+    server
+        .send(NotificationMessage::new(
+            "textDocument/publishDiagnostics",
+            Some(serde_json::to_value(&params).unwrap()),
+        ))
+        .await;
+
+    assert_eq!(first.recv().await.unwrap(), params);
+    assert_eq!(second.recv().await.unwrap(), params);
+    assert!(logs.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_malformed_messages_are_skipped() {
+    let (client, mut server) = connect(DEFAULT_REQUEST_TIMEOUT);
+
+    codec::write_frame(&mut server.writer, b"{\"not\": \"json-rpc\"}")
+        .await
+        .unwrap();
+    let server = async {
+        let request = server.recv_request().await;
+        server
+            .send(ResponseMessage::ok(request.id, json!(42)))
+            .await;
+    };
+
+    let (result, ()) = tokio::join!(
+        client.send_request("ping", None, DEFAULT_REQUEST_TIMEOUT),
+        server
+    );
+    assert_eq!(result.unwrap(), json!(42));
+}
+
+#[tokio::test]
+async fn test_closed_connection_fails_requests() {
+    let (client, mut server) = connect(DEFAULT_REQUEST_TIMEOUT);
+    let mut diagnostics = client.subscribe::<PublishDiagnostics>();
+
+    let server = async move {
+        let request = server.recv_request().await;
+        assert_eq!(request.method, "textDocument/definition");
+        // The server exits without answering
+        drop(server);
+    };
+    let (result, ()) = tokio::join!(
+        client.send_request("textDocument/definition", None, DEFAULT_REQUEST_TIMEOUT),
+        server
+    );
+
+    let error = result.unwrap_err();
+    assert!(matches!(
+        &error,
+        ContextEngineError::Transport { server, method: Some(method), .. }
+            if server == "fake-ls" && method == "textDocument/definition"
+    ));
+    assert!(error.is_transient());
+    assert!(client.is_closed());
+    assert_eq!(diagnostics.recv().await, None);
+
+    assert!(matches!(
+        client.request::<Shutdown>(()).await,
+        Err(ContextEngineError::Transport { .. })
+    ));
+    assert!(matches!(
+        client.send_notification("exit", None),
+        Err(ContextEngineError::Transport { .. })
+    ));
+}
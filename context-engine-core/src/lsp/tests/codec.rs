@@ -0,0 +1,98 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+use serde_json::json;
+use tokio::io::BufReader;
+
+use super::*;
+use crate::lsp::NotificationMessage;
+
+#[tokio::test]
+async fn test_read_frames_with_headers() {
+    let input =
+        b"Content-Length: 2\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{}\
+content-length:  4 \r\n\r\nnull";
+    let mut reader = &input[..];
+
+    assert_eq!(read_frame(&mut reader).await.unwrap(), Some(b"{}".to_vec()));
+    assert_eq!(
+        read_frame(&mut reader).await.unwrap(),
+        Some(b"null".to_vec())
+    );
+    assert_eq!(read_frame(&mut reader).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_read_frame_errors() {
+    let cases: [(&[u8], io::ErrorKind); 5] = [
+        (b"Content-Type: json\r\n\r\n{}", io::ErrorKind::InvalidData),
+        (b"Content-Length: two\r\n\r\n{}", io::ErrorKind::InvalidData),
+        (b"garbage\r\n\r\n", io::ErrorKind::InvalidData),
+        (b"Content-Length: 10\r\n", io::ErrorKind::UnexpectedEof),
+        (
+            b"Content-Length: 10\r\n\r\n{}",
+            io::ErrorKind::UnexpectedEof,
+        ),
+    ];
+
+    for (input, kind) in cases {
+        let mut reader = input;
+        let error = read_frame(&mut reader).await.unwrap_err();
+        assert_eq!(error.kind(), kind, "{}", String::from_utf8_lossy(input));
+    }
+}
+
+#[tokio::test]
+async fn test_oversized_frame_is_rejected() {
+    let input = format!("Content-Length: {}\r\n\r\n", MAX_CONTENT_LENGTH + 1);
+    let error = read_frame(&mut input.as_bytes()).await.unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[tokio::test]
+async fn test_write_and_read_message() {
+    let message = Message::from(NotificationMessage::new(
+        "window/logMessage",
+        Some(json!({ "type": 3, "message": "héllo" })),
+    ));
+
+    let mut buffer = Vec::new();
+    write_message(&mut buffer, &message).await.unwrap();
+
+    let content = serde_json::to_vec(&message).unwrap();
+    let header = format!("Content-Length: {}\r\n\r\n", content.len());
+    assert!(buffer.starts_with(header.as_bytes()));
+
+    let mut reader = BufReader::new(&buffer[..]);
+    assert_eq!(read_message(&mut reader).await.unwrap(), Some(message));
+    assert_eq!(read_message(&mut reader).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_read_message_rejects_non_messages() {
+    let mut reader = &b"Content-Length: 7\r\n\r\n[1,2,3]"[..];
+    let error = read_message(&mut reader).await.unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_frames_roundtrip(contents in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..64), 0..8)) {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let frames = runtime.block_on(async {
+            let mut buffer = Vec::new();
+            for content in &contents {
+                write_frame(&mut buffer, content).await.unwrap();
+            }
+
+            let mut reader = &buffer[..];
+            let mut frames = Vec::new();
+            while let Some(frame) = read_frame(&mut reader).await.unwrap() {
+                frames.push(frame);
+            }
+            frames
+        });
+        prop_assert_eq!(frames, contents);
+    }
+}
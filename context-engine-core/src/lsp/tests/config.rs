@@ -0,0 +1,51 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+#[test]
+fn test_server_config_defaults() {
+    let config: ServerConfig = toml::from_str(
+        r#"
+        name = "pyright"
+        command = "pyright-langserver"
+        args = ["--stdio"]
+        "#,
+    )
+    .unwrap();
+
+    let mut expected = ServerConfig::new("pyright", "pyright-langserver");
+    expected.args = vec!["--stdio".to_string()];
+    assert_eq!(config, expected);
+    assert!(config.handles("python"));
+    assert_eq!(config.request_timeout(), Duration::from_secs(30));
+}
+
+#[test]
+fn test_backoff_is_capped() {
+    let policy = RestartPolicy {
+        initial_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_millis(700),
+        ..RestartPolicy::default()
+    };
+
+    let delays = (0..6)
+        .map(|attempt| policy.backoff(attempt))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        delays,
+        [100, 100, 200, 400, 700, 700].map(Duration::from_millis)
+    );
+    assert_eq!(policy.backoff(u32::MAX), policy.max_backoff);
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_backoff_is_monotonic(attempt in 1u32..100) {
+        let policy = RestartPolicy::default();
+        prop_assert!(policy.backoff(attempt) <= policy.backoff(attempt + 1));
+        prop_assert!(policy.backoff(attempt) <= policy.max_backoff);
+    }
+}
@@ -0,0 +1,107 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+use serde_json::json;
+
+use super::*;
+
+#[test]
+fn test_message_kinds_are_distinguished() {
+    let request: Message = serde_json::from_value(json!({
+        "jsonrpc": "2.0",
+        "id": "a",
+        "method": "workspace/configuration",
+        "params": { "items": [] },
+    }))
+    .unwrap();
+    assert!(matches!(&request, Message::Request(r) if r.id == RequestId::String("a".into())));
+    assert_eq!(request.method(), Some("workspace/configuration"));
+
+    let notification: Message = serde_json::from_value(json!({
+        "jsonrpc": "2.0",
+        "method": "initialized",
+    }))
+    .unwrap();
+    assert!(matches!(&notification, Message::Notification(n) if n.params.is_none()));
+
+    let response: Message = serde_json::from_value(json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": { "code": -32700, "message": "Parse error" },
+    }))
+    .unwrap();
+    let Message::Response(response) = response else {
+        unreachable!()
+    };
+    assert_eq!(response.id, None);
+    assert_eq!(response.into_result().unwrap_err().code, -32700);
+}
+
+#[test]
+fn test_response_null_result() {
+    let response = ResponseMessage::ok(RequestId::Number(7), Value::Null);
+    assert_eq!(
+        serde_json::to_value(&response).unwrap(),
+        json!({ "jsonrpc": "2.0", "id": 7, "result": null })
+    );
+
+    let json = serde_json::to_string(&response).unwrap();
+    let parsed: ResponseMessage = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, response);
+
+    // A response without result or error is a successful response too
+    let parsed: ResponseMessage = serde_json::from_str(r#"{"jsonrpc":"2.0","id":7}"#).unwrap();
+    assert_eq!(parsed.result, None);
+    assert_eq!(parsed.into_result().unwrap(), Value::Null);
+}
+
+#[test]
+fn test_unsupported_version_is_rejected() {
+    let result = serde_json::from_value::<Message>(json!({
+        "jsonrpc": "1.0",
+        "method": "initialized",
+    }));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_message_display() {
+    let request = Message::from(RequestMessage::new(RequestId::Number(3), "shutdown", None));
+    assert_eq!(request.to_string(), "request shutdown (#3)");
+    let notification = Message::from(NotificationMessage::new("exit", None));
+    assert_eq!(notification.to_string(), "notification exit");
+    let response = Message::from(ResponseMessage::ok(RequestId::String("x".into()), json!(1)));
+    assert_eq!(response.to_string(), "response (#\"x\")");
+}
+
+fn arb_message() -> impl Strategy<Value = Message> {
+    let id = prop_oneof![
+        any::<i32>().prop_map(RequestId::Number),
+        "[a-z0-9-]{1,8}".prop_map(RequestId::String),
+    ];
+    let params = prop::option::of(prop_oneof![
+        Just(json!({})),
+        any::<i64>().prop_map(|n| json!({ "n": n })),
+        "\\PC{0,10}".prop_map(|s| json!([s])),
+    ]);
+    let method = "[a-z]{1,8}(/[a-zA-Z]{1,8})?";
+
+    prop_oneof![
+        (id.clone(), method, params.clone())
+            .prop_map(|(id, method, params)| RequestMessage::new(id, method, params).into()),
+        (method, params.clone())
+            .prop_map(|(method, params)| NotificationMessage::new(method, params).into()),
+        (id, params.prop_map(|p| p.unwrap_or(json!(true))))
+            .prop_map(|(id, result)| ResponseMessage::ok(id, result).into()),
+    ]
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_message_serialization_roundtrip(message in arb_message()) {
+        let json = serde_json::to_string(&message).unwrap();
+        let parsed: Message = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!(parsed, message);
+    }
+}
@@ -0,0 +1,66 @@
+#![allow(clippy::unwrap_used)]
+
+use std::time::Duration;
+
+use super::*;
+
+fn shell(name: &str, script: &str) -> ServerConfig {
+    let mut config = ServerConfig::new(name, "sh");
+    config.args = vec!["-c".to_string(), script.to_string()];
+    config
+}
+
+#[tokio::test]
+async fn test_missing_command_is_a_configuration_error() {
+    let config = ServerConfig::new("missing", "/nonexistent/language-server");
+    let Err(error) = CommandLauncher.launch(&config, &std::env::temp_dir()) else {
+        unreachable!()
+    };
+
+    assert!(matches!(
+        error,
+        ContextEngineError::Configuration { key: Some(key), .. } if key == "servers.missing.command"
+    ));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_exit_code_is_reported() {
+    let process = CommandLauncher
+        .launch(&shell("exits", "exit 3"), &std::env::temp_dir())
+        .unwrap();
+    assert_eq!(process.exited.await.unwrap(), Some(3));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_process_is_killed() {
+    let process = CommandLauncher
+        .launch(&shell("sleeps", "sleep 30"), &std::env::temp_dir())
+        .unwrap();
+
+    drop(process.kill);
+    let exit_code = tokio::time::timeout(Duration::from_secs(5), process.exited)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(exit_code, None);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_stdio_is_connected() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut process = CommandLauncher
+        .launch(&shell("echo", "cat"), &std::env::temp_dir())
+        .unwrap();
+
+    process.writer.write_all(b"ping").await.unwrap();
+    drop(process.writer);
+
+    let mut output = String::new();
+    process.reader.read_to_string(&mut output).await.unwrap();
+    assert_eq!(output, "ping");
+    assert_eq!(process.exited.await.unwrap(), Some(0));
+}
@@ -0,0 +1,227 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use proptest::prelude::*;
+
+use super::*;
+
+fn uri(path: &str) -> Uri {
+    Uri::from_str(&format!("file://{path}")).unwrap()
+}
+
+fn route_names(registry: &LanguageRegistry, uri: &Uri) -> Vec<String> {
+    registry
+        .route(uri, None)
+        .into_iter()
+        .map(|route| route.server.name.clone())
+        .collect()
+}
+
+#[test]
+fn test_language_by_extension() {
+    let registry = LanguageRegistry::with_defaults();
+
+    for (path, expected) in [
+        ("/repo/src/main.rs", Some("rust")),
+        ("/repo/web/index.ts", Some("typescript")),
+        ("/repo/web/App.TSX", Some("typescriptreact")),
+        ("/repo/web/server.mjs", Some("javascript")),
+        ("/repo/tools/gen.py", Some("python")),
+        ("/repo/README.md", None),
+        ("/repo/.rs", None),
+        ("/repo/Makefile", None),
+    ] {
+        assert_eq!(registry.language_id(&uri(path), None), expected, "{path}");
+    }
+}
+
+#[test]
+fn test_longest_extension_wins() {
+    let mut registry = LanguageRegistry::with_defaults();
+    registry.add_language(LanguageConfig::new("typescript-declaration", &["d.ts"]));
+
+    assert_eq!(
+        registry.language_id(&uri("/repo/types/index.d.ts"), None),
+        Some("typescript-declaration")
+    );
+    assert_eq!(
+        registry.language_id(&uri("/repo/types/index.ts"), None),
+        Some("typescript")
+    );
+}
+
+#[test]
+fn test_globs_take_precedence_over_extensions() {
+    let mut registry = LanguageRegistry::with_defaults();
+    let mut dockerfile = LanguageConfig::new("dockerfile", &[]);
+    dockerfile.globs = vec![Glob::new("Dockerfile*").unwrap()];
+    registry.add_language(dockerfile);
+    let mut workflow = LanguageConfig::new("github-actions-workflow", &[]);
+    workflow.globs = vec![Glob::new(".github/workflows/*.yml").unwrap()];
+    registry.add_language(workflow);
+    let mut stubs = LanguageConfig::new("rust-template", &[]);
+    stubs.globs = vec![Glob::new("templates/**/*.rs").unwrap()];
+    registry.add_language(stubs);
+
+    assert_eq!(
+        registry.language_id(&uri("/repo/deploy/Dockerfile.prod"), None),
+        Some("dockerfile")
+    );
+    assert_eq!(
+        registry.language_id(&uri("/home/me/repo/.github/workflows/ci.yml"), None),
+        Some("github-actions-workflow")
+    );
+    assert_eq!(
+        registry.language_id(&uri("/repo/templates/bin/main.rs"), None),
+        Some("rust-template")
+    );
+    assert_eq!(
+        registry.language_id(&uri("/repo/src/templates.rs"), None),
+        Some("rust")
+    );
+}
+
+#[test]
+fn test_language_by_shebang() {
+    let registry = LanguageRegistry::with_defaults();
+    let script = uri("/repo/bin/release");
+
+    for (content, expected) in [
+        ("#!/usr/bin/env python3\n", Some("python")),
+        ("#!/usr/bin/python3.12 -u\n", Some("python")),
+        ("#!/usr/bin/env -S PYTHONPATH=. python -u\n", Some("python")),
+        ("#! /usr/local/bin/node\n", Some("javascript")),
+        ("#!/bin/sh\n", None),
+        ("#!/usr/bin/env pythonista\n", None),
+        ("print('no shebang')\n", None),
+        ("", None),
+    ] {
+        assert_eq!(
+            registry.language_id(&script, Some(content)),
+            expected,
+            "{content:?}"
+        );
+    }
+    // The extension is preferred over the shebang
+    assert_eq!(
+        registry.language_id(&uri("/repo/bin/tool.rs"), Some("#!/usr/bin/env python\n")),
+        Some("rust")
+    );
+}
+
+#[test]
+fn test_route_orders_servers_and_finds_roots() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join("services/api");
+    std::fs::create_dir_all(project.join("app")).unwrap();
+    std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+    std::fs::write(project.join("pyproject.toml"), "").unwrap();
+
+    let registry = LanguageRegistry::with_defaults();
+    let file = Uri::from_file_path(&project.join("app/main.py")).unwrap();
+    let routes = registry.route(&file, None);
+
+    assert_eq!(route_names(&registry, &file), ["pyright", "ruff"]);
+    assert!(
+        routes
+            .iter()
+            .all(|route| route.root == Some(project.clone()))
+    );
+
+    let lib = Uri::from_file_path(&project.join("app/lib.rs")).unwrap();
+    let routes = registry.route(&lib, None);
+    assert_eq!(routes.len(), 1);
+    assert_eq!(routes.first().unwrap().root.as_deref(), Some(dir.path()));
+
+    let orphan = Uri::from_file_path(&dir.path().join("web/index.ts")).unwrap();
+    assert_eq!(
+        registry
+            .route(&orphan, None)
+            .into_iter()
+            .map(|route| route.root)
+            .collect::<Vec<_>>(),
+        [None]
+    );
+    assert!(registry.route(&uri("/repo/README.md"), None).is_empty());
+}
+
+#[test]
+fn test_add_replaces_by_name() {
+    let mut registry = LanguageRegistry::with_defaults();
+    let mut pylsp = ServerConfig::new("pyright", "pylsp");
+    pylsp.languages = vec!["python".to_string()];
+    registry.add_server(pylsp);
+    registry.add_language(LanguageConfig::new("python", &["pyw"]));
+
+    assert_eq!(registry.servers().len(), 4);
+    assert_eq!(registry.server("pyright").unwrap().command, "pylsp");
+    assert_eq!(
+        route_names(&registry, &uri("/repo/gui.pyw")),
+        ["pyright", "ruff"]
+    );
+    assert_eq!(registry.language_id(&uri("/repo/gen.py"), None), None);
+    assert_eq!(registry.language("python").unwrap().extensions, ["pyw"]);
+}
+
+#[test]
+fn test_registry_from_toml() {
+    let registry: LanguageRegistry = toml::from_str(
+        r#"
+        [[languages]]
+        id = "terraform"
+        extensions = ["tf", "tfvars"]
+        globs = ["*.tf.json"]
+
+        [[servers]]
+        name = "terraform-ls"
+        command = "terraform-ls"
+        args = ["serve"]
+        languages = ["terraform"]
+        root_markers = [".terraform"]
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        registry.language_id(&uri("/infra/main.tf.json"), None),
+        Some("terraform")
+    );
+    assert_eq!(
+        route_names(&registry, &uri("/infra/vars.tfvars")),
+        ["terraform-ls"]
+    );
+    assert!(
+        toml::from_str::<LanguageRegistry>("[[languages]]\nid = \"x\"\nglobs = [\"[\"]").is_err()
+    );
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_extension_match_ignores_directories(
+        dirs in prop::collection::vec("[a-z][a-z.]{0,7}", 0..4),
+        stem in "[a-zA-Z0-9_]{1,12}",
+    ) {
+        let registry = LanguageRegistry::with_defaults();
+        let path = format!("/{}/{stem}.rs", dirs.join("/"));
+        prop_assert_eq!(registry.language_id(&uri(&path), None), Some("rust"));
+    }
+
+    #[test]
+    fn prop_find_root_is_an_ancestor(depth in 0usize..5, marker_at in 0usize..5) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut folder = dir.path().to_path_buf();
+        let mut folders = vec![folder.clone()];
+        for level in 0..depth {
+            folder = folder.join(format!("d{level}"));
+            folders.push(folder.clone());
+        }
+        std::fs::create_dir_all(&folder).unwrap();
+        let marked = folders.get(marker_at.min(depth)).unwrap();
+        std::fs::write(marked.join("Cargo.toml"), "").unwrap();
+
+        let root = find_root(&folder.join("lib.rs"), &["Cargo.toml".to_string()]);
+        prop_assert_eq!(root.as_ref(), Some(marked));
+    }
+}
@@ -0,0 +1,354 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use lsp_types::request::WorkspaceSymbolRequest;
+use lsp_types::{TextDocumentItem, WorkspaceSymbolParams};
+use serde_json::{Value, json};
+
+use super::*;
+use crate::JsonRpcError;
+use crate::lsp::testing::{FakeLauncher, FakeLog, FakeServer, FakeSession};
+
+/// Scripted behavior and observations of the fake servers.
+#[derive(Default)]
+struct FakeState {
+    /// Number of launches that fail before a process starts
+    failing_launches: AtomicU32,
+    /// Number of launches that exit right after `initialized`
+    crashing_launches: AtomicU32,
+    /// The launches that exit right after `initialized`
+    crashing: Mutex<Vec<u32>>,
+    /// Whether servers leave requests other than the lifecycle ones
+    /// unanswered
+    hang: AtomicBool,
+}
+
+impl FakeServer for FakeState {
+    fn launch(&self, config: &ServerConfig, launch: u32) -> Result<(), ContextEngineError> {
+        if take(&self.failing_launches) {
+            return Err(ContextEngineError::Configuration {
+                key: Some(format!("servers.{}.command", config.name)),
+                message: "not found".to_string(),
+            });
+        }
+        if take(&self.crashing_launches) {
+            self.crashing.lock().push(launch);
+        }
+        Ok(())
+    }
+
+    fn initialize(&self) -> Value {
+        json!({ "capabilities": {}, "serverInfo": { "name": "fake" } })
+    }
+
+    async fn request(
+        &self,
+        _session: &FakeSession,
+        _method: &str,
+        _params: Value,
+    ) -> Option<Result<Value, JsonRpcError>> {
+        (!self.hang.load(Ordering::SeqCst)).then_some(Ok(Value::Null))
+    }
+
+    async fn notify(&self, session: &FakeSession, method: &str, _params: Value) {
+        if method == "initialized" && self.crashing.lock().contains(&session.launch()) {
+            session.exit(101);
+        }
+    }
+}
+
+/// The documents opened in the servers, with the launch they belong to.
+fn opened(log: &FakeLog) -> Vec<(u32, String)> {
+    log.messages()
+        .into_iter()
+        .filter(|message| message.method == "textDocument/didOpen")
+        .map(|message| {
+            let uri = message
+                .params
+                .pointer("/textDocument/uri")
+                .and_then(Value::as_str)
+                .unwrap();
+            (message.launch, uri.to_string())
+        })
+        .collect()
+}
+
+/// Decrements a counter, returning true if it was positive.
+fn take(counter: &AtomicU32) -> bool {
+    counter
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+        .is_ok()
+}
+
+fn policy() -> RestartPolicy {
+    RestartPolicy {
+        initial_backoff: Duration::from_millis(5),
+        max_backoff: Duration::from_millis(20),
+        max_restarts: 2,
+        reset_after: Duration::from_secs(60),
+        max_timeouts: 2,
+        shutdown_timeout: Duration::from_secs(1),
+    }
+}
+
+fn supervisor(documents: Arc<DocumentStore>) -> (LspSupervisor, FakeLauncher<FakeState>) {
+    let launcher = FakeLauncher::new(FakeState::default());
+    let supervisor =
+        LspSupervisor::with_launcher(std::env::temp_dir(), documents, launcher.clone(), policy());
+    (supervisor, launcher)
+}
+
+fn rust_analyzer() -> ServerConfig {
+    let mut config = ServerConfig::new("rust-analyzer", "rust-analyzer");
+    config.languages = vec!["rust".to_string()];
+    config.request_timeout_ms = 2_000;
+    config
+}
+
+fn open_documents() -> Arc<DocumentStore> {
+    let documents = Arc::new(DocumentStore::default());
+    for (path, language) in [("/src/lib.rs", "rust"), ("/tools/gen.py", "python")] {
+        let uri = Uri::from_str(&format!("file://{path}")).unwrap();
+        documents
+            .open(TextDocumentItem::new(
+                uri,
+                language.to_string(),
+                1,
+                String::new(),
+            ))
+            .unwrap();
+    }
+    documents
+}
+
+#[tokio::test]
+async fn test_start_performs_handshake_and_replays_documents() {
+    let (supervisor, fake) = supervisor(open_documents());
+    supervisor.start(rust_analyzer()).unwrap();
+
+    let client = supervisor.client("rust-analyzer").await.unwrap();
+    assert_eq!(client.server(), "rust-analyzer");
+    assert_eq!(
+        supervisor.status("rust-analyzer"),
+        Some(ServerStatus::Running)
+    );
+    assert_eq!(
+        supervisor
+            .initialize_result("rust-analyzer")
+            .unwrap()
+            .server_info
+            .unwrap()
+            .name,
+        "fake"
+    );
+    assert_eq!(
+        supervisor.server_version("rust-analyzer").as_deref(),
+        Some("fake")
+    );
+    assert_eq!(supervisor.server_version("pyright"), None);
+
+    let symbols = supervisor
+        .request::<WorkspaceSymbolRequest>("rust-analyzer", WorkspaceSymbolParams::default())
+        .await
+        .unwrap();
+    assert_eq!(symbols, None);
+
+    assert_eq!(
+        fake.log().methods(1),
+        [
+            "initialize",
+            "initialized",
+            "textDocument/didOpen",
+            "workspace/symbol"
+        ]
+    );
+    // Only documents of the server's languages are opened
+    assert_eq!(opened(fake.log()), [(1, "file:///src/lib.rs".to_string())]);
+}
+
+#[tokio::test]
+async fn test_document_sync_notifies_servers_of_the_language() {
+    let documents = open_documents();
+    let (supervisor, fake) = supervisor(Arc::clone(&documents));
+    supervisor.start(rust_analyzer()).unwrap();
+    supervisor.client("rust-analyzer").await.unwrap();
+
+    let uri = Uri::from_str("file:///src/main.rs").unwrap();
+    let item = TextDocumentItem::new(uri.clone(), "rust".to_string(), 1, String::new());
+    supervisor.open_document(&documents.open(item).unwrap());
+    let change = TextDocumentContentChangeEvent {
+        range: None,
+        range_length: None,
+        text: "fn main() {}\n".to_string(),
+    };
+    supervisor.change_document(&documents.apply_changes(&uri, 2, &[change]).unwrap());
+    supervisor.close_document(&documents.close(&uri).unwrap());
+    let python = Uri::from_str("file:///tools/gen.py").unwrap();
+    supervisor.close_document(&documents.close(&python).unwrap());
+
+    // Answered after the notifications sent before it
+    supervisor
+        .request::<WorkspaceSymbolRequest>("rust-analyzer", WorkspaceSymbolParams::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        fake.log().methods(1),
+        [
+            "initialize",
+            "initialized",
+            "textDocument/didOpen",
+            "textDocument/didOpen",
+            "textDocument/didChange",
+            "textDocument/didClose",
+            "workspace/symbol"
+        ]
+    );
+    assert_eq!(
+        opened(fake.log()).last().unwrap(),
+        &(1, "file:///src/main.rs".to_string())
+    );
+    supervisor.stop_all().await;
+}
+
+#[tokio::test]
+async fn test_crashed_server_is_restarted_and_documents_are_replayed() {
+    let (supervisor, fake) = supervisor(open_documents());
+    fake.server().crashing_launches.store(1, Ordering::SeqCst);
+    supervisor.start(rust_analyzer()).unwrap();
+    let mut status = supervisor.watch_status("rust-analyzer").unwrap();
+
+    let restarting = status
+        .wait_for(ServerStatus::is_degraded)
+        .await
+        .unwrap()
+        .clone();
+    assert!(matches!(
+        restarting,
+        ServerStatus::Restarting {
+            attempt: 1,
+            error: ContextEngineError::ServerCrashed {
+                exit_code: Some(101),
+                ..
+            },
+        }
+    ));
+    supervisor.client("rust-analyzer").await.unwrap();
+    assert_eq!(fake.log().launches(), 2);
+    // The first launch exited before it read the replayed documents
+    assert_eq!(opened(fake.log()), [(2, "file:///src/lib.rs".to_string())]);
+}
+
+#[tokio::test]
+async fn test_server_is_given_up_after_max_restarts() {
+    let (supervisor, fake) = supervisor(open_documents());
+    fake.server()
+        .failing_launches
+        .store(u32::MAX, Ordering::SeqCst);
+    supervisor.start(rust_analyzer()).unwrap();
+
+    let error = supervisor.client("rust-analyzer").await.unwrap_err();
+    assert_eq!(error.code(), crate::ErrorCode::Configuration);
+
+    let status = supervisor.status("rust-analyzer").unwrap();
+    assert_eq!(status, ServerStatus::Failed { error });
+    assert!(status.is_degraded());
+    // The first launch plus `max_restarts` restarts
+    assert_eq!(fake.log().launches(), 3);
+}
+
+#[tokio::test]
+async fn test_hung_server_is_killed_and_restarted() {
+    let (supervisor, fake) = supervisor(open_documents());
+    fake.server().hang.store(true, Ordering::SeqCst);
+    let mut config = rust_analyzer();
+    config.request_timeout_ms = 50;
+    supervisor.start(config).unwrap();
+    supervisor.client("rust-analyzer").await.unwrap();
+
+    let mut status = supervisor.watch_status("rust-analyzer").unwrap();
+    for _ in 0..2 {
+        let error = supervisor
+            .request::<WorkspaceSymbolRequest>("rust-analyzer", WorkspaceSymbolParams::default())
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), crate::ErrorCode::Timeout);
+    }
+
+    let restarting = status
+        .wait_for(ServerStatus::is_degraded)
+        .await
+        .unwrap()
+        .clone();
+    assert_eq!(
+        restarting,
+        ServerStatus::Restarting {
+            attempt: 1,
+            error: ContextEngineError::ServerCrashed {
+                server: "rust-analyzer".to_string(),
+                exit_code: None,
+                message: "killed after 2 consecutive request timeouts".to_string(),
+            },
+        }
+    );
+
+    fake.server().hang.store(false, Ordering::SeqCst);
+    supervisor
+        .request::<WorkspaceSymbolRequest>("rust-analyzer", WorkspaceSymbolParams::default())
+        .await
+        .unwrap();
+    assert_eq!(fake.log().launches(), 2);
+}
+
+#[tokio::test]
+async fn test_stop_shuts_server_down() {
+    let (supervisor, fake) = supervisor(Arc::new(DocumentStore::default()));
+    supervisor.start(rust_analyzer()).unwrap();
+    supervisor.client("rust-analyzer").await.unwrap();
+
+    supervisor.stop("rust-analyzer").await.unwrap();
+    assert_eq!(
+        supervisor.status("rust-analyzer"),
+        Some(ServerStatus::Stopped)
+    );
+    assert_eq!(
+        fake.log().methods(1),
+        ["initialize", "initialized", "shutdown", "exit"]
+    );
+    assert_eq!(fake.log().launches(), 1);
+    assert!(matches!(
+        supervisor.client("rust-analyzer").await,
+        Err(ContextEngineError::Transport { .. })
+    ));
+
+    // A stopped server can be started again
+    supervisor.start(rust_analyzer()).unwrap();
+    supervisor.client("rust-analyzer").await.unwrap();
+    assert_eq!(fake.log().launches(), 2);
+    supervisor.stop_all().await;
+}
+
+#[tokio::test]
+async fn test_server_names_are_checked() {
+    let (supervisor, _) = supervisor(Arc::new(DocumentStore::default()));
+    supervisor.start(rust_analyzer()).unwrap();
+
+    assert!(matches!(
+        supervisor.start(rust_analyzer()),
+        Err(ContextEngineError::Configuration { .. })
+    ));
+    assert!(matches!(
+        supervisor.client("pyright").await,
+        Err(ContextEngineError::Configuration { key: Some(key), .. }) if key == "servers.pyright"
+    ));
+    assert_eq!(supervisor.status("pyright"), None);
+    assert_eq!(
+        supervisor
+            .statuses()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>(),
+        ["rust-analyzer"]
+    );
+}
@@ -0,0 +1,182 @@
+//! Extraction of usage patterns from the references of a symbol.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use lsp_types::{Location, PositionEncodingKind, Uri};
+use tracing::warn;
+
+use crate::document::TextDocument;
+use crate::error::ContextEngineError;
+use crate::lsp::LanguageRegistry;
+use crate::patterns::UsagePatterns;
+use crate::patterns::template::{enclosing_node, generalize};
+use crate::syntax::{SyntaxLayer, SyntaxSnapshot};
+use crate::types::{RangeExt, UriExt};
+
+/// Turns the references of a symbol into generalized usage patterns.
+///
+/// The references usually come from a `textDocument/references` request.
+/// For each reference, the extractor finds the innermost statement around
+/// it in the syntax tree of its document and generalizes the statement into
+/// a template (see [`UsagePattern`](crate::patterns::UsagePattern)).
+///
+/// Open documents are taken from the [`SyntaxLayer`], so that references
+/// into unsaved edits resolve against the text the language server saw.
+/// Other documents are read from disk and parsed with the grammar of the
+/// language the [`LanguageRegistry`] assigns to them.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::document::TextDocument;
+/// use context_engine_core::lsp::LanguageRegistry;
+/// use context_engine_core::patterns::PatternExtractor;
+/// use context_engine_core::syntax::SyntaxLayer;
+/// use context_engine_core::types::{Location, Position, PositionEncodingKind, Range, Uri};
+/// use std::str::FromStr;
+/// use std::sync::Arc;
+///
+/// let uri = Uri::from_str("file:///src/main.rs").unwrap();
+/// let text = "fn main() {\n    let a = User::new(1);\n    let b = User::new(2);\n}\n";
+/// let layer = SyntaxLayer::new();
+/// layer.update(Arc::new(TextDocument::new(
+///     uri.clone(),
+///     "rust",
+///     1,
+///     text,
+///     PositionEncodingKind::UTF16,
+/// )));
+///
+/// let registry = LanguageRegistry::with_defaults();
+/// let extractor = PatternExtractor::new(&layer, &registry, PositionEncodingKind::UTF16);
+/// let references = [1, 2].map(|line| Location {
+///     uri: uri.clone(),
+///     range: Range::new(Position::new(line, 12), Position::new(line, 16)),
+/// });
+///
+/// let patterns = extractor.extract(&references);
+/// let pattern = patterns.usage_patterns.first().unwrap();
+/// assert_eq!(pattern.code_template, "let ... = User::new(...);");
+/// assert_eq!(pattern.count, 2);
+/// assert_eq!(pattern.usage_frequency_percent, 100.0);
+/// ```
+#[derive(Debug)]
+pub struct PatternExtractor<'a> {
+    layer: &'a SyntaxLayer,
+    registry: &'a LanguageRegistry,
+    encoding: PositionEncodingKind,
+}
+
+impl<'a> PatternExtractor<'a> {
+    /// Creates an extractor.
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - The syntax trees of the open documents
+    /// * `registry` - The registry that recognizes the language of documents
+    ///   that are not open
+    /// * `encoding` - The position encoding of the reference locations, i.e.
+    ///   the one negotiated with the language server that found them
+    pub fn new(
+        layer: &'a SyntaxLayer,
+        registry: &'a LanguageRegistry,
+        encoding: PositionEncodingKind,
+    ) -> Self {
+        Self {
+            layer,
+            registry,
+            encoding,
+        }
+    }
+
+    /// Extracts the usage patterns of a set of references.
+    ///
+    /// References that cannot be resolved, e.g. because their file no longer
+    /// exists or its language has no grammar, are left out of the patterns
+    /// and counted in [`UsagePatterns::unresolved`].
+    pub fn extract(&self, references: &[Location]) -> UsagePatterns {
+        let mut snapshots = HashMap::new();
+        let mut templates = Vec::with_capacity(references.len());
+        let mut unresolved = 0;
+        for reference in references {
+            let snapshot = snapshots
+                .entry(reference.uri.as_str())
+                .or_insert_with(|| self.snapshot(&reference.uri));
+            let template = match snapshot {
+                Ok(snapshot) => self.template_in(snapshot, reference),
+                Err(error) => Err(error.clone()),
+            };
+            match template {
+                Ok(template) => templates.push(template),
+                Err(error) => {
+                    warn!("Skipping reference in {}: {error}", reference.uri.as_str());
+                    unresolved += 1;
+                }
+            }
+        }
+
+        let mut patterns = UsagePatterns::from_templates(templates);
+        patterns.unresolved = unresolved;
+        patterns
+    }
+
+    /// Returns the template of a single reference.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::Parse`] if the document of the reference
+    /// cannot be read or has no grammar, and
+    /// [`ContextEngineError::Location`] if the range of the reference is not
+    /// in the document.
+    pub fn template(&self, reference: &Location) -> Result<String, ContextEngineError> {
+        let snapshot = self.snapshot(&reference.uri)?;
+        self.template_in(&snapshot, reference)
+    }
+
+    fn template_in(
+        &self,
+        snapshot: &SyntaxSnapshot,
+        reference: &Location,
+    ) -> Result<String, ContextEngineError> {
+        let document = snapshot.document();
+        let range = reference
+            .range
+            .to_offsets(document.line_index(), &self.encoding)?;
+        let node = enclosing_node(snapshot.tree(), range.clone()).ok_or_else(|| {
+            ContextEngineError::Parse {
+                uri: Some(reference.uri.clone()),
+                message: "the reference is outside of any statement".to_string(),
+            }
+        })?;
+        Ok(generalize(node, document.text(), range))
+    }
+
+    /// Returns the syntax snapshot of a document, from the layer if it is
+    /// open or else from disk.
+    fn snapshot(&self, uri: &Uri) -> Result<Arc<SyntaxSnapshot>, ContextEngineError> {
+        if let Some(snapshot) = self.layer.get(uri) {
+            return Ok(snapshot);
+        }
+
+        let error = |message: String| ContextEngineError::Parse {
+            uri: Some(uri.clone()),
+            message,
+        };
+        let path = uri.to_file_path()?;
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| error(format!("cannot read {}: {e}", path.display())))?;
+        let language_id = self
+            .registry
+            .language_id(uri, Some(&text))
+            .ok_or_else(|| error("the language of the document is unknown".to_string()))?;
+        let document = TextDocument::new(uri.clone(), language_id, 0, text, self.encoding.clone());
+        SyntaxSnapshot::parse(Arc::new(document))
+            .map(Arc::new)
+            .ok_or_else(|| error(format!("there is no grammar for {language_id}")))
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/extractor.rs"]
+mod tests;
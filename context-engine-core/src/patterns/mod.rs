@@ -0,0 +1,35 @@
+//! Usage patterns of symbols.
+//!
+//! Knowing that a type has a builder is less useful than knowing how the
+//! codebase actually uses it. This module takes the references of a symbol,
+//! e.g. from a `textDocument/references` request, finds the statement around
+//! each of them in the syntax tree of its document and generalizes it into a
+//! `code_template`: literals, bindings and arguments become `...` holes, while
+//! the structure of the code and the names of the API stay. Structurally
+//! equal templates are grouped and counted:
+//!
+//! ```json
+//! {
+//!   "usage_patterns": [
+//!     {
+//!       "code_template": "let ... = User::builder().name(...).email(...).build()?;",
+//!       "count": 45,
+//!       "usage_frequency_percent": 32.1
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! ## Structs
+//!
+//! * [`PatternExtractor`] - Turns references into usage patterns.
+//! * [`UsagePatterns`] - The usage patterns of a symbol, most frequent first.
+//! * [`UsagePattern`] - A code template and how often it occurs.
+
+mod extractor;
+mod template;
+mod usage;
+
+pub use extractor::PatternExtractor;
+pub use template::HOLE;
+pub use usage::{UsagePattern, UsagePatterns};
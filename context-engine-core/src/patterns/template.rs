@@ -0,0 +1,377 @@
+//! Generalization of the code around a reference into a template.
+
+use std::ops::Range;
+
+use crate::syntax::{SyntaxKind, SyntaxNode, SyntaxTree};
+
+/// The placeholder that stands for generalized code in a template.
+pub const HOLE: &str = "...";
+
+/// Keywords that name a value rather than start a construct.
+const VALUE_KEYWORDS: &[&str] = &["self", "Self", "super", "crate", "this", "cls"];
+
+/// Returns the node whose code becomes the template of a reference: the
+/// innermost statement that contains the reference, or else the top-level
+/// node that does.
+pub(crate) fn enclosing_node(tree: &SyntaxTree, reference: Range<usize>) -> Option<&SyntaxNode> {
+    let ancestors = tree.ancestors_for_byte_range(reference.start, reference.end);
+    ancestors
+        .iter()
+        .rev()
+        .find(|node| node.kind().is_statement())
+        .or_else(|| ancestors.get(1))
+        .copied()
+}
+
+/// Generalizes the code of a node into a template.
+///
+/// Literals, identifiers and arguments become [`HOLE`]s, except for the
+/// reference itself, the names of called functions, macros, fields and
+/// receivers, path segments and capitalized names such as types and enum
+/// variants. Groups and blocks that do not contain the reference collapse to
+/// a single hole, while `match` arms keep their patterns. Tokens are joined
+/// with canonical spacing so that code that only differs in formatting or
+/// comments yields the same template.
+///
+/// # Arguments
+///
+/// * `node` - The node to generalize, e.g. from [`enclosing_node`]
+/// * `source` - The text the node was parsed from
+/// * `reference` - The byte range of the reference, which is kept verbatim
+pub(crate) fn generalize(node: &SyntaxNode, source: &str, reference: Range<usize>) -> String {
+    let mut template = Template {
+        source,
+        reference,
+        pieces: Vec::new(),
+        generics: 0,
+    };
+    template.node(node, false);
+    template.render()
+}
+
+/// How a piece of a template relates to its neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    /// A name, literal or closed group, which binary operators follow
+    Operand,
+    /// A placeholder for generalized code
+    Hole,
+    /// An operator, keyword or separator
+    Operator,
+    /// An opening delimiter
+    Open,
+}
+
+/// A token of a template.
+#[derive(Debug)]
+struct Piece<'a> {
+    text: &'a str,
+    class: Class,
+    /// Whether no space goes before the piece
+    glue_before: bool,
+    /// Whether no space goes after the piece
+    glue_after: bool,
+}
+
+/// A template under construction.
+struct Template<'a> {
+    source: &'a str,
+    reference: Range<usize>,
+    pieces: Vec<Piece<'a>>,
+    /// The depth of open generic argument lists
+    generics: usize,
+}
+
+impl<'a> Template<'a> {
+    /// Returns true if a node contains the reference.
+    fn contains(&self, node: &SyntaxNode) -> bool {
+        let (start, end) = (node.start_byte(), node.end_byte());
+        match self.reference.is_empty() {
+            true => start <= self.reference.start && self.reference.start < end,
+            false => start < self.reference.end && self.reference.start < end,
+        }
+    }
+
+    fn node(&mut self, node: &SyntaxNode, keep: bool) {
+        let contains = self.contains(node);
+        let text = node.text(self.source);
+        match node.kind() {
+            SyntaxKind::Comment => {}
+            SyntaxKind::Identifier => {
+                let capitalized = text.starts_with(|c: char| c.is_uppercase());
+                match contains || keep || capitalized {
+                    true => self.push(text, Class::Operand, false, false),
+                    false => self.hole(),
+                }
+            }
+            SyntaxKind::String | SyntaxKind::Number | SyntaxKind::Character => match contains {
+                true => self.push(text, Class::Operand, false, false),
+                false => self.hole(),
+            },
+            SyntaxKind::Keyword => match VALUE_KEYWORDS.contains(&text) {
+                true => self.push(text, Class::Operand, false, false),
+                false => self.push(text, Class::Operator, false, false),
+            },
+            SyntaxKind::Lifetime => self.push(text, Class::Operand, false, false),
+            SyntaxKind::Punctuation | SyntaxKind::Error => self.punctuation(text),
+            SyntaxKind::Parenthesized | SyntaxKind::Bracketed => self.group(node, contains),
+            SyntaxKind::Block => self.block(node, contains),
+            SyntaxKind::Path => {
+                for child in node.children() {
+                    self.node(child, true);
+                }
+            }
+            SyntaxKind::CallExpression
+            | SyntaxKind::MacroInvocation
+            | SyntaxKind::FieldExpression
+            | SyntaxKind::IndexExpression
+            | SyntaxKind::TryExpression => self.expression(node),
+            _ => self.composite(node),
+        }
+    }
+
+    /// Adds a postfix expression, keeping the names it applies.
+    fn expression(&mut self, node: &SyntaxNode) {
+        for child in node.children() {
+            let text = child.text(self.source);
+            match (node.kind(), child.kind(), child.field()) {
+                (SyntaxKind::TryExpression, SyntaxKind::Punctuation, _) if text == "?" => {
+                    self.push(text, Class::Operand, true, false);
+                }
+                (SyntaxKind::MacroInvocation, SyntaxKind::Punctuation, _) if text == "!" => {
+                    self.push(text, Class::Operand, true, false);
+                }
+                (_, _, Some("function" | "macro"))
+                | (SyntaxKind::FieldExpression, _, Some("value" | "field")) => {
+                    self.node(child, true);
+                }
+                _ => self.node(child, false),
+            }
+        }
+    }
+
+    /// Adds a parenthesized or bracketed group. Each element of a group that
+    /// contains the reference becomes a hole unless it contains the
+    /// reference itself; any other group becomes a single hole.
+    fn group(&mut self, node: &SyntaxNode, contains: bool) {
+        let (open, inner, close) = self.delimited(node);
+        if let Some(open) = open {
+            self.node(open, false);
+        }
+        match contains {
+            true => {
+                let elements = inner
+                    .split(|child| self.is(child, ","))
+                    .filter(|element| !self.is_blank(element))
+                    .collect::<Vec<_>>();
+                for (index, element) in elements.into_iter().enumerate() {
+                    if index > 0 {
+                        self.punctuation(",");
+                    }
+                    self.part(element);
+                }
+            }
+            false if !self.is_blank(inner) => self.hole(),
+            false => {}
+        }
+        if let Some(close) = close {
+            self.node(close, false);
+        }
+    }
+
+    /// Adds a block. `match` bodies keep their arms; the statements of other
+    /// blocks become holes unless they contain the reference.
+    fn block(&mut self, node: &SyntaxNode, contains: bool) {
+        let (open, inner, close) = self.delimited(node);
+        if let Some(open) = open {
+            self.node(open, false);
+        }
+        if self.is_match_body(inner) {
+            let arms = inner
+                .iter()
+                .filter(|child| child.kind() == SyntaxKind::Statement);
+            for (index, arm) in arms.enumerate() {
+                if index > 0 {
+                    self.punctuation(",");
+                }
+                self.composite(arm);
+            }
+        } else if contains {
+            for child in inner {
+                match self.contains(child) || self.is(child, ";") || self.is(child, ",") {
+                    true => self.node(child, false),
+                    false if child.kind() == SyntaxKind::Comment => {}
+                    false => self.hole(),
+                }
+            }
+        } else if !self.is_blank(inner) {
+            self.hole();
+        }
+        if let Some(close) = close {
+            self.node(close, false);
+        }
+    }
+
+    /// Adds the children of a node in order. The expression of a `match` arm
+    /// becomes a hole unless it contains the reference.
+    fn composite(&mut self, node: &SyntaxNode) {
+        let children = node.children();
+        for (index, child) in children.iter().enumerate() {
+            if node.kind() == SyntaxKind::Statement && self.is(child, "=>") {
+                self.node(child, false);
+                let mut rest = children.get(index + 1..).unwrap_or_default();
+                if let Some((last, init)) = rest.split_last() {
+                    if self.is(last, ",") {
+                        rest = init;
+                    }
+                }
+                self.part(rest);
+                return;
+            }
+            self.node(child, false);
+        }
+    }
+
+    /// Adds a sequence of sibling nodes: as they are if they contain the
+    /// reference, or else as a single hole.
+    fn part(&mut self, nodes: &[SyntaxNode]) {
+        match nodes.iter().any(|node| self.contains(node)) {
+            true => {
+                for node in nodes {
+                    self.node(node, false);
+                }
+            }
+            false if !self.is_blank(nodes) => self.hole(),
+            false => {}
+        }
+    }
+
+    /// Splits the children of a group into its delimiters and the nodes
+    /// between them.
+    fn delimited<'n>(
+        &self,
+        node: &'n SyntaxNode,
+    ) -> (
+        Option<&'n SyntaxNode>,
+        &'n [SyntaxNode],
+        Option<&'n SyntaxNode>,
+    ) {
+        let mut inner = node.children();
+        let mut open = None;
+        let mut close = None;
+        if let Some((first, rest)) = inner.split_first() {
+            if matches!(first.text(self.source), "(" | "[" | "{") {
+                open = Some(first);
+                inner = rest;
+            }
+        }
+        if let Some((last, rest)) = inner.split_last() {
+            if open.is_some() && (last.kind() == SyntaxKind::Error || last.kind().is_token()) {
+                let delimiter = last.text(self.source);
+                if matches!(delimiter, ")" | "]" | "}" | "") {
+                    close = Some(last);
+                    inner = rest;
+                }
+            }
+        }
+        (open, inner, close)
+    }
+
+    /// Returns true if the statements of a block are `match` arms.
+    fn is_match_body(&self, statements: &[SyntaxNode]) -> bool {
+        statements.iter().any(|statement| {
+            statement.kind() == SyntaxKind::Statement
+                && statement
+                    .children()
+                    .iter()
+                    .any(|child| self.is(child, "=>"))
+        })
+    }
+
+    /// Returns true if a node is the punctuation `text`.
+    fn is(&self, node: &SyntaxNode, text: &str) -> bool {
+        node.kind() == SyntaxKind::Punctuation && node.text(self.source) == text
+    }
+
+    /// Returns true if nodes contain nothing but comments.
+    fn is_blank(&self, nodes: &[SyntaxNode]) -> bool {
+        nodes.iter().all(|node| node.kind() == SyntaxKind::Comment)
+    }
+
+    /// Returns true if the last piece is something that operators apply to.
+    fn after_operand(&self) -> bool {
+        self.pieces
+            .last()
+            .is_some_and(|piece| matches!(piece.class, Class::Operand | Class::Hole))
+    }
+
+    /// Adds a hole, unless the last piece is one.
+    fn hole(&mut self) {
+        if self.pieces.last().map(|piece| piece.class) != Some(Class::Hole) {
+            self.push(HOLE, Class::Hole, false, false);
+        }
+    }
+
+    /// Adds an operator or delimiter, deciding how it binds to its
+    /// neighbours.
+    fn punctuation(&mut self, text: &'a str) {
+        let last = self.pieces.last().map(|piece| piece.text);
+        let after_operand = self.after_operand();
+        let (class, glue_before, glue_after) = match text {
+            "" => return,
+            "," | ";" | ":" => (Class::Operator, true, false),
+            "." | "?." | "::" => (Class::Operator, true, true),
+            "(" | "[" => (Class::Open, after_operand, true),
+            ")" | "]" => (Class::Operand, true, false),
+            "{" => (Class::Open, false, false),
+            "}" => (Class::Operand, last == Some("{"), false),
+            "#" | "#!" | "@" => (Class::Operator, false, true),
+            "<" if last.is_some_and(|last| {
+                last == "::" || last == "impl" || last.starts_with(|c: char| c.is_uppercase())
+            }) =>
+            {
+                self.generics += 1;
+                (Class::Open, true, true)
+            }
+            ">" if self.generics >= 1 => {
+                self.generics -= 1;
+                (Class::Operand, true, false)
+            }
+            ">>" if self.generics >= 2 => {
+                self.generics -= 2;
+                (Class::Operand, true, false)
+            }
+            "&" | "&&" | "*" | "!" | "-" | "~" if !after_operand => (Class::Operator, false, true),
+            _ => (Class::Operator, false, false),
+        };
+        self.push(text, class, glue_before, glue_after);
+    }
+
+    fn push(&mut self, text: &'a str, class: Class, glue_before: bool, glue_after: bool) {
+        self.pieces.push(Piece {
+            text,
+            class,
+            glue_before,
+            glue_after,
+        });
+    }
+
+    /// Joins the pieces, with a space between two pieces unless either of
+    /// them binds to the other.
+    fn render(&self) -> String {
+        let mut template = String::new();
+        let mut glue = true;
+        for piece in &self.pieces {
+            if !glue && !piece.glue_before {
+                template.push(' ');
+            }
+            template.push_str(piece.text);
+            glue = piece.glue_after;
+        }
+        template
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/template.rs"]
+mod tests;
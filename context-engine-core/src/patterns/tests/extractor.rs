@@ -0,0 +1,120 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use lsp_types::{Position, Range};
+
+use super::*;
+
+const MAIN: &str = r#"fn main() -> Result<(), Error> {
+    let user = User::builder().name("Ada").email("ada@example.com").build()?;
+    match user.validate() {
+        Ok(_) => {}
+        Err(error) => return Err(error),
+    }
+    let admin = User::builder().name(name).email(email).build()?;
+}
+"#;
+
+const LIB: &str = r#"pub fn load(id: u64) -> Result<User, Error> {
+    let user = User::builder().name(&row.name).email(&row.email).build()?;
+    Ok(user)
+}
+"#;
+
+/// Returns the locations of every occurrence of `name` in a text.
+fn references(uri: &Uri, text: &str, name: &str) -> Vec<Location> {
+    let index = crate::types::LineIndex::new(text);
+    text.match_indices(name)
+        .map(|(offset, _)| {
+            let start = index
+                .position(offset, &PositionEncodingKind::UTF16)
+                .unwrap();
+            let end = index
+                .position(offset + name.len(), &PositionEncodingKind::UTF16)
+                .unwrap();
+            Location::new(uri.clone(), Range::new(start, end))
+        })
+        .collect()
+}
+
+#[test]
+fn test_extract_from_open_and_closed_documents() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lib.rs");
+    std::fs::write(&path, LIB).unwrap();
+    let lib = Uri::from_file_path(&path).unwrap();
+
+    // The open document has unsaved edits, which the references refer to
+    let main = Uri::from_str("file:///project/src/main.rs").unwrap();
+    let layer = SyntaxLayer::new();
+    layer.update(Arc::new(TextDocument::new(
+        main.clone(),
+        "rust",
+        3,
+        MAIN,
+        PositionEncodingKind::UTF16,
+    )));
+
+    let mut locations = references(&main, MAIN, "User");
+    locations.extend(references(&main, MAIN, "user."));
+    locations.extend(references(&lib, LIB, "User"));
+
+    let registry = LanguageRegistry::with_defaults();
+    let extractor = PatternExtractor::new(&layer, &registry, PositionEncodingKind::UTF16);
+    let patterns = extractor.extract(&locations);
+    assert_eq!(patterns.unresolved, 0);
+    insta::assert_snapshot!(serde_json::to_string_pretty(&patterns).unwrap());
+}
+
+#[test]
+fn test_unresolved_references_are_counted() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes.txt");
+    std::fs::write(&notes, "User").unwrap();
+
+    let registry = LanguageRegistry::with_defaults();
+    let layer = SyntaxLayer::new();
+    let extractor = PatternExtractor::new(&layer, &registry, PositionEncodingKind::UTF16);
+    let range = Range::new(Position::new(0, 0), Position::new(0, 4));
+    let locations = [
+        Location::new(Uri::from_file_path(&notes).unwrap(), range),
+        Location::new(
+            Uri::from_file_path(&dir.path().join("gone.rs")).unwrap(),
+            range,
+        ),
+    ];
+
+    for location in &locations {
+        assert!(matches!(
+            extractor.template(location),
+            Err(ContextEngineError::Parse { .. })
+        ));
+    }
+    let patterns = extractor.extract(&locations);
+    assert!(patterns.is_empty());
+    assert_eq!(patterns.unresolved, 2);
+}
+
+#[test]
+fn test_references_in_the_encoding_of_the_server() {
+    let uri = Uri::from_str("file:///project/src/lib.rs").unwrap();
+    let text = "fn f() {\n    log(\"🦀\", Level::Info);\n}\n";
+    let layer = SyntaxLayer::new();
+    layer.update(Arc::new(TextDocument::new(
+        uri.clone(),
+        "rust",
+        1,
+        text,
+        PositionEncodingKind::UTF16,
+    )));
+
+    // UTF-8 columns of `Level`, as a server that negotiated UTF-8 reports them
+    let range = Range::new(Position::new(1, 16), Position::new(1, 21));
+    let registry = LanguageRegistry::new();
+    let extractor = PatternExtractor::new(&layer, &registry, PositionEncodingKind::UTF8);
+    assert_eq!(
+        extractor.template(&Location::new(uri, range)).unwrap(),
+        "log(..., Level::Info);"
+    );
+}
@@ -0,0 +1,117 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+use crate::syntax::Language;
+
+/// Returns the template of the `occurrence`-th reference to `name` in a text.
+fn template_of(language: Language, text: &str, name: &str, occurrence: usize) -> String {
+    let start = text.match_indices(name).nth(occurrence).unwrap().0;
+    let reference = start..start + name.len();
+    let tree = SyntaxTree::parse(language, text);
+    let node = enclosing_node(&tree, reference.clone()).unwrap();
+    generalize(node, text, reference)
+}
+
+/// Returns the templates of all references to `name` in a text.
+fn templates(language: Language, text: &str, name: &str) -> String {
+    (0..text.matches(name).count())
+        .map(|occurrence| template_of(language, text, name, occurrence))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn test_rust_templates() {
+    let text = r#"
+fn main() -> Result<(), Error> {
+    let user = User::builder().name("Ada").email(email).build()?;
+    let admin = User::builder()
+        .name(name) // the display name
+        .email("admin@example.com")
+        .build()?;
+    match user.validate() {
+        Ok(valid) => println!("{valid}"),
+        Err(error) => return Err(error.into()),
+    }
+    users.push(User::new(1, "x"));
+    let users: Vec<User> = Vec::new();
+    impl Display for User {}
+    if let Some(user) = find::<User>(&mut ids[0]) { run(); }
+}
+"#;
+    insta::assert_snapshot!(templates(Language::Rust, text, "User"));
+}
+
+#[test]
+fn test_rust_reference_in_arguments() {
+    let text = "fn f() { send(&client, User::default(), 3, [a, b]); }";
+    insta::assert_snapshot!(template_of(Language::Rust, text, "User", 0), @"send(..., User::default(), ..., ...);");
+}
+
+#[test]
+fn test_typescript_templates() {
+    let text = r#"
+const client = new ApiClient({ baseUrl: url, retries: 3 });
+await client.get(`/users/${id}`).then((user) => render(user));
+export class Admin extends ApiClient {
+    private token = "";
+}
+"#;
+    insta::assert_snapshot!(templates(Language::TypeScript, text, "ApiClient"));
+}
+
+#[test]
+fn test_python_templates() {
+    let text = r#"
+session = Session(timeout=30)
+with Session() as session:
+    session.get(url)
+class Cached(Session):
+    pass
+@retry(times=3)
+def fetch(session: Session) -> None:
+    return session.get("https://example.com")
+"#;
+    insta::assert_snapshot!(templates(Language::Python, text, "Session"));
+}
+
+#[test]
+fn test_formatting_and_comments_are_ignored() {
+    let a = "fn f() { let x = Config::load(path, /* strict */ true)?; }";
+    let b = "fn f() {\n    let config =\n        Config::load( other_path,false ) ?;\n}";
+    assert_eq!(
+        template_of(Language::Rust, a, "Config", 0),
+        template_of(Language::Rust, b, "Config", 0)
+    );
+}
+
+#[test]
+fn test_top_level_fallback() {
+    let text = "#[derive(Parser)]\nstruct Args {}";
+    let reference = text.find("Parser").unwrap();
+    let tree = SyntaxTree::parse(Language::Rust, text);
+    let node = enclosing_node(&tree, reference..reference + 6).unwrap();
+    assert_eq!(
+        generalize(node, text, reference..reference + 6),
+        "#[derive(Parser)] struct Args {}"
+    );
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_templates_ignore_bindings_and_literals(
+        binding in "[a-z][a-z0-9_]{0,8}",
+        number in 0u32..1000,
+        text in "[a-z ]{0,10}",
+    ) {
+        prop_assume!(!matches!(binding.as_str(), "fn" | "if" | "in" | "let" | "mut" | "use" | "as" | "ref"));
+        let source = format!("fn f() {{ let {binding} = Item::new({number}, \"{text}\"); }}");
+        prop_assert_eq!(
+            template_of(Language::Rust, &source, "Item", 0),
+            "let ... = Item::new(...);"
+        );
+    }
+}
@@ -0,0 +1,46 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+#[test]
+fn test_patterns_are_ordered_by_count_then_template() {
+    let templates = ["b;", "a;", "c;", "c;", "b;", "c;"].map(String::from);
+    let patterns = UsagePatterns::from_templates(templates);
+    insta::assert_snapshot!(serde_json::to_string_pretty(&patterns).unwrap());
+    assert_eq!(patterns.total(), 6);
+}
+
+#[test]
+fn test_no_templates() {
+    let patterns = UsagePatterns::from_templates(Vec::new());
+    assert!(patterns.is_empty());
+    assert_eq!(
+        serde_json::to_value(&patterns).unwrap(),
+        serde_json::json!({ "usage_patterns": [] })
+    );
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_patterns_account_for_every_template(
+        templates in prop::collection::vec("[a-c]", 1..50),
+    ) {
+        let patterns = UsagePatterns::from_templates(templates.clone());
+        prop_assert_eq!(patterns.total(), templates.len());
+
+        let percent = patterns
+            .usage_patterns
+            .iter()
+            .map(|pattern| pattern.usage_frequency_percent)
+            .sum::<f64>();
+        prop_assert!((percent - 100.0).abs() <= 0.15);
+
+        for pair in patterns.usage_patterns.windows(2) {
+            let [a, b] = pair else { unreachable!() };
+            prop_assert!((b.count, &a.code_template) <= (a.count, &b.code_template));
+        }
+    }
+}
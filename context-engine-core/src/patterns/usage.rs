@@ -0,0 +1,99 @@
+//! Usage patterns and their frequencies.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A generalized piece of code and how often the references of a symbol
+/// follow it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsagePattern {
+    /// The statement around the references, with the code that varies
+    /// between them replaced by [`HOLE`](crate::patterns::HOLE)s
+    pub code_template: String,
+    /// The number of references that follow the template
+    pub count: usize,
+    /// The share of the references that follow the template, in percent
+    /// rounded to one decimal
+    pub usage_frequency_percent: f64,
+}
+
+/// The usage patterns of a symbol.
+///
+/// Patterns are ordered by descending count and then by template, so the
+/// same references always yield the same output.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::patterns::UsagePatterns;
+///
+/// let patterns = UsagePatterns::from_templates([
+///     "f(...);".to_string(),
+///     "let ... = f(...);".to_string(),
+///     "let ... = f(...);".to_string(),
+/// ]);
+/// let templates = patterns
+///     .usage_patterns
+///     .iter()
+///     .map(|pattern| (pattern.code_template.as_str(), pattern.usage_frequency_percent))
+///     .collect::<Vec<_>>();
+/// assert_eq!(templates, [("let ... = f(...);", 66.7), ("f(...);", 33.3)]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsagePatterns {
+    /// The patterns, most frequent first
+    pub usage_patterns: Vec<UsagePattern>,
+    /// The number of references that could not be resolved to a template and
+    /// are not counted in the patterns
+    #[serde(skip)]
+    pub unresolved: usize,
+}
+
+impl UsagePatterns {
+    /// Groups equal templates into patterns.
+    pub fn from_templates(templates: impl IntoIterator<Item = String>) -> Self {
+        let mut counts = HashMap::<String, usize>::new();
+        let mut total = 0;
+        for template in templates {
+            *counts.entry(template).or_default() += 1;
+            total += 1;
+        }
+
+        let mut usage_patterns = counts
+            .into_iter()
+            .map(|(code_template, count)| UsagePattern {
+                code_template,
+                count,
+                usage_frequency_percent: (count as f64 * 1000.0 / total as f64).round() / 10.0,
+            })
+            .collect::<Vec<_>>();
+        usage_patterns.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.code_template.cmp(&b.code_template))
+        });
+
+        Self {
+            usage_patterns,
+            unresolved: 0,
+        }
+    }
+
+    /// Returns the total number of references in the patterns.
+    pub fn total(&self) -> usize {
+        self.usage_patterns
+            .iter()
+            .map(|pattern| pattern.count)
+            .sum()
+    }
+
+    /// Returns true if there are no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.usage_patterns.is_empty()
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/usage.rs"]
+mod tests;
@@ -0,0 +1,131 @@
+//! Languages the syntax layer can parse.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A grammar of the syntax layer.
+///
+/// JavaScript and the JSX dialects share the [`Language::TypeScript`]
+/// grammar, which is a superset of them as far as the syntax layer is
+/// concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// Rust
+    Rust,
+    /// TypeScript, TSX, JavaScript and JSX
+    TypeScript,
+    /// Python
+    Python,
+}
+
+impl Language {
+    /// Returns the grammar for an LSP language identifier.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::syntax::Language;
+    ///
+    /// assert_eq!(Language::from_language_id("rust"), Some(Language::Rust));
+    /// assert_eq!(
+    ///     Language::from_language_id("javascriptreact"),
+    ///     Some(Language::TypeScript)
+    /// );
+    /// assert_eq!(Language::from_language_id("markdown"), None);
+    /// ```
+    pub fn from_language_id(language_id: &str) -> Option<Self> {
+        match language_id {
+            "rust" => Some(Language::Rust),
+            "typescript" | "typescriptreact" | "javascript" | "javascriptreact" => {
+                Some(Language::TypeScript)
+            }
+            "python" => Some(Language::Python),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the language.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::TypeScript => "typescript",
+            Language::Python => "python",
+        }
+    }
+
+    /// Returns true if `word` is a reserved keyword of the language.
+    ///
+    /// Contextual keywords such as `union` in Rust or `type` in TypeScript are
+    /// not reserved; the parser recognizes them by their position.
+    pub fn is_keyword(&self, word: &str) -> bool {
+        let keywords: &[&str] = match self {
+            Language::Rust => &[
+                "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else",
+                "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match",
+                "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct",
+                "super", "trait", "true", "type", "unsafe", "use", "where", "while", "yield",
+            ],
+            Language::TypeScript => &[
+                "break",
+                "case",
+                "catch",
+                "class",
+                "const",
+                "continue",
+                "debugger",
+                "default",
+                "delete",
+                "do",
+                "else",
+                "enum",
+                "export",
+                "extends",
+                "false",
+                "finally",
+                "for",
+                "function",
+                "if",
+                "implements",
+                "import",
+                "in",
+                "instanceof",
+                "interface",
+                "let",
+                "new",
+                "null",
+                "return",
+                "super",
+                "switch",
+                "this",
+                "throw",
+                "true",
+                "try",
+                "typeof",
+                "var",
+                "void",
+                "while",
+                "with",
+                "yield",
+            ],
+            Language::Python => &[
+                "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+                "continue", "def", "del", "elif", "else", "except", "finally", "for", "from",
+                "global", "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass",
+                "raise", "return", "try", "while", "with", "yield",
+            ],
+        };
+        keywords.contains(&word)
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/language.rs"]
+mod tests;
@@ -0,0 +1,221 @@
+//! Syntax trees of the open documents.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use lsp_types::{Position, Range, Uri};
+use parking_lot::RwLock;
+
+use crate::document::{DocumentStore, TextDocument};
+use crate::error::ContextEngineError;
+use crate::syntax::{InputEdit, Language, SyntaxNode, SyntaxTree};
+
+/// A syntax tree together with the document snapshot it was parsed from.
+#[derive(Debug)]
+pub struct SyntaxSnapshot {
+    document: Arc<TextDocument>,
+    tree: SyntaxTree,
+}
+
+impl SyntaxSnapshot {
+    /// Parses a document that is not kept in a [`SyntaxLayer`], e.g. a file
+    /// read from disk.
+    ///
+    /// # Returns
+    ///
+    /// The syntax snapshot of the document, or `None` if the language of the
+    /// document has no grammar.
+    pub fn parse(document: Arc<TextDocument>) -> Option<Self> {
+        let language = Language::from_language_id(document.language_id())?;
+        let tree = SyntaxTree::parse(language, document.text());
+        Some(Self { document, tree })
+    }
+
+    /// Returns the document the tree was parsed from.
+    pub fn document(&self) -> &Arc<TextDocument> {
+        &self.document
+    }
+
+    /// Returns the syntax tree.
+    pub fn tree(&self) -> &SyntaxTree {
+        &self.tree
+    }
+
+    /// Returns the LSP range of a node, in the encoding of the document.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::Location`] if the node is not from this
+    /// tree.
+    pub fn node_range(&self, node: &SyntaxNode) -> Result<Range, ContextEngineError> {
+        Ok(node.range(self.document.line_index(), self.document.encoding())?)
+    }
+
+    /// Returns the nodes that contain a position, from the root down to the
+    /// smallest one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::Location`] if the position is not in the
+    /// document.
+    pub fn ancestors_at(&self, position: Position) -> Result<Vec<&SyntaxNode>, ContextEngineError> {
+        let offset = self.document.offset(position)?;
+        Ok(self.tree.ancestors_for_byte_range(offset, offset))
+    }
+
+    /// Returns the nodes that contain a range, from the root down to the
+    /// smallest one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::Location`] if the range is not in the
+    /// document.
+    pub fn ancestors_for_range(
+        &self,
+        range: Range,
+    ) -> Result<Vec<&SyntaxNode>, ContextEngineError> {
+        Ok(self.tree.ancestors_for_range(
+            range,
+            self.document.line_index(),
+            self.document.encoding(),
+        )?)
+    }
+}
+
+/// Syntax trees of documents, kept up to date as the documents change.
+///
+/// Each document is parsed with the grammar of its language identifier.
+/// When a newer version of a document arrives, its tree is reparsed
+/// incrementally from the tree of the previous version. Documents in
+/// languages without a grammar are ignored.
+///
+/// The layer can be shared between threads.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::document::{DocumentStore, TextDocument};
+/// use context_engine_core::syntax::{SyntaxKind, SyntaxLayer};
+/// use context_engine_core::types::{Position, PositionEncodingKind, Uri};
+/// use std::str::FromStr;
+/// use std::sync::Arc;
+///
+/// let layer = SyntaxLayer::new();
+/// let uri = Uri::from_str("file:///src/lib.rs").unwrap();
+/// let document = TextDocument::new(
+///     uri.clone(),
+///     "rust",
+///     1,
+///     "fn main() {\n    run();\n}\n",
+///     PositionEncodingKind::UTF16,
+/// );
+///
+/// let snapshot = layer.update(Arc::new(document)).unwrap();
+/// let kinds = snapshot
+///     .ancestors_at(Position::new(1, 5))
+///     .unwrap()
+///     .iter()
+///     .map(|node| node.kind())
+///     .collect::<Vec<_>>();
+/// assert_eq!(
+///     kinds,
+///     [
+///         SyntaxKind::SourceFile,
+///         SyntaxKind::Function,
+///         SyntaxKind::Block,
+///         SyntaxKind::Statement,
+///         SyntaxKind::CallExpression,
+///         SyntaxKind::Identifier,
+///     ]
+/// );
+/// ```
+#[derive(Debug, Default)]
+pub struct SyntaxLayer {
+    snapshots: RwLock<HashMap<Uri, Arc<SyntaxSnapshot>>>,
+}
+
+impl SyntaxLayer {
+    /// Creates an empty layer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a document snapshot, reusing the tree of an older version of
+    /// the document.
+    ///
+    /// # Returns
+    ///
+    /// The syntax snapshot of the document, which is the existing one if it
+    /// is newer than `document` or has the same text, or `None` if the
+    /// language of the document has no grammar. A document with the version of
+    /// the existing snapshot but another text is parsed again.
+    pub fn update(&self, document: Arc<TextDocument>) -> Option<Arc<SyntaxSnapshot>> {
+        let Some(language) = Language::from_language_id(document.language_id()) else {
+            self.snapshots.write().remove(document.uri());
+            return None;
+        };
+
+        let previous = self.get(document.uri());
+        let tree = match &previous {
+            Some(previous)
+                if previous.document.language_id() == document.language_id()
+                    && (previous.document.version() > document.version()
+                        || (previous.document.version() == document.version()
+                            && previous.document.text() == document.text())) =>
+            {
+                return Some(previous.clone());
+            }
+            Some(previous) if previous.tree.language() == language => {
+                let edit = InputEdit::between(previous.document.text(), document.text());
+                previous.tree.reparse(document.text(), &edit)
+            }
+            _ => SyntaxTree::parse(language, document.text()),
+        };
+
+        let snapshot = Arc::new(SyntaxSnapshot { document, tree });
+        let mut snapshots = self.snapshots.write();
+        match snapshots.get(snapshot.document.uri()) {
+            // Another thread stored a newer version in the meantime
+            Some(current) if current.document.version() > snapshot.document.version() => {
+                Some(current.clone())
+            }
+            _ => {
+                snapshots.insert(snapshot.document.uri().clone(), snapshot.clone());
+                Some(snapshot)
+            }
+        }
+    }
+
+    /// Brings the layer in line with a document store: parses the open
+    /// documents and forgets the closed ones.
+    pub fn sync(&self, store: &DocumentStore) {
+        for document in store.snapshots() {
+            self.update(document);
+        }
+        self.snapshots.write().retain(|uri, _| store.is_open(uri));
+    }
+
+    /// Returns the syntax snapshot of a document.
+    pub fn get(&self, uri: &Uri) -> Option<Arc<SyntaxSnapshot>> {
+        self.snapshots.read().get(uri).cloned()
+    }
+
+    /// Forgets the tree of a document, e.g. when it is closed.
+    pub fn remove(&self, uri: &Uri) -> Option<Arc<SyntaxSnapshot>> {
+        self.snapshots.write().remove(uri)
+    }
+
+    /// Returns the number of documents with a tree.
+    pub fn len(&self) -> usize {
+        self.snapshots.read().len()
+    }
+
+    /// Returns true if no document has a tree.
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.read().is_empty()
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/layer.rs"]
+mod tests;
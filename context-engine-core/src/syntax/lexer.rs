@@ -0,0 +1,654 @@
+//! Tokenization of source text.
+
+use std::ops::Range;
+
+use crate::syntax::Language;
+
+/// A bracket pair, or an indented block in Python.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Delimiter {
+    /// `(...)`
+    Paren,
+    /// `[...]`
+    Bracket,
+    /// `{...}`
+    Brace,
+    /// The lines of a block indented deeper than its header
+    Indent,
+}
+
+/// The kind of a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    Identifier,
+    Keyword,
+    String,
+    Number,
+    Character,
+    Lifetime,
+    Comment,
+    Punctuation,
+    Open(Delimiter),
+    Close(Delimiter),
+    /// The end of a logical line in Python
+    Newline,
+    /// A character that cannot start a token
+    Error,
+}
+
+/// A token with its byte range in the source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+    /// Whether a line break precedes the token
+    pub newline_before: bool,
+}
+
+/// The tokens of a part of a source text.
+#[derive(Debug)]
+pub(crate) struct Tokens {
+    pub tokens: Vec<Token>,
+    /// Whether the tokens cover the part exactly: no string literal or
+    /// comment is left unterminated and no token extends beyond the part
+    pub complete: bool,
+}
+
+/// Splits `text[start..end]` into tokens, including the `Newline` and
+/// indentation tokens of Python.
+///
+/// `start` must be the start of a line or a token; tokenization stops at the
+/// first token that starts at or after `end`.
+pub(crate) fn tokenize(language: Language, text: &str, start: usize, end: usize) -> Tokens {
+    let mut lexer = Lexer::new(language, text, start);
+    let mut tokens = Vec::new();
+    let mut complete = true;
+    let mut layout = Layout::default();
+
+    while let Some(token) = lexer.next_token() {
+        if token.span.start >= end {
+            break;
+        }
+        complete &= token.span.end <= end && !lexer.unterminated;
+        if language == Language::Python {
+            layout.before(text, &token, &mut tokens);
+        }
+        tokens.push(token);
+    }
+
+    if language == Language::Python {
+        let end = tokens.last().map_or(start, |token| token.span.end);
+        layout.finish(end, &mut tokens);
+    }
+    Tokens { tokens, complete }
+}
+
+/// Returns the first token at or after `start` that is not a comment.
+pub(crate) fn next_significant(language: Language, text: &str, start: usize) -> Option<Token> {
+    let mut lexer = Lexer::new(language, text, start);
+    std::iter::from_fn(|| lexer.next_token()).find(|token| token.kind != TokenKind::Comment)
+}
+
+/// The indentation state of Python source.
+#[derive(Default)]
+struct Layout {
+    /// The columns of the enclosing indented blocks
+    indents: Vec<usize>,
+    /// The depth of brackets, within which line breaks are insignificant
+    depth: usize,
+    /// Whether the current logical line has tokens other than comments
+    pending_line: bool,
+    last_end: usize,
+}
+
+impl Layout {
+    /// Emits the layout tokens that precede `token`.
+    fn before(&mut self, text: &str, token: &Token, tokens: &mut Vec<Token>) {
+        if token.newline_before && self.depth == 0 {
+            if self.pending_line {
+                tokens.push(marker(TokenKind::Newline, self.last_end));
+                self.pending_line = false;
+            }
+            if token.kind != TokenKind::Comment {
+                let column = indentation(text, token.span.start);
+                let current = self.indents.last().copied().unwrap_or(0);
+                if column > current {
+                    self.indents.push(column);
+                    tokens.push(marker(TokenKind::Open(Delimiter::Indent), token.span.start));
+                }
+                while self.indents.last().is_some_and(|&indent| column < indent) {
+                    self.indents.pop();
+                    tokens.push(marker(
+                        TokenKind::Close(Delimiter::Indent),
+                        token.span.start,
+                    ));
+                }
+            }
+        }
+
+        match token.kind {
+            TokenKind::Open(_) => self.depth += 1,
+            TokenKind::Close(_) => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+        if token.kind != TokenKind::Comment {
+            self.pending_line = true;
+            self.last_end = token.span.end;
+        }
+    }
+
+    /// Emits the layout tokens at the end of the source.
+    fn finish(&mut self, end: usize, tokens: &mut Vec<Token>) {
+        if self.pending_line {
+            tokens.push(marker(TokenKind::Newline, self.last_end));
+        }
+        for _ in self.indents.drain(..) {
+            tokens.push(marker(TokenKind::Close(Delimiter::Indent), end));
+        }
+    }
+}
+
+/// Returns an empty token at `offset`.
+fn marker(kind: TokenKind, offset: usize) -> Token {
+    Token {
+        kind,
+        span: offset..offset,
+        newline_before: false,
+    }
+}
+
+/// Returns the column of `offset` in its line, with tabs advancing to the
+/// next multiple of 8.
+pub(crate) fn indentation(text: &str, offset: usize) -> usize {
+    let line = text.get(..offset).unwrap_or("");
+    let line = line.rsplit('\n').next().unwrap_or(line);
+    line.chars().fold(0, |column, c| match c {
+        '\t' => (column / 8 + 1) * 8,
+        _ => column + 1,
+    })
+}
+
+/// Multi-character operators, longest first.
+const RUST_OPERATORS: &[&str] = &[
+    "..=", "...", "<<=", ">>=", "::", "->", "=>", "==", "!=", "<=", ">=", "&&", "||", "+=", "-=",
+    "*=", "/=", "%=", "^=", "&=", "|=", "<<", ">>", "..",
+];
+const TYPESCRIPT_OPERATORS: &[&str] = &[
+    ">>>=", "===", "!==", "**=", "...", ">>>", "<<=", ">>=", "&&=", "||=", "??=", "=>", "==", "!=",
+    "<=", ">=", "&&", "||", "??", "?.", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+    "**", "<<", ">>",
+];
+const PYTHON_OPERATORS: &[&str] = &[
+    "**=", "//=", ">>=", "<<=", "...", "->", ":=", "**", "//", "==", "!=", "<=", ">=", "<<", ">>",
+    "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "@=",
+];
+
+/// Splits source text into tokens, skipping whitespace.
+struct Lexer<'a> {
+    language: Language,
+    text: &'a str,
+    pos: usize,
+    /// The kind of the last token, to tell regular expressions from divisions
+    last: Option<(TokenKind, &'a str)>,
+    /// Whether a string literal or comment was not terminated
+    unterminated: bool,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(language: Language, text: &'a str, start: usize) -> Self {
+        Self {
+            language,
+            text,
+            pos: start,
+            last: None,
+            unterminated: false,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        self.text.get(self.pos..).unwrap_or("")
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        if self.rest().starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_while(&mut self, predicate: impl Fn(char) -> bool) {
+        while let Some(c) = self.peek() {
+            if !predicate(c) {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let newline_before = self.skip_whitespace();
+        let start = self.pos;
+        let c = self.bump()?;
+
+        let kind = match self.language {
+            Language::Rust => self.rust_token(c),
+            Language::TypeScript => self.typescript_token(c),
+            Language::Python => self.python_token(c),
+        };
+        let kind = match kind {
+            Some(kind) => kind,
+            None if is_ident_start(c) => {
+                self.eat_while(is_ident_continue);
+                let word = self.text.get(start..self.pos).unwrap_or("");
+                if self.language.is_keyword(word) {
+                    TokenKind::Keyword
+                } else {
+                    TokenKind::Identifier
+                }
+            }
+            None if c.is_ascii_digit() => {
+                self.number();
+                TokenKind::Number
+            }
+            None => self.delimiter_or_operator(c),
+        };
+
+        let span = start..self.pos;
+        if kind != TokenKind::Comment {
+            self.last = Some((kind, self.text.get(span.clone()).unwrap_or("")));
+        }
+        Some(Token {
+            kind,
+            span,
+            newline_before,
+        })
+    }
+
+    /// Skips whitespace and returns true if it contains a line break.
+    fn skip_whitespace(&mut self) -> bool {
+        let mut newline = false;
+        loop {
+            match self.peek() {
+                Some('\n') => {
+                    newline = true;
+                    self.bump();
+                }
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                // Explicit line joining in Python
+                Some('\\')
+                    if self.language == Language::Python
+                        && (self.rest().starts_with("\\\n")
+                            || self.rest().starts_with("\\\r\n")) =>
+                {
+                    self.bump();
+                    self.eat("\r");
+                    self.bump();
+                }
+                _ => return newline,
+            }
+        }
+    }
+
+    fn rust_token(&mut self, c: char) -> Option<TokenKind> {
+        let kind = match c {
+            '/' if self.peek() == Some('/') => {
+                self.line_comment();
+                TokenKind::Comment
+            }
+            '/' if self.peek() == Some('*') => {
+                self.block_comment(true);
+                TokenKind::Comment
+            }
+            '"' => {
+                self.quoted('"');
+                TokenKind::String
+            }
+            'r' if matches!(self.peek(), Some('"' | '#')) && self.raw_string() => TokenKind::String,
+            'b' | 'c' if self.peek() == Some('"') => {
+                self.bump();
+                self.quoted('"');
+                TokenKind::String
+            }
+            'b' | 'c' if self.peek() == Some('r') && self.peek_nth(1) == Some('"') => {
+                self.bump();
+                self.raw_string();
+                TokenKind::String
+            }
+            'b' if self.peek() == Some('\'') => {
+                self.bump();
+                self.quoted('\'');
+                TokenKind::Character
+            }
+            '\'' => self.char_or_lifetime(),
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    fn typescript_token(&mut self, c: char) -> Option<TokenKind> {
+        let kind = match c {
+            '/' if self.peek() == Some('/') => {
+                self.line_comment();
+                TokenKind::Comment
+            }
+            '/' if self.peek() == Some('*') => {
+                self.block_comment(false);
+                TokenKind::Comment
+            }
+            '"' | '\'' => {
+                self.quoted(c);
+                TokenKind::String
+            }
+            '`' => {
+                self.template();
+                TokenKind::String
+            }
+            '/' if self.regex_allowed() => {
+                self.regex();
+                TokenKind::String
+            }
+            '$' | '#' if self.peek().is_some_and(is_ident_continue) || c == '$' => {
+                self.eat_while(is_ident_continue);
+                TokenKind::Identifier
+            }
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    fn python_token(&mut self, c: char) -> Option<TokenKind> {
+        let kind = match c {
+            '#' => {
+                self.line_comment();
+                TokenKind::Comment
+            }
+            '"' | '\'' => {
+                self.python_string(c);
+                TokenKind::String
+            }
+            _ if is_ident_start(c) => {
+                // String prefixes such as `r`, `b`, `f` or `rb`
+                let start = self.pos - c.len_utf8();
+                self.eat_while(is_ident_continue);
+                let word = self.text.get(start..self.pos).unwrap_or("");
+                let is_prefix = word.len() <= 2
+                    && word
+                        .chars()
+                        .all(|c| matches!(c.to_ascii_lowercase(), 'r' | 'b' | 'f' | 'u'));
+                match self.peek() {
+                    Some(quote @ ('"' | '\'')) if is_prefix => {
+                        self.bump();
+                        self.python_string(quote);
+                        TokenKind::String
+                    }
+                    _ if self.language.is_keyword(word) => TokenKind::Keyword,
+                    _ => TokenKind::Identifier,
+                }
+            }
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    fn delimiter_or_operator(&mut self, c: char) -> TokenKind {
+        match c {
+            '(' => return TokenKind::Open(Delimiter::Paren),
+            '[' => return TokenKind::Open(Delimiter::Bracket),
+            '{' => return TokenKind::Open(Delimiter::Brace),
+            ')' => return TokenKind::Close(Delimiter::Paren),
+            ']' => return TokenKind::Close(Delimiter::Bracket),
+            '}' => return TokenKind::Close(Delimiter::Brace),
+            _ => {}
+        }
+        if !c.is_ascii_punctuation() {
+            return TokenKind::Error;
+        }
+
+        let operators = match self.language {
+            Language::Rust => RUST_OPERATORS,
+            Language::TypeScript => TYPESCRIPT_OPERATORS,
+            Language::Python => PYTHON_OPERATORS,
+        };
+        let start = self.pos - c.len_utf8();
+        let rest = self.text.get(start..).unwrap_or("");
+        if let Some(operator) = operators.iter().find(|op| rest.starts_with(**op)) {
+            self.pos = start + operator.len();
+        }
+        TokenKind::Punctuation
+    }
+
+    fn line_comment(&mut self) {
+        self.eat_while(|c| c != '\n');
+    }
+
+    fn block_comment(&mut self, nested: bool) {
+        self.bump();
+        let mut depth = 1;
+        while depth > 0 {
+            if self.eat("*/") {
+                depth -= 1;
+            } else if nested && self.eat("/*") {
+                depth += 1;
+            } else if self.bump().is_none() {
+                self.unterminated = true;
+                return;
+            }
+        }
+    }
+
+    /// Scans the rest of a literal enclosed in `quote`, with `\` escapes.
+    fn quoted(&mut self, quote: char) {
+        loop {
+            match self.bump() {
+                Some('\\') => {
+                    self.bump();
+                }
+                Some(c) if c == quote => return,
+                // Only Rust strings span lines
+                Some('\n') if quote != '"' || self.language != Language::Rust => {
+                    self.unterminated = true;
+                    return;
+                }
+                Some(_) => {}
+                None => {
+                    self.unterminated = true;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Scans a raw string after its `r`, returning false if it is a raw
+    /// identifier instead.
+    fn raw_string(&mut self) -> bool {
+        let start = self.pos;
+        self.eat_while(|c| c == '#');
+        let hashes = self.pos - start;
+        if self.peek() != Some('"') {
+            if hashes == 1 && self.peek().is_some_and(is_ident_start) {
+                self.eat_while(is_ident_continue);
+                return false;
+            }
+            return true;
+        }
+        self.bump();
+
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        match self.rest().find(&terminator) {
+            Some(end) => self.pos += end + terminator.len(),
+            None => {
+                self.pos = self.text.len();
+                self.unterminated = true;
+            }
+        }
+        true
+    }
+
+    fn char_or_lifetime(&mut self) -> TokenKind {
+        match (self.peek(), self.peek_nth(1)) {
+            (Some('\\'), _) => {
+                self.quoted('\'');
+                TokenKind::Character
+            }
+            (Some(_), Some('\'')) => {
+                self.bump();
+                self.bump();
+                TokenKind::Character
+            }
+            (Some(c), _) if is_ident_start(c) => {
+                self.eat_while(is_ident_continue);
+                TokenKind::Lifetime
+            }
+            _ => TokenKind::Punctuation,
+        }
+    }
+
+    fn number(&mut self) {
+        let mut previous = '0';
+        let mut seen_dot = false;
+        loop {
+            match self.peek() {
+                Some(c) if is_ident_continue(c) => {
+                    previous = c;
+                    self.bump();
+                }
+                Some('.') if !seen_dot && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) => {
+                    seen_dot = true;
+                    previous = '.';
+                    self.bump();
+                }
+                Some('+' | '-')
+                    if matches!(previous, 'e' | 'E')
+                        && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) =>
+                {
+                    previous = '+';
+                    self.bump();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Scans the rest of a template literal, including its substitutions.
+    fn template(&mut self) {
+        loop {
+            match self.bump() {
+                Some('`') => return,
+                Some('\\') => {
+                    self.bump();
+                }
+                Some('$') if self.peek() == Some('{') => {
+                    self.bump();
+                    let mut depth = 1;
+                    while depth > 0 {
+                        match self.bump() {
+                            Some('{') => depth += 1,
+                            Some('}') => depth -= 1,
+                            Some('`') => self.template(),
+                            Some(quote @ ('"' | '\'')) => self.quoted(quote),
+                            Some(_) => {}
+                            None => {
+                                self.unterminated = true;
+                                return;
+                            }
+                        }
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    self.unterminated = true;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Returns true if a `/` starts a regular expression rather than a
+    /// division.
+    fn regex_allowed(&self) -> bool {
+        match self.last {
+            None => true,
+            Some((TokenKind::Punctuation | TokenKind::Open(_), _)) => true,
+            Some((TokenKind::Keyword, word)) => {
+                !matches!(word, "this" | "super" | "true" | "false" | "null")
+            }
+            Some(_) => false,
+        }
+    }
+
+    fn regex(&mut self) {
+        let mut in_class = false;
+        loop {
+            match self.bump() {
+                Some('\\') => {
+                    self.bump();
+                }
+                Some('[') => in_class = true,
+                Some(']') => in_class = false,
+                Some('/') if !in_class => break,
+                Some('\n') | None => {
+                    self.unterminated = true;
+                    return;
+                }
+                Some(_) => {}
+            }
+        }
+        self.eat_while(|c| c.is_ascii_alphabetic());
+    }
+
+    /// Scans the rest of a Python string, which may be triple-quoted.
+    fn python_string(&mut self, quote: char) {
+        let triple = if quote == '"' { "\"\"" } else { "''" };
+        if !self.eat(triple) {
+            self.quoted(quote);
+            return;
+        }
+
+        let terminator = if quote == '"' { "\"\"\"" } else { "'''" };
+        loop {
+            if self.eat(terminator) {
+                return;
+            }
+            match self.bump() {
+                Some('\\') => {
+                    self.bump();
+                }
+                Some(_) => {}
+                None => {
+                    self.unterminated = true;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c == '_' || c.is_alphabetic()
+}
+
+fn is_ident_continue(c: char) -> bool {
+    c == '_' || c.is_alphanumeric()
+}
+
+#[cfg(test)]
+#[path = "tests/lexer.rs"]
+mod tests;
@@ -0,0 +1,40 @@
+//! Syntax trees of source documents.
+//!
+//! Language servers answer questions about symbols, but some analyses need
+//! the shape of the code itself, e.g. the expression around a reference, and
+//! not every language has a server. This module parses the open documents
+//! with a grammar per language into concrete [`SyntaxTree`]s, keeps them in
+//! the [`SyntaxLayer`] and reparses them incrementally as edits arrive.
+//!
+//! The layer is meant to use Tree-sitter grammars, which cannot be built
+//! yet, so the trees come from a hand-written, error-tolerant parser
+//! instead. Only [`SyntaxTree::parse`] and [`SyntaxTree::reparse`] reach it,
+//! so Tree-sitter can replace it without changing the users of the trees.
+//!
+//! Trees address text in bytes and [`Point`]s, like Tree-sitter; both convert
+//! to and from the LSP [`Position`](crate::types::Position) of any encoding
+//! through the [`LineIndex`](crate::types::LineIndex) of the document.
+//!
+//! ## Structs
+//!
+//! * [`SyntaxLayer`] - Syntax trees of the open documents.
+//! * [`SyntaxSnapshot`] - A syntax tree with the document it was parsed from.
+//! * [`SyntaxTree`] - A concrete syntax tree of a source text.
+//! * [`SyntaxNode`] - A node of a syntax tree.
+//! * [`Point`] - A row and byte column in a text.
+//! * [`InputEdit`] - An edit of a text, in bytes.
+//!
+//! ## Enums
+//!
+//! * [`Language`] - The grammars of the layer: Rust, TypeScript and Python.
+//! * [`SyntaxKind`] - The kind of a syntax node.
+
+mod language;
+mod layer;
+mod lexer;
+mod parser;
+mod tree;
+
+pub use language::Language;
+pub use layer::{SyntaxLayer, SyntaxSnapshot};
+pub use tree::{InputEdit, Point, SyntaxKind, SyntaxNode, SyntaxTree};
@@ -0,0 +1,1492 @@
+//! An error-tolerant parser producing [`SyntaxNode`]s.
+//!
+//! Parsing happens in three steps:
+//!
+//! 1. The text is split into tokens by the [lexer](crate::syntax::lexer).
+//! 2. The tokens are grouped into token trees by their delimiters. A missing
+//!    closing delimiter closes its group at the end of the enclosing one, and a
+//!    closing delimiter without an opening one becomes an error token.
+//! 3. The token trees of a group are split into statements, which are
+//!    classified into items, and the rest of each statement is parsed into
+//!    postfix expressions such as calls and field accesses.
+//!
+//! Whether a statement ends only depends on its own tokens and on the next
+//! token, so the top-level statements of a text can be parsed one region at a
+//! time, which is what makes incremental reparsing possible.
+
+use std::ops::Range;
+
+use crate::syntax::lexer::{self, Delimiter, Token, TokenKind};
+use crate::syntax::{Language, SyntaxKind, SyntaxNode};
+
+/// Parses a whole source text.
+pub(crate) fn parse(language: Language, text: &str) -> SyntaxNode {
+    let tokens = lexer::tokenize(language, text, 0, text.len());
+    let (trees, _) = build(tokens.tokens);
+    let parser = Parser { language, text };
+    let statements = parser.split(Context::Items, &trees);
+    let children = parser.statement_nodes(Context::Items, &statements);
+    SyntaxNode::new(SyntaxKind::SourceFile, 0..text.len(), children)
+}
+
+/// Parses the top-level statements in `region` of a source text.
+///
+/// Returns `None` unless parsing the region yields exactly the top-level
+/// nodes a full parse of `text` would yield for it: the region must start at
+/// a statement, be balanced, and end with a statement that the token after
+/// the region does not continue.
+pub(crate) fn parse_region(
+    language: Language,
+    text: &str,
+    region: Range<usize>,
+) -> Option<Vec<SyntaxNode>> {
+    let tokens = lexer::tokenize(language, text, region.start, region.end);
+    let lookahead = lexer::next_significant(language, text, region.end);
+    if !tokens.complete {
+        return None;
+    }
+
+    match language {
+        // The lexer decides whether `/` starts a regular expression from the
+        // token before it, which is unknown at the edges of the region
+        Language::TypeScript => {
+            let starts_with_slash = |token: Option<&Token>| {
+                token.is_some_and(|token| {
+                    token.kind != TokenKind::Comment
+                        && text
+                            .get(token.span.clone())
+                            .is_some_and(|s| s.starts_with('/'))
+                })
+            };
+            if starts_with_slash(tokens.tokens.first()) || starts_with_slash(lookahead.as_ref()) {
+                return None;
+            }
+        }
+        // Indentation is only known to be reset at unindented lines
+        Language::Python => {
+            if region.start > 0 && lexer::indentation(text, region.start) > 0 {
+                return None;
+            }
+            if lookahead.as_ref().is_some_and(|token| {
+                !token.newline_before || lexer::indentation(text, token.span.start) > 0
+            }) {
+                return None;
+            }
+        }
+        Language::Rust => {}
+    }
+
+    let (trees, balanced) = build(tokens.tokens);
+    if !balanced {
+        return None;
+    }
+    let parser = Parser { language, text };
+    let statements = parser.split(Context::Items, &trees);
+    let last = statements
+        .iter()
+        .rev()
+        .find(|statement| !matches!(statement.as_slice(), [tree] if is_comment(tree)));
+    if let Some(last) = last {
+        let next = lookahead.as_ref().map(|token| parser.next_of_token(token));
+        if !parser.ends(Context::Items, last, next) {
+            return None;
+        }
+    }
+    Some(parser.statement_nodes(Context::Items, &statements))
+}
+
+/// A token, or a group of token trees enclosed in delimiters.
+#[derive(Debug)]
+enum Tree {
+    Token(Token),
+    Group {
+        delimiter: Delimiter,
+        open: Token,
+        /// The closing delimiter, if it is not missing
+        close: Option<Token>,
+        children: Vec<Tree>,
+    },
+}
+
+/// Groups tokens into token trees, returning whether all delimiters were
+/// balanced.
+fn build(tokens: Vec<Token>) -> (Vec<Tree>, bool) {
+    let mut root = Vec::new();
+    let mut stack: Vec<(Delimiter, Token, Vec<Tree>)> = Vec::new();
+    let mut balanced = true;
+
+    fn close(
+        root: &mut Vec<Tree>,
+        stack: &mut Vec<(Delimiter, Token, Vec<Tree>)>,
+        close: Option<Token>,
+    ) {
+        if let Some((delimiter, open, children)) = stack.pop() {
+            let group = Tree::Group {
+                delimiter,
+                open,
+                close,
+                children,
+            };
+            match stack.last_mut() {
+                Some((_, _, parent)) => parent.push(group),
+                None => root.push(group),
+            }
+        }
+    }
+
+    for token in tokens {
+        match token.kind {
+            TokenKind::Open(delimiter) => stack.push((delimiter, token, Vec::new())),
+            TokenKind::Close(delimiter) => {
+                if stack.iter().any(|(open, _, _)| *open == delimiter) {
+                    // Groups opened after the matching one miss their closing
+                    // delimiters
+                    while stack.last().is_some_and(|(open, _, _)| *open != delimiter) {
+                        balanced = false;
+                        close(&mut root, &mut stack, None);
+                    }
+                    close(&mut root, &mut stack, Some(token));
+                } else {
+                    balanced = false;
+                    let error = Tree::Token(Token {
+                        kind: TokenKind::Error,
+                        ..token
+                    });
+                    match stack.last_mut() {
+                        Some((_, _, parent)) => parent.push(error),
+                        None => root.push(error),
+                    }
+                }
+            }
+            _ => match stack.last_mut() {
+                Some((_, _, parent)) => parent.push(Tree::Token(token)),
+                None => root.push(Tree::Token(token)),
+            },
+        }
+    }
+    while !stack.is_empty() {
+        balanced = false;
+        close(&mut root, &mut stack, None);
+    }
+    (root, balanced)
+}
+
+fn is_comment(tree: &Tree) -> bool {
+    matches!(tree, Tree::Token(token) if token.kind == TokenKind::Comment)
+}
+
+fn is_newline(tree: &Tree) -> bool {
+    matches!(tree, Tree::Token(token) if token.kind == TokenKind::Newline)
+}
+
+/// Whether a tree matters to the structure of a statement.
+fn is_significant(tree: &Tree) -> bool {
+    !is_comment(tree) && !is_newline(tree)
+}
+
+fn delimiter(tree: Option<&Tree>) -> Option<Delimiter> {
+    match tree {
+        Some(Tree::Group { delimiter, .. }) => Some(*delimiter),
+        _ => None,
+    }
+}
+
+fn token_kind(tree: Option<&Tree>) -> Option<TokenKind> {
+    match tree {
+        Some(Tree::Token(token)) => Some(token.kind),
+        _ => None,
+    }
+}
+
+/// The kind of statements in a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    /// Statements and items of a source file, module or function body
+    Items,
+    /// Members of an `impl`, trait or class body
+    Members,
+    /// Fields of a struct, interface or object literal
+    Fields,
+    /// Variants of an enum
+    Variants,
+    /// Arms of a `match`
+    Arms,
+}
+
+/// How to parse the contents of a group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Body {
+    Statements(Context),
+    Expressions,
+}
+
+/// The first token of a tree, as seen when deciding whether a statement ends
+/// before it.
+#[derive(Debug, Clone, Copy)]
+struct Next<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    newline_before: bool,
+}
+
+/// A part of a statement with a role in its item.
+#[derive(Debug)]
+struct Label {
+    /// The indices of the trees in the statement
+    range: Range<usize>,
+    field: Option<&'static str>,
+    /// The kind of the node wrapping the trees, if they are not a single
+    /// node of their own
+    wrap: Option<SyntaxKind>,
+    body: Option<Body>,
+}
+
+impl Label {
+    fn field(index: usize, field: &'static str) -> Self {
+        Self {
+            range: index..index + 1,
+            field: Some(field),
+            wrap: None,
+            body: None,
+        }
+    }
+
+    fn body(index: usize, body: Body) -> Self {
+        Self {
+            body: Some(body),
+            ..Self::field(index, "body")
+        }
+    }
+
+    fn wrap(range: Range<usize>, kind: SyntaxKind, field: Option<&'static str>) -> Self {
+        Self {
+            range,
+            field,
+            wrap: Some(kind),
+            body: None,
+        }
+    }
+}
+
+/// The kind of a statement and the roles of its parts.
+#[derive(Debug)]
+struct Shape {
+    kind: SyntaxKind,
+    labels: Vec<Label>,
+}
+
+/// Leading keywords that may precede the keyword of a Rust item.
+const RUST_QUALIFIERS: &[&str] = &["const", "async", "unsafe", "extern", "default", "auto"];
+
+/// Rust keywords whose statements end with their first brace group.
+const RUST_BLOCK_ITEMS: &[&str] = &[
+    "fn",
+    "struct",
+    "enum",
+    "union",
+    "trait",
+    "impl",
+    "mod",
+    "extern",
+    "unsafe",
+    "loop",
+    "while",
+    "for",
+    "match",
+    "macro_rules",
+];
+
+/// Words that may precede the keyword or name of a TypeScript declaration.
+const TYPESCRIPT_MODIFIERS: &[&str] = &[
+    "export",
+    "default",
+    "declare",
+    "abstract",
+    "async",
+    "public",
+    "private",
+    "protected",
+    "static",
+    "readonly",
+    "override",
+    "accessor",
+    "get",
+    "set",
+];
+
+/// TypeScript keywords whose statements end with their last brace group.
+const TYPESCRIPT_BLOCK_ITEMS: &[&str] = &[
+    "function",
+    "class",
+    "interface",
+    "enum",
+    "namespace",
+    "module",
+    "for",
+    "while",
+    "switch",
+    "with",
+    "else",
+];
+
+struct Parser<'a> {
+    language: Language,
+    text: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn text(&self, tree: Option<&Tree>) -> &'a str {
+        match tree {
+            Some(Tree::Token(token)) => self.text.get(token.span.clone()).unwrap_or(""),
+            _ => "",
+        }
+    }
+
+    /// Returns true if the tree is a keyword, identifier or punctuation
+    /// spelled `word`.
+    fn is(&self, tree: Option<&Tree>, word: &str) -> bool {
+        matches!(
+            token_kind(tree),
+            Some(TokenKind::Keyword | TokenKind::Identifier | TokenKind::Punctuation)
+        ) && self.text(tree) == word
+    }
+
+    fn next_of_token(&self, token: &Token) -> Next<'a> {
+        Next {
+            kind: token.kind,
+            text: self.text.get(token.span.clone()).unwrap_or(""),
+            newline_before: token.newline_before,
+        }
+    }
+
+    fn next_of(&self, tree: &Tree) -> Next<'a> {
+        match tree {
+            Tree::Token(token) | Tree::Group { open: token, .. } => self.next_of_token(token),
+        }
+    }
+
+    /// Splits token trees into statements; comments before a statement are
+    /// statements of their own.
+    fn split<'t>(&self, context: Context, trees: &'t [Tree]) -> Vec<Vec<&'t Tree>> {
+        let mut statements = Vec::new();
+        let mut current = Vec::new();
+        for (index, tree) in trees.iter().enumerate() {
+            if current.is_empty() {
+                if is_comment(tree) {
+                    statements.push(vec![tree]);
+                    continue;
+                }
+                if is_newline(tree) {
+                    continue;
+                }
+            }
+            current.push(tree);
+            if is_comment(tree) {
+                continue;
+            }
+
+            let next = trees
+                .get(index + 1..)
+                .unwrap_or_default()
+                .iter()
+                .find(|tree| !is_comment(tree))
+                .map(|tree| self.next_of(tree));
+            if self.ends(context, &current, next) {
+                statements.push(std::mem::take(&mut current));
+            }
+        }
+        if !current.is_empty() {
+            statements.push(current);
+        }
+        statements
+    }
+
+    /// Returns true if a statement ends after its last tree, given the first
+    /// token after it that is not a comment.
+    fn ends(&self, context: Context, statement: &[&Tree], next: Option<Next<'_>>) -> bool {
+        match self.language {
+            Language::Rust => self.rust_ends(context, statement, next),
+            Language::TypeScript => self.typescript_ends(context, statement, next),
+            Language::Python => self.python_ends(statement, next),
+        }
+    }
+
+    fn rust_ends(&self, context: Context, statement: &[&Tree], next: Option<Next<'_>>) -> bool {
+        let significant = significant(statement);
+        let last = significant.last().copied();
+        match context {
+            Context::Fields | Context::Variants => self.is(last, ","),
+            Context::Arms => {
+                self.is(last, ",")
+                    || (delimiter(last) == Some(Delimiter::Brace)
+                        && self.is(
+                            significant
+                                .len()
+                                .checked_sub(2)
+                                .and_then(|i| significant.get(i))
+                                .copied(),
+                            "=>",
+                        ))
+            }
+            Context::Items | Context::Members => {
+                if self.is(last, ";") {
+                    return true;
+                }
+                // Inner attributes
+                if let [first, bang, group] = significant.as_slice() {
+                    if self.is(Some(*first), "#")
+                        && self.is(Some(*bang), "!")
+                        && delimiter(Some(*group)) == Some(Delimiter::Bracket)
+                    {
+                        return true;
+                    }
+                }
+                if delimiter(last) != Some(Delimiter::Brace) {
+                    return false;
+                }
+                match self.rust_head(&significant) {
+                    RustHead::Word(_, "if") => next.map_or(true, |next| next.text != "else"),
+                    RustHead::Word(_, word) => RUST_BLOCK_ITEMS.contains(&word),
+                    RustHead::Macro | RustHead::Block => true,
+                    RustHead::Other => false,
+                }
+            }
+        }
+    }
+
+    /// Returns the index and keyword that determine the kind of a Rust
+    /// statement, skipping attributes, visibility and qualifiers.
+    fn rust_head(&self, significant: &[&Tree]) -> RustHead<'a> {
+        let at = |index: usize| significant.get(index).copied();
+        let mut index = 0;
+        loop {
+            if self.is(at(index), "#") && delimiter(at(index + 1)) == Some(Delimiter::Bracket) {
+                index += 2;
+            } else if self.is(at(index), "pub") {
+                index += 1;
+                if delimiter(at(index)) == Some(Delimiter::Paren) {
+                    index += 1;
+                }
+            } else {
+                break;
+            }
+        }
+
+        match at(index) {
+            Some(Tree::Group {
+                delimiter: Delimiter::Brace,
+                ..
+            }) if index == 0 => return RustHead::Block,
+            Some(Tree::Token(token)) if token.kind == TokenKind::Identifier => {
+                // A macro invocation or definition such as `a::b!` or
+                // `macro_rules! name`
+                let mut end = index + 1;
+                while self.is(at(end), "::")
+                    && token_kind(at(end + 1)) == Some(TokenKind::Identifier)
+                {
+                    end += 2;
+                }
+                if self.is(at(end), "!") {
+                    return match self.text(at(index)) {
+                        "macro_rules" => RustHead::Word(index, "macro_rules"),
+                        _ => RustHead::Macro,
+                    };
+                }
+            }
+            _ => {}
+        }
+
+        let mut item = index;
+        while RUST_QUALIFIERS.contains(&self.text(at(item)))
+            || (token_kind(at(item)) == Some(TokenKind::String)
+                && self.is(item.checked_sub(1).and_then(at), "extern"))
+        {
+            item += 1;
+        }
+        match self.text(at(item)) {
+            word @ ("fn" | "impl" | "trait" | "mod" | "struct" | "enum" | "union" | "type"
+            | "static" | "use" | "crate")
+                if item > index =>
+            {
+                RustHead::Word(item, word)
+            }
+            _ => match token_kind(at(index)) {
+                Some(TokenKind::Keyword | TokenKind::Identifier) => {
+                    RustHead::Word(index, self.text(at(index)))
+                }
+                _ => RustHead::Other,
+            },
+        }
+    }
+
+    fn typescript_ends(
+        &self,
+        context: Context,
+        statement: &[&Tree],
+        next: Option<Next<'_>>,
+    ) -> bool {
+        let significant = significant(statement);
+        let last = significant.last().copied();
+        if self.is(last, ";") {
+            return context != Context::Variants;
+        }
+        if self.is(last, ",") && matches!(context, Context::Fields | Context::Variants) {
+            return true;
+        }
+        if context == Context::Variants {
+            return false;
+        }
+
+        if delimiter(last) == Some(Delimiter::Brace) {
+            let head = self.typescript_head(&significant);
+            let at = |index: usize| significant.get(index).copied();
+            match context {
+                Context::Members if !significant.iter().any(|&tree| self.is(Some(tree), "=")) => {
+                    return true;
+                }
+                Context::Items => match self.text(at(head)) {
+                    "if" => return next.map_or(true, |next| next.text != "else"),
+                    "try" | "catch" => {
+                        return next.map_or(true, |next| !matches!(next.text, "catch" | "finally"));
+                    }
+                    "finally" => return true,
+                    word if TYPESCRIPT_BLOCK_ITEMS.contains(&word) => return true,
+                    _ if head == 0 && delimiter(at(0)) == Some(Delimiter::Brace) => return true,
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        self.automatic_semicolon(&significant, next)
+    }
+
+    /// Returns the index of the first tree of a TypeScript statement after
+    /// its decorators and modifiers.
+    fn typescript_head(&self, significant: &[&Tree]) -> usize {
+        let at = |index: usize| significant.get(index).copied();
+        let mut index = 0;
+        while self.is(at(index), "@") {
+            index += 1;
+            if token_kind(at(index)) == Some(TokenKind::Identifier) {
+                index += 1;
+                while self.is(at(index), ".")
+                    && token_kind(at(index + 1)) == Some(TokenKind::Identifier)
+                {
+                    index += 2;
+                }
+            }
+            if delimiter(at(index)) == Some(Delimiter::Paren) {
+                index += 1;
+            }
+        }
+        while TYPESCRIPT_MODIFIERS.contains(&self.text(at(index)))
+            && at(index + 1).is_some_and(|next| {
+                token_kind(Some(next)) != Some(TokenKind::Punctuation)
+                    && delimiter(Some(next)) != Some(Delimiter::Paren)
+            })
+        {
+            index += 1;
+        }
+        if self.is(at(index), "const") && self.is(at(index + 1), "enum") {
+            index += 1;
+        }
+        index
+    }
+
+    /// Returns true if a TypeScript statement ends at a line break by
+    /// automatic semicolon insertion.
+    fn automatic_semicolon(&self, significant: &[&Tree], next: Option<Next<'_>>) -> bool {
+        let Some(next) = next else {
+            return true;
+        };
+        if !next.newline_before || self.is(significant.first().copied(), "@") {
+            return false;
+        }
+        let last = significant.last().copied();
+        let can_end = match last {
+            Some(Tree::Group { .. }) => true,
+            Some(Tree::Token(token)) => match token.kind {
+                TokenKind::Identifier
+                | TokenKind::String
+                | TokenKind::Number
+                | TokenKind::Character => true,
+                TokenKind::Keyword => matches!(
+                    self.text(last),
+                    "this" | "super" | "null" | "true" | "false" | "return" | "break" | "continue"
+                ),
+                TokenKind::Punctuation => matches!(self.text(last), "++" | "--"),
+                _ => false,
+            },
+            None => false,
+        };
+        let continues = match next.kind {
+            TokenKind::Open(Delimiter::Paren | Delimiter::Bracket) => true,
+            TokenKind::Punctuation => !matches!(next.text, "++" | "--" | "!" | "~" | "@" | "#"),
+            TokenKind::Keyword => {
+                matches!(next.text, "in" | "instanceof" | "extends" | "implements")
+            }
+            _ => false,
+        };
+        can_end && !continues
+    }
+
+    fn python_ends(&self, statement: &[&Tree], next: Option<Next<'_>>) -> bool {
+        let last = statement
+            .iter()
+            .rev()
+            .find(|tree| !is_comment(tree))
+            .copied();
+        if delimiter(last) == Some(Delimiter::Indent) {
+            return true;
+        }
+        if !last.is_some_and(is_newline) {
+            return false;
+        }
+
+        // Decorators continue on the next line
+        let mut line_start = true;
+        let mut decorator = false;
+        for tree in statement.iter().filter(|tree| !is_comment(tree)) {
+            if is_newline(tree) {
+                line_start = true;
+            } else if line_start {
+                decorator = self.is(Some(*tree), "@");
+                line_start = false;
+            }
+        }
+        !decorator && next.map_or(true, |next| next.kind != TokenKind::Open(Delimiter::Indent))
+    }
+
+    /// Returns the nodes of statements.
+    fn statement_nodes(&self, context: Context, statements: &[Vec<&Tree>]) -> Vec<SyntaxNode> {
+        statements
+            .iter()
+            .flat_map(|statement| self.statement(context, statement))
+            .collect()
+    }
+
+    /// Returns the node of a statement, and the separator after it in lists.
+    fn statement(&self, context: Context, statement: &[&Tree]) -> Vec<SyntaxNode> {
+        let mut trees = statement;
+        let mut separator = None;
+        if matches!(context, Context::Fields | Context::Variants) {
+            if let Some((last, rest)) = trees.split_last() {
+                if self.is(Some(*last), ",") || self.is(Some(*last), ";") {
+                    separator = self.node(last, None);
+                    trees = rest;
+                }
+            }
+        }
+        if let [tree] = trees {
+            if is_comment(tree) {
+                return self.node(tree, None).into_iter().collect();
+            }
+        }
+
+        let shape = match self.language {
+            Language::Rust => self.rust_shape(context, trees),
+            Language::TypeScript => self.typescript_shape(context, trees),
+            Language::Python => self.python_shape(context, trees),
+        };
+        let mut children = Vec::new();
+        let mut position = 0;
+        for label in shape.labels {
+            if label.range.start < position {
+                continue;
+            }
+            children.extend(
+                self.expressions(trees.get(position..label.range.start).unwrap_or_default()),
+            );
+            let inner = trees.get(label.range.clone()).unwrap_or_default();
+            let node = match (label.wrap, inner) {
+                (Some(kind), _) => {
+                    let nodes = self.expressions(inner);
+                    (!nodes.is_empty()).then(|| SyntaxNode::wrap(kind, nodes))
+                }
+                (None, [tree]) => self.node(tree, label.body),
+                (None, _) => None,
+            };
+            if let Some(node) = node {
+                children.push(match label.field {
+                    Some(field) => node.with_field(field),
+                    None => node,
+                });
+            }
+            position = label.range.end;
+        }
+        children.extend(self.expressions(trees.get(position..).unwrap_or_default()));
+
+        let mut nodes = Vec::new();
+        if !children.is_empty() {
+            nodes.push(SyntaxNode::wrap(shape.kind, children));
+        }
+        nodes.extend(separator);
+        nodes
+    }
+
+    fn rust_shape(&self, context: Context, trees: &[&Tree]) -> Shape {
+        let (significant, indices) = significant_indices(trees);
+        let at = |index: usize| significant.get(index).copied();
+        let index_of = |index: usize| indices.get(index).copied().unwrap_or(trees.len());
+        let mut labels = Vec::new();
+
+        if let [first, bang, group] = significant.as_slice() {
+            if self.is(Some(*first), "#")
+                && self.is(Some(*bang), "!")
+                && delimiter(Some(*group)) == Some(Delimiter::Bracket)
+            {
+                return Shape {
+                    kind: SyntaxKind::Attribute,
+                    labels,
+                };
+            }
+        }
+
+        let mut start = 0;
+        loop {
+            if self.is(at(start), "#") && delimiter(at(start + 1)) == Some(Delimiter::Bracket) {
+                labels.push(Label::wrap(
+                    index_of(start)..index_of(start + 1) + 1,
+                    SyntaxKind::Attribute,
+                    None,
+                ));
+                start += 2;
+            } else if self.is(at(start), "pub") {
+                start += 1;
+                if delimiter(at(start)) == Some(Delimiter::Paren) {
+                    start += 1;
+                }
+            } else {
+                break;
+            }
+        }
+        let name_at = |index: usize, labels: &mut Vec<Label>| {
+            if token_kind(at(index)) == Some(TokenKind::Identifier) {
+                labels.push(Label::field(index_of(index), "name"));
+            }
+        };
+        let body_at = |body: Body, labels: &mut Vec<Label>| {
+            if delimiter(significant.last().copied()) == Some(Delimiter::Brace)
+                && significant.len() > start + 1
+            {
+                labels.push(Label::body(index_of(significant.len() - 1), body));
+            }
+        };
+
+        let kind = match context {
+            Context::Fields => {
+                if token_kind(at(start)) == Some(TokenKind::Identifier)
+                    && (self.is(at(start + 1), ":") || significant.len() == start + 1)
+                {
+                    name_at(start, &mut labels);
+                    SyntaxKind::Field
+                } else {
+                    SyntaxKind::Statement
+                }
+            }
+            Context::Variants => {
+                if token_kind(at(start)) == Some(TokenKind::Identifier) {
+                    name_at(start, &mut labels);
+                    if delimiter(at(start + 1)) == Some(Delimiter::Brace) {
+                        labels.push(Label::body(
+                            index_of(start + 1),
+                            Body::Statements(Context::Fields),
+                        ));
+                    }
+                    SyntaxKind::Variant
+                } else {
+                    SyntaxKind::Statement
+                }
+            }
+            Context::Arms => SyntaxKind::Statement,
+            Context::Items | Context::Members => match self.rust_head(&significant) {
+                RustHead::Word(head, "fn") => {
+                    name_at(head + 1, &mut labels);
+                    if delimiter(at(head + 2)) == Some(Delimiter::Paren) {
+                        labels.push(Label::field(index_of(head + 2), "parameters"));
+                    } else if let Some(parameters) = (head + 2..significant.len())
+                        .find(|&index| delimiter(at(index)) == Some(Delimiter::Paren))
+                    {
+                        labels.push(Label::field(index_of(parameters), "parameters"));
+                    }
+                    body_at(Body::Statements(Context::Items), &mut labels);
+                    match context {
+                        Context::Members => SyntaxKind::Method,
+                        _ => SyntaxKind::Function,
+                    }
+                }
+                RustHead::Word(head, word @ ("struct" | "union")) => {
+                    name_at(head + 1, &mut labels);
+                    body_at(Body::Statements(Context::Fields), &mut labels);
+                    match word {
+                        "union" => SyntaxKind::Union,
+                        _ => SyntaxKind::Struct,
+                    }
+                }
+                RustHead::Word(head, "enum") => {
+                    name_at(head + 1, &mut labels);
+                    body_at(Body::Statements(Context::Variants), &mut labels);
+                    SyntaxKind::Enum
+                }
+                RustHead::Word(head, "trait") => {
+                    name_at(head + 1, &mut labels);
+                    body_at(Body::Statements(Context::Members), &mut labels);
+                    SyntaxKind::Trait
+                }
+                RustHead::Word(head, "impl") => {
+                    self.impl_labels(&significant, head, &index_of, &mut labels);
+                    body_at(Body::Statements(Context::Members), &mut labels);
+                    SyntaxKind::Impl
+                }
+                RustHead::Word(head, "mod") => {
+                    name_at(head + 1, &mut labels);
+                    body_at(Body::Statements(Context::Items), &mut labels);
+                    SyntaxKind::Module
+                }
+                RustHead::Word(head, "const") => {
+                    name_at(head + 1, &mut labels);
+                    SyntaxKind::Constant
+                }
+                RustHead::Word(head, "static") => {
+                    let name = if self.is(at(head + 1), "mut") {
+                        head + 2
+                    } else {
+                        head + 1
+                    };
+                    name_at(name, &mut labels);
+                    SyntaxKind::Static
+                }
+                RustHead::Word(head, "type") => {
+                    name_at(head + 1, &mut labels);
+                    SyntaxKind::TypeAlias
+                }
+                RustHead::Word(head, "macro_rules") => {
+                    name_at(head + 2, &mut labels);
+                    body_at(Body::Expressions, &mut labels);
+                    SyntaxKind::Macro
+                }
+                RustHead::Word(_, "use" | "crate") => SyntaxKind::Import,
+                RustHead::Word(head, "let") => {
+                    let mut name = head + 1;
+                    while self.is(at(name), "mut") || self.is(at(name), "ref") {
+                        name += 1;
+                    }
+                    if !self.is(at(name + 1), "::") && delimiter(at(name + 1)).is_none() {
+                        name_at(name, &mut labels);
+                    }
+                    SyntaxKind::Variable
+                }
+                RustHead::Word(_, "extern") => {
+                    body_at(Body::Statements(Context::Items), &mut labels);
+                    SyntaxKind::Statement
+                }
+                _ => SyntaxKind::Statement,
+            },
+        };
+        Shape { kind, labels }
+    }
+
+    /// Labels the trait and the type of an `impl` block.
+    fn impl_labels(
+        &self,
+        significant: &[&Tree],
+        head: usize,
+        index_of: &dyn Fn(usize) -> usize,
+        labels: &mut Vec<Label>,
+    ) {
+        let at = |index: usize| significant.get(index).copied();
+        let mut start = head + 1;
+        if self.is(at(start), "<") {
+            start = self.skip_generics(significant, start);
+        }
+        if self.is(at(start), "!") {
+            start += 1;
+        }
+        let end = match delimiter(significant.last().copied()) {
+            Some(Delimiter::Brace) => significant.len() - 1,
+            _ => significant.len(),
+        };
+        let end = (start..end)
+            .find(|&index| self.is(at(index), "where"))
+            .unwrap_or(end);
+
+        let mut depth = 0i32;
+        let mut separator = None;
+        for index in start..end {
+            match self.text(at(index)) {
+                "<" => depth += 1,
+                ">" => depth -= 1,
+                ">>" => depth -= 2,
+                "for" if depth == 0 && index > start => {
+                    separator = Some(index);
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        let mut wrap = |range: Range<usize>, field: &'static str| {
+            if range.start < range.end {
+                labels.push(Label::wrap(
+                    index_of(range.start)..index_of(range.end - 1) + 1,
+                    SyntaxKind::Type,
+                    Some(field),
+                ));
+            }
+        };
+        match separator {
+            Some(separator) => {
+                wrap(start..separator, "trait");
+                wrap(separator + 1..end, "type");
+            }
+            None => wrap(start..end, "type"),
+        }
+    }
+
+    /// Returns the index after the generic parameters starting at `start`.
+    fn skip_generics(&self, significant: &[&Tree], start: usize) -> usize {
+        let mut depth = 0i32;
+        for (index, &tree) in significant.iter().enumerate().skip(start) {
+            match self.text(Some(tree)) {
+                "<" => depth += 1,
+                ">" => depth -= 1,
+                ">>" => depth -= 2,
+                _ => {}
+            }
+            if depth <= 0 {
+                return index + 1;
+            }
+        }
+        significant.len()
+    }
+
+    fn typescript_shape(&self, context: Context, trees: &[&Tree]) -> Shape {
+        let (significant, indices) = significant_indices(trees);
+        let at = |index: usize| significant.get(index).copied();
+        let index_of = |index: usize| indices.get(index).copied().unwrap_or(trees.len());
+        let mut labels = Vec::new();
+
+        // Decorators
+        let mut index = 0;
+        while self.is(at(index), "@") {
+            let start = index;
+            index += 1;
+            if token_kind(at(index)) == Some(TokenKind::Identifier) {
+                index += 1;
+                while self.is(at(index), ".")
+                    && token_kind(at(index + 1)) == Some(TokenKind::Identifier)
+                {
+                    index += 2;
+                }
+            }
+            if delimiter(at(index)) == Some(Delimiter::Paren) {
+                index += 1;
+            }
+            labels.push(Label::wrap(
+                index_of(start)..index_of(index - 1) + 1,
+                SyntaxKind::Attribute,
+                None,
+            ));
+        }
+        let head = self.typescript_head(&significant);
+        let is_name = |index: usize| token_kind(at(index)) == Some(TokenKind::Identifier);
+        let name_at = |index: usize, labels: &mut Vec<Label>| {
+            if is_name(index) {
+                labels.push(Label::field(index_of(index), "name"));
+            }
+        };
+        let parameters_at = |from: usize, labels: &mut Vec<Label>| {
+            if let Some(parameters) = (from..significant.len())
+                .find(|&index| delimiter(at(index)) == Some(Delimiter::Paren))
+            {
+                labels.push(Label::field(index_of(parameters), "parameters"));
+            }
+        };
+        let body_at = |body: Body, labels: &mut Vec<Label>| {
+            if delimiter(significant.last().copied()) == Some(Delimiter::Brace)
+                && significant.len() > head + 1
+            {
+                labels.push(Label::body(index_of(significant.len() - 1), body));
+            }
+        };
+
+        let kind = match context {
+            Context::Variants => {
+                if matches!(
+                    token_kind(at(head)),
+                    Some(TokenKind::Identifier | TokenKind::String)
+                ) {
+                    labels.push(Label::field(index_of(head), "name"));
+                    SyntaxKind::Variant
+                } else {
+                    SyntaxKind::Statement
+                }
+            }
+            Context::Fields | Context::Members => {
+                let name = if self.is(at(head), "*") {
+                    head + 1
+                } else {
+                    head
+                };
+                let mut after = name + 1;
+                if self.is(at(after), "?") || self.is(at(after), "!") {
+                    after += 1;
+                }
+                let is_member_name = matches!(
+                    token_kind(at(name)),
+                    Some(TokenKind::Identifier | TokenKind::String | TokenKind::Number)
+                );
+                if is_member_name
+                    && (delimiter(at(after)) == Some(Delimiter::Paren) || self.is(at(after), "<"))
+                {
+                    labels.push(Label::field(index_of(name), "name"));
+                    parameters_at(after, &mut labels);
+                    body_at(Body::Statements(Context::Items), &mut labels);
+                    SyntaxKind::Method
+                } else if is_member_name
+                    && (at(after).is_none()
+                        || [":", "=", ";", ","].iter().any(|s| self.is(at(after), s)))
+                {
+                    labels.push(Label::field(index_of(name), "name"));
+                    SyntaxKind::Field
+                } else {
+                    SyntaxKind::Statement
+                }
+            }
+            Context::Items | Context::Arms => match (self.text(at(head)), token_kind(at(head))) {
+                ("function", Some(TokenKind::Keyword)) => {
+                    let name = if self.is(at(head + 1), "*") {
+                        head + 2
+                    } else {
+                        head + 1
+                    };
+                    name_at(name, &mut labels);
+                    parameters_at(name, &mut labels);
+                    body_at(Body::Statements(Context::Items), &mut labels);
+                    SyntaxKind::Function
+                }
+                ("class", _) => {
+                    name_at(head + 1, &mut labels);
+                    body_at(Body::Statements(Context::Members), &mut labels);
+                    SyntaxKind::Class
+                }
+                ("interface", _) => {
+                    name_at(head + 1, &mut labels);
+                    body_at(Body::Statements(Context::Fields), &mut labels);
+                    SyntaxKind::Interface
+                }
+                ("enum", _) => {
+                    name_at(head + 1, &mut labels);
+                    body_at(Body::Statements(Context::Variants), &mut labels);
+                    SyntaxKind::Enum
+                }
+                ("namespace" | "module", Some(TokenKind::Identifier))
+                    if matches!(
+                        token_kind(at(head + 1)),
+                        Some(TokenKind::Identifier | TokenKind::String)
+                    ) =>
+                {
+                    labels.push(Label::field(index_of(head + 1), "name"));
+                    body_at(Body::Statements(Context::Items), &mut labels);
+                    SyntaxKind::Module
+                }
+                ("type", Some(TokenKind::Identifier)) if is_name(head + 1) => {
+                    name_at(head + 1, &mut labels);
+                    SyntaxKind::TypeAlias
+                }
+                ("import", _)
+                    if !self.is(at(head + 1), ".")
+                        && delimiter(at(head + 1)) != Some(Delimiter::Paren) =>
+                {
+                    SyntaxKind::Import
+                }
+                ("const" | "let" | "var", _) => {
+                    name_at(head + 1, &mut labels);
+                    SyntaxKind::Variable
+                }
+                _ => SyntaxKind::Statement,
+            },
+        };
+        Shape { kind, labels }
+    }
+
+    fn python_shape(&self, context: Context, trees: &[&Tree]) -> Shape {
+        let (significant, indices) = significant_indices(trees);
+        let at = |index: usize| significant.get(index).copied();
+        let index_of = |index: usize| indices.get(index).copied().unwrap_or(trees.len());
+        let mut labels = Vec::new();
+
+        // Decorators, each on a line of its own
+        let mut head = 0;
+        while self.is(at(head), "@") {
+            let start = index_of(head);
+            let end = (start..trees.len())
+                .find(|&index| trees.get(index).is_some_and(|tree| is_newline(tree)))
+                .unwrap_or(trees.len());
+            labels.push(Label::wrap(start..end, SyntaxKind::Attribute, None));
+            head = indices
+                .iter()
+                .position(|&index| index > end)
+                .unwrap_or(significant.len());
+        }
+        if self.is(at(head), "async") {
+            head += 1;
+        }
+
+        let name_at = |index: usize, labels: &mut Vec<Label>| {
+            if token_kind(at(index)) == Some(TokenKind::Identifier) {
+                labels.push(Label::field(index_of(index), "name"));
+            }
+        };
+        let body_at = |body: Body, labels: &mut Vec<Label>| {
+            if delimiter(significant.last().copied()) == Some(Delimiter::Indent) {
+                labels.push(Label::body(index_of(significant.len() - 1), body));
+            }
+        };
+
+        let kind = match self.text(at(head)) {
+            "def" => {
+                name_at(head + 1, &mut labels);
+                if delimiter(at(head + 2)) == Some(Delimiter::Paren) {
+                    labels.push(Label::field(index_of(head + 2), "parameters"));
+                }
+                body_at(Body::Statements(Context::Items), &mut labels);
+                match context {
+                    Context::Members => SyntaxKind::Method,
+                    _ => SyntaxKind::Function,
+                }
+            }
+            "class" => {
+                name_at(head + 1, &mut labels);
+                body_at(Body::Statements(Context::Members), &mut labels);
+                SyntaxKind::Class
+            }
+            "import" | "from" => SyntaxKind::Import,
+            _ if token_kind(at(head)) == Some(TokenKind::Identifier)
+                && (self.is(at(head + 1), "=") || self.is(at(head + 1), ":")) =>
+            {
+                name_at(head, &mut labels);
+                match context {
+                    Context::Members => SyntaxKind::Field,
+                    _ => SyntaxKind::Variable,
+                }
+            }
+            _ => SyntaxKind::Statement,
+        };
+        Shape { kind, labels }
+    }
+
+    /// Returns the node of a single tree, parsing the contents of a group as
+    /// `body`, or as its default body if `None`.
+    fn node(&self, tree: &Tree, body: Option<Body>) -> Option<SyntaxNode> {
+        match tree {
+            Tree::Token(token) => self.leaf(token),
+            Tree::Group { delimiter, .. } => {
+                let body = body.unwrap_or_else(|| self.default_body(*delimiter, None, false));
+                Some(self.group(tree, body))
+            }
+        }
+    }
+
+    fn leaf(&self, token: &Token) -> Option<SyntaxNode> {
+        let kind = match token.kind {
+            TokenKind::Identifier => SyntaxKind::Identifier,
+            TokenKind::Keyword => SyntaxKind::Keyword,
+            TokenKind::String => SyntaxKind::String,
+            TokenKind::Number => SyntaxKind::Number,
+            TokenKind::Character => SyntaxKind::Character,
+            TokenKind::Lifetime => SyntaxKind::Lifetime,
+            TokenKind::Comment => SyntaxKind::Comment,
+            TokenKind::Punctuation | TokenKind::Open(_) | TokenKind::Close(_) => {
+                SyntaxKind::Punctuation
+            }
+            TokenKind::Error => SyntaxKind::Error,
+            TokenKind::Newline => return None,
+        };
+        Some(SyntaxNode::new(kind, token.span.clone(), Vec::new()))
+    }
+
+    /// Returns how to parse a group in an expression, given the tree before
+    /// it.
+    fn default_body(
+        &self,
+        delimiter: Delimiter,
+        previous: Option<&Tree>,
+        after_match: bool,
+    ) -> Body {
+        match (self.language, delimiter) {
+            (_, Delimiter::Paren | Delimiter::Bracket) => Body::Expressions,
+            (_, Delimiter::Indent) => Body::Statements(Context::Items),
+            (Language::Rust, Delimiter::Brace) if after_match => Body::Statements(Context::Arms),
+            (Language::Rust, Delimiter::Brace) => Body::Statements(Context::Items),
+            (Language::TypeScript, Delimiter::Brace) => {
+                let block = match previous {
+                    None => true,
+                    Some(Tree::Group { delimiter, .. }) => *delimiter == Delimiter::Paren,
+                    Some(tree @ Tree::Token(token)) => match token.kind {
+                        TokenKind::Punctuation => self.text(Some(tree)) == "=>",
+                        TokenKind::Keyword => {
+                            matches!(self.text(Some(tree)), "else" | "try" | "finally" | "do")
+                        }
+                        _ => false,
+                    },
+                };
+                match block {
+                    true => Body::Statements(Context::Items),
+                    false => Body::Statements(Context::Fields),
+                }
+            }
+            (Language::Python, Delimiter::Brace) => Body::Expressions,
+        }
+    }
+
+    fn group(&self, tree: &Tree, body: Body) -> SyntaxNode {
+        let Tree::Group {
+            delimiter,
+            open,
+            close,
+            children,
+        } = tree
+        else {
+            return SyntaxNode::new(SyntaxKind::Error, 0..0, Vec::new());
+        };
+        let kind = match delimiter {
+            Delimiter::Paren => SyntaxKind::Parenthesized,
+            Delimiter::Bracket => SyntaxKind::Bracketed,
+            Delimiter::Brace | Delimiter::Indent => SyntaxKind::Block,
+        };
+        let indent = *delimiter == Delimiter::Indent;
+
+        let mut nodes = Vec::new();
+        if !indent {
+            nodes.extend(self.leaf(open));
+        }
+        match body {
+            Body::Statements(context) => {
+                let statements = self.split(context, children);
+                nodes.extend(self.statement_nodes(context, &statements));
+            }
+            Body::Expressions => {
+                let children = children.iter().collect::<Vec<_>>();
+                nodes.extend(self.expressions(&children));
+            }
+        }
+        match close {
+            Some(close) if !indent => nodes.extend(self.leaf(close)),
+            Some(_) => {}
+            None => {
+                let end = nodes.last().map_or(open.span.end, SyntaxNode::end_byte);
+                nodes.push(SyntaxNode::new(SyntaxKind::Error, end..end, Vec::new()));
+            }
+        }
+
+        match (indent, nodes.last()) {
+            (true, Some(_)) => SyntaxNode::wrap(kind, nodes),
+            (false, Some(last)) => {
+                let end = last.end_byte();
+                SyntaxNode::new(kind, open.span.start..end, nodes)
+            }
+            (_, None) => SyntaxNode::new(kind, open.span.clone(), nodes),
+        }
+    }
+
+    /// Returns true if a token can start a chain of postfix expressions.
+    fn is_primary(&self, token: &Token) -> bool {
+        match token.kind {
+            TokenKind::Identifier
+            | TokenKind::String
+            | TokenKind::Number
+            | TokenKind::Character => true,
+            TokenKind::Keyword => matches!(
+                self.text.get(token.span.clone()),
+                Some("self" | "Self" | "super" | "crate" | "this")
+            ),
+            _ => false,
+        }
+    }
+
+    /// Parses a sequence of token trees into expressions.
+    fn expressions(&self, trees: &[&Tree]) -> Vec<SyntaxNode> {
+        let mut nodes = Vec::new();
+        let mut after_match = false;
+        let mut previous = None;
+        let mut index = 0;
+
+        while let Some(&tree) = trees.get(index) {
+            index += 1;
+            let mut node = match tree {
+                Tree::Token(token) => {
+                    let Some(leaf) = self.leaf(token) else {
+                        continue;
+                    };
+                    if self.language == Language::Rust && self.is(Some(tree), "match") {
+                        after_match = true;
+                    }
+                    if !self.is_primary(token) {
+                        nodes.push(leaf);
+                        previous = Some(tree);
+                        continue;
+                    }
+                    leaf
+                }
+                Tree::Group { delimiter, .. } => {
+                    let body = self.default_body(*delimiter, previous, after_match);
+                    let node = self.group(tree, body);
+                    if matches!(delimiter, Delimiter::Brace | Delimiter::Indent) {
+                        after_match = false;
+                        nodes.push(node);
+                        previous = Some(tree);
+                        continue;
+                    }
+                    node
+                }
+            };
+
+            // Paths such as `std::io::Result`
+            if self.language == Language::Rust && matches!(tree, Tree::Token(_)) {
+                let mut segments = vec![node];
+                while let (Some(&separator), Some(&segment)) =
+                    (trees.get(index), trees.get(index + 1))
+                {
+                    let is_segment = matches!(
+                        segment,
+                        Tree::Token(token) if token.kind == TokenKind::Identifier || self.is_primary(token)
+                    );
+                    if !self.is(Some(separator), "::") || !is_segment {
+                        break;
+                    }
+                    segments.extend(self.node(separator, None));
+                    segments.extend(self.node(segment, None));
+                    index += 2;
+                }
+                node = match segments.len() {
+                    1 => segments.remove(0),
+                    _ => SyntaxNode::wrap(SyntaxKind::Path, segments),
+                };
+            }
+
+            // Postfix expressions
+            loop {
+                let next = trees.get(index).copied();
+                let after = trees.get(index + 1).copied();
+                match next {
+                    Some(
+                        group @ Tree::Group {
+                            delimiter: Delimiter::Paren,
+                            ..
+                        },
+                    ) => {
+                        let arguments = self.group(group, Body::Expressions);
+                        node = SyntaxNode::wrap(
+                            SyntaxKind::CallExpression,
+                            vec![
+                                node.with_field("function"),
+                                arguments.with_field("arguments"),
+                            ],
+                        );
+                        index += 1;
+                    }
+                    Some(
+                        group @ Tree::Group {
+                            delimiter: Delimiter::Bracket,
+                            ..
+                        },
+                    ) => {
+                        let index_node = self.group(group, Body::Expressions);
+                        node = SyntaxNode::wrap(
+                            SyntaxKind::IndexExpression,
+                            vec![node.with_field("value"), index_node.with_field("index")],
+                        );
+                        index += 1;
+                    }
+                    Some(bang)
+                        if self.language == Language::Rust
+                            && self.is(Some(bang), "!")
+                            && matches!(node.kind(), SyntaxKind::Identifier | SyntaxKind::Path)
+                            && matches!(after, Some(Tree::Group { .. })) =>
+                    {
+                        let mut children = vec![node.with_field("macro")];
+                        children.extend(self.node(bang, None));
+                        if let Some(group) = after {
+                            children
+                                .push(self.group(group, Body::Expressions).with_field("arguments"));
+                        }
+                        node = SyntaxNode::wrap(SyntaxKind::MacroInvocation, children);
+                        index += 2;
+                    }
+                    Some(dot)
+                        if (self.is(Some(dot), ".") || self.is(Some(dot), "?."))
+                            && matches!(
+                                token_kind(after),
+                                Some(
+                                    TokenKind::Identifier | TokenKind::Keyword | TokenKind::Number
+                                )
+                            ) =>
+                    {
+                        let mut children = vec![node.with_field("value")];
+                        children.extend(self.node(dot, None));
+                        children.extend(
+                            after
+                                .and_then(|field| self.node(field, None))
+                                .map(|field| field.with_field("field")),
+                        );
+                        node = SyntaxNode::wrap(SyntaxKind::FieldExpression, children);
+                        index += 2;
+                    }
+                    Some(question)
+                        if self.language == Language::Rust && self.is(Some(question), "?") =>
+                    {
+                        let mut children = vec![node.with_field("value")];
+                        children.extend(self.node(question, None));
+                        node = SyntaxNode::wrap(SyntaxKind::TryExpression, children);
+                        index += 1;
+                    }
+                    _ => break,
+                }
+            }
+
+            previous = index
+                .checked_sub(1)
+                .and_then(|last| trees.get(last))
+                .copied();
+            nodes.push(node);
+        }
+        nodes
+    }
+}
+
+/// The part of a Rust statement that determines its kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RustHead<'a> {
+    /// A keyword or identifier, with its index among the significant trees
+    Word(usize, &'a str),
+    /// A macro invocation
+    Macro,
+    /// A block
+    Block,
+    /// Anything else
+    Other,
+}
+
+/// Returns the significant trees of a statement.
+fn significant<'t>(statement: &[&'t Tree]) -> Vec<&'t Tree> {
+    statement
+        .iter()
+        .copied()
+        .filter(|tree| is_significant(tree))
+        .collect()
+}
+
+/// Returns the significant trees of a statement with their indices.
+fn significant_indices<'t>(statement: &[&'t Tree]) -> (Vec<&'t Tree>, Vec<usize>) {
+    statement
+        .iter()
+        .enumerate()
+        .filter(|(_, tree)| is_significant(tree))
+        .map(|(index, tree)| (*tree, index))
+        .unzip()
+}
+
+#[cfg(test)]
+#[path = "tests/parser.rs"]
+mod tests;
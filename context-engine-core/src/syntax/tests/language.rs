@@ -0,0 +1,57 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+#[test]
+fn test_from_language_id() {
+    for (id, language) in [
+        ("rust", Some(Language::Rust)),
+        ("typescript", Some(Language::TypeScript)),
+        ("typescriptreact", Some(Language::TypeScript)),
+        ("javascript", Some(Language::TypeScript)),
+        ("python", Some(Language::Python)),
+        ("Rust", None),
+        ("go", None),
+    ] {
+        assert_eq!(Language::from_language_id(id), language, "{id}");
+    }
+}
+
+#[test]
+fn test_keywords() {
+    assert!(Language::Rust.is_keyword("fn"));
+    assert!(!Language::Rust.is_keyword("union"));
+    assert!(Language::TypeScript.is_keyword("function"));
+    assert!(!Language::TypeScript.is_keyword("type"));
+    assert!(Language::Python.is_keyword("None"));
+    assert!(!Language::Python.is_keyword("self"));
+}
+
+#[test]
+fn test_serialization() {
+    assert_eq!(
+        serde_json::to_string(&Language::TypeScript).unwrap(),
+        "\"typescript\""
+    );
+    assert_eq!(
+        serde_json::from_str::<Language>("\"python\"").unwrap(),
+        Language::Python
+    );
+    assert_eq!(Language::Rust.to_string(), "rust");
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_name_round_trips(
+        language in prop_oneof![
+            Just(Language::Rust),
+            Just(Language::TypeScript),
+            Just(Language::Python)
+        ]
+    ) {
+        prop_assert_eq!(Language::from_language_id(language.name()), Some(language));
+    }
+}
@@ -0,0 +1,187 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use lsp_types::{PositionEncodingKind, TextDocumentContentChangeEvent, TextDocumentItem};
+use proptest::prelude::*;
+
+use super::*;
+use crate::syntax::SyntaxKind;
+
+fn uri(path: &str) -> Uri {
+    Uri::from_str(&format!("file://{path}")).unwrap()
+}
+
+fn document(path: &str, language_id: &str, version: i32, text: &str) -> Arc<TextDocument> {
+    Arc::new(TextDocument::new(
+        uri(path),
+        language_id,
+        version,
+        text,
+        PositionEncodingKind::UTF16,
+    ))
+}
+
+#[test]
+fn test_update_reparses_newer_versions() {
+    let layer = SyntaxLayer::new();
+    let first = layer
+        .update(document("/a.rs", "rust", 1, "fn a() {}\n\nfn b() {}\n"))
+        .unwrap();
+    assert_eq!(first.tree().root().children().len(), 2);
+
+    let second = layer
+        .update(document(
+            "/a.rs",
+            "rust",
+            2,
+            "fn a() {}\n\nfn b() {}\n\nfn c() {}\n",
+        ))
+        .unwrap();
+    assert_eq!(second.document().version(), 2);
+    assert_eq!(second.tree().root().children().len(), 3);
+    assert_eq!(second.tree().reparsed_range(), 11..32);
+
+    // Older versions do not replace the tree
+    let stale = layer
+        .update(document("/a.rs", "rust", 1, "fn a() {}\n"))
+        .unwrap();
+    assert!(Arc::ptr_eq(&stale, &second));
+    assert_eq!(layer.len(), 1);
+
+    // The same version with the same text keeps the tree, with another text
+    // it is parsed again
+    let same = layer
+        .update(document(
+            "/a.rs",
+            "rust",
+            2,
+            "fn a() {}\n\nfn b() {}\n\nfn c() {}\n",
+        ))
+        .unwrap();
+    assert!(Arc::ptr_eq(&same, &second));
+    let reused = layer
+        .update(document("/a.rs", "rust", 2, "fn a() {}\n"))
+        .unwrap();
+    assert_eq!(reused.tree().root().children().len(), 1);
+    assert!(Arc::ptr_eq(
+        &layer.get(reused.document().uri()).unwrap(),
+        &reused
+    ));
+}
+
+#[test]
+fn test_unsupported_languages() {
+    let layer = SyntaxLayer::new();
+    assert!(
+        layer
+            .update(document("/a.md", "markdown", 1, "# A"))
+            .is_none()
+    );
+    assert!(layer.is_empty());
+
+    // A document whose language changes to an unsupported one loses its tree
+    layer
+        .update(document("/a.py", "python", 1, "x = 1\n"))
+        .unwrap();
+    assert!(
+        layer
+            .update(document("/a.py", "plaintext", 2, "x = 1\n"))
+            .is_none()
+    );
+    assert!(layer.get(&uri("/a.py")).is_none());
+}
+
+#[test]
+fn test_sync_with_store() {
+    let store = DocumentStore::new(PositionEncodingKind::UTF8);
+    let layer = SyntaxLayer::default();
+    for path in ["/a.rs", "/b.ts", "/c.txt"] {
+        let language_id = match path {
+            "/a.rs" => "rust",
+            "/b.ts" => "typescript",
+            _ => "plaintext",
+        };
+        store
+            .open(TextDocumentItem::new(
+                uri(path),
+                language_id.to_string(),
+                1,
+                "x".to_string(),
+            ))
+            .unwrap();
+    }
+    layer.sync(&store);
+    assert_eq!(layer.len(), 2);
+
+    store.close(&uri("/a.rs")).unwrap();
+    let change = TextDocumentContentChangeEvent {
+        range: None,
+        range_length: None,
+        text: "let y = 2".to_string(),
+    };
+    store.apply_changes(&uri("/b.ts"), 2, &[change]).unwrap();
+    layer.sync(&store);
+
+    assert!(layer.get(&uri("/a.rs")).is_none());
+    let snapshot = layer.get(&uri("/b.ts")).unwrap();
+    assert_eq!(snapshot.document().version(), 2);
+    assert_eq!(
+        snapshot.tree().root().children().first().unwrap().kind(),
+        SyntaxKind::Variable
+    );
+    assert!(layer.remove(&uri("/b.ts")).is_some());
+    assert!(layer.is_empty());
+}
+
+#[test]
+fn test_positions_in_document_encoding() {
+    let layer = SyntaxLayer::new();
+    let snapshot = layer
+        .update(document("/a.ts", "typescript", 1, "const s = \"🦀\"; f(s)"))
+        .unwrap();
+
+    let ancestors = snapshot.ancestors_at(Position::new(0, 16)).unwrap();
+    let call = ancestors.get(2).unwrap();
+    assert_eq!(call.kind(), SyntaxKind::CallExpression);
+    assert_eq!(
+        snapshot.node_range(call).unwrap(),
+        Range::new(Position::new(0, 16), Position::new(0, 20))
+    );
+
+    let range = Range::new(Position::new(0, 18), Position::new(0, 19));
+    let smallest = snapshot.ancestors_for_range(range).unwrap().pop().unwrap();
+    assert_eq!(smallest.kind(), SyntaxKind::Identifier);
+    assert!(snapshot.ancestors_at(Position::new(2, 0)).is_err());
+}
+
+#[test]
+fn test_parse_outside_layer() {
+    let snapshot = SyntaxSnapshot::parse(document("/a.py", "python", 0, "def f():\n    pass\n"));
+    let snapshot = snapshot.unwrap();
+    assert_eq!(snapshot.tree().language(), Language::Python);
+    assert_eq!(
+        snapshot.tree().root().children().first().unwrap().kind(),
+        SyntaxKind::Function
+    );
+    assert!(SyntaxSnapshot::parse(document("/a.txt", "plaintext", 0, "")).is_none());
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_versions_only_move_forward(versions in prop::collection::vec(0i32..10, 1..10)) {
+        let layer = SyntaxLayer::new();
+        let mut latest = i32::MIN;
+        for version in versions {
+            let text = format!("fn f{version}() {{}}");
+            let snapshot = layer.update(document("/a.rs", "rust", version, &text)).unwrap();
+            latest = latest.max(version);
+            prop_assert_eq!(snapshot.document().version(), latest);
+            prop_assert_eq!(
+                snapshot.tree(),
+                &SyntaxTree::parse(Language::Rust, snapshot.document().text())
+            );
+        }
+    }
+}
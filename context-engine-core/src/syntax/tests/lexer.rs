@@ -0,0 +1,134 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+/// Returns the kinds and texts of the tokens of a whole text.
+fn lex(language: Language, text: &str) -> Vec<(TokenKind, &str)> {
+    let tokens = tokenize(language, text, 0, text.len());
+    assert!(tokens.complete, "{text}");
+    tokens
+        .tokens
+        .iter()
+        .map(|token| (token.kind, text.get(token.span.clone()).unwrap()))
+        .collect()
+}
+
+#[test]
+fn test_rust_tokens() {
+    use TokenKind::*;
+
+    let text = "r#\"a\"b\"# 'a' 'b br\"x\" r#type /* /* */ */ x::<u8>..=1.5e-3f64";
+    assert_eq!(
+        lex(Language::Rust, text),
+        [
+            (String, "r#\"a\"b\"#"),
+            (Character, "'a'"),
+            (Lifetime, "'b"),
+            (String, "br\"x\""),
+            (Identifier, "r#type"),
+            (Comment, "/* /* */ */"),
+            (Identifier, "x"),
+            (Punctuation, "::"),
+            (Punctuation, "<"),
+            (Identifier, "u8"),
+            (Punctuation, ">"),
+            (Punctuation, "..="),
+            (Number, "1.5e-3f64"),
+        ]
+    );
+}
+
+#[test]
+fn test_typescript_tokens() {
+    use TokenKind::*;
+
+    let text = "a / b; x = /[/]+/g; `t ${ {a: `n`} } `; #p?.q";
+    assert_eq!(
+        lex(Language::TypeScript, text),
+        [
+            (Identifier, "a"),
+            (Punctuation, "/"),
+            (Identifier, "b"),
+            (Punctuation, ";"),
+            (Identifier, "x"),
+            (Punctuation, "="),
+            (String, "/[/]+/g"),
+            (Punctuation, ";"),
+            (String, "`t ${ {a: `n`} } `"),
+            (Punctuation, ";"),
+            (Identifier, "#p"),
+            (Punctuation, "?."),
+            (Identifier, "q"),
+        ]
+    );
+}
+
+#[test]
+fn test_python_layout() {
+    use TokenKind::*;
+
+    let text = "def f(a,\n      b):\n    # c\n    return rb'''x\ny'''\n\nf()\n";
+    assert_eq!(
+        lex(Language::Python, text),
+        [
+            (Keyword, "def"),
+            (Identifier, "f"),
+            (Open(Delimiter::Paren), "("),
+            (Identifier, "a"),
+            (Punctuation, ","),
+            (Identifier, "b"),
+            (Close(Delimiter::Paren), ")"),
+            (Punctuation, ":"),
+            (Newline, ""),
+            (Comment, "# c"),
+            (Open(Delimiter::Indent), ""),
+            (Keyword, "return"),
+            (String, "rb'''x\ny'''"),
+            (Newline, ""),
+            (Close(Delimiter::Indent), ""),
+            (Identifier, "f"),
+            (Open(Delimiter::Paren), "("),
+            (Close(Delimiter::Paren), ")"),
+            (Newline, ""),
+        ]
+    );
+}
+
+#[test]
+fn test_incomplete_tokens() {
+    let text = "let s = \"abc\"; /* x";
+
+    // The string straddles the end of the part
+    assert!(!tokenize(Language::Rust, text, 0, 10).complete);
+    assert!(tokenize(Language::Rust, text, 0, 14).complete);
+    // The comment is not terminated
+    assert!(!tokenize(Language::Rust, text, 0, text.len()).complete);
+}
+
+#[test]
+fn test_next_significant() {
+    let text = "a /* b */ // c\n d";
+    let token = next_significant(Language::Rust, text, 1).unwrap();
+    assert_eq!(text.get(token.span).unwrap(), "d");
+    assert!(token.newline_before);
+    assert!(next_significant(Language::Rust, text, text.len()).is_none());
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_tokens_are_ordered(text in "[a-z0-9 \n\t'\"/*#`{}()\\[\\].:;=<>!\\\\-]{0,40}") {
+        for language in [Language::Rust, Language::TypeScript, Language::Python] {
+            let tokens = tokenize(language, &text, 0, text.len()).tokens;
+            let mut end = 0;
+            for token in &tokens {
+                prop_assert!(token.span.start <= token.span.end);
+                prop_assert!(end <= token.span.start || token.span.is_empty());
+                prop_assert!(token.span.end <= text.len());
+                end = end.max(token.span.end);
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+/// Returns the kinds and texts of the top-level nodes of a text.
+fn statements(language: Language, text: &str) -> Vec<(SyntaxKind, &str)> {
+    parse(language, text)
+        .children()
+        .iter()
+        .map(|node| (node.kind(), node.text(text)))
+        .collect()
+}
+
+#[test]
+fn test_rust_statement_ends() {
+    let text = "#![allow(x)]\nif a {} else {}\nmacro_rules! m { () => {} }\nm! {}\nlet x = loop \
+                {};\nunsafe impl Send for A {}";
+    assert_eq!(
+        statements(Language::Rust, text),
+        [
+            (SyntaxKind::Attribute, "#![allow(x)]"),
+            (SyntaxKind::Statement, "if a {} else {}"),
+            (SyntaxKind::Macro, "macro_rules! m { () => {} }"),
+            (SyntaxKind::Statement, "m! {}"),
+            (SyntaxKind::Variable, "let x = loop {};"),
+            (SyntaxKind::Impl, "unsafe impl Send for A {}"),
+        ]
+    );
+}
+
+#[test]
+fn test_rust_match_arms() {
+    let text = "match x { A => {} B => b, _ => c }";
+    let root = parse(Language::Rust, text);
+    let arms = root
+        .descendants()
+        .filter(|node| node.kind() == SyntaxKind::Statement)
+        .map(|node| node.text(text))
+        .collect::<Vec<_>>();
+    assert_eq!(arms, [text, "A => {}", "B => b,", "_ => c"]);
+}
+
+#[test]
+fn test_typescript_automatic_semicolons() {
+    let text = "let a = b\n(c)\nlet d = 1\nd++\nreturn\nx\nif (a) {}\nelse {}\n";
+    assert_eq!(
+        statements(Language::TypeScript, text),
+        [
+            (SyntaxKind::Variable, "let a = b\n(c)"),
+            (SyntaxKind::Variable, "let d = 1"),
+            (SyntaxKind::Statement, "d++"),
+            (SyntaxKind::Statement, "return"),
+            (SyntaxKind::Statement, "x"),
+            (SyntaxKind::Statement, "if (a) {}\nelse {}"),
+        ]
+    );
+}
+
+#[test]
+fn test_typescript_object_literals() {
+    let text = "const o = { a: 1, b() {} };";
+    let root = parse(Language::TypeScript, text);
+    let members = root
+        .descendants()
+        .filter(|node| matches!(node.kind(), SyntaxKind::Field | SyntaxKind::Method))
+        .map(|node| (node.kind(), node.name(text).unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        members,
+        [(SyntaxKind::Field, "a"), (SyntaxKind::Method, "b")]
+    );
+}
+
+#[test]
+fn test_python_decorators_and_blocks() {
+    let text = "@a\n@b(1)\ndef f():\n    pass\nif x:\n    y\nelse:\n    z\n";
+    assert_eq!(
+        statements(Language::Python, text),
+        [
+            (SyntaxKind::Function, "@a\n@b(1)\ndef f():\n    pass"),
+            (SyntaxKind::Statement, "if x:\n    y"),
+            (SyntaxKind::Statement, "else:\n    z"),
+        ]
+    );
+    let function = parse(Language::Python, text);
+    let attributes = function
+        .descendants()
+        .filter(|node| node.kind() == SyntaxKind::Attribute)
+        .map(|node| node.text(text))
+        .collect::<Vec<_>>();
+    assert_eq!(attributes, ["@a", "@b(1)"]);
+}
+
+#[test]
+fn test_comments_before_statements_stand_alone() {
+    let text = "// a\nfn f() {} // b\n/* c */ fn g() {}";
+    assert_eq!(
+        statements(Language::Rust, text),
+        [
+            (SyntaxKind::Comment, "// a"),
+            (SyntaxKind::Function, "fn f() {}"),
+            (SyntaxKind::Comment, "// b"),
+            (SyntaxKind::Comment, "/* c */"),
+            (SyntaxKind::Function, "fn g() {}"),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_region() {
+    let text = "fn a() {}\nfn b() {}\nfn c() {}";
+    let region = parse_region(Language::Rust, text, 10..19).unwrap();
+    assert_eq!(region.len(), 1);
+    assert_eq!(region.first().unwrap().name(text), Some("b"));
+
+    // Unbalanced regions and statements continued after the region are
+    // rejected
+    assert!(parse_region(Language::Rust, text, 10..15).is_none());
+    let text = "if a {}\nelse {}";
+    assert!(parse_region(Language::Rust, text, 0..7).is_none());
+    // Indented regions in Python are rejected
+    let text = "if a:\n    b\n    c\n";
+    assert!(parse_region(Language::Python, text, 16..17).is_none());
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_parse_covers_text(text in "[a-z ;:=,.(){}\\[\\]\n]{0,60}") {
+        for language in [Language::Rust, Language::TypeScript, Language::Python] {
+            let root = parse(language, &text);
+            prop_assert_eq!(root.byte_range(), 0..text.len());
+
+            // Every non-whitespace byte is covered by a leaf
+            let mut covered = vec![false; text.len()];
+            for leaf in root.descendants().filter(|node| node.children().is_empty()) {
+                for byte in covered.iter_mut().take(leaf.end_byte()).skip(leaf.start_byte()) {
+                    *byte = true;
+                }
+            }
+            for (byte, covered) in text.bytes().zip(covered) {
+                prop_assert!(covered || byte.is_ascii_whitespace());
+            }
+        }
+    }
+}
@@ -0,0 +1,539 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+const RUST: &str = r#"//! Crate docs
+use std::io::{self, Read};
+
+#[derive(Debug, Clone)]
+pub struct Point<T> {
+    pub x: T,
+    y: T,
+}
+
+enum Shape {
+    Circle { radius: f64 },
+    Square(f64),
+    Empty,
+}
+
+impl<T: Copy> Display for Point<T> where T: Debug {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+/// Runs the program
+fn main() -> io::Result<()> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    if input.is_empty() {
+        return Ok(());
+    } else {
+        println!("{}", input.len());
+    }
+    match input.as_str() {
+        "a" => run(1),
+        _ => {}
+    }
+    Ok(())
+}
+
+const LIMIT: usize = 10;
+"#;
+
+const TYPESCRIPT: &str = r#"import { readFile } from "fs";
+
+@Component({ selector: "app" })
+export class Service extends Base {
+    private count = 0;
+    constructor(private readonly http: Http) {
+        super();
+    }
+    async load(id: string): Promise<void> {
+        const data = await this.http.get(`/items/${id}`);
+        this.count++
+        return data.items.map((item) => item.name);
+    }
+}
+
+interface Options {
+    name: string;
+    retry?(attempts: number): boolean
+}
+
+enum Color { Red, Green = "green" }
+
+function run(options: Options) {
+    if (options.name) {
+        console.log(options.name)
+    } else {
+        throw new Error("no name")
+    }
+}
+
+const config = { retries: 3, run }
+"#;
+
+const PYTHON: &str = r#"import os
+from typing import List
+
+LIMIT = 10
+
+@dataclass
+class Point:
+    x: int = 0
+
+    def norm(self) -> float:
+        # Euclidean norm
+        return math.sqrt(self.x ** 2)
+
+def main(args: List[str]):
+    for arg in args:
+        if arg.startswith("-"):
+            print(arg)
+    else:
+        pass
+    return os.path.join(*args)
+
+main(sys.argv)
+"#;
+
+fn kinds(node: &SyntaxNode) -> Vec<SyntaxKind> {
+    node.children().iter().map(SyntaxNode::kind).collect()
+}
+
+fn names<'a>(node: &SyntaxNode, source: &'a str) -> Vec<&'a str> {
+    node.children()
+        .iter()
+        .filter_map(|child| child.name(source))
+        .collect()
+}
+
+#[test]
+fn test_rust_items() {
+    let tree = SyntaxTree::parse(Language::Rust, RUST);
+
+    assert!(!tree.has_errors());
+    assert_eq!(
+        kinds(tree.root()),
+        [
+            SyntaxKind::Comment,
+            SyntaxKind::Import,
+            SyntaxKind::Struct,
+            SyntaxKind::Enum,
+            SyntaxKind::Impl,
+            SyntaxKind::Comment,
+            SyntaxKind::Function,
+            SyntaxKind::Constant,
+        ]
+    );
+    assert_eq!(
+        names(tree.root(), RUST),
+        ["Point", "Shape", "main", "LIMIT"]
+    );
+
+    let point = tree.root().children().get(2).unwrap();
+    assert!(
+        point
+            .children()
+            .iter()
+            .any(|child| child.kind() == SyntaxKind::Attribute)
+    );
+    let fields = point.child_by_field_name("body").unwrap();
+    assert_eq!(names(fields, RUST), ["x", "y"]);
+
+    let shape = tree.root().children().get(3).unwrap();
+    let variants = shape.child_by_field_name("body").unwrap();
+    assert_eq!(names(variants, RUST), ["Circle", "Square", "Empty"]);
+
+    let display = tree.root().children().get(4).unwrap();
+    assert_eq!(
+        display.child_by_field_name("trait").unwrap().text(RUST),
+        "Display"
+    );
+    assert_eq!(
+        display.child_by_field_name("type").unwrap().text(RUST),
+        "Point<T>"
+    );
+    let methods = display.child_by_field_name("body").unwrap();
+    assert_eq!(
+        kinds(methods),
+        [
+            SyntaxKind::Punctuation,
+            SyntaxKind::Method,
+            SyntaxKind::Punctuation
+        ]
+    );
+}
+
+#[test]
+fn test_rust_expressions() {
+    let source = "fn f() { io::stdin().read(&mut x)?.len(); }";
+    let tree = SyntaxTree::parse(Language::Rust, source);
+    let offset = source.find("stdin").unwrap();
+
+    let ancestors = tree
+        .ancestors_for_byte_range(offset, offset)
+        .iter()
+        .map(|node| (node.kind(), node.text(source)))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ancestors,
+        [
+            (SyntaxKind::SourceFile, source),
+            (SyntaxKind::Function, source),
+            (SyntaxKind::Block, "{ io::stdin().read(&mut x)?.len(); }"),
+            (SyntaxKind::Statement, "io::stdin().read(&mut x)?.len();"),
+            (
+                SyntaxKind::CallExpression,
+                "io::stdin().read(&mut x)?.len()"
+            ),
+            (SyntaxKind::FieldExpression, "io::stdin().read(&mut x)?.len"),
+            (SyntaxKind::TryExpression, "io::stdin().read(&mut x)?"),
+            (SyntaxKind::CallExpression, "io::stdin().read(&mut x)"),
+            (SyntaxKind::FieldExpression, "io::stdin().read"),
+            (SyntaxKind::CallExpression, "io::stdin()"),
+            (SyntaxKind::Path, "io::stdin"),
+            (SyntaxKind::Identifier, "stdin"),
+        ]
+    );
+}
+
+#[test]
+fn test_typescript_items() {
+    let tree = SyntaxTree::parse(Language::TypeScript, TYPESCRIPT);
+
+    assert!(!tree.has_errors());
+    assert_eq!(
+        kinds(tree.root()),
+        [
+            SyntaxKind::Import,
+            SyntaxKind::Class,
+            SyntaxKind::Interface,
+            SyntaxKind::Enum,
+            SyntaxKind::Function,
+            SyntaxKind::Variable,
+        ]
+    );
+    assert_eq!(
+        names(tree.root(), TYPESCRIPT),
+        ["Service", "Options", "Color", "run", "config"]
+    );
+
+    let class = tree.root().children().get(1).unwrap();
+    assert_eq!(
+        class.children().first().unwrap().kind(),
+        SyntaxKind::Attribute
+    );
+    let members = class.child_by_field_name("body").unwrap();
+    assert_eq!(names(members, TYPESCRIPT), ["count", "constructor", "load"]);
+
+    let interface = tree.root().children().get(2).unwrap();
+    let fields = interface.child_by_field_name("body").unwrap();
+    assert_eq!(names(fields, TYPESCRIPT), ["name", "retry"]);
+}
+
+#[test]
+fn test_python_items() {
+    let tree = SyntaxTree::parse(Language::Python, PYTHON);
+
+    assert!(!tree.has_errors());
+    assert_eq!(
+        kinds(tree.root()),
+        [
+            SyntaxKind::Import,
+            SyntaxKind::Import,
+            SyntaxKind::Variable,
+            SyntaxKind::Class,
+            SyntaxKind::Function,
+            SyntaxKind::Statement,
+        ]
+    );
+    assert_eq!(names(tree.root(), PYTHON), ["LIMIT", "Point", "main"]);
+
+    let class = tree.root().children().get(3).unwrap();
+    assert_eq!(class.children().first().unwrap().text(PYTHON), "@dataclass");
+    let members = class.child_by_field_name("body").unwrap();
+    assert_eq!(kinds(members), [SyntaxKind::Field, SyntaxKind::Method]);
+    assert_eq!(names(members, PYTHON), ["x", "norm"]);
+}
+
+#[test]
+fn test_errors_are_contained() {
+    let source = "fn a() {\n    let x = (1;\n}\n\nfn b() {}\n)";
+    let tree = SyntaxTree::parse(Language::Rust, source);
+
+    assert!(tree.has_errors());
+    assert_eq!(names(tree.root(), source), ["a", "b"]);
+    let stray = tree.root().descendants().last().unwrap();
+    assert_eq!(stray.kind(), SyntaxKind::Error);
+    assert_eq!(stray.text(source), ")");
+}
+
+#[test]
+fn test_unclosed_delimiter() {
+    let source = "fn a() {\n    run(";
+    let tree = SyntaxTree::parse(Language::Rust, source);
+
+    assert!(tree.has_errors());
+    let empty = tree
+        .root()
+        .descendants()
+        .filter(|node| node.kind() == SyntaxKind::Error)
+        .collect::<Vec<_>>();
+    assert_eq!(empty.len(), 2);
+    assert!(empty.iter().all(|node| node.start_byte() == source.len()));
+}
+
+#[test]
+fn test_reparse_only_parses_edited_statements() {
+    let old = "fn a() {}\n\nfn b() {}\n\nfn c() {}\n\nfn d() {}\n";
+    let new = old.replace("fn b() {}", "fn b() { x(); }");
+    let tree = SyntaxTree::parse(Language::Rust, old);
+
+    let edit = InputEdit::between(old, &new);
+    let reparsed = tree.reparse(&new, &edit);
+
+    assert_eq!(reparsed, SyntaxTree::parse(Language::Rust, &new));
+    // The function before the edit is parsed again, the one after it too,
+    // and the last one is shifted
+    assert_eq!(reparsed.reparsed_range(), 0..37);
+    assert_eq!(
+        reparsed.root().children().last().unwrap().text(&new),
+        "fn d() {}"
+    );
+}
+
+#[test]
+fn test_reparse_merges_statements() {
+    let old = "fn a() {\n    run();\n}\n\nfn b() {}\n";
+    let new = "fn a() {\n    run();\n\nfn b() {}\n";
+    let tree = SyntaxTree::parse(Language::Rust, old);
+
+    let reparsed = tree.reparse(new, &InputEdit::between(old, new));
+
+    assert_eq!(reparsed, SyntaxTree::parse(Language::Rust, new));
+    assert_eq!(reparsed.root().children().len(), 1);
+}
+
+#[test]
+fn test_input_edit_between() {
+    assert_eq!(
+        InputEdit::between("abc", "abc"),
+        InputEdit {
+            start_byte: 3,
+            old_end_byte: 3,
+            new_end_byte: 3
+        }
+    );
+    assert_eq!(
+        InputEdit::between("aaa", "aaaa"),
+        InputEdit {
+            start_byte: 3,
+            old_end_byte: 3,
+            new_end_byte: 4
+        }
+    );
+    // Edits never split characters
+    assert_eq!(
+        InputEdit::between("é", "è"),
+        InputEdit {
+            start_byte: 0,
+            old_end_byte: 2,
+            new_end_byte: 2
+        }
+    );
+}
+
+#[test]
+fn test_point_conversions() {
+    let text = "a\n🦀 = b";
+    let index = LineIndex::new(text);
+
+    let point = Point::from_offset(text.find('=').unwrap(), &index).unwrap();
+    assert_eq!(point, Point::new(1, 5));
+    assert_eq!(point.to_offset(&index).unwrap(), 7);
+    assert_eq!(
+        point
+            .to_position(&index, &PositionEncodingKind::UTF16)
+            .unwrap(),
+        Position::new(1, 3)
+    );
+    assert_eq!(
+        point
+            .to_position(&index, &PositionEncodingKind::UTF32)
+            .unwrap(),
+        Position::new(1, 2)
+    );
+    assert_eq!(
+        Point::from_position(Position::new(1, 2), &index, &PositionEncodingKind::UTF32).unwrap(),
+        point
+    );
+    assert!(Point::from_offset(3, &index).is_err());
+}
+
+#[test]
+fn test_node_range() {
+    let text = "let s = \"🦀\";\nlet t = s;";
+    let tree = SyntaxTree::parse(Language::Rust, text);
+    let index = LineIndex::new(text);
+    let second = tree.root().children().get(1).unwrap();
+
+    assert_eq!(
+        second.range(&index, &PositionEncodingKind::UTF16).unwrap(),
+        Range::new(Position::new(1, 0), Position::new(1, 10))
+    );
+    let first = tree.root().children().first().unwrap();
+    assert_eq!(
+        first.range(&index, &PositionEncodingKind::UTF16).unwrap(),
+        Range::new(Position::new(0, 0), Position::new(0, 13))
+    );
+}
+
+/// Inserts, deletes or replaces a slice of a source text.
+fn edited(source: &str, start: usize, length: usize, insert: &str) -> String {
+    let mut start = start % (source.len() + 1);
+    while !source.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (start + length).min(source.len());
+    while !source.is_char_boundary(end) {
+        end += 1;
+    }
+    format!(
+        "{}{}{}",
+        source.get(..start).unwrap(),
+        insert,
+        source.get(end..).unwrap()
+    )
+}
+
+fn fragments(language: Language) -> Vec<&'static str> {
+    let common = vec![
+        "", " ", "\n", "\n\n", "x", "(", ")", "{", "}", "[", "]", ";", ",", ".", ":", "=", "\"",
+        "'", "//", "#", "@", "a.b(c)", "\n    ",
+    ];
+    let specific: &[&str] = match language {
+        Language::Rust => &[
+            "fn f() {}",
+            "struct S { a: u8 }",
+            "if x {} ",
+            "else {}",
+            "/*",
+            "*/",
+            "r#\"",
+            "'a",
+            "!",
+            "::",
+            "#[test]\n",
+            "match x { _ => {} }",
+        ],
+        Language::TypeScript => &[
+            "function f() {}",
+            "class C { m() {} }",
+            "if (x) {}",
+            "else {}",
+            "`${",
+            "`",
+            "/re/",
+            "/",
+            "a\n(b)",
+            "try {}",
+            "catch {}",
+            "@d\n",
+        ],
+        Language::Python => &[
+            "def f():\n    pass\n",
+            "class C:\n    x = 1\n",
+            "\n\t",
+            "if x:\n",
+            "\"\"\"",
+            "\\\n",
+            "@d\n",
+            "else:\n    pass\n",
+            "  ",
+        ],
+    };
+    common.into_iter().chain(specific.iter().copied()).collect()
+}
+
+fn source(language: Language) -> &'static str {
+    match language {
+        Language::Rust => RUST,
+        Language::TypeScript => TYPESCRIPT,
+        Language::Python => PYTHON,
+    }
+}
+
+fn languages() -> impl Strategy<Value = Language> {
+    prop_oneof![
+        Just(Language::Rust),
+        Just(Language::TypeScript),
+        Just(Language::Python)
+    ]
+}
+
+// Property-based tests
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn prop_reparse_matches_full_parse(
+        language in languages(),
+        edits in prop::collection::vec((any::<usize>(), 0usize..40, any::<prop::sample::Index>()), 1..4),
+    ) {
+        let fragments = fragments(language);
+        let mut text = source(language).to_string();
+        let mut tree = SyntaxTree::parse(language, &text);
+
+        for (start, length, fragment) in edits {
+            let new = edited(&text, start, length, fragment.get(&fragments));
+            let edit = InputEdit::between(&text, &new);
+            tree = tree.reparse(&new, &edit);
+            let expected = SyntaxTree::parse(language, &new);
+            prop_assert_eq!(
+                &tree,
+                &expected,
+                "reparsed:\n{}\nparsed:\n{}\ntext:\n{}",
+                tree.root(),
+                expected.root(),
+                new
+            );
+            text = new;
+        }
+    }
+
+    #[test]
+    fn prop_nodes_nest(language in languages(), start in any::<usize>(), length in 0usize..40) {
+        let text = edited(source(language), start, length, "");
+        let tree = SyntaxTree::parse(language, &text);
+
+        prop_assert_eq!(tree.root().byte_range(), 0..text.len());
+        for node in tree.root().descendants() {
+            prop_assert!(node.start_byte() <= node.end_byte());
+            prop_assert!(text.is_char_boundary(node.start_byte()));
+            prop_assert!(text.is_char_boundary(node.end_byte()));
+            let mut end = node.start_byte();
+            for child in node.children() {
+                prop_assert!(end <= child.start_byte());
+                end = child.end_byte();
+            }
+            prop_assert!(end <= node.end_byte());
+        }
+    }
+
+    #[test]
+    fn prop_input_edit_between(old in "[ab\u{e9}]{0,8}", new in "[ab\u{e9}]{0,8}") {
+        let edit = InputEdit::between(&old, &new);
+        let spliced = format!(
+            "{}{}{}",
+            old.get(..edit.start_byte).unwrap(),
+            new.get(edit.start_byte..edit.new_end_byte).unwrap(),
+            old.get(edit.old_end_byte..).unwrap()
+        );
+        prop_assert_eq!(spliced, new);
+    }
+}
@@ -0,0 +1,666 @@
+//! Concrete syntax trees and their coordinates.
+
+use std::fmt;
+use std::ops::Range as ByteRange;
+
+use lsp_types::{Position, PositionEncodingKind, Range};
+use serde::{Deserialize, Serialize};
+
+use crate::syntax::{Language, parser};
+use crate::types::{LineIndex, LocationError};
+
+/// The kind of a [`SyntaxNode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyntaxKind {
+    /// The root of a tree
+    SourceFile,
+    /// `{...}`, or an indented block in Python
+    Block,
+    /// `(...)`
+    Parenthesized,
+    /// `[...]`
+    Bracketed,
+
+    /// A module or namespace with a body
+    Module,
+    /// A struct definition
+    Struct,
+    /// A union definition
+    Union,
+    /// An enum definition
+    Enum,
+    /// A trait definition
+    Trait,
+    /// An `impl` block
+    Impl,
+    /// An interface definition
+    Interface,
+    /// A class definition
+    Class,
+    /// A free function
+    Function,
+    /// A function in an `impl`, trait or class body
+    Method,
+    /// A constant
+    Constant,
+    /// A static variable
+    Static,
+    /// A type alias
+    TypeAlias,
+    /// A macro definition
+    Macro,
+    /// An import such as `use`, `import` or `from ... import`
+    Import,
+    /// A field of a struct, interface or class
+    Field,
+    /// A variant of an enum
+    Variant,
+    /// A variable declaration such as `let`, `const` or an assignment to a
+    /// name
+    Variable,
+    /// An attribute or decorator
+    Attribute,
+
+    /// Any other statement, or an arm of a `match`
+    Statement,
+    /// A function or method call
+    CallExpression,
+    /// A macro invocation such as `println!(...)`
+    MacroInvocation,
+    /// An access to a field or method such as `a.b`
+    FieldExpression,
+    /// An index expression such as `a[b]`
+    IndexExpression,
+    /// A Rust `?` expression such as `a?`
+    TryExpression,
+    /// A path such as `std::io::Result`
+    Path,
+    /// A type in the header of an `impl` block
+    Type,
+
+    /// An identifier
+    Identifier,
+    /// A reserved keyword
+    Keyword,
+    /// A string, template or regular expression literal
+    String,
+    /// A number literal
+    Number,
+    /// A character literal
+    Character,
+    /// A Rust lifetime
+    Lifetime,
+    /// A comment, including doc comments
+    Comment,
+    /// An operator or separator
+    Punctuation,
+    /// An unexpected token, or an empty node where a closing delimiter is
+    /// missing
+    Error,
+}
+
+impl SyntaxKind {
+    /// Returns true for definitions of named items, e.g. functions, types and
+    /// fields.
+    pub fn is_definition(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Module
+                | SyntaxKind::Struct
+                | SyntaxKind::Union
+                | SyntaxKind::Enum
+                | SyntaxKind::Trait
+                | SyntaxKind::Impl
+                | SyntaxKind::Interface
+                | SyntaxKind::Class
+                | SyntaxKind::Function
+                | SyntaxKind::Method
+                | SyntaxKind::Constant
+                | SyntaxKind::Static
+                | SyntaxKind::TypeAlias
+                | SyntaxKind::Macro
+                | SyntaxKind::Field
+                | SyntaxKind::Variant
+        )
+    }
+
+    /// Returns true for statements, including definitions.
+    pub fn is_statement(&self) -> bool {
+        self.is_definition()
+            || matches!(
+                self,
+                SyntaxKind::Import | SyntaxKind::Variable | SyntaxKind::Statement
+            )
+    }
+
+    /// Returns true for expressions that are not single tokens.
+    pub fn is_expression(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::CallExpression
+                | SyntaxKind::MacroInvocation
+                | SyntaxKind::FieldExpression
+                | SyntaxKind::IndexExpression
+                | SyntaxKind::TryExpression
+                | SyntaxKind::Path
+        )
+    }
+
+    /// Returns true for nodes made of a single token.
+    pub fn is_token(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Identifier
+                | SyntaxKind::Keyword
+                | SyntaxKind::String
+                | SyntaxKind::Number
+                | SyntaxKind::Character
+                | SyntaxKind::Lifetime
+                | SyntaxKind::Comment
+                | SyntaxKind::Punctuation
+                | SyntaxKind::Error
+        )
+    }
+}
+
+/// A node of a [`SyntaxTree`].
+///
+/// Nodes cover a byte range of the source text and own their children, in
+/// source order. Children may be labelled with a field name such as `name`,
+/// `body`, `parameters`, `function` or `arguments`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxNode {
+    kind: SyntaxKind,
+    field: Option<&'static str>,
+    range: ByteRange<usize>,
+    children: Vec<SyntaxNode>,
+}
+
+impl SyntaxNode {
+    pub(crate) fn new(
+        kind: SyntaxKind,
+        range: ByteRange<usize>,
+        children: Vec<SyntaxNode>,
+    ) -> Self {
+        Self {
+            kind,
+            field: None,
+            range,
+            children,
+        }
+    }
+
+    /// Creates a node covering its children.
+    pub(crate) fn wrap(kind: SyntaxKind, children: Vec<SyntaxNode>) -> Self {
+        let start = children.first().map_or(0, |child| child.range.start);
+        let end = children.last().map_or(start, |child| child.range.end);
+        Self::new(kind, start..end, children)
+    }
+
+    pub(crate) fn with_field(mut self, field: &'static str) -> Self {
+        self.field = Some(field);
+        self
+    }
+
+    /// Returns the kind of the node.
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    /// Returns the field name under which the node is a child of its parent.
+    pub fn field(&self) -> Option<&'static str> {
+        self.field
+    }
+
+    /// Returns the byte range of the node in the source text.
+    pub fn byte_range(&self) -> ByteRange<usize> {
+        self.range.clone()
+    }
+
+    /// Returns the byte offset of the start of the node.
+    pub fn start_byte(&self) -> usize {
+        self.range.start
+    }
+
+    /// Returns the byte offset of the end of the node.
+    pub fn end_byte(&self) -> usize {
+        self.range.end
+    }
+
+    /// Returns the children of the node.
+    pub fn children(&self) -> &[SyntaxNode] {
+        &self.children
+    }
+
+    /// Returns the first child with the given field name.
+    pub fn child_by_field_name(&self, field: &str) -> Option<&SyntaxNode> {
+        self.children
+            .iter()
+            .find(|child| child.field == Some(field))
+    }
+
+    /// Returns the text of the node.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The source text the tree was parsed from
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        source.get(self.range.clone()).unwrap_or("")
+    }
+
+    /// Returns the text of the `name` child of the node.
+    pub fn name<'a>(&self, source: &'a str) -> Option<&'a str> {
+        self.child_by_field_name("name")
+            .map(|name| name.text(source))
+    }
+
+    /// Returns the node and all its descendants in pre-order.
+    pub fn descendants(&self) -> impl Iterator<Item = &SyntaxNode> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+
+    /// Returns true if the node or a descendant is an [`SyntaxKind::Error`].
+    pub fn has_error(&self) -> bool {
+        self.descendants()
+            .any(|node| node.kind == SyntaxKind::Error)
+    }
+
+    /// Returns the LSP range of the node.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LocationError`] if the node is not within the text of
+    /// `index`.
+    pub fn range(
+        &self,
+        index: &LineIndex,
+        encoding: &PositionEncodingKind,
+    ) -> Result<Range, LocationError> {
+        Ok(Range::new(
+            index.position(self.range.start, encoding)?,
+            index.position(self.range.end, encoding)?,
+        ))
+    }
+
+    /// Moves the node and its descendants by `delta` bytes.
+    pub(crate) fn shift(&mut self, delta: isize) {
+        self.range = self.range.start.saturating_add_signed(delta)
+            ..self.range.end.saturating_add_signed(delta);
+        for child in &mut self.children {
+            child.shift(delta);
+        }
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}", "", indent = depth * 2)?;
+        if let Some(field) = self.field {
+            write!(f, "{field}: ")?;
+        }
+        writeln!(f, "{:?} {:?}", self.kind, self.range)?;
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats the node as an indented outline of its descendants.
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// A position in a text as a zero-based row and a byte column, as used by
+/// Tree-sitter.
+///
+/// The column is the UTF-8 [`Position::character`], so converting between
+/// points and positions in other encodings needs the [`LineIndex`] of the
+/// text.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::syntax::Point;
+/// use context_engine_core::types::{LineIndex, Position, PositionEncodingKind};
+///
+/// let index = LineIndex::new("let s = \"héllo\";\nnext");
+/// let point = Point::from_offset(12, &index).unwrap();
+/// assert_eq!(point, Point::new(0, 12));
+///
+/// let position = point.to_position(&index, &PositionEncodingKind::UTF16).unwrap();
+/// assert_eq!(position, Position::new(0, 11));
+/// assert_eq!(
+///     Point::from_position(position, &index, &PositionEncodingKind::UTF16).unwrap(),
+///     point
+/// );
+/// ```
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct Point {
+    /// The zero-based line
+    pub row: u32,
+    /// The zero-based byte offset in the line
+    pub column: u32,
+}
+
+impl Point {
+    /// Creates a point.
+    pub fn new(row: u32, column: u32) -> Self {
+        Self { row, column }
+    }
+
+    /// Returns the point of a byte offset.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LocationError`] if the offset is not within the text of
+    /// `index` or not at a character boundary.
+    pub fn from_offset(offset: usize, index: &LineIndex) -> Result<Self, LocationError> {
+        let position = index.position(offset, &PositionEncodingKind::UTF8)?;
+        Ok(Self::new(position.line, position.character))
+    }
+
+    /// Returns the byte offset of the point.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LocationError`] if the point is not within the text of
+    /// `index`.
+    pub fn to_offset(&self, index: &LineIndex) -> Result<usize, LocationError> {
+        index.offset(self.as_utf8_position(), &PositionEncodingKind::UTF8)
+    }
+
+    /// Returns the point of an LSP position in `encoding`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LocationError`] if the position is not within the text of
+    /// `index`.
+    pub fn from_position(
+        position: Position,
+        index: &LineIndex,
+        encoding: &PositionEncodingKind,
+    ) -> Result<Self, LocationError> {
+        let utf8 = index.convert(position, encoding, &PositionEncodingKind::UTF8)?;
+        Ok(Self::new(utf8.line, utf8.character))
+    }
+
+    /// Returns the LSP position of the point in `encoding`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LocationError`] if the point is not within the text of
+    /// `index`.
+    pub fn to_position(
+        &self,
+        index: &LineIndex,
+        encoding: &PositionEncodingKind,
+    ) -> Result<Position, LocationError> {
+        index.convert(
+            self.as_utf8_position(),
+            &PositionEncodingKind::UTF8,
+            encoding,
+        )
+    }
+
+    fn as_utf8_position(&self) -> Position {
+        Position::new(self.row, self.column)
+    }
+}
+
+/// An edit of a source text, in bytes.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::syntax::InputEdit;
+///
+/// let edit = InputEdit::between("fn start() {}", "fn run() {}");
+/// assert_eq!(edit, InputEdit { start_byte: 3, old_end_byte: 8, new_end_byte: 6 });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputEdit {
+    /// The start of the edited text
+    pub start_byte: usize,
+    /// The end of the edited text before the edit
+    pub old_end_byte: usize,
+    /// The end of the edited text after the edit
+    pub new_end_byte: usize,
+}
+
+impl InputEdit {
+    /// Returns the smallest edit that turns `old` into `new`, i.e. the text
+    /// between their common prefix and their common suffix.
+    pub fn between(old: &str, new: &str) -> Self {
+        let mut prefix = old
+            .bytes()
+            .zip(new.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(prefix) || !new.is_char_boundary(prefix) {
+            prefix -= 1;
+        }
+
+        let max_suffix = old.len().min(new.len()) - prefix;
+        let mut suffix = old
+            .bytes()
+            .rev()
+            .zip(new.bytes().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(old.len() - suffix) || !new.is_char_boundary(new.len() - suffix)
+        {
+            suffix -= 1;
+        }
+
+        Self {
+            start_byte: prefix,
+            old_end_byte: old.len() - suffix,
+            new_end_byte: new.len() - suffix,
+        }
+    }
+
+    /// Returns the change of the length of the text.
+    pub fn delta(&self) -> isize {
+        self.new_end_byte as isize - self.old_end_byte as isize
+    }
+}
+
+/// A concrete syntax tree of a source text.
+///
+/// The parser is error tolerant: unexpected tokens become
+/// [`SyntaxKind::Error`] nodes and missing closing delimiters empty error
+/// nodes, so every text has a tree. After an edit, [`SyntaxTree::reparse`]
+/// reuses the top-level statements the edit did not touch and only parses
+/// the text around the edit again.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::syntax::{InputEdit, Language, SyntaxKind, SyntaxTree};
+///
+/// let old = "fn main() {}\n\nstruct Point { x: f64 }\n";
+/// let tree = SyntaxTree::parse(Language::Rust, old);
+/// let kinds = tree.root().children().iter().map(|n| n.kind()).collect::<Vec<_>>();
+/// assert_eq!(kinds, [SyntaxKind::Function, SyntaxKind::Struct]);
+///
+/// let new = "fn main() { run(); }\n\nstruct Point { x: f64 }\n";
+/// let tree = tree.reparse(new, &InputEdit::between(old, new));
+/// assert_eq!(tree, SyntaxTree::parse(Language::Rust, new));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SyntaxTree {
+    language: Language,
+    root: SyntaxNode,
+    reparsed: ByteRange<usize>,
+}
+
+/// Trees are equal if their languages and nodes are, regardless of how they
+/// were parsed.
+impl PartialEq for SyntaxTree {
+    fn eq(&self, other: &Self) -> bool {
+        self.language == other.language && self.root == other.root
+    }
+}
+
+impl Eq for SyntaxTree {}
+
+impl SyntaxTree {
+    /// Parses a source text.
+    pub fn parse(language: Language, text: &str) -> Self {
+        Self {
+            language,
+            root: parser::parse(language, text),
+            reparsed: 0..text.len(),
+        }
+    }
+
+    /// Parses an edited source text, reusing the parts of this tree the edit
+    /// did not touch.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text after the edit
+    /// * `edit` - The edit of the text this tree was parsed from
+    pub fn reparse(&self, text: &str, edit: &InputEdit) -> Self {
+        let top = self.root.children();
+        let old_len = self.root.end_byte();
+        if edit.start_byte > edit.old_end_byte
+            || edit.old_end_byte > old_len
+            || old_len.checked_add_signed(edit.delta()) != Some(text.len())
+        {
+            return Self::parse(self.language, text);
+        }
+
+        // The statements the edit touches, plus an untouched one on either
+        // side, whose boundaries are known to be statement boundaries
+        let first = top
+            .iter()
+            .position(|node| node.end_byte() >= edit.start_byte)
+            .unwrap_or(top.len());
+        let last = top
+            .iter()
+            .rposition(|node| node.start_byte() <= edit.old_end_byte)
+            .map_or(0, |index| index + 1);
+        let lo = first.saturating_sub(1);
+        let hi = (last.max(first) + 1).min(top.len());
+
+        let start = match lo {
+            0 => 0,
+            _ => top.get(lo).map_or(edit.start_byte, SyntaxNode::start_byte),
+        };
+        let old_end = match top.get(hi.wrapping_sub(1)) {
+            Some(node) if hi < top.len() => node.end_byte(),
+            _ => old_len,
+        };
+        let Some(end) = old_end.checked_add_signed(edit.delta()) else {
+            return Self::parse(self.language, text);
+        };
+
+        let Some(middle) = parser::parse_region(self.language, text, start..end) else {
+            return Self::parse(self.language, text);
+        };
+        let mut children = top.get(..lo).unwrap_or_default().to_vec();
+        children.extend(middle);
+        children.extend(top.get(hi..).unwrap_or_default().iter().map(|node| {
+            let mut node = node.clone();
+            node.shift(edit.delta());
+            node
+        }));
+
+        Self {
+            language: self.language,
+            root: SyntaxNode::new(SyntaxKind::SourceFile, 0..text.len(), children),
+            reparsed: start..end,
+        }
+    }
+
+    /// Returns the language of the tree.
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// Returns the root node, a [`SyntaxKind::SourceFile`].
+    pub fn root(&self) -> &SyntaxNode {
+        &self.root
+    }
+
+    /// Returns the byte range of the text that was parsed to create the tree;
+    /// the whole text unless the tree was [reparsed](SyntaxTree::reparse).
+    pub fn reparsed_range(&self) -> ByteRange<usize> {
+        self.reparsed.clone()
+    }
+
+    /// Returns true if the tree contains [`SyntaxKind::Error`] nodes.
+    pub fn has_errors(&self) -> bool {
+        self.root.has_error()
+    }
+
+    /// Returns the nodes that contain a byte range, from the root down to the
+    /// smallest one.
+    ///
+    /// When an empty range lies on the boundary of two nodes, the one that
+    /// starts there is chosen.
+    pub fn ancestors_for_byte_range(&self, start: usize, end: usize) -> Vec<&SyntaxNode> {
+        let mut ancestors = Vec::new();
+        if start > end || end > self.root.end_byte() {
+            return ancestors;
+        }
+
+        let mut node = &self.root;
+        loop {
+            ancestors.push(node);
+            let contains = |child: &&SyntaxNode| {
+                child.start_byte() <= start
+                    && end <= child.end_byte()
+                    && child.start_byte() < child.end_byte()
+            };
+            let child = node
+                .children
+                .iter()
+                .filter(contains)
+                .find(|child| start < child.end_byte())
+                .or_else(|| node.children.iter().find(contains));
+            match child {
+                Some(child) => node = child,
+                None => return ancestors,
+            }
+        }
+    }
+
+    /// Returns the smallest node that contains a byte range.
+    pub fn descendant_for_byte_range(&self, start: usize, end: usize) -> Option<&SyntaxNode> {
+        self.ancestors_for_byte_range(start, end).pop()
+    }
+
+    /// Returns the nodes that contain an LSP range, from the root down to the
+    /// smallest one.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LocationError`] if the range is not within the text of
+    /// `index`.
+    pub fn ancestors_for_range(
+        &self,
+        range: Range,
+        index: &LineIndex,
+        encoding: &PositionEncodingKind,
+    ) -> Result<Vec<&SyntaxNode>, LocationError> {
+        let start = index.offset(range.start, encoding)?;
+        let end = index.offset(range.end, encoding)?;
+        Ok(self.ancestors_for_byte_range(start, end))
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/tree.rs"]
+mod tests;
@@ -0,0 +1,204 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use proptest::prelude::*;
+use serde_json::json;
+
+use super::*;
+
+fn all_errors() -> Vec<ContextEngineError> {
+    vec![
+        ContextEngineError::Transport {
+            server: "rust-analyzer".to_string(),
+            method: Some("initialize".to_string()),
+            message: "broken pipe".to_string(),
+        },
+        ContextEngineError::ServerCrashed {
+            server: "pyright".to_string(),
+            exit_code: Some(101),
+            message: "panicked".to_string(),
+        },
+        ContextEngineError::Parse {
+            uri: Some(Uri::from_str("file:///src/main.rs").unwrap()),
+            message: "unexpected token".to_string(),
+        },
+        ContextEngineError::CacheCorrupted {
+            path: "/cache/graph.bin".to_string(),
+            message: "schema mismatch".to_string(),
+        },
+        ContextEngineError::Configuration {
+            key: Some("servers.rust.command".to_string()),
+            message: "missing".to_string(),
+        },
+        ContextEngineError::Timeout {
+            server: "tsserver".to_string(),
+            method: "textDocument/hover".to_string(),
+            timeout_ms: 500,
+        },
+        ContextEngineError::Cancelled {
+            method: "workspace/symbol".to_string(),
+        },
+        ContextEngineError::from(LocationError::InvalidRange),
+        ContextEngineError::DocumentNotOpen {
+            uri: Uri::from_str("file:///src/lib.rs").unwrap(),
+        },
+        ContextEngineError::VersionConflict {
+            uri: Uri::from_str("file:///src/lib.rs").unwrap(),
+            current: 3,
+            received: 2,
+        },
+        ContextEngineError::ServerError {
+            server: "rust-analyzer".to_string(),
+            method: "textDocument/rename".to_string(),
+            code: -32801,
+            message: "content modified".to_string(),
+        },
+        ContextEngineError::Edit {
+            uri: Some(Uri::from_str("file:///src/new.rs").unwrap()),
+            message: "the file already exists".to_string(),
+        },
+    ]
+}
+
+#[test]
+fn test_error_codes_are_stable_and_unique() {
+    let codes = all_errors()
+        .iter()
+        .map(|error| error.code().as_i64())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        codes,
+        vec![
+            -32001, -32002, -32003, -32004, -32005, -32006, -32800, -32602, -32007, -32801, -32008,
+            -32009
+        ]
+    );
+}
+
+#[test]
+fn test_error_context_accessors() {
+    let errors = all_errors();
+    let [
+        transport,
+        _,
+        parse,
+        _,
+        _,
+        timeout,
+        cancelled,
+        location,
+        not_open,
+        conflict,
+        server_error,
+        edit,
+    ] = &errors[..]
+    else {
+        unreachable!()
+    };
+
+    assert_eq!(transport.server(), Some("rust-analyzer"));
+    assert_eq!(transport.method(), Some("initialize"));
+    assert_eq!(timeout.method(), Some("textDocument/hover"));
+    assert_eq!(cancelled.server(), None);
+    assert_eq!(location.method(), None);
+    assert_eq!(
+        parse.uri().map(|uri| uri.as_str()),
+        Some("file:///src/main.rs")
+    );
+    assert_eq!(conflict.uri(), not_open.uri());
+    assert_eq!(timeout.uri(), None);
+    assert_eq!(server_error.server(), Some("rust-analyzer"));
+    assert_eq!(server_error.method(), Some("textDocument/rename"));
+    assert_eq!(
+        edit.to_string(),
+        "Cannot apply edit to file:///src/new.rs: the file already exists"
+    );
+
+    let transient = errors
+        .iter()
+        .filter(|error| error.is_transient())
+        .map(ContextEngineError::code)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        transient,
+        vec![
+            ErrorCode::Transport,
+            ErrorCode::ServerCrashed,
+            ErrorCode::Timeout,
+            ErrorCode::ServerError
+        ]
+    );
+}
+
+#[test]
+fn test_error_json_rpc_conversion() {
+    let error = ContextEngineError::ServerCrashed {
+        server: "pyright".to_string(),
+        exit_code: Some(1),
+        message: "out of memory".to_string(),
+    };
+
+    let json_rpc = JsonRpcError::from(error.clone());
+    assert_eq!(json_rpc.code, -32002);
+    assert_eq!(json_rpc.message, error.to_string());
+    assert_eq!(
+        serde_json::to_value(&json_rpc).unwrap(),
+        json!({
+            "code": -32002,
+            "message": "Language server 'pyright' crashed (exit code Some(1)): out of memory",
+            "data": {
+                "kind": "server_crashed",
+                "server": "pyright",
+                "exit_code": 1,
+                "message": "out of memory",
+            },
+        })
+    );
+
+    let location = ContextEngineError::from(LocationError::PositionOutOfBounds("line 9".into()));
+    assert_eq!(location.to_string(), "Position out of bounds: line 9");
+    assert_eq!(
+        location.to_json_rpc_error().data.unwrap(),
+        json!({ "kind": "location", "source": { "PositionOutOfBounds": "line 9" } })
+    );
+
+    // JSON-RPC errors without data omit the member
+    let plain = JsonRpcError::new(-32601, "Method not found");
+    assert_eq!(
+        serde_json::to_string(&plain).unwrap(),
+        r#"{"code":-32601,"message":"Method not found"}"#
+    );
+}
+
+#[test]
+fn test_error_from_serde_json() {
+    let parse_error = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+    let error = ContextEngineError::from(parse_error);
+    assert_eq!(error.code(), ErrorCode::Parse);
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_error_serialization_roundtrip(
+        index in 0usize..11,
+        message in "\\PC{0,20}",
+    ) {
+        let mut error = all_errors().swap_remove(index);
+        if let ContextEngineError::Transport { message: m, .. }
+        | ContextEngineError::Parse { message: m, .. }
+        | ContextEngineError::CacheCorrupted { message: m, .. } = &mut error
+        {
+            *m = message;
+        }
+
+        let json = serde_json::to_string(&error).unwrap();
+        let deserialized: ContextEngineError = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!(&deserialized, &error);
+
+        let data = error.to_json_rpc_error().data.unwrap();
+        let from_data: ContextEngineError = serde_json::from_value(data).unwrap();
+        prop_assert_eq!(from_data.code(), error.code());
+    }
+}
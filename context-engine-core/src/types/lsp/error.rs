@@ -13,6 +13,10 @@ pub enum LocationError {
     #[error("Invalid URI format: {0}")]
     InvalidUri(String),
 
+    /// Error that occurs when a file URI names a file on another machine
+    #[error("URI authority is not local: {0}")]
+    NonLocalUri(String),
+
     /// Error that occurs when an invalid position is created
     #[error("Invalid position: line={line}, character={character}, reason={reason}")]
     InvalidPosition {
@@ -29,7 +33,36 @@ pub enum LocationError {
     #[error("Invalid range: start position occurs after end position")]
     InvalidRange,
 
+    /// Error that occurs when the text edits of a batch overlap each other
+    #[error("Invalid text edits: edit ranges overlap")]
+    OverlappingEdits,
+
     /// Error that occurs when a position is out of bounds
     #[error("Position out of bounds: {0}")]
     PositionOutOfBounds(String),
+
+    /// Error that occurs when a byte or char offset lies past the end of a
+    /// document
+    #[error("Offset out of bounds: offset={offset}, len={len}")]
+    OffsetOutOfBounds {
+        /// The offset that caused the error
+        offset: usize,
+        /// The length of the document in the offset's unit
+        len: usize,
+    },
+
+    /// Error that occurs when an offset does not point to a valid position,
+    /// e.g. into the middle of a multi-byte character
+    #[error("Invalid offset: offset={offset}, reason={reason}")]
+    InvalidOffset {
+        /// The offset that caused the error
+        offset: usize,
+        /// Reason why the offset is invalid
+        reason: String,
+    },
+
+    /// Error that occurs when a position encoding other than UTF-8, UTF-16 or
+    /// UTF-32 is requested
+    #[error("Unsupported position encoding: {0}")]
+    UnsupportedEncoding(String),
 }
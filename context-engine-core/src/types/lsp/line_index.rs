@@ -0,0 +1,481 @@
+//! Position-encoding-aware line index.
+//!
+//! LSP positions count the `character` component in code units of a
+//! negotiated [`PositionEncodingKind`] (UTF-16 by default). This module
+//! provides [`LineIndex`], which is built once over the text of a document
+//! and converts between LSP [`Position`]s, byte offsets and char offsets for
+//! every encoding the protocol defines.
+
+use std::collections::HashMap;
+
+use lsp_types::{Position, PositionEncodingKind};
+
+use crate::types::LocationError;
+
+/// The code unit used to count the `character` component of a [`Position`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Utf8,
+    Utf16,
+    Utf32,
+}
+
+impl TryFrom<&PositionEncodingKind> for Encoding {
+    type Error = LocationError;
+
+    fn try_from(kind: &PositionEncodingKind) -> Result<Self, Self::Error> {
+        match kind.as_str() {
+            "utf-8" => Ok(Encoding::Utf8),
+            "utf-16" => Ok(Encoding::Utf16),
+            "utf-32" => Ok(Encoding::Utf32),
+            other => Err(LocationError::UnsupportedEncoding(other.to_string())),
+        }
+    }
+}
+
+/// A non-ASCII character on a line, i.e. a character that occupies more than
+/// one UTF-8 code unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WideChar {
+    /// Byte offset of the character relative to the start of its line.
+    start: usize,
+    /// Number of UTF-8 code units of the character (2, 3 or 4).
+    len_utf8: usize,
+}
+
+impl Encoding {
+    /// The name of the code units of the encoding, for error messages.
+    fn unit_name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8 code units",
+            Encoding::Utf16 => "UTF-16 code units",
+            Encoding::Utf32 => "characters",
+        }
+    }
+}
+
+impl WideChar {
+    /// Number of code units the character occupies in the given encoding.
+    fn units(&self, encoding: Encoding) -> usize {
+        match encoding {
+            Encoding::Utf8 => self.len_utf8,
+            Encoding::Utf16 if self.len_utf8 == 4 => 2,
+            Encoding::Utf16 | Encoding::Utf32 => 1,
+        }
+    }
+}
+
+/// Index over the lines of a text document.
+///
+/// The index records where every line starts and ends and which characters on
+/// each line are wider than one byte. This is enough to convert between LSP
+/// [`Position`]s in any [`PositionEncodingKind`], byte offsets and char
+/// (Unicode scalar value) offsets without keeping the text itself.
+///
+/// Lines are terminated by `\n`, `\r\n` or a lone `\r`, as mandated by the
+/// LSP specification. The terminator is not part of the line, so a position
+/// can never point inside it.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::types::{LineIndex, Position, PositionEncodingKind};
+///
+/// let index = LineIndex::new("let crab = \"🦀\";\nlet x = 1;\n");
+///
+/// // The closing quote follows the crab emoji, which is two UTF-16 code
+/// // units, one UTF-32 code unit and four UTF-8 code units wide.
+/// let utf16 = Position::new(0, 14);
+/// let offset = index.offset(utf16, &PositionEncodingKind::UTF16).unwrap();
+/// assert_eq!(offset, 16);
+///
+/// let utf32 = index.position(offset, &PositionEncodingKind::UTF32).unwrap();
+/// assert_eq!(utf32, Position::new(0, 13));
+///
+/// // Positions past the end of a line are rejected
+/// assert!(index.offset(Position::new(1, 11), &PositionEncodingKind::UTF16).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    /// Byte offset at which each line starts. Never empty.
+    line_starts: Vec<usize>,
+    /// Byte offset at which the content of each line ends, excluding the line
+    /// terminator.
+    line_ends: Vec<usize>,
+    /// Char offset at which each line starts.
+    char_starts: Vec<usize>,
+    /// Characters wider than one byte, keyed by line number.
+    wide_chars: HashMap<u32, Vec<WideChar>>,
+    /// Total length of the text in bytes.
+    len: usize,
+    /// Total length of the text in chars.
+    char_len: usize,
+}
+
+impl LineIndex {
+    /// Builds a line index over the given text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::LineIndex;
+    ///
+    /// let index = LineIndex::new("fn main() {}\r\n// end");
+    /// assert_eq!(index.line_count(), 2);
+    /// assert_eq!(index.len(), 20);
+    /// ```
+    pub fn new(text: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut line_ends = Vec::new();
+        let mut char_starts = vec![0];
+        let mut wide_chars: HashMap<u32, Vec<WideChar>> = HashMap::new();
+
+        let mut char_count = 0;
+        let mut chars = text.char_indices().peekable();
+        while let Some((offset, ch)) = chars.next() {
+            char_count += 1;
+            let line_start = line_starts.last().copied().unwrap_or(0);
+
+            let terminator_len = match ch {
+                '\n' => 1,
+                '\r' if matches!(chars.peek(), Some((_, '\n'))) => {
+                    chars.next();
+                    char_count += 1;
+                    2
+                }
+                '\r' => 1,
+                _ => {
+                    if ch.len_utf8() > 1 {
+                        let line = u32::try_from(line_starts.len() - 1).unwrap_or(u32::MAX);
+                        wide_chars.entry(line).or_default().push(WideChar {
+                            start: offset - line_start,
+                            len_utf8: ch.len_utf8(),
+                        });
+                    }
+                    continue;
+                }
+            };
+
+            line_ends.push(offset);
+            line_starts.push(offset + terminator_len);
+            char_starts.push(char_count);
+        }
+        line_ends.push(text.len());
+
+        Self {
+            line_starts,
+            line_ends,
+            char_starts,
+            wide_chars,
+            len: text.len(),
+            char_len: char_count,
+        }
+    }
+
+    /// Returns the number of lines in the indexed text.
+    ///
+    /// A text always has at least one (possibly empty) line, and a trailing
+    /// line terminator starts a new, empty line.
+    pub fn line_count(&self) -> u32 {
+        u32::try_from(self.line_starts.len()).unwrap_or(u32::MAX)
+    }
+
+    /// Returns the length of the indexed text in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the indexed text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the length of the indexed text in chars.
+    pub fn char_len(&self) -> usize {
+        self.char_len
+    }
+
+    /// Returns the position just past the last character of the text.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LocationError::UnsupportedEncoding`] if the encoding is not
+    /// one of UTF-8, UTF-16 or UTF-32.
+    pub fn end_position(&self, encoding: &PositionEncodingKind) -> Result<Position, LocationError> {
+        self.position(self.len, encoding)
+    }
+
+    /// Converts a position into a byte offset into the indexed text.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The position to convert
+    /// * `encoding` - The encoding in which `position.character` is counted
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(usize)` - The byte offset of the position
+    /// * `Err(LocationError)` - If the line or character lies outside the text,
+    ///   the character splits a multi-unit character, or the encoding is not
+    ///   supported
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{LineIndex, Position, PositionEncodingKind};
+    ///
+    /// let index = LineIndex::new("ä = 1\nb = 2");
+    ///
+    /// let utf8 = index.offset(Position::new(0, 2), &PositionEncodingKind::UTF8);
+    /// assert_eq!(utf8.unwrap(), 2);
+    ///
+    /// let utf16 = index.offset(Position::new(0, 1), &PositionEncodingKind::UTF16);
+    /// assert_eq!(utf16.unwrap(), 2);
+    ///
+    /// // UTF-8 offset 1 points into the middle of 'ä'
+    /// assert!(index.offset(Position::new(0, 1), &PositionEncodingKind::UTF8).is_err());
+    /// ```
+    pub fn offset(
+        &self,
+        position: Position,
+        encoding: &PositionEncodingKind,
+    ) -> Result<usize, LocationError> {
+        let encoding = Encoding::try_from(encoding)?;
+        let (line_start, line_end) = self.line_bounds(position)?;
+        let target = position.character as usize;
+
+        // `extra` accumulates how many more bytes than code units the wide
+        // characters before the target occupy.
+        let mut extra = 0;
+        for wide in self.line_wide_chars(position.line) {
+            let unit_start = wide.start - extra;
+            if target <= unit_start {
+                break;
+            }
+
+            let units = wide.units(encoding);
+            if target < unit_start + units {
+                return Err(LocationError::InvalidPosition {
+                    line: position.line,
+                    character: position.character,
+                    reason: "character splits a multi-unit character".to_string(),
+                });
+            }
+            extra += wide.len_utf8 - units;
+        }
+
+        let offset = line_start + target + extra;
+        if offset > line_end {
+            let narrowing = self
+                .line_wide_chars(position.line)
+                .iter()
+                .map(|wide| wide.len_utf8 - wide.units(encoding))
+                .sum::<usize>();
+            return Err(LocationError::PositionOutOfBounds(format!(
+                "character {} is past the end of line {} ({} {} long)",
+                position.character,
+                position.line,
+                line_end - line_start - narrowing,
+                encoding.unit_name()
+            )));
+        }
+
+        Ok(offset)
+    }
+
+    /// Converts a byte offset into the indexed text into a position.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The byte offset to convert
+    /// * `encoding` - The encoding in which the returned `character` is counted
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Position)` - The position at the offset
+    /// * `Err(LocationError)` - If the offset lies past the end of the text, is
+    ///   not on a char boundary or points inside a line terminator, or the
+    ///   encoding is not supported
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{LineIndex, Position, PositionEncodingKind};
+    ///
+    /// let index = LineIndex::new("a\n𝔘 = 1");
+    ///
+    /// let position = index.position(7, &PositionEncodingKind::UTF16).unwrap();
+    /// assert_eq!(position, Position::new(1, 3));
+    ///
+    /// let position = index.position(7, &PositionEncodingKind::UTF32).unwrap();
+    /// assert_eq!(position, Position::new(1, 2));
+    /// ```
+    pub fn position(
+        &self,
+        offset: usize,
+        encoding: &PositionEncodingKind,
+    ) -> Result<Position, LocationError> {
+        let encoding = Encoding::try_from(encoding)?;
+        if offset > self.len {
+            return Err(LocationError::OffsetOutOfBounds {
+                offset,
+                len: self.len,
+            });
+        }
+
+        let line_number = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let line = u32::try_from(line_number).unwrap_or(u32::MAX);
+        let (line_start, line_end) = self.line_bounds(Position::new(line, 0))?;
+        if offset > line_end {
+            return Err(LocationError::InvalidOffset {
+                offset,
+                reason: "offset points inside a line terminator".to_string(),
+            });
+        }
+
+        let column = offset - line_start;
+        let mut units = column;
+        for wide in self.line_wide_chars(line) {
+            if wide.start >= column {
+                break;
+            }
+            if column < wide.start + wide.len_utf8 {
+                return Err(LocationError::InvalidOffset {
+                    offset,
+                    reason: "offset is not on a char boundary".to_string(),
+                });
+            }
+            units = units - wide.len_utf8 + wide.units(encoding);
+        }
+
+        Ok(Position::new(
+            line,
+            u32::try_from(units).unwrap_or(u32::MAX),
+        ))
+    }
+
+    /// Converts a position into a char offset into the indexed text.
+    ///
+    /// Char offsets count Unicode scalar values from the start of the text,
+    /// including line terminators (`\r\n` counts as two chars).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{LineIndex, Position, PositionEncodingKind};
+    ///
+    /// let index = LineIndex::new("🦀\n🦀!");
+    /// let offset = index.char_offset(Position::new(1, 2), &PositionEncodingKind::UTF16);
+    /// assert_eq!(offset.unwrap(), 3);
+    /// ```
+    pub fn char_offset(
+        &self,
+        position: Position,
+        encoding: &PositionEncodingKind,
+    ) -> Result<usize, LocationError> {
+        let offset = self.offset(position, encoding)?;
+        let utf32 = self.position(offset, &PositionEncodingKind::UTF32)?;
+        let line_char_start = self
+            .char_starts
+            .get(utf32.line as usize)
+            .copied()
+            .unwrap_or(self.char_len);
+
+        Ok(line_char_start + utf32.character as usize)
+    }
+
+    /// Converts a char offset into the indexed text into a position.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the char offset lies past the end of the text or
+    /// inside a `\r\n` line terminator, or if the encoding is not supported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{LineIndex, Position, PositionEncodingKind};
+    ///
+    /// let index = LineIndex::new("🦀\n🦀!");
+    /// let position = index.position_at_char(3, &PositionEncodingKind::UTF8);
+    /// assert_eq!(position.unwrap(), Position::new(1, 4));
+    /// ```
+    pub fn position_at_char(
+        &self,
+        char_offset: usize,
+        encoding: &PositionEncodingKind,
+    ) -> Result<Position, LocationError> {
+        if char_offset > self.char_len {
+            return Err(LocationError::OffsetOutOfBounds {
+                offset: char_offset,
+                len: self.char_len,
+            });
+        }
+
+        let line_number = self
+            .char_starts
+            .partition_point(|&start| start <= char_offset)
+            - 1;
+        let line = u32::try_from(line_number).unwrap_or(u32::MAX);
+        let line_char_start = self.char_starts.get(line_number).copied().unwrap_or(0);
+        let column = u32::try_from(char_offset - line_char_start).unwrap_or(u32::MAX);
+
+        let utf32 = Position::new(line, column);
+        let offset = self
+            .offset(utf32, &PositionEncodingKind::UTF32)
+            .map_err(|_| LocationError::InvalidOffset {
+                offset: char_offset,
+                reason: "char offset points inside a line terminator".to_string(),
+            })?;
+
+        self.position(offset, encoding)
+    }
+
+    /// Converts a position from one encoding into another.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{LineIndex, Position, PositionEncodingKind};
+    ///
+    /// let index = LineIndex::new("größe");
+    /// let converted = index.convert(
+    ///     Position::new(0, 4),
+    ///     &PositionEncodingKind::UTF16,
+    ///     &PositionEncodingKind::UTF8,
+    /// );
+    /// assert_eq!(converted.unwrap(), Position::new(0, 6));
+    /// ```
+    pub fn convert(
+        &self,
+        position: Position,
+        from: &PositionEncodingKind,
+        to: &PositionEncodingKind,
+    ) -> Result<Position, LocationError> {
+        let offset = self.offset(position, from)?;
+        self.position(offset, to)
+    }
+
+    /// Returns the byte offsets of the start and the end of the content of the
+    /// position's line.
+    fn line_bounds(&self, position: Position) -> Result<(usize, usize), LocationError> {
+        let line = position.line as usize;
+        match (self.line_starts.get(line), self.line_ends.get(line)) {
+            (Some(&start), Some(&end)) => Ok((start, end)),
+            _ => Err(LocationError::PositionOutOfBounds(format!(
+                "line {} is past the end of the document ({} lines)",
+                position.line,
+                self.line_count()
+            ))),
+        }
+    }
+
+    /// Returns the wide characters of a line in ascending order.
+    fn line_wide_chars(&self, line: u32) -> &[WideChar] {
+        self.wide_chars.get(&line).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/line_index.rs"]
+mod tests;
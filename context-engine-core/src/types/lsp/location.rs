@@ -5,7 +5,7 @@
 
 use std::str::FromStr;
 
-use lsp_types::{Location, Range, Uri};
+use lsp_types::{Location, PositionEncodingKind, Range, TextEdit, Uri};
 
 use crate::types::{LocationError, RangeExt, UriExt};
 
@@ -90,6 +90,41 @@ pub trait LocationExt {
     /// assert_eq!(location.filename(), Some("main.rs".to_string()));
     /// ```
     fn filename(&self) -> Option<String>;
+
+    /// Maps the location through a batch of text edits applied to its
+    /// document.
+    ///
+    /// See [`RangeExt::rebased`] for how the range is mapped.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(Location))` - The location in the edited document
+    /// * `Ok(None)` - If the code of the location was deleted or replaced
+    /// * `Err(LocationError)` - If the edits overlap or the encoding is not
+    ///   supported
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{
+    ///     Location, LocationExt, Position, PositionEncodingKind, Range, TextEdit,
+    /// };
+    ///
+    /// let range = Range::new(Position::new(4, 0), Position::new(6, 1));
+    /// let location = Location::validated("file:///src/main.rs", range).unwrap();
+    ///
+    /// // Two lines are deleted above the location
+    /// let edit = TextEdit::new(Range::new(Position::new(1, 0), Position::new(3, 0)), String::new());
+    /// let rebased = location.rebased(&[edit], &PositionEncodingKind::UTF16).unwrap().unwrap();
+    ///
+    /// assert_eq!(rebased.uri, location.uri);
+    /// assert_eq!(rebased.range, Range::new(Position::new(2, 0), Position::new(4, 1)));
+    /// ```
+    fn rebased(
+        &self,
+        edits: &[TextEdit],
+        encoding: &PositionEncodingKind,
+    ) -> Result<Option<Location>, LocationError>;
 }
 
 impl LocationExt for Location {
@@ -119,6 +154,17 @@ impl LocationExt for Location {
     fn filename(&self) -> Option<String> {
         self.uri.filename()
     }
+
+    fn rebased(
+        &self,
+        edits: &[TextEdit],
+        encoding: &PositionEncodingKind,
+    ) -> Result<Option<Location>, LocationError> {
+        Ok(self
+            .range
+            .rebased(edits, encoding)?
+            .map(|range| Location::new(self.uri.clone(), range)))
+    }
 }
 
 #[cfg(test)]
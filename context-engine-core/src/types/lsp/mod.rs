@@ -1,6 +1,7 @@
 //! Types for working with source code locations, positions, ranges, and URIs.
 
 pub mod error;
+pub mod line_index;
 pub mod location;
 pub mod position;
 pub mod range;
@@ -3,7 +3,9 @@
 //! This module provides extension methods for the [`lsp_types::Position`] type,
 //! adding functionality specific to the Context Engine's needs.
 
-use lsp_types::Position;
+use lsp_types::{Position, PositionEncodingKind};
+
+use crate::types::{LineIndex, LocationError};
 
 /// Extension methods for Position
 ///
@@ -63,6 +65,75 @@ pub trait PositionExt {
     /// assert!(!pos1.is_after(&pos2)); // Not after
     /// ```
     fn is_after(&self, other: &Position) -> bool;
+
+    /// Converts the position into a byte offset into the text indexed by
+    /// `index`.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The line index of the document the position points into
+    /// * `encoding` - The encoding in which the `character` component is
+    ///   counted
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(usize)` - The byte offset of the position
+    /// * `Err(LocationError)` - If the position does not exist in the text
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{LineIndex, Position, PositionEncodingKind, PositionExt};
+    ///
+    /// let index = LineIndex::new("fn café() {}");
+    /// let position = Position::new(0, 8);
+    ///
+    /// assert_eq!(position.to_offset(&index, &PositionEncodingKind::UTF16).unwrap(), 9);
+    /// assert!(position.to_offset(&index, &PositionEncodingKind::UTF8).is_ok());
+    /// ```
+    fn to_offset(
+        &self,
+        index: &LineIndex,
+        encoding: &PositionEncodingKind,
+    ) -> Result<usize, LocationError>;
+
+    /// Validates the position against the text indexed by `index`.
+    ///
+    /// A position is valid if its line exists in the text and its character
+    /// lies on the line (the end of the line included) without splitting a
+    /// multi-unit character.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Position)` - A copy of the position if it is valid
+    /// * `Err(LocationError)` - [`LocationError::PositionOutOfBounds`] if the
+    ///   position lies outside the text, or another error describing why the
+    ///   position is invalid
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{
+    ///     LineIndex, LocationError, Position, PositionEncodingKind, PositionExt,
+    /// };
+    ///
+    /// let index = LineIndex::new("let x = 1;\n");
+    ///
+    /// assert!(Position::new(0, 10).validated_in(&index, &PositionEncodingKind::UTF16).is_ok());
+    /// assert!(matches!(
+    ///     Position::new(0, 11).validated_in(&index, &PositionEncodingKind::UTF16),
+    ///     Err(LocationError::PositionOutOfBounds(_))
+    /// ));
+    /// assert!(matches!(
+    ///     Position::new(2, 0).validated_in(&index, &PositionEncodingKind::UTF16),
+    ///     Err(LocationError::PositionOutOfBounds(_))
+    /// ));
+    /// ```
+    fn validated_in(
+        &self,
+        index: &LineIndex,
+        encoding: &PositionEncodingKind,
+    ) -> Result<Position, LocationError>;
 }
 
 impl PositionExt for Position {
@@ -73,6 +144,22 @@ impl PositionExt for Position {
     fn is_after(&self, other: &Position) -> bool {
         self.line > other.line || (self.line == other.line && self.character > other.character)
     }
+
+    fn to_offset(
+        &self,
+        index: &LineIndex,
+        encoding: &PositionEncodingKind,
+    ) -> Result<usize, LocationError> {
+        index.offset(*self, encoding)
+    }
+
+    fn validated_in(
+        &self,
+        index: &LineIndex,
+        encoding: &PositionEncodingKind,
+    ) -> Result<Position, LocationError> {
+        index.offset(*self, encoding).map(|_| *self)
+    }
 }
 
 #[cfg(test)]
@@ -3,9 +3,12 @@
 //! This module provides extension methods for the [`lsp_types::Range`] type,
 //! adding functionality specific to the Context Engine's needs.
 
-use lsp_types::{Position, Range};
+use std::cmp::Ordering;
+use std::ops;
 
-use crate::types::{LocationError, PositionExt};
+use lsp_types::{Position, PositionEncodingKind, Range, TextEdit};
+
+use crate::types::{LineIndex, LocationError, PositionExt};
 
 /// Extension methods for Range
 ///
@@ -165,6 +168,234 @@ pub trait RangeExt {
     fn contains_range(&self, range: &Range) -> bool {
         self.contains_position(&range.start) && self.contains_position(&range.end)
     }
+
+    /// Validates the range against the text indexed by `index`.
+    ///
+    /// A range is valid if both of its positions are valid in the text (see
+    /// [`PositionExt::validated_in`]) and its start is not after its end.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The line index of the document the range points into
+    /// * `encoding` - The encoding in which the `character` components are
+    ///   counted
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Range)` - A copy of the range if it is valid
+    /// * `Err(LocationError)` - If either position is invalid or the range is
+    ///   reversed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{LineIndex, Position, PositionEncodingKind, Range, RangeExt};
+    ///
+    /// let index = LineIndex::new("struct Ünïcode;");
+    ///
+    /// let range = Range::new(Position::new(0, 7), Position::new(0, 14));
+    /// assert!(range.validated_in(&index, &PositionEncodingKind::UTF16).is_ok());
+    ///
+    /// // In UTF-8 the same range ends in the middle of the line
+    /// let utf8 = range.to_offsets(&index, &PositionEncodingKind::UTF8).unwrap();
+    /// assert_eq!(utf8, 7..14);
+    ///
+    /// let too_long = Range::new(Position::new(0, 7), Position::new(0, 16));
+    /// assert!(too_long.validated_in(&index, &PositionEncodingKind::UTF16).is_err());
+    /// ```
+    fn validated_in(
+        &self,
+        index: &LineIndex,
+        encoding: &PositionEncodingKind,
+    ) -> Result<Range, LocationError>;
+
+    /// Converts the range into a range of byte offsets into the text indexed
+    /// by `index`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ops::Range<usize>)` - The byte offsets of the range
+    /// * `Err(LocationError)` - If the range is not valid in the text (see
+    ///   [`RangeExt::validated_in`])
+    fn to_offsets(
+        &self,
+        index: &LineIndex,
+        encoding: &PositionEncodingKind,
+    ) -> Result<ops::Range<usize>, LocationError>;
+
+    /// Returns the intersection of two ranges.
+    ///
+    /// Ranges that only touch at one position intersect in an empty range at
+    /// that position.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(Range)` - The range covered by both ranges
+    /// * `None` - If the ranges are disjoint
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{Position, Range, RangeExt};
+    ///
+    /// let a = Range::new(Position::new(1, 0), Position::new(3, 0));
+    /// let b = Range::new(Position::new(2, 5), Position::new(4, 0));
+    /// let c = Range::new(Position::new(5, 0), Position::new(6, 0));
+    ///
+    /// assert_eq!(a.intersect(&b), Some(Range::new(Position::new(2, 5), Position::new(3, 0))));
+    /// assert_eq!(a.intersect(&c), None);
+    /// ```
+    fn intersect(&self, other: &Range) -> Option<Range>;
+
+    /// Returns the smallest range that covers both ranges, including any gap
+    /// between them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{Position, Range, RangeExt};
+    ///
+    /// let a = Range::new(Position::new(1, 0), Position::new(1, 5));
+    /// let b = Range::new(Position::new(3, 2), Position::new(3, 4));
+    ///
+    /// assert_eq!(a.cover(&b), Range::new(Position::new(1, 0), Position::new(3, 4)));
+    /// ```
+    fn cover(&self, other: &Range) -> Range;
+
+    /// Returns the union of two ranges if it is itself a range.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(Range)` - The union if the ranges overlap or touch
+    /// * `None` - If there is a gap between the ranges
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{Position, Range, RangeExt};
+    ///
+    /// let a = Range::new(Position::new(1, 0), Position::new(1, 5));
+    /// let b = Range::new(Position::new(1, 5), Position::new(1, 9));
+    /// let c = Range::new(Position::new(2, 0), Position::new(2, 1));
+    ///
+    /// assert_eq!(a.union(&b), Some(Range::new(Position::new(1, 0), Position::new(1, 9))));
+    /// assert_eq!(a.union(&c), None);
+    /// ```
+    fn union(&self, other: &Range) -> Option<Range> {
+        self.touches(other).then(|| self.cover(other))
+    }
+
+    /// Returns true if the ranges share at least one character.
+    ///
+    /// Ranges that only touch at one position do not overlap. An empty range
+    /// overlaps a range that contains its position strictly inside.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{Position, Range, RangeExt};
+    ///
+    /// let a = Range::new(Position::new(1, 0), Position::new(1, 5));
+    /// let b = Range::new(Position::new(1, 4), Position::new(2, 0));
+    /// let c = Range::new(Position::new(1, 5), Position::new(2, 0));
+    ///
+    /// assert!(a.overlaps(&b));
+    /// assert!(!a.overlaps(&c));
+    /// ```
+    fn overlaps(&self, other: &Range) -> bool;
+
+    /// Returns true if the ranges overlap or are adjacent, i.e. share at
+    /// least one position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{Position, Range, RangeExt};
+    ///
+    /// let a = Range::new(Position::new(1, 0), Position::new(1, 5));
+    /// let b = Range::new(Position::new(1, 5), Position::new(2, 0));
+    /// let c = Range::new(Position::new(1, 6), Position::new(2, 0));
+    ///
+    /// assert!(a.touches(&b));
+    /// assert!(!a.touches(&c));
+    /// ```
+    fn touches(&self, other: &Range) -> bool;
+
+    /// Compares two ranges by their start position and then by their end
+    /// position.
+    ///
+    /// This is the document order of ranges; it can be used to sort ranges,
+    /// which do not implement [`Ord`] themselves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{Position, Range, RangeExt};
+    ///
+    /// let mut ranges = vec![
+    ///     Range::new(Position::new(2, 0), Position::new(2, 1)),
+    ///     Range::new(Position::new(1, 0), Position::new(3, 0)),
+    ///     Range::new(Position::new(1, 0), Position::new(1, 1)),
+    /// ];
+    /// ranges.sort_by(Range::cmp_position);
+    ///
+    /// assert_eq!(ranges[0], Range::new(Position::new(1, 0), Position::new(1, 1)));
+    /// assert_eq!(ranges[2], Range::new(Position::new(2, 0), Position::new(2, 1)));
+    /// ```
+    fn cmp_position(&self, other: &Range) -> Ordering;
+
+    /// Maps the range through a batch of text edits, so that it points at the
+    /// same code in the edited document.
+    ///
+    /// `edits` follows the semantics of LSP `TextEdit[]`: all edit ranges refer
+    /// to the document before any edit is applied and must not overlap. To
+    /// follow several consecutive batches, rebase through each batch in turn.
+    ///
+    /// * Edits before the range shift it.
+    /// * Edits inside the range grow or shrink it.
+    /// * Edits overlapping one end of the range cut that end off.
+    /// * An edit that replaces exactly the range maps it onto the new text.
+    /// * An edit that replaces more than the range removes it.
+    ///
+    /// Insertions at the start of the range are placed before the range;
+    /// insertions at its end are placed after it.
+    ///
+    /// # Arguments
+    ///
+    /// * `edits` - The batch of edits applied to the document
+    /// * `encoding` - The encoding in which the `character` components of the
+    ///   range and the edits are counted
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(Range))` - The range in the edited document
+    /// * `Ok(None)` - If the code of the range was deleted or replaced
+    /// * `Err(LocationError)` - If the edits overlap or the encoding is not
+    ///   supported
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{Position, PositionEncodingKind, Range, RangeExt, TextEdit};
+    ///
+    /// // `let total = add(a, b);` where `add(a, b)` is cached at 12..21
+    /// let cached = Range::new(Position::new(0, 12), Position::new(0, 21));
+    ///
+    /// let edits = [
+    ///     // Rename `total` to `sum`
+    ///     TextEdit::new(Range::new(Position::new(0, 4), Position::new(0, 9)), "sum".to_string()),
+    ///     // Insert a line above
+    ///     TextEdit::new(Range::new(Position::new(0, 0), Position::new(0, 0)), "// sum\n".to_string()),
+    /// ];
+    ///
+    /// let rebased = cached.rebased(&edits, &PositionEncodingKind::UTF16).unwrap();
+    /// assert_eq!(rebased, Some(Range::new(Position::new(1, 10), Position::new(1, 19))));
+    /// ```
+    fn rebased(
+        &self,
+        edits: &[TextEdit],
+        encoding: &PositionEncodingKind,
+    ) -> Result<Option<Range>, LocationError>;
 }
 
 impl RangeExt for Range {
@@ -186,6 +417,161 @@ impl RangeExt for Range {
 
         Ok(Range::new(start, end))
     }
+
+    fn validated_in(
+        &self,
+        index: &LineIndex,
+        encoding: &PositionEncodingKind,
+    ) -> Result<Range, LocationError> {
+        self.to_offsets(index, encoding).map(|_| *self)
+    }
+
+    fn to_offsets(
+        &self,
+        index: &LineIndex,
+        encoding: &PositionEncodingKind,
+    ) -> Result<ops::Range<usize>, LocationError> {
+        let start = self.start.to_offset(index, encoding)?;
+        let end = self.end.to_offset(index, encoding)?;
+        if start > end {
+            return Err(LocationError::InvalidRange);
+        }
+
+        Ok(start..end)
+    }
+
+    fn intersect(&self, other: &Range) -> Option<Range> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        (start <= end).then(|| Range::new(start, end))
+    }
+
+    fn cover(&self, other: &Range) -> Range {
+        Range::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    fn overlaps(&self, other: &Range) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    fn touches(&self, other: &Range) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    fn cmp_position(&self, other: &Range) -> Ordering {
+        self.start
+            .cmp(&other.start)
+            .then_with(|| self.end.cmp(&other.end))
+    }
+
+    fn rebased(
+        &self,
+        edits: &[TextEdit],
+        encoding: &PositionEncodingKind,
+    ) -> Result<Option<Range>, LocationError> {
+        let mut sorted = edits.iter().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| a.range.cmp_position(&b.range));
+        if sorted
+            .windows(2)
+            .any(|pair| matches!(pair, [a, b] if b.range.start < a.range.end))
+        {
+            return Err(LocationError::OverlappingEdits);
+        }
+
+        // Applying the edits back to front keeps the positions of the edits
+        // that are still to be applied valid.
+        let mut range = *self;
+        for edit in sorted.into_iter().rev() {
+            match rebase_through_edit(range, edit, encoding)? {
+                Some(rebased) => range = rebased,
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(range))
+    }
+}
+
+/// Maps a range through a single text edit.
+fn rebase_through_edit(
+    range: Range,
+    edit: &TextEdit,
+    encoding: &PositionEncodingKind,
+) -> Result<Option<Range>, LocationError> {
+    let edited = edit.range;
+    let inserted_end = inserted_text_end(edit, encoding)?;
+
+    if edited == range {
+        return Ok(Some(Range::new(edited.start, inserted_end)));
+    }
+
+    // The edit lies entirely after the range
+    if edited.start >= range.end && !(range.is_empty() && edited.end <= range.start) {
+        return Ok(Some(range));
+    }
+
+    // The edit lies entirely before the range
+    if edited.end <= range.start {
+        return Ok(Some(Range::new(
+            shift_position(range.start, edited, inserted_end),
+            shift_position(range.end, edited, inserted_end),
+        )));
+    }
+
+    // The edit replaces more than the range
+    if edited.start <= range.start && edited.end >= range.end {
+        return Ok(None);
+    }
+
+    let start = if edited.start < range.start {
+        // The edit cuts off the start of the range
+        inserted_end
+    } else {
+        range.start
+    };
+    let end = if edited.end > range.end {
+        // The edit cuts off the end of the range
+        edited.start
+    } else {
+        shift_position(range.end, edited, inserted_end)
+    };
+
+    Ok(Some(Range::new(start, end.max(start))))
+}
+
+/// Returns the position at which the text inserted by an edit ends.
+fn inserted_text_end(
+    edit: &TextEdit,
+    encoding: &PositionEncodingKind,
+) -> Result<Position, LocationError> {
+    let inserted = LineIndex::new(&edit.new_text).end_position(encoding)?;
+    if inserted.line == 0 {
+        Ok(Position::new(
+            edit.range.start.line,
+            edit.range.start.character + inserted.character,
+        ))
+    } else {
+        Ok(Position::new(
+            edit.range.start.line + inserted.line,
+            inserted.character,
+        ))
+    }
+}
+
+/// Shifts a position at or after the end of an edited range to where it ends
+/// up once the edit is applied.
+fn shift_position(position: Position, edited: Range, inserted_end: Position) -> Position {
+    if position.line == edited.end.line {
+        Position::new(
+            inserted_end.line,
+            inserted_end.character + (position.character - edited.end.character),
+        )
+    } else {
+        Position::new(
+            position.line - edited.end.line + inserted_end.line,
+            position.character,
+        )
+    }
 }
 
 #[cfg(test)]
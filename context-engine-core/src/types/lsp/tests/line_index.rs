@@ -0,0 +1,237 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+use crate::types::{PositionExt, Range, RangeExt};
+
+const ENCODINGS: [PositionEncodingKind; 3] = [
+    PositionEncodingKind::UTF8,
+    PositionEncodingKind::UTF16,
+    PositionEncodingKind::UTF32,
+];
+
+#[test]
+fn test_line_index_lines() {
+    let index = LineIndex::new("");
+    assert_eq!(index.line_count(), 1);
+    assert!(index.is_empty());
+    assert_eq!(
+        index.end_position(&PositionEncodingKind::UTF16).unwrap(),
+        Position::new(0, 0)
+    );
+
+    let index = LineIndex::new("a\nb\r\nc\rd\n");
+    assert_eq!(index.line_count(), 5);
+    assert_eq!(index.len(), 9);
+    assert_eq!(index.char_len(), 9);
+
+    let utf16 = PositionEncodingKind::UTF16;
+    assert_eq!(index.offset(Position::new(0, 1), &utf16).unwrap(), 1);
+    assert_eq!(index.offset(Position::new(1, 0), &utf16).unwrap(), 2);
+    assert_eq!(index.offset(Position::new(1, 1), &utf16).unwrap(), 3);
+    assert_eq!(index.offset(Position::new(2, 0), &utf16).unwrap(), 5);
+    assert_eq!(index.offset(Position::new(3, 0), &utf16).unwrap(), 7);
+    assert_eq!(index.offset(Position::new(4, 0), &utf16).unwrap(), 9);
+    assert_eq!(index.end_position(&utf16).unwrap(), Position::new(4, 0));
+
+    // The line terminator is not part of the line
+    assert!(matches!(
+        index.offset(Position::new(1, 2), &utf16),
+        Err(LocationError::PositionOutOfBounds(_))
+    ));
+    assert!(matches!(
+        index.offset(Position::new(5, 0), &utf16),
+        Err(LocationError::PositionOutOfBounds(_))
+    ));
+
+    // Offsets between '\r' and '\n' do not map to a position
+    assert!(matches!(
+        index.position(4, &utf16),
+        Err(LocationError::InvalidOffset { .. })
+    ));
+    assert!(matches!(
+        index.position(10, &utf16),
+        Err(LocationError::OffsetOutOfBounds { offset: 10, len: 9 })
+    ));
+}
+
+#[test]
+fn test_line_index_encodings() {
+    // 'é' is 2 UTF-8 units, '€' is 3, '🦀' is 4 (and a UTF-16 surrogate pair)
+    let index = LineIndex::new("x\né€🦀y");
+    let end_of_crab = 1 + 1 + 2 + 3 + 4;
+
+    let cases = [
+        (PositionEncodingKind::UTF8, 9),
+        (PositionEncodingKind::UTF16, 4),
+        (PositionEncodingKind::UTF32, 3),
+    ];
+    for (encoding, character) in cases {
+        let position = Position::new(1, character);
+        assert_eq!(index.offset(position, &encoding).unwrap(), end_of_crab);
+        assert_eq!(index.position(end_of_crab, &encoding).unwrap(), position);
+    }
+
+    // Splitting the surrogate pair of the crab
+    assert!(matches!(
+        index.offset(Position::new(1, 3), &PositionEncodingKind::UTF16),
+        Err(LocationError::InvalidPosition {
+            line: 1,
+            character: 3,
+            ..
+        })
+    ));
+    // Splitting the UTF-8 sequence of the euro sign
+    assert!(matches!(
+        index.offset(Position::new(1, 3), &PositionEncodingKind::UTF8),
+        Err(LocationError::InvalidPosition { .. })
+    ));
+    assert!(matches!(
+        index.position(5, &PositionEncodingKind::UTF16),
+        Err(LocationError::InvalidOffset { .. })
+    ));
+
+    // The length of the line is reported in the encoding of the position
+    let cases = [
+        (PositionEncodingKind::UTF8, "(10 UTF-8 code units long)"),
+        (PositionEncodingKind::UTF16, "(5 UTF-16 code units long)"),
+        (PositionEncodingKind::UTF32, "(4 characters long)"),
+    ];
+    for (encoding, length) in cases {
+        assert!(matches!(
+            index.offset(Position::new(1, 20), &encoding),
+            Err(LocationError::PositionOutOfBounds(message)) if message.ends_with(length)
+        ));
+    }
+
+    // Unknown encodings are rejected
+    let unknown = PositionEncodingKind::new("utf-7");
+    assert_eq!(
+        index.offset(Position::new(0, 0), &unknown),
+        Err(LocationError::UnsupportedEncoding("utf-7".to_string()))
+    );
+}
+
+#[test]
+fn test_line_index_char_offsets() {
+    let index = LineIndex::new("αβ\r\n🦀z");
+    let utf16 = PositionEncodingKind::UTF16;
+
+    assert_eq!(index.char_offset(Position::new(0, 2), &utf16).unwrap(), 2);
+    assert_eq!(index.char_offset(Position::new(1, 0), &utf16).unwrap(), 4);
+    assert_eq!(index.char_offset(Position::new(1, 2), &utf16).unwrap(), 5);
+    assert_eq!(index.char_offset(Position::new(1, 3), &utf16).unwrap(), 6);
+
+    assert_eq!(
+        index.position_at_char(5, &utf16).unwrap(),
+        Position::new(1, 2)
+    );
+    assert_eq!(
+        index.position_at_char(6, &utf16).unwrap(),
+        Position::new(1, 3)
+    );
+    assert!(index.position_at_char(3, &utf16).is_err());
+    assert!(index.position_at_char(7, &utf16).is_err());
+}
+
+#[test]
+fn test_position_and_range_validation() {
+    let index = LineIndex::new("fn 😀() {}\n");
+    let utf16 = PositionEncodingKind::UTF16;
+
+    assert!(Position::new(0, 10).validated_in(&index, &utf16).is_ok());
+    assert!(Position::new(1, 0).validated_in(&index, &utf16).is_ok());
+    assert!(matches!(
+        Position::new(0, 11).validated_in(&index, &utf16),
+        Err(LocationError::PositionOutOfBounds(_))
+    ));
+    assert!(Position::new(0, 4).validated_in(&index, &utf16).is_err());
+
+    let range = Range::new(Position::new(0, 3), Position::new(0, 5));
+    assert_eq!(range.validated_in(&index, &utf16).unwrap(), range);
+    assert_eq!(range.to_offsets(&index, &utf16).unwrap(), 3..7);
+
+    let reversed = Range::new(Position::new(0, 5), Position::new(0, 3));
+    assert_eq!(
+        reversed.validated_in(&index, &utf16),
+        Err(LocationError::InvalidRange)
+    );
+}
+
+/// Returns all byte offsets of `text` that are char boundaries and not inside
+/// a `\r\n` terminator.
+fn valid_offsets(text: &str) -> Vec<usize> {
+    (0..=text.len())
+        .filter(|&offset| text.is_char_boundary(offset))
+        .filter(|&offset| {
+            !(offset > 0
+                && text.as_bytes().get(offset - 1) == Some(&b'\r')
+                && text.as_bytes().get(offset) == Some(&b'\n'))
+        })
+        .collect()
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_line_index_offset_roundtrip(text in "(\\PC|\r|\n|\r\n){0,40}") {
+        let index = LineIndex::new(&text);
+
+        for offset in valid_offsets(&text) {
+            for encoding in &ENCODINGS {
+                let position = index.position(offset, encoding).unwrap();
+                prop_assert_eq!(index.offset(position, encoding).unwrap(), offset);
+                prop_assert!(position.validated_in(&index, encoding).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn prop_line_index_matches_std_encodings(text in "(\\PC|\n){0,40}") {
+        let index = LineIndex::new(&text);
+
+        for offset in valid_offsets(&text) {
+            let before = text.get(..offset).unwrap();
+            let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let prefix = text.get(line_start..offset).unwrap();
+            let line = before.matches('\n').count() as u32;
+
+            let utf16 = index.position(offset, &PositionEncodingKind::UTF16).unwrap();
+            prop_assert_eq!(utf16, Position::new(line, prefix.encode_utf16().count() as u32));
+
+            let utf32 = index.position(offset, &PositionEncodingKind::UTF32).unwrap();
+            prop_assert_eq!(utf32, Position::new(line, prefix.chars().count() as u32));
+
+            let utf8 = index.position(offset, &PositionEncodingKind::UTF8).unwrap();
+            prop_assert_eq!(utf8, Position::new(line, prefix.len() as u32));
+
+            let char_offset = before.chars().count();
+            prop_assert_eq!(
+                index.char_offset(utf16, &PositionEncodingKind::UTF16).unwrap(),
+                char_offset
+            );
+            prop_assert_eq!(
+                index.position_at_char(char_offset, &PositionEncodingKind::UTF16).unwrap(),
+                utf16
+            );
+        }
+    }
+
+    #[test]
+    fn prop_line_index_convert_is_reversible(text in "\\PC{0,20}", column in 0u32..40) {
+        let index = LineIndex::new(&text);
+        let position = Position::new(0, column);
+
+        if let Ok(utf8) = index.convert(
+            position,
+            &PositionEncodingKind::UTF16,
+            &PositionEncodingKind::UTF8,
+        ) {
+            let back = index
+                .convert(utf8, &PositionEncodingKind::UTF8, &PositionEncodingKind::UTF16)
+                .unwrap();
+            prop_assert_eq!(back, position);
+        }
+    }
+}
@@ -60,8 +60,247 @@ fn test_range_extension_methods() {
     assert!(Range::validated(mid, start).is_err());
 }
 
+fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+    Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1))
+}
+
+fn edit(start: (u32, u32), end: (u32, u32), new_text: &str) -> TextEdit {
+    TextEdit::new(range(start, end), new_text.to_string())
+}
+
+/// Applies a batch of edits to a text.
+fn apply_edits(text: &str, edits: &[TextEdit], encoding: &PositionEncodingKind) -> String {
+    let index = LineIndex::new(text);
+    let mut offsets = edits
+        .iter()
+        .map(|edit| {
+            (
+                edit.range.to_offsets(&index, encoding).unwrap(),
+                &edit.new_text,
+            )
+        })
+        .collect::<Vec<_>>();
+    offsets.sort_by_key(|(offsets, _)| std::cmp::Reverse(offsets.start));
+
+    let mut edited = text.to_string();
+    for (offsets, new_text) in offsets {
+        edited.replace_range(offsets, new_text);
+    }
+    edited
+}
+
+#[test]
+fn test_range_algebra() {
+    let a = range((1, 0), (3, 0));
+    let b = range((2, 5), (4, 0));
+    let adjacent = range((3, 0), (3, 4));
+    let disjoint = range((5, 0), (6, 0));
+    let empty_inside = range((2, 0), (2, 0));
+
+    // Intersection
+    assert_eq!(a.intersect(&b), Some(range((2, 5), (3, 0))));
+    assert_eq!(a.intersect(&adjacent), Some(range((3, 0), (3, 0))));
+    assert_eq!(a.intersect(&disjoint), None);
+    assert_eq!(a.intersect(&empty_inside), Some(empty_inside));
+
+    // Cover and union
+    assert_eq!(a.cover(&disjoint), range((1, 0), (6, 0)));
+    assert_eq!(a.union(&b), Some(range((1, 0), (4, 0))));
+    assert_eq!(a.union(&adjacent), Some(range((1, 0), (3, 4))));
+    assert_eq!(a.union(&disjoint), None);
+
+    // Overlaps and touches
+    assert!(a.overlaps(&b));
+    assert!(!a.overlaps(&adjacent));
+    assert!(a.touches(&adjacent));
+    assert!(!a.touches(&disjoint));
+    assert!(a.overlaps(&empty_inside));
+    assert!(!empty_inside.overlaps(&empty_inside));
+    assert!(empty_inside.touches(&empty_inside));
+
+    // Ordering
+    let mut ranges = vec![disjoint, b, adjacent, a, empty_inside];
+    ranges.sort_by(Range::cmp_position);
+    assert_eq!(ranges, vec![a, empty_inside, b, adjacent, disjoint]);
+}
+
+#[test]
+fn test_range_rebased() {
+    let utf16 = PositionEncodingKind::UTF16;
+    let cached = range((2, 4), (2, 10));
+
+    // Edits after the range, including an insertion at its end
+    let after = [edit((2, 10), (2, 10), "x"), edit((3, 0), (5, 0), "")];
+    assert_eq!(cached.rebased(&after, &utf16).unwrap(), Some(cached));
+
+    // Insertion at the start of the range moves it
+    let at_start = [edit((2, 4), (2, 4), "pub ")];
+    assert_eq!(
+        cached.rebased(&at_start, &utf16).unwrap(),
+        Some(range((2, 8), (2, 14)))
+    );
+
+    // Multi-line insertion before the range on the same line
+    let before = [edit((2, 0), (2, 2), "a\nbcd")];
+    assert_eq!(
+        cached.rebased(&before, &utf16).unwrap(),
+        Some(range((3, 5), (3, 11)))
+    );
+
+    // Edit inside the range grows it
+    let inside = [edit((2, 5), (2, 6), "long\nname")];
+    assert_eq!(
+        cached.rebased(&inside, &utf16).unwrap(),
+        Some(range((2, 4), (3, 8)))
+    );
+
+    // Edits overlapping the ends of the range cut them off
+    let overlap_start = [edit((2, 0), (2, 6), "ab")];
+    assert_eq!(
+        cached.rebased(&overlap_start, &utf16).unwrap(),
+        Some(range((2, 2), (2, 6)))
+    );
+    let overlap_end = [edit((2, 8), (3, 0), "")];
+    assert_eq!(
+        cached.rebased(&overlap_end, &utf16).unwrap(),
+        Some(range((2, 4), (2, 8)))
+    );
+
+    // Replacing exactly the range maps it onto the new text
+    let rename = [edit((2, 4), (2, 10), "renamed_fn")];
+    assert_eq!(
+        cached.rebased(&rename, &utf16).unwrap(),
+        Some(range((2, 4), (2, 14)))
+    );
+
+    // Replacing more than the range removes it
+    let deleted = [edit((1, 0), (3, 0), "")];
+    assert_eq!(cached.rebased(&deleted, &utf16).unwrap(), None);
+
+    // The character offsets of inserted text depend on the encoding
+    let emoji = [edit((2, 0), (2, 0), "🦀")];
+    assert_eq!(
+        cached.rebased(&emoji, &utf16).unwrap(),
+        Some(range((2, 6), (2, 12)))
+    );
+    assert_eq!(
+        cached.rebased(&emoji, &PositionEncodingKind::UTF8).unwrap(),
+        Some(range((2, 8), (2, 14)))
+    );
+
+    // Overlapping edits are rejected
+    let overlapping = [edit((0, 0), (0, 5), ""), edit((0, 3), (0, 8), "")];
+    assert_eq!(
+        cached.rebased(&overlapping, &utf16),
+        Err(LocationError::OverlappingEdits)
+    );
+}
+
+/// Strategy producing a multi-line text and `count` sorted char boundary
+/// offsets into it.
+fn text_with_offsets(count: usize) -> impl Strategy<Value = (String, Vec<usize>)> {
+    (
+        "([a-zé🦀 ]{0,6}\n){1,5}",
+        prop::collection::vec(any::<prop::sample::Index>(), count),
+    )
+        .prop_map(|(text, indices)| {
+            let boundaries = (0..=text.len())
+                .filter(|&offset| text.is_char_boundary(offset))
+                .collect::<Vec<_>>();
+            let mut offsets = indices
+                .iter()
+                .map(|index| *index.get(&boundaries))
+                .collect::<Vec<_>>();
+            offsets.sort_unstable();
+            (text, offsets)
+        })
+}
+
 // Property-based tests
 proptest! {
+    #[test]
+    fn prop_range_algebra_is_consistent(
+        a_start in (0u32..5, 0u32..5),
+        a_end in (0u32..5, 0u32..5),
+        b_start in (0u32..5, 0u32..5),
+        b_end in (0u32..5, 0u32..5),
+    ) {
+        let a = range(a_start.min(a_end), a_start.max(a_end));
+        let b = range(b_start.min(b_end), b_start.max(b_end));
+
+        prop_assert_eq!(a.overlaps(&b), b.overlaps(&a));
+        prop_assert_eq!(a.touches(&b), b.touches(&a));
+        prop_assert_eq!(a.intersect(&b), b.intersect(&a));
+        prop_assert_eq!(a.intersect(&b).is_some(), a.touches(&b));
+        if a.overlaps(&b) {
+            prop_assert!(a.touches(&b));
+        }
+        if a.overlaps(&b) && !a.is_empty() && !b.is_empty() {
+            prop_assert!(!a.intersect(&b).unwrap().is_empty());
+        }
+        if let Some(intersection) = a.intersect(&b) {
+            prop_assert!(a.contains_range(&intersection));
+            prop_assert!(b.contains_range(&intersection));
+        }
+
+        let cover = a.cover(&b);
+        prop_assert!(cover.contains_range(&a) && cover.contains_range(&b));
+        prop_assert_eq!(a.union(&b).is_some(), a.touches(&b));
+
+        prop_assert_eq!(a.cmp_position(&b), b.cmp_position(&a).reverse());
+        prop_assert_eq!(a.cmp_position(&b) == std::cmp::Ordering::Equal, a == b);
+    }
+
+    #[test]
+    fn prop_range_rebased_keeps_text_of_untouched_ranges(
+        (text, offsets) in text_with_offsets(6),
+        before_text in "[a-z🦀\n]{0,4}",
+        after_text in "[a-z🦀\n]{0,4}",
+        encoding in prop::sample::select(vec![
+            PositionEncodingKind::UTF8,
+            PositionEncodingKind::UTF16,
+            PositionEncodingKind::UTF32,
+        ]),
+    ) {
+        let index = LineIndex::new(&text);
+        let position = |offset: usize| index.position(offset, &encoding).unwrap();
+        let [e1, e2, r1, r2, e3, e4] = offsets[..] else { unreachable!() };
+
+        let cached = Range::new(position(r1), position(r2));
+        let edits = [
+            TextEdit::new(Range::new(position(e3), position(e4)), after_text),
+            TextEdit::new(Range::new(position(e1), position(e2)), before_text),
+        ];
+        // An empty edit equal to an empty range replaces the range
+        prop_assume!(edits.iter().all(|edit| edit.range != cached));
+        let edited = apply_edits(&text, &edits, &encoding);
+
+        let rebased = cached.rebased(&edits, &encoding).unwrap().unwrap();
+        let rebased_offsets = rebased.to_offsets(&LineIndex::new(&edited), &encoding).unwrap();
+        prop_assert_eq!(edited.get(rebased_offsets), text.get(r1..r2));
+    }
+
+    #[test]
+    fn prop_range_rebased_through_own_replacement(
+        (text, offsets) in text_with_offsets(2),
+        new_text in "[a-z🦀\n]{0,6}",
+    ) {
+        let index = LineIndex::new(&text);
+        let utf16 = PositionEncodingKind::UTF16;
+        let [start, end] = offsets[..] else { unreachable!() };
+
+        let cached = Range::new(
+            index.position(start, &utf16).unwrap(),
+            index.position(end, &utf16).unwrap(),
+        );
+        let edits = [TextEdit::new(cached, new_text.clone())];
+        let edited = apply_edits(&text, &edits, &utf16);
+
+        let rebased = cached.rebased(&edits, &utf16).unwrap().unwrap();
+        let rebased_offsets = rebased.to_offsets(&LineIndex::new(&edited), &utf16).unwrap();
+        prop_assert_eq!(edited.get(rebased_offsets), Some(new_text.as_str()));
+    }
+
     #[test]
     fn prop_range_contains_all_intermediate_positions(
         start_line in 0u32..1000,
@@ -41,8 +41,82 @@ fn test_uri_extension_methods() {
     assert!(invalid_syntax.is_err());
 }
 
+#[test]
+fn test_uri_to_file_path() {
+    let uri = Uri::from_str("file:///src/main.rs").unwrap();
+    assert_eq!(uri.to_file_path().unwrap(), PathBuf::from("/src/main.rs"));
+
+    let uri = Uri::from_str("file:///my%20project/%F0%9F%A6%80.rs").unwrap();
+    assert_eq!(
+        uri.to_file_path().unwrap(),
+        PathBuf::from("/my project/🦀.rs")
+    );
+
+    let uri = Uri::from_str("file://localhost/src/lib.rs").unwrap();
+    assert_eq!(uri.to_file_path().unwrap(), PathBuf::from("/src/lib.rs"));
+
+    // Query and fragment are not part of the path
+    let uri = Uri::from_str("file:///src/lib.rs?query#fragment").unwrap();
+    assert_eq!(uri.to_file_path().unwrap(), PathBuf::from("/src/lib.rs"));
+
+    let uri = Uri::from_str("file:///src/a%2Fb.rs").unwrap();
+    assert!(matches!(
+        uri.to_file_path(),
+        Err(LocationError::InvalidUri(_))
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_uri_to_file_path_rejects_remote_authority() {
+    let uri = Uri::from_str("file://build-server/src/main.rs").unwrap();
+    assert_eq!(
+        uri.to_file_path(),
+        Err(LocationError::NonLocalUri("build-server".to_string()))
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_uri_from_file_path() {
+    let uri = Uri::from_file_path(Path::new("/src/main.rs")).unwrap();
+    assert_eq!(uri.as_str(), "file:///src/main.rs");
+
+    let uri = Uri::from_file_path(Path::new("/")).unwrap();
+    assert_eq!(uri.as_str(), "file:///");
+    assert_eq!(uri.to_file_path().unwrap(), PathBuf::from("/"));
+
+    let path = Path::new("/a b/100%/#tag/what?/ünïcode.rs");
+    let uri = Uri::from_file_path(path).unwrap();
+    assert_eq!(
+        uri.as_str(),
+        "file:///a%20b/100%25/%23tag/what%3F/%C3%BCn%C3%AFcode.rs"
+    );
+    assert!(uri.is_file_uri());
+    assert_eq!(uri.filename(), Some("%C3%BCn%C3%AFcode.rs".to_string()));
+    assert_eq!(uri.to_file_path().unwrap(), path);
+
+    assert!(Uri::from_file_path(Path::new("relative/path.rs")).is_err());
+}
+
 // Property-based tests
 proptest! {
+    #[cfg(unix)]
+    #[test]
+    fn prop_uri_file_path_roundtrip(
+        segments in prop::collection::vec("[^/\\x00]{1,12}", 1..5)
+    ) {
+        let path = PathBuf::from(format!("/{}", segments.join("/")));
+
+        let uri = Uri::from_file_path(&path).unwrap();
+        prop_assert!(uri.is_file_uri());
+        prop_assert_eq!(uri.to_file_path().unwrap(), path.clone());
+
+        // Round trip through the string representation as well
+        let reparsed = Uri::from_str(uri.as_str()).unwrap();
+        prop_assert_eq!(reparsed.to_file_path().unwrap(), path);
+    }
+
     #[test]
     fn prop_uri_roundtrip(filename in "[a-zA-Z0-9_]+\\.rs") {
         let uri_str = format!("file:///{filename}");
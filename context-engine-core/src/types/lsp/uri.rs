@@ -4,7 +4,7 @@
 //! which is used as the URI type in the LSP protocol. It adds functionality
 //! specific to the Context Engine's needs, particularly for file URIs.
 
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::str::FromStr;
 
 use lsp_types::{Location, Range, Uri};
@@ -99,13 +99,17 @@ pub trait UriExt {
 
     /// Extracts the file path from the URI.
     ///
-    /// This only works for file:// URIs.
+    /// This only works for file:// URIs. The scheme is stripped and every
+    /// path segment is percent-decoded. The authority must be empty or
+    /// `localhost`; any other host names a remote machine and is rejected
+    /// (except on Windows, where it is turned into a UNC path). The query and
+    /// fragment components are not part of the path and are ignored.
     ///
     /// # Returns
     ///
     /// * `Ok(PathBuf)` - The file path if it could be extracted
-    /// * `Err(LocationError)` - If the URI is not a file URI or the path is
-    ///   invalid
+    /// * `Err(LocationError)` - If the URI is not a file URI, has a non-local
+    ///   authority or the path is invalid
     ///
     /// # Examples
     ///
@@ -117,6 +121,16 @@ pub trait UriExt {
     /// let path = uri.to_file_path();
     /// assert!(path.is_ok());
     ///
+    /// # #[cfg(unix)]
+    /// # {
+    /// let uri = Uri::from_str("file:///home/me/my%20project/caf%C3%A9.rs").unwrap();
+    /// let path = uri.to_file_path().unwrap();
+    /// assert_eq!(path, std::path::Path::new("/home/me/my project/café.rs"));
+    ///
+    /// let remote = Uri::from_str("file://build-server/src/main.rs").unwrap();
+    /// assert!(remote.to_file_path().is_err());
+    /// # }
+    ///
     /// let http_uri = Uri::from_str("http://example.com/main.rs").unwrap();
     /// let path = http_uri.to_file_path();
     /// assert!(path.is_err());
@@ -152,6 +166,42 @@ pub trait UriExt {
     /// assert!(invalid.is_err());
     /// ```
     fn new_file_uri(uri_str: &str) -> Result<Uri, LocationError>;
+
+    /// Creates a new file URI from an absolute file path.
+    ///
+    /// Every byte of the path that is not allowed verbatim in a URI path
+    /// segment is percent-encoded, so that
+    /// [`to_file_path`](UriExt::to_file_path) returns exactly the same
+    /// path. This includes spaces, non-ASCII characters, `%`, `#` and `?`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The absolute path to convert
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Uri)` - The file URI of the path
+    /// * `Err(LocationError)` - If the path is relative or cannot be
+    ///   represented as a URI
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context_engine_core::types::{Uri, UriExt};
+    /// use std::path::Path;
+    ///
+    /// # #[cfg(unix)]
+    /// # {
+    /// let path = Path::new("/tmp/notes #1/what?.md");
+    /// let uri = Uri::from_file_path(path).unwrap();
+    /// assert_eq!(uri.as_str(), "file:///tmp/notes%20%231/what%3F.md");
+    /// assert_eq!(uri.to_file_path().unwrap(), path);
+    /// # }
+    ///
+    /// // Relative paths have no URI
+    /// assert!(Uri::from_file_path(Path::new("src/main.rs")).is_err());
+    /// ```
+    fn from_file_path(path: &Path) -> Result<Uri, LocationError>;
 }
 
 impl UriExt for Uri {
@@ -183,7 +233,25 @@ impl UriExt for Uri {
             )));
         }
 
-        Ok(PathBuf::from(self.as_str()))
+        let host = self
+            .authority()
+            .map(|authority| authority.host().as_str())
+            .unwrap_or_default();
+        let is_local = host.is_empty() || host.eq_ignore_ascii_case("localhost");
+
+        let mut segments = Vec::new();
+        for segment in self.path().segments() {
+            let decoded = percent_decode(segment.as_str())?;
+            if decoded.contains(&b'/') || decoded.contains(&0) {
+                return Err(LocationError::InvalidUri(format!(
+                    "path segment '{}' decodes to an invalid file name",
+                    segment.as_str()
+                )));
+            }
+            segments.push(decoded);
+        }
+
+        file_path_from_segments(host, is_local, segments)
     }
 
     fn new_file_uri(uri_str: &str) -> Result<Uri, LocationError> {
@@ -200,6 +268,189 @@ impl UriExt for Uri {
 
         Ok(url)
     }
+
+    fn from_file_path(path: &Path) -> Result<Uri, LocationError> {
+        if !path.is_absolute() {
+            return Err(LocationError::InvalidUri(format!(
+                "file path must be absolute, got {}",
+                path.display()
+            )));
+        }
+
+        let uri_str = format!("file://{}", encode_file_path(path)?);
+        Uri::from_str(&uri_str).map_err(|_| LocationError::InvalidUri(uri_str))
+    }
+}
+
+/// Returns true if the byte may appear verbatim in a URI path segment.
+///
+/// These are the `pchar` characters of RFC 3986 except `%`, which always
+/// starts an escape sequence.
+fn is_path_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@".contains(&byte)
+}
+
+/// Percent-encodes a single path segment.
+fn percent_encode_segment(segment: &[u8], out: &mut String) {
+    for &byte in segment {
+        if is_path_char(byte) {
+            out.push(char::from(byte));
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+}
+
+/// Decodes the percent escapes of a URI component into raw bytes.
+fn percent_decode(input: &str) -> Result<Vec<u8>, LocationError> {
+    let mut decoded = Vec::with_capacity(input.len());
+    let mut bytes = input.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte != b'%' {
+            decoded.push(byte);
+            continue;
+        }
+
+        let escape = bytes
+            .next()
+            .zip(bytes.next())
+            .and_then(|(high, low)| Some((hex_value(high)? << 4) | hex_value(low)?))
+            .ok_or_else(|| {
+                LocationError::InvalidUri(format!("invalid percent escape in '{input}'"))
+            })?;
+        decoded.push(escape);
+    }
+
+    Ok(decoded)
+}
+
+/// Returns the value of an ASCII hex digit.
+fn hex_value(digit: u8) -> Option<u8> {
+    char::from(digit)
+        .to_digit(16)
+        .and_then(|value| u8::try_from(value).ok())
+}
+
+/// Encodes an absolute path as the path component of a file URI.
+#[cfg(unix)]
+fn encode_file_path(path: &Path) -> Result<String, LocationError> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut encoded = String::new();
+    for component in path.components() {
+        if component == Component::RootDir {
+            continue;
+        }
+        encoded.push('/');
+        percent_encode_segment(component.as_os_str().as_bytes(), &mut encoded);
+    }
+    if encoded.is_empty() {
+        encoded.push('/');
+    }
+
+    Ok(encoded)
+}
+
+/// Encodes an absolute path as the path component of a file URI, prefixed by
+/// the authority for UNC paths.
+#[cfg(not(unix))]
+fn encode_file_path(path: &Path) -> Result<String, LocationError> {
+    use std::path::Prefix;
+
+    let mut authority = String::new();
+    let mut encoded = String::new();
+    for component in path.components() {
+        let segment = match component {
+            Component::Prefix(prefix) => match prefix.kind() {
+                Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+                    format!("{}:", char::from(letter).to_ascii_uppercase())
+                }
+                Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => {
+                    authority = server.to_string_lossy().into_owned();
+                    share.to_string_lossy().into_owned()
+                }
+                _ => {
+                    return Err(LocationError::InvalidUri(format!(
+                        "unsupported path prefix in {}",
+                        path.display()
+                    )));
+                }
+            },
+            Component::RootDir => continue,
+            Component::CurDir | Component::ParentDir | Component::Normal(_) => component
+                .as_os_str()
+                .to_str()
+                .map(str::to_string)
+                .ok_or_else(|| {
+                    LocationError::InvalidUri(format!(
+                        "path is not valid unicode: {}",
+                        path.display()
+                    ))
+                })?,
+        };
+        encoded.push('/');
+        percent_encode_segment(segment.as_bytes(), &mut encoded);
+    }
+
+    Ok(format!("{authority}{encoded}"))
+}
+
+/// Builds a file path from the decoded segments of a file URI.
+#[cfg(unix)]
+fn file_path_from_segments(
+    host: &str,
+    is_local: bool,
+    segments: Vec<Vec<u8>>,
+) -> Result<PathBuf, LocationError> {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+
+    if !is_local {
+        return Err(LocationError::NonLocalUri(host.to_string()));
+    }
+
+    let mut path = Vec::new();
+    for segment in segments {
+        path.push(b'/');
+        path.extend(segment);
+    }
+    if path.is_empty() {
+        path.push(b'/');
+    }
+
+    Ok(PathBuf::from(OsString::from_vec(path)))
+}
+
+/// Builds a file path from the decoded segments of a file URI.
+#[cfg(not(unix))]
+fn file_path_from_segments(
+    host: &str,
+    is_local: bool,
+    segments: Vec<Vec<u8>>,
+) -> Result<PathBuf, LocationError> {
+    let mut segments = segments
+        .into_iter()
+        .map(|segment| {
+            String::from_utf8(segment)
+                .map_err(|_| LocationError::InvalidUri("path is not valid UTF-8".to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut path = if is_local {
+        // `/C:/Users` becomes `C:\Users`
+        match segments.first() {
+            Some(drive) if drive.len() == 2 && drive.ends_with(':') => {
+                let drive = segments.remove(0);
+                PathBuf::from(format!("{drive}\\"))
+            }
+            _ => PathBuf::from("\\"),
+        }
+    } else {
+        PathBuf::from(format!("\\\\{host}\\"))
+    };
+    path.extend(segments.iter().filter(|segment| !segment.is_empty()));
+
+    Ok(path)
 }
 
 #[cfg(test)]
@@ -14,6 +14,10 @@
 //!       before another position.
 //!     * [`is_after`](PositionExt::is_after): Checks if the position is after
 //!       another position.
+//!     * [`to_offset`](PositionExt::to_offset): Converts the position into a
+//!       byte offset using a [`LineIndex`].
+//!     * [`validated_in`](PositionExt::validated_in): Validates the position
+//!       against the text of a document.
 //! * [`RangeExt`] - Extension trait for [`lsp_types::Range`]. It extends the
 //!   `Range` with the following methods:
 //!     * [`validated`](RangeExt::validated): Constructs a validated range.
@@ -22,6 +26,18 @@
 //!       range contains a given position.
 //!     * [`contains_range`](RangeExt::contains_range): Checks if the range
 //!       contains another range.
+//!     * [`validated_in`](RangeExt::validated_in): Validates the range against
+//!       the text of a document.
+//!     * [`to_offsets`](RangeExt::to_offsets): Converts the range into byte
+//!       offsets using a [`LineIndex`].
+//!     * [`intersect`](RangeExt::intersect), [`cover`](RangeExt::cover) and
+//!       [`union`](RangeExt::union): Combine two ranges.
+//!     * [`overlaps`](RangeExt::overlaps) and [`touches`](RangeExt::touches):
+//!       Check how two ranges relate to each other.
+//!     * [`cmp_position`](RangeExt::cmp_position): Orders ranges in document
+//!       order.
+//!     * [`rebased`](RangeExt::rebased): Maps the range through a batch of
+//!       [`TextEdit`]s.
 //! * [`UriExt`] - Extension trait for [`lsp_types::Uri`]. It extends the `Uri`
 //!   with the following methods:
 //!     * [`is_file_uri`](UriExt::is_file_uri): Returns true if this is a file
@@ -34,14 +50,24 @@
 //!       the `Uri`.
 //!     * [`new_file_uri`](UriExt::new_file_uri): Creates a new `Uri` from a
 //!       string, validating it's a file `Uri`.
+//!     * [`from_file_path`](UriExt::from_file_path): Creates a new file `Uri`
+//!       from an absolute file path.
 //! * [`LocationExt`] - Extension trait for [`lsp_types::Location`]. It extends
 //!   the `Location` with the following methods:
 //!     * [`validated`](LocationExt::validated): Constructs a validated
 //!       `Location`.
 //!     * [`filename`](LocationExt::filename): Returns the filename of the
 //!       `Location`.
+//!     * [`rebased`](LocationExt::rebased): Maps the `Location` through a batch
+//!       of [`TextEdit`]s applied to its document.
 //! * [`LocationError`] - Error types for location-related operations
 //!
+//! ## Structs
+//!
+//! * [`LineIndex`] - Index over the lines of a document that converts between
+//!   [`Position`]s in any [`PositionEncodingKind`], byte offsets and char
+//!   offsets.
+//!
 //! ## Usage Example
 //!
 //! ```
@@ -77,13 +103,14 @@
 //! ```
 
 // Re-export the standard LSP types for convenience
-pub use lsp_types::{Location, Position, Range, Uri};
+pub use lsp_types::{Location, Position, PositionEncodingKind, Range, TextEdit, Uri};
 
 // mod error;
 mod lsp;
 
 // Re-export extension traits for easy access
 pub use lsp::error::LocationError;
+pub use lsp::line_index::LineIndex;
 pub use lsp::location::LocationExt;
 pub use lsp::position::PositionExt;
 pub use lsp::range::RangeExt;
@@ -0,0 +1,259 @@
+//! Unified diffs and the symbols they change.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ContextEngineError;
+use crate::graph::{KnowledgeGraph, SymbolNode, SymbolRef};
+use crate::types::{Position, Range, RangeExt, Uri, UriExt};
+
+/// A changed region of a file: the lines removed from the old version and
+/// the lines that replace them in the new one.
+///
+/// Both sides are ranges of whole lines, from the start of the first line to
+/// the start of the line after the last. A side without lines is an empty
+/// range at the start of the line after the change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hunk {
+    /// The removed lines of the old version
+    pub old: Range,
+    /// The added lines of the new version
+    pub new: Range,
+}
+
+impl Hunk {
+    /// Parses the header of a hunk, e.g. `@@ -3,2 +3 @@ fn main() {`.
+    fn parse(header: &str) -> Option<Self> {
+        let mut parts = header.strip_prefix("@@ ")?.split(' ');
+        let old = lines(parts.next()?.strip_prefix('-')?)?;
+        let new = lines(parts.next()?.strip_prefix('+')?)?;
+        Some(Self { old, new })
+    }
+
+    /// Returns the lines of the new version the hunk changes: the added
+    /// lines, or the line after removed ones.
+    fn changed_lines(&self) -> std::ops::Range<u32> {
+        let start = self.new.start.line;
+        start..self.new.end.line.max(start + 1)
+    }
+}
+
+/// Converts the lines of a side of a hunk header, `start[,count]` with
+/// lines counted from 1, to a range.
+fn lines(spec: &str) -> Option<Range> {
+    let (start, count) = match spec.split_once(',') {
+        Some((start, count)) => (start.parse::<u32>().ok()?, count.parse::<u32>().ok()?),
+        None => (spec.parse::<u32>().ok()?, 1),
+    };
+    // Without lines, `start` is the line before the change
+    let first = match count {
+        0 => start,
+        _ => start.checked_sub(1)?,
+    };
+    let end = first.checked_add(count)?;
+    Some(Range::new(Position::new(first, 0), Position::new(end, 0)))
+}
+
+/// The changes of a file in a diff.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDiff {
+    /// The path of the old version, relative to the root of the diff;
+    /// `None` for an added file
+    pub old_path: Option<PathBuf>,
+    /// The path of the new version, relative to the root of the diff;
+    /// `None` for a deleted file
+    pub new_path: Option<PathBuf>,
+    /// The changed regions, in file order
+    pub hunks: Vec<Hunk>,
+}
+
+impl FileDiff {
+    /// Parses the files of a diff in the unified format of `git diff`.
+    ///
+    /// Only the headers are read: the lines of the hunks are skipped, so
+    /// the diff may have any number of context lines.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ContextEngineError::Parse`] if a hunk header is
+    /// malformed or a hunk precedes the header of its file.
+    pub fn parse(diff: &str) -> Result<Vec<FileDiff>, ContextEngineError> {
+        let mut files: Vec<FileDiff> = Vec::new();
+        // The lines of the current hunk still to skip, old and new
+        let mut remaining = (0u32, 0u32);
+        for line in diff.lines() {
+            if remaining != (0, 0) {
+                match line.chars().next() {
+                    Some('-') => remaining.0 = remaining.0.saturating_sub(1),
+                    Some('+') => remaining.1 = remaining.1.saturating_sub(1),
+                    Some('\\') => {}
+                    _ => {
+                        remaining.0 = remaining.0.saturating_sub(1);
+                        remaining.1 = remaining.1.saturating_sub(1);
+                    }
+                }
+                continue;
+            }
+
+            if let Some(paths) = line.strip_prefix("diff --git ") {
+                let (old, new) = paths.split_once(" b/").unwrap_or((paths, paths));
+                files.push(FileDiff {
+                    old_path: diff_path(old),
+                    new_path: Some(PathBuf::from(new)),
+                    hunks: Vec::new(),
+                });
+                continue;
+            }
+            let Some(file) = files.last_mut() else {
+                if line.starts_with("@@") {
+                    return Err(parse_error("a hunk precedes the header of its file"));
+                }
+                continue;
+            };
+            if let Some(path) = line.strip_prefix("--- ") {
+                file.old_path = diff_path(path);
+            } else if let Some(path) = line.strip_prefix("+++ ") {
+                file.new_path = diff_path(path);
+            } else if let Some(path) = line.strip_prefix("rename from ") {
+                file.old_path = Some(PathBuf::from(unquote(path)));
+            } else if let Some(path) = line.strip_prefix("rename to ") {
+                file.new_path = Some(PathBuf::from(unquote(path)));
+            } else if line.starts_with("new file mode") {
+                file.old_path = None;
+            } else if line.starts_with("deleted file mode") {
+                file.new_path = None;
+            } else if line.starts_with("@@") {
+                let hunk = Hunk::parse(line)
+                    .ok_or_else(|| parse_error(&format!("malformed hunk header '{line}'")))?;
+                remaining = (
+                    hunk.old.end.line - hunk.old.start.line,
+                    hunk.new.end.line - hunk.new.start.line,
+                );
+                file.hunks.push(hunk);
+            }
+        }
+        Ok(files)
+    }
+
+    /// Returns the symbols of a graph whose definitions the changes of the
+    /// file overlap, in the order of the hunks.
+    ///
+    /// A changed line is attributed to the innermost symbol whose definition
+    /// contains it, so that a change inside a method changes the method and
+    /// not its type. Removed lines change the symbol they were removed from.
+    /// Every symbol of a deleted file is changed, if the graph still has it.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The graph, indexed from the new version of the file
+    /// * `root` - The folder the paths of the diff are relative to
+    pub fn changed_symbols(&self, graph: &KnowledgeGraph, root: &Path) -> Vec<ChangedSymbol> {
+        let Some(path) = &self.new_path else {
+            let Some(uri) = self.old_path.as_ref().and_then(|path| file_uri(root, path)) else {
+                return Vec::new();
+            };
+            return graph
+                .symbols_in(&uri)
+                .into_iter()
+                .map(|node| ChangedSymbol {
+                    symbol: node.into(),
+                    ranges: vec![node.extent()],
+                })
+                .collect();
+        };
+        let Some(uri) = file_uri(root, path) else {
+            return Vec::new();
+        };
+
+        let symbols = graph.symbols_in(&uri);
+        let mut changed: Vec<ChangedSymbol> = Vec::new();
+        for hunk in &self.hunks {
+            let overlapping = symbols
+                .iter()
+                .copied()
+                .filter(|node| node.extent().overlaps(&hunk.new))
+                .collect::<Vec<_>>();
+            for line in hunk.changed_lines() {
+                let Some(node) = overlapping
+                    .iter()
+                    .filter(|node| spans_line(node, line))
+                    .max_by_key(|node| {
+                        let extent = node.extent();
+                        (extent.start, std::cmp::Reverse(extent.end))
+                    })
+                else {
+                    continue;
+                };
+                let range = hunk.new.intersect(&node.extent()).unwrap_or(hunk.new);
+                match changed
+                    .iter_mut()
+                    .find(|change| change.symbol.id == node.id())
+                {
+                    Some(change) if change.ranges.contains(&range) => {}
+                    Some(change) => change.ranges.push(range),
+                    None => changed.push(ChangedSymbol {
+                        symbol: SymbolRef::from(*node),
+                        ranges: vec![range],
+                    }),
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// A symbol whose definition a diff changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangedSymbol {
+    /// The symbol
+    #[serde(flatten)]
+    pub symbol: SymbolRef,
+    /// The changed parts of its definition, in the new version
+    pub ranges: Vec<Range>,
+}
+
+/// Returns true if the definition of a symbol starts at or before a line
+/// and ends at or after it.
+fn spans_line(node: &SymbolNode, line: u32) -> bool {
+    let extent = node.extent();
+    extent.start.line <= line && line <= extent.end.line
+}
+
+/// Returns the URI of a path of a diff.
+fn file_uri(root: &Path, path: &Path) -> Option<Uri> {
+    Uri::from_file_path(&root.join(path)).ok()
+}
+
+/// Reads a path of a file header, without its `a/` or `b/` prefix; `None`
+/// for `/dev/null`.
+fn diff_path(path: &str) -> Option<PathBuf> {
+    // git ends paths with spaces with a tab
+    let path = unquote(path.trim_end_matches('\t'));
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(PathBuf::from(path))
+}
+
+/// Removes the quotes git puts around paths with special characters.
+fn unquote(path: &str) -> &str {
+    path.strip_prefix('"')
+        .and_then(|path| path.strip_suffix('"'))
+        .unwrap_or(path)
+}
+
+fn parse_error(message: &str) -> ContextEngineError {
+    ContextEngineError::Parse {
+        uri: None,
+        message: format!("Invalid diff: {message}"),
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/diff.rs"]
+mod tests;
@@ -0,0 +1,140 @@
+//! Reading diffs from the local git repository of a workspace.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::error::ContextEngineError;
+use crate::vcs::FileDiff;
+
+/// What a diff compares.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DiffTarget {
+    /// The working tree, with staged and unstaged changes, against a
+    /// revision; files git does not track are left out
+    WorkingTree {
+        /// The revision compared with, e.g. `HEAD` or `main`
+        base: String,
+    },
+    /// Two revisions
+    Revisions {
+        /// The old revision, e.g. `main`
+        base: String,
+        /// The new revision, e.g. `HEAD`
+        head: String,
+    },
+}
+
+impl Default for DiffTarget {
+    fn default() -> Self {
+        DiffTarget::WorkingTree {
+            base: "HEAD".to_string(),
+        }
+    }
+}
+
+impl DiffTarget {
+    /// Returns the revisions to pass to `git diff`.
+    fn revisions(&self) -> Vec<&str> {
+        match self {
+            DiffTarget::WorkingTree { base } => vec![base],
+            DiffTarget::Revisions { base, head } => vec![base, head],
+        }
+    }
+}
+
+/// Reads diffs from the local git repository of a workspace by running
+/// `git diff` in its root.
+///
+/// Only the local repository is read: git is told not to fetch missing
+/// objects from a remote, and not to take the locks of the index.
+///
+/// # Examples
+///
+/// ```no_run
+/// use context_engine_core::vcs::{DiffTarget, Git};
+///
+/// # async fn example() -> Result<(), context_engine_core::ContextEngineError> {
+/// let git = Git::new("/workspace");
+/// for file in git.diff(&DiffTarget::default()).await? {
+///     println!("{:?}: {} hunks", file.new_path, file.hunks.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Git {
+    root: PathBuf,
+}
+
+impl Git {
+    /// Creates a reader for the repository containing a workspace root.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Returns the workspace root, which the paths of diffs are relative to.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Reads the changes of the files below the workspace root.
+    ///
+    /// # Returns
+    ///
+    /// The changed files, with paths relative to the workspace root.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ContextEngineError::Configuration`] if a revision is not
+    /// valid, git cannot be run or the root is not in a git repository, and
+    /// a [`ContextEngineError::Parse`] if the diff cannot be read.
+    pub async fn diff(&self, target: &DiffTarget) -> Result<Vec<FileDiff>, ContextEngineError> {
+        let revisions = target.revisions();
+        if let Some(revision) = revisions
+            .iter()
+            .find(|revision| revision.is_empty() || revision.starts_with('-'))
+        {
+            return Err(ContextEngineError::Configuration {
+                key: None,
+                message: format!("'{revision}' is not a git revision"),
+            });
+        }
+
+        let output = Command::new("git")
+            .args(["-c", "core.quotePath=false", "diff"])
+            .args(["--no-color", "--no-ext-diff", "--no-textconv"])
+            .args(["--unified=0", "--relative", "--find-renames"])
+            .args(["--src-prefix=a/", "--dst-prefix=b/"])
+            .args(&revisions)
+            .arg("--")
+            .current_dir(&self.root)
+            .env("GIT_OPTIONAL_LOCKS", "0")
+            .env("GIT_NO_LAZY_FETCH", "1")
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output()
+            .await
+            .map_err(|error| ContextEngineError::Configuration {
+                key: None,
+                message: format!("Failed to run git: {error}"),
+            })?;
+        if !output.status.success() {
+            return Err(ContextEngineError::Configuration {
+                key: None,
+                message: format!(
+                    "git diff failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            });
+        }
+        FileDiff::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/git.rs"]
+mod tests;
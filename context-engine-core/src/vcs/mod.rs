@@ -0,0 +1,45 @@
+//! Changes recorded by version control.
+//!
+//! To tell what a branch or an uncommitted change affects, the Context
+//! Engine reads the diff from the local git repository of the workspace with
+//! [`Git`], and maps every changed hunk to the symbols of the
+//! [`KnowledgeGraph`](crate::graph::KnowledgeGraph) whose definitions it
+//! overlaps (see [`FileDiff::changed_symbols`]). The
+//! [`ImpactAnalyzer`](crate::graph::ImpactAnalyzer) then follows the
+//! dependents of those symbols.
+//!
+//! ## Structs
+//!
+//! * [`Git`] - Reads diffs from the local git repository of a workspace.
+//! * [`FileDiff`] - The changes of a file.
+//! * [`Hunk`] - A changed region of a file.
+//! * [`ChangedSymbol`] - A symbol whose definition a diff changes.
+//!
+//! ## Enums
+//!
+//! * [`DiffTarget`] - What a diff compares.
+//!
+//! ## Usage Example
+//!
+//! ```
+//! use context_engine_core::vcs::FileDiff;
+//!
+//! let diff = "diff --git a/src/lib.rs b/src/lib.rs\n\
+//!             --- a/src/lib.rs\n\
+//!             +++ b/src/lib.rs\n\
+//!             @@ -3 +3,2 @@ fn main() {\n\
+//!             -    parse();\n\
+//!             +    let config = parse();\n\
+//!             +    run(config);\n";
+//! let files = FileDiff::parse(diff).unwrap();
+//! let file = files.first().unwrap();
+//! assert_eq!(file.new_path.as_deref(), Some(std::path::Path::new("src/lib.rs")));
+//! let hunk = file.hunks.first().unwrap();
+//! assert_eq!((hunk.new.start.line, hunk.new.end.line), (2, 4));
+//! ```
+
+mod diff;
+mod git;
+
+pub use diff::{ChangedSymbol, FileDiff, Hunk};
+pub use git::{DiffTarget, Git};
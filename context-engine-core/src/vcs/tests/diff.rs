@@ -0,0 +1,210 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+use crate::graph::NodeKind;
+
+const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 3b18e51..a9c2f1d 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -2,2 +2,3 @@ impl Parser {
+-    fn parse() {}
+--- a removed line that looks like a header
++    fn parse() {
++++ an added line that looks like a header
++    }
+@@ -9,0 +11 @@ fn main() {
++    run();
+@@ -20 +21,0 @@ fn run() {
+-    stop();
+\\ No newline at end of file
+diff --git a/old.rs b/new.rs
+similarity index 100%
+rename from old.rs
+rename to new.rs
+diff --git a/gone.rs b/gone.rs
+deleted file mode 100644
+--- a/gone.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-fn gone() {}
+diff --git a/logo.png b/logo.png
+new file mode 100644
+Binary files /dev/null and b/logo.png differ
+";
+
+fn lines(start: u32, end: u32) -> Range {
+    Range::new(Position::new(start, 0), Position::new(end, 0))
+}
+
+#[test]
+fn test_parse() {
+    let files = FileDiff::parse(DIFF).unwrap();
+    let paths = files
+        .iter()
+        .map(|file| (file.old_path.as_deref(), file.new_path.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        [
+            (Some(Path::new("src/lib.rs")), Some(Path::new("src/lib.rs"))),
+            (Some(Path::new("old.rs")), Some(Path::new("new.rs"))),
+            (Some(Path::new("gone.rs")), None),
+            (None, Some(Path::new("logo.png"))),
+        ]
+    );
+
+    let lib = files.first().unwrap();
+    assert_eq!(
+        lib.hunks,
+        [
+            Hunk {
+                old: lines(1, 3),
+                new: lines(1, 4),
+            },
+            // Added after line 9, removed after line 21
+            Hunk {
+                old: lines(9, 9),
+                new: lines(10, 11),
+            },
+            Hunk {
+                old: lines(19, 20),
+                new: lines(21, 21),
+            },
+        ]
+    );
+    assert!(files.get(1).unwrap().hunks.is_empty());
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = FileDiff::parse("@@ -1 +1 @@\n").unwrap_err();
+    assert!(matches!(error, ContextEngineError::Parse { .. }));
+    let error = FileDiff::parse("diff --git a/x b/x\n@@ -1 +one @@\n").unwrap_err();
+    assert!(error.to_string().contains("malformed hunk header"));
+    assert!(FileDiff::parse("").unwrap().is_empty());
+}
+
+/// A graph over `src/lib.rs` of a folder: a type with two methods, and a
+/// function.
+fn graph(root: &Path) -> KnowledgeGraph {
+    let uri = Uri::from_file_path(&root.join("src/lib.rs")).unwrap();
+    let symbol = |kind, name: &str, start: u32, end: u32| {
+        let signature = Range::new(Position::new(start, 0), Position::new(start, 10));
+        SymbolNode::new(kind, name, format!("app::{name}"), uri.clone(), signature)
+            .with_body(Range::new(Position::new(start, 11), Position::new(end, 1)))
+    };
+    let mut graph = KnowledgeGraph::new();
+    graph.add_node(symbol(NodeKind::Type, "Parser", 0, 8));
+    graph.add_node(symbol(NodeKind::Method, "Parser::parse", 1, 3));
+    graph.add_node(symbol(NodeKind::Method, "Parser::reset", 5, 7));
+    graph.add_node(symbol(NodeKind::Function, "main", 10, 12));
+    graph
+}
+
+fn names(changed: &[ChangedSymbol]) -> Vec<&str> {
+    changed
+        .iter()
+        .map(|change| change.symbol.qualified_name.as_str())
+        .collect()
+}
+
+fn file(hunks: Vec<Hunk>) -> FileDiff {
+    FileDiff {
+        old_path: Some(PathBuf::from("src/lib.rs")),
+        new_path: Some(PathBuf::from("src/lib.rs")),
+        hunks,
+    }
+}
+
+#[test]
+fn test_changed_symbols() {
+    let root = Path::new("/app");
+    let graph = graph(root);
+    let hunk = |old: Range, new: Range| Hunk { old, new };
+
+    // A change inside a method changes the method, not its type
+    let changed = file(vec![hunk(lines(2, 3), lines(2, 3))]).changed_symbols(&graph, root);
+    assert_eq!(names(&changed), ["app::Parser::parse"]);
+    assert_eq!(
+        changed.first().unwrap().ranges,
+        [Range::new(Position::new(2, 0), Position::new(3, 0))]
+    );
+
+    // Lines between the methods change the type
+    let changed = file(vec![
+        hunk(lines(3, 6), lines(3, 6)),
+        hunk(lines(11, 11), lines(11, 11)),
+    ])
+    .changed_symbols(&graph, root);
+    assert_eq!(
+        names(&changed),
+        [
+            "app::Parser::parse",
+            "app::Parser",
+            "app::Parser::reset",
+            "app::main"
+        ]
+    );
+    // Removed lines change the symbol they were removed from
+    assert_eq!(
+        changed.get(3).unwrap().ranges,
+        [Range::new(Position::new(11, 0), Position::new(11, 0))]
+    );
+
+    // Changes outside of definitions change nothing
+    let changed = file(vec![hunk(lines(9, 10), lines(9, 10))]).changed_symbols(&graph, root);
+    assert!(changed.is_empty());
+
+    // Every symbol of a deleted file is changed
+    let deleted = FileDiff {
+        new_path: None,
+        ..file(Vec::new())
+    };
+    assert_eq!(deleted.changed_symbols(&graph, root).len(), 4);
+    let added = FileDiff {
+        new_path: Some(PathBuf::from("src/main.rs")),
+        ..file(vec![hunk(lines(0, 0), lines(0, 3))])
+    };
+    assert!(added.changed_symbols(&graph, root).is_empty());
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_hunk_headers_round_trip(
+        old_start in 1u32..1000,
+        old_count in 0u32..50,
+        new_start in 1u32..1000,
+        new_count in 0u32..50,
+    ) {
+        let diff = format!(
+            "diff --git a/x.rs b/x.rs\n--- a/x.rs\n+++ b/x.rs\n@@ -{old_start},{old_count} +{new_start},{new_count} @@\n"
+        );
+        let files = FileDiff::parse(&diff).unwrap();
+        let hunk = *files.first().unwrap().hunks.first().unwrap();
+        prop_assert_eq!(hunk.old.end.line - hunk.old.start.line, old_count);
+        prop_assert_eq!(hunk.new.end.line - hunk.new.start.line, new_count);
+        let first = |start: u32, count: u32| if count == 0 { start } else { start - 1 };
+        prop_assert_eq!(hunk.old.start.line, first(old_start, old_count));
+        prop_assert_eq!(hunk.new.start.line, first(new_start, new_count));
+    }
+
+    #[test]
+    fn prop_changed_symbols_overlap_the_hunks(start in 0u32..14, count in 0u32..6) {
+        let root = Path::new("/app");
+        let graph = graph(root);
+        let new = lines(start, start + count);
+        let diff = file(vec![Hunk { old: new, new }]);
+        for change in diff.changed_symbols(&graph, root) {
+            let node = graph.node(change.symbol.id).unwrap();
+            prop_assert!(node.extent().overlaps(&new));
+            for range in change.ranges {
+                prop_assert!(new.contains_range(&range));
+            }
+        }
+    }
+}
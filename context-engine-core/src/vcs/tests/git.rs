@@ -0,0 +1,115 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+/// Runs git in a folder, failing the test if it fails.
+fn run(root: &Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args([
+            "-c",
+            "commit.gpgsign=false",
+            "-c",
+            "init.defaultBranch=main",
+        ])
+        .args(args)
+        .current_dir(root)
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {args:?} failed");
+}
+
+/// Creates a repository with `src/lib.rs` and `README.md` committed twice.
+fn repository(root: &Path) {
+    std::fs::create_dir(root.join("src")).unwrap();
+    run(root, &["init", "--quiet"]);
+    std::fs::write(root.join("src/lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+    std::fs::write(root.join("README.md"), "# App\n").unwrap();
+    run(root, &["add", "."]);
+    run(root, &["commit", "--quiet", "-m", "first"]);
+    run(root, &["tag", "first"]);
+    std::fs::write(root.join("src/lib.rs"), "fn a() {}\nfn b() { a(); }\n").unwrap();
+    run(root, &["commit", "--quiet", "-am", "second"]);
+}
+
+#[tokio::test]
+async fn test_working_tree_and_revisions() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    repository(root);
+    let git = Git::new(root);
+    assert!(git.diff(&DiffTarget::default()).await.unwrap().is_empty());
+
+    // Staged and unstaged changes are both compared with the base
+    std::fs::write(root.join("src/lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+    std::fs::write(root.join("README.md"), "# App\n\nDocs\n").unwrap();
+    run(root, &["add", "README.md"]);
+    let files = git.diff(&DiffTarget::default()).await.unwrap();
+    let paths = files
+        .iter()
+        .map(|file| file.new_path.clone().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        [PathBuf::from("README.md"), PathBuf::from("src/lib.rs")]
+    );
+    let lib = files.get(1).unwrap().hunks.first().unwrap();
+    assert_eq!((lib.new.start.line, lib.new.end.line), (1, 2));
+
+    let files = git
+        .diff(&DiffTarget::Revisions {
+            base: "first".to_string(),
+            head: "HEAD".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(files.len(), 1);
+
+    // Paths are relative to a root below the top of the repository
+    let files = Git::new(root.join("src"))
+        .diff(&DiffTarget::default())
+        .await
+        .unwrap();
+    let file = files.first().unwrap();
+    assert_eq!(
+        (files.len(), file.new_path.as_deref()),
+        (1, Some(Path::new("lib.rs")))
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_revisions_and_repositories() {
+    let dir = tempfile::tempdir().unwrap();
+    let git = Git::new(dir.path());
+    let error = git.diff(&DiffTarget::default()).await.unwrap_err();
+    assert!(matches!(error, ContextEngineError::Configuration { .. }));
+
+    repository(dir.path());
+    let target = DiffTarget::WorkingTree {
+        base: "--output=/tmp/diff".to_string(),
+    };
+    let error = git.diff(&target).await.unwrap_err();
+    assert!(error.to_string().contains("is not a git revision"));
+    let target = DiffTarget::WorkingTree {
+        base: "missing".to_string(),
+    };
+    let error = git.diff(&target).await.unwrap_err();
+    assert!(error.to_string().contains("git diff failed"));
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_targets_round_trip(base in "[a-z]{1,8}", head in proptest::option::of("[a-z]{1,8}")) {
+        let target = match head {
+            Some(head) => DiffTarget::Revisions { base, head },
+            None => DiffTarget::WorkingTree { base },
+        };
+        let json = serde_json::to_value(&target).unwrap();
+        prop_assert_eq!(serde_json::from_value::<DiffTarget>(json).unwrap(), target.clone());
+        prop_assert!(!target.revisions().is_empty());
+    }
+}
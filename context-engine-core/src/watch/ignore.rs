@@ -0,0 +1,155 @@
+//! Rules for the paths a watcher ignores.
+
+use std::path::{Component, Path};
+
+use tracing::debug;
+
+use crate::workspace::Glob;
+
+/// A rule of a `.gitignore` file.
+#[derive(Debug, Clone)]
+struct Rule {
+    glob: Glob,
+    /// The rule re-includes paths that an earlier rule ignored (`!`)
+    negated: bool,
+    /// The rule only matches directories (trailing `/`)
+    directory: bool,
+    /// The rule only matches paths relative to its `.gitignore`
+    anchored: bool,
+}
+
+impl Rule {
+    fn is_match(&self, path: &str, is_dir: bool) -> bool {
+        // An anchored single-segment pattern of the root `.gitignore` compiles
+        // to a glob that would match the name at any depth
+        let depth = !(self.anchored && self.glob.is_basename_pattern()) || !path.contains('/');
+        (is_dir || !self.directory) && depth && self.glob.is_match(path)
+    }
+}
+
+/// The paths of a workspace that are not watched: those matching one of the
+/// configured excludes or ignored by a `.gitignore` file.
+///
+/// `.gitignore` rules follow git: a pattern without a `/` matches a name at
+/// any depth below its `.gitignore`, other patterns match paths relative to
+/// it, a trailing `/` only matches directories, and a leading `!` includes a
+/// path again. The last matching rule wins, but the excludes always apply.
+/// A path is also ignored if one of its ancestors is.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::watch::IgnoreRules;
+/// use context_engine_core::workspace::Glob;
+/// use std::path::Path;
+///
+/// let mut rules = IgnoreRules::new(vec![Glob::new("target").unwrap()]);
+/// rules.add_gitignore(Path::new(""), "*.log\n!keep.log\n/generated/\n");
+///
+/// assert!(rules.is_ignored(Path::new("target/debug/app"), false));
+/// assert!(rules.is_ignored(Path::new("logs/run.log"), false));
+/// assert!(!rules.is_ignored(Path::new("logs/keep.log"), false));
+/// assert!(rules.is_ignored(Path::new("generated/api.rs"), false));
+/// assert!(!rules.is_ignored(Path::new("src/generated"), false));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    excludes: Vec<Glob>,
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    /// Creates rules that ignore the paths matching one of the `excludes`.
+    pub fn new(excludes: Vec<Glob>) -> Self {
+        Self {
+            excludes,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Adds the rules of a `.gitignore` file.
+    ///
+    /// Invalid patterns are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory of the file, relative to the workspace root
+    /// * `content` - The content of the file
+    pub fn add_gitignore(&mut self, dir: &Path, content: &str) {
+        let prefix = slash_path(dir);
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, line),
+            };
+            let (directory, pattern) = match pattern.strip_suffix('/') {
+                Some(pattern) => (true, pattern),
+                None => (false, pattern),
+            };
+            // A pattern with a `/` other than at its end is relative to the
+            // directory of the file; any other pattern matches at any depth
+            let anchored = pattern.contains('/');
+            let pattern = pattern.trim_start_matches('/');
+            let pattern = match (prefix.is_empty(), anchored) {
+                (true, _) => pattern.to_string(),
+                (false, true) => format!("{prefix}/{pattern}"),
+                (false, false) => format!("{prefix}/**/{pattern}"),
+            };
+            match Glob::new(&pattern) {
+                Ok(glob) => self.rules.push(Rule {
+                    glob,
+                    negated,
+                    directory,
+                    anchored,
+                }),
+                Err(error) => debug!(%pattern, %error, "Skipping invalid .gitignore pattern"),
+            }
+        }
+    }
+
+    /// Returns true if a path relative to the workspace root is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path, relative to the workspace root
+    /// * `is_dir` - Whether the path is a directory
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let path = slash_path(path);
+        let mut ancestors = path
+            .match_indices('/')
+            .map(|(index, _)| path.get(..index).unwrap_or_default());
+        ancestors.any(|ancestor| self.matches(ancestor, true)) || self.matches(&path, is_dir)
+    }
+
+    /// Returns true if a path is ignored by its own name, regardless of its
+    /// ancestors.
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.excludes.iter().any(|glob| glob.is_match(path)) {
+            return true;
+        }
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.is_match(path, is_dir))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+/// Joins the normal components of a path with `/`.
+fn slash_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+#[path = "tests/ignore.rs"]
+mod tests;
@@ -0,0 +1,118 @@
+//! Invalidation of the analysis of changed files.
+
+use std::collections::BTreeSet;
+
+use lsp_types::{FileEvent, Uri};
+
+use crate::cache::GraphStore;
+use crate::error::ContextEngineError;
+use crate::graph::{Direction, KnowledgeGraph, SymbolId, SymbolNode};
+
+/// What a batch of file changes invalidated.
+///
+/// The symbols of the changed files are removed from the graph, together
+/// with all their relationships. Symbols of other files that had a
+/// relationship to one of them, e.g. the callers of a changed function, are
+/// kept but are [dependents]: their relationships must be collected again
+/// once the changed files are analyzed.
+///
+/// [dependents]: Invalidation::dependents
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Invalidation {
+    /// The URIs of the changed files
+    pub files: BTreeSet<String>,
+    /// The symbols removed from the graph, ordered by id
+    pub removed: Vec<SymbolNode>,
+    /// The symbols of unchanged files whose relationships are stale
+    pub dependents: BTreeSet<SymbolId>,
+    /// The URIs of the files that define the dependents
+    pub dependent_files: BTreeSet<String>,
+}
+
+impl Invalidation {
+    /// Returns true if the changes invalidated nothing.
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.dependents.is_empty()
+    }
+
+    /// Removes the cache entries of the changed files and of the files of
+    /// the dependents from a store.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::CacheCorrupted`] if an entry cannot be
+    /// removed.
+    pub fn evict(&self, store: &GraphStore) -> Result<(), ContextEngineError> {
+        for uri in self.files.iter().chain(&self.dependent_files) {
+            if let Ok(uri) = uri.parse::<Uri>() {
+                store.remove(&uri)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Removes the symbols of changed files from a graph.
+///
+/// # Returns
+///
+/// The removed symbols and the dependents whose relationships are stale.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::graph::{EdgeKind, KnowledgeGraph, NodeKind, SymbolNode};
+/// use context_engine_core::types::{Position, Range, Uri};
+/// use context_engine_core::watch::invalidate;
+/// use lsp_types::{FileChangeType, FileEvent};
+/// use std::str::FromStr;
+///
+/// let user = Uri::from_str("file:///src/user.rs").unwrap();
+/// let main = Uri::from_str("file:///src/main.rs").unwrap();
+/// let range = Range::new(Position::new(0, 0), Position::new(0, 10));
+///
+/// let mut graph = KnowledgeGraph::new();
+/// let validate = graph.add_node(SymbolNode::new(NodeKind::Function, "validate", "app::validate", user.clone(), range));
+/// let run = graph.add_node(SymbolNode::new(NodeKind::Function, "run", "app::run", main, range));
+/// graph.add_edge(run, EdgeKind::Calls, validate);
+///
+/// let invalidation = invalidate(&mut graph, &[FileEvent::new(user, FileChangeType::CHANGED)]);
+/// assert_eq!(invalidation.removed[0].name, "validate");
+/// assert!(invalidation.dependents.contains(&run));
+/// assert_eq!(graph.len(), 1);
+/// ```
+pub fn invalidate(graph: &mut KnowledgeGraph, events: &[FileEvent]) -> Invalidation {
+    let mut invalidation = Invalidation::default();
+    for event in events {
+        invalidation.files.insert(event.uri.as_str().to_string());
+    }
+
+    for event in events {
+        let ids: Vec<SymbolId> = graph
+            .symbols_in(&event.uri)
+            .into_iter()
+            .map(SymbolNode::id)
+            .collect();
+        for id in ids {
+            for edge in graph.edges_of(id, Direction::Incoming) {
+                if let Some(source) = graph.node(edge.from) {
+                    if !invalidation.files.contains(source.uri.as_str()) {
+                        invalidation.dependents.insert(edge.from);
+                        invalidation
+                            .dependent_files
+                            .insert(source.uri.as_str().to_string());
+                    }
+                }
+            }
+        }
+        invalidation
+            .removed
+            .extend(graph.remove_document(&event.uri));
+    }
+    invalidation.removed.sort_by_key(SymbolNode::id);
+    invalidation
+}
+
+#[cfg(test)]
+#[path = "tests/invalidate.rs"]
+mod tests;
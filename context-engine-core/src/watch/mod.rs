@@ -0,0 +1,33 @@
+//! Watching of workspace files.
+//!
+//! The knowledge graph and its cache are only useful while they match the
+//! files on disk. The [`FileWatcher`] polls the workspace for changes,
+//! debounces bursts such as a `git checkout`, honours `.gitignore` files and
+//! configured excludes through [`IgnoreRules`], and compares content hashes
+//! so that writes which keep the content are not reported. The changes are
+//! forwarded to the language servers as `workspace/didChangeWatchedFiles`
+//! with [`notify_servers`], and [`invalidate`] removes the symbols of the
+//! changed files from the graph while collecting the dependents whose
+//! relationships must be refreshed.
+//!
+//! ## Structs
+//!
+//! * [`FileWatcher`] - Watches the files of a workspace for changes.
+//! * [`WatchConfig`] - How a watcher polls the workspace.
+//! * [`IgnoreRules`] - The paths a watcher ignores.
+//! * [`Invalidation`] - What a batch of file changes invalidated.
+//!
+//! ## Functions
+//!
+//! * [`notify_servers`] - Sends file changes to the language servers.
+//! * [`invalidate`] - Removes the symbols of changed files from a graph.
+
+mod ignore;
+mod invalidate;
+mod notify;
+mod watcher;
+
+pub use ignore::IgnoreRules;
+pub use invalidate::{Invalidation, invalidate};
+pub use notify::notify_servers;
+pub use watcher::{FileWatcher, WatchConfig};
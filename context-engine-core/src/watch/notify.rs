@@ -0,0 +1,165 @@
+//! Forwarding of file changes to language servers.
+
+use std::path::PathBuf;
+
+use lsp_types::notification::{DidChangeWatchedFiles, Notification};
+use lsp_types::{
+    DidChangeWatchedFilesParams, DidChangeWatchedFilesRegistrationOptions, FileChangeType,
+    FileEvent, GlobPattern, OneOf, Registration, WatchKind,
+};
+use tracing::{debug, warn};
+
+use crate::lsp::{LanguageRegistry, LspSupervisor, ServerStatus};
+use crate::types::UriExt;
+use crate::workspace::Glob;
+
+/// Sends `workspace/didChangeWatchedFiles` to the running language servers.
+///
+/// A server that registered file system watchers dynamically receives the
+/// changes that match one of its watchers, with the kinds of changes the
+/// watcher asked for. Any other server receives the changes of the files
+/// the `registry` routes to it. Servers without matching changes are not
+/// notified; failures are logged.
+pub async fn notify_servers(
+    supervisor: &LspSupervisor,
+    registry: &LanguageRegistry,
+    events: &[FileEvent],
+) {
+    if events.is_empty() {
+        return;
+    }
+    for (name, status) in supervisor.statuses() {
+        if status != ServerStatus::Running {
+            continue;
+        }
+        let client = match supervisor.client(&name).await {
+            Ok(client) => client,
+            Err(error) => {
+                debug!(server = %name, %error, "Skipping file changes for unavailable server");
+                continue;
+            }
+        };
+
+        let watchers = watchers(&client.capabilities().registrations());
+        let changes: Vec<FileEvent> = events
+            .iter()
+            .filter(|event| match &watchers {
+                Some(watchers) => watchers.iter().any(|watcher| watcher.matches(event)),
+                None => registry
+                    .route(&event.uri, None)
+                    .iter()
+                    .any(|route| route.server.name == name),
+            })
+            .cloned()
+            .collect();
+        if changes.is_empty() {
+            continue;
+        }
+
+        debug!(server = %name, changes = changes.len(), "Sending watched file changes");
+        let params = DidChangeWatchedFilesParams { changes };
+        if let Err(error) = client.notify::<DidChangeWatchedFiles>(params) {
+            warn!(server = %name, %error, "Failed to send watched file changes");
+        }
+    }
+}
+
+/// A file system watcher a server registered.
+#[derive(Debug)]
+struct Watcher {
+    globs: Vec<Glob>,
+    /// The folder relative patterns are matched against
+    base: Option<PathBuf>,
+    kind: WatchKind,
+}
+
+impl Watcher {
+    fn matches(&self, event: &FileEvent) -> bool {
+        let kind = match event.typ {
+            FileChangeType::CREATED => WatchKind::Create,
+            FileChangeType::CHANGED => WatchKind::Change,
+            _ => WatchKind::Delete,
+        };
+        let Ok(path) = event.uri.to_file_path() else {
+            return false;
+        };
+        let subject = match &self.base {
+            Some(base) => match path.strip_prefix(base) {
+                Ok(relative) => relative,
+                Err(_) => return false,
+            },
+            None => path.as_path(),
+        };
+        self.kind.contains(kind) && self.globs.iter().any(|glob| glob.is_match_path(subject))
+    }
+}
+
+/// Returns the file system watchers among the registrations of a server, or
+/// `None` if the server registered none.
+fn watchers(registrations: &[Registration]) -> Option<Vec<Watcher>> {
+    let mut watchers = Vec::new();
+    let mut registered = false;
+    for registration in registrations {
+        if registration.method != DidChangeWatchedFiles::METHOD {
+            continue;
+        }
+        registered = true;
+        let options = registration.register_options.clone().and_then(|options| {
+            serde_json::from_value::<DidChangeWatchedFilesRegistrationOptions>(options).ok()
+        });
+        for watcher in options.into_iter().flat_map(|options| options.watchers) {
+            let (pattern, base) = match watcher.glob_pattern {
+                GlobPattern::String(pattern) => (pattern, None),
+                GlobPattern::Relative(relative) => {
+                    let base = match relative.base_uri {
+                        OneOf::Left(folder) => folder.uri,
+                        OneOf::Right(uri) => uri,
+                    };
+                    let Ok(base) = base.to_file_path() else {
+                        continue;
+                    };
+                    (relative.pattern, Some(base))
+                }
+            };
+            watchers.push(Watcher {
+                globs: expand_braces(&pattern)
+                    .iter()
+                    .filter_map(|pattern| Glob::new(pattern).ok())
+                    .collect(),
+                base,
+                kind: watcher.kind.unwrap_or(WatchKind::all()),
+            });
+        }
+    }
+    registered.then_some(watchers)
+}
+
+/// Expands the `{a,b}` alternatives of an LSP glob pattern, which [`Glob`]
+/// does not support, into one pattern per alternative.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some(open) = pattern.find('{') else {
+        return vec![pattern.to_string()];
+    };
+    let Some(close) = pattern
+        .get(open..)
+        .and_then(|rest| rest.find('}'))
+        .map(|close| open + close)
+    else {
+        return vec![pattern.to_string()];
+    };
+    let (Some(prefix), Some(alternatives), Some(suffix)) = (
+        pattern.get(..open),
+        pattern.get(open + 1..close),
+        pattern.get(close + 1..),
+    ) else {
+        return vec![pattern.to_string()];
+    };
+    alternatives
+        .split(',')
+        .flat_map(|alternative| expand_braces(&format!("{prefix}{alternative}{suffix}")))
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "tests/notify.rs"]
+mod tests;
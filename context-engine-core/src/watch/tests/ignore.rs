@@ -0,0 +1,86 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+fn rules(gitignore: &str) -> IgnoreRules {
+    let mut rules = IgnoreRules::new(vec![Glob::new("node_modules").unwrap()]);
+    rules.add_gitignore(Path::new(""), gitignore);
+    rules
+}
+
+#[test]
+fn test_excludes() {
+    let rules = rules("");
+    assert!(rules.is_ignored(Path::new("node_modules"), true));
+    assert!(rules.is_ignored(Path::new("web/node_modules/react/index.js"), false));
+    assert!(!rules.is_ignored(Path::new("src/main.rs"), false));
+}
+
+#[test]
+fn test_comments_and_blank_lines() {
+    let rules = rules("# build output\n\n   \n*.o\n");
+    assert!(rules.is_ignored(Path::new("lib/a.o"), false));
+    assert!(!rules.is_ignored(Path::new("# build output"), false));
+}
+
+#[test]
+fn test_directory_patterns() {
+    let rules = rules("out/\n");
+    assert!(rules.is_ignored(Path::new("out"), true));
+    assert!(rules.is_ignored(Path::new("web/out/bundle.js"), false));
+    // Only directories match
+    assert!(!rules.is_ignored(Path::new("out"), false));
+}
+
+#[test]
+fn test_anchored_patterns() {
+    let rules = rules("/TODO\ndocs/*.html\n");
+    assert!(rules.is_ignored(Path::new("TODO"), false));
+    assert!(!rules.is_ignored(Path::new("src/TODO"), false));
+    assert!(rules.is_ignored(Path::new("docs/index.html"), false));
+    assert!(!rules.is_ignored(Path::new("web/docs/index.html"), false));
+}
+
+#[test]
+fn test_negation_last_rule_wins() {
+    let rules = rules("*.json\n!package.json\n");
+    assert!(rules.is_ignored(Path::new("data.json"), false));
+    assert!(!rules.is_ignored(Path::new("web/package.json"), false));
+
+    let rules = self::rules("!package.json\n*.json\n");
+    assert!(rules.is_ignored(Path::new("package.json"), false));
+}
+
+#[test]
+fn test_nested_gitignore() {
+    let mut rules = rules("");
+    rules.add_gitignore(Path::new("web"), "*.map\n/dist\n");
+    assert!(rules.is_ignored(Path::new("web/app.js.map"), false));
+    assert!(rules.is_ignored(Path::new("web/lib/app.js.map"), false));
+    assert!(!rules.is_ignored(Path::new("app.js.map"), false));
+    assert!(rules.is_ignored(Path::new("web/dist/app.js"), false));
+    assert!(!rules.is_ignored(Path::new("web/lib/dist"), true));
+}
+
+#[test]
+fn test_invalid_patterns_are_skipped() {
+    let rules = rules("[abc\n*.tmp\n");
+    assert!(rules.is_ignored(Path::new("a.tmp"), false));
+    assert!(!rules.is_ignored(Path::new("[abc"), false));
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn test_children_of_ignored_directories_are_ignored(
+        dir in "[a-z]{1,8}",
+        file in "[a-z]{1,8}\\.rs",
+    ) {
+        let rules = rules(&format!("{dir}/\n"));
+        let path = Path::new(&dir).join(&file);
+        prop_assert!(rules.is_ignored(&path, false));
+        prop_assert!(rules.is_ignored(&Path::new("src").join(&path), false));
+    }
+}
@@ -0,0 +1,112 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use lsp_types::{FileChangeType, Position, Range};
+use proptest::prelude::*;
+
+use super::*;
+use crate::cache::{CacheKey, FileGraph};
+use crate::graph::{EdgeKind, NodeKind};
+
+fn uri(path: &str) -> Uri {
+    Uri::from_str(&format!("file:///app/src/{path}")).unwrap()
+}
+
+fn node(graph: &mut KnowledgeGraph, kind: NodeKind, name: &str, file: &str, line: u32) -> SymbolId {
+    let range = Range::new(Position::new(line, 0), Position::new(line, 20));
+    graph.add_node(SymbolNode::new(
+        kind,
+        name,
+        format!("app::{name}"),
+        uri(file),
+        range,
+    ))
+}
+
+/// `main.rs` calls into `user.rs`, which implements a trait of `named.rs`.
+fn graph() -> (KnowledgeGraph, [SymbolId; 4]) {
+    let mut graph = KnowledgeGraph::new();
+    let main = node(&mut graph, NodeKind::Function, "main", "main.rs", 0);
+    let user = node(&mut graph, NodeKind::Type, "User", "user.rs", 0);
+    let validate = node(&mut graph, NodeKind::Method, "validate", "user.rs", 2);
+    let named = node(&mut graph, NodeKind::Trait, "Named", "named.rs", 0);
+    graph.add_edge(main, EdgeKind::Calls, validate);
+    graph.add_edge(user, EdgeKind::Defines, validate);
+    graph.add_edge(user, EdgeKind::Implements, named);
+    (graph, [main, user, validate, named])
+}
+
+fn changed(path: &str) -> FileEvent {
+    FileEvent::new(uri(path), FileChangeType::CHANGED)
+}
+
+#[test]
+fn test_invalidate_removes_symbols_and_collects_dependents() {
+    let (mut graph, [main, user, validate, named]) = graph();
+    let invalidation = invalidate(&mut graph, &[changed("user.rs")]);
+
+    let removed: Vec<SymbolId> = invalidation.removed.iter().map(SymbolNode::id).collect();
+    assert_eq!(removed, [user, validate]);
+    assert_eq!(invalidation.dependents, BTreeSet::from([main]));
+    assert_eq!(
+        invalidation.dependent_files,
+        BTreeSet::from([uri("main.rs").as_str().to_string()])
+    );
+    assert!(graph.node(main).is_some());
+    assert!(graph.node(named).is_some());
+    assert_eq!(graph.edge_count(), 0);
+}
+
+#[test]
+fn test_changed_files_are_not_their_own_dependents() {
+    let (mut graph, _) = graph();
+    let invalidation = invalidate(&mut graph, &[changed("main.rs"), changed("user.rs")]);
+    assert_eq!(invalidation.removed.len(), 3);
+    assert!(invalidation.dependents.is_empty());
+    assert!(invalidation.dependent_files.is_empty());
+}
+
+#[test]
+fn test_unknown_files_invalidate_nothing() {
+    let (mut graph, _) = graph();
+    let invalidation = invalidate(&mut graph, &[changed("new.rs")]);
+    assert!(invalidation.is_empty());
+    assert_eq!(graph.len(), 4);
+}
+
+#[test]
+fn test_evict_removes_changed_and_dependent_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = GraphStore::open(dir.path()).unwrap();
+    let (mut graph, _) = graph();
+    let key = CacheKey::new(b"", "rust-analyzer");
+    for path in ["main.rs", "user.rs", "named.rs"] {
+        store
+            .put(&key, &FileGraph::extract(&graph, &uri(path)))
+            .unwrap();
+    }
+
+    let invalidation = invalidate(&mut graph, &[changed("user.rs")]);
+    invalidation.evict(&store).unwrap();
+    assert_eq!(store.get(&uri("user.rs"), &key).unwrap(), None);
+    assert_eq!(store.get(&uri("main.rs"), &key).unwrap(), None);
+    assert!(store.get(&uri("named.rs"), &key).unwrap().is_some());
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn test_invalidate_keeps_other_files(changed_files in prop::collection::btree_set(
+        prop::sample::select(vec!["main.rs", "user.rs", "named.rs"]),
+        0..=3,
+    )) {
+        let (mut graph, _) = graph();
+        let events: Vec<FileEvent> = changed_files.iter().map(|path| changed(path)).collect();
+        let invalidation = invalidate(&mut graph, &events);
+        for node in graph.nodes() {
+            prop_assert!(!invalidation.files.contains(node.uri.as_str()));
+        }
+        prop_assert_eq!(graph.len() + invalidation.removed.len(), 4);
+    }
+}
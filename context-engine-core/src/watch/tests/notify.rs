@@ -0,0 +1,154 @@
+#![allow(clippy::unwrap_used)]
+
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use lsp_types::Uri;
+use proptest::prelude::*;
+use serde_json::{Value, json};
+
+use super::*;
+use crate::document::DocumentStore;
+use crate::lsp::ServerConfig;
+use crate::lsp::testing::{self, FakeLauncher, FakeLog, FakeServer, FakeSession};
+
+/// A server that registers file watchers once initialized.
+#[derive(Default)]
+struct Watcher {
+    /// The options of the watchers the server registers once initialized
+    watchers: Option<Value>,
+}
+
+impl FakeServer for Watcher {
+    async fn notify(&self, session: &FakeSession, method: &str, _params: Value) {
+        if let ("initialized", Some(watchers)) = (method, &self.watchers) {
+            session.request(
+                "client/registerCapability",
+                json!({ "registrations": [{
+                    "id": "watchers",
+                    "method": "workspace/didChangeWatchedFiles",
+                    "registerOptions": { "watchers": watchers },
+                }] }),
+            );
+        }
+    }
+}
+
+fn uri(path: &str) -> Uri {
+    Uri::from_str(&format!("file:///app/{path}")).unwrap()
+}
+
+fn events() -> Vec<FileEvent> {
+    vec![
+        FileEvent::new(uri("Cargo.toml"), FileChangeType::CHANGED),
+        FileEvent::new(uri("src/lib.rs"), FileChangeType::DELETED),
+        FileEvent::new(uri("src/main.rs"), FileChangeType::CREATED),
+        FileEvent::new(uri("tools/gen.py"), FileChangeType::CHANGED),
+    ]
+}
+
+/// Starts rust-analyzer on a fake server and waits until it registered its
+/// watchers, if it has any.
+async fn start(launcher: FakeLauncher<Watcher>) -> Arc<LspSupervisor> {
+    let registers = launcher.server().watchers.is_some();
+    let supervisor = testing::start(
+        Path::new("/app"),
+        Arc::new(DocumentStore::default()),
+        launcher,
+        ServerConfig::new("rust-analyzer", "rust-analyzer"),
+    )
+    .await
+    .unwrap();
+    let client = supervisor.client("rust-analyzer").await.unwrap();
+    while registers && client.capabilities().registrations().is_empty() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    supervisor
+}
+
+/// The watched file changes received by the server, as
+/// `<type> <file name>`.
+fn changes(log: &FakeLog) -> Vec<String> {
+    log.params("workspace/didChangeWatchedFiles")
+        .into_iter()
+        .flat_map(|params| {
+            serde_json::from_value::<DidChangeWatchedFilesParams>(params)
+                .unwrap()
+                .changes
+        })
+        .map(|change| {
+            let name = change.uri.as_str().rsplit('/').next().unwrap().to_string();
+            format!("{:?} {name}", change.typ)
+        })
+        .collect()
+}
+
+/// Waits until the server received a number of changes.
+async fn received(launcher: &FakeLauncher<Watcher>, count: usize) -> Vec<String> {
+    testing::wait_until(|| changes(launcher.log()).len() >= count).await;
+    changes(launcher.log())
+}
+
+#[tokio::test]
+async fn test_changes_are_routed_by_language() {
+    let launcher = FakeLauncher::new(Watcher::default());
+    let supervisor = start(launcher.clone()).await;
+    let registry = LanguageRegistry::with_defaults();
+
+    notify_servers(&supervisor, &registry, &events()).await;
+    assert_eq!(
+        received(&launcher, 2).await,
+        ["Deleted lib.rs", "Created main.rs"]
+    );
+    supervisor.stop_all().await;
+}
+
+#[tokio::test]
+async fn test_changes_are_filtered_by_registered_watchers() {
+    let launcher = FakeLauncher::new(Watcher {
+        watchers: Some(json!([
+            { "globPattern": "**/*.{rs,toml}", "kind": 3 },
+            { "globPattern": { "baseUri": "file:///app/tools", "pattern": "*.py" } },
+        ])),
+    });
+    let supervisor = start(launcher.clone()).await;
+    let registry = LanguageRegistry::with_defaults();
+
+    notify_servers(&supervisor, &registry, &events()).await;
+    assert_eq!(
+        received(&launcher, 3).await,
+        ["Changed Cargo.toml", "Created main.rs", "Changed gen.py",]
+    );
+    supervisor.stop_all().await;
+}
+
+#[test]
+fn test_expand_braces() {
+    assert_eq!(expand_braces("**/*.rs"), ["**/*.rs"]);
+    assert_eq!(
+        expand_braces("**/Cargo.{toml,lock}"),
+        ["**/Cargo.toml", "**/Cargo.lock"]
+    );
+    assert_eq!(
+        expand_braces("{src,tests}/*.{rs,py}"),
+        ["src/*.rs", "src/*.py", "tests/*.rs", "tests/*.py"]
+    );
+    assert_eq!(expand_braces("a{b"), ["a{b"]);
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn test_expand_braces_yields_one_pattern_per_alternative(
+        alternatives in prop::collection::vec("[a-z]{1,4}", 1..5),
+    ) {
+        let pattern = format!("**/*.{{{}}}", alternatives.join(","));
+        let expanded = expand_braces(&pattern);
+        prop_assert_eq!(expanded.len(), alternatives.len());
+        for (pattern, alternative) in expanded.iter().zip(&alternatives) {
+            prop_assert_eq!(pattern, &format!("**/*.{alternative}"));
+        }
+    }
+}
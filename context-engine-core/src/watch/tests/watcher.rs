@@ -0,0 +1,177 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+fn config() -> WatchConfig {
+    WatchConfig {
+        poll_interval: Duration::from_millis(20),
+        debounce: Duration::from_millis(60),
+        ..WatchConfig::default()
+    }
+}
+
+fn workspace() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/lib.rs"), "pub mod user;").unwrap();
+    std::fs::write(dir.path().join("src/user.rs"), "pub struct User;").unwrap();
+    dir
+}
+
+/// Renders events as `<kind> <path relative to root>`.
+fn render(root: &Path, events: &[FileEvent]) -> Vec<String> {
+    events
+        .iter()
+        .map(|event| {
+            let path = event.uri.to_file_path().unwrap();
+            let kind = match event.typ {
+                FileChangeType::CREATED => "created",
+                FileChangeType::CHANGED => "changed",
+                _ => "deleted",
+            };
+            format!("{kind} {}", path.strip_prefix(root).unwrap().display())
+        })
+        .collect()
+}
+
+#[test]
+fn test_poll_reports_changes() {
+    let dir = workspace();
+    let root = dir.path().canonicalize().unwrap();
+    let mut watcher = FileWatcher::new(&root, config());
+    assert_eq!(watcher.len(), 2);
+    assert!(watcher.poll().is_empty());
+
+    std::fs::write(root.join("src/user.rs"), "pub struct User { name: String }").unwrap();
+    std::fs::write(root.join("src/account.rs"), "pub struct Account;").unwrap();
+    std::fs::remove_file(root.join("src/lib.rs")).unwrap();
+    assert_eq!(
+        render(&root, &watcher.poll()),
+        [
+            "created src/account.rs",
+            "deleted src/lib.rs",
+            "changed src/user.rs"
+        ]
+    );
+    assert!(watcher.poll().is_empty());
+}
+
+#[test]
+fn test_poll_skips_writes_without_changes() {
+    let dir = workspace();
+    let mut watcher = FileWatcher::new(dir.path(), config());
+    std::fs::write(dir.path().join("src/user.rs"), "pub struct User;").unwrap();
+    assert!(watcher.poll().is_empty());
+}
+
+#[test]
+fn test_ignored_paths_are_not_watched() {
+    let dir = workspace();
+    let root = dir.path();
+    std::fs::write(root.join(".gitignore"), "*.log\n/generated/\n").unwrap();
+    let mut watcher = FileWatcher::new(root, config());
+
+    std::fs::create_dir_all(root.join("generated")).unwrap();
+    std::fs::create_dir_all(root.join("target/debug")).unwrap();
+    std::fs::create_dir_all(root.join(".git")).unwrap();
+    std::fs::write(root.join("generated/api.rs"), "").unwrap();
+    std::fs::write(root.join("target/debug/app"), "").unwrap();
+    std::fs::write(root.join(".git/HEAD"), "").unwrap();
+    std::fs::write(root.join("src/debug.log"), "").unwrap();
+    assert!(watcher.poll().is_empty());
+
+    // Changes to the rules apply to the next poll
+    std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+    let root = root.canonicalize().unwrap();
+    let events = render(&root, &watcher.poll());
+    assert_eq!(events.len(), 1);
+    assert!(events.iter().all(|event| event.ends_with("api.rs")));
+}
+
+#[tokio::test]
+async fn test_changes_debounces_bursts() {
+    let dir = workspace();
+    let root = dir.path().canonicalize().unwrap();
+    let mut watcher = FileWatcher::new(&root, config());
+
+    let writer = tokio::spawn({
+        let root = root.clone();
+        async move {
+            // A burst whose writes are closer together than the debounce
+            for index in 0..5 {
+                let path = root.join(format!("src/module_{index}.rs"));
+                std::fs::write(path, format!("pub fn f{index}() {{}}")).unwrap();
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            // A file that is created and deleted again within the burst
+            std::fs::write(root.join("src/scratch.rs"), "").unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            std::fs::remove_file(root.join("src/scratch.rs")).unwrap();
+        }
+    });
+
+    let events = tokio::time::timeout(Duration::from_secs(10), watcher.changes())
+        .await
+        .unwrap();
+    writer.await.unwrap();
+    assert_eq!(
+        render(&root, &events),
+        (0..5)
+            .map(|index| format!("created src/module_{index}.rs"))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn test_changes_skips_bursts_that_cancel_out() {
+    let dir = workspace();
+    let root = dir.path().canonicalize().unwrap();
+    let mut watcher = FileWatcher::new(&root, config());
+
+    // Reverting a change within the burst is not reported, the next change is
+    std::fs::write(root.join("src/user.rs"), "pub struct Account;").unwrap();
+    tokio::spawn({
+        let root = root.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(40)).await;
+            std::fs::write(root.join("src/user.rs"), "pub struct User;").unwrap();
+            tokio::time::sleep(Duration::from_millis(400)).await;
+            std::fs::write(root.join("src/lib.rs"), "pub mod account;").unwrap();
+        }
+    });
+
+    let events = tokio::time::timeout(Duration::from_secs(10), watcher.changes())
+        .await
+        .unwrap();
+    assert_eq!(render(&root, &events), ["changed src/lib.rs"]);
+}
+
+// Property-based tests
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn test_poll_reports_only_changed_contents(
+        contents in prop::collection::vec(("[a-z]{0,8}", "[a-z]{0,8}"), 1..5),
+    ) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        for (index, (before, _)) in contents.iter().enumerate() {
+            std::fs::write(root.join(format!("{index}.txt")), before).unwrap();
+        }
+        let mut watcher = FileWatcher::new(&root, config());
+        for (index, (_, after)) in contents.iter().enumerate() {
+            std::fs::write(root.join(format!("{index}.txt")), after).unwrap();
+        }
+
+        let expected: Vec<String> = contents
+            .iter()
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(index, _)| format!("changed {index}.txt"))
+            .collect();
+        prop_assert_eq!(render(&root, &watcher.poll()), expected);
+    }
+}
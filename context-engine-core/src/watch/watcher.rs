@@ -0,0 +1,284 @@
+//! Polling of the files of a workspace.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use lsp_types::{FileChangeType, FileEvent, Uri};
+use tracing::{debug, warn};
+
+use crate::cache::content_hash;
+use crate::index::DEFAULT_EXCLUDES;
+use crate::types::UriExt;
+use crate::watch::IgnoreRules;
+use crate::workspace::Glob;
+
+/// How a [`FileWatcher`] polls the workspace.
+#[derive(Debug, Clone)]
+pub struct WatchConfig {
+    /// The time between two polls while the workspace is quiet
+    pub poll_interval: Duration,
+    /// The time without further changes after which a burst of changes is
+    /// reported
+    pub debounce: Duration,
+    /// Paths relative to the workspace root that are not watched, in
+    /// addition to those ignored by `.gitignore` files
+    pub excludes: Vec<Glob>,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_millis(500),
+            debounce: Duration::from_millis(200),
+            excludes: DEFAULT_EXCLUDES
+                .iter()
+                .filter_map(|name| Glob::new(name).ok())
+                .collect(),
+        }
+    }
+}
+
+/// The state of a watched file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+/// The watched files by path.
+type Files = BTreeMap<PathBuf, FileState>;
+
+/// Watches the files of a workspace for changes.
+///
+/// The watcher polls the workspace, so it works on every platform and file
+/// system. Hidden files and directories, the configured excludes and the
+/// paths ignored by `.gitignore` files are not watched. A file whose
+/// modification time changed is only reported if its content hash changed
+/// too, so writes that keep the content, e.g. saving an unchanged buffer,
+/// are skipped.
+///
+/// Changes are debounced: once a poll finds a change, the watcher keeps
+/// polling every [debounce] until the workspace is quiet, and then reports
+/// the net changes of the whole burst. A `git checkout` that rewrites
+/// hundreds of files thus results in one batch, and a file that was created
+/// and deleted again is not reported at all.
+///
+/// [debounce]: WatchConfig::debounce
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::watch::{FileWatcher, WatchConfig};
+/// use lsp_types::FileChangeType;
+///
+/// let dir = std::env::temp_dir().join(format!("file-watcher-doc-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir).unwrap();
+/// std::fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+///
+/// let mut watcher = FileWatcher::new(&dir, WatchConfig::default());
+/// assert_eq!(watcher.len(), 1);
+///
+/// std::fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+/// assert!(watcher.poll().is_empty());
+///
+/// std::fs::write(dir.join("main.rs"), "fn main() { run() }").unwrap();
+/// let events = watcher.poll();
+/// assert_eq!(events[0].typ, FileChangeType::CHANGED);
+/// # std::fs::remove_dir_all(&dir).ok();
+/// ```
+#[derive(Debug)]
+pub struct FileWatcher {
+    root: PathBuf,
+    config: WatchConfig,
+    files: Files,
+}
+
+impl FileWatcher {
+    /// Creates a watcher and records the current state of the workspace.
+    pub fn new(root: impl Into<PathBuf>, config: WatchConfig) -> Self {
+        let root = root.into();
+        let files = scan(&root, &config.excludes, &Files::new());
+        Self {
+            root,
+            config,
+            files,
+        }
+    }
+
+    /// Returns the workspace root.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the number of watched files.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns true if no files are watched.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Polls the workspace once, without debouncing.
+    ///
+    /// # Returns
+    ///
+    /// The changes since the last poll, ordered by path.
+    pub fn poll(&mut self) -> Vec<FileEvent> {
+        let files = scan(&self.root, &self.config.excludes, &self.files);
+        let events = diff(&self.files, &files);
+        self.files = files;
+        events
+    }
+
+    /// Waits for the next burst of changes.
+    ///
+    /// Polls the workspace every [poll interval] until something changes,
+    /// then every [debounce] until nothing changes anymore. Bursts whose
+    /// changes cancel out are not reported.
+    ///
+    /// # Returns
+    ///
+    /// The net changes of the burst, ordered by path; never empty.
+    ///
+    /// [poll interval]: WatchConfig::poll_interval
+    /// [debounce]: WatchConfig::debounce
+    pub async fn changes(&mut self) -> Vec<FileEvent> {
+        loop {
+            tokio::time::sleep(self.config.poll_interval).await;
+            let mut latest = self.scan(&self.files).await;
+            if diff(&self.files, &latest).is_empty() {
+                continue;
+            }
+
+            loop {
+                tokio::time::sleep(self.config.debounce).await;
+                let next = self.scan(&latest).await;
+                let quiet = diff(&latest, &next).is_empty();
+                latest = next;
+                if quiet {
+                    break;
+                }
+            }
+
+            let events = diff(&self.files, &latest);
+            self.files = latest;
+            if !events.is_empty() {
+                debug!(changes = events.len(), "Workspace files changed");
+                return events;
+            }
+        }
+    }
+
+    /// Scans the workspace on a blocking thread.
+    async fn scan(&self, previous: &Files) -> Files {
+        let root = self.root.clone();
+        let excludes = self.config.excludes.clone();
+        let previous = previous.clone();
+        match tokio::task::spawn_blocking(move || scan(&root, &excludes, &previous)).await {
+            Ok(files) => files,
+            Err(error) => {
+                warn!(%error, "Failed to scan workspace files");
+                self.files.clone()
+            }
+        }
+    }
+}
+
+/// Returns the watched files below `root`.
+///
+/// Files whose modification time and length did not change since the
+/// `previous` scan keep their hash instead of being read again.
+fn scan(root: &Path, excludes: &[Glob], previous: &Files) -> Files {
+    let mut rules = IgnoreRules::new(excludes.to_vec());
+    let mut files = Files::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let relative_dir = dir.strip_prefix(root).unwrap_or(&dir);
+        if let Ok(content) = std::fs::read_to_string(dir.join(".gitignore")) {
+            rules.add_gitignore(relative_dir, &content);
+        }
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(error) => {
+                debug!(path = %dir.display(), %error, "Failed to read watched directory");
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            if rules.is_ignored(relative, file_type.is_dir()) {
+                continue;
+            }
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file() {
+                if let Some(state) = file_state(&path, previous.get(&path)) {
+                    files.insert(path, state);
+                }
+            }
+        }
+    }
+    files
+}
+
+/// Returns the state of a file, reusing the hash of its previous state if
+/// the file looks unchanged; `None` if the file is gone.
+fn file_state(path: &Path, previous: Option<&FileState>) -> Option<FileState> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok();
+    let len = metadata.len();
+    if let Some(previous) = previous {
+        if previous.modified == modified && previous.len == len && modified.is_some() {
+            return Some(*previous);
+        }
+    }
+    let content = std::fs::read(path).ok()?;
+    Some(FileState {
+        modified,
+        len,
+        hash: content_hash(&content),
+    })
+}
+
+/// Returns the changes between two scans, ordered by path.
+fn diff(old: &Files, new: &Files) -> Vec<FileEvent> {
+    let mut changes = Vec::new();
+    for (path, state) in new {
+        match old.get(path) {
+            None => changes.push((path, FileChangeType::CREATED)),
+            Some(old) if old.hash != state.hash => changes.push((path, FileChangeType::CHANGED)),
+            Some(_) => {}
+        }
+    }
+    for path in old.keys().filter(|path| !new.contains_key(*path)) {
+        changes.push((path, FileChangeType::DELETED));
+    }
+    changes.sort_by_key(|(path, _)| *path);
+
+    changes
+        .into_iter()
+        .filter_map(|(path, typ)| match Uri::from_file_path(path) {
+            Ok(uri) => Some(FileEvent::new(uri, typ)),
+            Err(error) => {
+                debug!(path = %path.display(), %error, "Skipping change of unaddressable file");
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "tests/watcher.rs"]
+mod tests;
@@ -0,0 +1,123 @@
+//! Interning of document URIs into compact identifiers.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use lsp_types::Uri;
+use serde::{Deserialize, Serialize};
+
+/// Compact identifier of a document in a workspace.
+///
+/// Ids are handed out by a [`DocumentTable`] in the order documents are first
+/// seen and are only meaningful together with the table that issued them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct DocumentId(u32);
+
+impl DocumentId {
+    /// Returns the raw index of the id in its [`DocumentTable`].
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for DocumentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "doc#{}", self.0)
+    }
+}
+
+/// Bidirectional table between document URIs and [`DocumentId`]s.
+///
+/// The table does not normalize URIs itself; callers are expected to intern
+/// canonical URIs only (see
+/// [`UriNormalizer`](crate::workspace::UriNormalizer)).
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::types::Uri;
+/// use context_engine_core::workspace::DocumentTable;
+/// use std::str::FromStr;
+///
+/// let mut table = DocumentTable::default();
+/// let main = Uri::from_str("file:///src/main.rs").unwrap();
+/// let lib = Uri::from_str("file:///src/lib.rs").unwrap();
+///
+/// let main_id = table.intern(&main);
+/// let lib_id = table.intern(&lib);
+/// assert_ne!(main_id, lib_id);
+/// assert_eq!(table.intern(&main), main_id);
+///
+/// assert_eq!(table.uri(lib_id), Some(&lib));
+/// assert_eq!(table.get(&main), Some(main_id));
+/// assert_eq!(table.len(), 2);
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<Uri>", into = "Vec<Uri>")]
+pub struct DocumentTable {
+    uris: Vec<Uri>,
+    ids: HashMap<Uri, DocumentId>,
+}
+
+impl DocumentTable {
+    /// Returns the id of the URI, assigning a new one if the URI has not been
+    /// seen before.
+    pub fn intern(&mut self, uri: &Uri) -> DocumentId {
+        if let Some(&id) = self.ids.get(uri) {
+            return id;
+        }
+
+        let id = DocumentId(u32::try_from(self.uris.len()).unwrap_or(u32::MAX));
+        self.uris.push(uri.clone());
+        self.ids.insert(uri.clone(), id);
+        id
+    }
+
+    /// Returns the id of the URI if it has been interned.
+    pub fn get(&self, uri: &Uri) -> Option<DocumentId> {
+        self.ids.get(uri).copied()
+    }
+
+    /// Returns the URI of the id if it was issued by this table.
+    pub fn uri(&self, id: DocumentId) -> Option<&Uri> {
+        self.uris.get(id.0 as usize)
+    }
+
+    /// Returns the number of interned documents.
+    pub fn len(&self) -> usize {
+        self.uris.len()
+    }
+
+    /// Returns true if no document has been interned.
+    pub fn is_empty(&self) -> bool {
+        self.uris.is_empty()
+    }
+
+    /// Iterates over all interned documents in id order.
+    pub fn iter(&self) -> impl Iterator<Item = (DocumentId, &Uri)> {
+        self.uris
+            .iter()
+            .enumerate()
+            .map(|(index, uri)| (DocumentId(u32::try_from(index).unwrap_or(u32::MAX)), uri))
+    }
+}
+
+impl From<Vec<Uri>> for DocumentTable {
+    fn from(uris: Vec<Uri>) -> Self {
+        let mut table = DocumentTable::default();
+        for uri in &uris {
+            table.intern(uri);
+        }
+        table
+    }
+}
+
+impl From<DocumentTable> for Vec<Uri> {
+    fn from(table: DocumentTable) -> Self {
+        table.uris
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/document_id.rs"]
+mod tests;
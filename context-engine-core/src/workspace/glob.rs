@@ -0,0 +1,277 @@
+//! Glob patterns for matching workspace paths.
+
+use std::fmt;
+use std::path::{Component, Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ContextEngineError;
+
+/// A compiled glob pattern.
+///
+/// Patterns are matched against `/`-separated paths and support
+///
+/// * `?` - any character except `/`,
+/// * `*` - any sequence of characters except `/`,
+/// * `**` - as a whole path segment, any number of segments (including none),
+/// * `[abc]`, `[a-z]`, `[!a-z]` - a character (not) in a set, and
+/// * `\` - escaping the following character.
+///
+/// A pattern without a `/` matches the last segment of a path, e.g. `*.rs`
+/// matches `src/main.rs`. Any other pattern matches the whole path; a leading
+/// `/` is ignored.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::workspace::Glob;
+///
+/// let glob = Glob::new("*.d.ts").unwrap();
+/// assert!(glob.is_match("types/index.d.ts"));
+/// assert!(!glob.is_match("types/index.ts"));
+///
+/// let glob = Glob::new("src/**/test_*.py").unwrap();
+/// assert!(glob.is_match("src/test_api.py"));
+/// assert!(glob.is_match("src/app/unit/test_api.py"));
+/// assert!(!glob.is_match("lib/src/test_api.py"));
+/// ```
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Glob {
+    pattern: String,
+    tokens: Vec<Token>,
+    basename: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Char(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    Star,
+    /// `**/`, matching an empty string or any string ending with `/`
+    AnyDirs,
+    /// `**` that is not followed by `/`
+    AnyPath,
+    /// `[...]`
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    /// Compiles a glob pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextEngineError::Configuration`] if the pattern is empty
+    /// or contains an unclosed character class.
+    pub fn new(pattern: &str) -> Result<Self, ContextEngineError> {
+        let invalid = |reason: &str| ContextEngineError::Configuration {
+            key: None,
+            message: format!("Invalid glob pattern {pattern:?}: {reason}"),
+        };
+
+        let trimmed = pattern.strip_prefix('/').unwrap_or(pattern);
+        if trimmed.is_empty() {
+            return Err(invalid("empty pattern"));
+        }
+
+        let mut tokens = Vec::new();
+        let mut chars = trimmed.chars().peekable();
+        let mut segment_start = true;
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '?' => Token::AnyChar,
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    match chars.peek() {
+                        Some('/') if segment_start => {
+                            chars.next();
+                            segment_start = true;
+                            tokens.push(Token::AnyDirs);
+                            continue;
+                        }
+                        None if segment_start => Token::AnyPath,
+                        // `**` within a segment behaves like `*`
+                        _ => Token::Star,
+                    }
+                }
+                '*' => Token::Star,
+                '[' => parse_class(&mut chars).ok_or_else(|| invalid("unclosed '['"))?,
+                '\\' => Token::Char(chars.next().ok_or_else(|| invalid("trailing '\\'"))?),
+                c => Token::Char(c),
+            };
+            segment_start = token == Token::Char('/');
+            tokens.push(token);
+        }
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            basename: !trimmed.contains('/'),
+            tokens,
+        })
+    }
+
+    /// Returns the source of the pattern.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns true if the pattern only matches the last segment of paths.
+    pub fn is_basename_pattern(&self) -> bool {
+        self.basename
+    }
+
+    /// Returns true if the `/`-separated path matches the pattern.
+    pub fn is_match(&self, path: &str) -> bool {
+        let path = path.strip_prefix('/').unwrap_or(path);
+        let subject = if self.basename {
+            path.rsplit('/').next().unwrap_or(path)
+        } else {
+            path
+        };
+
+        let chars = subject.chars().collect::<Vec<_>>();
+        matches(&self.tokens, &chars)
+    }
+
+    /// Returns true if the path matches the pattern.
+    ///
+    /// The components of the path are joined with `/`; prefixes such as
+    /// Windows drive letters are ignored.
+    pub fn is_match_path(&self, path: &Path) -> bool {
+        let path = path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        self.is_match(&path)
+    }
+}
+
+impl fmt::Debug for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Glob").field(&self.pattern).finish()
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+impl TryFrom<String> for Glob {
+    type Error = ContextEngineError;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Glob::new(&pattern)
+    }
+}
+
+impl From<Glob> for String {
+    fn from(glob: Glob) -> Self {
+        glob.pattern
+    }
+}
+
+/// Parses a character class after its opening `[`.
+fn parse_class(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<Token> {
+    let negated = matches!(chars.peek(), Some('!' | '^'));
+    if negated {
+        chars.next();
+    }
+
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        let c = chars.next()?;
+        if c == ']' && !first {
+            return Some(Token::Class { negated, ranges });
+        }
+        first = false;
+
+        let start = if c == '\\' { chars.next()? } else { c };
+        let mut lookahead = chars.clone();
+        let end = match (lookahead.next(), lookahead.next()) {
+            (Some('-'), Some(end)) if end != ']' => {
+                chars.next();
+                chars.next();
+                end
+            }
+            _ => start,
+        };
+        ranges.push((start, end));
+    }
+}
+
+impl Token {
+    fn matches_char(&self, c: char) -> bool {
+        match self {
+            Token::Char(expected) => *expected == c,
+            Token::AnyChar => c != '/',
+            Token::Class { negated, ranges } => {
+                c != '/' && ranges.iter().any(|&(start, end)| start <= c && c <= end) != *negated
+            }
+            Token::Star | Token::AnyDirs | Token::AnyPath => false,
+        }
+    }
+}
+
+/// Returns true if the tokens match the whole text.
+fn matches(tokens: &[Token], text: &[char]) -> bool {
+    match tokens {
+        [] => text.is_empty(),
+        [Token::Star, rest @ ..] => {
+            let mut tail = text;
+            loop {
+                if matches(rest, tail) {
+                    return true;
+                }
+                match tail.split_first() {
+                    Some((c, next)) if *c != '/' => tail = next,
+                    _ => return false,
+                }
+            }
+        }
+        [Token::AnyPath, rest @ ..] => {
+            let mut tail = text;
+            loop {
+                if matches(rest, tail) {
+                    return true;
+                }
+                match tail.split_first() {
+                    Some((_, next)) => tail = next,
+                    None => return false,
+                }
+            }
+        }
+        [Token::AnyDirs, rest @ ..] => {
+            if matches(rest, text) {
+                return true;
+            }
+            let mut tail = text;
+            while let Some((c, next)) = tail.split_first() {
+                tail = next;
+                if *c == '/' && matches(rest, tail) {
+                    return true;
+                }
+            }
+            false
+        }
+        [token, rest @ ..] => match text.split_first() {
+            Some((c, tail)) => token.matches_char(*c) && matches(rest, tail),
+            None => false,
+        },
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/glob.rs"]
+mod tests;
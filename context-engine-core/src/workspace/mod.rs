@@ -0,0 +1,47 @@
+//! Workspace-level identity of documents.
+//!
+//! Language servers do not agree on how they spell the URI of a file: drive
+//! letters differ in case, paths contain `..` segments or trailing slashes,
+//! and files may be reached through symlinks. This module canonicalizes URIs
+//! relative to a workspace root and interns every file into a compact
+//! [`DocumentId`], so that results from several servers can be compared and
+//! deduplicated cheaply. [`Glob`] patterns select workspace paths, e.g. to
+//! assign files to languages.
+//!
+//! ## Structs
+//!
+//! * [`UriNormalizer`] - Canonicalizes URIs of a workspace and interns them
+//!   into [`DocumentId`]s.
+//! * [`DocumentTable`] - Bidirectional table between canonical URIs and
+//!   [`DocumentId`]s.
+//! * [`DocumentId`] - Compact identifier of a document in a workspace.
+//! * [`Glob`] - Compiled glob pattern for matching workspace paths.
+//!
+//! ## Usage Example
+//!
+//! ```
+//! use context_engine_core::types::{Uri, UriExt};
+//! use context_engine_core::workspace::UriNormalizer;
+//! use std::str::FromStr;
+//!
+//! let root = std::env::temp_dir();
+//! let normalizer = UriNormalizer::new(&root).unwrap();
+//!
+//! let uri = Uri::from_file_path(&root.join("src/main.rs")).unwrap();
+//! let messy = Uri::from_file_path(&root.join("src/../src/./main.rs")).unwrap();
+//!
+//! let id = normalizer.intern(&uri).unwrap();
+//! assert_eq!(normalizer.intern(&messy).unwrap(), id);
+//! assert_eq!(
+//!     normalizer.relative_path(&uri).unwrap(),
+//!     Some(std::path::PathBuf::from("src/main.rs"))
+//! );
+//! ```
+
+mod document_id;
+mod glob;
+mod normalizer;
+
+pub use document_id::{DocumentId, DocumentTable};
+pub use glob::Glob;
+pub use normalizer::UriNormalizer;
@@ -0,0 +1,242 @@
+//! Canonicalization of document URIs relative to a workspace root.
+
+use std::path::{Component, Path, PathBuf};
+use std::{fs, io};
+
+use lsp_types::Uri;
+use parking_lot::RwLock;
+
+use crate::types::{LocationError, UriExt};
+use crate::workspace::{DocumentId, DocumentTable};
+
+/// Canonicalizes the URIs of a workspace and interns them into
+/// [`DocumentId`]s.
+///
+/// Two URIs that name the same file yield the same canonical URI and the same
+/// id, no matter how the language server spelled them. Canonicalization
+///
+/// 1. percent-decodes the URI into a file path (see [`UriExt::to_file_path`]),
+/// 2. removes `.` and `..` segments and trailing slashes lexically,
+/// 3. resolves symlinks of the longest prefix of the path that exists on disk,
+/// 4. spells Windows drive letters in upper case, and
+/// 5. re-encodes the path as a file URI (see [`UriExt::from_file_path`]).
+///
+/// The normalizer is shared between all language servers of a workspace, so
+/// interning is internally synchronized.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::types::{Uri, UriExt};
+/// use context_engine_core::workspace::UriNormalizer;
+///
+/// let root = std::env::temp_dir();
+/// let normalizer = UriNormalizer::new(&root).unwrap();
+///
+/// let uri = Uri::from_file_path(&root.join("crate/src/lib.rs")).unwrap();
+/// let id = normalizer.intern(&uri).unwrap();
+///
+/// assert_eq!(normalizer.uri(id), Some(normalizer.normalize(&uri).unwrap()));
+/// assert_eq!(normalizer.get(&uri), Some(id));
+/// ```
+#[derive(Debug)]
+pub struct UriNormalizer {
+    root: PathBuf,
+    table: RwLock<DocumentTable>,
+}
+
+impl UriNormalizer {
+    /// Creates a normalizer for the workspace rooted at `root`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LocationError::InvalidUri`] if the root is not an absolute
+    /// path.
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, LocationError> {
+        Self::with_table(root, DocumentTable::default())
+    }
+
+    /// Creates a normalizer for the workspace rooted at `root` that continues
+    /// interning into an existing table, e.g. one restored from a cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LocationError::InvalidUri`] if the root is not an absolute
+    /// path.
+    pub fn with_table(root: impl AsRef<Path>, table: DocumentTable) -> Result<Self, LocationError> {
+        let root = root.as_ref();
+        if !root.is_absolute() {
+            return Err(LocationError::InvalidUri(format!(
+                "workspace root must be absolute, got {}",
+                root.display()
+            )));
+        }
+
+        Ok(Self {
+            root: canonicalize(root),
+            table: RwLock::new(table),
+        })
+    }
+
+    /// Returns the canonical workspace root.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the canonical form of a file path.
+    ///
+    /// Relative paths are resolved against the workspace root.
+    pub fn normalize_path(&self, path: &Path) -> PathBuf {
+        if path.is_absolute() {
+            canonicalize(path)
+        } else {
+            canonicalize(&self.root.join(path))
+        }
+    }
+
+    /// Returns the canonical form of a file URI.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URI is not a local file URI.
+    pub fn normalize(&self, uri: &Uri) -> Result<Uri, LocationError> {
+        let path = uri.to_file_path()?;
+        Uri::from_file_path(&self.normalize_path(&path))
+    }
+
+    /// Returns the path of the URI relative to the workspace root.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(PathBuf))` - The relative path if the file is inside the
+    ///   workspace
+    /// * `Ok(None)` - If the file is outside the workspace
+    /// * `Err(LocationError)` - If the URI is not a local file URI
+    pub fn relative_path(&self, uri: &Uri) -> Result<Option<PathBuf>, LocationError> {
+        let path = self.normalize_path(&uri.to_file_path()?);
+        Ok(path.strip_prefix(&self.root).ok().map(Path::to_path_buf))
+    }
+
+    /// Returns true if the URI names a file inside the workspace.
+    pub fn contains(&self, uri: &Uri) -> bool {
+        matches!(self.relative_path(uri), Ok(Some(_)))
+    }
+
+    /// Canonicalizes the URI and returns its id, assigning a new one if the
+    /// file has not been seen before.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URI is not a local file URI.
+    pub fn intern(&self, uri: &Uri) -> Result<DocumentId, LocationError> {
+        let canonical = self.normalize(uri)?;
+        if let Some(id) = self.table.read().get(&canonical) {
+            return Ok(id);
+        }
+
+        Ok(self.table.write().intern(&canonical))
+    }
+
+    /// Returns the id of the URI if the file has been interned.
+    pub fn get(&self, uri: &Uri) -> Option<DocumentId> {
+        let canonical = self.normalize(uri).ok()?;
+        self.table.read().get(&canonical)
+    }
+
+    /// Returns the canonical URI of an id.
+    pub fn uri(&self, id: DocumentId) -> Option<Uri> {
+        self.table.read().uri(id).cloned()
+    }
+
+    /// Returns a copy of the table of all interned documents.
+    pub fn table(&self) -> DocumentTable {
+        self.table.read().clone()
+    }
+}
+
+/// Returns the canonical form of an absolute path.
+///
+/// The path is cleaned up lexically first. Symlinks are then resolved for the
+/// longest prefix of the path that exists; the rest of the path is appended
+/// as is, so paths of files that have not been created yet still normalize
+/// consistently.
+fn canonicalize(path: &Path) -> PathBuf {
+    let lexical = normalize_lexically(path);
+
+    let mut existing = lexical.as_path();
+    let mut missing = Vec::new();
+    loop {
+        match fs::canonicalize(existing) {
+            Ok(resolved) => {
+                let mut resolved = simplify(resolved);
+                resolved.extend(missing.iter().rev());
+                return resolved;
+            }
+            Err(error) if error.kind() == io::ErrorKind::PermissionDenied => break,
+            Err(_) => {}
+        }
+
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+
+    lexical
+}
+
+/// Removes `.` and `..` segments and trailing separators without touching
+/// the file system.
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+
+    simplify(normalized)
+}
+
+/// Brings a canonical path into the form used in file URIs.
+#[cfg(not(windows))]
+fn simplify(path: PathBuf) -> PathBuf {
+    path
+}
+
+/// Brings a canonical path into the form used in file URIs.
+///
+/// `fs::canonicalize` returns verbatim paths (`\\?\C:\...`) on Windows and
+/// drive letters may be spelled in either case, so both are normalized to a
+/// plain upper-case drive path.
+#[cfg(windows)]
+fn simplify(path: PathBuf) -> PathBuf {
+    use std::path::Prefix;
+
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path;
+    };
+
+    let prefix = match prefix.kind() {
+        Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+            format!("{}:", char::from(letter).to_ascii_uppercase())
+        }
+        _ => return path,
+    };
+
+    let mut simplified = PathBuf::from(prefix);
+    simplified.extend(components);
+    simplified
+}
+
+#[cfg(test)]
+#[path = "tests/normalizer.rs"]
+mod tests;
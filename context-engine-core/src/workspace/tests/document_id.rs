@@ -0,0 +1,64 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use proptest::prelude::*;
+
+use super::*;
+
+#[test]
+fn test_document_table_interning() {
+    let mut table = DocumentTable::default();
+    assert!(table.is_empty());
+
+    let main = Uri::from_str("file:///src/main.rs").unwrap();
+    let lib = Uri::from_str("file:///src/lib.rs").unwrap();
+
+    let main_id = table.intern(&main);
+    let lib_id = table.intern(&lib);
+    assert_eq!(main_id.index(), 0);
+    assert_eq!(lib_id.index(), 1);
+    assert_eq!(table.intern(&main), main_id);
+    assert_eq!(table.len(), 2);
+
+    assert_eq!(table.get(&lib), Some(lib_id));
+    assert_eq!(table.uri(main_id), Some(&main));
+    assert_eq!(table.uri(DocumentId(2)), None);
+    assert_eq!(
+        table.iter().collect::<Vec<_>>(),
+        vec![(main_id, &main), (lib_id, &lib)]
+    );
+    assert_eq!(main_id.to_string(), "doc#0");
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_document_table_serialization_roundtrip(
+        filenames in prop::collection::vec("[a-z0-9_]{1,8}\\.rs", 0..20)
+    ) {
+        let mut table = DocumentTable::default();
+        let ids = filenames
+            .iter()
+            .map(|filename| {
+                let uri = Uri::from_str(&format!("file:///src/{filename}")).unwrap();
+                (table.intern(&uri), uri)
+            })
+            .collect::<Vec<_>>();
+
+        let config = bincode::config::standard();
+        let encoded = bincode::serde::encode_to_vec(&table, config).unwrap();
+        let (decoded, _): (DocumentTable, usize) =
+            bincode::serde::decode_from_slice(&encoded, config).unwrap();
+
+        let json = serde_json::to_string(&table).unwrap();
+        let from_json: DocumentTable = serde_json::from_str(&json).unwrap();
+
+        for (id, uri) in ids {
+            prop_assert_eq!(decoded.get(&uri), Some(id));
+            prop_assert_eq!(decoded.uri(id), Some(&uri));
+            prop_assert_eq!(from_json.get(&uri), Some(id));
+        }
+        prop_assert_eq!(decoded.len(), table.len());
+    }
+}
@@ -0,0 +1,110 @@
+#![allow(clippy::unwrap_used)]
+
+use std::path::PathBuf;
+
+use proptest::prelude::*;
+
+use super::*;
+
+#[test]
+fn test_glob_wildcards() {
+    let cases = [
+        ("*.rs", "src/main.rs", true),
+        ("*.rs", "main.rs.bak", false),
+        ("?.py", "a.py", true),
+        ("?.py", "ab.py", false),
+        ("Dockerfile", "ops/Dockerfile", true),
+        ("Dockerfile.*", "ops/Dockerfile.dev", true),
+        ("src/*.rs", "src/lib.rs", true),
+        ("src/*.rs", "src/bin/main.rs", false),
+        ("/src/*.rs", "src/lib.rs", true),
+        ("src/**", "src/bin/main.rs", true),
+        ("**/tests/*.rs", "tests/api.rs", true),
+        ("**/tests/*.rs", "crates/core/tests/api.rs", true),
+        ("a/**/b", "a/b", true),
+        ("a/**/b", "a/x/y/b", true),
+        ("a/**/b", "a/xb", false),
+        ("a**b/c", "axyzb/c", true),
+        ("a**b/c", "ax/b/c", false),
+    ];
+
+    for (pattern, path, expected) in cases {
+        let glob = Glob::new(pattern).unwrap();
+        assert_eq!(glob.is_match(path), expected, "{pattern} against {path}");
+    }
+}
+
+#[test]
+fn test_glob_character_classes() {
+    let cases = [
+        ("[abc].txt", "b.txt", true),
+        ("[abc].txt", "d.txt", false),
+        ("[a-c0-9].txt", "7.txt", true),
+        ("[!a-c].txt", "a.txt", false),
+        ("[!a-c].txt", "z.txt", true),
+        ("[^a-c].txt", "z.txt", true),
+        ("[]].txt", "].txt", true),
+        ("[a-].txt", "-.txt", true),
+        ("x[!/]y", "x/y", false),
+        ("\\*.md", "*.md", true),
+        ("\\*.md", "a.md", false),
+    ];
+
+    for (pattern, path, expected) in cases {
+        let glob = Glob::new(pattern).unwrap();
+        assert_eq!(glob.is_match(path), expected, "{pattern} against {path}");
+    }
+}
+
+#[test]
+fn test_invalid_globs() {
+    for pattern in ["", "/", "[abc", "[!", "abc\\"] {
+        let error = Glob::new(pattern).unwrap_err();
+        assert!(
+            matches!(error, ContextEngineError::Configuration { .. }),
+            "{pattern}"
+        );
+    }
+}
+
+#[test]
+fn test_glob_paths_and_serde() {
+    let glob = Glob::new("src/**/*.rs").unwrap();
+    assert!(!glob.is_basename_pattern());
+    assert!(glob.is_match_path(&PathBuf::from("src").join("a").join("b.rs")));
+
+    let json = serde_json::to_string(&glob).unwrap();
+    assert_eq!(json, r#""src/**/*.rs""#);
+    assert_eq!(serde_json::from_str::<Glob>(&json).unwrap(), glob);
+    assert!(serde_json::from_str::<Glob>(r#""[""#).is_err());
+    assert_eq!(glob.to_string(), "src/**/*.rs");
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_literal_patterns_match_themselves(path in "[a-z0-9_.-]{1,8}(/[a-z0-9_.-]{1,8}){0,3}") {
+        let glob = Glob::new(&path).unwrap();
+        prop_assert!(glob.is_match(&path));
+        let other = format!("{path}x");
+        prop_assert!(!glob.is_match(&other));
+    }
+
+    #[test]
+    fn prop_double_star_prefix_matches_any_directory(
+        dirs in prop::collection::vec("[a-z]{1,5}", 0..4),
+        name in "[a-z]{1,5}",
+    ) {
+        let glob = Glob::new(&format!("**/{name}.rs")).unwrap();
+        let mut segments = dirs;
+        segments.push(format!("{name}.rs"));
+        prop_assert!(glob.is_match(&segments.join("/")));
+    }
+
+    #[test]
+    fn prop_star_does_not_cross_segments(dir in "[a-z]{1,5}", name in "[a-z]{1,5}") {
+        let glob = Glob::new("*/x").unwrap();
+        let path = format!("{dir}/{name}/x");
+        prop_assert!(!glob.is_match(&path));
+    }
+}
@@ -0,0 +1,127 @@
+#![allow(clippy::unwrap_used)]
+
+use std::str::FromStr;
+
+use proptest::prelude::*;
+
+use super::*;
+
+fn workspace() -> (tempfile::TempDir, UriNormalizer) {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+    let normalizer = UriNormalizer::new(dir.path()).unwrap();
+    (dir, normalizer)
+}
+
+#[test]
+fn test_normalizer_canonicalizes_lexically() {
+    let (dir, normalizer) = workspace();
+    let main = dir.path().join("src/main.rs");
+
+    let id = normalizer
+        .intern(&Uri::from_file_path(&main).unwrap())
+        .unwrap();
+
+    let variants = [
+        dir.path().join("src/nested/../main.rs"),
+        dir.path().join("src/./main.rs"),
+        dir.path().join("src/nested/../../src/main.rs"),
+    ];
+    for variant in variants {
+        let uri = Uri::from_file_path(&variant).unwrap();
+        assert_eq!(normalizer.intern(&uri).unwrap(), id);
+        assert_eq!(
+            normalizer.relative_path(&uri).unwrap(),
+            Some(PathBuf::from("src/main.rs"))
+        );
+    }
+
+    assert_eq!(normalizer.table().len(), 1);
+}
+
+#[test]
+fn test_normalizer_handles_missing_files_and_trailing_slashes() {
+    let (dir, normalizer) = workspace();
+
+    let missing = Uri::from_file_path(&dir.path().join("src/new/file.rs")).unwrap();
+    let spelled = Uri::from_file_path(&dir.path().join("src/nested/../new/./file.rs")).unwrap();
+    assert_eq!(
+        normalizer.intern(&missing).unwrap(),
+        normalizer.intern(&spelled).unwrap()
+    );
+
+    let folder = normalizer.normalize_path(&dir.path().join("src/nested/"));
+    assert_eq!(folder, normalizer.normalize_path(Path::new("src/nested")));
+}
+
+#[test]
+fn test_normalizer_relative_paths() {
+    let (_dir, normalizer) = workspace();
+
+    let outside = Uri::from_file_path(&normalizer.root().join("../elsewhere.rs")).unwrap();
+    assert_eq!(normalizer.relative_path(&outside).unwrap(), None);
+    assert!(!normalizer.contains(&outside));
+
+    let inside = Uri::from_file_path(&normalizer.root().join("src/main.rs")).unwrap();
+    assert!(normalizer.contains(&inside));
+
+    let http = Uri::from_str("http://example.com/main.rs").unwrap();
+    assert!(normalizer.relative_path(&http).is_err());
+    assert!(normalizer.intern(&http).is_err());
+    assert_eq!(normalizer.get(&http), None);
+
+    assert!(UriNormalizer::new("relative/root").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_normalizer_resolves_symlinks() {
+    let (dir, normalizer) = workspace();
+    std::os::unix::fs::symlink(dir.path().join("src"), dir.path().join("link")).unwrap();
+
+    let real = Uri::from_file_path(&dir.path().join("src/main.rs")).unwrap();
+    let linked = Uri::from_file_path(&dir.path().join("link/main.rs")).unwrap();
+
+    let id = normalizer.intern(&linked).unwrap();
+    assert_eq!(normalizer.get(&real), Some(id));
+    assert_eq!(
+        normalizer.uri(id),
+        Some(normalizer.normalize(&real).unwrap())
+    );
+}
+
+#[test]
+fn test_normalizer_with_restored_table() {
+    let (dir, normalizer) = workspace();
+    let main = Uri::from_file_path(&dir.path().join("src/main.rs")).unwrap();
+    let id = normalizer.intern(&main).unwrap();
+
+    let restored = UriNormalizer::with_table(dir.path(), normalizer.table()).unwrap();
+    assert_eq!(restored.get(&main), Some(id));
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_normalizer_ignores_dot_segments(
+        segments in prop::collection::vec("[a-z]{1,6}", 1..5),
+        detour in "[a-z]{1,6}",
+    ) {
+        let (dir, normalizer) = workspace();
+        let plain = dir.path().join(segments.join("/"));
+
+        let mut spelled = dir.path().to_path_buf();
+        for segment in &segments {
+            spelled.push(".");
+            spelled.push(&detour);
+            spelled.push("..");
+            spelled.push(segment);
+        }
+
+        let plain = Uri::from_file_path(&plain).unwrap();
+        let spelled = Uri::from_file_path(&spelled).unwrap();
+        prop_assert_eq!(normalizer.normalize(&plain).unwrap(), normalizer.normalize(&spelled).unwrap());
+        prop_assert_eq!(normalizer.intern(&plain).unwrap(), normalizer.intern(&spelled).unwrap());
+    }
+}
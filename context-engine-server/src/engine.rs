@@ -0,0 +1,522 @@
+//! The workspace state shared by all sessions of the server.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use context_engine_core::ContextEngineError;
+use context_engine_core::cache::{ANALYZER_VERSION, CacheKey, FileGraph, GraphStore, content_hash};
+use context_engine_core::deps::{DependencyIndexer, DependencyOutcome};
+use context_engine_core::diagnostics::ContentAnalyzer;
+use context_engine_core::document::DocumentStore;
+use context_engine_core::edit::Renamer;
+use context_engine_core::graph::KnowledgeGraph;
+use context_engine_core::index::IndexConfig;
+use context_engine_core::lsp::{LanguageRegistry, LspSupervisor, ServerStatus};
+use context_engine_core::types::{Uri, UriExt};
+use context_engine_core::watch::{
+    FileWatcher, Invalidation, WatchConfig, invalidate, notify_servers,
+};
+use lsp_types::FileEvent;
+use parking_lot::{Mutex, RwLock, RwLockReadGuard};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// The name of the file, in the directory of the store, that the checkpoint
+/// of the indexer is kept in.
+const CHECKPOINT_FILE: &str = "index.checkpoint";
+
+/// The analysis of a workspace: its language servers, open documents and
+/// knowledge graph.
+///
+/// An engine is shared by all MCP sessions of a server process, so that
+/// several agents use one index of the workspace. With a [`GraphStore`], the
+/// graph is saved after indexing and loaded again on the next start. The
+/// [`DependencyIndexer`] adds the locked dependencies of a Rust workspace to
+/// the graph, and [`Engine::watch`] keeps the graph in step with the files
+/// on disk.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_server::Engine;
+///
+/// let engine = Engine::new("/path/to/workspace");
+/// let status = engine.status();
+/// assert_eq!(status.symbols, 0);
+/// assert!(status.servers.is_empty());
+/// ```
+pub struct Engine {
+    root: PathBuf,
+    documents: Arc<DocumentStore>,
+    registry: Arc<LanguageRegistry>,
+    supervisor: Arc<LspSupervisor>,
+    analyzer: ContentAnalyzer,
+    renamer: Renamer,
+    dependencies: DependencyIndexer,
+    graph: RwLock<KnowledgeGraph>,
+    /// The content hash of every file by URI when the graph was indexed
+    indexed: RwLock<BTreeMap<String, u64>>,
+    store: Option<GraphStore>,
+    /// The task of the file watcher, once started
+    watcher: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Engine {
+    /// Creates an engine for a workspace with the default languages and
+    /// language servers. No server is started.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let documents = Arc::new(DocumentStore::default());
+        let supervisor = Arc::new(LspSupervisor::new(&root, Arc::clone(&documents)));
+        Self::with_supervisor(
+            root,
+            documents,
+            Arc::new(LanguageRegistry::with_defaults()),
+            supervisor,
+        )
+    }
+
+    /// Creates an engine on top of existing components.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The workspace root
+    /// * `documents` - The open documents, shared with the supervisor
+    /// * `registry` - Routes documents to language servers
+    /// * `supervisor` - Runs the language servers
+    pub fn with_supervisor(
+        root: impl Into<PathBuf>,
+        documents: Arc<DocumentStore>,
+        registry: Arc<LanguageRegistry>,
+        supervisor: Arc<LspSupervisor>,
+    ) -> Self {
+        let analyzer = ContentAnalyzer::new(
+            Arc::clone(&supervisor),
+            Arc::clone(&registry),
+            Arc::clone(&documents),
+        );
+        let renamer = Renamer::new(
+            Arc::clone(&supervisor),
+            Arc::clone(&registry),
+            Arc::clone(&documents),
+        );
+        let root = root.into();
+        let dependencies = DependencyIndexer::new(
+            &root,
+            Arc::clone(&supervisor),
+            Arc::clone(&registry),
+            Arc::clone(&documents),
+        );
+        Self {
+            root,
+            documents,
+            registry,
+            supervisor,
+            analyzer,
+            renamer,
+            dependencies,
+            graph: RwLock::new(KnowledgeGraph::new()),
+            indexed: RwLock::default(),
+            store: None,
+            watcher: Mutex::new(None),
+        }
+    }
+
+    /// Keeps the graph of the workspace in a store, so that a restart does
+    /// not start from an empty graph. See [`Engine::load_graph`] and
+    /// [`Engine::save_graph`].
+    pub fn with_store(mut self, store: GraphStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Returns the workspace root.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the open documents.
+    pub fn documents(&self) -> &Arc<DocumentStore> {
+        &self.documents
+    }
+
+    /// Returns the language registry.
+    pub fn registry(&self) -> &Arc<LanguageRegistry> {
+        &self.registry
+    }
+
+    /// Returns the supervisor of the language servers.
+    pub fn supervisor(&self) -> &Arc<LspSupervisor> {
+        &self.supervisor
+    }
+
+    /// Returns the analyzer of unsaved content, shared so that analyses of
+    /// the same document wait for each other.
+    pub fn analyzer(&self) -> &ContentAnalyzer {
+        &self.analyzer
+    }
+
+    /// Returns the renamer of symbols.
+    pub fn renamer(&self) -> &Renamer {
+        &self.renamer
+    }
+
+    /// Indexes the dependencies with another indexer, e.g. one with another
+    /// cache directory.
+    pub fn with_dependencies(mut self, dependencies: DependencyIndexer) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Returns the indexer of the dependencies of the workspace.
+    pub fn dependencies(&self) -> &DependencyIndexer {
+        &self.dependencies
+    }
+
+    /// Returns the store the graph is kept in, if any.
+    pub fn store(&self) -> Option<&GraphStore> {
+        self.store.as_ref()
+    }
+
+    /// Returns the knowledge graph of the workspace.
+    pub fn graph(&self) -> RwLockReadGuard<'_, KnowledgeGraph> {
+        self.graph.read()
+    }
+
+    /// Replaces the knowledge graph of the workspace, e.g. after indexing.
+    pub fn set_graph(&self, graph: KnowledgeGraph) {
+        *self.graph.write() = graph;
+    }
+
+    /// Returns the content hash of every file by URI when the graph was
+    /// indexed, to tell whether a file changed since.
+    pub fn indexed_files(&self) -> RwLockReadGuard<'_, BTreeMap<String, u64>> {
+        self.indexed.read()
+    }
+
+    /// Replaces the content hashes of the indexed files, e.g. after
+    /// indexing.
+    pub fn set_indexed_files(&self, files: BTreeMap<String, u64>) {
+        *self.indexed.write() = files;
+    }
+
+    /// Replaces the graph with the files of the store whose content did not
+    /// change since they were saved.
+    ///
+    /// Waits for the started language servers to be initialized, as their
+    /// versions are part of the keys of the entries. Entries of changed or
+    /// deleted files, or of another version of the analyzer or of the main
+    /// language server of their file, are removed.
+    ///
+    /// # Returns
+    ///
+    /// The number of restored files; 0 without a store. A store that cannot
+    /// be read is logged and restores nothing.
+    pub async fn load_graph(&self) -> usize {
+        let Some(store) = &self.store else {
+            return 0;
+        };
+        let entries = match store.entries() {
+            Ok(entries) => entries,
+            Err(error) => {
+                warn!(%error, "Failed to load the graph store");
+                return 0;
+            }
+        };
+
+        let servers = entries
+            .iter()
+            .filter_map(|(_, file)| {
+                let route = self.registry.route(&file.uri, None);
+                route.first().map(|route| route.server.name.clone())
+            })
+            .collect::<BTreeSet<_>>();
+        for server in servers {
+            if self.supervisor.status(&server).is_some() {
+                if let Err(error) = self.supervisor.client(&server).await {
+                    warn!(server, %error, "Language server unavailable, dropping its stored files");
+                }
+            }
+        }
+
+        let mut files = Vec::new();
+        let mut indexed = BTreeMap::new();
+        for (key, file) in entries {
+            let content = file
+                .uri
+                .to_file_path()
+                .ok()
+                .and_then(|path| std::fs::read(path).ok());
+            let hash = content.map(|content| content_hash(&content));
+            if key.analyzer_version != ANALYZER_VERSION
+                || key.server_version != self.server_version(&file.uri)
+                || hash != Some(key.content_hash)
+            {
+                if let Err(error) = store.remove(&file.uri) {
+                    warn!(%error, "Failed to remove a stale graph store entry");
+                }
+                continue;
+            }
+            indexed.insert(file.uri.as_str().to_string(), key.content_hash);
+            files.push(file);
+        }
+
+        self.set_graph(FileGraph::restore(&files));
+        self.set_indexed_files(indexed);
+        info!(files = files.len(), "Loaded the graph from the store");
+        files.len()
+    }
+
+    /// Brings the store in step with the graph of every indexed file, keyed
+    /// by its content hash when it was indexed and the version of its main
+    /// language server.
+    ///
+    /// Only the entries whose key or graph changed are written, and only
+    /// the entries of files that are no longer indexed are removed. Does
+    /// nothing without a store. Failures are logged, as the store is only a
+    /// cache.
+    pub fn save_graph(&self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let mut stored = match store.entries() {
+            Ok(entries) => entries
+                .into_iter()
+                .map(|(key, file)| (file.uri.as_str().to_string(), (key, file)))
+                .collect::<HashMap<_, _>>(),
+            Err(error) => {
+                warn!(%error, "Failed to read the graph store");
+                return;
+            }
+        };
+
+        let graph = self.graph.read();
+        for (uri, hash) in self.indexed.read().iter() {
+            let entry = stored.remove(uri);
+            let Ok(uri) = uri.parse::<Uri>() else {
+                continue;
+            };
+            let key = CacheKey {
+                content_hash: *hash,
+                analyzer_version: ANALYZER_VERSION.to_string(),
+                server_version: self.server_version(&uri),
+            };
+            let file = FileGraph::extract(&graph, &uri);
+            if entry.is_some_and(|(stored_key, stored)| stored_key == key && stored == file) {
+                continue;
+            }
+            if let Err(error) = store.put(&key, &file) {
+                warn!(%error, "Failed to save to the graph store");
+                return;
+            }
+        }
+        for (_, file) in stored.into_values() {
+            if let Err(error) = store.remove(&file.uri) {
+                warn!(%error, "Failed to remove a graph store entry");
+            }
+        }
+    }
+
+    /// Returns the configuration of the indexer of the workspace, which
+    /// keeps its checkpoint next to the store, so that files that did not
+    /// change since the last index are not analyzed again and an
+    /// interrupted index resumes.
+    pub fn index_config(&self) -> IndexConfig {
+        let config = IndexConfig::default();
+        match &self.store {
+            Some(store) => config.with_checkpoint(store.dir().join(CHECKPOINT_FILE)),
+            None => config,
+        }
+    }
+
+    /// Indexes the dependencies the workspace locks in its `Cargo.lock`, or
+    /// loads them from the dependency cache, and adds their symbols to the
+    /// graph in the namespaces of their versions.
+    ///
+    /// # Returns
+    ///
+    /// The indexed dependencies; none if the workspace has no `Cargo.lock`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ContextEngineError::Parse`] if `Cargo.lock` is invalid.
+    pub async fn index_dependencies(&self) -> Result<DependencyOutcome, ContextEngineError> {
+        if !self.root.join("Cargo.lock").is_file() {
+            return Ok(DependencyOutcome::default());
+        }
+        let outcome = self.dependencies.index_lockfile().await?;
+        outcome.merge_into(&mut self.graph.write());
+        info!(
+            dependencies = outcome.dependencies.len(),
+            unresolved = outcome.unresolved.len(),
+            "Indexed dependencies"
+        );
+        Ok(outcome)
+    }
+
+    /// Returns the name and version of the main language server of a file,
+    /// as it reported them; empty if it is unknown.
+    fn server_version(&self, uri: &Uri) -> String {
+        let route = self.registry.route(uri, None);
+        route
+            .first()
+            .and_then(|route| self.supervisor.server_version(&route.server.name))
+            .unwrap_or_default()
+    }
+
+    /// Starts the language servers whose root markers, e.g. `Cargo.toml`,
+    /// are in the workspace root.
+    ///
+    /// # Returns
+    ///
+    /// The names of the started servers. Servers that fail to start are
+    /// logged and skipped.
+    pub fn start_servers(&self) -> Vec<String> {
+        let mut started = Vec::new();
+        for server in self.registry.servers() {
+            let applies = server
+                .root_markers
+                .iter()
+                .any(|marker| self.root.join(marker).exists());
+            if !applies {
+                continue;
+            }
+            match self.supervisor.start(server.clone()) {
+                Ok(()) => {
+                    info!(server = %server.name, "Started language server");
+                    started.push(server.name.clone());
+                }
+                Err(error) => {
+                    warn!(server = %server.name, %error, "Failed to start language server")
+                }
+            }
+        }
+        started
+    }
+
+    /// Starts watching the files of the workspace in the background, and
+    /// [applies](Engine::apply_changes) every burst of changes.
+    ///
+    /// A watcher that was already started is stopped. The watcher stops with
+    /// [`Engine::shutdown`] or when the engine is dropped.
+    pub fn watch(self: &Arc<Self>, config: WatchConfig) {
+        let engine = Arc::downgrade(self);
+        let root = self.root.clone();
+        let task = tokio::spawn(async move {
+            // The first scan reads every file of the workspace
+            let watcher = tokio::task::spawn_blocking(move || FileWatcher::new(root, config)).await;
+            let Ok(mut watcher) = watcher else {
+                return;
+            };
+            debug!(files = watcher.len(), "Watching workspace files");
+            loop {
+                let events = watcher.changes().await;
+                let Some(engine) = engine.upgrade() else {
+                    return;
+                };
+                engine.apply_changes(&events).await;
+            }
+        });
+        if let Some(previous) = self.watcher.lock().replace(task) {
+            previous.abort();
+        }
+    }
+
+    /// Applies changes of files on disk: the language servers are notified,
+    /// the symbols of the changed files are removed from the graph until
+    /// they are indexed again, and the store forgets them and the files of
+    /// their dependents.
+    ///
+    /// # Returns
+    ///
+    /// What the changes invalidated.
+    pub async fn apply_changes(&self, events: &[FileEvent]) -> Invalidation {
+        notify_servers(&self.supervisor, &self.registry, events).await;
+        let invalidation = invalidate(&mut self.graph.write(), events);
+        {
+            let mut indexed = self.indexed.write();
+            for uri in &invalidation.files {
+                indexed.remove(uri);
+            }
+        }
+        if let Some(store) = &self.store {
+            if let Err(error) = invalidation.evict(store) {
+                warn!(%error, "Failed to evict changed files from the graph store");
+            }
+        }
+        info!(
+            files = invalidation.files.len(),
+            removed = invalidation.removed.len(),
+            dependents = invalidation.dependents.len(),
+            "Applied workspace file changes"
+        );
+        invalidation
+    }
+
+    /// Returns a summary of the state of the workspace.
+    pub fn status(&self) -> WorkspaceStatus {
+        let graph = self.graph.read();
+        WorkspaceStatus {
+            root: self.root.display().to_string(),
+            servers: self
+                .supervisor
+                .statuses()
+                .into_iter()
+                .map(|(name, status)| ServerState { name, status })
+                .collect(),
+            symbols: graph.len(),
+            relationships: graph.edge_count(),
+            open_documents: self.documents.len(),
+        }
+    }
+
+    /// Stops the file watcher and all language servers.
+    pub async fn shutdown(&self) {
+        if let Some(watcher) = self.watcher.lock().take() {
+            watcher.abort();
+        }
+        self.supervisor.stop_all().await;
+    }
+}
+
+impl fmt::Debug for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Engine")
+            .field("root", &self.root)
+            .field("documents", &self.documents.len())
+            .field("symbols", &self.graph.read().len())
+            .finish()
+    }
+}
+
+/// A summary of the state of a workspace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceStatus {
+    /// The workspace root
+    pub root: String,
+    /// The language servers, ordered by name
+    pub servers: Vec<ServerState>,
+    /// The number of symbols in the knowledge graph
+    pub symbols: usize,
+    /// The number of relationships in the knowledge graph
+    pub relationships: usize,
+    /// The number of open documents
+    pub open_documents: usize,
+}
+
+/// The state of a language server of a workspace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerState {
+    /// The name of the server
+    pub name: String,
+    /// Its state
+    pub status: ServerStatus,
+}
+
+#[cfg(test)]
+#[path = "tests/engine.rs"]
+mod tests;
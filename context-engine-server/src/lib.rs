@@ -0,0 +1,17 @@
+//! Context Engine MCP Server
+//!
+//! A Model Context Protocol (MCP) server that provides intelligent code
+//! context for AI development tools. The [`Engine`] holds the analysis of a
+//! workspace, the [`McpServer`] exposes it through the [tools] and
+//! resources of MCP, and the [transports](transport) connect clients, e.g.
+//! an editor or desktop assistant that launches the server as a subprocess
+//! and talks to it over stdio, or several agents that share one long-lived
+//! server over HTTP.
+
+pub mod engine;
+pub mod mcp;
+pub mod tools;
+pub mod transport;
+
+pub use engine::Engine;
+pub use mcp::McpServer;
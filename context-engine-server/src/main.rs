@@ -1,20 +1,98 @@
-//! Context Engine HTTP Server
+//! Context Engine MCP Server
 //!
 //! A Model Context Protocol (MCP) server that provides intelligent
 //! code context for AI development tools.
+//!
+//! Usage: `context-engine-server [--http ADDRESS] [WORKSPACE]`
+//!
+//! The server analyzes the workspace, the current directory by default, and
+//! speaks MCP over stdin and stdout. With `--http`, it instead serves any
+//! number of sessions over streamable HTTP at `http://ADDRESS/mcp` until it
+//! is interrupted, so that several clients share one index. Logs go to
+//! stderr.
+//!
+//! The knowledge graph is saved in the cache directory of the user after
+//! indexing, and loaded again when the server starts on the same workspace.
+//! While the server runs, it watches the workspace and forwards changed
+//! files to the language servers.
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use tracing::info;
+use context_engine_core::cache::{GraphStore, content_hash};
+use context_engine_core::deps::default_cache_dir;
+use context_engine_core::watch::WatchConfig;
+use context_engine_server::transport::{HttpConfig, serve_http, serve_stdio};
+use context_engine_server::{Engine, McpServer, tools};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
 
-#[cfg_attr(test, mutants::skip)] // TODO: Remove this attribute when ready
+#[cfg_attr(test, mutants::skip)]
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize tracing
+    // Initialize tracing; stdout carries the protocol
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
         .init();
 
-    info!("Starting Context Engine server");
+    let mut http = None;
+    let mut root = None;
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--http" {
+            let address = args.next().ok_or("--http requires an address")?;
+            let address = address
+                .to_str()
+                .ok_or("invalid address")?
+                .parse::<SocketAddr>()?;
+            http = Some(address);
+        } else {
+            root = Some(PathBuf::from(arg));
+        }
+    }
+    let root = match root {
+        Some(root) => root,
+        None => std::env::current_dir()?,
+    };
+    let root = root.canonicalize()?;
+    info!(root = %root.display(), "Starting Context Engine server");
 
-    // TODO: Implement server initialization
+    let mut engine = Engine::new(&root);
+    match GraphStore::open(store_dir(&root)) {
+        Ok(store) => engine = engine.with_store(store),
+        Err(error) => warn!(%error, "Failed to open the graph store, starting without it"),
+    }
+    let engine = Arc::new(engine);
+    engine.start_servers();
+    engine.load_graph().await;
+    engine.watch(WatchConfig::default());
+    let server = McpServer::new(Arc::clone(&engine), tools::default_tools());
+    match http {
+        Some(address) => {
+            let listener = TcpListener::bind(address).await?;
+            let shutdown = async {
+                let _ = tokio::signal::ctrl_c().await;
+            };
+            serve_http(&server, listener, HttpConfig::default(), shutdown).await?;
+            info!("Interrupted, stopping language servers");
+        }
+        None => {
+            serve_stdio(&server, tokio::io::stdin(), tokio::io::stdout()).await?;
+            info!("Client disconnected, stopping language servers");
+        }
+    }
+
+    engine.shutdown().await;
     Ok(())
 }
+
+/// Returns the directory the graph of a workspace is saved in:
+/// `context-engine/graphs/<hash of the root>` next to the dependency cache.
+fn store_dir(root: &Path) -> PathBuf {
+    let hash = content_hash(root.as_os_str().as_encoded_bytes());
+    default_cache_dir()
+        .with_file_name("graphs")
+        .join(format!("{hash:016x}"))
+}
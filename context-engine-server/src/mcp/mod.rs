@@ -0,0 +1,53 @@
+//! The Model Context Protocol.
+//!
+//! This module implements the server side of MCP on top of the JSON-RPC
+//! messages of the core crate: the lifecycle of a [`Session`] from
+//! `initialize` to the handling of requests, the dispatch of `tools/call` to
+//! the [`ToolHandler`]s of a [`ToolRegistry`], the resources of the
+//! workspace, cancellation and progress notifications. Transports only move
+//! messages between a client and a session.
+//!
+//! ## Structs
+//!
+//! * [`McpServer`] - The engine of a workspace and the tools that expose it.
+//! * [`Session`] - A connection to an MCP client.
+//! * [`ToolRegistry`] - The tools of a server, by name.
+//! * [`ToolContext`] - What a tool call has access to.
+//! * [`Progress`] - Sends the progress notifications of a request.
+//! * [`Tool`], [`CallToolParams`], [`CallToolResult`], [`Resource`],
+//!   [`InitializeParams`], [`InitializeResult`] and the other message types of
+//!   the protocol.
+//!
+//! ## Enums
+//!
+//! * [`ToolError`] - Errors of tool calls.
+//! * [`Content`] - A piece of content of a tool result.
+//!
+//! ## Traits
+//!
+//! * [`ToolHandler`] - A tool of the server.
+//!
+//! ## Functions
+//!
+//! * [`negotiate_version`] - Chooses the protocol version of a session.
+//! * [`parse_arguments`] - Parses the arguments of a tool.
+
+mod protocol;
+mod resources;
+mod server;
+mod tool;
+
+pub use protocol::{
+    CallToolParams, CallToolResult, CancelledParams, Content, Implementation, InitializeParams,
+    InitializeResult, LATEST_PROTOCOL_VERSION, ListResourcesResult, ListToolsResult,
+    PROTOCOL_VERSIONS, ProgressParams, ReadResourceParams, ReadResourceResult, Resource,
+    ResourceContents, ResourcesCapability, ServerCapabilities, Tool, ToolsCapability, methods,
+    negotiate_version,
+};
+pub use resources::{RESOURCE_NOT_FOUND, STATUS_URI, list_resources, read_resource};
+pub use server::{
+    INVALID_REQUEST, METHOD_NOT_FOUND, McpServer, PARSE_ERROR, Session, error_response,
+};
+pub use tool::{
+    Progress, ToolContext, ToolError, ToolFuture, ToolHandler, ToolRegistry, parse_arguments,
+};
@@ -0,0 +1,287 @@
+//! Messages of the Model Context Protocol.
+//!
+//! MCP runs on JSON-RPC 2.0, so the envelopes are the
+//! [JSON-RPC messages](context_engine_core::lsp::Message) of the core crate;
+//! this module defines the parameters and results of the MCP methods the
+//! server implements.
+
+use context_engine_core::lsp::RequestId;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The protocol versions the server speaks, newest first.
+pub const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// The newest protocol version the server speaks.
+pub const LATEST_PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// The method names of MCP requests and notifications.
+pub mod methods {
+    /// Starts a session
+    pub const INITIALIZE: &str = "initialize";
+    /// Sent by the client once it received the `initialize` response
+    pub const INITIALIZED: &str = "notifications/initialized";
+    /// Checks that the other side is alive
+    pub const PING: &str = "ping";
+    /// Lists the tools of the server
+    pub const TOOLS_LIST: &str = "tools/list";
+    /// Calls a tool
+    pub const TOOLS_CALL: &str = "tools/call";
+    /// Lists the resources of the server
+    pub const RESOURCES_LIST: &str = "resources/list";
+    /// Reads a resource
+    pub const RESOURCES_READ: &str = "resources/read";
+    /// Cancels a request
+    pub const CANCELLED: &str = "notifications/cancelled";
+    /// Reports the progress of a request
+    pub const PROGRESS: &str = "notifications/progress";
+}
+
+/// Returns the protocol version to use with a client that requested
+/// `requested`: the requested version if the server speaks it, otherwise
+/// the newest version the server speaks.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_server::mcp::negotiate_version;
+///
+/// assert_eq!(negotiate_version("2025-03-26"), "2025-03-26");
+/// assert_eq!(negotiate_version("2023-01-01"), "2025-06-18");
+/// ```
+pub fn negotiate_version(requested: &str) -> &'static str {
+    PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| **version == requested)
+        .copied()
+        .unwrap_or(LATEST_PROTOCOL_VERSION)
+}
+
+/// The name and version of an MCP client or server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Implementation {
+    /// The name of the implementation
+    pub name: String,
+    /// The version of the implementation
+    pub version: String,
+}
+
+/// The parameters of an `initialize` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeParams {
+    /// The newest protocol version the client speaks
+    pub protocol_version: String,
+    /// The capabilities of the client
+    #[serde(default)]
+    pub capabilities: Value,
+    /// The client
+    pub client_info: Implementation,
+}
+
+/// The result of an `initialize` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeResult {
+    /// The protocol version of the session
+    pub protocol_version: String,
+    /// The capabilities of the server
+    pub capabilities: ServerCapabilities,
+    /// The server
+    pub server_info: Implementation,
+    /// How to use the server, for the model of the client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+}
+
+/// The capabilities of the server.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerCapabilities {
+    /// Present if the server offers tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<ToolsCapability>,
+    /// Present if the server offers resources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourcesCapability>,
+}
+
+/// The tool capabilities of the server.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolsCapability {
+    /// Whether the server notifies clients when its tools change
+    pub list_changed: bool,
+}
+
+/// The resource capabilities of the server.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourcesCapability {
+    /// Whether clients can subscribe to changes of resources
+    pub subscribe: bool,
+    /// Whether the server notifies clients when its resources change
+    pub list_changed: bool,
+}
+
+/// A tool the server offers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tool {
+    /// The name the tool is called by, e.g. `symbol.search`
+    pub name: String,
+    /// What the tool does, for the model of the client
+    pub description: String,
+    /// The JSON schema of the arguments of the tool
+    pub input_schema: Value,
+}
+
+/// The result of a `tools/list` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListToolsResult {
+    /// The tools
+    pub tools: Vec<Tool>,
+}
+
+/// The parameters of a `tools/call` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallToolParams {
+    /// The name of the tool
+    pub name: String,
+    /// The arguments of the tool
+    #[serde(default)]
+    pub arguments: Option<Value>,
+}
+
+/// A piece of content of a tool result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Content {
+    /// Text for the model
+    Text {
+        /// The text
+        text: String,
+    },
+}
+
+/// The result of a `tools/call` request.
+///
+/// Failures of the tool itself, as opposed to invalid requests, are results
+/// with `is_error` set, so that the model of the client sees them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolResult {
+    /// The content of the result
+    pub content: Vec<Content>,
+    /// The result as a JSON object, for clients that process it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+    /// Whether the tool failed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+}
+
+impl CallToolResult {
+    /// Creates a result from a JSON value, which is also rendered as text
+    /// for clients that do not process structured content.
+    pub fn json(value: Value) -> Self {
+        let text = serde_json::to_string_pretty(&value).unwrap_or_else(|_| value.to_string());
+        Self {
+            content: vec![Content::Text { text }],
+            structured_content: Some(value),
+            is_error: false,
+        }
+    }
+
+    /// Creates a failed result.
+    pub fn error(message: impl Into<String>, data: Option<Value>) -> Self {
+        Self {
+            content: vec![Content::Text {
+                text: message.into(),
+            }],
+            structured_content: data,
+            is_error: true,
+        }
+    }
+}
+
+/// A resource the server offers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    /// The URI of the resource
+    pub uri: String,
+    /// The name of the resource
+    pub name: String,
+    /// What the resource holds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The MIME type of the content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// The result of a `resources/list` request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListResourcesResult {
+    /// The resources
+    pub resources: Vec<Resource>,
+}
+
+/// The parameters of a `resources/read` request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadResourceParams {
+    /// The URI of the resource
+    pub uri: String,
+}
+
+/// The content of a resource.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    /// The URI of the resource
+    pub uri: String,
+    /// The MIME type of the content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// The content
+    pub text: String,
+}
+
+/// The result of a `resources/read` request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadResourceResult {
+    /// The contents of the resource
+    pub contents: Vec<ResourceContents>,
+}
+
+/// The parameters of a `notifications/progress` notification.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressParams {
+    /// The token the client attached to the request
+    pub progress_token: Value,
+    /// The progress so far; increases with every notification
+    pub progress: f64,
+    /// The total of the progress, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    /// What is being done
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// The parameters of a `notifications/cancelled` notification.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelledParams {
+    /// The request to cancel
+    pub request_id: RequestId,
+    /// Why the request is cancelled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[cfg(test)]
+#[path = "tests/protocol.rs"]
+mod tests;
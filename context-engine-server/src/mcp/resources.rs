@@ -0,0 +1,80 @@
+//! The resources of the server.
+
+use context_engine_core::JsonRpcError;
+use context_engine_core::types::Uri;
+
+use crate::Engine;
+use crate::mcp::{Resource, ResourceContents};
+
+/// The URI of the resource with the status of the workspace.
+pub const STATUS_URI: &str = "context-engine://workspace/status";
+
+/// The MCP error code for unknown resources.
+pub const RESOURCE_NOT_FOUND: i64 = -32002;
+
+/// Returns the resources of a workspace: its status and its open
+/// documents.
+pub fn list_resources(engine: &Engine) -> Vec<Resource> {
+    let mut resources = vec![Resource {
+        uri: STATUS_URI.to_string(),
+        name: "Workspace status".to_string(),
+        description: Some(
+            "The language servers, open documents and size of the knowledge graph".to_string(),
+        ),
+        mime_type: Some("application/json".to_string()),
+    }];
+    let mut documents = engine.documents().snapshots();
+    documents.sort_by(|a, b| a.uri().as_str().cmp(b.uri().as_str()));
+    resources.extend(documents.iter().map(|document| {
+        let uri = document.uri().as_str().to_string();
+        Resource {
+            name: uri.rsplit('/').next().unwrap_or(&uri).to_string(),
+            description: Some(format!(
+                "Open {} document, version {}",
+                document.language_id(),
+                document.version()
+            )),
+            mime_type: Some("text/plain".to_string()),
+            uri,
+        }
+    }));
+    resources
+}
+
+/// Reads a resource of a workspace.
+///
+/// # Errors
+///
+/// Returns a JSON-RPC error with code [`RESOURCE_NOT_FOUND`] if the
+/// resource does not exist.
+pub fn read_resource(engine: &Engine, uri: &str) -> Result<ResourceContents, JsonRpcError> {
+    if uri == STATUS_URI {
+        let status = serde_json::to_string_pretty(&engine.status())
+            .map_err(|error| JsonRpcError::new(RESOURCE_NOT_FOUND, error.to_string()))?;
+        return Ok(ResourceContents {
+            uri: uri.to_string(),
+            mime_type: Some("application/json".to_string()),
+            text: status,
+        });
+    }
+    let document = uri
+        .parse::<Uri>()
+        .ok()
+        .and_then(|parsed| engine.documents().get(&parsed));
+    match document {
+        Some(document) => Ok(ResourceContents {
+            uri: uri.to_string(),
+            mime_type: Some("text/plain".to_string()),
+            text: document.text().to_string(),
+        }),
+        None => Err(JsonRpcError {
+            code: RESOURCE_NOT_FOUND,
+            message: format!("Resource not found: {uri}"),
+            data: Some(serde_json::json!({ "uri": uri })),
+        }),
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/resources.rs"]
+mod tests;
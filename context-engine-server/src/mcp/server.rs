@@ -0,0 +1,372 @@
+//! The MCP lifecycle and the dispatch of requests.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use context_engine_core::lsp::{
+    Message, NotificationMessage, RequestId, RequestMessage, ResponseMessage,
+};
+use context_engine_core::{ErrorCode, JsonRpcError};
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
+
+use crate::Engine;
+use crate::mcp::{
+    CallToolParams, CancelledParams, Implementation, InitializeParams, InitializeResult,
+    ListResourcesResult, ListToolsResult, Progress, ReadResourceParams, ReadResourceResult,
+    ResourcesCapability, ServerCapabilities, ToolContext, ToolRegistry, ToolsCapability,
+    list_resources, methods, negotiate_version, read_resource,
+};
+
+/// JSON-RPC error code for messages that are not valid requests.
+pub const INVALID_REQUEST: i64 = -32600;
+
+/// JSON-RPC error code for requests whose method does not exist.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// JSON-RPC error code for messages that are not valid JSON.
+pub const PARSE_ERROR: i64 = -32700;
+
+/// What the server tells the model of the client about itself.
+const INSTRUCTIONS: &str =
+    "Context Engine knows the symbols of the workspace and of its dependencies and how they \
+     relate. Index the workspace with `workspace.index`, then look symbols up with \
+     `symbol.search` and get everything about one with `symbol.describe`. Before changing a \
+     symbol, see what the change affects with `symbol.impact`, and what the uncommitted changes \
+     or a branch affect with `workspace.diffImpact`. Check code before writing it with \
+     `document.analyzeContent`, and fix it with `codeAction.list` and `codeAction.apply`. Rename \
+     symbols with `symbol.rename`, which previews the rename until asked to apply it.";
+
+/// An MCP server: the engine of a workspace and the tools that expose it.
+///
+/// The server is independent of the transport. Every connection, e.g. the
+/// stdio of a client process, opens a [`Session`] that goes through the MCP
+/// lifecycle on its own, while all sessions share the engine.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::lsp::{RequestMessage, RequestId};
+/// use context_engine_server::{Engine, McpServer, tools};
+/// use serde_json::json;
+/// use std::sync::Arc;
+///
+/// # tokio_test::block_on(async {
+/// let server = McpServer::new(Arc::new(Engine::new("/path/to/workspace")), tools::default_tools());
+/// let session = server.session();
+/// let (messages, _) = tokio::sync::mpsc::unbounded_channel();
+///
+/// let initialize = RequestMessage::new(RequestId::Number(1), "initialize", Some(json!({
+///     "protocolVersion": "2025-06-18",
+///     "capabilities": {},
+///     "clientInfo": { "name": "example", "version": "1.0.0" },
+/// })));
+/// let response = session.handle_request(initialize, messages).await.unwrap();
+/// assert_eq!(response.into_result().unwrap()["protocolVersion"], "2025-06-18");
+/// # });
+/// ```
+#[derive(Debug, Clone)]
+pub struct McpServer {
+    engine: Arc<Engine>,
+    tools: Arc<ToolRegistry>,
+}
+
+impl McpServer {
+    /// Creates a server.
+    pub fn new(engine: Arc<Engine>, tools: ToolRegistry) -> Self {
+        Self {
+            engine,
+            tools: Arc::new(tools),
+        }
+    }
+
+    /// Returns the engine of the server.
+    pub fn engine(&self) -> &Arc<Engine> {
+        &self.engine
+    }
+
+    /// Returns the tools of the server.
+    pub fn tools(&self) -> &ToolRegistry {
+        &self.tools
+    }
+
+    /// Opens a session for a new connection.
+    pub fn session(&self) -> Session {
+        Session {
+            server: self.clone(),
+            protocol_version: Mutex::new(None),
+            initialized: AtomicBool::new(false),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// A connection to an MCP client.
+///
+/// A session answers `initialize` once, negotiating the protocol version,
+/// and rejects every request but `ping` before. Requests are handled
+/// concurrently; a `notifications/cancelled` notification drops the
+/// handling of the request it names, which then gets no response.
+pub struct Session {
+    server: McpServer,
+    protocol_version: Mutex<Option<String>>,
+    initialized: AtomicBool,
+    in_flight: Mutex<HashMap<RequestId, oneshot::Sender<()>>>,
+}
+
+impl Session {
+    /// Returns the negotiated protocol version, once `initialize` was
+    /// answered.
+    pub fn protocol_version(&self) -> Option<String> {
+        self.protocol_version.lock().clone()
+    }
+
+    /// Returns true once the client confirmed the initialization with
+    /// `notifications/initialized`.
+    pub fn is_initialized(&self) -> bool {
+        self.initialized.load(Ordering::SeqCst)
+    }
+
+    /// Handles a message of the client.
+    ///
+    /// # Returns
+    ///
+    /// The response to a request, unless the request was cancelled. Other
+    /// messages get no response.
+    pub async fn handle(
+        &self,
+        message: Message,
+        messages: mpsc::UnboundedSender<Message>,
+    ) -> Option<ResponseMessage> {
+        match message {
+            Message::Request(request) => self.handle_request(request, messages).await,
+            Message::Notification(notification) => {
+                self.handle_notification(notification);
+                None
+            }
+            Message::Response(response) => {
+                debug!(id = ?response.id, "Ignoring response of the client");
+                None
+            }
+        }
+    }
+
+    /// Handles a request of the client.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request
+    /// * `messages` - Where the notifications about the request, e.g. its
+    ///   progress, go; they are sent before the response is returned
+    ///
+    /// # Returns
+    ///
+    /// The response, or `None` if the request was cancelled. A request whose
+    /// id is still in use by another one is answered with an
+    /// [`INVALID_REQUEST`] error.
+    pub async fn handle_request(
+        &self,
+        request: RequestMessage,
+        messages: mpsc::UnboundedSender<Message>,
+    ) -> Option<ResponseMessage> {
+        let id = request.id.clone();
+        // `initialize` must not be cancelled
+        if request.method == methods::INITIALIZE {
+            return Some(respond(id, self.initialize(request.params)));
+        }
+
+        let (cancel, cancelled) = oneshot::channel();
+        match self.in_flight.lock().entry(id.clone()) {
+            // Replacing the entry would cancel the earlier request
+            Entry::Occupied(_) => {
+                return Some(ResponseMessage::err(
+                    id,
+                    JsonRpcError::new(INVALID_REQUEST, "A request with this id is in flight"),
+                ));
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(cancel);
+            }
+        }
+        let result = tokio::select! {
+            result = self.dispatch(request, messages) => Some(result),
+            _ = cancelled => None,
+        };
+        self.in_flight.lock().remove(&id);
+        match result {
+            Some(result) => Some(respond(id, result)),
+            None => {
+                debug!(?id, "Request cancelled");
+                None
+            }
+        }
+    }
+
+    /// Handles a notification of the client.
+    pub fn handle_notification(&self, notification: NotificationMessage) {
+        match notification.method.as_str() {
+            methods::INITIALIZED => self.initialized.store(true, Ordering::SeqCst),
+            methods::CANCELLED => {
+                let params = notification
+                    .params
+                    .and_then(|params| serde_json::from_value::<CancelledParams>(params).ok());
+                let Some(params) = params else {
+                    warn!("Ignoring invalid cancellation");
+                    return;
+                };
+                // Unknown requests are already answered
+                if let Some(cancel) = self.in_flight.lock().remove(&params.request_id) {
+                    let _ = cancel.send(());
+                }
+            }
+            method => debug!(method, "Ignoring notification"),
+        }
+    }
+
+    fn initialize(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let params = parse_params::<InitializeParams>(params)?;
+        let mut version = self.protocol_version.lock();
+        if version.is_some() {
+            return Err(JsonRpcError::new(
+                INVALID_REQUEST,
+                "The session is already initialized",
+            ));
+        }
+        let negotiated = negotiate_version(&params.protocol_version);
+        debug!(
+            client = %params.client_info.name,
+            requested = %params.protocol_version,
+            negotiated,
+            "Initializing session"
+        );
+        *version = Some(negotiated.to_string());
+        to_value(InitializeResult {
+            protocol_version: negotiated.to_string(),
+            capabilities: ServerCapabilities {
+                tools: Some(ToolsCapability {
+                    list_changed: false,
+                }),
+                resources: Some(ResourcesCapability {
+                    subscribe: false,
+                    list_changed: false,
+                }),
+            },
+            server_info: Implementation {
+                name: env!("CARGO_PKG_NAME").to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            instructions: Some(INSTRUCTIONS.to_string()),
+        })
+    }
+
+    async fn dispatch(
+        &self,
+        request: RequestMessage,
+        messages: mpsc::UnboundedSender<Message>,
+    ) -> Result<Value, JsonRpcError> {
+        if request.method == methods::PING {
+            return Ok(json!({}));
+        }
+        if self.protocol_version.lock().is_none() {
+            return Err(JsonRpcError::new(
+                INVALID_REQUEST,
+                format!("Received {} before initialize", request.method),
+            ));
+        }
+
+        let engine = &self.server.engine;
+        match request.method.as_str() {
+            methods::TOOLS_LIST => to_value(ListToolsResult {
+                tools: self.server.tools.definitions(),
+            }),
+            methods::TOOLS_CALL => {
+                let token = request
+                    .params
+                    .as_ref()
+                    .and_then(|params| params.pointer("/_meta/progressToken"))
+                    .cloned();
+                let params = parse_params::<CallToolParams>(request.params)?;
+                let Some(tool) = self.server.tools.get(&params.name) else {
+                    return Err(JsonRpcError::new(
+                        ErrorCode::InvalidParams.as_i64(),
+                        format!("Unknown tool: {}", params.name),
+                    ));
+                };
+                let context = ToolContext {
+                    engine: Arc::clone(engine),
+                    progress: Progress::new(token, messages),
+                };
+                let arguments = params.arguments.unwrap_or_else(|| json!({}));
+                let result = match tool.call(context, arguments).await {
+                    Ok(result) => result,
+                    Err(error) => error.into_response()?,
+                };
+                to_value(result)
+            }
+            methods::RESOURCES_LIST => to_value(ListResourcesResult {
+                resources: list_resources(engine),
+            }),
+            methods::RESOURCES_READ => {
+                let params = parse_params::<ReadResourceParams>(request.params)?;
+                to_value(ReadResourceResult {
+                    contents: vec![read_resource(engine, &params.uri)?],
+                })
+            }
+            method => Err(JsonRpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Method not found: {method}"),
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("protocol_version", &self.protocol_version())
+            .field("initialized", &self.is_initialized())
+            .field("in_flight", &self.in_flight.lock().len())
+            .finish()
+    }
+}
+
+/// Returns the response to a request that is not a valid message, e.g.
+/// because it is not JSON.
+pub fn error_response(code: i64, message: impl Into<String>) -> ResponseMessage {
+    ResponseMessage {
+        id: None,
+        ..ResponseMessage::err(RequestId::Number(0), JsonRpcError::new(code, message))
+    }
+}
+
+fn respond(id: RequestId, result: Result<Value, JsonRpcError>) -> ResponseMessage {
+    match result {
+        Ok(result) => ResponseMessage::ok(id, result),
+        Err(error) => ResponseMessage::err(id, error),
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Option<Value>) -> Result<T, JsonRpcError> {
+    serde_json::from_value(params.unwrap_or(Value::Null)).map_err(|error| {
+        JsonRpcError::new(
+            ErrorCode::InvalidParams.as_i64(),
+            format!("Invalid params: {error}"),
+        )
+    })
+}
+
+fn to_value(result: impl Serialize) -> Result<Value, JsonRpcError> {
+    serde_json::to_value(result)
+        .map_err(|error| JsonRpcError::new(ErrorCode::Parse.as_i64(), error.to_string()))
+}
+
+#[cfg(test)]
+#[path = "tests/server.rs"]
+mod tests;
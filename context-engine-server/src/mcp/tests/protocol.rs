@@ -0,0 +1,110 @@
+#![allow(clippy::unwrap_used)]
+
+use pretty_assertions::assert_eq;
+use proptest::prelude::*;
+use serde_json::json;
+
+use super::*;
+
+#[test]
+fn test_negotiate_supported_versions() {
+    for version in PROTOCOL_VERSIONS {
+        assert_eq!(negotiate_version(version), *version);
+    }
+    assert_eq!(negotiate_version("2099-01-01"), LATEST_PROTOCOL_VERSION);
+}
+
+#[test]
+fn test_initialize_params_from_client() {
+    let params: InitializeParams = serde_json::from_value(json!({
+        "protocolVersion": "2025-03-26",
+        "capabilities": { "roots": { "listChanged": true } },
+        "clientInfo": { "name": "desktop", "version": "0.9.0" },
+    }))
+    .unwrap();
+    assert_eq!(params.protocol_version, "2025-03-26");
+    assert_eq!(params.client_info.name, "desktop");
+    assert_eq!(
+        params.capabilities.pointer("/roots/listChanged").unwrap(),
+        true
+    );
+}
+
+#[test]
+fn test_initialize_result_shape() {
+    let result = InitializeResult {
+        protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+        capabilities: ServerCapabilities {
+            tools: Some(ToolsCapability::default()),
+            resources: None,
+        },
+        server_info: Implementation {
+            name: "context-engine-server".to_string(),
+            version: "0.1.0".to_string(),
+        },
+        instructions: None,
+    };
+    assert_eq!(
+        serde_json::to_value(result).unwrap(),
+        json!({
+            "protocolVersion": "2025-06-18",
+            "capabilities": { "tools": { "listChanged": false } },
+            "serverInfo": { "name": "context-engine-server", "version": "0.1.0" },
+        })
+    );
+}
+
+#[test]
+fn test_call_tool_result_shape() {
+    let result = CallToolResult::json(json!({ "symbols": 3 }));
+    assert_eq!(
+        serde_json::to_value(&result).unwrap(),
+        json!({
+            "content": [{ "type": "text", "text": "{\n  \"symbols\": 3\n}" }],
+            "structuredContent": { "symbols": 3 },
+        })
+    );
+
+    let error = CallToolResult::error("Server crashed", None);
+    assert_eq!(
+        serde_json::to_value(&error).unwrap(),
+        json!({
+            "content": [{ "type": "text", "text": "Server crashed" }],
+            "isError": true,
+        })
+    );
+}
+
+#[test]
+fn test_cancelled_params_accept_both_id_kinds() {
+    let params: CancelledParams =
+        serde_json::from_value(json!({ "requestId": 7, "reason": "user" })).unwrap();
+    assert_eq!(params.request_id, RequestId::Number(7));
+    let params: CancelledParams = serde_json::from_value(json!({ "requestId": "call-7" })).unwrap();
+    assert_eq!(params.request_id, RequestId::String("call-7".to_string()));
+    assert_eq!(params.reason, None);
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_negotiated_version_is_supported(requested in "[0-9]{4}-[0-9]{2}-[0-9]{2}") {
+        let negotiated = negotiate_version(&requested);
+        prop_assert!(PROTOCOL_VERSIONS.contains(&negotiated));
+        if PROTOCOL_VERSIONS.contains(&requested.as_str()) {
+            prop_assert_eq!(negotiated, requested.as_str());
+        }
+    }
+
+    #[test]
+    fn prop_progress_params_round_trip(progress in 0.0f64..1e6, token in "[a-z0-9]{1,12}") {
+        let params = ProgressParams {
+            progress_token: json!(token),
+            progress,
+            total: None,
+            message: None,
+        };
+        let value = serde_json::to_value(&params).unwrap();
+        prop_assert_eq!(serde_json::from_value::<ProgressParams>(value).unwrap(), params);
+    }
+}
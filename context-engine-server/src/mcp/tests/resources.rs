@@ -0,0 +1,64 @@
+#![allow(clippy::unwrap_used)]
+
+use lsp_types::TextDocumentItem;
+use proptest::prelude::*;
+
+use super::*;
+
+fn engine_with_document() -> Engine {
+    let engine = Engine::new("/workspace");
+    let uri = "file:///workspace/src/lib.rs".parse::<Uri>().unwrap();
+    engine
+        .documents()
+        .open(TextDocumentItem::new(
+            uri,
+            "rust".to_string(),
+            3,
+            "pub fn greet() {}\n".to_string(),
+        ))
+        .unwrap();
+    engine
+}
+
+#[test]
+fn test_list_status_and_documents() {
+    let engine = engine_with_document();
+    let resources = list_resources(&engine);
+    let uris = resources
+        .iter()
+        .map(|resource| resource.uri.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(uris, [STATUS_URI, "file:///workspace/src/lib.rs"]);
+    let document = resources.get(1).unwrap();
+    assert_eq!(document.name, "lib.rs");
+    assert_eq!(
+        document.description.as_deref(),
+        Some("Open rust document, version 3")
+    );
+}
+
+#[test]
+fn test_read_resources() {
+    let engine = engine_with_document();
+    let status = read_resource(&engine, STATUS_URI).unwrap();
+    let status: serde_json::Value = serde_json::from_str(&status.text).unwrap();
+    assert_eq!(status.pointer("/openDocuments").unwrap(), 1);
+
+    let document = read_resource(&engine, "file:///workspace/src/lib.rs").unwrap();
+    assert_eq!(document.text, "pub fn greet() {}\n");
+
+    let error = read_resource(&engine, "file:///workspace/src/main.rs").unwrap_err();
+    assert_eq!(error.code, RESOURCE_NOT_FOUND);
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_unknown_resources_are_not_found(name in "[a-z]{1,10}") {
+        let engine = Engine::new("/workspace");
+        let uri = format!("file:///elsewhere/{name}.rs");
+        let error = read_resource(&engine, &uri).unwrap_err();
+        prop_assert_eq!(error.code, RESOURCE_NOT_FOUND);
+        prop_assert_eq!(list_resources(&engine).len(), 1);
+    }
+}
@@ -0,0 +1,311 @@
+#![allow(clippy::unwrap_used)]
+
+use std::time::Duration;
+
+use proptest::prelude::*;
+
+use super::*;
+use crate::mcp::{CallToolResult, Tool, ToolFuture, ToolHandler};
+use crate::tools::default_tools;
+
+/// Waits until it is cancelled.
+struct Hang;
+
+impl ToolHandler for Hang {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "hang".to_string(),
+            description: "Never completes".to_string(),
+            input_schema: json!({ "type": "object" }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, _arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            context
+                .progress
+                .report(0.0, None, Some("hanging".to_string()));
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+            Ok(CallToolResult::json(json!({})))
+        })
+    }
+}
+
+fn server() -> McpServer {
+    let mut tools = default_tools();
+    tools.register(Hang);
+    McpServer::new(Arc::new(Engine::new("/workspace")), tools)
+}
+
+fn request(id: i32, method: &str, params: Value) -> RequestMessage {
+    RequestMessage::new(RequestId::Number(id), method, Some(params))
+}
+
+fn initialize(version: &str) -> RequestMessage {
+    request(
+        0,
+        "initialize",
+        json!({
+            "protocolVersion": version,
+            "capabilities": {},
+            "clientInfo": { "name": "test", "version": "1.0.0" },
+        }),
+    )
+}
+
+async fn call(session: &Session, request: RequestMessage) -> Result<Value, JsonRpcError> {
+    let (messages, _) = mpsc::unbounded_channel();
+    session
+        .handle_request(request, messages)
+        .await
+        .unwrap()
+        .into_result()
+}
+
+async fn initialized_session(server: &McpServer) -> Session {
+    let session = server.session();
+    call(&session, initialize("2025-06-18")).await.unwrap();
+    session.handle_notification(NotificationMessage::new("notifications/initialized", None));
+    session
+}
+
+#[tokio::test]
+async fn test_lifecycle() {
+    let server = server();
+    let session = server.session();
+
+    // Only pings are answered before initialize
+    assert_eq!(
+        call(&session, request(1, "ping", json!({}))).await,
+        Ok(json!({}))
+    );
+    let error = call(&session, request(2, "tools/list", json!({})))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code, INVALID_REQUEST);
+
+    let result = call(&session, initialize("2024-11-05")).await.unwrap();
+    assert_eq!(result.pointer("/protocolVersion").unwrap(), "2024-11-05");
+    assert_eq!(
+        result.pointer("/serverInfo/name").unwrap(),
+        "context-engine-server"
+    );
+    assert_eq!(
+        result.pointer("/capabilities/tools/listChanged").unwrap(),
+        false
+    );
+    assert_eq!(session.protocol_version().as_deref(), Some("2024-11-05"));
+    assert!(!session.is_initialized());
+
+    session.handle_notification(NotificationMessage::new("notifications/initialized", None));
+    assert!(session.is_initialized());
+
+    let error = call(&session, initialize("2025-06-18")).await.unwrap_err();
+    assert_eq!(error.code, INVALID_REQUEST);
+}
+
+#[tokio::test]
+async fn test_unsupported_version_gets_latest() {
+    let server = server();
+    let session = server.session();
+    let result = call(&session, initialize("2020-01-01")).await.unwrap();
+    assert_eq!(result.pointer("/protocolVersion").unwrap(), "2025-06-18");
+}
+
+#[tokio::test]
+async fn test_invalid_initialize_params() {
+    let server = server();
+    let session = server.session();
+    let error = call(&session, request(0, "initialize", json!({})))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code, -32602);
+    assert_eq!(session.protocol_version(), None);
+}
+
+#[tokio::test]
+async fn test_tools() {
+    let server = server();
+    let session = initialized_session(&server).await;
+
+    let result = call(&session, request(1, "tools/list", json!({})))
+        .await
+        .unwrap();
+    let names = result
+        .pointer("/tools")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tool| tool.pointer("/name").unwrap().as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "codeAction.apply",
+            "codeAction.list",
+            "codeAction.resolve",
+            "document.analyzeContent",
+            "hang",
+            "symbol.describe",
+            "symbol.impact",
+            "symbol.rename",
+            "symbol.search",
+            "workspace.diffImpact",
+            "workspace.index",
+            "workspace.status"
+        ]
+    );
+
+    let result = call(
+        &session,
+        request(2, "tools/call", json!({ "name": "workspace.status" })),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        result.pointer("/structuredContent/root").unwrap(),
+        "/workspace"
+    );
+
+    let error = call(
+        &session,
+        request(
+            3,
+            "tools/call",
+            json!({ "name": "missing", "arguments": {} }),
+        ),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error.code, -32602);
+    assert_eq!(error.message, "Unknown tool: missing");
+
+    let error = call(
+        &session,
+        request(
+            4,
+            "tools/call",
+            json!({ "name": "symbol.search", "arguments": { "limit": "many" } }),
+        ),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error.code, -32602);
+}
+
+#[tokio::test]
+async fn test_resources() {
+    let server = server();
+    let session = initialized_session(&server).await;
+    let result = call(&session, request(1, "resources/list", json!({})))
+        .await
+        .unwrap();
+    assert_eq!(
+        result.pointer("/resources/0/uri").unwrap(),
+        "context-engine://workspace/status"
+    );
+
+    let result = call(
+        &session,
+        request(
+            2,
+            "resources/read",
+            json!({ "uri": "context-engine://workspace/status" }),
+        ),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        result.pointer("/contents/0/mimeType").unwrap(),
+        "application/json"
+    );
+
+    let error = call(
+        &session,
+        request(3, "resources/read", json!({ "uri": "file:///missing.rs" })),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error.code, -32002);
+}
+
+#[tokio::test]
+async fn test_unknown_method() {
+    let server = server();
+    let session = initialized_session(&server).await;
+    let error = call(&session, request(1, "prompts/list", json!({})))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code, METHOD_NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_cancelled_request_gets_no_response() {
+    let server = server();
+    let session = Arc::new(initialized_session(&server).await);
+    let (messages, mut sent) = mpsc::unbounded_channel();
+
+    let hanging = {
+        let session = Arc::clone(&session);
+        tokio::spawn(async move {
+            let request = request(
+                7,
+                "tools/call",
+                json!({ "name": "hang", "_meta": { "progressToken": "hang-7" } }),
+            );
+            session.handle_request(request, messages).await
+        })
+    };
+    // The tool reports progress once it runs
+    let progress = serde_json::to_value(sent.recv().await.unwrap()).unwrap();
+    assert_eq!(progress.pointer("/params/progressToken").unwrap(), "hang-7");
+
+    // A request reusing the id of one in flight is rejected and leaves the
+    // earlier one running
+    let error = call(&session, request(7, "tools/list", json!({})))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code, INVALID_REQUEST);
+    assert!(!hanging.is_finished());
+
+    session.handle_notification(NotificationMessage::new(
+        "notifications/cancelled",
+        Some(json!({ "requestId": 7, "reason": "user" })),
+    ));
+    assert_eq!(hanging.await.unwrap(), None);
+    // Cancelling an answered request does nothing
+    session.handle_notification(NotificationMessage::new(
+        "notifications/cancelled",
+        Some(json!({ "requestId": 7 })),
+    ));
+}
+
+#[test]
+fn test_error_response_has_no_id() {
+    let response = error_response(PARSE_ERROR, "Parse error");
+    assert_eq!(
+        serde_json::to_value(response).unwrap(),
+        json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": { "code": -32700, "message": "Parse error" },
+        })
+    );
+}
+
+// Property-based tests
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn prop_requests_before_initialize_are_rejected(method in "[a-z]{1,8}/[a-z]{1,8}") {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let error = runtime.block_on(async {
+            let server = server();
+            let session = server.session();
+            call(&session, request(1, &method, json!({}))).await.unwrap_err()
+        });
+        prop_assert_eq!(error.code, INVALID_REQUEST);
+    }
+}
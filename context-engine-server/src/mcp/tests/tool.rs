@@ -0,0 +1,121 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+use serde_json::json;
+
+use super::*;
+
+/// Echoes its arguments.
+struct Echo;
+
+impl ToolHandler for Echo {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "echo".to_string(),
+            description: "Echoes its arguments".to_string(),
+            input_schema: json!({ "type": "object" }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            context.progress.report(1.0, Some(1.0), None);
+            Ok(CallToolResult::json(arguments))
+        })
+    }
+}
+
+fn context(token: Option<Value>) -> (ToolContext, mpsc::UnboundedReceiver<Message>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let context = ToolContext {
+        engine: Arc::new(Engine::new("/workspace")),
+        progress: Progress::new(token, sender),
+    };
+    (context, receiver)
+}
+
+#[tokio::test]
+async fn test_registry_calls_tools_by_name() {
+    let mut tools = ToolRegistry::new();
+    tools.register(Echo);
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools.definitions().first().unwrap().name, "echo");
+    assert!(tools.get("missing").is_none());
+
+    let (context, _) = context(None);
+    let result = tools
+        .get("echo")
+        .unwrap()
+        .call(context, json!({ "a": 1 }))
+        .await
+        .unwrap();
+    assert_eq!(result.structured_content, Some(json!({ "a": 1 })));
+}
+
+#[tokio::test]
+async fn test_progress_requires_token() {
+    let (context, mut notifications) = self::context(None);
+    assert!(!context.progress.is_enabled());
+    Echo.call(context, json!({})).await.unwrap();
+    assert!(notifications.try_recv().is_err());
+
+    let (context, mut notifications) = self::context(Some(json!("token-1")));
+    Echo.call(context, json!({})).await.unwrap();
+    let notification = notifications.try_recv().unwrap();
+    assert_eq!(notification.method(), Some("notifications/progress"));
+    let Message::Notification(notification) = notification else {
+        unreachable!("progress is reported with notifications");
+    };
+    assert_eq!(
+        notification.params.unwrap(),
+        json!({ "progressToken": "token-1", "progress": 1.0, "total": 1.0 })
+    );
+}
+
+#[test]
+fn test_error_responses() {
+    let invalid = ToolError::InvalidArguments("missing field `name`".to_string());
+    let error = invalid.into_response().unwrap_err();
+    assert_eq!(error.code, -32602);
+
+    let failed = ToolError::Engine(ContextEngineError::Cancelled {
+        method: "textDocument/references".to_string(),
+    });
+    let result = failed.into_response().unwrap();
+    assert!(result.is_error);
+    assert_eq!(
+        result.structured_content.unwrap().pointer("/kind").unwrap(),
+        "cancelled"
+    );
+}
+
+#[test]
+fn test_parse_arguments() {
+    #[derive(Debug, serde::Deserialize, PartialEq)]
+    struct Arguments {
+        name: String,
+    }
+    assert_eq!(
+        parse_arguments::<Arguments>(json!({ "name": "User" })).unwrap(),
+        Arguments {
+            name: "User".to_string()
+        }
+    );
+    assert!(matches!(
+        parse_arguments::<Arguments>(json!({})),
+        Err(ToolError::InvalidArguments(_))
+    ));
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_progress_notifications_carry_token(token in 0i64..1000, progress in 0u32..100) {
+        let (context, mut notifications) = context(Some(json!(token)));
+        context.progress.report(f64::from(progress), None, Some("indexing".to_string()));
+        let params = serde_json::to_value(notifications.try_recv().unwrap()).unwrap();
+        let params = params.get("params").unwrap();
+        prop_assert_eq!(params.pointer("/progressToken").unwrap(), &json!(token));
+        prop_assert_eq!(params.pointer("/message").unwrap(), &json!("indexing"));
+    }
+}
@@ -0,0 +1,197 @@
+//! The tools of the server and what they are called with.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use context_engine_core::lsp::{Message, NotificationMessage};
+use context_engine_core::{ContextEngineError, ErrorCode, JsonRpcError};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+use crate::Engine;
+use crate::mcp::{CallToolResult, ProgressParams, Tool, methods};
+
+/// The future of a tool call.
+pub type ToolFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<CallToolResult, ToolError>> + Send + 'a>>;
+
+/// Errors of tool calls.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ToolError {
+    /// The arguments do not match the input schema of the tool; reported to
+    /// the client as a JSON-RPC `InvalidParams` error
+    #[error("Invalid arguments: {0}")]
+    InvalidArguments(String),
+
+    /// The tool failed; reported to the model as a tool result with
+    /// `isError` set
+    #[error(transparent)]
+    Engine(#[from] ContextEngineError),
+}
+
+impl ToolError {
+    /// Converts the error into the response to a `tools/call` request.
+    pub fn into_response(self) -> Result<CallToolResult, JsonRpcError> {
+        match self {
+            ToolError::InvalidArguments(message) => Err(JsonRpcError::new(
+                ErrorCode::InvalidParams.as_i64(),
+                format!("Invalid arguments: {message}"),
+            )),
+            ToolError::Engine(error) => Ok(CallToolResult::error(
+                error.to_string(),
+                serde_json::to_value(&error).ok(),
+            )),
+        }
+    }
+}
+
+/// A tool of the server.
+///
+/// Tools are shared by all transports and sessions. Calls run concurrently
+/// and are dropped when the client cancels them.
+pub trait ToolHandler: Send + Sync {
+    /// Returns the name, description and input schema of the tool.
+    fn definition(&self) -> Tool;
+
+    /// Calls the tool.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The engine and the progress reporter of the call
+    /// * `arguments` - The arguments, an empty object if the client sent none
+    ///
+    /// # Errors
+    ///
+    /// * [`ToolError::InvalidArguments`] - If the arguments are invalid
+    /// * [`ToolError::Engine`] - If the tool failed
+    fn call<'a>(&'a self, context: ToolContext, arguments: Value) -> ToolFuture<'a>;
+}
+
+/// Parses the arguments of a tool.
+///
+/// # Errors
+///
+/// Returns [`ToolError::InvalidArguments`] if the arguments do not
+/// deserialize into `T`.
+pub fn parse_arguments<T: DeserializeOwned>(arguments: Value) -> Result<T, ToolError> {
+    serde_json::from_value(arguments)
+        .map_err(|error| ToolError::InvalidArguments(error.to_string()))
+}
+
+/// What a tool call has access to.
+#[derive(Clone)]
+pub struct ToolContext {
+    /// The workspace
+    pub engine: Arc<Engine>,
+    /// Reports the progress of the call
+    pub progress: Progress,
+}
+
+impl fmt::Debug for ToolContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ToolContext")
+            .field("root", &self.engine.root())
+            .field("progress", &self.progress)
+            .finish()
+    }
+}
+
+/// Sends `notifications/progress` for a request that carries a progress
+/// token; does nothing for other requests.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    token: Option<Value>,
+    messages: mpsc::UnboundedSender<Message>,
+}
+
+impl Progress {
+    /// Creates a progress reporter.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The `_meta.progressToken` of the request, if any
+    /// * `messages` - Where the messages to the client go
+    pub fn new(token: Option<Value>, messages: mpsc::UnboundedSender<Message>) -> Self {
+        Self { token, messages }
+    }
+
+    /// Returns true if the client asked for progress notifications.
+    pub fn is_enabled(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// Reports progress.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress` - The progress so far, which must increase with each report
+    /// * `total` - The total, if known
+    /// * `message` - What is being done
+    pub fn report(&self, progress: f64, total: Option<f64>, message: Option<String>) {
+        let Some(token) = &self.token else {
+            return;
+        };
+        let params = ProgressParams {
+            progress_token: token.clone(),
+            progress,
+            total,
+            message,
+        };
+        let notification =
+            NotificationMessage::new(methods::PROGRESS, serde_json::to_value(params).ok());
+        // The session is gone if the receiver is closed
+        let _ = self.messages.send(notification.into());
+    }
+}
+
+/// The tools of a server, by name.
+#[derive(Default, Clone)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, Arc<dyn ToolHandler>>,
+}
+
+impl ToolRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a tool, replacing any tool with the same name.
+    pub fn register(&mut self, tool: impl ToolHandler + 'static) {
+        self.tools.insert(tool.definition().name, Arc::new(tool));
+    }
+
+    /// Returns a tool by name.
+    pub fn get(&self, name: &str) -> Option<Arc<dyn ToolHandler>> {
+        self.tools.get(name).cloned()
+    }
+
+    /// Returns the definitions of the tools, ordered by name.
+    pub fn definitions(&self) -> Vec<Tool> {
+        self.tools.values().map(|tool| tool.definition()).collect()
+    }
+
+    /// Returns the number of tools.
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+
+    /// Returns true if there are no tools.
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+}
+
+impl fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.tools.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/tool.rs"]
+mod tests;
@@ -0,0 +1,306 @@
+#![allow(clippy::unwrap_used)]
+
+use std::time::Duration;
+
+use context_engine_core::graph::{NodeKind, SymbolNode};
+use context_engine_core::types::{Position, Range};
+use proptest::prelude::*;
+
+use super::*;
+
+fn node(name: &str) -> SymbolNode {
+    let uri = "file:///workspace/src/lib.rs".parse::<Uri>().unwrap();
+    let range = Range::new(Position::new(0, 0), Position::new(0, 1));
+    SymbolNode::new(NodeKind::Function, name, format!("app::{name}"), uri, range)
+}
+
+#[test]
+fn test_status_counts_graph() {
+    let engine = Engine::new("/workspace");
+    let mut graph = KnowledgeGraph::new();
+    graph.add_node(node("greet"));
+    graph.add_node(node("run"));
+    engine.set_graph(graph);
+
+    let status = engine.status();
+    assert_eq!(status.root, "/workspace");
+    assert_eq!(status.symbols, 2);
+    assert_eq!(status.relationships, 0);
+    assert_eq!(engine.graph().lookup("app::run").len(), 1);
+}
+
+#[tokio::test]
+async fn test_graph_is_saved_and_loaded() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("app");
+    std::fs::create_dir(&root).unwrap();
+    let files = [("lib.rs", "greet"), ("main.rs", "run")];
+    let mut graph = KnowledgeGraph::new();
+    let mut indexed = BTreeMap::new();
+    for (file, name) in files {
+        let path = root.join(file);
+        std::fs::write(&path, format!("fn {name}() {{}}")).unwrap();
+        let uri = Uri::from_file_path(&path).unwrap();
+        let range = Range::new(Position::new(0, 0), Position::new(0, 1));
+        graph.add_node(SymbolNode::new(
+            NodeKind::Function,
+            name,
+            format!("app::{name}"),
+            uri.clone(),
+            range,
+        ));
+        let hash = content_hash(&std::fs::read(&path).unwrap());
+        indexed.insert(uri.as_str().to_string(), hash);
+    }
+    let store = dir.path().join("store");
+    let engine = Engine::new(&root).with_store(GraphStore::open(&store).unwrap());
+    engine.set_graph(graph);
+    engine.set_indexed_files(indexed);
+    engine.save_graph();
+    assert_eq!(engine.store().unwrap().len(), 2);
+
+    // An entry of another version of the language server is dropped
+    let path = root.join("util.rs");
+    std::fs::write(&path, "fn util() {}").unwrap();
+    let uri = Uri::from_file_path(&path).unwrap();
+    let key = CacheKey::new(b"fn util() {}", "rust-analyzer 1.0.0");
+    let mut util = KnowledgeGraph::new();
+    util.add_node(SymbolNode::new(
+        NodeKind::Function,
+        "util",
+        "app::util",
+        uri.clone(),
+        Range::new(Position::new(0, 0), Position::new(0, 1)),
+    ));
+    engine
+        .store()
+        .unwrap()
+        .put(&key, &FileGraph::extract(&util, &uri))
+        .unwrap();
+
+    // Only the unchanged file is loaded, and the other entries are dropped
+    std::fs::write(root.join("main.rs"), "fn run() { greet(); }").unwrap();
+    let engine = Engine::new(&root).with_store(GraphStore::open(&store).unwrap());
+    assert_eq!(engine.load_graph().await, 1);
+    assert_eq!(engine.graph().lookup("app::greet").len(), 1);
+    assert!(engine.graph().lookup("app::run").is_empty());
+    assert!(engine.graph().lookup("app::util").is_empty());
+    assert_eq!(engine.indexed_files().len(), 1);
+    assert_eq!(engine.store().unwrap().len(), 1);
+
+    // Without a store there is nothing to load or save
+    let engine = Engine::new(&root);
+    assert_eq!(engine.load_graph().await, 0);
+    engine.save_graph();
+    assert!(engine.graph().is_empty());
+}
+
+/// Returns the paths of the entries of a store that were written since
+/// [`age_entries`].
+fn written_entries(store: &Path) -> Vec<PathBuf> {
+    let mut written = std::fs::read_dir(store)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "bin"))
+        .filter(|path| {
+            std::fs::metadata(path).unwrap().modified().unwrap() != std::time::UNIX_EPOCH
+        })
+        .collect::<Vec<_>>();
+    written.sort();
+    written
+}
+
+/// Sets the modification time of the entries of a store to the epoch.
+fn age_entries(store: &Path) {
+    for entry in std::fs::read_dir(store).unwrap() {
+        let file = std::fs::File::options()
+            .append(true)
+            .open(entry.unwrap().path())
+            .unwrap();
+        file.set_modified(std::time::UNIX_EPOCH).unwrap();
+    }
+}
+
+#[test]
+fn test_save_graph_writes_only_changed_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("app");
+    let store = dir.path().join("store");
+    let engine = watched_engine(&root, &store);
+    let lib = Uri::from_file_path(&root.join("src/lib.rs")).unwrap();
+    let main = Uri::from_file_path(&root.join("src/main.rs")).unwrap();
+    let mut graph = engine.graph().clone();
+    let range = Range::new(Position::new(0, 0), Position::new(0, 1));
+    graph.add_node(SymbolNode::new(
+        NodeKind::Function,
+        "main",
+        "app::main",
+        main.clone(),
+        range,
+    ));
+    engine.set_graph(graph);
+    let mut indexed = engine.indexed_files().clone();
+    indexed.insert(main.as_str().to_string(), content_hash(b"fn main() {}"));
+    engine.set_indexed_files(indexed.clone());
+    engine.save_graph();
+    assert_eq!(engine.store().unwrap().len(), 2);
+
+    // Nothing changed
+    age_entries(&store);
+    engine.save_graph();
+    assert!(written_entries(&store).is_empty());
+
+    // Only the changed file is written
+    indexed.insert(
+        main.as_str().to_string(),
+        content_hash(b"fn main() { greet(); }"),
+    );
+    engine.set_indexed_files(indexed.clone());
+    engine.save_graph();
+    assert_eq!(written_entries(&store).len(), 1);
+    let key = CacheKey::new(b"fn main() { greet(); }", "");
+    assert!(engine.store().unwrap().get(&main, &key).unwrap().is_some());
+
+    // Only the file that is no longer indexed is removed
+    age_entries(&store);
+    indexed.remove(main.as_str());
+    engine.set_indexed_files(indexed);
+    engine.save_graph();
+    assert_eq!(engine.store().unwrap().len(), 1);
+    assert!(written_entries(&store).is_empty());
+    let key = CacheKey::new(b"fn greet() {}", "");
+    assert!(engine.store().unwrap().get(&lib, &key).unwrap().is_some());
+}
+
+#[test]
+fn test_index_config_keeps_the_checkpoint_next_to_the_store() {
+    let dir = tempfile::tempdir().unwrap();
+    assert_eq!(Engine::new(dir.path()).index_config().checkpoint, None);
+
+    let store = dir.path().join("store");
+    let engine = Engine::new(dir.path()).with_store(GraphStore::open(&store).unwrap());
+    let checkpoint = engine.index_config().checkpoint.unwrap();
+    assert_eq!(checkpoint.parent(), Some(store.as_path()));
+}
+
+#[tokio::test]
+async fn test_index_dependencies_of_the_lockfile() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = Engine::new(dir.path()).with_dependencies(
+        DependencyIndexer::new(
+            dir.path(),
+            Arc::clone(Engine::new(dir.path()).supervisor()),
+            Arc::new(LanguageRegistry::with_defaults()),
+            Arc::new(DocumentStore::default()),
+        )
+        .with_cargo_home(dir.path().join("cargo"))
+        .with_cache_dir(dir.path().join("cache")),
+    );
+    // Not a Rust workspace
+    let outcome = engine.index_dependencies().await.unwrap();
+    assert!(outcome.dependencies.is_empty() && outcome.unresolved.is_empty());
+
+    let lockfile = "[[package]]\nname = \"tokio\"\nversion = \"1.45.1\"\nsource = \
+                    \"registry+https://github.com/rust-lang/crates.io-index\"\n";
+    std::fs::write(dir.path().join("Cargo.lock"), lockfile).unwrap();
+    let outcome = engine.index_dependencies().await.unwrap();
+    let unresolved = outcome
+        .unresolved
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(unresolved, ["tokio 1.45.1"]);
+
+    std::fs::write(dir.path().join("Cargo.lock"), "[[package]\n").unwrap();
+    let error = engine.index_dependencies().await.unwrap_err();
+    assert!(matches!(error, ContextEngineError::Parse { .. }));
+}
+
+/// An engine over a folder with `src/lib.rs`, whose function `greet` is in
+/// the graph and the store.
+fn watched_engine(root: &Path, store: &Path) -> Engine {
+    let path = root.join("src/lib.rs");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "fn greet() {}").unwrap();
+    let uri = Uri::from_file_path(&path).unwrap();
+    let range = Range::new(Position::new(0, 0), Position::new(0, 1));
+    let mut graph = KnowledgeGraph::new();
+    graph.add_node(SymbolNode::new(
+        NodeKind::Function,
+        "greet",
+        "app::greet",
+        uri.clone(),
+        range,
+    ));
+    let engine = Engine::new(root).with_store(GraphStore::open(store).unwrap());
+    engine.set_graph(graph);
+    let hash = content_hash(b"fn greet() {}");
+    engine.set_indexed_files(BTreeMap::from([(uri.as_str().to_string(), hash)]));
+    engine.save_graph();
+    engine
+}
+
+#[tokio::test]
+async fn test_apply_changes_invalidates_the_changed_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = watched_engine(&dir.path().join("app"), &dir.path().join("store"));
+    let uri = Uri::from_file_path(&dir.path().join("app/src/lib.rs")).unwrap();
+
+    let events = [FileEvent::new(uri, lsp_types::FileChangeType::CHANGED)];
+    let invalidation = engine.apply_changes(&events).await;
+    assert_eq!(invalidation.removed.len(), 1);
+    assert!(engine.graph().is_empty());
+    assert!(engine.indexed_files().is_empty());
+    assert!(engine.store().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_watch_applies_changes_on_disk() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("app");
+    let engine = Arc::new(watched_engine(&root, &dir.path().join("store")));
+    let config = WatchConfig {
+        poll_interval: Duration::from_millis(10),
+        debounce: Duration::from_millis(10),
+        ..WatchConfig::default()
+    };
+    engine.watch(config);
+    // Let the watcher record the files before changing them
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    std::fs::write(root.join("src/lib.rs"), "fn greet() { run() }").unwrap();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !engine.graph().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert!(engine.indexed_files().is_empty());
+    engine.shutdown().await;
+}
+
+#[test]
+fn test_start_servers_by_root_markers() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = Engine::new(dir.path());
+    // No root marker of any server
+    assert!(engine.start_servers().is_empty());
+    assert!(engine.status().servers.is_empty());
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_status_serializes_in_camel_case(symbols in 0usize..20) {
+        let engine = Engine::new("/workspace");
+        let mut graph = KnowledgeGraph::new();
+        for index in 0..symbols {
+            graph.add_node(node(&format!("f{index}")));
+        }
+        engine.set_graph(graph);
+        let status = serde_json::to_value(engine.status()).unwrap();
+        prop_assert_eq!(status.pointer("/symbols").unwrap(), &serde_json::json!(symbols));
+        prop_assert_eq!(status.pointer("/openDocuments").unwrap(), &serde_json::json!(0));
+    }
+}
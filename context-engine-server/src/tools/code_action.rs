@@ -0,0 +1,578 @@
+//! Tools about code actions and the workspace edits they make.
+
+use std::sync::Arc;
+
+use context_engine_core::ContextEngineError;
+use context_engine_core::diagnostics::{DiagnosticInfo, Overlay};
+use context_engine_core::edit::{EditPlan, EditPlanner};
+use context_engine_core::lsp::LspClient;
+use context_engine_core::types::{LineIndex, PositionEncodingKind, Range, Uri, UriExt};
+use lsp_types::request::{CodeActionRequest, CodeActionResolveRequest, Request};
+use lsp_types::{
+    CodeAction, CodeActionContext, CodeActionKind, CodeActionParams, CodeActionTriggerKind,
+    TextDocumentIdentifier, WorkspaceEdit,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tracing::debug;
+
+use crate::Engine;
+use crate::mcp::{
+    CallToolResult, Tool, ToolContext, ToolError, ToolFuture, ToolHandler, parse_arguments,
+};
+
+/// The arguments of `codeAction.list`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ListArguments {
+    /// The document
+    uri: Uri,
+    /// The range to list actions for; if `None`, the range of the
+    /// diagnostics
+    range: Option<Range>,
+    /// Diagnostics of the document, as reported by
+    /// `document.analyzeContent`, to list fixes for
+    #[serde(default)]
+    diagnostics: Vec<DiagnosticInfo>,
+    /// The kinds of actions to list, e.g. `quickfix`
+    only: Option<Vec<String>>,
+    /// Unsaved text of the document; if `None`, its text in the store or
+    /// on disk
+    content: Option<String>,
+    /// The language of the document, if it cannot be recognized
+    language_id: Option<String>,
+}
+
+/// The arguments of `codeAction.resolve`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ResolveArguments {
+    /// The document the action was listed for
+    uri: Uri,
+    /// The language server that listed the action
+    server: String,
+    /// The action to resolve
+    action: CodeAction,
+    /// The text of the document the action was listed for, if unsaved
+    content: Option<String>,
+    /// The language of the document, if it cannot be recognized
+    language_id: Option<String>,
+}
+
+/// Where `codeAction.apply` applies an edit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Target {
+    /// The open documents of the document store
+    #[default]
+    Memory,
+    /// The files on disk
+    Disk,
+}
+
+/// How `codeAction.apply` shows the changed files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Output {
+    /// A unified diff of each file
+    #[default]
+    Diff,
+    /// The whole text of each file after the edit
+    Text,
+}
+
+/// The arguments of `codeAction.apply`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ApplyArguments {
+    /// The document the action was listed for
+    uri: Option<Uri>,
+    /// The language server that listed the action, or that made the edit
+    server: Option<String>,
+    /// The action to apply; resolved first if it has no edit
+    action: Option<CodeAction>,
+    /// The edit to apply, instead of an action
+    edit: Option<WorkspaceEdit>,
+    /// The text of the document the action was listed for, if unsaved
+    content: Option<String>,
+    /// The language of the document, if it cannot be recognized
+    language_id: Option<String>,
+    /// Where the edit is applied
+    #[serde(default)]
+    target: Target,
+    /// Whether to only show what the edit would change
+    #[serde(default)]
+    dry_run: bool,
+    /// How the changed files are shown
+    #[serde(default)]
+    output: Output,
+}
+
+/// `codeAction.list`: the code actions the language servers offer for a
+/// range or diagnostics of a document.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListCodeActionsTool;
+
+impl ToolHandler for ListCodeActionsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "codeAction.list".to_string(),
+            description: "Lists the code actions, i.e. quick fixes, refactorings and source \
+                          actions, the language servers offer for a range of a document or for \
+                          diagnostics reported by `document.analyzeContent`. Works on unsaved \
+                          `content` too. Actions without an `edit` are resolved lazily by \
+                          `codeAction.resolve` or `codeAction.apply`; pass them on unchanged, \
+                          with their `server`."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "uri": {
+                        "type": "string",
+                        "description": "The URI of the document, e.g. `file:///app/src/user.rs`",
+                    },
+                    "range": range_schema(),
+                    "diagnostics": {
+                        "type": "array",
+                        "items": { "type": "object" },
+                        "description": "Diagnostics from `document.analyzeContent` to list fixes for; \
+                                        without a `range`, actions are listed for their range",
+                    },
+                    "only": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "The kinds of actions, e.g. `quickfix`, `refactor.extract` \
+                                        or `source.organizeImports`",
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Unsaved text of the whole document",
+                    },
+                    "languageId": {
+                        "type": "string",
+                        "description": "The LSP language identifier, if the URI does not reveal it",
+                    },
+                },
+                "required": ["uri"],
+            }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let arguments = parse_arguments::<ListArguments>(arguments)?;
+            let diagnostic_range = arguments
+                .diagnostics
+                .iter()
+                .map(|info| info.range)
+                .reduce(|a, b| Range::new(a.start.min(b.start), a.end.max(b.end)));
+            let Some(range) = arguments.range.or(diagnostic_range) else {
+                return Err(ToolError::InvalidArguments(
+                    "Expected a `range` or `diagnostics`".to_string(),
+                ));
+            };
+            let engine = &context.engine;
+            let text = text_of(engine, &arguments.uri, arguments.content)?;
+            let overlay = engine
+                .analyzer()
+                .overlay(&arguments.uri, &text, arguments.language_id.as_deref())
+                .await?;
+
+            let index = LineIndex::new(&text);
+            let store_encoding = engine.documents().encoding();
+            let mut actions = Vec::new();
+            let mut supported = false;
+            let mut error = None;
+            for (server, client) in overlay.servers() {
+                if !client.supports(CodeActionRequest::METHOD) {
+                    continue;
+                }
+                supported = true;
+                let encoding = client.capabilities().position_encoding();
+                let to_server = |range| convert(&index, range, store_encoding, &encoding);
+                let mut diagnostics = Vec::new();
+                for info in &arguments.diagnostics {
+                    if info.server == *server {
+                        let mut diagnostic = info.to_lsp();
+                        diagnostic.range = to_server(diagnostic.range)?;
+                        diagnostics.push(diagnostic);
+                    }
+                }
+                let params = CodeActionParams {
+                    text_document: TextDocumentIdentifier::new(arguments.uri.clone()),
+                    range: to_server(range)?,
+                    context: CodeActionContext {
+                        diagnostics,
+                        only: arguments
+                            .only
+                            .clone()
+                            .map(|kinds| kinds.into_iter().map(CodeActionKind::from).collect()),
+                        trigger_kind: Some(CodeActionTriggerKind::INVOKED),
+                    },
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                };
+                match engine
+                    .supervisor()
+                    .request::<CodeActionRequest>(server, params)
+                    .await
+                {
+                    Ok(response) => actions.extend(
+                        response
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|action| json!({ "server": server, "action": action })),
+                    ),
+                    Err(e) => {
+                        debug!(server = %server, error = %e, "Listing code actions failed");
+                        error = Some(e);
+                    }
+                }
+            }
+            drop(overlay);
+
+            if !supported {
+                return Err(ContextEngineError::Configuration {
+                    key: None,
+                    message: format!(
+                        "No running language server provides code actions for {}",
+                        arguments.uri.as_str()
+                    ),
+                }
+                .into());
+            }
+            // Report the failure unless another server answered
+            if let (true, Some(error)) = (actions.is_empty(), error) {
+                return Err(error.into());
+            }
+            Ok(CallToolResult::json(json!({
+                "uri": arguments.uri,
+                "actions": actions,
+            })))
+        })
+    }
+}
+
+/// `codeAction.resolve`: fills in the edit of a code action that a language
+/// server computes lazily.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResolveCodeActionTool;
+
+impl ToolHandler for ResolveCodeActionTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "codeAction.resolve".to_string(),
+            description: "Resolves a code action listed by `codeAction.list`, so that it has its \
+                          workspace `edit`. Actions that are resolved already, or whose server \
+                          cannot resolve them, are returned unchanged."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "uri": {
+                        "type": "string",
+                        "description": "The URI of the document the action was listed for",
+                    },
+                    "server": {
+                        "type": "string",
+                        "description": "The language server that listed the action",
+                    },
+                    "action": {
+                        "type": "object",
+                        "description": "The action as listed",
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "The unsaved text the action was listed for",
+                    },
+                    "languageId": {
+                        "type": "string",
+                        "description": "The LSP language identifier, if the URI does not reveal it",
+                    },
+                },
+                "required": ["uri", "server", "action"],
+            }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let arguments = parse_arguments::<ResolveArguments>(arguments)?;
+            let engine = &context.engine;
+            let text = text_of(engine, &arguments.uri, arguments.content)?;
+            let overlay = engine
+                .analyzer()
+                .overlay(&arguments.uri, &text, arguments.language_id.as_deref())
+                .await?;
+            let (server, client) = server_of(&overlay, Some(&arguments.server))?;
+            let action = resolve(engine, server, client, arguments.action).await?;
+            Ok(CallToolResult::json(json!({
+                "server": server,
+                "resolved": action.edit.is_some(),
+                "action": action,
+            })))
+        })
+    }
+}
+
+/// `codeAction.apply`: applies the workspace edit of a code action, or any
+/// workspace edit, to the open documents or to disk.
+///
+/// The edit is planned with an [`EditPlanner`] first, so that nothing
+/// changes unless every text edit and resource operation of it applies.
+/// Edits of files outside of the workspace root are rejected, even in dry
+/// runs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApplyCodeActionTool;
+
+impl ToolHandler for ApplyCodeActionTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "codeAction.apply".to_string(),
+            description: "Applies a code action listed by `codeAction.list`, resolving it first \
+                          if needed, or a workspace edit. Text edits and file creations, renames \
+                          and deletions are applied to the in-memory documents (`target: memory`, \
+                          the default) or written to disk (`target: disk`). Returns a unified \
+                          diff or the new text of every changed file; with `dryRun`, nothing is \
+                          applied. Actions that only run a command are not supported, and files \
+                          outside of the workspace cannot be edited."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "uri": {
+                        "type": "string",
+                        "description": "The URI of the document the action was listed for",
+                    },
+                    "server": {
+                        "type": "string",
+                        "description": "The language server that listed the action or made the edit",
+                    },
+                    "action": {
+                        "type": "object",
+                        "description": "The action as listed; requires `uri`",
+                    },
+                    "edit": {
+                        "type": "object",
+                        "description": "An LSP WorkspaceEdit to apply instead of an action",
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "The unsaved text the action was listed for",
+                    },
+                    "languageId": {
+                        "type": "string",
+                        "description": "The LSP language identifier, if the URI does not reveal it",
+                    },
+                    "target": {
+                        "type": "string",
+                        "enum": ["memory", "disk"],
+                        "default": "memory",
+                    },
+                    "dryRun": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Whether to only show what the edit would change",
+                    },
+                    "output": {
+                        "type": "string",
+                        "enum": ["diff", "text"],
+                        "default": "diff",
+                    },
+                },
+            }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let arguments = parse_arguments::<ApplyArguments>(arguments)?;
+            let engine = &context.engine;
+            match (&arguments.action, &arguments.edit) {
+                (Some(action), None) => {
+                    let Some(uri) = &arguments.uri else {
+                        return Err(ToolError::InvalidArguments(
+                            "Expected the `uri` the action was listed for".to_string(),
+                        ));
+                    };
+                    let text = text_of(engine, uri, arguments.content.clone())?;
+                    let overlay = engine
+                        .analyzer()
+                        .overlay(uri, &text, arguments.language_id.as_deref())
+                        .await?;
+                    let (server, client) = server_of(&overlay, arguments.server.as_deref())?;
+                    let action = resolve(engine, server, client, action.clone()).await?;
+                    let Some(edit) = &action.edit else {
+                        let message = match &action.command {
+                            Some(command) => format!(
+                                "`{}` only runs the command `{}`, which cannot be applied",
+                                action.title, command.command
+                            ),
+                            None => format!("`{}` has no edit", action.title),
+                        };
+                        return Err(ContextEngineError::Edit { uri: None, message }.into());
+                    };
+                    // The action was computed on the text of the overlay
+                    let planner = EditPlanner::new(
+                        engine.documents(),
+                        client.capabilities().position_encoding(),
+                    )
+                    .with_content(uri.clone(), text);
+                    let plan = planner.plan(edit)?;
+                    // Applied while the overlay is open, so that the servers
+                    // are restored to the edited documents
+                    apply(engine, &plan, &arguments)
+                }
+                (None, Some(edit)) => {
+                    let encoding = match &arguments.server {
+                        Some(server) => engine
+                            .supervisor()
+                            .client(server)
+                            .await?
+                            .capabilities()
+                            .position_encoding(),
+                        None => engine.documents().encoding().clone(),
+                    };
+                    let mut planner = EditPlanner::new(engine.documents(), encoding);
+                    if let (Some(uri), Some(content)) = (&arguments.uri, &arguments.content) {
+                        planner = planner.with_content(uri.clone(), content.clone());
+                    }
+                    let plan = planner.plan(edit)?;
+                    apply(engine, &plan, &arguments)
+                }
+                _ => Err(ToolError::InvalidArguments(
+                    "Expected either an `action` or an `edit`".to_string(),
+                )),
+            }
+        })
+    }
+}
+
+/// Applies a planned edit unless it is a dry run, and describes the changed
+/// files.
+fn apply(
+    engine: &Engine,
+    plan: &EditPlan,
+    arguments: &ApplyArguments,
+) -> Result<CallToolResult, ToolError> {
+    // Previews must not disclose files outside of the workspace either
+    plan.check_within(engine.root())?;
+    if !arguments.dry_run {
+        match arguments.target {
+            Target::Memory => plan.apply_to_store(engine.supervisor(), engine.registry())?,
+            Target::Disk => plan.apply_to_disk(engine.supervisor())?,
+        }
+    }
+    let changes = plan
+        .changes()
+        .iter()
+        .map(|change| match arguments.output {
+            Output::Diff => json!({
+                "uri": change.uri,
+                "operation": change.operation,
+                "diff": change.diff(),
+            }),
+            Output::Text => json!({
+                "uri": change.uri,
+                "operation": change.operation,
+                "text": change.after,
+            }),
+        })
+        .collect::<Vec<_>>();
+    Ok(CallToolResult::json(json!({
+        "applied": !arguments.dry_run,
+        "target": arguments.target,
+        "changes": changes,
+    })))
+}
+
+/// Returns the text of a document: the given content, the text of the open
+/// document, or the file on disk.
+fn text_of(
+    engine: &Engine,
+    uri: &Uri,
+    content: Option<String>,
+) -> Result<String, ContextEngineError> {
+    if let Some(content) = content {
+        return Ok(content);
+    }
+    if let Some(document) = engine.documents().get(uri) {
+        return Ok(document.text().to_string());
+    }
+    let path = uri.to_file_path()?;
+    std::fs::read_to_string(&path).map_err(|e| ContextEngineError::Parse {
+        uri: Some(uri.clone()),
+        message: format!("cannot read {}: {e}", path.display()),
+    })
+}
+
+/// Returns a server an overlay is open in: the named one, or the first.
+fn server_of<'o>(
+    overlay: &'o Overlay,
+    name: Option<&str>,
+) -> Result<(&'o str, &'o Arc<LspClient>), ContextEngineError> {
+    overlay
+        .servers()
+        .iter()
+        .find(|(server, _)| name.map_or(true, |name| server == name))
+        .map(|(server, client)| (server.as_str(), client))
+        .ok_or_else(|| ContextEngineError::Configuration {
+            key: None,
+            message: format!(
+                "Language server {} is not running for {}",
+                name.unwrap_or_default(),
+                overlay.language_id()
+            ),
+        })
+}
+
+/// Resolves the edit of a code action if it has none and the server can
+/// resolve it.
+async fn resolve(
+    engine: &Engine,
+    server: &str,
+    client: &LspClient,
+    action: CodeAction,
+) -> Result<CodeAction, ContextEngineError> {
+    if action.edit.is_some() || !client.supports(CodeActionResolveRequest::METHOD) {
+        return Ok(action);
+    }
+    engine
+        .supervisor()
+        .request::<CodeActionResolveRequest>(server, action)
+        .await
+}
+
+/// Converts a range of a text from one position encoding to another.
+fn convert(
+    index: &LineIndex,
+    range: Range,
+    from: &PositionEncodingKind,
+    to: &PositionEncodingKind,
+) -> Result<Range, ContextEngineError> {
+    Ok(Range::new(
+        index.convert(range.start, from, to)?,
+        index.convert(range.end, from, to)?,
+    ))
+}
+
+/// The input schema of a range.
+fn range_schema() -> Value {
+    let position = json!({
+        "type": "object",
+        "properties": {
+            "line": { "type": "integer", "minimum": 0 },
+            "character": { "type": "integer", "minimum": 0 },
+        },
+        "required": ["line", "character"],
+    });
+    json!({
+        "type": "object",
+        "properties": { "start": position, "end": position },
+        "required": ["start", "end"],
+    })
+}
+
+#[cfg(test)]
+#[path = "tests/code_action.rs"]
+mod tests;
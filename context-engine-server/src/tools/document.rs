@@ -0,0 +1,94 @@
+//! Tools about single documents.
+
+use context_engine_core::ContextEngineError;
+use context_engine_core::diagnostics::Severity;
+use context_engine_core::types::Uri;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::mcp::{CallToolResult, Tool, ToolContext, ToolFuture, ToolHandler, parse_arguments};
+
+/// The arguments of `document.analyzeContent`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct AnalyzeArguments {
+    /// The document the content is analyzed as
+    uri: Uri,
+    /// The proposed text of the document
+    content: String,
+    /// The language of the content, if it cannot be recognized from the
+    /// document
+    language_id: Option<String>,
+}
+
+/// `document.analyzeContent`: the diagnostics the language servers report
+/// for proposed content of a document, without writing it to disk.
+///
+/// See [`ContentAnalyzer`](context_engine_core::diagnostics::ContentAnalyzer)
+/// for how the content is opened in the servers and how long the tool waits
+/// for their diagnostics.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AnalyzeContentTool;
+
+impl ToolHandler for AnalyzeContentTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "document.analyzeContent".to_string(),
+            description: "Validates unsaved code against the real workspace: opens the content as \
+                          an in-memory version of the document in its language servers, waits \
+                          until their diagnostics settle and returns them with ranges, severities \
+                          and codes. Nothing is written to disk, and the document is restored \
+                          afterwards. `settled` is false for servers that were still working when \
+                          the analysis timed out."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "uri": {
+                        "type": "string",
+                        "description": "The URI of the document, which need not exist, e.g. `file:///app/src/user.rs`",
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "The proposed text of the whole document",
+                    },
+                    "languageId": {
+                        "type": "string",
+                        "description": "The LSP language identifier, if the URI does not reveal it",
+                    },
+                },
+                "required": ["uri", "content"],
+            }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let arguments = parse_arguments::<AnalyzeArguments>(arguments)?;
+            let analysis = context
+                .engine
+                .analyzer()
+                .analyze(
+                    &arguments.uri,
+                    &arguments.content,
+                    arguments.language_id.as_deref(),
+                )
+                .await?;
+
+            let summary = json!({
+                "errors": analysis.count(Severity::Error),
+                "warnings": analysis.count(Severity::Warning),
+                "settled": analysis.is_settled(),
+            });
+            let mut result = serde_json::to_value(analysis).map_err(ContextEngineError::from)?;
+            if let Some(result) = result.as_object_mut() {
+                result.insert("summary".to_string(), summary);
+            }
+            Ok(CallToolResult::json(result))
+        })
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/document.rs"]
+mod tests;
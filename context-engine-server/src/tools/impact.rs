@@ -0,0 +1,549 @@
+//! Tools about the impact of changes.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use context_engine_core::ContextEngineError;
+use context_engine_core::graph::{
+    Edge, EdgeKind, ImpactAnalyzer, ImpactConfig, ImpactNode, ImpactTree, SymbolId, SymbolNode,
+    SymbolRef,
+};
+use context_engine_core::types::{
+    LineIndex, Location, Position, PositionEncodingKind, Range, Uri, UriExt,
+};
+use context_engine_core::vcs::{ChangedSymbol, DiffTarget, Git};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
+
+use crate::Engine;
+use crate::mcp::{CallToolResult, Tool, ToolContext, ToolFuture, ToolHandler, parse_arguments};
+use crate::tools::symbol::{find_references, lookup_symbol, position_schema};
+
+/// The deepest impact analysis `symbol.impact` runs.
+const MAX_DEPTH: usize = 10;
+
+/// The most affected symbols `symbol.impact` collects.
+const MAX_NODES: usize = 1000;
+
+/// The most changed symbols `workspace.diffImpact` analyzes.
+const MAX_CHANGED_SYMBOLS: usize = 100;
+
+/// The most `textDocument/references` requests an impact analysis has in
+/// flight at a time.
+const CONCURRENCY: usize = 4;
+
+/// The most `textDocument/references` requests one call of `symbol.impact`
+/// or `workspace.diffImpact` sends.
+const MAX_REFERENCE_REQUESTS: usize = 200;
+
+/// The arguments of `symbol.impact`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ImpactArguments {
+    /// The fully qualified name of the symbol
+    name: Option<String>,
+    /// The document of the symbol, with `position`
+    uri: Option<Uri>,
+    /// A position in the definition of the symbol
+    position: Option<Position>,
+    /// How many steps away from the symbol dependents are collected
+    #[serde(default = "default_max_depth")]
+    max_depth: usize,
+    /// The most affected symbols collected
+    #[serde(default = "default_max_nodes")]
+    max_nodes: usize,
+    /// Whether to ask language servers for references besides the edges of
+    /// the knowledge graph
+    #[serde(default = "default_references")]
+    references: bool,
+}
+
+impl ImpactArguments {
+    /// Returns the limits of the analysis, capped.
+    fn config(&self) -> ImpactConfig {
+        ImpactConfig {
+            max_depth: self.max_depth.min(MAX_DEPTH),
+            max_nodes: self.max_nodes.min(MAX_NODES),
+        }
+    }
+}
+
+fn default_max_depth() -> usize {
+    ImpactConfig::default().max_depth
+}
+
+fn default_max_nodes() -> usize {
+    ImpactConfig::default().max_nodes
+}
+
+fn default_references() -> bool {
+    true
+}
+
+/// `symbol.impact`: the symbols a change of a symbol affects, transitively.
+///
+/// The analysis follows the callers, implementors and re-exports of the
+/// knowledge graph (see [`ImpactAnalyzer`]), and the references the
+/// language servers of the affected symbols find. The references of every
+/// affected symbol are asked for once; the symbols that contain them are
+/// added to the tree, until no new symbol turns up or a limit is reached.
+/// At most [`MAX_REFERENCE_REQUESTS`] requests are sent; `references_truncated`
+/// tells whether symbols were left without their references.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImpactAnalysisTool;
+
+impl ToolHandler for ImpactAnalysisTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "symbol.impact".to_string(),
+            description: "Analyzes what a change of a symbol, given by its fully qualified name \
+                          or by a position in its definition, affects: its callers, referrers, \
+                          implementors and re-exports, transitively. Returns a tree of dependents \
+                          with the locations of their signatures and of their uses, each listed \
+                          once; dependents that close a cycle are marked with `cycle`. Lists the \
+                          affected public API and tests. `references_truncated` tells whether the \
+                          limit of reference requests left dependents out."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "The fully qualified name, e.g. `app::user::User`",
+                    },
+                    "uri": {
+                        "type": "string",
+                        "description": "The URI of the document, together with `position`",
+                    },
+                    "position": position_schema(),
+                    "maxDepth": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": MAX_DEPTH,
+                        "default": default_max_depth(),
+                        "description": "How many steps away dependents are collected",
+                    },
+                    "maxNodes": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": MAX_NODES,
+                        "default": default_max_nodes(),
+                        "description": "The most affected symbols collected",
+                    },
+                    "references": {
+                        "type": "boolean",
+                        "default": true,
+                        "description": "Whether to ask language servers for references",
+                    },
+                },
+            }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let arguments = parse_arguments::<ImpactArguments>(arguments)?;
+            let engine = &context.engine;
+
+            let id = match lookup_symbol(
+                &engine.graph(),
+                arguments.name.as_deref(),
+                arguments.uri.as_ref(),
+                arguments.position,
+            )? {
+                Ok(symbol) => symbol.id(),
+                Err(not_found) => return Ok(not_found),
+            };
+            let config = arguments.config();
+            let mut cache = ReferenceCache::new(MAX_REFERENCE_REQUESTS);
+            let references = arguments.references.then_some(&mut cache);
+            let Some(tree) = impact(engine, id, config, references).await else {
+                return Ok(CallToolResult::error(
+                    "The symbol is no longer in the knowledge graph; look it up again",
+                    None,
+                ));
+            };
+            let mut tree = serde_json::to_value(tree).map_err(ContextEngineError::from)?;
+            if let Some(tree) = tree.as_object_mut() {
+                tree.insert("references_truncated".to_string(), cache.truncated.into());
+            }
+            Ok(CallToolResult::json(tree))
+        })
+    }
+}
+
+/// The arguments of `workspace.diffImpact`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct DiffImpactArguments {
+    /// The revision compared with
+    #[serde(default = "default_base")]
+    base: String,
+    /// The revision compared, or `None` for the working tree
+    head: Option<String>,
+    /// How many steps away from a changed symbol dependents are collected
+    #[serde(default = "default_max_depth")]
+    max_depth: usize,
+    /// The most affected symbols collected per changed symbol
+    #[serde(default = "default_max_nodes")]
+    max_nodes: usize,
+    /// Whether to ask language servers for references besides the edges of
+    /// the knowledge graph
+    #[serde(default = "default_references")]
+    references: bool,
+}
+
+impl DiffImpactArguments {
+    /// Returns what the diff compares.
+    fn target(&self) -> DiffTarget {
+        match &self.head {
+            Some(head) => DiffTarget::Revisions {
+                base: self.base.clone(),
+                head: head.clone(),
+            },
+            None => DiffTarget::WorkingTree {
+                base: self.base.clone(),
+            },
+        }
+    }
+
+    /// Returns the limits of the analysis of each changed symbol, capped.
+    fn config(&self) -> ImpactConfig {
+        ImpactConfig {
+            max_depth: self.max_depth.min(MAX_DEPTH),
+            max_nodes: self.max_nodes.min(MAX_NODES),
+        }
+    }
+}
+
+fn default_base() -> String {
+    "HEAD".to_string()
+}
+
+/// `workspace.diffImpact`: what the changes of a git diff affect, by changed
+/// symbol.
+///
+/// The diff of the working tree against a revision, or between two
+/// revisions, is read from the local repository (see [`Git`]). Its hunks
+/// are mapped to the symbols of the knowledge graph whose definitions they
+/// overlap (see [`FileDiff::changed_symbols`]), and the impact of every
+/// changed symbol is analyzed as by [`ImpactAnalysisTool`]. The analyses
+/// share the references they find, so that the references of a symbol are
+/// asked for once, and the [`MAX_REFERENCE_REQUESTS`] requests they may send
+/// in total.
+///
+/// [`FileDiff::changed_symbols`]: context_engine_core::vcs::FileDiff::changed_symbols
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiffImpactTool;
+
+impl ToolHandler for DiffImpactTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "workspace.diffImpact".to_string(),
+            description: "Reports what the changes of a git diff affect: the uncommitted changes \
+                          of the working tree against `base`, or the changes between `base` and \
+                          `head`, read from the local repository. Maps the changed lines to the \
+                          symbols whose definitions they touch and lists, for every changed \
+                          symbol, its dependents transitively and the tests among them. The \
+                          knowledge graph is indexed from the working tree, so symbols are mapped \
+                          best when `head` is omitted or checked out. `references_truncated` \
+                          tells whether the limit of reference requests, shared by all changed \
+                          symbols, left dependents out."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "base": {
+                        "type": "string",
+                        "default": "HEAD",
+                        "description": "The revision compared with, e.g. `HEAD` or `main`",
+                    },
+                    "head": {
+                        "type": "string",
+                        "description": "The revision compared; the working tree if omitted",
+                    },
+                    "maxDepth": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": MAX_DEPTH,
+                        "default": default_max_depth(),
+                        "description": "How many steps away dependents are collected",
+                    },
+                    "maxNodes": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": MAX_NODES,
+                        "default": default_max_nodes(),
+                        "description": "The most affected symbols collected per changed symbol",
+                    },
+                    "references": {
+                        "type": "boolean",
+                        "default": true,
+                        "description": "Whether to ask language servers for references",
+                    },
+                },
+            }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let arguments = parse_arguments::<DiffImpactArguments>(arguments)?;
+            let engine = &context.engine;
+            let files = Git::new(engine.root()).diff(&arguments.target()).await?;
+
+            let (mut changed, mut unmapped) = (Vec::new(), Vec::new());
+            {
+                let graph = engine.graph();
+                for file in &files {
+                    let symbols = file.changed_symbols(&graph, engine.root());
+                    if symbols.is_empty() {
+                        let path = file.new_path.as_ref().or(file.old_path.as_ref());
+                        unmapped.extend(path.map(|path| path.display().to_string()));
+                    }
+                    changed.extend(symbols);
+                }
+            }
+            let omitted = changed.len().saturating_sub(MAX_CHANGED_SYMBOLS);
+            changed.truncate(MAX_CHANGED_SYMBOLS);
+
+            let config = arguments.config();
+            let mut cache = ReferenceCache::new(MAX_REFERENCE_REQUESTS);
+            let mut changes = Vec::new();
+            let mut tests = Vec::<SymbolRef>::new();
+            for change in changed {
+                let references = arguments.references.then_some(&mut cache);
+                let Some(tree) = impact(engine, change.symbol.id, config, references).await else {
+                    continue;
+                };
+                for test in &tree.tests {
+                    if !tests.iter().any(|known| known.id == test.id) {
+                        tests.push(test.clone());
+                    }
+                }
+                changes.push(change_report(change, tree));
+            }
+            tests.sort_by(|a, b| a.qualified_name.cmp(&b.qualified_name));
+
+            Ok(CallToolResult::json(json!({
+                "base": arguments.base,
+                "head": arguments.head,
+                "files": files.len(),
+                "changes": changes,
+                "omitted": omitted,
+                "unmapped": unmapped,
+                "tests": tests,
+                "references_truncated": cache.truncated,
+            })))
+        })
+    }
+}
+
+/// Returns the report of a changed symbol: where it changed, and the
+/// symbols and tests its change affects.
+fn change_report(change: ChangedSymbol, tree: ImpactTree) -> Value {
+    let mut dependents = Vec::new();
+    for child in &tree.root.children {
+        collect_dependents(child, &tree.root, 1, &mut dependents);
+    }
+    json!({
+        "symbol": change.symbol,
+        "ranges": change.ranges,
+        "public": tree.root.public,
+        "test": tree.root.test,
+        "dependents": dependents,
+        "tests": tree.tests,
+        "truncated": tree.truncated,
+    })
+}
+
+/// Lists a dependent and the dependents below it, with what they depend on
+/// and how far from the changed symbol they are; cycles are left out, as
+/// their symbols are listed already.
+fn collect_dependents(node: &ImpactNode, parent: &ImpactNode, depth: usize, out: &mut Vec<Value>) {
+    if node.cycle {
+        return;
+    }
+    out.push(json!({
+        "symbol": node.symbol,
+        "via": node.via,
+        "depends_on": parent.symbol.qualified_name,
+        "depth": depth,
+        "public": node.public,
+        "test": node.test,
+    }));
+    for child in &node.children {
+        collect_dependents(child, node, depth + 1, out);
+    }
+}
+
+/// The references language servers found for the impact analyses of one
+/// tool call, so that the references of a symbol are asked for once.
+pub(super) struct ReferenceCache {
+    /// The symbols whose references were asked for
+    asked: HashSet<SymbolId>,
+    /// The references found, as relationships of the symbols they are in
+    sites: Vec<(Edge, Location)>,
+    /// The line indexes of the documents references were converted in
+    texts: HashMap<String, Option<LineIndex>>,
+    /// The requests that may still be sent
+    remaining: usize,
+    /// Whether symbols were left without their references because no
+    /// request remained
+    truncated: bool,
+}
+
+impl ReferenceCache {
+    /// Creates an empty cache that allows a number of requests.
+    pub(super) fn new(max_requests: usize) -> Self {
+        Self {
+            asked: HashSet::new(),
+            sites: Vec::new(),
+            texts: HashMap::new(),
+            remaining: max_requests,
+            truncated: false,
+        }
+    }
+}
+
+/// Runs an impact analysis, adding the references language servers find to
+/// the edges of the graph if given a cache for them.
+///
+/// The references of at most [`CONCURRENCY`] symbols are asked for at a
+/// time. References already in the cache, also those found for other
+/// symbols, are reused; once the cache allows no more requests, the
+/// remaining symbols are left without their references.
+///
+/// # Returns
+///
+/// The tree, or `None` if the graph no longer has the symbol.
+pub(super) async fn impact(
+    engine: &Arc<Engine>,
+    id: SymbolId,
+    config: ImpactConfig,
+    mut references: Option<&mut ReferenceCache>,
+) -> Option<ImpactTree> {
+    loop {
+        let (tree, pending) = {
+            let graph = engine.graph();
+            let sites = references
+                .as_deref()
+                .into_iter()
+                .flat_map(|cache| &cache.sites);
+            let tree = ImpactAnalyzer::new(&graph, engine.documents())
+                .with_config(config)
+                .with_sites(sites.cloned())
+                .analyze(id)?;
+            let pending = match references.as_deref_mut() {
+                Some(cache) => {
+                    let mut pending = tree
+                        .root
+                        .descendants()
+                        .into_iter()
+                        .filter(|node| !node.cycle && !cache.asked.contains(&node.symbol.id))
+                        .filter_map(|node| graph.node(node.symbol.id).cloned())
+                        .collect::<Vec<_>>();
+                    if pending.len() > cache.remaining {
+                        cache.truncated = true;
+                        pending.truncate(cache.remaining);
+                    }
+                    cache.remaining -= pending.len();
+                    cache.asked.extend(pending.iter().map(SymbolNode::id));
+                    pending
+                }
+                None => Vec::new(),
+            };
+            (tree, pending)
+        };
+        let Some(cache) = references.as_deref_mut().filter(|_| !pending.is_empty()) else {
+            return Some(tree);
+        };
+
+        let semaphore = Arc::new(Semaphore::new(CONCURRENCY));
+        let mut tasks = JoinSet::new();
+        for symbol in pending {
+            let engine = Arc::clone(engine);
+            let semaphore = Arc::clone(&semaphore);
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await.ok();
+                let result = find_references(&engine, &symbol).await;
+                (symbol, result)
+            });
+        }
+        let mut found = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok(result) => found.push(result),
+                Err(error) => warn!(%error, "Reference task failed"),
+            }
+        }
+
+        // In a fixed order, so that the tree does not depend on the order
+        // the servers answered in
+        found.sort_by_key(|(symbol, _)| symbol.id());
+        for (symbol, result) in found {
+            let (locations, encoding) = match result {
+                Ok(Some(references)) => references,
+                Ok(None) => continue,
+                Err(error) => {
+                    debug!(symbol = %symbol.qualified_name, %error, "No references");
+                    continue;
+                }
+            };
+            let graph = engine.graph();
+            for location in locations {
+                let Some(location) =
+                    to_store_encoding(engine, location, &encoding, &mut cache.texts)
+                else {
+                    continue;
+                };
+                let Some(referrer) = graph.symbol_at(&location.uri, location.range.start) else {
+                    continue;
+                };
+                if referrer.id() != symbol.id() {
+                    let edge = Edge::new(referrer.id(), EdgeKind::References, symbol.id());
+                    cache.sites.push((edge, location));
+                }
+            }
+        }
+    }
+}
+
+/// Converts a location from the position encoding of a server to the one
+/// of the document store, in which the graph is indexed.
+///
+/// # Returns
+///
+/// The location, or `None` if its document cannot be read or its range is
+/// not valid in it.
+fn to_store_encoding(
+    engine: &Engine,
+    location: Location,
+    encoding: &PositionEncodingKind,
+    texts: &mut HashMap<String, Option<LineIndex>>,
+) -> Option<Location> {
+    let store = engine.documents().encoding();
+    if encoding == store {
+        return Some(location);
+    }
+    let index = texts
+        .entry(location.uri.as_str().to_string())
+        .or_insert_with(|| {
+            let text = match engine.documents().get(&location.uri) {
+                Some(document) => document.text().to_string(),
+                None => std::fs::read_to_string(location.uri.to_file_path().ok()?).ok()?,
+            };
+            Some(LineIndex::new(&text))
+        })
+        .as_ref()?;
+    let start = index.convert(location.range.start, encoding, store).ok()?;
+    let end = index.convert(location.range.end, encoding, store).ok()?;
+    Some(Location::new(location.uri, Range::new(start, end)))
+}
+
+#[cfg(test)]
+#[path = "tests/impact.rs"]
+mod tests;
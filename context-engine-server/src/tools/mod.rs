@@ -0,0 +1,64 @@
+//! The MCP tools of the server.
+//!
+//! Each tool is a [`ToolHandler`](crate::mcp::ToolHandler) over the
+//! [`Engine`](crate::Engine) of the workspace; [`default_tools`] registers
+//! all of them.
+//!
+//! ## Structs
+//!
+//! * [`WorkspaceStatusTool`] - `workspace.status`: the state of the workspace.
+//! * [`IndexWorkspaceTool`] - `workspace.index`: builds the knowledge graph.
+//! * [`SearchSymbolsTool`] - `symbol.search`: finds symbols by name and kind.
+//! * [`DescribeSymbolTool`] - `symbol.describe`: the context card of a symbol.
+//! * [`RenameSymbolTool`] - `symbol.rename`: renames a symbol across the
+//!   workspace.
+//! * [`ImpactAnalysisTool`] - `symbol.impact`: the symbols a change of a symbol
+//!   affects.
+//! * [`DiffImpactTool`] - `workspace.diffImpact`: what the changes of a git
+//!   diff affect.
+//! * [`AnalyzeContentTool`] - `document.analyzeContent`: diagnostics of unsaved
+//!   content.
+//! * [`ListCodeActionsTool`] - `codeAction.list`: the code actions for a range.
+//! * [`ResolveCodeActionTool`] - `codeAction.resolve`: the edit of a code
+//!   action.
+//! * [`ApplyCodeActionTool`] - `codeAction.apply`: applies a code action or a
+//!   workspace edit.
+//!
+//! ## Functions
+//!
+//! * [`default_tools`] - The tools of the server.
+
+mod code_action;
+mod document;
+mod impact;
+mod symbol;
+mod workspace;
+
+#[cfg(test)]
+#[path = "tests/support.rs"]
+mod test_support;
+
+pub use code_action::{ApplyCodeActionTool, ListCodeActionsTool, ResolveCodeActionTool};
+pub use document::AnalyzeContentTool;
+pub use impact::{DiffImpactTool, ImpactAnalysisTool};
+pub use symbol::{DescribeSymbolTool, RenameSymbolTool, SearchSymbolsTool};
+pub use workspace::{IndexWorkspaceTool, WorkspaceStatusTool};
+
+use crate::mcp::ToolRegistry;
+
+/// Returns a registry with all tools of the server.
+pub fn default_tools() -> ToolRegistry {
+    let mut tools = ToolRegistry::new();
+    tools.register(WorkspaceStatusTool);
+    tools.register(IndexWorkspaceTool);
+    tools.register(SearchSymbolsTool);
+    tools.register(DescribeSymbolTool);
+    tools.register(RenameSymbolTool);
+    tools.register(ImpactAnalysisTool);
+    tools.register(DiffImpactTool);
+    tools.register(AnalyzeContentTool);
+    tools.register(ListCodeActionsTool);
+    tools.register(ResolveCodeActionTool);
+    tools.register(ApplyCodeActionTool);
+    tools
+}
@@ -0,0 +1,542 @@
+//! Tools about symbols.
+
+use std::sync::Arc;
+
+use context_engine_core::ContextEngineError;
+use context_engine_core::document::TextDocument;
+use context_engine_core::edit::FileOperation;
+use context_engine_core::graph::{ContextCard, KnowledgeGraph, SymbolNode, SymbolQuery};
+use context_engine_core::patterns::{PatternExtractor, UsagePattern};
+use context_engine_core::syntax::SyntaxLayer;
+use context_engine_core::types::{Position, Uri, UriExt};
+use lsp_types::request::{References, Request};
+use lsp_types::{
+    Location, PositionEncodingKind, ReferenceContext, ReferenceParams, TextDocumentIdentifier,
+    TextDocumentPositionParams,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::debug;
+
+use crate::Engine;
+use crate::mcp::{
+    CallToolResult, Tool, ToolContext, ToolError, ToolFuture, ToolHandler, parse_arguments,
+};
+
+/// The number of symbols `symbol.search` returns if the query sets no
+/// limit.
+const DEFAULT_LIMIT: usize = 50;
+
+/// The depth of related types `symbol.describe` collects by default.
+const DEFAULT_DEPTH: usize = 1;
+
+/// The deepest related types `symbol.describe` collects.
+const MAX_DEPTH: usize = 3;
+
+/// The token budget of `symbol.describe` by default.
+const DEFAULT_TOKEN_BUDGET: usize = 2000;
+
+/// The number of similar symbols the symbol tools suggest for a name they
+/// do not know.
+const SUGGESTIONS: usize = 5;
+
+/// `symbol.search`: finds symbols of the knowledge graph by name, kind and
+/// document.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchSymbolsTool;
+
+impl ToolHandler for SearchSymbolsTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "symbol.search".to_string(),
+            description: "Finds symbols of the knowledge graph whose qualified name contains a \
+                          string, ignoring case, optionally restricted to kinds and a document. \
+                          Returns their qualified names and the locations of their signatures, \
+                          bodies and doc comments, best matches first."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "A substring of the qualified names, e.g. `user::User`",
+                    },
+                    "kinds": {
+                        "type": "array",
+                        "items": {
+                            "enum": [
+                                "crate", "module", "type", "trait", "function", "method",
+                                "field", "variant", "constant",
+                            ],
+                        },
+                    },
+                    "uri": {
+                        "type": "string",
+                        "description": "The URI of the document that defines the symbols",
+                    },
+                    "limit": { "type": "integer", "minimum": 1, "default": DEFAULT_LIMIT },
+                },
+            }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let mut query = parse_arguments::<SymbolQuery>(arguments)?;
+            query.limit = Some(query.limit.unwrap_or(DEFAULT_LIMIT));
+            let graph = context.engine.graph();
+            let symbols = graph.query(&query);
+            let result = json!({ "symbols": symbols });
+            Ok(CallToolResult::json(result))
+        })
+    }
+}
+
+/// The arguments of `symbol.describe`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct DescribeArguments {
+    /// The fully qualified name of the symbol
+    name: Option<String>,
+    /// The document of the symbol, with `position`
+    uri: Option<Uri>,
+    /// A position in the definition of the symbol
+    position: Option<Position>,
+    /// How many steps away related types are collected
+    #[serde(default = "default_depth")]
+    depth: usize,
+    /// The maximum size of the card in tokens
+    #[serde(default = "default_token_budget")]
+    token_budget: usize,
+    /// Whether to ask the language server for references to extract usage
+    /// patterns from
+    #[serde(default = "default_usages")]
+    usages: bool,
+}
+
+fn default_depth() -> usize {
+    DEFAULT_DEPTH
+}
+
+fn default_token_budget() -> usize {
+    DEFAULT_TOKEN_BUDGET
+}
+
+fn default_usages() -> bool {
+    true
+}
+
+/// `symbol.describe`: the context card of a symbol, i.e. everything about
+/// it in one answer.
+///
+/// The card comes from the knowledge graph (see [`ContextCard`]); its usage
+/// patterns come from the references the language server of the symbol's
+/// document finds, if it is running. Without a running server, the card has
+/// no usage patterns.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DescribeSymbolTool;
+
+impl ToolHandler for DescribeSymbolTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "symbol.describe".to_string(),
+            description: "Describes a symbol, given by its fully qualified name or by a position \
+                          in its definition: its signature, body and doc comment locations, its \
+                          inherent and trait methods, constructors, members, implemented traits, \
+                          implementors, usage patterns, callers and related types. Least valuable \
+                          sections are trimmed first to fit the token budget; `truncated` counts \
+                          what was dropped."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "The fully qualified name, e.g. `app::user::User`",
+                    },
+                    "uri": {
+                        "type": "string",
+                        "description": "The URI of the document, together with `position`",
+                    },
+                    "position": position_schema(),
+                    "depth": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": MAX_DEPTH,
+                        "default": DEFAULT_DEPTH,
+                        "description": "How many steps away related types are collected",
+                    },
+                    "tokenBudget": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "default": DEFAULT_TOKEN_BUDGET,
+                    },
+                    "usages": {
+                        "type": "boolean",
+                        "default": true,
+                        "description": "Whether to extract usage patterns from references",
+                    },
+                },
+            }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let arguments = parse_arguments::<DescribeArguments>(arguments)?;
+            let depth = arguments.depth.min(MAX_DEPTH);
+            let engine = &context.engine;
+
+            let card = {
+                let graph = engine.graph();
+                let symbol = match lookup_symbol(
+                    &graph,
+                    arguments.name.as_deref(),
+                    arguments.uri.as_ref(),
+                    arguments.position,
+                )? {
+                    Ok(symbol) => symbol,
+                    Err(not_found) => return Ok(not_found),
+                };
+                ContextCard::new(&graph, symbol.id(), depth)
+            };
+            // The graph was replaced by a new index since the lookup
+            let Some(mut card) = card else {
+                return Err(ContextEngineError::Configuration {
+                    key: None,
+                    message: "The symbol is no longer in the knowledge graph; look it up again"
+                        .to_string(),
+                }
+                .into());
+            };
+
+            if arguments.usages {
+                match usage_patterns(engine, &card.symbol).await {
+                    Ok(patterns) => card = card.with_usage_patterns(patterns),
+                    Err(error) => debug!(%error, "No usage patterns"),
+                }
+            }
+            card.trim_to(arguments.token_budget);
+            let card = serde_json::to_value(card).map_err(ContextEngineError::from)?;
+            Ok(CallToolResult::json(card))
+        })
+    }
+}
+
+/// The arguments of `symbol.rename`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RenameArguments {
+    /// The fully qualified name of the symbol
+    name: Option<String>,
+    /// The document the symbol is named in, with `position`
+    uri: Option<Uri>,
+    /// A position in a name of the symbol
+    position: Option<Position>,
+    /// The new name of the symbol
+    new_name: String,
+    /// Whether to write the rename to disk instead of only previewing it
+    #[serde(default)]
+    apply: bool,
+}
+
+/// `symbol.rename`: renames a symbol in every file of the workspace.
+///
+/// The rename is computed by the language server of the symbol's document
+/// (see [`Renamer`](context_engine_core::edit::Renamer)) and previewed file by
+/// file. With `apply`, it is written to disk: all files, or none.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenameSymbolTool;
+
+impl ToolHandler for RenameSymbolTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "symbol.rename".to_string(),
+            description: "Renames a symbol, given by its fully qualified name or by a position in \
+                          one of its names, across the workspace with its language server. \
+                          Returns every changed file with the ranges of the replaced names and a \
+                          unified diff. Nothing is changed unless `apply` is set; then all files \
+                          are written, or none. Fails if a changed file is out of date with the \
+                          open documents or changed since the workspace was indexed."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "The fully qualified name, e.g. `app::user::User`",
+                    },
+                    "uri": {
+                        "type": "string",
+                        "description": "The URI of the document, together with `position`",
+                    },
+                    "position": position_schema(),
+                    "newName": { "type": "string", "minLength": 1 },
+                    "apply": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Whether to write the rename to disk",
+                    },
+                },
+                "required": ["newName"],
+            }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let arguments = parse_arguments::<RenameArguments>(arguments)?;
+            let engine = &context.engine;
+
+            let (uri, position) = match (&arguments.name, &arguments.uri, arguments.position) {
+                (Some(name), _, _) => {
+                    let symbol = engine.graph().lookup(name).first().map(|s| (*s).clone());
+                    let Some(symbol) = symbol else {
+                        return Ok(CallToolResult::error(
+                            "No symbol found; index the workspace or search with `symbol.search`",
+                            None,
+                        ));
+                    };
+                    let Some(document) = symbol_document(engine, &symbol)? else {
+                        return Ok(CallToolResult::error(
+                            format!("No language is configured for {}", symbol.uri.as_str()),
+                            None,
+                        ));
+                    };
+                    let offset = name_offset(&document, &symbol)?;
+                    let position = document
+                        .line_index()
+                        .position(offset, engine.documents().encoding())
+                        .map_err(ContextEngineError::from)?;
+                    (symbol.uri, position)
+                }
+                (None, Some(uri), Some(position)) => (uri.clone(), position),
+                _ => {
+                    return Err(ToolError::InvalidArguments(
+                        "Expected a `name`, or a `uri` and a `position`".to_string(),
+                    ));
+                }
+            };
+
+            let indexed = engine.indexed_files().clone();
+            let preview = engine
+                .renamer()
+                .preview(&uri, position, &arguments.new_name, &indexed)
+                .await?;
+            if arguments.apply {
+                preview.apply(engine.supervisor())?;
+            }
+            let files = preview
+                .plan
+                .changes()
+                .iter()
+                .map(|change| {
+                    let source = match &change.operation {
+                        FileOperation::Rename { from } => from,
+                        _ => &change.uri,
+                    };
+                    let ranges = preview
+                        .locations_in(source)
+                        .map(|location| location.range)
+                        .collect::<Vec<_>>();
+                    json!({
+                        "uri": change.uri.as_str(),
+                        "operation": change.operation,
+                        "ranges": ranges,
+                        "diff": change.diff(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let result = json!({
+                "server": preview.server,
+                "oldName": preview.old_name,
+                "newName": preview.new_name,
+                "applied": arguments.apply,
+                "files": files,
+            });
+            Ok(CallToolResult::json(result))
+        })
+    }
+}
+
+/// Returns the document that defines a symbol: the open one, or its file
+/// on disk.
+///
+/// # Returns
+///
+/// The document, `None` if the language of the file is not known.
+fn symbol_document(
+    engine: &Engine,
+    symbol: &SymbolNode,
+) -> Result<Option<Arc<TextDocument>>, ContextEngineError> {
+    if let Some(document) = engine.documents().get(&symbol.uri) {
+        return Ok(Some(document));
+    }
+    let path = symbol.uri.to_file_path()?;
+    let text = std::fs::read_to_string(&path).map_err(|e| ContextEngineError::Parse {
+        uri: Some(symbol.uri.clone()),
+        message: format!("cannot read {}: {e}", path.display()),
+    })?;
+    let Some(language_id) = engine.registry().language_id(&symbol.uri, Some(&text)) else {
+        return Ok(None);
+    };
+    Ok(Some(Arc::new(TextDocument::new(
+        symbol.uri.clone(),
+        language_id,
+        0,
+        text,
+        engine.documents().encoding().clone(),
+    ))))
+}
+
+/// Returns the byte offset of the name of a symbol in its signature, or of
+/// the signature if the name is not found in it.
+fn name_offset(document: &TextDocument, symbol: &SymbolNode) -> Result<usize, ContextEngineError> {
+    let start = document.offset(symbol.signature.start)?;
+    let end = document.offset(symbol.signature.end)?;
+    let name = document
+        .text()
+        .get(start..end)
+        .and_then(|signature| find_identifier(signature, &symbol.name))
+        .unwrap_or_default();
+    Ok(start + name)
+}
+
+/// Returns the byte offset of the first occurrence of a name in a text that
+/// stands as a whole identifier, e.g. `b` in `pub b: u8` but not in `pub`.
+fn find_identifier(text: &str, name: &str) -> Option<usize> {
+    let is_identifier = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(name).map(|(at, _)| at).find(|&at| {
+        let before = text.get(..at).and_then(|text| text.chars().next_back());
+        let after = text
+            .get(at + name.len()..)
+            .and_then(|text| text.chars().next());
+        !before.is_some_and(is_identifier) && !after.is_some_and(is_identifier)
+    })
+}
+
+/// Returns the JSON schema of a position in a document.
+pub(super) fn position_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "line": { "type": "integer", "minimum": 0 },
+            "character": { "type": "integer", "minimum": 0 },
+        },
+        "required": ["line", "character"],
+    })
+}
+
+/// Looks up the symbol a tool is about, given by its fully qualified name
+/// or by a position in its definition.
+///
+/// # Returns
+///
+/// The symbol, or the result to answer with if there is none: an error that
+/// suggests symbols with similar names.
+///
+/// # Errors
+///
+/// Returns [`ToolError::InvalidArguments`] if neither a name nor a URI and a
+/// position are given.
+pub(super) fn lookup_symbol<'g>(
+    graph: &'g KnowledgeGraph,
+    name: Option<&str>,
+    uri: Option<&Uri>,
+    position: Option<Position>,
+) -> Result<Result<&'g SymbolNode, CallToolResult>, ToolError> {
+    let symbol = match (name, uri, position) {
+        (Some(name), _, _) => graph.lookup(name).first().copied(),
+        (None, Some(uri), Some(position)) => graph.symbol_at(uri, position),
+        _ => {
+            return Err(ToolError::InvalidArguments(
+                "Expected a `name`, or a `uri` and a `position`".to_string(),
+            ));
+        }
+    };
+    Ok(symbol.ok_or_else(|| {
+        let suggestions = name
+            .map(|name| {
+                let short = name.rsplit(['.', ':']).next().unwrap_or(name);
+                graph.query(&SymbolQuery::named(short).with_limit(SUGGESTIONS))
+            })
+            .unwrap_or_default();
+        CallToolResult::error(
+            "No symbol found; index the workspace or search with `symbol.search`",
+            Some(json!({ "suggestions": suggestions })),
+        )
+    }))
+}
+
+/// Extracts the usage patterns of a symbol from the references the first
+/// running language server of its document finds.
+///
+/// # Returns
+///
+/// The patterns, none if no running server can find references.
+async fn usage_patterns(
+    engine: &Engine,
+    symbol: &SymbolNode,
+) -> Result<Vec<UsagePattern>, ContextEngineError> {
+    let Some((references, encoding)) = find_references(engine, symbol).await? else {
+        return Ok(Vec::new());
+    };
+    let layer = SyntaxLayer::new();
+    layer.sync(engine.documents());
+    let extractor = PatternExtractor::new(&layer, engine.registry(), encoding);
+    Ok(extractor.extract(&references).usage_patterns)
+}
+
+/// Finds the references to a symbol, without its declaration, with the
+/// first running language server of its document that supports it.
+///
+/// # Returns
+///
+/// The references and the position encoding of their ranges, or `None` if
+/// no running server can find references.
+pub(super) async fn find_references(
+    engine: &Engine,
+    symbol: &SymbolNode,
+) -> Result<Option<(Vec<Location>, PositionEncodingKind)>, ContextEngineError> {
+    let Some(document) = symbol_document(engine, symbol)? else {
+        return Ok(None);
+    };
+    let supervisor = engine.supervisor();
+    let routes = engine.registry().route(&symbol.uri, Some(document.text()));
+    let Some(server) = routes
+        .iter()
+        .map(|route| route.server.name.as_str())
+        .find(|name| supervisor.supports(name, References::METHOD))
+    else {
+        return Ok(None);
+    };
+    let encoding = supervisor
+        .client(server)
+        .await?
+        .capabilities()
+        .position_encoding();
+
+    // References are found from the name of the symbol
+    let offset = name_offset(&document, symbol)?;
+    let position = document.line_index().position(offset, &encoding)?;
+    let params = ReferenceParams {
+        text_document_position: TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(symbol.uri.clone()),
+            position,
+        ),
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+        context: ReferenceContext {
+            include_declaration: false,
+        },
+    };
+    let references = supervisor
+        .request::<References>(server, params)
+        .await?
+        .unwrap_or_default();
+    Ok(Some((references, encoding)))
+}
+
+#[cfg(test)]
+#[path = "tests/symbol.rs"]
+mod tests;
@@ -0,0 +1,502 @@
+#![allow(clippy::unwrap_used)]
+
+use std::collections::HashMap;
+
+use context_engine_core::JsonRpcError;
+use context_engine_core::lsp::testing::{FakeDocument, FakeServer, FakeSession};
+use lsp_types::TextDocumentItem;
+use parking_lot::Mutex;
+use proptest::prelude::*;
+
+use super::*;
+use crate::tools::test_support::{self, context};
+
+/// A language server that offers to replace every `todo!()` in the
+/// requested range, resolving the edit lazily, to fix the diagnostics it is
+/// given, to write a `NOTES.md` next to the document, and to run a command.
+#[derive(Default)]
+struct ActionServer {
+    /// The last version and text of every document opened or changed,
+    /// including closed ones
+    documents: Mutex<HashMap<String, FakeDocument>>,
+}
+
+impl FakeServer for ActionServer {
+    fn initialize(&self) -> Value {
+        json!({ "capabilities": {
+            "codeActionProvider": { "resolveProvider": true },
+        } })
+    }
+
+    async fn notify(&self, _session: &FakeSession, method: &str, params: Value) {
+        let uri = params.pointer("/textDocument/uri").and_then(Value::as_str);
+        let version = params
+            .pointer("/textDocument/version")
+            .and_then(Value::as_i64);
+        let text = match method {
+            "textDocument/didOpen" => params.pointer("/textDocument/text"),
+            "textDocument/didChange" => params.pointer("/contentChanges/0/text"),
+            _ => None,
+        };
+        if let (Some(uri), Some(version), Some(text)) = (uri, version, text.and_then(Value::as_str))
+        {
+            let document = FakeDocument {
+                version: version as i32,
+                text: text.to_string(),
+            };
+            self.documents.lock().insert(uri.to_string(), document);
+        }
+    }
+
+    async fn request(
+        &self,
+        _session: &FakeSession,
+        method: &str,
+        params: Value,
+    ) -> Option<Result<Value, JsonRpcError>> {
+        let result = match method {
+            "textDocument/codeAction" => code_actions(&self.documents.lock(), &params),
+            // Actions without data are resolved already
+            "codeAction/resolve" if params.pointer("/data").is_none() => params,
+            "codeAction/resolve" => {
+                let uri = params.pointer("/data/uri").and_then(Value::as_str).unwrap();
+                let line = params.pointer("/data/line").unwrap();
+                let character = params.pointer("/data/character").and_then(Value::as_u64);
+                let character = character.unwrap();
+                let version = self.documents.lock().get(uri).unwrap().version;
+                let mut action = params.clone();
+                action.as_object_mut().unwrap().insert(
+                    "edit".to_string(),
+                    json!({ "documentChanges": [{
+                        "textDocument": { "uri": uri, "version": version },
+                        "edits": [{
+                            "range": {
+                                "start": { "line": line, "character": character },
+                                "end": { "line": line, "character": character + 7 },
+                            },
+                            "newText": "unimplemented!()",
+                        }],
+                    }] }),
+                );
+                action
+            }
+            _ => Value::Null,
+        };
+        Some(Ok(result))
+    }
+}
+
+fn code_actions(documents: &HashMap<String, FakeDocument>, params: &Value) -> Value {
+    let uri = params
+        .pointer("/textDocument/uri")
+        .and_then(Value::as_str)
+        .unwrap();
+    let line = |pointer: &str| params.pointer(pointer).and_then(Value::as_u64).unwrap();
+    let lines = line("/range/start/line")..=line("/range/end/line");
+    let text = &documents.get(uri).unwrap().text;
+
+    let mut actions = Vec::new();
+    for (line, content) in text.lines().enumerate() {
+        let Some(character) = content.find("todo!()") else {
+            continue;
+        };
+        if lines.contains(&(line as u64)) {
+            actions.push(json!({
+                "title": "Replace `todo!()` with `unimplemented!()`",
+                "kind": "quickfix",
+                "data": { "uri": uri, "line": line, "character": character },
+            }));
+        }
+    }
+    let diagnostics = params
+        .pointer("/context/diagnostics")
+        .and_then(Value::as_array);
+    for diagnostic in diagnostics.into_iter().flatten() {
+        let code = diagnostic.pointer("/code").cloned().unwrap_or_default();
+        actions.push(json!({ "title": format!("Fix {code}"), "kind": "quickfix" }));
+    }
+    let (directory, _) = uri.rsplit_once('/').unwrap();
+    let notes = format!("{directory}/NOTES.md");
+    actions.push(json!({
+        "title": "Write notes",
+        "kind": "source",
+        "edit": { "documentChanges": [
+            { "kind": "create", "uri": notes },
+            {
+                "textDocument": { "uri": notes, "version": null },
+                "edits": [{
+                    "range": {
+                        "start": { "line": 0, "character": 0 },
+                        "end": { "line": 0, "character": 0 },
+                    },
+                    "newText": "# Notes\n",
+                }],
+            },
+        ] },
+    }));
+    actions.push(json!({ "title": "Run tests", "command": "tests.run" }));
+    json!(actions)
+}
+
+/// Returns a context with a running fake language server.
+async fn action_context(root: &std::path::Path) -> ToolContext {
+    let (context, _, _) = test_support::running_context(root, ActionServer::default()).await;
+    context
+}
+
+/// Lists the actions for a line and returns the one with a title.
+async fn action(context: &ToolContext, arguments: Value, title: &str) -> Value {
+    let result = ListCodeActionsTool
+        .call(context.clone(), arguments)
+        .await
+        .unwrap();
+    let actions = result.structured_content.unwrap();
+    let actions = actions.pointer("/actions").unwrap().as_array().unwrap();
+    actions
+        .iter()
+        .find(|entry| entry.pointer("/action/title").and_then(Value::as_str) == Some(title))
+        .cloned()
+        .unwrap()
+}
+
+fn line(line: u32) -> Value {
+    json!({
+        "start": { "line": line, "character": 0 },
+        "end": { "line": line, "character": 0 },
+    })
+}
+
+const TODO: &str = "Replace `todo!()` with `unimplemented!()`";
+
+#[tokio::test]
+async fn test_list_resolve_and_apply_unsaved_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let context = action_context(dir.path()).await;
+    let uri = Uri::from_file_path(&dir.path().join("src/user.rs")).unwrap();
+    let content = "fn f() {\n    todo!()\n}\n";
+
+    let entry = action(
+        &context,
+        json!({ "uri": uri, "range": line(1), "content": content }),
+        TODO,
+    )
+    .await;
+    assert_eq!(entry.pointer("/server").unwrap(), "rust-analyzer");
+    let listed = entry.pointer("/action").unwrap().clone();
+    assert!(listed.pointer("/edit").is_none());
+
+    let resolved = ResolveCodeActionTool
+        .call(
+            context.clone(),
+            json!({
+                "uri": uri,
+                "server": "rust-analyzer",
+                "action": listed,
+                "content": content,
+            }),
+        )
+        .await
+        .unwrap()
+        .structured_content
+        .unwrap();
+    assert_eq!(resolved.pointer("/resolved").unwrap(), true);
+    assert_eq!(
+        resolved.pointer("/action/edit/documentChanges/0/edits/0/newText"),
+        Some(&json!("unimplemented!()"))
+    );
+
+    let applied = ApplyCodeActionTool
+        .call(
+            context.clone(),
+            json!({
+                "uri": uri,
+                "server": "rust-analyzer",
+                "action": listed,
+                "content": content,
+                "output": "text",
+            }),
+        )
+        .await
+        .unwrap()
+        .structured_content
+        .unwrap();
+    assert_eq!(
+        applied,
+        json!({
+            "applied": true,
+            "target": "memory",
+            "changes": [{
+                "uri": uri,
+                "operation": { "kind": "modify" },
+                "text": "fn f() {\n    unimplemented!()\n}\n",
+            }],
+        })
+    );
+    // The edited content is opened in the store, not written to disk
+    let document = context.engine.documents().get(&uri).unwrap();
+    assert_eq!(document.text(), "fn f() {\n    unimplemented!()\n}\n");
+    assert_eq!(document.language_id(), "rust");
+}
+
+#[tokio::test]
+async fn test_apply_to_open_document() {
+    let dir = tempfile::tempdir().unwrap();
+    let context = action_context(dir.path()).await;
+    let path = dir.path().join("src/lib.rs");
+    let uri = Uri::from_file_path(&path).unwrap();
+    context
+        .engine
+        .documents()
+        .open(TextDocumentItem::new(
+            uri.clone(),
+            "rust".to_string(),
+            3,
+            "fn g() {\n    todo!()\n}\n".to_string(),
+        ))
+        .unwrap();
+
+    let entry = action(&context, json!({ "uri": uri, "range": line(1) }), TODO).await;
+    // The server resolves the edit for the version of the overlay
+    let applied = ApplyCodeActionTool
+        .call(
+            context.clone(),
+            json!({ "uri": uri, "action": entry.pointer("/action").unwrap() }),
+        )
+        .await
+        .unwrap()
+        .structured_content
+        .unwrap();
+    let path = path.display();
+    assert_eq!(
+        applied.pointer("/changes/0/diff").unwrap(),
+        &format!(
+            "--- {path}\n+++ {path}\n@@ -1,3 +1,3 @@\n fn g() {{\n-    todo!()\n+    \
+             unimplemented!()\n }}\n",
+        )
+    );
+    let document = context.engine.documents().get(&uri).unwrap();
+    assert_eq!(document.version(), 4);
+    assert_eq!(document.text(), "fn g() {\n    unimplemented!()\n}\n");
+}
+
+#[tokio::test]
+async fn test_dry_run_and_apply_resource_operations_to_disk() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/lib.rs"), "fn h() {}\n").unwrap();
+    let context = action_context(dir.path()).await;
+    let uri = Uri::from_file_path(&dir.path().join("src/lib.rs")).unwrap();
+    let notes = dir.path().join("src/NOTES.md");
+
+    let entry = action(
+        &context,
+        json!({ "uri": uri, "range": line(0) }),
+        "Write notes",
+    )
+    .await;
+    let mut arguments = json!({
+        "uri": uri,
+        "action": entry.pointer("/action").unwrap(),
+        "target": "disk",
+        "dryRun": true,
+    });
+    let preview = ApplyCodeActionTool
+        .call(context.clone(), arguments.clone())
+        .await
+        .unwrap()
+        .structured_content
+        .unwrap();
+    assert_eq!(preview.pointer("/applied").unwrap(), false);
+    assert_eq!(
+        preview.pointer("/changes/0/operation/kind").unwrap(),
+        "create"
+    );
+    let diff = preview.pointer("/changes/0/diff").and_then(Value::as_str);
+    assert!(diff.unwrap().starts_with("--- /dev/null\n"));
+    assert!(!notes.exists());
+
+    arguments
+        .as_object_mut()
+        .unwrap()
+        .insert("dryRun".to_string(), json!(false));
+    ApplyCodeActionTool
+        .call(context.clone(), arguments.clone())
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read_to_string(&notes).unwrap(), "# Notes\n");
+    assert!(context.engine.documents().is_empty());
+
+    // The file exists now
+    let error = ApplyCodeActionTool
+        .call(context.clone(), arguments)
+        .await
+        .unwrap_err();
+    let ToolError::Engine(error) = error else {
+        unreachable!("expected an engine error, got {error}");
+    };
+    assert!(matches!(error, ContextEngineError::Edit { .. }));
+}
+
+#[tokio::test]
+async fn test_list_for_diagnostics() {
+    let context = action_context(std::path::Path::new("/workspace")).await;
+    let diagnostic = json!({
+        "range": {
+            "start": { "line": 0, "character": 3 },
+            "end": { "line": 0, "character": 4 },
+        },
+        "severity": "error",
+        "code": "E0425",
+        "message": "cannot find value `x`",
+        "server": "rust-analyzer",
+    });
+    let entry = action(
+        &context,
+        json!({
+            "uri": "file:///workspace/src/new.rs",
+            "content": "fn x() {}\n",
+            "diagnostics": [diagnostic],
+        }),
+        "Fix \"E0425\"",
+    )
+    .await;
+    assert_eq!(entry.pointer("/action/kind").unwrap(), "quickfix");
+}
+
+#[tokio::test]
+async fn test_command_only_action_is_not_applied() {
+    let context = action_context(std::path::Path::new("/workspace")).await;
+    let error = ApplyCodeActionTool
+        .call(
+            context,
+            json!({
+                "uri": "file:///workspace/src/new.rs",
+                "content": "fn x() {}\n",
+                "action": {
+                    "title": "Run tests",
+                    "command": { "title": "Run tests", "command": "tests.run" },
+                },
+            }),
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("only runs the command `tests.run`")
+    );
+}
+
+#[tokio::test]
+async fn test_apply_workspace_edit() {
+    let dir = tempfile::tempdir().unwrap();
+    let context = context(Engine::new(dir.path()));
+    let path = dir.path().join("src/lib.rs");
+    let uri = Uri::from_file_path(&path).unwrap();
+    let edit = json!({ "changes": { uri.as_str(): [{
+        "range": {
+            "start": { "line": 0, "character": 3 },
+            "end": { "line": 0, "character": 4 },
+        },
+        "newText": "renamed",
+    }] } });
+    let applied = ApplyCodeActionTool
+        .call(
+            context.clone(),
+            json!({ "uri": uri, "edit": edit, "content": "fn f() {}\n", "dryRun": true }),
+        )
+        .await
+        .unwrap()
+        .structured_content
+        .unwrap();
+    let path = path.display();
+    assert_eq!(
+        applied.pointer("/changes/0/diff").unwrap(),
+        &format!("--- {path}\n+++ {path}\n@@ -1 +1 @@\n-fn f() {{}}\n+fn renamed() {{}}\n")
+    );
+    assert!(context.engine.documents().is_empty());
+
+    // Files outside of the workspace are neither edited nor previewed
+    let outside = json!({ "documentChanges": [
+        { "kind": "create", "uri": "file:///etc/context-engine-test.rs" },
+    ] });
+    for dry_run in [true, false] {
+        let error = ApplyCodeActionTool
+            .call(
+                context.clone(),
+                json!({ "edit": outside, "dryRun": dry_run }),
+            )
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("outside of the workspace"));
+    }
+    assert!(context.engine.documents().is_empty());
+}
+
+#[tokio::test]
+async fn test_invalid_arguments() {
+    let context = context(Engine::new("/workspace"));
+    let uri = "file:///workspace/src/lib.rs";
+    let error = ListCodeActionsTool
+        .call(context.clone(), json!({ "uri": uri, "content": "" }))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ToolError::InvalidArguments(_)));
+
+    let error = ApplyCodeActionTool
+        .call(context.clone(), json!({ "uri": uri }))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ToolError::InvalidArguments(_)));
+
+    let error = ApplyCodeActionTool
+        .call(context, json!({ "action": { "title": "Fix" } }))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ToolError::InvalidArguments(_)));
+}
+
+// Property-based tests
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn prop_one_fix_per_todo_in_range(
+        todos in proptest::collection::vec(any::<bool>(), 1..8),
+        start in 0u32..8,
+    ) {
+        let content = todos
+            .iter()
+            .map(|todo| if *todo { "    todo!()" } else { "    let x = 1;" })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let end = todos.len() as u32 - 1;
+        let start = start.min(end);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let actions = runtime.block_on(async {
+            let context = action_context(std::path::Path::new("/workspace")).await;
+            let range = json!({
+                "start": { "line": start, "character": 0 },
+                "end": { "line": end, "character": 0 },
+            });
+            let arguments = json!({
+                "uri": "file:///workspace/src/lib.rs",
+                "content": content,
+                "range": range,
+                "only": ["quickfix"],
+            });
+            let result = ListCodeActionsTool.call(context.clone(), arguments).await.unwrap();
+            context.engine.supervisor().stop_all().await;
+            result.structured_content.unwrap()
+        });
+        let fixes = actions
+            .pointer("/actions")
+            .and_then(Value::as_array)
+            .unwrap()
+            .iter()
+            .filter(|entry| entry.pointer("/action/title") == Some(&json!(TODO)))
+            .count();
+        let expected = todos.iter().skip(start as usize).filter(|todo| **todo).count();
+        prop_assert_eq!(fixes, expected);
+    }
+}
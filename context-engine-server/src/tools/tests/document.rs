@@ -0,0 +1,152 @@
+#![allow(clippy::unwrap_used)]
+
+use std::path::Path;
+
+use context_engine_core::JsonRpcError;
+use context_engine_core::lsp::testing::{FakeServer, FakeSession};
+use proptest::prelude::*;
+
+use super::*;
+use crate::Engine;
+use crate::mcp::ToolError;
+use crate::tools::test_support::{context, running_context};
+
+/// A language server with pull diagnostics that reports every `todo!()`
+/// of the documents it has open.
+struct PullDiagnostics;
+
+impl FakeServer for PullDiagnostics {
+    fn initialize(&self) -> Value {
+        json!({ "capabilities": { "diagnosticProvider": {
+            "interFileDependencies": false,
+            "workspaceDiagnostics": false,
+        } } })
+    }
+
+    async fn request(
+        &self,
+        session: &FakeSession,
+        method: &str,
+        params: Value,
+    ) -> Option<Result<Value, JsonRpcError>> {
+        if method != "textDocument/diagnostic" {
+            return Some(Ok(Value::Null));
+        }
+        let uri = params.pointer("/textDocument/uri").and_then(Value::as_str);
+        let text = uri.and_then(|uri| session.text(uri));
+        let items = text
+            .unwrap_or_default()
+            .lines()
+            .enumerate()
+            .filter_map(|(line, text)| {
+                let character = text.find("todo!()")?;
+                Some(json!({
+                    "range": {
+                        "start": { "line": line, "character": character },
+                        "end": { "line": line, "character": character + 7 },
+                    },
+                    "severity": 2,
+                    "code": "todo",
+                    "source": "fake",
+                    "message": "`todo!()` left in code",
+                }))
+            })
+            .collect::<Vec<_>>();
+        Some(Ok(json!({ "kind": "full", "items": items })))
+    }
+}
+
+/// Returns a context over `/workspace` with a running fake language server.
+async fn diagnosing_context() -> ToolContext {
+    let (context, _, _) = running_context(Path::new("/workspace"), PullDiagnostics).await;
+    context
+}
+
+#[tokio::test]
+async fn test_analyze_content() {
+    let context = diagnosing_context().await;
+    let result = AnalyzeContentTool
+        .call(
+            context.clone(),
+            json!({
+                "uri": "file:///workspace/src/user.rs",
+                "content": "fn f() {\n    todo!()\n}\n",
+            }),
+        )
+        .await
+        .unwrap();
+    assert!(!result.is_error);
+    let analysis = result.structured_content.unwrap();
+    assert_eq!(
+        analysis.pointer("/diagnostics/0").unwrap(),
+        &json!({
+            "range": {
+                "start": { "line": 1, "character": 4 },
+                "end": { "line": 1, "character": 11 },
+            },
+            "severity": "warning",
+            "code": "todo",
+            "source": "fake",
+            "message": "`todo!()` left in code",
+            "server": "rust-analyzer",
+        })
+    );
+    assert_eq!(
+        analysis.pointer("/summary").unwrap(),
+        &json!({ "errors": 0, "warnings": 1, "settled": true })
+    );
+    assert_eq!(analysis.pointer("/servers/0/mode").unwrap(), "pull");
+    // Nothing was opened in the workspace
+    assert!(context.engine.documents().is_empty());
+}
+
+#[tokio::test]
+async fn test_analyze_without_server() {
+    let error = AnalyzeContentTool
+        .call(
+            context(Engine::new("/workspace")),
+            json!({ "uri": "file:///workspace/src/user.rs", "content": "" }),
+        )
+        .await
+        .unwrap_err();
+    let ToolError::Engine(error) = error else {
+        unreachable!("expected an engine error, got {error}");
+    };
+    assert!(matches!(error, ContextEngineError::Configuration { .. }));
+}
+
+#[tokio::test]
+async fn test_analyze_invalid_arguments() {
+    let error = AnalyzeContentTool
+        .call(
+            context(Engine::new("/workspace")),
+            json!({ "uri": "file:///workspace/src/user.rs" }),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ToolError::InvalidArguments(_)));
+}
+
+// Property-based tests
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn prop_every_todo_is_reported(todos in proptest::collection::vec(any::<bool>(), 0..8)) {
+        let content = todos
+            .iter()
+            .map(|todo| if *todo { "    todo!()" } else { "    let x = 1;" })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let analysis = runtime.block_on(async {
+            let context = diagnosing_context().await;
+            let arguments = json!({ "uri": "file:///workspace/src/lib.rs", "content": content });
+            let result = AnalyzeContentTool.call(context.clone(), arguments).await.unwrap();
+            context.engine.supervisor().stop_all().await;
+            result.structured_content.unwrap()
+        });
+        let warnings = todos.iter().filter(|todo| **todo).count();
+        prop_assert_eq!(analysis.pointer("/summary/warnings"), Some(&json!(warnings)));
+    }
+}
@@ -0,0 +1,357 @@
+#![allow(clippy::unwrap_used)]
+
+use std::path::Path;
+
+use context_engine_core::JsonRpcError;
+use context_engine_core::graph::{KnowledgeGraph, NodeKind};
+use context_engine_core::lsp::LspSupervisor;
+use context_engine_core::lsp::testing::{FakeServer, FakeSession};
+use proptest::prelude::*;
+
+use super::*;
+use crate::mcp::ToolError;
+use crate::tools::test_support::{context, running_context};
+
+const LIB_RS: &str = "pub fn parse() {}\n\npub fn load() { parse(); }\n\nfn main() {\n\x20   let \
+                      _ = parse;\n}\n#[test]\nfn loads() { load(); }\n";
+
+/// A language server with UTF-8 positions that finds the references of
+/// `parse` and `load` in `lib.rs`.
+struct ReferencesServer {
+    lib: Uri,
+}
+
+impl FakeServer for ReferencesServer {
+    fn initialize(&self) -> Value {
+        json!({ "capabilities": {
+            "positionEncoding": "utf-8",
+            "referencesProvider": true,
+        } })
+    }
+
+    async fn request(
+        &self,
+        _session: &FakeSession,
+        method: &str,
+        params: Value,
+    ) -> Option<Result<Value, JsonRpcError>> {
+        let reference = |line: u32, start: u32, end: u32| {
+            Location::new(
+                self.lib.clone(),
+                Range::new(Position::new(line, start), Position::new(line, end)),
+            )
+        };
+        let result = match method {
+            "textDocument/references" => {
+                match params.pointer("/position/line").and_then(Value::as_u64) {
+                    Some(0) => json!([reference(2, 16, 21), reference(5, 12, 17)]),
+                    Some(2) => json!([reference(8, 13, 17)]),
+                    _ => json!([]),
+                }
+            }
+            _ => Value::Null,
+        };
+        Some(Ok(result))
+    }
+}
+
+/// Writes `lib.rs` and returns a context whose graph knows its functions
+/// and the calls between them, with a running fake language server.
+async fn lib_context(root: &Path) -> (ToolContext, Arc<LspSupervisor>) {
+    std::fs::write(root.join("lib.rs"), LIB_RS).unwrap();
+    let lib = Uri::from_file_path(&root.join("lib.rs")).unwrap();
+    let server = ReferencesServer { lib: lib.clone() };
+    let (context, _, supervisor) = running_context(root, server).await;
+
+    let line = |line, end| Range::new(Position::new(line, 0), Position::new(line, end));
+    let function = |name: &str, signature| {
+        SymbolNode::new(
+            NodeKind::Function,
+            name,
+            format!("app::{name}"),
+            lib.clone(),
+            signature,
+        )
+    };
+    let mut graph = KnowledgeGraph::new();
+    let parse = graph.add_node(function("parse", line(0, 14)));
+    let load = graph.add_node(
+        function("load", line(2, 13))
+            .with_body(Range::new(Position::new(2, 14), Position::new(2, 26))),
+    );
+    graph.add_node(
+        function("main", line(4, 9))
+            .with_body(Range::new(Position::new(4, 10), Position::new(6, 1))),
+    );
+    let loads = graph.add_node(
+        function("loads", line(8, 10))
+            .with_body(Range::new(Position::new(8, 11), Position::new(8, 22))),
+    );
+    graph.add_edge(load, EdgeKind::Calls, parse);
+    graph.add_edge(loads, EdgeKind::Calls, load);
+
+    context.engine.set_graph(graph);
+    (context, supervisor)
+}
+
+fn names(value: &Value) -> Vec<&str> {
+    value
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| symbol.pointer("/qualified_name").unwrap().as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_impact_with_references() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let (context, supervisor) = lib_context(&root).await;
+
+    let result = ImpactAnalysisTool
+        .call(context.clone(), json!({ "name": "app::parse" }))
+        .await
+        .unwrap();
+    let tree = result.structured_content.unwrap();
+    assert_eq!(
+        names(tree.pointer("/root/children").unwrap()),
+        ["app::load", "app::main"]
+    );
+    // The call found in the graph and the reference at the same place
+    let load = tree.pointer("/root/children/0").unwrap();
+    assert_eq!(load.pointer("/via").unwrap(), "calls");
+    assert_eq!(load.pointer("/sites/0/range/start/line").unwrap(), 2);
+    assert_eq!(names(load.pointer("/children").unwrap()), ["app::loads"]);
+    let main = tree.pointer("/root/children/1").unwrap();
+    assert_eq!(main.pointer("/via").unwrap(), "references");
+    assert_eq!(
+        main.pointer("/sites/0/range").unwrap(),
+        &json!({
+            "start": { "line": 5, "character": 12 },
+            "end": { "line": 5, "character": 17 },
+        })
+    );
+    assert_eq!(tree.pointer("/affected").unwrap(), 3);
+    assert_eq!(names(tree.pointer("/public_api").unwrap()), ["app::load"]);
+    assert_eq!(names(tree.pointer("/tests").unwrap()), ["app::loads"]);
+    assert_eq!(tree.pointer("/references_truncated").unwrap(), false);
+
+    let result = ImpactAnalysisTool
+        .call(
+            context,
+            json!({ "name": "app::parse", "references": false, "maxDepth": 1 }),
+        )
+        .await
+        .unwrap();
+    let tree = result.structured_content.unwrap();
+    assert_eq!(
+        names(tree.pointer("/root/children").unwrap()),
+        ["app::load"]
+    );
+    assert_eq!(tree.pointer("/truncated").unwrap(), true);
+    supervisor.stop_all().await;
+}
+
+#[tokio::test]
+async fn test_references_are_shared_and_capped() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let (context, supervisor) = lib_context(&root).await;
+    let engine = &context.engine;
+    let id = |name: &str| engine.graph().lookup(name).first().unwrap().id();
+    let config = ImpactConfig::default();
+
+    // Every symbol of the tree of `parse` is asked for once
+    let mut cache = ReferenceCache::new(MAX_REFERENCE_REQUESTS);
+    let tree = impact(engine, id("app::parse"), config, Some(&mut cache))
+        .await
+        .unwrap();
+    assert_eq!(tree.affected, 3);
+    assert_eq!(MAX_REFERENCE_REQUESTS - cache.remaining, 4);
+    // The dependents of `load` are known already
+    let tree = impact(engine, id("app::load"), config, Some(&mut cache))
+        .await
+        .unwrap();
+    assert_eq!(tree.affected, 1);
+    assert_eq!(MAX_REFERENCE_REQUESTS - cache.remaining, 4);
+    assert!(!cache.truncated);
+
+    // Without requests, `main` is never found
+    let mut cache = ReferenceCache::new(0);
+    let tree = impact(engine, id("app::parse"), config, Some(&mut cache))
+        .await
+        .unwrap();
+    assert_eq!(
+        names(&serde_json::to_value(&tree.root.children).unwrap()),
+        ["app::load"]
+    );
+    assert_eq!(cache.remaining, 0);
+    assert!(cache.truncated);
+    supervisor.stop_all().await;
+}
+
+#[tokio::test]
+async fn test_impact_of_unknown_symbol() {
+    let context = context(Engine::new("/workspace"));
+    let result = ImpactAnalysisTool
+        .call(context.clone(), json!({ "name": "app::parse" }))
+        .await
+        .unwrap();
+    assert!(result.is_error);
+
+    let error = ImpactAnalysisTool
+        .call(context, json!({ "name": "app::parse", "depth": 2 }))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ToolError::InvalidArguments(_)));
+}
+
+/// Runs git in a folder, failing the test if it fails.
+fn run_git(root: &Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args([
+            "-c",
+            "commit.gpgsign=false",
+            "-c",
+            "init.defaultBranch=main",
+        ])
+        .args(args)
+        .current_dir(root)
+        .stdout(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {args:?} failed");
+}
+
+#[tokio::test]
+async fn test_diff_impact() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let (context, supervisor) = lib_context(&root).await;
+    std::fs::write(root.join("README.md"), "# App\n").unwrap();
+    run_git(&root, &["init", "--quiet"]);
+    run_git(&root, &["add", "."]);
+    run_git(&root, &["commit", "--quiet", "-m", "first"]);
+
+    let result = DiffImpactTool
+        .call(context.clone(), json!({}))
+        .await
+        .unwrap();
+    let report = result.structured_content.unwrap();
+    assert_eq!(report.pointer("/changes").unwrap(), &json!([]));
+
+    std::fs::write(
+        root.join("lib.rs"),
+        LIB_RS.replacen("pub fn parse() {}", "pub fn parse() { todo!() }", 1),
+    )
+    .unwrap();
+    std::fs::write(root.join("README.md"), "# App\n\nDocs\n").unwrap();
+    let result = DiffImpactTool
+        .call(context.clone(), json!({}))
+        .await
+        .unwrap();
+    let report = result.structured_content.unwrap();
+    assert_eq!(report.pointer("/files").unwrap(), 2);
+    assert_eq!(report.pointer("/unmapped").unwrap(), &json!(["README.md"]));
+    let changes = report.pointer("/changes").unwrap().as_array().unwrap();
+    assert_eq!(changes.len(), 1);
+    let change = changes.first().unwrap();
+    assert_eq!(
+        change.pointer("/symbol/qualified_name").unwrap(),
+        "app::parse"
+    );
+    assert_eq!(change.pointer("/ranges/0/start/line").unwrap(), 0);
+    let dependents = change
+        .pointer("/dependents")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|dependent| {
+            (
+                dependent
+                    .pointer("/symbol/qualified_name")
+                    .unwrap()
+                    .as_str()
+                    .unwrap(),
+                dependent.pointer("/depends_on").unwrap().as_str().unwrap(),
+                dependent.pointer("/depth").unwrap().as_u64().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        dependents,
+        [
+            ("app::load", "app::parse", 1),
+            ("app::loads", "app::load", 2),
+            ("app::main", "app::parse", 1),
+        ]
+    );
+    assert_eq!(names(change.pointer("/tests").unwrap()), ["app::loads"]);
+    assert_eq!(names(report.pointer("/tests").unwrap()), ["app::loads"]);
+    assert_eq!(report.pointer("/references_truncated").unwrap(), false);
+
+    // Committed changes are compared between revisions
+    run_git(&root, &["commit", "--quiet", "-am", "second"]);
+    let result = DiffImpactTool
+        .call(
+            context.clone(),
+            json!({ "base": "HEAD~1", "head": "HEAD", "references": false }),
+        )
+        .await
+        .unwrap();
+    let report = result.structured_content.unwrap();
+    assert_eq!(
+        report
+            .pointer("/changes/0/dependents")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    let error = DiffImpactTool
+        .call(context, json!({ "base": "missing" }))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        ToolError::Engine(ContextEngineError::Configuration { .. })
+    ));
+    supervisor.stop_all().await;
+}
+
+// Property-based tests
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn prop_limits_are_capped(max_depth in 0usize..100, max_nodes in 0usize..5000) {
+        let arguments = serde_json::from_value::<ImpactArguments>(json!({
+            "name": "app::parse",
+            "maxDepth": max_depth,
+            "maxNodes": max_nodes,
+        }))
+        .unwrap();
+        let config = arguments.config();
+        prop_assert_eq!(config.max_depth, max_depth.min(MAX_DEPTH));
+        prop_assert_eq!(config.max_nodes, max_nodes.min(MAX_NODES));
+    }
+
+    #[test]
+    fn prop_diff_targets(base in "[a-z]{1,8}", head in proptest::option::of("[a-z]{1,8}")) {
+        let arguments = serde_json::from_value::<DiffImpactArguments>(json!({
+            "base": base,
+            "head": head,
+        }))
+        .unwrap();
+        let expected = match head {
+            Some(head) => DiffTarget::Revisions { base, head },
+            None => DiffTarget::WorkingTree { base },
+        };
+        prop_assert_eq!(arguments.target(), expected);
+    }
+}
@@ -0,0 +1,40 @@
+//! Helpers shared by the tests of the tools.
+
+#![allow(clippy::unwrap_used)]
+
+use std::path::Path;
+use std::sync::Arc;
+
+use context_engine_core::document::DocumentStore;
+use context_engine_core::lsp::testing::{self, FakeLauncher, FakeServer};
+use context_engine_core::lsp::{LanguageRegistry, LspSupervisor};
+use tokio::sync::mpsc;
+
+use crate::Engine;
+use crate::mcp::{Progress, ToolContext};
+
+/// Returns a context over an engine that drops its progress notifications.
+pub(crate) fn context(engine: Engine) -> ToolContext {
+    let (notifications, _) = mpsc::unbounded_channel();
+    ToolContext {
+        engine: Arc::new(engine),
+        progress: Progress::new(None, notifications),
+    }
+}
+
+/// Returns a context whose engine runs rust-analyzer on a fake server,
+/// with the launcher and the supervisor of the server.
+pub(crate) async fn running_context<S: FakeServer>(
+    root: &Path,
+    server: S,
+) -> (ToolContext, FakeLauncher<S>, Arc<LspSupervisor>) {
+    let documents = Arc::new(DocumentStore::default());
+    let registry = Arc::new(LanguageRegistry::with_defaults());
+    let launcher = FakeLauncher::new(server);
+    let config = registry.server("rust-analyzer").unwrap().clone();
+    let supervisor = testing::start(root, Arc::clone(&documents), launcher.clone(), config)
+        .await
+        .unwrap();
+    let engine = Engine::with_supervisor(root, documents, registry, Arc::clone(&supervisor));
+    (context(engine), launcher, supervisor)
+}
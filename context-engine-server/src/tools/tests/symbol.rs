@@ -0,0 +1,510 @@
+#![allow(clippy::unwrap_used)]
+
+use context_engine_core::JsonRpcError;
+use context_engine_core::cache::content_hash;
+use context_engine_core::graph::{EdgeKind, NodeKind};
+use context_engine_core::lsp::LspSupervisor;
+use context_engine_core::lsp::testing::{FakeLauncher, FakeServer, FakeSession};
+use context_engine_core::types::Range;
+use proptest::prelude::*;
+
+use super::*;
+use crate::tools::test_support::{self, running_context};
+
+fn context(names: &[(NodeKind, &str)]) -> ToolContext {
+    let uri = "file:///workspace/src/user.rs".parse::<Uri>().unwrap();
+    let range = Range::new(Position::new(0, 0), Position::new(0, 1));
+    let mut graph = KnowledgeGraph::new();
+    for (kind, qualified_name) in names {
+        let name = qualified_name.rsplit("::").next().unwrap();
+        graph.add_node(SymbolNode::new(
+            *kind,
+            name,
+            *qualified_name,
+            uri.clone(),
+            range,
+        ));
+    }
+    let engine = Engine::new("/workspace");
+    engine.set_graph(graph);
+    test_support::context(engine)
+}
+
+fn names(result: &CallToolResult) -> Vec<String> {
+    result
+        .structured_content
+        .as_ref()
+        .unwrap()
+        .pointer("/symbols")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| {
+            symbol
+                .pointer("/qualified_name")
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_search_by_name_and_kind() {
+    let context = context(&[
+        (NodeKind::Module, "app::user"),
+        (NodeKind::Type, "app::user::User"),
+        (NodeKind::Method, "app::user::User::name"),
+        (NodeKind::Function, "app::greet"),
+    ]);
+
+    let result = SearchSymbolsTool
+        .call(context.clone(), json!({ "name": "user" }))
+        .await
+        .unwrap();
+    assert_eq!(names(&result).len(), 3);
+
+    let result = SearchSymbolsTool
+        .call(context, json!({ "name": "USER", "kinds": ["type"] }))
+        .await
+        .unwrap();
+    assert_eq!(names(&result), ["app::user::User"]);
+}
+
+#[tokio::test]
+async fn test_search_limit() {
+    let context = context(&[
+        (NodeKind::Function, "app::a"),
+        (NodeKind::Function, "app::b"),
+        (NodeKind::Function, "app::c"),
+    ]);
+    let result = SearchSymbolsTool
+        .call(context, json!({ "limit": 2 }))
+        .await
+        .unwrap();
+    assert_eq!(names(&result).len(), 2);
+}
+
+#[tokio::test]
+async fn test_search_invalid_kind() {
+    let context = context(&[]);
+    let error = SearchSymbolsTool
+        .call(context, json!({ "kinds": ["class"] }))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ToolError::InvalidArguments(_)));
+}
+
+/// A graph of a `User` type with two methods, a trait and a caller.
+fn describe_context() -> ToolContext {
+    let context = context(&[]);
+    let uri = "file:///workspace/src/user.rs".parse::<Uri>().unwrap();
+    let line = |line| Range::new(Position::new(line, 0), Position::new(line, 20));
+    let mut graph = KnowledgeGraph::new();
+    let mut add = |kind, name: &str, qualified_name: &str, line_number| {
+        graph.add_node(SymbolNode::new(
+            kind,
+            name,
+            qualified_name,
+            uri.clone(),
+            line(line_number),
+        ))
+    };
+    let user = add(NodeKind::Type, "User", "app::User", 0);
+    let new = add(NodeKind::Method, "new", "app::User::new", 2);
+    let validate = add(NodeKind::Method, "validate", "app::User::validate", 4);
+    let display = add(NodeKind::Trait, "Display", "app::Display", 6);
+    let main = add(NodeKind::Function, "main", "app::main", 8);
+    graph.add_edge(user, EdgeKind::Defines, new);
+    graph.add_edge(user, EdgeKind::Defines, validate);
+    graph.add_edge(user, EdgeKind::Implements, display);
+    graph.add_edge(main, EdgeKind::Calls, validate);
+    context.engine.set_graph(graph);
+    context
+}
+
+#[tokio::test]
+async fn test_describe_by_name() {
+    let result = DescribeSymbolTool
+        .call(
+            describe_context(),
+            json!({ "name": "app::User", "usages": false }),
+        )
+        .await
+        .unwrap();
+    assert!(!result.is_error);
+    let card = result.structured_content.unwrap();
+    assert_eq!(card.pointer("/symbol/qualified_name").unwrap(), "app::User");
+    assert_eq!(
+        card.pointer("/methods/1/qualified_name").unwrap(),
+        "app::User::validate"
+    );
+    assert_eq!(
+        card.pointer("/implements/0/qualified_name").unwrap(),
+        "app::Display"
+    );
+    assert!(card.get("truncated").is_none());
+}
+
+#[tokio::test]
+async fn test_describe_by_position() {
+    let result = DescribeSymbolTool
+        .call(
+            describe_context(),
+            json!({
+                "uri": "file:///workspace/src/user.rs",
+                "position": { "line": 4, "character": 3 },
+            }),
+        )
+        .await
+        .unwrap();
+    let card = result.structured_content.unwrap();
+    assert_eq!(
+        card.pointer("/symbol/qualified_name").unwrap(),
+        "app::User::validate"
+    );
+    assert_eq!(
+        card.pointer("/defined_in/qualified_name").unwrap(),
+        "app::User"
+    );
+    assert_eq!(
+        card.pointer("/used_by/0/symbol/qualified_name").unwrap(),
+        "app::main"
+    );
+}
+
+#[tokio::test]
+async fn test_describe_trims_to_budget() {
+    let result = DescribeSymbolTool
+        .call(
+            describe_context(),
+            json!({ "name": "app::User", "tokenBudget": 1, "usages": false }),
+        )
+        .await
+        .unwrap();
+    let card = result.structured_content.unwrap();
+    assert!(card.get("methods").is_none());
+    assert_eq!(card.pointer("/truncated/methods").unwrap(), 2);
+    assert_eq!(card.pointer("/symbol/name").unwrap(), "User");
+}
+
+#[tokio::test]
+async fn test_describe_unknown_symbol() {
+    let result = DescribeSymbolTool
+        .call(describe_context(), json!({ "name": "app::model::User" }))
+        .await
+        .unwrap();
+    assert!(result.is_error);
+    let suggestions = result.structured_content.unwrap();
+    assert_eq!(
+        suggestions
+            .pointer("/suggestions/0/qualified_name")
+            .unwrap(),
+        "app::User"
+    );
+
+    let error = DescribeSymbolTool
+        .call(
+            describe_context(),
+            json!({ "uri": "file:///workspace/src/user.rs" }),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ToolError::InvalidArguments(_)));
+    let error = DescribeSymbolTool
+        .call(describe_context(), json!({ "name": "User", "budget": 10 }))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ToolError::InvalidArguments(_)));
+}
+
+const USER_RS: &str = "pub struct User;\n";
+
+const MAIN_RS: &str =
+    "fn main() {\n    let a = User::new(1);\n    let b = User::new(2);\n    a.validate();\n}\n";
+
+/// A language server that finds the references of `User` in `main.rs`,
+/// and renames it in both files.
+struct ReferencesServer {
+    main: Uri,
+}
+
+impl FakeServer for ReferencesServer {
+    fn initialize(&self) -> Value {
+        json!({ "capabilities": {
+            "referencesProvider": true,
+            "renameProvider": true,
+        } })
+    }
+
+    async fn request(
+        &self,
+        _session: &FakeSession,
+        method: &str,
+        params: Value,
+    ) -> Option<Result<Value, JsonRpcError>> {
+        let result = match method {
+            "textDocument/references" => {
+                let reference = |line| {
+                    json!({
+                        "uri": self.main.as_str(),
+                        "range": {
+                            "start": { "line": line, "character": 12 },
+                            "end": { "line": line, "character": 16 },
+                        },
+                    })
+                };
+                json!([reference(1), reference(2)])
+            }
+            "textDocument/rename" => {
+                let user = params
+                    .pointer("/textDocument/uri")
+                    .unwrap()
+                    .as_str()
+                    .unwrap();
+                let edit = |line, character| {
+                    json!({
+                        "range": {
+                            "start": { "line": line, "character": character },
+                            "end": { "line": line, "character": character + 4 },
+                        },
+                        "newText": params.pointer("/newName").unwrap(),
+                    })
+                };
+                json!({ "changes": {
+                    user: [edit(0, 11)],
+                    self.main.as_str(): [edit(1, 12), edit(2, 12)],
+                } })
+            }
+            _ => Value::Null,
+        };
+        Some(Ok(result))
+    }
+}
+
+/// Writes `user.rs` and `main.rs` to a crate and returns a context whose
+/// graph knows `app::User`, with a running fake language server.
+async fn user_context(
+    root: &std::path::Path,
+) -> (
+    ToolContext,
+    FakeLauncher<ReferencesServer>,
+    Arc<LspSupervisor>,
+) {
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(root.join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+    std::fs::write(root.join("src/user.rs"), USER_RS).unwrap();
+    std::fs::write(root.join("src/main.rs"), MAIN_RS).unwrap();
+
+    let server = ReferencesServer {
+        main: file_uri(root, "main.rs"),
+    };
+    let (context, launcher, supervisor) = running_context(root, server).await;
+    let mut graph = KnowledgeGraph::new();
+    graph.add_node(SymbolNode::new(
+        NodeKind::Type,
+        "User",
+        "app::User",
+        file_uri(root, "user.rs"),
+        Range::new(Position::new(0, 0), Position::new(0, 15)),
+    ));
+    context.engine.set_graph(graph);
+    (context, launcher, supervisor)
+}
+
+fn file_uri(root: &std::path::Path, name: &str) -> Uri {
+    format!("file://{}/src/{name}", root.display())
+        .parse::<Uri>()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_describe_extracts_usage_patterns() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let (context, launcher, supervisor) = user_context(&root).await;
+
+    let result = DescribeSymbolTool
+        .call(context, json!({ "name": "app::User" }))
+        .await
+        .unwrap();
+    let card = result.structured_content.unwrap();
+    assert_eq!(
+        card.pointer("/usage_patterns/0").unwrap(),
+        &json!({
+            "code_template": "let ... = User::new(...);",
+            "count": 2,
+            "usage_frequency_percent": 100.0,
+        })
+    );
+    // The references are asked for at the name of the type
+    let requests = launcher.log().params("textDocument/references");
+    assert_eq!(
+        requests.first().unwrap().pointer("/position").unwrap(),
+        &json!({ "line": 0, "character": 11 })
+    );
+    supervisor.stop_all().await;
+}
+
+#[tokio::test]
+async fn test_rename_previews_then_applies() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let (context, launcher, supervisor) = user_context(&root).await;
+    let hashes = [("user.rs", USER_RS), ("main.rs", MAIN_RS)]
+        .into_iter()
+        .map(|(name, text)| {
+            let uri = file_uri(&root, name).as_str().to_string();
+            (uri, content_hash(text.as_bytes()))
+        })
+        .collect();
+    context.engine.set_indexed_files(hashes);
+
+    let arguments = json!({ "name": "app::User", "newName": "Account" });
+    let result = RenameSymbolTool
+        .call(context.clone(), arguments.clone())
+        .await
+        .unwrap();
+    let preview = result.structured_content.unwrap();
+    assert_eq!(preview.pointer("/applied").unwrap(), false);
+    assert_eq!(preview.pointer("/server").unwrap(), "rust-analyzer");
+    let files = preview.pointer("/files").unwrap().as_array().unwrap();
+    assert_eq!(files.len(), 2);
+    let main = files
+        .iter()
+        .find(|file| file.pointer("/uri").unwrap() == file_uri(&root, "main.rs").as_str())
+        .unwrap();
+    assert_eq!(main.pointer("/operation/kind").unwrap(), "modify");
+    assert_eq!(
+        main.pointer("/ranges").unwrap().as_array().unwrap().len(),
+        2
+    );
+    assert!(
+        main.pointer("/diff")
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .contains("+    let a = Account::new(1);\n")
+    );
+    assert_eq!(
+        std::fs::read_to_string(root.join("src/user.rs")).unwrap(),
+        USER_RS
+    );
+    // The rename is asked for at the name of the type
+    let requests = launcher.log().params("textDocument/rename");
+    assert_eq!(
+        requests.last().unwrap().pointer("/position").unwrap(),
+        &json!({ "line": 0, "character": 11 })
+    );
+
+    let apply = json!({ "name": "app::User", "newName": "Account", "apply": true });
+    let result = RenameSymbolTool.call(context.clone(), apply).await.unwrap();
+    assert_eq!(
+        result
+            .structured_content
+            .unwrap()
+            .pointer("/applied")
+            .unwrap(),
+        true
+    );
+    assert_eq!(
+        std::fs::read_to_string(root.join("src/user.rs")).unwrap(),
+        "pub struct Account;\n"
+    );
+
+    // The files changed since the workspace was indexed
+    let error = RenameSymbolTool.call(context, arguments).await.unwrap_err();
+    assert!(matches!(
+        error,
+        ToolError::Engine(ContextEngineError::Edit { message, .. })
+            if message.contains("index the workspace again")
+    ));
+    supervisor.stop_all().await;
+}
+
+#[tokio::test]
+async fn test_rename_invalid_arguments() {
+    let error = RenameSymbolTool
+        .call(describe_context(), json!({ "name": "app::User" }))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ToolError::InvalidArguments(_)));
+    let error = RenameSymbolTool
+        .call(
+            describe_context(),
+            json!({ "uri": "file:///workspace/src/user.rs", "newName": "Account" }),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ToolError::InvalidArguments(_)));
+
+    let result = RenameSymbolTool
+        .call(
+            describe_context(),
+            json!({ "name": "app::Missing", "newName": "Account" }),
+        )
+        .await
+        .unwrap();
+    assert!(result.is_error);
+}
+
+#[test]
+fn test_name_offset_finds_whole_identifiers() {
+    let offset = |kind, name: &str, text: &str| {
+        let uri = "file:///workspace/src/lib.rs".parse::<Uri>().unwrap();
+        let end = Position::new(0, text.len() as u32);
+        let document = TextDocument::new(uri.clone(), "rust", 1, text, PositionEncodingKind::UTF16);
+        let symbol = SymbolNode::new(
+            kind,
+            name,
+            format!("app::{name}"),
+            uri,
+            Range::new(Position::new(0, 0), end),
+        );
+        name_offset(&document, &symbol).unwrap()
+    };
+    assert_eq!(offset(NodeKind::Field, "b", "pub b: u8"), 4);
+    assert_eq!(offset(NodeKind::Function, "n", "fn n()"), 3);
+    assert_eq!(
+        offset(NodeKind::Function, "f", "pub(crate) fn f(fn_f: u8)"),
+        14
+    );
+    // Without a whole identifier, the signature is used
+    assert_eq!(offset(NodeKind::Function, "u", "fn user()"), 0);
+}
+
+// Property-based tests
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn prop_search_never_exceeds_limit(count in 0usize..12, limit in 1usize..8) {
+        let qualified = (0..count).map(|index| format!("app::f{index}")).collect::<Vec<_>>();
+        let names_and_kinds = qualified
+            .iter()
+            .map(|name| (NodeKind::Function, name.as_str()))
+            .collect::<Vec<_>>();
+        let context = context(&names_and_kinds);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime
+            .block_on(SearchSymbolsTool.call(context, json!({ "limit": limit })))
+            .unwrap();
+        prop_assert_eq!(names(&result).len(), count.min(limit));
+    }
+
+    #[test]
+    fn prop_found_identifiers_stand_alone(
+        prefix in "[a-z_ (:]{0,8}",
+        name in "[a-z]{1,4}",
+        suffix in "[a-z_ ):]{0,8}",
+    ) {
+        let text = format!("{prefix}{name}{suffix}");
+        if let Some(at) = find_identifier(&text, &name) {
+            prop_assert_eq!(text.get(at..at + name.len()), Some(name.as_str()));
+            let before = text.get(..at).and_then(|text| text.chars().next_back());
+            prop_assert!(!before.is_some_and(|c| c.is_alphanumeric() || c == '_'));
+        }
+    }
+}
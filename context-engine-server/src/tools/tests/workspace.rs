@@ -0,0 +1,87 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+use tokio::sync::mpsc;
+
+use super::*;
+use crate::Engine;
+use crate::mcp::{Progress, ToolError};
+
+fn context(
+    root: &std::path::Path,
+    token: Option<Value>,
+) -> (
+    ToolContext,
+    mpsc::UnboundedReceiver<context_engine_core::lsp::Message>,
+) {
+    let (notifications, receiver) = mpsc::unbounded_channel();
+    let context = ToolContext {
+        engine: Arc::new(Engine::new(root)),
+        progress: Progress::new(token, notifications),
+    };
+    (context, receiver)
+}
+
+#[tokio::test]
+async fn test_status() {
+    let dir = tempfile::tempdir().unwrap();
+    let (context, _) = context(dir.path(), None);
+    let result = WorkspaceStatusTool.call(context, json!({})).await.unwrap();
+    let status = result.structured_content.unwrap();
+    assert_eq!(
+        status.pointer("/root").unwrap(),
+        dir.path().display().to_string().as_str()
+    );
+    assert_eq!(status.pointer("/symbols").unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_index_reports_progress() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "not source code").unwrap();
+    let (context, mut notifications) = context(dir.path(), Some(json!(42)));
+    let engine = Arc::clone(&context.engine);
+
+    let result = IndexWorkspaceTool.call(context, json!({})).await.unwrap();
+    let outcome = result.structured_content.unwrap();
+    assert_eq!(outcome.pointer("/indexed").unwrap(), 0);
+    assert_eq!(outcome.pointer("/errors").unwrap(), &json!([]));
+    assert_eq!(engine.status().symbols, 0);
+
+    let mut reported = Vec::new();
+    while let Ok(notification) = notifications.try_recv() {
+        let notification = serde_json::to_value(notification).unwrap();
+        let params = notification.get("params").unwrap();
+        assert_eq!(params.pointer("/progressToken").unwrap(), 42);
+        reported.push(params.pointer("/progress").unwrap().as_f64().unwrap());
+    }
+    assert_eq!(reported.last(), Some(&100.0));
+    assert!(reported.windows(2).all(|pair| pair.first() < pair.get(1)));
+}
+
+#[tokio::test]
+async fn test_index_rejects_unknown_arguments() {
+    let dir = tempfile::tempdir().unwrap();
+    let (context, _) = context(dir.path(), None);
+    let error = IndexWorkspaceTool
+        .call(context, json!({ "depth": 3 }))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ToolError::InvalidArguments(_)));
+}
+
+// Property-based tests
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(8))]
+
+    #[test]
+    fn prop_index_accepts_any_concurrency(concurrency in 0usize..16) {
+        let dir = tempfile::tempdir().unwrap();
+        let (context, _) = context(dir.path(), None);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime
+            .block_on(IndexWorkspaceTool.call(context, json!({ "concurrency": concurrency })))
+            .unwrap();
+        prop_assert!(!result.is_error);
+    }
+}
@@ -0,0 +1,157 @@
+//! Tools about the workspace as a whole.
+
+use std::sync::Arc;
+
+use context_engine_core::index::{IndexPhase, Indexer};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::mcp::{CallToolResult, Tool, ToolContext, ToolFuture, ToolHandler, parse_arguments};
+
+/// `workspace.status`: the language servers, open documents and size of the
+/// knowledge graph of the workspace.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WorkspaceStatusTool;
+
+impl ToolHandler for WorkspaceStatusTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "workspace.status".to_string(),
+            description: "Returns the state of the language servers, the number of open documents \
+                          and the size of the knowledge graph of the workspace."
+                .to_string(),
+            input_schema: json!({ "type": "object", "properties": {} }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, _arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let status = serde_json::to_value(context.engine.status())
+                .map_err(context_engine_core::ContextEngineError::from)?;
+            Ok(CallToolResult::json(status))
+        })
+    }
+}
+
+/// The arguments of `workspace.index`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct IndexArguments {
+    /// The number of files each language server works on at the same time
+    concurrency: Option<usize>,
+}
+
+/// `workspace.index`: builds the knowledge graph of the workspace from its
+/// language servers, reporting progress while it runs, saves it to the
+/// store of the engine and adds the locked dependencies of the workspace.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IndexWorkspaceTool;
+
+impl ToolHandler for IndexWorkspaceTool {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "workspace.index".to_string(),
+            description: "Indexes the workspace: collects the symbols of every source file and \
+                          their relationships from the language servers into the knowledge graph. \
+                          Reports progress if the request has a progress token. The graph is \
+                          saved, so the next start of the server begins from it, and files that \
+                          did not change since the last index are not analyzed again. The \
+                          dependencies locked in Cargo.lock are added in namespaces of their \
+                          versions, e.g. tokio@1.45.1::sync::Mutex."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "concurrency": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "The number of files each language server works on at the same time",
+                    },
+                },
+            }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let arguments = parse_arguments::<IndexArguments>(arguments)?;
+            let engine = &context.engine;
+            let mut config = engine.index_config();
+            if let Some(concurrency) = arguments.concurrency {
+                config.concurrency = concurrency.max(1);
+            }
+            let indexer = Indexer::new(
+                engine.root(),
+                Arc::clone(engine.supervisor()),
+                Arc::clone(engine.registry()),
+                Arc::clone(engine.documents()),
+                config,
+            );
+
+            let mut progress = indexer.progress();
+            let run = indexer.run();
+            tokio::pin!(run);
+            let mut reported = None;
+            let outcome = loop {
+                tokio::select! {
+                    outcome = &mut run => break outcome,
+                    changed = progress.changed() => {
+                        if changed.is_err() {
+                            break (&mut run).await;
+                        }
+                        let current = *progress.borrow_and_update();
+                        let percentage = current.percentage();
+                        // Progress must increase with every notification
+                        if reported.map_or(true, |reported| percentage > reported) {
+                            reported = Some(percentage);
+                            context.progress.report(
+                                f64::from(percentage),
+                                Some(100.0),
+                                Some(current.message()),
+                            );
+                        }
+                    }
+                }
+            };
+            if reported != Some(100) {
+                context
+                    .progress
+                    .report(100.0, Some(100.0), Some(IndexPhase::Done.to_string()));
+            }
+
+            let mut errors = outcome
+                .errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            let (indexed, unchanged) = (outcome.indexed, outcome.unchanged);
+            engine.set_indexed_files(outcome.files);
+            engine.set_graph(outcome.graph);
+            engine.save_graph();
+
+            let dependencies = match engine.index_dependencies().await {
+                Ok(dependencies) => {
+                    errors.extend(dependencies.errors.iter().map(ToString::to_string));
+                    dependencies.dependencies.len()
+                }
+                Err(error) => {
+                    errors.push(error.to_string());
+                    0
+                }
+            };
+            let graph = engine.graph();
+            Ok(CallToolResult::json(json!({
+                "indexed": indexed,
+                "unchanged": unchanged,
+                "dependencies": dependencies,
+                "symbols": graph.len(),
+                "relationships": graph.edge_count(),
+                "errors": errors,
+            })))
+        })
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/workspace.rs"]
+mod tests;
@@ -0,0 +1,472 @@
+//! The streamable HTTP transport.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use std::{fmt, io};
+
+use context_engine_core::JsonRpcError;
+use context_engine_core::lsp::{Message, RequestMessage, ResponseMessage};
+use parking_lot::Mutex;
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use super::parse;
+use super::sse::{EventStream, parse_event_id};
+use super::wire::{HttpRequest, HttpResponse, read_request, write_event_stream_head};
+use crate::mcp::{
+    INVALID_REQUEST, McpServer, PARSE_ERROR, PROTOCOL_VERSIONS, Session, error_response, methods,
+};
+
+/// The header that carries the id of a session.
+pub const SESSION_HEADER: &str = "Mcp-Session-Id";
+
+/// The header that carries the negotiated protocol version.
+pub const PROTOCOL_VERSION_HEADER: &str = "MCP-Protocol-Version";
+
+/// How many ended event streams a session keeps for clients to resume.
+const RETAINED_STREAMS: usize = 64;
+
+/// The configuration of the HTTP transport.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpConfig {
+    /// The path of the MCP endpoint
+    pub path: String,
+    /// Browser origins allowed to connect besides the local ones, e.g.
+    /// `https://agents.example.com`; requests without `Origin` header are
+    /// always allowed
+    pub allowed_origins: Vec<String>,
+    /// How long a session without requests and open streams lives
+    pub session_timeout: Duration,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            path: "/mcp".to_string(),
+            allowed_origins: Vec::new(),
+            session_timeout: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+/// Serves MCP sessions over streamable HTTP until `shutdown` completes.
+///
+/// All sessions share the engine of the server, so one long-lived process
+/// serves several clients. The endpoint at [`HttpConfig::path`] accepts
+///
+/// * `POST` with one JSON-RPC message. `initialize` opens a session whose id
+///   the response carries in the `Mcp-Session-Id` header, which every later
+///   request must send. Notifications and responses are answered with `202
+///   Accepted`. Other requests are answered with a `text/event-stream` that
+///   carries their notifications, e.g. progress, and ends with their response,
+///   if the client accepts it, and with a JSON response otherwise.
+/// * `GET` with a `Last-Event-ID` header, to resume an event stream after its
+///   connection was lost. The server sends no messages outside of requests, so
+///   there is no stream without `Last-Event-ID`.
+/// * `DELETE`, to end a session.
+///
+/// Requests from browsers are only allowed from local origins and
+/// [`HttpConfig::allowed_origins`], which protects local servers against
+/// DNS rebinding.
+///
+/// # Errors
+///
+/// Returns the I/O error if the listener fails.
+pub async fn serve_http<F>(
+    server: &McpServer,
+    listener: TcpListener,
+    config: HttpConfig,
+    shutdown: F,
+) -> io::Result<()>
+where
+    F: Future<Output = ()>,
+{
+    let state = Arc::new(HttpState {
+        server: server.clone(),
+        config,
+        sessions: Mutex::new(HashMap::new()),
+    });
+    if let Ok(address) = listener.local_addr() {
+        info!(%address, path = %state.config.path, "Serving MCP over HTTP");
+    }
+
+    tokio::pin!(shutdown);
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = &mut shutdown => break,
+        };
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            if let Err(error) = state.serve_connection(stream).await {
+                debug!(%peer, %error, "Connection failed");
+            }
+        });
+    }
+    debug!("Shutting down the HTTP transport");
+    Ok(())
+}
+
+/// A session of the HTTP transport and its event streams.
+struct HttpSession {
+    id: String,
+    session: Session,
+    streams: Mutex<BTreeMap<u64, Arc<EventStream>>>,
+    next_stream: AtomicU64,
+    last_active: Mutex<Instant>,
+}
+
+impl HttpSession {
+    fn new(session: Session) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            session,
+            streams: Mutex::new(BTreeMap::new()),
+            next_stream: AtomicU64::new(1),
+            last_active: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Opens an event stream, forgetting the oldest ended streams.
+    fn open_stream(&self) -> Arc<EventStream> {
+        let stream = Arc::new(EventStream::new(
+            self.next_stream.fetch_add(1, Ordering::SeqCst),
+        ));
+        let mut streams = self.streams.lock();
+        streams.insert(stream.id(), Arc::clone(&stream));
+        let ended = streams
+            .values()
+            .filter(|stream| stream.is_closed())
+            .map(|stream| stream.id())
+            .collect::<Vec<_>>();
+        for id in ended
+            .iter()
+            .take(ended.len().saturating_sub(RETAINED_STREAMS))
+        {
+            streams.remove(id);
+        }
+        stream
+    }
+
+    fn stream(&self, id: u64) -> Option<Arc<EventStream>> {
+        self.streams.lock().get(&id).cloned()
+    }
+
+    fn is_expired(&self, now: Instant, timeout: Duration) -> bool {
+        now.duration_since(*self.last_active.lock()) > timeout
+            && self
+                .streams
+                .lock()
+                .values()
+                .all(|stream| stream.is_closed())
+    }
+}
+
+/// What to answer a request with.
+enum Reply {
+    /// A complete response
+    Response(HttpResponse),
+    /// An event stream, from an index on
+    Events {
+        headers: Vec<(String, String)>,
+        stream: Arc<EventStream>,
+        from: usize,
+    },
+}
+
+impl From<HttpResponse> for Reply {
+    fn from(response: HttpResponse) -> Self {
+        Reply::Response(response)
+    }
+}
+
+/// The state shared by the connections.
+struct HttpState {
+    server: McpServer,
+    config: HttpConfig,
+    sessions: Mutex<HashMap<String, Arc<HttpSession>>>,
+}
+
+impl HttpState {
+    async fn serve_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        loop {
+            let request = match read_request(&mut reader).await? {
+                None => return Ok(()),
+                Some(Ok(request)) => request,
+                Some(Err(error)) => return error.response().write_to(&mut writer, true).await,
+            };
+            let close = request.wants_close();
+            match self.handle(request).await {
+                Reply::Response(response) => {
+                    response.write_to(&mut writer, close).await?;
+                    if close {
+                        return Ok(());
+                    }
+                }
+                Reply::Events {
+                    headers,
+                    stream,
+                    from,
+                } => {
+                    write_event_stream_head(&mut writer, &headers).await?;
+                    stream.write_to(&mut writer, from).await?;
+                    return writer.shutdown().await;
+                }
+            }
+        }
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Reply {
+        if request.path != self.config.path {
+            return HttpResponse::text(404, "Not found").into();
+        }
+        if let Some(origin) = request.header("origin") {
+            if !origin_allowed(origin, &self.config.allowed_origins) {
+                warn!(origin, "Rejecting request from a foreign origin");
+                return HttpResponse::text(403, "Origin not allowed").into();
+            }
+        }
+        self.expire_sessions();
+
+        match request.method.as_str() {
+            "POST" => self.post(request).await,
+            "GET" => self.get(&request),
+            "DELETE" => self.delete(&request),
+            _ => HttpResponse::text(405, "Method not allowed")
+                .with_header("Allow", "GET, POST, DELETE")
+                .into(),
+        }
+    }
+
+    async fn post(&self, request: HttpRequest) -> Reply {
+        let accepts_events = request.accepts("text/event-stream");
+        let json = request.header("content-type").map_or(true, |content_type| {
+            content_type.starts_with("application/json")
+        });
+        if !json {
+            return HttpResponse::text(415, "Expected application/json").into();
+        }
+        let message = match std::str::from_utf8(&request.body) {
+            Ok(body) => parse(body),
+            Err(error) => Err(error_response(PARSE_ERROR, format!("Parse error: {error}"))),
+        };
+        let message = match message {
+            Ok(message) => message,
+            Err(response) => return json_response(400, &response).into(),
+        };
+
+        if let Message::Request(request) = &message {
+            if request.method == methods::INITIALIZE {
+                return self.initialize(request.clone()).await.into();
+            }
+        }
+        let session = match self.session(&request) {
+            Ok(session) => session,
+            Err(response) => return response.into(),
+        };
+
+        let request = match message {
+            Message::Request(request) => request,
+            message => {
+                let (messages, _) = mpsc::unbounded_channel();
+                session.session.handle(message, messages).await;
+                return HttpResponse::empty(202).into();
+            }
+        };
+        let headers = vec![(SESSION_HEADER.to_string(), session.id.clone())];
+        if accepts_events {
+            let stream = session.open_stream();
+            tokio::spawn(stream_request(
+                Arc::clone(&session),
+                request,
+                Arc::clone(&stream),
+            ));
+            return Reply::Events {
+                headers,
+                stream,
+                from: 0,
+            };
+        }
+
+        // Notifications about the request have no way to the client
+        let (messages, _) = mpsc::unbounded_channel();
+        match session.session.handle_request(request, messages).await {
+            Some(response) => with_headers(json_response(200, &response), headers).into(),
+            None => with_headers(HttpResponse::empty(202), headers).into(),
+        }
+    }
+
+    async fn initialize(&self, request: RequestMessage) -> HttpResponse {
+        let session = Arc::new(HttpSession::new(self.server.session()));
+        let (messages, _) = mpsc::unbounded_channel();
+        let id = request.id.clone();
+        let Some(response) = session.session.handle_request(request, messages).await else {
+            let error = JsonRpcError::new(INVALID_REQUEST, "The initialize request was cancelled");
+            return json_response(200, &ResponseMessage::err(id, error));
+        };
+        if response.error.is_some() {
+            return json_response(200, &response);
+        }
+
+        let id = session.id.clone();
+        self.sessions.lock().insert(id.clone(), session);
+        info!(session = %id, "Opened HTTP session");
+        json_response(200, &response).with_header(SESSION_HEADER, id)
+    }
+
+    fn get(&self, request: &HttpRequest) -> Reply {
+        if !request.accepts("text/event-stream") {
+            return HttpResponse::text(406, "Expected text/event-stream").into();
+        }
+        let session = match self.session(request) {
+            Ok(session) => session,
+            Err(response) => return response.into(),
+        };
+        let Some(last_event) = request.header("last-event-id") else {
+            return HttpResponse::text(405, "The server sends no messages outside of requests")
+                .with_header("Allow", "POST, DELETE")
+                .into();
+        };
+        let Some((stream, index)) = parse_event_id(last_event) else {
+            return HttpResponse::text(400, "Invalid Last-Event-ID").into();
+        };
+        let Some(stream) = session.stream(stream) else {
+            return HttpResponse::text(404, "Unknown event stream").into();
+        };
+        debug!(stream = stream.id(), index, "Resuming event stream");
+        Reply::Events {
+            headers: Vec::new(),
+            stream,
+            from: index + 1,
+        }
+    }
+
+    fn delete(&self, request: &HttpRequest) -> Reply {
+        let Some(id) = request.header(SESSION_HEADER) else {
+            return HttpResponse::text(400, "Missing Mcp-Session-Id header").into();
+        };
+        match self.sessions.lock().remove(id) {
+            Some(_) => {
+                info!(session = %id, "Closed HTTP session");
+                HttpResponse::empty(200).into()
+            }
+            None => HttpResponse::text(404, "Unknown session").into(),
+        }
+    }
+
+    /// Returns the session of a request, or the response to a request
+    /// without valid session.
+    fn session(&self, request: &HttpRequest) -> Result<Arc<HttpSession>, HttpResponse> {
+        let Some(id) = request.header(SESSION_HEADER) else {
+            return Err(json_response(
+                400,
+                &error_response(INVALID_REQUEST, "Missing Mcp-Session-Id header"),
+            ));
+        };
+        let Some(session) = self.sessions.lock().get(id).cloned() else {
+            return Err(json_response(
+                404,
+                &error_response(INVALID_REQUEST, "Unknown session"),
+            ));
+        };
+        if let Some(version) = request.header(PROTOCOL_VERSION_HEADER) {
+            if !PROTOCOL_VERSIONS.contains(&version) {
+                return Err(json_response(
+                    400,
+                    &error_response(
+                        INVALID_REQUEST,
+                        format!("Unsupported protocol version: {version}"),
+                    ),
+                ));
+            }
+        }
+        *session.last_active.lock() = Instant::now();
+        Ok(session)
+    }
+
+    fn expire_sessions(&self) {
+        let now = Instant::now();
+        self.sessions.lock().retain(|id, session| {
+            let expired = session.is_expired(now, self.config.session_timeout);
+            if expired {
+                info!(session = %id, "HTTP session expired");
+            }
+            !expired
+        });
+    }
+}
+
+impl fmt::Debug for HttpState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpState")
+            .field("config", &self.config)
+            .field("sessions", &self.sessions.lock().len())
+            .finish()
+    }
+}
+
+/// Handles a request, sending its notifications and its response on an
+/// event stream, which ends with the response.
+///
+/// The handling continues if the client disconnects from the stream, so
+/// that it can resume the stream later.
+async fn stream_request(
+    session: Arc<HttpSession>,
+    request: RequestMessage,
+    stream: Arc<EventStream>,
+) {
+    let (messages, mut notifications) = mpsc::unbounded_channel();
+    let handling = session.session.handle_request(request, messages);
+    tokio::pin!(handling);
+    let response = loop {
+        tokio::select! {
+            biased;
+            Some(message) = notifications.recv() => stream.push(message),
+            response = &mut handling => break response,
+        }
+    };
+    while let Ok(message) = notifications.try_recv() {
+        stream.push(message);
+    }
+    if let Some(response) = response {
+        stream.push(response.into());
+    }
+    stream.close();
+}
+
+fn with_headers(mut response: HttpResponse, headers: Vec<(String, String)>) -> HttpResponse {
+    response.headers.extend(headers);
+    response
+}
+
+fn json_response(status: u16, response: &ResponseMessage) -> HttpResponse {
+    HttpResponse::json(status, serde_json::to_vec(response).unwrap_or_default())
+}
+
+/// Returns true if requests from a browser origin are allowed: local
+/// origins and the configured ones.
+fn origin_allowed(origin: &str, allowed: &[String]) -> bool {
+    if allowed.iter().any(|allowed| allowed == origin) {
+        return true;
+    }
+    let host = origin
+        .split_once("://")
+        .map_or(origin, |(_, rest)| rest)
+        .trim_end_matches('/');
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+#[cfg(test)]
+#[path = "tests/http.rs"]
+mod tests;
@@ -0,0 +1,41 @@
+//! Transports that connect MCP clients to the server.
+//!
+//! A transport moves JSON-RPC messages between a client and a
+//! [`Session`](crate::mcp::Session); all protocol logic lives in the
+//! session, so every transport serves the same tools.
+//!
+//! ## Structs
+//!
+//! * [`HttpConfig`] - The configuration of the HTTP transport
+//! * [`EventStream`] - A resumable stream of server-sent events
+//! * [`HttpRequest`] / [`HttpResponse`] - The HTTP/1.1 messages of the HTTP
+//!   transport
+//!
+//! ## Functions
+//!
+//! * [`serve_stdio`] - Serves one session over newline-delimited JSON on a pair
+//!   of streams, usually stdin and stdout.
+//! * [`serve_http`] - Serves any number of sessions over the MCP streamable
+//!   HTTP transport.
+
+mod http;
+mod sse;
+mod stdio;
+mod wire;
+
+use context_engine_core::lsp::{Message, ResponseMessage};
+pub use http::{HttpConfig, PROTOCOL_VERSION_HEADER, SESSION_HEADER, serve_http};
+use serde_json::Value;
+pub use sse::{EventStream, event_id, parse_event_id};
+pub use stdio::serve_stdio;
+pub use wire::{HttpRequest, HttpResponse, MAX_BODY, WireError, read_request};
+
+use crate::mcp::{INVALID_REQUEST, PARSE_ERROR, error_response};
+
+/// Parses a message of a client, or returns the error response to send.
+fn parse(text: &str) -> Result<Message, ResponseMessage> {
+    let value = serde_json::from_str::<Value>(text)
+        .map_err(|error| error_response(PARSE_ERROR, format!("Parse error: {error}")))?;
+    serde_json::from_value::<Message>(value)
+        .map_err(|error| error_response(INVALID_REQUEST, format!("Invalid request: {error}")))
+}
@@ -0,0 +1,167 @@
+//! Server-sent event streams that clients can resume.
+
+use std::io;
+use std::time::Duration;
+
+use context_engine_core::lsp::Message;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::watch;
+
+/// How often an idle stream sends a comment, so that proxies keep the
+/// connection open and a closed connection is noticed.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// The messages of a stream and whether more will follow.
+#[derive(Debug, Default)]
+struct Events {
+    messages: Vec<Message>,
+    closed: bool,
+}
+
+/// A stream of server-sent events, each carrying one JSON-RPC message.
+///
+/// The stream keeps every message it was sent, so that a client that lost
+/// its connection can resume after the last event it received. Event ids
+/// are `<stream>-<index>`, which identifies the stream to resume from the id
+/// alone.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_core::lsp::{Message, NotificationMessage};
+/// use context_engine_server::transport::EventStream;
+///
+/// # tokio_test::block_on(async {
+/// let stream = EventStream::new(7);
+/// stream.push(Message::from(NotificationMessage::new("notifications/progress", None)));
+/// stream.close();
+///
+/// let mut body = Vec::new();
+/// stream.write_to(&mut body, 0).await.unwrap();
+/// let body = String::from_utf8(body).unwrap();
+/// assert!(body.starts_with("id: 7-0\ndata: {"));
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct EventStream {
+    id: u64,
+    events: watch::Sender<Events>,
+}
+
+impl EventStream {
+    /// Creates an open stream without events.
+    pub fn new(id: u64) -> Self {
+        Self {
+            id,
+            events: watch::Sender::new(Events::default()),
+        }
+    }
+
+    /// Returns the id of the stream.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the number of events sent so far.
+    pub fn len(&self) -> usize {
+        self.events.borrow().messages.len()
+    }
+
+    /// Returns true if no event was sent yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true once the stream ended.
+    pub fn is_closed(&self) -> bool {
+        self.events.borrow().closed
+    }
+
+    /// Sends a message; messages sent after the stream ended are dropped.
+    pub fn push(&self, message: Message) {
+        self.events.send_modify(|events| {
+            if !events.closed {
+                events.messages.push(message);
+            }
+        });
+    }
+
+    /// Ends the stream.
+    pub fn close(&self) {
+        self.events.send_modify(|events| events.closed = true);
+    }
+
+    /// Writes the events of the stream as the body of a `text/event-stream`
+    /// response, waiting for new events until the stream ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The connection
+    /// * `from` - The index of the first event to write, e.g. 0 for a new
+    ///   stream, or the index after the last event a resuming client received
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the connection fails, e.g. because the client
+    /// disconnected. The stream is unaffected and can be resumed.
+    pub async fn write_to<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        from: usize,
+    ) -> io::Result<()> {
+        let mut events = self.events.subscribe();
+        let mut next = from;
+        loop {
+            let (messages, closed) = {
+                let events = events.borrow_and_update();
+                let messages = events.messages.get(next..).unwrap_or_default().to_vec();
+                (messages, events.closed)
+            };
+            for message in messages {
+                let data = serde_json::to_string(&message).map_err(io::Error::other)?;
+                let event = format!("id: {}\ndata: {data}\n\n", event_id(self.id, next));
+                writer.write_all(event.as_bytes()).await?;
+                next += 1;
+            }
+            writer.flush().await?;
+            if closed {
+                return Ok(());
+            }
+
+            tokio::select! {
+                changed = events.changed() => {
+                    if changed.is_err() {
+                        return Ok(());
+                    }
+                }
+                _ = tokio::time::sleep(KEEP_ALIVE) => {
+                    writer.write_all(b": keep-alive\n\n").await?;
+                }
+            }
+        }
+    }
+}
+
+/// Returns the id of the event at an index of a stream.
+pub fn event_id(stream: u64, index: usize) -> String {
+    format!("{stream}-{index}")
+}
+
+/// Parses an event id into the id of its stream and its index.
+///
+/// # Examples
+///
+/// ```
+/// use context_engine_server::transport::parse_event_id;
+///
+/// assert_eq!(parse_event_id("3-12"), Some((3, 12)));
+/// assert_eq!(parse_event_id("3"), None);
+/// ```
+pub fn parse_event_id(id: &str) -> Option<(u64, usize)> {
+    let (stream, index) = id.trim().split_once('-')?;
+    Some((stream.parse().ok()?, index.parse().ok()?))
+}
+
+#[cfg(test)]
+#[path = "tests/sse.rs"]
+mod tests;
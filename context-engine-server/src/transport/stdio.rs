@@ -0,0 +1,119 @@
+//! The stdio transport.
+
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use context_engine_core::lsp::Message;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
+
+use super::parse;
+use crate::mcp::McpServer;
+
+/// How long requests in flight may take to complete once the client closed
+/// the input stream.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Serves one MCP session over a pair of streams, usually the stdin and
+/// stdout of the server process.
+///
+/// Messages are JSON-RPC messages, one per line, without embedded newlines.
+/// Requests are handled concurrently and their responses are written in the
+/// order they complete. Lines that are not valid messages are answered with
+/// a JSON-RPC error without id.
+///
+/// # Returns
+///
+/// Once the client closed the input stream and the requests in flight
+/// completed, or were dropped after a grace period.
+///
+/// # Errors
+///
+/// Returns the I/O error if the input stream cannot be read.
+pub async fn serve_stdio<R, W>(server: &McpServer, reader: R, writer: W) -> io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let session = Arc::new(server.session());
+    let (outgoing, messages) = mpsc::unbounded_channel::<Message>();
+    let write = tokio::spawn(write_loop(messages, writer));
+
+    let mut requests = JoinSet::new();
+    let mut lines = BufReader::new(reader).lines();
+    let result = loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break Ok(()),
+            Err(error) => break Err(error),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message = match parse(&line) {
+            Ok(message) => message,
+            Err(response) => {
+                let _ = outgoing.send(response.into());
+                continue;
+            }
+        };
+        match message {
+            Message::Request(request) => {
+                let session = Arc::clone(&session);
+                let outgoing = outgoing.clone();
+                requests.spawn(async move {
+                    if let Some(response) = session.handle_request(request, outgoing.clone()).await
+                    {
+                        let _ = outgoing.send(response.into());
+                    }
+                });
+            }
+            message => {
+                session.handle(message, outgoing.clone()).await;
+            }
+        }
+        // Reap the finished requests
+        while requests.try_join_next().is_some() {}
+    };
+
+    debug!("Input closed, ending session");
+    let finished = tokio::time::timeout(SHUTDOWN_GRACE, async {
+        while requests.join_next().await.is_some() {}
+    })
+    .await;
+    if finished.is_err() {
+        warn!(
+            requests = requests.len(),
+            "Dropping requests still in flight"
+        );
+        requests.shutdown().await;
+    }
+    drop(outgoing);
+    if let Ok(Err(error)) = write.await {
+        warn!(%error, "Failed to write to the client");
+    }
+    result
+}
+
+async fn write_loop<W>(
+    mut messages: mpsc::UnboundedReceiver<Message>,
+    mut writer: W,
+) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    while let Some(message) = messages.recv().await {
+        let mut line = serde_json::to_vec(&message).map_err(io::Error::other)?;
+        line.push(b'\n');
+        writer.write_all(&line).await?;
+        writer.flush().await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "tests/stdio.rs"]
+mod tests;
@@ -0,0 +1,443 @@
+#![allow(clippy::unwrap_used)]
+
+use std::net::SocketAddr;
+
+use proptest::prelude::*;
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+use tokio::sync::oneshot;
+
+use super::*;
+use crate::Engine;
+use crate::mcp::{CallToolResult, Tool, ToolContext, ToolFuture, ToolHandler};
+use crate::tools::default_tools;
+
+/// Reports two steps of progress, then completes.
+struct Steps;
+
+impl ToolHandler for Steps {
+    fn definition(&self) -> Tool {
+        Tool {
+            name: "steps".to_string(),
+            description: "Completes in two steps".to_string(),
+            input_schema: json!({ "type": "object" }),
+        }
+    }
+
+    fn call<'a>(&'a self, context: ToolContext, _arguments: Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            context.progress.report(1.0, Some(2.0), None);
+            context.progress.report(2.0, Some(2.0), None);
+            Ok(CallToolResult::json(json!({ "steps": 2 })))
+        })
+    }
+}
+
+/// A server listening on a local port.
+struct TestServer {
+    address: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl TestServer {
+    async fn start() -> Self {
+        let mut tools = default_tools();
+        tools.register(Steps);
+        let server = McpServer::new(Arc::new(Engine::new("/workspace")), tools);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let stopped = async {
+                let _ = stopped.await;
+            };
+            serve_http(&server, listener, HttpConfig::default(), stopped).await
+        });
+        Self {
+            address,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    async fn connect(&self) -> Connection {
+        Connection(BufReader::new(
+            TcpStream::connect(self.address).await.unwrap(),
+        ))
+    }
+
+    /// Sends a request on a new connection.
+    async fn send(&self, method: &str, headers: &[(&str, &str)], body: &str) -> Reply {
+        self.connect().await.send(method, headers, body).await
+    }
+
+    async fn initialize(&self) -> String {
+        let reply = self
+            .send(
+                "POST",
+                &[],
+                r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{"protocolVersion":"2025-06-18","capabilities":{},"clientInfo":{"name":"test","version":"1.0"}}}"#,
+            )
+            .await;
+        assert_eq!(reply.status, 200);
+        reply.header(SESSION_HEADER).unwrap().to_string()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+/// A response of the server.
+#[derive(Debug)]
+struct Reply {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Reply {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+
+    /// Returns the ids and messages of the events of an event stream.
+    fn events(&self) -> Vec<(String, Value)> {
+        assert_eq!(self.header("content-type"), Some("text/event-stream"));
+        self.body
+            .split("\n\n")
+            .filter(|event| !event.is_empty())
+            .map(|event| {
+                let (id, data) = event.split_once('\n').unwrap();
+                (
+                    id.strip_prefix("id: ").unwrap().to_string(),
+                    serde_json::from_str(data.strip_prefix("data: ").unwrap()).unwrap(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// A connection to the server, which may carry several requests.
+struct Connection(BufReader<TcpStream>);
+
+impl Connection {
+    async fn send(&mut self, method: &str, headers: &[(&str, &str)], body: &str) -> Reply {
+        let mut request = format!(
+            "{method} /mcp HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n",
+            body.len()
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{name}: {value}\r\n"));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+        self.0
+            .get_mut()
+            .write_all(request.as_bytes())
+            .await
+            .unwrap();
+
+        let mut status = String::new();
+        self.0.read_line(&mut status).await.unwrap();
+        let status = status.split_whitespace().nth(1).unwrap().parse().unwrap();
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            self.0.read_line(&mut line).await.unwrap();
+            let Some((name, value)) = line.trim_end().split_once(": ") else {
+                break;
+            };
+            headers.push((name.to_string(), value.to_string()));
+        }
+        let mut reply = Reply {
+            status,
+            headers,
+            body: String::new(),
+        };
+        match reply.header("content-length") {
+            Some(length) => {
+                let mut body = vec![0; length.parse().unwrap()];
+                self.0.read_exact(&mut body).await.unwrap();
+                reply.body = String::from_utf8(body).unwrap();
+            }
+            None => {
+                self.0.read_to_string(&mut reply.body).await.unwrap();
+            }
+        }
+        reply
+    }
+}
+
+const JSON: (&str, &str) = ("Accept", "application/json");
+const BOTH: (&str, &str) = ("Accept", "application/json, text/event-stream");
+
+#[tokio::test]
+async fn test_sessions() {
+    let server = TestServer::start().await;
+    let first = server.initialize().await;
+    let second = server.initialize().await;
+    assert_ne!(first, second);
+
+    let reply = server
+        .send(
+            "POST",
+            &[
+                JSON,
+                (SESSION_HEADER, &first),
+                (PROTOCOL_VERSION_HEADER, "2025-06-18"),
+            ],
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+        )
+        .await;
+    assert_eq!(reply.status, 202);
+    assert!(reply.body.is_empty());
+
+    let reply = server
+        .send(
+            "POST",
+            &[JSON, (SESSION_HEADER, &first)],
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#,
+        )
+        .await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.header(SESSION_HEADER), Some(first.as_str()));
+    assert_eq!(reply.json().pointer("/id").unwrap(), 1);
+    assert!(reply.json().pointer("/result/tools/0/name").is_some());
+
+    let list = r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#;
+    let missing = server.send("POST", &[JSON], list).await;
+    assert_eq!(missing.status, 400);
+    assert_eq!(missing.json().pointer("/error/code").unwrap(), -32600);
+    let unknown = server
+        .send("POST", &[JSON, (SESSION_HEADER, "nope")], list)
+        .await;
+    assert_eq!(unknown.status, 404);
+    let version = server
+        .send(
+            "POST",
+            &[
+                JSON,
+                (SESSION_HEADER, &first),
+                (PROTOCOL_VERSION_HEADER, "1999-01-01"),
+            ],
+            list,
+        )
+        .await;
+    assert_eq!(version.status, 400);
+
+    let deleted = server.send("DELETE", &[(SESSION_HEADER, &first)], "").await;
+    assert_eq!(deleted.status, 200);
+    let gone = server
+        .send("POST", &[JSON, (SESSION_HEADER, &first)], list)
+        .await;
+    assert_eq!(gone.status, 404);
+    let other = server
+        .send("POST", &[JSON, (SESSION_HEADER, &second)], list)
+        .await;
+    assert_eq!(other.status, 200);
+}
+
+#[tokio::test]
+async fn test_failed_initialize_opens_no_session() {
+    let server = TestServer::start().await;
+    let reply = server
+        .send(
+            "POST",
+            &[JSON],
+            r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{}}"#,
+        )
+        .await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.json().pointer("/error/code").unwrap(), -32602);
+    assert_eq!(reply.header(SESSION_HEADER), None);
+}
+
+#[tokio::test]
+async fn test_streamed_response_and_resumption() {
+    let server = TestServer::start().await;
+    let session = server.initialize().await;
+
+    let call = r#"{"jsonrpc":"2.0","id":7,"method":"tools/call","params":{"name":"steps","_meta":{"progressToken":"t"}}}"#;
+    let reply = server
+        .send("POST", &[BOTH, (SESSION_HEADER, &session)], call)
+        .await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.header(SESSION_HEADER), Some(session.as_str()));
+    let events = reply.events();
+    let ids = events.iter().map(|(id, _)| id.as_str()).collect::<Vec<_>>();
+    assert_eq!(ids, ["1-0", "1-1", "1-2"]);
+    let messages = events
+        .iter()
+        .map(|(_, message)| message)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages.first().unwrap().pointer("/method").unwrap(),
+        "notifications/progress"
+    );
+    assert_eq!(
+        messages
+            .get(1)
+            .unwrap()
+            .pointer("/params/progress")
+            .unwrap(),
+        2.0
+    );
+    assert_eq!(
+        messages
+            .get(2)
+            .unwrap()
+            .pointer("/result/structuredContent"),
+        Some(&json!({ "steps": 2 }))
+    );
+
+    // A client that lost the connection after the first event
+    let resumed = server
+        .send(
+            "GET",
+            &[
+                ("Accept", "text/event-stream"),
+                (SESSION_HEADER, &session),
+                ("Last-Event-ID", "1-0"),
+            ],
+            "",
+        )
+        .await;
+    assert_eq!(resumed.status, 200);
+    let ids = resumed
+        .events()
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids, ["1-1", "1-2"]);
+
+    let unknown = server
+        .send(
+            "GET",
+            &[
+                ("Accept", "text/event-stream"),
+                (SESSION_HEADER, &session),
+                ("Last-Event-ID", "9-0"),
+            ],
+            "",
+        )
+        .await;
+    assert_eq!(unknown.status, 404);
+    let standalone = server
+        .send(
+            "GET",
+            &[("Accept", "text/event-stream"), (SESSION_HEADER, &session)],
+            "",
+        )
+        .await;
+    assert_eq!(standalone.status, 405);
+}
+
+#[tokio::test]
+async fn test_keep_alive_connection() {
+    let server = TestServer::start().await;
+    let session = server.initialize().await;
+    let mut connection = server.connect().await;
+    for id in 1..=3 {
+        let reply = connection
+            .send(
+                "POST",
+                &[JSON, (SESSION_HEADER, &session)],
+                &format!(r#"{{"jsonrpc":"2.0","id":{id},"method":"ping"}}"#),
+            )
+            .await;
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.json().pointer("/id").unwrap(), id);
+    }
+}
+
+#[tokio::test]
+async fn test_invalid_requests() {
+    let server = TestServer::start().await;
+    let session = server.initialize().await;
+
+    let reply = server
+        .send("POST", &[JSON, (SESSION_HEADER, &session)], "{not json")
+        .await;
+    assert_eq!(reply.status, 400);
+    assert_eq!(reply.json().pointer("/error/code").unwrap(), -32700);
+
+    let reply = server
+        .send("POST", &[JSON, (SESSION_HEADER, &session)], "[1, 2]")
+        .await;
+    assert_eq!(reply.status, 400);
+    assert_eq!(reply.json().pointer("/error/code").unwrap(), -32600);
+
+    let reply = server
+        .send("POST", &[("Content-Type", "text/plain")], "ping")
+        .await;
+    assert_eq!(reply.status, 415);
+
+    let reply = server.send("PUT", &[], "").await;
+    assert_eq!(reply.status, 405);
+    assert_eq!(reply.header("allow"), Some("GET, POST, DELETE"));
+
+    let mut connection = server.connect().await;
+    connection
+        .0
+        .get_mut()
+        .write_all(b"GET /other HTTP/1.1\r\n\r\n")
+        .await
+        .unwrap();
+    let mut status = String::new();
+    connection.0.read_line(&mut status).await.unwrap();
+    assert_eq!(status, "HTTP/1.1 404 Not Found\r\n");
+}
+
+#[tokio::test]
+async fn test_foreign_origins_are_rejected() {
+    let server = TestServer::start().await;
+    let ping = r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+    let reply = server
+        .send("POST", &[("Origin", "https://evil.example")], ping)
+        .await;
+    assert_eq!(reply.status, 403);
+    let reply = server
+        .send("POST", &[("Origin", "http://localhost:3000")], ping)
+        .await;
+    assert_eq!(reply.status, 400);
+}
+
+#[test]
+fn test_origin_allowed() {
+    let allowed = ["https://agents.example.com".to_string()];
+    assert!(origin_allowed("http://localhost", &[]));
+    assert!(origin_allowed("http://127.0.0.1:8080", &[]));
+    assert!(origin_allowed("http://[::1]:8080", &[]));
+    assert!(origin_allowed("https://agents.example.com", &allowed));
+    assert!(!origin_allowed("https://agents.example.com.evil", &allowed));
+    assert!(!origin_allowed("http://localhost.evil.example", &[]));
+    assert!(!origin_allowed("null", &[]));
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_unlisted_remote_origins_are_rejected(
+        scheme in "https?",
+        host in "[a-z]{1,12}\\.[a-z]{2,5}",
+        port in proptest::option::of(1u16..),
+    ) {
+        let origin = match port {
+            Some(port) => format!("{scheme}://{host}:{port}"),
+            None => format!("{scheme}://{host}"),
+        };
+        prop_assert!(!origin_allowed(&origin, &[]));
+        prop_assert!(origin_allowed(&origin, std::slice::from_ref(&origin)));
+    }
+}
@@ -0,0 +1,100 @@
+#![allow(clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use context_engine_core::lsp::NotificationMessage;
+use proptest::prelude::*;
+use serde_json::json;
+
+use super::*;
+
+fn message(n: u32) -> Message {
+    NotificationMessage::new("notifications/progress", Some(json!({ "progress": n }))).into()
+}
+
+/// Returns the ids and data of the events of a body.
+fn events(body: &[u8]) -> Vec<(String, serde_json::Value)> {
+    String::from_utf8(body.to_vec())
+        .unwrap()
+        .split("\n\n")
+        .filter(|event| !event.is_empty() && !event.starts_with(':'))
+        .map(|event| {
+            let (id, data) = event.split_once('\n').unwrap();
+            (
+                id.strip_prefix("id: ").unwrap().to_string(),
+                serde_json::from_str(data.strip_prefix("data: ").unwrap()).unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_write_and_resume() {
+    let stream = EventStream::new(3);
+    stream.push(message(1));
+    stream.push(message(2));
+    stream.close();
+    stream.push(message(3));
+    assert_eq!(stream.len(), 2);
+    assert!(stream.is_closed());
+
+    let mut body = Vec::new();
+    stream.write_to(&mut body, 0).await.unwrap();
+    let all = events(&body);
+    assert_eq!(
+        all.iter().map(|(id, _)| id.as_str()).collect::<Vec<_>>(),
+        ["3-0", "3-1"]
+    );
+    assert_eq!(
+        all.first().unwrap().1.pointer("/params/progress").unwrap(),
+        1
+    );
+
+    let (stream_id, index) = parse_event_id("3-0").unwrap();
+    assert_eq!(stream_id, stream.id());
+    let mut body = Vec::new();
+    stream.write_to(&mut body, index + 1).await.unwrap();
+    assert_eq!(events(&body).len(), 1);
+
+    let mut body = Vec::new();
+    stream.write_to(&mut body, 10).await.unwrap();
+    assert!(body.is_empty());
+}
+
+#[tokio::test]
+async fn test_write_waits_for_events() {
+    let stream = Arc::new(EventStream::new(1));
+    assert!(stream.is_empty());
+    let writer = tokio::spawn({
+        let stream = Arc::clone(&stream);
+        async move {
+            let mut body = Vec::new();
+            stream.write_to(&mut body, 0).await.unwrap();
+            body
+        }
+    });
+
+    tokio::task::yield_now().await;
+    stream.push(message(1));
+    tokio::task::yield_now().await;
+    stream.push(message(2));
+    stream.close();
+    let body = writer.await.unwrap();
+    assert_eq!(events(&body).len(), 2);
+}
+
+#[test]
+fn test_parse_invalid_event_ids() {
+    assert_eq!(parse_event_id(""), None);
+    assert_eq!(parse_event_id("a-1"), None);
+    assert_eq!(parse_event_id("1-"), None);
+    assert_eq!(parse_event_id(" 4-2 "), Some((4, 2)));
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_event_ids_round_trip(stream in any::<u64>(), index in any::<usize>()) {
+        prop_assert_eq!(parse_event_id(&event_id(stream, index)), Some((stream, index)));
+    }
+}
@@ -0,0 +1,140 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+use serde_json::Value;
+use tokio::io::{DuplexStream, Lines};
+
+use super::*;
+use crate::Engine;
+use crate::mcp::{INVALID_REQUEST, PARSE_ERROR};
+use crate::tools::default_tools;
+
+/// A client connected to a server over in-memory pipes.
+struct Client {
+    input: DuplexStream,
+    output: Lines<BufReader<DuplexStream>>,
+    server: tokio::task::JoinHandle<io::Result<()>>,
+}
+
+impl Client {
+    fn connect() -> Self {
+        let (input, server_input) = tokio::io::duplex(64 * 1024);
+        let (server_output, output) = tokio::io::duplex(64 * 1024);
+        let server = McpServer::new(Arc::new(Engine::new("/workspace")), default_tools());
+        let server =
+            tokio::spawn(async move { serve_stdio(&server, server_input, server_output).await });
+        Self {
+            input,
+            output: BufReader::new(output).lines(),
+            server,
+        }
+    }
+
+    async fn send(&mut self, line: &str) {
+        self.input.write_all(line.as_bytes()).await.unwrap();
+        self.input.write_all(b"\n").await.unwrap();
+    }
+
+    async fn receive(&mut self) -> Value {
+        let line = self.output.next_line().await.unwrap().unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+}
+
+#[tokio::test]
+async fn test_session_over_stdio() {
+    let mut client = Client::connect();
+    client
+        .send(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-03-26","capabilities":{},"clientInfo":{"name":"test","version":"1.0"}}}"#)
+        .await;
+    let response = client.receive().await;
+    assert_eq!(response.pointer("/id").unwrap(), 1);
+    assert_eq!(
+        response.pointer("/result/protocolVersion").unwrap(),
+        "2025-03-26"
+    );
+
+    client
+        .send(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+        .await;
+    client.send("").await;
+    client
+        .send(r#"{"jsonrpc":"2.0","id":"list","method":"tools/list"}"#)
+        .await;
+    let response = client.receive().await;
+    assert_eq!(response.pointer("/id").unwrap(), "list");
+    assert!(
+        !response
+            .pointer("/result/tools")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+
+    client
+        .send(r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"workspace.index","arguments":{},"_meta":{"progressToken":"p"}}}"#)
+        .await;
+    // Progress notifications come before the response
+    let mut message = client.receive().await;
+    while message.get("method").and_then(Value::as_str) == Some("notifications/progress") {
+        assert_eq!(message.pointer("/params/progressToken").unwrap(), "p");
+        message = client.receive().await;
+    }
+    assert_eq!(message.pointer("/id").unwrap(), 2);
+    assert_eq!(
+        message
+            .pointer("/result/structuredContent/indexed")
+            .unwrap(),
+        0
+    );
+
+    drop(client.input);
+    client.server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_invalid_lines() {
+    let mut client = Client::connect();
+    client.send("{not json").await;
+    let response = client.receive().await;
+    assert_eq!(response.pointer("/id").unwrap(), &Value::Null);
+    assert_eq!(response.pointer("/error/code").unwrap(), PARSE_ERROR);
+
+    client
+        .send(r#"{"jsonrpc":"1.0","id":1,"method":"ping"}"#)
+        .await;
+    let response = client.receive().await;
+    assert_eq!(response.pointer("/error/code").unwrap(), INVALID_REQUEST);
+
+    client
+        .send(r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#)
+        .await;
+    let response = client.receive().await;
+    assert_eq!(response.pointer("/id").unwrap(), 2);
+    assert_eq!(response.pointer("/result").unwrap(), &serde_json::json!({}));
+}
+
+#[tokio::test]
+async fn test_closing_input_ends_session() {
+    let client = Client::connect();
+    drop(client.input);
+    client.server.await.unwrap().unwrap();
+}
+
+// Property-based tests
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn prop_garbage_lines_get_parse_errors(line in "[a-z{}:,]{1,20}") {
+        prop_assume!(serde_json::from_str::<Value>(&line).is_err());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let response = runtime.block_on(async {
+            let mut client = Client::connect();
+            client.send(&line).await;
+            client.receive().await
+        });
+        prop_assert_eq!(response.pointer("/error/code").unwrap(), &serde_json::json!(PARSE_ERROR));
+    }
+}
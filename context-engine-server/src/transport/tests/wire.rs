@@ -0,0 +1,134 @@
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+
+use super::*;
+
+async fn read(bytes: &[u8]) -> Option<Result<HttpRequest, WireError>> {
+    let mut reader = bytes;
+    read_request(&mut reader).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_read_request() {
+    let bytes = b"POST /mcp?debug=1 HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}GET";
+    let request = read(bytes).await.unwrap().unwrap();
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/mcp");
+    assert_eq!(request.header("content-type"), Some("application/json"));
+    assert_eq!(request.header("CONTENT-TYPE"), Some("application/json"));
+    assert_eq!(request.body, b"{}");
+}
+
+#[tokio::test]
+async fn test_read_consecutive_requests() {
+    let bytes = b"GET /a HTTP/1.1\r\n\r\n\r\nDELETE /b HTTP/1.1\r\nMcp-Session-Id: 1\r\n\r\n";
+    let mut reader = &bytes[..];
+    let first = read_request(&mut reader).await.unwrap().unwrap().unwrap();
+    let second = read_request(&mut reader).await.unwrap().unwrap().unwrap();
+    assert_eq!((first.method.as_str(), first.path.as_str()), ("GET", "/a"));
+    assert_eq!(second.header("mcp-session-id"), Some("1"));
+    assert!(read_request(&mut reader).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_invalid_requests() {
+    assert_eq!(read(b"").await, None);
+    assert_eq!(read(b"GET /").await, Some(Err(WireError::BadRequest)));
+    assert_eq!(
+        read(b"GET / SPDY/3\r\n\r\n").await,
+        Some(Err(WireError::BadRequest))
+    );
+    assert_eq!(
+        read(b"GET / HTTP/1.1\r\nno colon\r\n\r\n").await,
+        Some(Err(WireError::BadRequest))
+    );
+    assert_eq!(
+        read(b"POST / HTTP/1.1\r\nContent-Length: many\r\n\r\n").await,
+        Some(Err(WireError::BadRequest))
+    );
+    assert_eq!(
+        read(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n").await,
+        Some(Err(WireError::Unsupported))
+    );
+    let too_large = format!(
+        "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+        MAX_BODY + 1
+    );
+    assert_eq!(
+        read(too_large.as_bytes()).await,
+        Some(Err(WireError::TooLarge))
+    );
+    assert_eq!(WireError::TooLarge.response().status, 413);
+}
+
+#[tokio::test]
+async fn test_truncated_body_fails() {
+    let mut reader = &b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}"[..];
+    assert!(read_request(&mut reader).await.is_err());
+}
+
+#[test]
+fn test_accepts() {
+    let request = |accept: &str| HttpRequest {
+        method: "POST".to_string(),
+        path: "/mcp".to_string(),
+        headers: vec![("accept".to_string(), accept.to_string())],
+        body: Vec::new(),
+    };
+    let both = request("application/json, text/event-stream;q=0.9");
+    assert!(both.accepts("application/json"));
+    assert!(both.accepts("text/event-stream"));
+    assert!(!request("application/json").accepts("text/event-stream"));
+    assert!(request("*/*").accepts("text/event-stream"));
+}
+
+#[tokio::test]
+async fn test_write_response() {
+    let mut bytes = Vec::new();
+    HttpResponse::json(200, b"{}".to_vec())
+        .with_header("Mcp-Session-Id", "abc")
+        .write_to(&mut bytes, true)
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(bytes).unwrap(),
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nMcp-Session-Id: \
+         abc\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}"
+    );
+
+    let mut bytes = Vec::new();
+    write_event_stream_head(&mut bytes, &[]).await.unwrap();
+    let head = String::from_utf8(bytes).unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n"));
+    assert!(head.ends_with("\r\n\r\n"));
+}
+
+// Property-based tests
+proptest! {
+    #[test]
+    fn prop_written_headers_are_read_back(
+        name in "[A-Za-z][A-Za-z-]{0,15}",
+        value in "[ -~]{0,30}",
+        body in proptest::collection::vec(any::<u8>(), 0..64),
+    ) {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let request = runtime.block_on(async {
+            let mut bytes = format!(
+                "POST /mcp HTTP/1.1\r\n{name}: {value}\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            )
+            .into_bytes();
+            bytes.extend(&body);
+            read(&bytes).await
+        });
+        let request = request.unwrap().unwrap();
+        let expected = if name.eq_ignore_ascii_case("content-length") {
+            body.len().to_string()
+        } else {
+            value.trim().to_string()
+        };
+        prop_assert_eq!(request.header(&name), Some(expected.as_str()));
+        prop_assert_eq!(request.body, body);
+    }
+}
@@ -0,0 +1,272 @@
+//! The HTTP/1.1 messages of the HTTP transport.
+//!
+//! The transport only needs a small part of HTTP: requests with a
+//! `Content-Length` body, responses with a complete body, and open-ended
+//! `text/event-stream` responses that end when the connection closes.
+
+use std::io;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The maximum size of the request line and headers.
+const MAX_HEAD: usize = 64 * 1024;
+
+/// The maximum size of a request body.
+pub const MAX_BODY: usize = 16 * 1024 * 1024;
+
+/// An HTTP request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// The method, e.g. `POST`
+    pub method: String,
+    /// The path, without query
+    pub path: String,
+    /// The headers, with lowercase names, in the order they were sent
+    pub headers: Vec<(String, String)>,
+    /// The body
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// Returns the value of the first header with a name, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns true if the `Accept` header allows a media type.
+    pub fn accepts(&self, media_type: &str) -> bool {
+        self.header("accept").is_some_and(|accept| {
+            accept.split(',').any(|item| {
+                let item = item.split(';').next().unwrap_or_default().trim();
+                item == media_type || item == "*/*"
+            })
+        })
+    }
+
+    /// Returns true if the client asked to close the connection after the
+    /// response.
+    pub fn wants_close(&self) -> bool {
+        self.header("connection")
+            .is_some_and(|connection| connection.eq_ignore_ascii_case("close"))
+    }
+}
+
+/// Errors of reading a request that are answered before the connection is
+/// closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireError {
+    /// The request is malformed
+    BadRequest,
+    /// The head or body of the request is too large
+    TooLarge,
+    /// The request uses a transfer coding other than `Content-Length`
+    Unsupported,
+}
+
+impl WireError {
+    /// Returns the response to the error.
+    pub fn response(self) -> HttpResponse {
+        match self {
+            WireError::BadRequest => HttpResponse::text(400, "Malformed HTTP request"),
+            WireError::TooLarge => HttpResponse::text(413, "Request too large"),
+            WireError::Unsupported => HttpResponse::text(501, "Unsupported transfer coding"),
+        }
+    }
+}
+
+/// Reads a request from a connection.
+///
+/// # Returns
+///
+/// * `Ok(None)` - If the connection was closed before a request started
+/// * `Ok(Some(Err(_)))` - If the request is invalid
+///
+/// # Errors
+///
+/// Returns the I/O error if the connection fails.
+pub async fn read_request<R>(reader: &mut R) -> io::Result<Option<Result<HttpRequest, WireError>>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut head = Vec::new();
+    loop {
+        let read = reader.read_until(b'\n', &mut head).await?;
+        if read == 0 {
+            return Ok((!head.is_empty()).then_some(Err(WireError::BadRequest)));
+        }
+        if head.len() > MAX_HEAD {
+            return Ok(Some(Err(WireError::TooLarge)));
+        }
+        // Empty lines before the request line are ignored
+        if head == b"\r\n" || head == b"\n" {
+            head.clear();
+            continue;
+        }
+        if head.ends_with(b"\r\n\r\n") || head.ends_with(b"\n\n") {
+            break;
+        }
+    }
+
+    let Ok(head) = String::from_utf8(head) else {
+        return Ok(Some(Err(WireError::BadRequest)));
+    };
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target), Some(version)) = (
+        request_line.next(),
+        request_line.next(),
+        request_line.next(),
+    ) else {
+        return Ok(Some(Err(WireError::BadRequest)));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Ok(Some(Err(WireError::BadRequest)));
+    }
+    let mut headers = Vec::new();
+    for line in lines.filter(|line| !line.is_empty()) {
+        let Some((name, value)) = line.split_once(':') else {
+            return Ok(Some(Err(WireError::BadRequest)));
+        };
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+    let mut request = HttpRequest {
+        method: method.to_ascii_uppercase(),
+        path: target.split('?').next().unwrap_or(target).to_string(),
+        headers,
+        body: Vec::new(),
+    };
+
+    if request.header("transfer-encoding").is_some() {
+        return Ok(Some(Err(WireError::Unsupported)));
+    }
+    let length = match request.header("content-length") {
+        Some(length) => match length.parse::<usize>() {
+            Ok(length) => length,
+            Err(_) => return Ok(Some(Err(WireError::BadRequest))),
+        },
+        None => 0,
+    };
+    if length > MAX_BODY {
+        return Ok(Some(Err(WireError::TooLarge)));
+    }
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body).await?;
+    Ok(Some(Ok(request)))
+}
+
+/// An HTTP response with a complete body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    /// The status code
+    pub status: u16,
+    /// The headers, besides `Content-Length`
+    pub headers: Vec<(String, String)>,
+    /// The body
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Creates a response without body.
+    pub fn empty(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Creates a plain text response.
+    pub fn text(status: u16, text: &str) -> Self {
+        Self::empty(status)
+            .with_header("Content-Type", "text/plain; charset=utf-8")
+            .with_body(text.as_bytes().to_vec())
+    }
+
+    /// Creates a JSON response.
+    pub fn json(status: u16, body: Vec<u8>) -> Self {
+        Self::empty(status)
+            .with_header("Content-Type", "application/json")
+            .with_body(body)
+    }
+
+    /// Adds a header.
+    pub fn with_header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    fn with_body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Writes the response to a connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the connection fails.
+    pub async fn write_to<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        close: bool,
+    ) -> io::Result<()> {
+        let mut head = status_line(self.status);
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        if close {
+            head.push_str("Connection: close\r\n");
+        }
+        head.push_str("\r\n");
+        writer.write_all(head.as_bytes()).await?;
+        writer.write_all(&self.body).await?;
+        writer.flush().await
+    }
+}
+
+/// Writes the head of a `text/event-stream` response, whose body lasts
+/// until the connection is closed.
+///
+/// # Errors
+///
+/// Returns the I/O error if the connection fails.
+pub async fn write_event_stream_head<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    headers: &[(String, String)],
+) -> io::Result<()> {
+    let mut head = status_line(200);
+    head.push_str(
+        "Content-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n",
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    writer.write_all(head.as_bytes()).await?;
+    writer.flush().await
+}
+
+fn status_line(status: u16) -> String {
+    let reason = match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        501 => "Not Implemented",
+        _ => "Unknown",
+    };
+    format!("HTTP/1.1 {status} {reason}\r\n")
+}
+
+#[cfg(test)]
+#[path = "tests/wire.rs"]
+mod tests;